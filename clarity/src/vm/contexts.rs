@@ -1793,6 +1793,15 @@ impl ContractContext {
         self.functions.get(name).cloned()
     }
 
+    /// Find the name of the function whose body is the expression with the given ID, if any.
+    ///  Used by eval hooks to tell when a function is entered.
+    pub fn lookup_function_by_body(&self, expr_id: u64) -> Option<&ClarityName> {
+        self.functions
+            .iter()
+            .find(|(_, function)| function.get_body().id == expr_id)
+            .map(|(name, _)| name)
+    }
+
     pub fn lookup_trait_definition(
         &self,
         name: &str,
@@ -0,0 +1,753 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{BufRead, Write};
+
+use hashbrown::HashMap;
+
+use super::EvalHook;
+use crate::vm::contexts::{Environment, LocalContext};
+use crate::vm::errors::Error;
+use crate::vm::types::QualifiedContractIdentifier;
+use crate::vm::{ClarityName, ExecutionResult, SymbolicExpression, Value};
+
+const HELP: &str = "Commands:
+  step, s                 stop at the next expression
+  next, n                 stop at the next expression, stepping over sub-expressions
+  finish, f               run until the current function returns
+  continue, c             run until the next breakpoint
+  break, b <location>     set a breakpoint at [contract:]line or [contract:]function
+  delete, d <id>          delete a breakpoint
+  breakpoints, bl         list breakpoints
+  print, p <name>         print a local binding, constant or data var
+  locals, l               print the local bindings
+  vars, v                 print the contract's data vars
+  backtrace, bt           print the function call stack
+  quit, q                 delete all breakpoints and run to completion
+  help, h                 print this message
+An empty line repeats the last command.";
+
+/// A place where the debugger stops execution
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    /// Stop when execution reaches a source line.  Source lines are only tracked by builds with
+    /// the `developer-mode` feature, so `Breakpoint::parse()` rejects these in other builds.
+    Line {
+        contract: Option<QualifiedContractIdentifier>,
+        line: u32,
+    },
+    /// Stop on entry to a function
+    Function {
+        contract: Option<QualifiedContractIdentifier>,
+        name: ClarityName,
+    },
+}
+
+impl Breakpoint {
+    /// Parse a breakpoint of the form `[contract:]line` or `[contract:]function-name`.  If the
+    /// contract is omitted, the breakpoint applies to every contract.  Line breakpoints are an
+    /// error unless this is a `developer-mode` build, since they would never be hit.
+    pub fn parse(spec: &str) -> Result<Breakpoint, String> {
+        let (contract, location) = match spec.rsplit_once(':') {
+            Some((contract, location)) => {
+                let contract = QualifiedContractIdentifier::parse(contract)
+                    .map_err(|e| format!("Invalid contract identifier '{}': {}", contract, e))?;
+                (Some(contract), location)
+            }
+            None => (None, spec),
+        };
+        if let Ok(line) = location.parse::<u32>() {
+            if !cfg!(feature = "developer-mode") {
+                return Err(format!(
+                    "Cannot break at line {}: source lines are only tracked by builds with the `developer-mode` feature. Break on a function instead.",
+                    line
+                ));
+            }
+            return Ok(Breakpoint::Line { contract, line });
+        }
+        let name = ClarityName::try_from(location.to_string())
+            .map_err(|e| format!("Invalid function name '{}': {}", location, e))?;
+        Ok(Breakpoint::Function { contract, name })
+    }
+
+    fn in_contract(&self, current: &QualifiedContractIdentifier) -> bool {
+        let contract = match self {
+            Breakpoint::Line { contract, .. } => contract,
+            Breakpoint::Function { contract, .. } => contract,
+        };
+        contract.as_ref().is_none_or(|contract| contract == current)
+    }
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (contract, location) = match self {
+            Breakpoint::Line { contract, line } => (contract, line.to_string()),
+            Breakpoint::Function { contract, name } => (contract, name.to_string()),
+        };
+        match contract {
+            Some(contract) => write!(f, "{}:{}", contract, location),
+            None => write!(f, "{}", location),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DebugCommand {
+    Step,
+    Next,
+    Finish,
+    Continue,
+    Break(Breakpoint),
+    Delete(usize),
+    Breakpoints,
+    Print(String),
+    Locals,
+    Vars,
+    Backtrace,
+    Quit,
+    Help,
+}
+
+impl DebugCommand {
+    fn parse(line: &str) -> Result<DebugCommand, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or("");
+        let arg = words.next();
+        if words.next().is_some() {
+            return Err(format!("Too many arguments to '{}'", command));
+        }
+
+        let command = match (command, arg) {
+            ("step" | "s", None) => DebugCommand::Step,
+            ("next" | "n", None) => DebugCommand::Next,
+            ("finish" | "f", None) => DebugCommand::Finish,
+            ("continue" | "c", None) => DebugCommand::Continue,
+            ("break" | "b", Some(spec)) => DebugCommand::Break(Breakpoint::parse(spec)?),
+            ("delete" | "d", Some(id)) => DebugCommand::Delete(
+                id.parse()
+                    .map_err(|_| format!("Invalid breakpoint id '{}'", id))?,
+            ),
+            ("breakpoints" | "bl", None) => DebugCommand::Breakpoints,
+            ("print" | "p", Some(name)) => DebugCommand::Print(name.to_string()),
+            ("locals" | "l", None) => DebugCommand::Locals,
+            ("vars" | "v", None) => DebugCommand::Vars,
+            ("backtrace" | "bt", None) => DebugCommand::Backtrace,
+            ("quit" | "q", None) => DebugCommand::Quit,
+            ("help" | "h", None) => DebugCommand::Help,
+            ("break" | "b" | "delete" | "d" | "print" | "p", None) => {
+                return Err(format!("'{}' needs an argument", command));
+            }
+            _ => return Err(format!("Unknown command '{}'. Try 'help'.", line.trim())),
+        };
+        Ok(command)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StepMode {
+    /// Run until a breakpoint is hit
+    Continue,
+    /// Stop at the next expression
+    Step,
+    /// Stop at the next expression which is no deeper than the given depth
+    Next(usize),
+    /// Stop once the expression at the given depth has been evaluated
+    Finish(usize),
+}
+
+/// A user-defined function which is being evaluated
+struct Frame {
+    contract: QualifiedContractIdentifier,
+    function: ClarityName,
+    body_id: u64,
+    depth: usize,
+}
+
+/// An interactive step debugger, which reads commands from `input` whenever execution stops and
+/// writes its output to `output`.
+pub struct Debugger<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    breakpoints: BTreeMap<usize, Breakpoint>,
+    next_breakpoint_id: usize,
+    mode: StepMode,
+    last_command: Option<DebugCommand>,
+    /// nesting depth of the expression being evaluated
+    depth: usize,
+    frames: Vec<Frame>,
+    current_line: Option<(QualifiedContractIdentifier, u32)>,
+    sources: HashMap<QualifiedContractIdentifier, Option<Vec<String>>>,
+}
+
+impl<R: BufRead, W: Write> Debugger<R, W> {
+    /// Make a debugger with the given breakpoints.  If there are none, execution stops at the
+    /// first expression.
+    pub fn new(input: R, output: W, breakpoints: Vec<Breakpoint>) -> Debugger<R, W> {
+        let mode = if breakpoints.is_empty() {
+            StepMode::Step
+        } else {
+            StepMode::Continue
+        };
+        let mut debugger = Debugger {
+            input,
+            output,
+            breakpoints: BTreeMap::new(),
+            next_breakpoint_id: 1,
+            mode,
+            last_command: None,
+            depth: 0,
+            frames: vec![],
+            current_line: None,
+            sources: HashMap::new(),
+        };
+        for breakpoint in breakpoints.into_iter() {
+            debugger.add_breakpoint(breakpoint);
+        }
+        debugger
+    }
+
+    /// Add a breakpoint, and return its ID
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        let id = self.next_breakpoint_id;
+        self.next_breakpoint_id += 1;
+        self.breakpoints.insert(id, breakpoint);
+        id
+    }
+
+    pub fn remove_breakpoint(&mut self, id: usize) -> Option<Breakpoint> {
+        self.breakpoints.remove(&id)
+    }
+
+    fn say(&mut self, msg: fmt::Arguments) {
+        // the debugger is best-effort: a broken terminal must not interrupt execution
+        let _ = self.output.write_fmt(msg);
+        let _ = self.output.write_all(b"\n");
+    }
+
+    /// Find the breakpoint, if any, which the expression about to be evaluated hits
+    fn hit_breakpoint(
+        &self,
+        contract: &QualifiedContractIdentifier,
+        line: Option<u32>,
+        entered: Option<&ClarityName>,
+    ) -> Option<usize> {
+        self.breakpoints
+            .iter()
+            .find(|(_, breakpoint)| {
+                breakpoint.in_contract(contract)
+                    && match breakpoint {
+                        Breakpoint::Line { line: bp_line, .. } => line == Some(*bp_line),
+                        Breakpoint::Function { name, .. } => entered == Some(name),
+                    }
+            })
+            .map(|(id, _)| *id)
+    }
+
+    fn source_line(
+        &mut self,
+        env: &mut Environment,
+        contract: &QualifiedContractIdentifier,
+        line: u32,
+    ) -> Option<String> {
+        let lines = self.sources.entry(contract.clone()).or_insert_with(|| {
+            env.global_context
+                .database
+                .get_contract_src(contract)
+                .map(|src| src.lines().map(|line| line.to_string()).collect())
+        });
+        lines
+            .as_ref()?
+            .get(usize::try_from(line).ok()?.checked_sub(1)?)
+            .cloned()
+    }
+
+    fn show_location(&mut self, env: &mut Environment, expr: &SymbolicExpression, reason: &str) {
+        let contract = env.contract_context.contract_identifier.clone();
+        let line = expr.span().start_line;
+        let mut location = format!("{} at {}", reason, &contract);
+        if line > 0 {
+            location.push_str(&format!(":{}", line));
+        }
+        if let Some(frame) = self.frames.last() {
+            location.push_str(&format!(" in {}", &frame.function));
+        }
+        self.say(format_args!("{}", location));
+
+        match self.source_line(env, &contract, line) {
+            Some(source) => self.say(format_args!("{:>5} | {}", line, source)),
+            None => self.say(format_args!("  {}", expr)),
+        }
+    }
+
+    fn print_name(&mut self, env: &mut Environment, context: &LocalContext, name: &str) {
+        if let Some(value) = context.lookup_variable(name) {
+            self.say(format_args!("{} = {}", name, value));
+        } else if let Some(value) = env.contract_context.lookup_variable(name).cloned() {
+            self.say(format_args!("{} = {}", name, value));
+        } else if let Some(value) = lookup_data_var(env, name) {
+            match value {
+                Ok(value) => self.say(format_args!("{} = {}", name, value)),
+                Err(e) => self.say(format_args!("Failed to load {}: {}", name, e)),
+            }
+        } else {
+            self.say(format_args!("No binding or data var named '{}'", name));
+        }
+    }
+
+    fn print_locals(&mut self, context: &LocalContext) {
+        let mut bindings = vec![];
+        let mut next = Some(context);
+        while let Some(context) = next {
+            let mut scope: Vec<_> = context.variables.iter().collect();
+            scope.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, value) in scope.into_iter() {
+                // inner scopes shadow outer ones
+                if !bindings.iter().any(|(bound, _)| bound == &name) {
+                    bindings.push((name, value));
+                }
+            }
+            next = context.parent;
+        }
+        if bindings.is_empty() {
+            self.say(format_args!("No local bindings"));
+        }
+        for (name, value) in bindings.into_iter() {
+            self.say(format_args!("{} = {}", name, value));
+        }
+    }
+
+    fn print_vars(&mut self, env: &mut Environment) {
+        let mut names: Vec<_> = env.contract_context.meta_data_var.keys().cloned().collect();
+        names.sort();
+        if names.is_empty() {
+            self.say(format_args!("No data vars"));
+        }
+        for name in names.into_iter() {
+            match lookup_data_var(env, &name) {
+                Some(Ok(value)) => self.say(format_args!("{} = {}", name, value)),
+                Some(Err(e)) => self.say(format_args!("Failed to load {}: {}", name, e)),
+                None => {}
+            }
+        }
+    }
+
+    fn print_backtrace(&mut self) {
+        if self.frames.is_empty() {
+            self.say(format_args!("Not in a function"));
+        }
+        let frames: Vec<_> = self
+            .frames
+            .iter()
+            .rev()
+            .map(|frame| format!("{}:{}", &frame.contract, &frame.function))
+            .collect();
+        for (i, frame) in frames.into_iter().enumerate() {
+            self.say(format_args!("#{} {}", i, frame));
+        }
+    }
+
+    /// Read and run commands until one of them resumes execution
+    fn prompt(&mut self, env: &mut Environment, context: &LocalContext) {
+        loop {
+            let _ = self.output.write_all(b"(debug) ");
+            let _ = self.output.flush();
+
+            let mut line = String::new();
+            match self.input.read_line(&mut line) {
+                Ok(0) | Err(_) => {
+                    // no more input, so run to completion
+                    self.breakpoints.clear();
+                    self.mode = StepMode::Continue;
+                    return;
+                }
+                Ok(_) => {}
+            }
+
+            let command = if line.trim().is_empty() {
+                match self.last_command.clone() {
+                    Some(command) => command,
+                    None => continue,
+                }
+            } else {
+                match DebugCommand::parse(&line) {
+                    Ok(command) => command,
+                    Err(msg) => {
+                        self.say(format_args!("{}", msg));
+                        continue;
+                    }
+                }
+            };
+            self.last_command = Some(command.clone());
+
+            match command {
+                DebugCommand::Step => {
+                    self.mode = StepMode::Step;
+                    return;
+                }
+                DebugCommand::Next => {
+                    self.mode = StepMode::Next(self.depth);
+                    return;
+                }
+                DebugCommand::Finish => {
+                    let depth = self.frames.last().map(|frame| frame.depth).unwrap_or(1);
+                    self.mode = StepMode::Finish(depth);
+                    return;
+                }
+                DebugCommand::Continue => {
+                    self.mode = StepMode::Continue;
+                    return;
+                }
+                DebugCommand::Quit => {
+                    self.breakpoints.clear();
+                    self.mode = StepMode::Continue;
+                    return;
+                }
+                DebugCommand::Break(breakpoint) => {
+                    let msg = format!("{}", &breakpoint);
+                    let id = self.add_breakpoint(breakpoint);
+                    self.say(format_args!("Breakpoint {} at {}", id, msg));
+                }
+                DebugCommand::Delete(id) => match self.remove_breakpoint(id) {
+                    Some(breakpoint) => {
+                        self.say(format_args!("Deleted breakpoint {} at {}", id, breakpoint))
+                    }
+                    None => self.say(format_args!("No breakpoint {}", id)),
+                },
+                DebugCommand::Breakpoints => {
+                    if self.breakpoints.is_empty() {
+                        self.say(format_args!("No breakpoints"));
+                    }
+                    let breakpoints: Vec<_> = self
+                        .breakpoints
+                        .iter()
+                        .map(|(id, breakpoint)| format!("{} {}", id, breakpoint))
+                        .collect();
+                    for breakpoint in breakpoints.into_iter() {
+                        self.say(format_args!("{}", breakpoint));
+                    }
+                }
+                DebugCommand::Print(name) => self.print_name(env, context, &name),
+                DebugCommand::Locals => self.print_locals(context),
+                DebugCommand::Vars => self.print_vars(env),
+                DebugCommand::Backtrace => self.print_backtrace(),
+                DebugCommand::Help => self.say(format_args!("{}", HELP)),
+            }
+        }
+    }
+}
+
+fn lookup_data_var(env: &mut Environment, name: &str) -> Option<Result<Value, Error>> {
+    let descriptor = env.contract_context.meta_data_var.get(name)?.clone();
+    let contract = env.contract_context.contract_identifier.clone();
+    let epoch = env.global_context.epoch_id;
+    Some(
+        env.global_context
+            .database
+            .lookup_variable(&contract, name, &descriptor, &epoch),
+    )
+}
+
+impl<R: BufRead, W: Write> EvalHook for Debugger<R, W> {
+    fn will_begin_eval(
+        &mut self,
+        env: &mut Environment,
+        context: &LocalContext,
+        expr: &SymbolicExpression,
+    ) {
+        self.depth += 1;
+
+        let contract = env.contract_context.contract_identifier.clone();
+        let entered = env
+            .contract_context
+            .lookup_function_by_body(expr.id)
+            .cloned();
+        if let Some(function) = entered.clone() {
+            self.frames.push(Frame {
+                contract: contract.clone(),
+                function,
+                body_id: expr.id,
+                depth: self.depth,
+            });
+        }
+
+        // only stop for a line breakpoint when execution moves onto the line
+        let line = expr.span().start_line;
+        let new_line = if line > 0 && self.current_line.as_ref() != Some(&(contract.clone(), line))
+        {
+            self.current_line = Some((contract.clone(), line));
+            Some(line)
+        } else {
+            None
+        };
+
+        let reason = if let Some(id) = self.hit_breakpoint(&contract, new_line, entered.as_ref()) {
+            format!("Breakpoint {}", id)
+        } else {
+            match self.mode {
+                StepMode::Step => "Step".to_string(),
+                StepMode::Next(depth) if self.depth <= depth => "Step".to_string(),
+                _ => return,
+            }
+        };
+
+        self.show_location(env, expr, &reason);
+        self.prompt(env, context);
+    }
+
+    fn did_finish_eval(
+        &mut self,
+        env: &mut Environment,
+        context: &LocalContext,
+        expr: &SymbolicExpression,
+        res: &core::result::Result<Value, Error>,
+    ) {
+        let returned = match self.frames.last() {
+            Some(frame) if frame.body_id == expr.id && frame.depth == self.depth => {
+                self.frames.pop().map(|frame| frame.function)
+            }
+            _ => None,
+        };
+
+        if self.mode == StepMode::Finish(self.depth) {
+            let what = match returned {
+                Some(function) => function.to_string(),
+                None => format!("{}", expr),
+            };
+            match res {
+                Ok(value) => self.say(format_args!("{} returned {}", what, value)),
+                Err(e) => self.say(format_args!("{} failed: {}", what, e)),
+            }
+            self.mode = StepMode::Step;
+            self.prompt(env, context);
+        }
+
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    fn did_complete(&mut self, _result: core::result::Result<&mut ExecutionResult, String>) {}
+}
+
+#[cfg(test)]
+mod test {
+    use stacks_common::types::StacksEpochId;
+
+    use super::*;
+    use crate::vm::ast::ASTRules;
+    use crate::vm::contexts::OwnedEnvironment;
+    use crate::vm::database::MemoryBackingStore;
+    use crate::vm::types::PrincipalData;
+
+    const CONTRACT: &str = "
+(define-data-var counter uint u0)
+(define-constant step u2)
+(define-private (double (x uint))
+  (let ((y (* x step)))
+    y))
+(define-public (bump (n uint))
+  (begin
+    (var-set counter (+ (var-get counter) (double n)))
+    (ok (var-get counter))))";
+
+    /// Call `bump` with the debugger attached, feeding it the given commands.  Returns the
+    /// call's result and the debugger's output, without prompts.
+    fn debug_bump(breakpoints: Vec<Breakpoint>, commands: &str) -> (Value, String) {
+        let contract_id = QualifiedContractIdentifier::local("counter").unwrap();
+        let mut debugger = Debugger::new(commands.as_bytes(), vec![], breakpoints);
+        let mut marf = MemoryBackingStore::new();
+        let result = {
+            let mut owned_env = OwnedEnvironment::new(marf.as_clarity_db(), StacksEpochId::Epoch21);
+            owned_env
+                .initialize_contract(contract_id.clone(), CONTRACT, None, ASTRules::PrecheckSize)
+                .unwrap();
+            owned_env.add_eval_hook(&mut debugger);
+            owned_env
+                .execute_transaction(
+                    PrincipalData::from(contract_id.issuer.clone()),
+                    None,
+                    contract_id,
+                    "bump",
+                    &[SymbolicExpression::atom_value(Value::UInt(3))],
+                )
+                .unwrap()
+                .0
+        };
+        let output = String::from_utf8(debugger.output).unwrap();
+        (result, output.replace("(debug) ", ""))
+    }
+
+    #[test]
+    fn test_parse_breakpoint() {
+        let contract_id =
+            QualifiedContractIdentifier::parse("S1G2081040G2081040G2081040G208105NK8PE5.counter")
+                .unwrap();
+        #[cfg(feature = "developer-mode")]
+        {
+            assert_eq!(
+                Breakpoint::parse("12").unwrap(),
+                Breakpoint::Line {
+                    contract: None,
+                    line: 12
+                }
+            );
+            assert_eq!(
+                Breakpoint::parse("S1G2081040G2081040G2081040G208105NK8PE5.counter:12").unwrap(),
+                Breakpoint::Line {
+                    contract: Some(contract_id.clone()),
+                    line: 12
+                }
+            );
+        }
+        // without source lines, a line breakpoint would never be hit
+        #[cfg(not(feature = "developer-mode"))]
+        {
+            assert!(Breakpoint::parse("12")
+                .unwrap_err()
+                .contains("`developer-mode`"));
+            assert!(
+                Breakpoint::parse("S1G2081040G2081040G2081040G208105NK8PE5.counter:12").is_err()
+            );
+        }
+        assert_eq!(
+            Breakpoint::parse("double").unwrap(),
+            Breakpoint::Function {
+                contract: None,
+                name: ClarityName::try_from("double".to_string()).unwrap()
+            }
+        );
+        let breakpoint =
+            Breakpoint::parse("S1G2081040G2081040G2081040G208105NK8PE5.counter:double").unwrap();
+        assert_eq!(
+            breakpoint,
+            Breakpoint::Function {
+                contract: Some(contract_id),
+                name: ClarityName::try_from("double".to_string()).unwrap()
+            }
+        );
+        assert_eq!(
+            breakpoint.to_string(),
+            "S1G2081040G2081040G2081040G208105NK8PE5.counter:double"
+        );
+
+        assert!(Breakpoint::parse("not-a-contract:12").is_err());
+        assert!(Breakpoint::parse("(double)").is_err());
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(DebugCommand::parse("s\n"), Ok(DebugCommand::Step));
+        assert_eq!(DebugCommand::parse("  next "), Ok(DebugCommand::Next));
+        assert_eq!(
+            DebugCommand::parse("b double"),
+            Ok(DebugCommand::Break(Breakpoint::Function {
+                contract: None,
+                name: ClarityName::try_from("double".to_string()).unwrap()
+            }))
+        );
+        assert_eq!(DebugCommand::parse("d 2"), Ok(DebugCommand::Delete(2)));
+        assert_eq!(
+            DebugCommand::parse("p counter"),
+            Ok(DebugCommand::Print("counter".into()))
+        );
+        assert!(DebugCommand::parse("print").is_err());
+        assert!(DebugCommand::parse("print a b").is_err());
+        assert!(DebugCommand::parse("delete one").is_err());
+        assert!(DebugCommand::parse("jump").is_err());
+    }
+
+    #[test]
+    fn test_function_breakpoint() {
+        let breakpoints = vec![Breakpoint::parse("double").unwrap()];
+        let (result, output) = debug_bump(
+            breakpoints,
+            "locals\nprint x\nprint step\nprint counter\nvars\nbt\nprint nope\nc\n",
+        );
+        assert_eq!(result, Value::okay(Value::UInt(6)).unwrap());
+
+        assert!(output.contains("Breakpoint 1 at S1G2081040G2081040G2081040G208105NK8PE5.counter"));
+        assert!(output.contains(" in double"));
+        assert!(output.contains("x = u3\nx = u3\nstep = u2\ncounter = u0\ncounter = u0\n"));
+        assert!(output.contains(
+            "#0 S1G2081040G2081040G2081040G208105NK8PE5.counter:double\n\
+             #1 S1G2081040G2081040G2081040G208105NK8PE5.counter:bump\n"
+        ));
+        assert!(output.contains("No binding or data var named 'nope'"));
+        // only stopped once
+        assert_eq!(output.matches("Breakpoint 1").count(), 1);
+    }
+
+    #[test]
+    fn test_step_and_finish() {
+        // stop on the first expression of `bump`, then step into `double` and out again
+        let (result, output) = debug_bump(vec![], "b double\nc\ns\ns\ns\ns\nl\nf\nq\n");
+        assert_eq!(result, Value::okay(Value::UInt(6)).unwrap());
+
+        assert!(output.starts_with("Step at "));
+        assert!(output.contains("Breakpoint 1 at double\n"));
+        assert_eq!(output.matches("Breakpoint 1 at S1G").count(), 1);
+        assert!(output.contains(" in double\n"));
+        // stepped into the `let` body, where `y` is bound
+        assert!(output.contains("x = u3\ny = u6\n") || output.contains("y = u6\nx = u3\n"));
+        assert!(output.contains("double returned u6"));
+    }
+
+    #[test]
+    fn test_next_skips_subexpressions() {
+        // `next` from the `begin` in `bump` goes straight to its result
+        let (_, step_output) = debug_bump(vec![], "s\ns\ns\nq\n");
+        let (_, next_output) = debug_bump(vec![], "n\nn\nq\n");
+
+        // stepping visits the sub-expressions of `begin`
+        assert_eq!(step_output.matches("Step at").count(), 4);
+        // nexting from the body of `bump` runs it to completion
+        assert_eq!(next_output.matches("Step at").count(), 1);
+        assert!(!next_output.contains(" in double"));
+    }
+
+    #[cfg(feature = "developer-mode")]
+    #[test]
+    fn test_line_breakpoint() {
+        let breakpoints = vec![Breakpoint::parse("9").unwrap()];
+        let (result, output) = debug_bump(breakpoints, "c\n");
+        assert_eq!(result, Value::okay(Value::UInt(6)).unwrap());
+
+        assert!(output.starts_with(concat!(
+            "Breakpoint 1 at S1G2081040G2081040G2081040G208105NK8PE5.counter:9 in bump\n",
+            "    9 |     (var-set counter (+ (var-get counter) (double n)))\n"
+        )));
+        // sub-expressions on the same line do not stop again
+        assert_eq!(output.matches("Breakpoint 1 at").count(), 1);
+    }
+
+    #[cfg(not(feature = "developer-mode"))]
+    #[test]
+    fn test_line_breakpoint_rejected() {
+        let (result, output) = debug_bump(vec![], "b 9\nbl\nq\n");
+        assert_eq!(result, Value::okay(Value::UInt(6)).unwrap());
+
+        assert!(output.contains("Cannot break at line 9"));
+        assert!(output.contains("No breakpoints"));
+    }
+
+    #[test]
+    fn test_detach_at_end_of_input() {
+        let (result, output) = debug_bump(vec![], "");
+        assert_eq!(result, Value::okay(Value::UInt(6)).unwrap());
+        assert_eq!(output.matches("Step at").count(), 1);
+    }
+}
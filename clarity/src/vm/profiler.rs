@@ -0,0 +1,344 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::EvalHook;
+use crate::vm::contexts::{Environment, LocalContext};
+use crate::vm::costs::ExecutionCost;
+use crate::vm::errors::Error;
+use crate::vm::{ExecutionResult, SymbolicExpression, Value};
+
+/// One dimension of an `ExecutionCost`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CostDimension {
+    Runtime,
+    ReadCount,
+    ReadLength,
+    WriteCount,
+    WriteLength,
+}
+
+impl CostDimension {
+    pub const ALL: [CostDimension; 5] = [
+        CostDimension::Runtime,
+        CostDimension::ReadCount,
+        CostDimension::ReadLength,
+        CostDimension::WriteCount,
+        CostDimension::WriteLength,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CostDimension::Runtime => "runtime",
+            CostDimension::ReadCount => "read_count",
+            CostDimension::ReadLength => "read_length",
+            CostDimension::WriteCount => "write_count",
+            CostDimension::WriteLength => "write_length",
+        }
+    }
+
+    pub fn of(&self, cost: &ExecutionCost) -> u64 {
+        match self {
+            CostDimension::Runtime => cost.runtime,
+            CostDimension::ReadCount => cost.read_count,
+            CostDimension::ReadLength => cost.read_length,
+            CostDimension::WriteCount => cost.write_count,
+            CostDimension::WriteLength => cost.write_length,
+        }
+    }
+}
+
+/// Costs attributed to a user-defined function
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionProfile {
+    pub calls: u64,
+    /// cost of the function's calls, including the functions that they call
+    pub cost: ExecutionCost,
+    /// cost of the function's calls, excluding the functions that they call
+    pub self_cost: ExecutionCost,
+}
+
+/// A function, or a native function application, which is being evaluated
+struct Frame {
+    name: String,
+    is_function: bool,
+    expr_id: u64,
+    depth: usize,
+    /// the tracker's total when the frame was entered
+    entry_total: ExecutionCost,
+}
+
+/// Attributes the cost of execution to the call stack which incurred it.
+///
+/// Costs are measured from the cost tracker's running total, starting from zero, so the profiler
+///  must be attached to an environment with a fresh cost tracker.  Costs incurred outside of any
+///  function (such as loading the called contract) are attributed to the contract itself.
+pub struct CostProfiler {
+    stack: Vec<Frame>,
+    depth: usize,
+    last_total: ExecutionCost,
+    /// self cost of each distinct call stack, keyed by its frames joined with `;`
+    stacks: BTreeMap<String, ExecutionCost>,
+    functions: BTreeMap<String, FunctionProfile>,
+}
+
+impl Default for CostProfiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn saturating_add(total: &mut ExecutionCost, delta: &ExecutionCost) {
+    total.runtime = total.runtime.saturating_add(delta.runtime);
+    total.read_count = total.read_count.saturating_add(delta.read_count);
+    total.read_length = total.read_length.saturating_add(delta.read_length);
+    total.write_count = total.write_count.saturating_add(delta.write_count);
+    total.write_length = total.write_length.saturating_add(delta.write_length);
+}
+
+impl CostProfiler {
+    pub fn new() -> CostProfiler {
+        CostProfiler {
+            stack: vec![],
+            depth: 0,
+            last_total: ExecutionCost::ZERO,
+            stacks: BTreeMap::new(),
+            functions: BTreeMap::new(),
+        }
+    }
+
+    /// Attribute the cost incurred since the last event to the current stack
+    fn charge(&mut self, env: &Environment) -> ExecutionCost {
+        let total = env.global_context.cost_track.get_total();
+        let mut delta = total.clone();
+        if delta.sub(&self.last_total).is_err() {
+            // the tracker's total was wound back, so there is nothing to attribute
+            delta = ExecutionCost::ZERO;
+        }
+        self.last_total = total.clone();
+        if delta.is_zero() {
+            return total;
+        }
+
+        let stack = if self.stack.is_empty() {
+            env.contract_context.contract_identifier.to_string()
+        } else {
+            self.stack
+                .iter()
+                .map(|frame| frame.name.as_str())
+                .collect::<Vec<_>>()
+                .join(";")
+        };
+        saturating_add(
+            self.stacks.entry(stack).or_insert(ExecutionCost::ZERO),
+            &delta,
+        );
+
+        if let Some(frame) = self.stack.iter().rev().find(|frame| frame.is_function) {
+            if let Some(profile) = self.functions.get_mut(&frame.name) {
+                saturating_add(&mut profile.self_cost, &delta);
+            }
+        }
+        total
+    }
+
+    /// The self cost of each distinct call stack, keyed by its frames joined with `;`.  Frames
+    ///  are either user-defined functions, named as `contract:function`, or native functions.
+    pub fn stacks(&self) -> &BTreeMap<String, ExecutionCost> {
+        &self.stacks
+    }
+
+    /// The cost of each user-defined function which was called, keyed by `contract:function`
+    pub fn functions(&self) -> &BTreeMap<String, FunctionProfile> {
+        &self.functions
+    }
+
+    /// The total cost which was attributed
+    pub fn total(&self) -> ExecutionCost {
+        let mut total = ExecutionCost::ZERO;
+        for cost in self.stacks.values() {
+            saturating_add(&mut total, cost);
+        }
+        total
+    }
+
+    /// Write the stacks in the folded format used by flamegraph tools, weighted by one cost
+    ///  dimension
+    pub fn write_folded<W: Write>(&self, out: &mut W, dimension: CostDimension) -> io::Result<()> {
+        for (stack, cost) in self.stacks.iter() {
+            let weight = dimension.of(cost);
+            if weight > 0 {
+                writeln!(out, "{} {}", stack, weight)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write a table of the functions which were called, costliest (by runtime) first
+    pub fn write_table<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|a, b| b.1.cost.runtime.cmp(&a.1.cost.runtime).then(a.0.cmp(b.0)));
+
+        let width = functions
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("function".len());
+        writeln!(
+            out,
+            "{:<width$} {:>8} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+            "function",
+            "calls",
+            "runtime",
+            "self_runtime",
+            "read_count",
+            "read_length",
+            "write_count",
+            "write_length",
+            width = width
+        )?;
+        for (name, profile) in functions.into_iter() {
+            writeln!(
+                out,
+                "{:<width$} {:>8} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+                name,
+                profile.calls,
+                profile.cost.runtime,
+                profile.self_cost.runtime,
+                profile.cost.read_count,
+                profile.cost.read_length,
+                profile.cost.write_count,
+                profile.cost.write_length,
+                width = width
+            )?;
+        }
+        let total = self.total();
+        writeln!(
+            out,
+            "{:<width$} {:>8} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+            "total",
+            "",
+            total.runtime,
+            "",
+            total.read_count,
+            total.read_length,
+            total.write_count,
+            total.write_length,
+            width = width
+        )
+    }
+
+    /// Write the profile into a folder: a folded stack file for each cost dimension, named
+    ///  `{prefix}.{dimension}.folded`, and the function table as `{prefix}.profile.txt`.
+    pub fn to_folder<P: AsRef<Path>>(&self, folder: P, prefix: &str) -> io::Result<()> {
+        for dimension in CostDimension::ALL.iter() {
+            let path = folder
+                .as_ref()
+                .join(format!("{}.{}.folded", prefix, dimension.name()));
+            let mut f = File::create(path)?;
+            self.write_folded(&mut f, *dimension)?;
+        }
+        let mut f = File::create(folder.as_ref().join(format!("{}.profile.txt", prefix)))?;
+        self.write_table(&mut f)
+    }
+}
+
+impl EvalHook for CostProfiler {
+    fn will_begin_eval(
+        &mut self,
+        env: &mut Environment,
+        _context: &LocalContext,
+        expr: &SymbolicExpression,
+    ) {
+        let entry_total = self.charge(env);
+        self.depth += 1;
+
+        if let Some(function) = env.contract_context.lookup_function_by_body(expr.id) {
+            let name = format!("{}:{}", &env.contract_context.contract_identifier, function);
+            let profile = self
+                .functions
+                .entry(name.clone())
+                .or_insert(FunctionProfile {
+                    calls: 0,
+                    cost: ExecutionCost::ZERO,
+                    self_cost: ExecutionCost::ZERO,
+                });
+            profile.calls += 1;
+            self.stack.push(Frame {
+                name,
+                is_function: true,
+                expr_id: expr.id,
+                depth: self.depth,
+                entry_total: entry_total.clone(),
+            });
+        }
+
+        // a function whose body is a native function application gets a frame for each
+        let native = expr
+            .match_list()
+            .and_then(|list| list.first())
+            .and_then(|head| head.match_atom())
+            // calls to user-defined functions get a frame when their body is evaluated
+            .filter(|name| !env.contract_context.functions.contains_key(*name));
+        if let Some(name) = native {
+            self.stack.push(Frame {
+                name: name.to_string(),
+                is_function: false,
+                expr_id: expr.id,
+                depth: self.depth,
+                entry_total,
+            });
+        }
+    }
+
+    fn did_finish_eval(
+        &mut self,
+        env: &mut Environment,
+        _context: &LocalContext,
+        expr: &SymbolicExpression,
+        _res: &core::result::Result<Value, Error>,
+    ) {
+        let total = self.charge(env);
+
+        while self
+            .stack
+            .last()
+            .is_some_and(|frame| frame.expr_id == expr.id && frame.depth == self.depth)
+        {
+            let Some(frame) = self.stack.pop() else {
+                break;
+            };
+            if frame.is_function {
+                let mut cost = total.clone();
+                if cost.sub(&frame.entry_total).is_err() {
+                    cost = ExecutionCost::ZERO;
+                }
+                if let Some(profile) = self.functions.get_mut(&frame.name) {
+                    saturating_add(&mut profile.cost, &cost);
+                }
+            }
+        }
+
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    fn did_complete(&mut self, _result: core::result::Result<&mut ExecutionResult, String>) {}
+}
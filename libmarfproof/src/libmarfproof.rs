@@ -0,0 +1,792 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verifier for Merkle proofs over the MARF, the authenticated index of Stacks chain state.
+//!
+//! Every Stacks block header commits to the root hash of its MARF trie (`state_index_root`).  A
+//! proof shows that a key either maps to a given value, or has no value at all, as of that block.
+//! A proof may pass through the tries of the block's ancestors, so the verifier also needs to
+//! know which block each of those trie root hashes belongs to.  A light client learns this from
+//! the ancestors' headers.
+//!
+//! This library only needs `alloc`, so it can be built without `std` by disabling the default
+//! features.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use sha2::{Digest, Sha512_256};
+
+#[cfg(test)]
+mod tests;
+
+/// Length of a trie hash, trie path, or block ID
+pub const TRIE_HASH_LEN: usize = 32;
+/// Length of a MARF value
+pub const MARF_VALUE_LEN: usize = 40;
+
+pub type TrieHash = [u8; TRIE_HASH_LEN];
+pub type MarfValue = [u8; MARF_VALUE_LEN];
+
+/// Trie node type IDs
+pub const NODE_ID_EMPTY: u8 = 0;
+pub const NODE_ID_LEAF: u8 = 1;
+pub const NODE_ID_NODE4: u8 = 2;
+pub const NODE_ID_NODE16: u8 = 3;
+pub const NODE_ID_NODE48: u8 = 4;
+pub const NODE_ID_NODE256: u8 = 5;
+
+/// A node ID encodes a back-pointer if its high bit is set
+const BACKPTR_BIT: u8 = 0x80;
+
+/// Proof entry type bytes
+const ENTRY_NODE4: u8 = 0;
+const ENTRY_NODE16: u8 = 1;
+const ENTRY_NODE48: u8 = 2;
+const ENTRY_NODE256: u8 = 3;
+const ENTRY_LEAF: u8 = 4;
+const ENTRY_SHUNT: u8 = 5;
+
+/// Non-inclusion proof terminal node type bytes
+const TERMINAL_LEAF: u8 = 0;
+const TERMINAL_NODE: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The proof could not be decoded
+    Decode(&'static str),
+    /// The proof is not shaped like a MARF proof
+    Malformed(&'static str),
+    /// The proof is for a different key
+    PathMismatch,
+    /// The proof is for a different value
+    ValueMismatch,
+    /// The non-inclusion proof leads to a value for the key
+    KeyPresent,
+    /// A trie root hash calculated from the proof does not belong to a known block
+    UnknownTrieRoot(TrieHash),
+    /// The proof leads to a different root hash than the expected one
+    RootMismatch {
+        expected: TrieHash,
+        computed: TrieHash,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Decode(msg) => write!(f, "failed to decode proof: {}", msg),
+            Error::Malformed(msg) => write!(f, "malformed proof: {}", msg),
+            Error::PathMismatch => write!(f, "proof is for a different key"),
+            Error::ValueMismatch => write!(f, "proof is for a different value"),
+            Error::KeyPresent => write!(f, "key has a value"),
+            Error::UnknownTrieRoot(root) => {
+                write!(f, "trie root ")?;
+                write_hex(f, root)?;
+                write!(f, " does not belong to a known block")
+            }
+            Error::RootMismatch { expected, computed } => {
+                write!(f, "proof leads to root hash ")?;
+                write_hex(f, computed)?;
+                write!(f, ", expected ")?;
+                write_hex(f, expected)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+fn write_hex(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result {
+    for b in bytes {
+        write!(f, "{:02x}", b)?;
+    }
+    Ok(())
+}
+
+/// A child pointer of a trie node.
+/// If this is a back-pointer, `back_block` is the ID of the block whose trie holds the child.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofPtr {
+    pub id: u8,
+    pub chr: u8,
+    pub back_block: [u8; TRIE_HASH_LEN],
+}
+
+impl ProofPtr {
+    pub fn is_empty(&self) -> bool {
+        self.id == NODE_ID_EMPTY
+    }
+
+    pub fn is_backptr(&self) -> bool {
+        self.id & BACKPTR_BIT != 0
+    }
+}
+
+/// An intermediate trie node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofNode {
+    pub id: u8,
+    pub path: Vec<u8>,
+    pub ptrs: Vec<ProofPtr>,
+}
+
+impl ProofNode {
+    /// How many child pointers does a node of this type have?
+    pub fn num_children(&self) -> Option<usize> {
+        match self.id {
+            NODE_ID_NODE4 => Some(4),
+            NODE_ID_NODE16 => Some(16),
+            NODE_ID_NODE48 => Some(48),
+            NODE_ID_NODE256 => Some(256),
+            _ => None,
+        }
+    }
+
+    /// Find the non-empty child pointer for the given path byte, if there is one
+    pub fn walk(&self, chr: u8) -> Option<&ProofPtr> {
+        self.ptrs
+            .iter()
+            .find(|ptr| !ptr.is_empty() && ptr.chr == chr)
+    }
+
+    /// Hash this node, given the hashes of all of its children
+    pub fn hash(&self, child_hashes: &[TrieHash]) -> TrieHash {
+        let mut hasher = Sha512_256::new();
+        hasher.update([self.id]);
+        for ptr in self.ptrs.iter() {
+            hasher.update([ptr.id, ptr.chr]);
+            hasher.update(ptr.back_block);
+        }
+        hasher.update([self.path.len() as u8]);
+        hasher.update(&self.path);
+        for child_hash in child_hashes.iter() {
+            hasher.update(child_hash);
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// A trie leaf.  Its path is the remainder of the key's path after its parent node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofLeaf {
+    pub path: Vec<u8>,
+    pub value: MarfValue,
+}
+
+impl ProofLeaf {
+    pub fn hash(&self) -> TrieHash {
+        let mut hasher = Sha512_256::new();
+        hasher.update([NODE_ID_LEAF]);
+        hasher.update([self.path.len() as u8]);
+        hasher.update(&self.path);
+        hasher.update(self.value);
+        hasher.finalize().into()
+    }
+}
+
+/// One step of a MARF proof.
+///
+/// A proof is a sequence of segment proofs, each followed by a shunt proof.  A segment proof is
+/// a walk from a node up to its trie's root, and a shunt proof links that trie's root to the
+/// trie of the next, later block the proof visits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofEntry {
+    /// An intermediate node on the key's path, with the hashes of all of its children except for
+    /// the one at `chr`
+    Node {
+        chr: u8,
+        node: ProofNode,
+        hashes: Vec<TrieHash>,
+    },
+    /// The leaf for the key
+    Leaf { chr: u8, leaf: ProofLeaf },
+    /// The ancestor trie root hashes of a trie, except for the one at `idx`
+    Shunt { idx: i64, hashes: Vec<TrieHash> },
+}
+
+impl ProofEntry {
+    fn is_shunt(&self) -> bool {
+        matches!(self, ProofEntry::Shunt { .. })
+    }
+}
+
+/// Proof that a key maps to a value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub entries: Vec<ProofEntry>,
+}
+
+/// The node at which a lookup for a key stops because the key is not in the trie
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalNode {
+    /// A leaf for a different key
+    Leaf(ProofLeaf),
+    /// An intermediate node which has no child for the key, with the hashes of all of its children
+    Node {
+        node: ProofNode,
+        hashes: Vec<TrieHash>,
+    },
+}
+
+impl TerminalNode {
+    fn hash(&self) -> Result<TrieHash, Error> {
+        match self {
+            TerminalNode::Leaf(leaf) => Ok(leaf.hash()),
+            TerminalNode::Node { node, hashes } => {
+                let count = node
+                    .num_children()
+                    .ok_or(Error::Malformed("unknown terminal node type"))?;
+                if node.ptrs.len() != count || hashes.len() != count {
+                    return Err(Error::Malformed("wrong number of terminal node children"));
+                }
+                Ok(node.hash(hashes))
+            }
+        }
+    }
+}
+
+/// Proof that a key has no value.
+/// The entries are the same as those of an inclusion proof, except that the first segment proof
+/// ends at the parent of `terminal` instead of at a leaf.  It is empty if `terminal` is a root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonInclusionProof {
+    pub terminal: TerminalNode,
+    pub entries: Vec<ProofEntry>,
+}
+
+impl InclusionProof {
+    /// Decode a consensus-serialized proof
+    pub fn from_bytes(bytes: &[u8]) -> Result<InclusionProof, Error> {
+        let mut reader = Reader::new(bytes);
+        let entries = reader.read_entries()?;
+        reader.finish()?;
+        Ok(InclusionProof { entries })
+    }
+
+    /// Verify that `path` maps to `value` in the trie with the given root hash.
+    /// `root_to_block` maps the root hash of each trie the proof visits to its block ID.
+    pub fn verify<F>(
+        &self,
+        path: &TrieHash,
+        value: &MarfValue,
+        root_hash: &TrieHash,
+        root_to_block: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&TrieHash) -> Option<TrieHash>,
+    {
+        verify_inclusion(&self.entries, path, value, root_hash, root_to_block)
+    }
+}
+
+impl NonInclusionProof {
+    /// Decode a consensus-serialized proof
+    pub fn from_bytes(bytes: &[u8]) -> Result<NonInclusionProof, Error> {
+        let mut reader = Reader::new(bytes);
+        let terminal = match reader.read_u8()? {
+            TERMINAL_LEAF => TerminalNode::Leaf(reader.read_leaf()?),
+            TERMINAL_NODE => {
+                let node = reader.read_node()?;
+                let hashes = reader.read_hash_vec()?;
+                TerminalNode::Node { node, hashes }
+            }
+            _ => return Err(Error::Decode("bad terminal node type")),
+        };
+        let entries = reader.read_entries()?;
+        reader.finish()?;
+        Ok(NonInclusionProof { terminal, entries })
+    }
+
+    /// Verify that `path` has no value in the trie with the given root hash.
+    /// `root_to_block` maps the root hash of each trie the proof visits to its block ID.
+    pub fn verify<F>(
+        &self,
+        path: &TrieHash,
+        root_hash: &TrieHash,
+        root_to_block: F,
+    ) -> Result<(), Error>
+    where
+        F: Fn(&TrieHash) -> Option<TrieHash>,
+    {
+        verify_non_inclusion(
+            &self.terminal,
+            &self.entries,
+            path,
+            root_hash,
+            root_to_block,
+        )
+    }
+}
+
+/// Hash a list of hashes
+fn hash_array(hashes: &[TrieHash]) -> TrieHash {
+    let mut hasher = Sha512_256::new();
+    for hash in hashes.iter() {
+        hasher.update(hash);
+    }
+    hasher.finalize().into()
+}
+
+/// Index of the first shunt proof entry at or after `start`
+fn next_shunt(entries: &[ProofEntry], start: usize) -> usize {
+    let mut i = start;
+    while i < entries.len() && !entries[i].is_shunt() {
+        i += 1;
+    }
+    i
+}
+
+/// Calculate the hash of a segment proof node, given the hash of its child on the key's path
+fn segment_node_hash(
+    node: &ProofNode,
+    child_hash: &TrieHash,
+    chr: u8,
+    hashes: &[TrieHash],
+) -> Result<TrieHash, Error> {
+    let count = node
+        .num_children()
+        .ok_or(Error::Malformed("unknown node type"))?;
+    if node.ptrs.len() != count || hashes.len() + 1 != count {
+        return Err(Error::Malformed("wrong number of node children"));
+    }
+
+    let mut all_hashes = Vec::with_capacity(count);
+    let mut hashes_iter = hashes.iter();
+    for ptr in node.ptrs.iter() {
+        if !ptr.is_empty() && ptr.chr == chr {
+            all_hashes.push(*child_hash);
+        } else {
+            let hash = hashes_iter
+                .next()
+                .ok_or(Error::Malformed("too few node child hashes"))?;
+            all_hashes.push(*hash);
+        }
+    }
+    if all_hashes.len() != count {
+        return Err(Error::Malformed("wrong number of node child hashes"));
+    }
+    Ok(node.hash(&all_hashes))
+}
+
+/// Calculate the root hash of a segment proof's trie, given the hash of its deepest node
+fn segment_root_hash(segment: &[ProofEntry], node_hash: &TrieHash) -> Result<TrieHash, Error> {
+    let mut hash = *node_hash;
+    for entry in segment.iter() {
+        hash = match entry {
+            ProofEntry::Leaf { leaf, .. } => leaf.hash(),
+            ProofEntry::Node { chr, node, hashes } => segment_node_hash(node, &hash, *chr, hashes)?,
+            ProofEntry::Shunt { .. } => {
+                return Err(Error::Malformed("shunt proof entry in segment proof"));
+            }
+        };
+    }
+    Ok(hash)
+}
+
+/// The part of the key's path that a segment proof walks, from the root down
+fn segment_path(segment: &[ProofEntry]) -> Result<Vec<u8>, Error> {
+    let mut path = Vec::new();
+    for entry in segment.iter().rev() {
+        match entry {
+            ProofEntry::Leaf { leaf, .. } => path.extend_from_slice(&leaf.path),
+            ProofEntry::Node { chr, node, .. } => {
+                path.extend_from_slice(&node.path);
+                path.push(*chr);
+            }
+            ProofEntry::Shunt { .. } => {
+                return Err(Error::Malformed("shunt proof entry in segment proof"));
+            }
+        }
+    }
+    Ok(path)
+}
+
+/// Check that the proof alternates between segment proofs and shunt proofs, and that each
+/// segment proof after the first walks a prefix of `path`.
+fn check_segments(entries: &[ProofEntry], path: &TrieHash) -> Result<(), Error> {
+    if entries.is_empty() {
+        return Err(Error::Malformed("empty proof"));
+    }
+
+    // the first segment proof is checked by the caller
+    let mut i = next_shunt(entries, 0);
+    while i < entries.len() {
+        // shunt proof
+        while i < entries.len() && entries[i].is_shunt() {
+            i += 1;
+        }
+        if i >= entries.len() {
+            break;
+        }
+
+        // segment proof
+        let j = next_shunt(entries, i);
+        let segment = segment_path(&entries[i..j])?;
+        if segment.len() > path.len() || path[..segment.len()] != segment[..] {
+            return Err(Error::PathMismatch);
+        }
+        i = j;
+    }
+
+    if !entries[entries.len() - 1].is_shunt() {
+        return Err(Error::Malformed("proof must end with a shunt proof"));
+    }
+    Ok(())
+}
+
+/// Combine the head of a shunt proof with the root node hash of its trie
+fn shunt_head_hash(node_root_hash: &TrieHash, entry: &ProofEntry) -> Result<TrieHash, Error> {
+    let ProofEntry::Shunt { idx, hashes } = entry else {
+        return Err(Error::Malformed("expected a shunt proof"));
+    };
+    if *idx != 0 {
+        return Err(Error::Malformed(
+            "first shunt proof entry must have idx == 0",
+        ));
+    }
+    if hashes.is_empty() {
+        // the trie has no ancestors
+        return Ok(*node_root_hash);
+    }
+    let mut all_hashes = Vec::with_capacity(hashes.len() + 1);
+    all_hashes.push(*node_root_hash);
+    all_hashes.extend_from_slice(hashes);
+    Ok(hash_array(&all_hashes))
+}
+
+/// Insert `hash` into a shunt proof entry's hashes, and hash the result
+fn shunt_next_hash(hash: &TrieHash, idx: i64, hashes: &[TrieHash]) -> Result<TrieHash, Error> {
+    if idx <= 0 || (idx as u64) > (hashes.len() as u64) + 1 {
+        return Err(Error::Malformed("bad shunt proof index"));
+    }
+    let mut all_hashes = Vec::with_capacity(hashes.len() + 1);
+    all_hashes.extend_from_slice(hashes);
+    all_hashes.insert((idx - 1) as usize, *hash);
+    Ok(hash_array(&all_hashes))
+}
+
+/// Walk the intermediate entries of a shunt proof
+fn shunt_tail_hash(initial_hash: &TrieHash, entries: &[ProofEntry]) -> Result<TrieHash, Error> {
+    let mut hash = *initial_hash;
+    for entry in entries.iter() {
+        let ProofEntry::Shunt { idx, hashes } = entry else {
+            return Err(Error::Malformed("expected a shunt proof"));
+        };
+        hash = shunt_next_hash(&hash, *idx, hashes)?;
+    }
+    Ok(hash)
+}
+
+/// Combine the last entry of a shunt proof with the root node hash of the next segment proof's
+/// trie, and the trie root hash calculated from the rest of the shunt proof.
+fn shunt_junction_hash(
+    node_root_hash: &TrieHash,
+    penultimate_trie_hash: &TrieHash,
+    entry: &ProofEntry,
+) -> Result<TrieHash, Error> {
+    let ProofEntry::Shunt { idx, hashes } = entry else {
+        return Err(Error::Malformed("expected a shunt proof"));
+    };
+    if *idx <= 0 || (*idx as u64) > (hashes.len() as u64) + 1 {
+        return Err(Error::Malformed("bad shunt proof index"));
+    }
+    let mut all_hashes = Vec::with_capacity(hashes.len() + 2);
+    all_hashes.push(*node_root_hash);
+    all_hashes.extend_from_slice(hashes);
+    all_hashes.insert(*idx as usize, *penultimate_trie_hash);
+    Ok(hash_array(&all_hashes))
+}
+
+/// Verify the chain of segment and shunt proofs, given the hash of the deepest node of the first
+/// segment proof.
+fn verify_proof_chain<F>(
+    entries: &[ProofEntry],
+    node_hash: &TrieHash,
+    root_hash: &TrieHash,
+    root_to_block: F,
+) -> Result<(), Error>
+where
+    F: Fn(&TrieHash) -> Option<TrieHash>,
+{
+    let lookup_block = |trie_hash: &TrieHash| -> Result<TrieHash, Error> {
+        root_to_block(trie_hash).ok_or(Error::UnknownTrieRoot(*trie_hash))
+    };
+
+    // first segment proof, from the oldest trie
+    let j = next_shunt(entries, 0);
+    let node_root_hash = segment_root_hash(&entries[..j], node_hash)?;
+
+    let mut i = j;
+    if i >= entries.len() {
+        return Err(Error::Malformed(
+            "missing shunt proof for the first segment proof",
+        ));
+    }
+    let mut trie_hash = shunt_head_hash(&node_root_hash, &entries[i])?;
+
+    i += 1;
+    if i < entries.len() {
+        // the next segment proof's path ends at a back-pointer to this trie's block
+        let mut node_hash = lookup_block(&trie_hash)?;
+        if entries[i].is_shunt() {
+            return Err(Error::Malformed(
+                "expected a segment proof after the first shunt proof",
+            ));
+        }
+
+        while i < entries.len() {
+            let j = next_shunt(entries, i + 1);
+            let next_node_root_hash = segment_root_hash(&entries[i..j], &node_hash)?;
+
+            i = j;
+            if i >= entries.len() {
+                return Err(Error::Malformed("missing shunt proof"));
+            }
+
+            // all but the last shunt proof entry make up the tail
+            let mut j = i;
+            while j < entries.len() {
+                match &entries[j] {
+                    ProofEntry::Shunt { idx, .. } if *idx != 0 => j += 1,
+                    _ => break,
+                }
+            }
+            if j <= i {
+                return Err(Error::Malformed("missing shunt proof junction"));
+            }
+            j -= 1;
+
+            let penultimate_trie_hash = shunt_tail_hash(&trie_hash, &entries[i..j])?;
+            trie_hash =
+                shunt_junction_hash(&next_node_root_hash, &penultimate_trie_hash, &entries[j])?;
+            node_hash = lookup_block(&trie_hash)?;
+
+            i = j + 1;
+            if trie_hash == *root_hash {
+                break;
+            }
+        }
+    }
+
+    if trie_hash != *root_hash {
+        return Err(Error::RootMismatch {
+            expected: *root_hash,
+            computed: trie_hash,
+        });
+    }
+    Ok(())
+}
+
+/// Verify that `path` maps to `value` in the MARF trie with root hash `root_hash`.
+/// `root_to_block` maps the root hash of each trie the proof visits to its block ID.
+pub fn verify_inclusion<F>(
+    entries: &[ProofEntry],
+    path: &TrieHash,
+    value: &MarfValue,
+    root_hash: &TrieHash,
+    root_to_block: F,
+) -> Result<(), Error>
+where
+    F: Fn(&TrieHash) -> Option<TrieHash>,
+{
+    check_segments(entries, path)?;
+
+    let ProofEntry::Leaf { leaf, .. } = &entries[0] else {
+        return Err(Error::Malformed("first proof entry is not a leaf"));
+    };
+    let first_segment = &entries[..next_shunt(entries, 0)];
+    if segment_path(first_segment)?[..] != path[..] {
+        return Err(Error::PathMismatch);
+    }
+    if leaf.value != *value {
+        return Err(Error::ValueMismatch);
+    }
+
+    verify_proof_chain(entries, &leaf.hash(), root_hash, root_to_block)
+}
+
+/// Verify that `path` has no value in the MARF trie with root hash `root_hash`.
+/// `root_to_block` maps the root hash of each trie the proof visits to its block ID.
+pub fn verify_non_inclusion<F>(
+    terminal: &TerminalNode,
+    entries: &[ProofEntry],
+    path: &TrieHash,
+    root_hash: &TrieHash,
+    root_to_block: F,
+) -> Result<(), Error>
+where
+    F: Fn(&TrieHash) -> Option<TrieHash>,
+{
+    check_segments(entries, path)?;
+
+    // the first segment proof walks the path down to the terminal node
+    let first_segment = &entries[..next_shunt(entries, 0)];
+    if first_segment
+        .iter()
+        .any(|entry| matches!(entry, ProofEntry::Leaf { .. }))
+    {
+        return Err(Error::Malformed("leaf in non-inclusion proof segment"));
+    }
+    let prefix = segment_path(first_segment)?;
+    if prefix.len() > path.len() || path[..prefix.len()] != prefix[..] {
+        return Err(Error::PathMismatch);
+    }
+    let rest = &path[prefix.len()..];
+
+    // the terminal node must show that the walk cannot continue
+    match terminal {
+        TerminalNode::Leaf(leaf) => {
+            if leaf.path[..] == rest[..] {
+                return Err(Error::KeyPresent);
+            }
+        }
+        TerminalNode::Node { node, .. } => {
+            let node_path_len = node.path.len();
+            if node_path_len >= rest.len() {
+                return Err(Error::Malformed("terminal node path is too long"));
+            }
+            if node.path[..] == rest[..node_path_len] && node.walk(rest[node_path_len]).is_some() {
+                return Err(Error::KeyPresent);
+            }
+        }
+    }
+
+    verify_proof_chain(entries, &terminal.hash()?, root_hash, root_to_block)
+}
+
+/// Decoder for consensus-serialized proofs
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        if !self.bytes.is_empty() {
+            return Err(Error::Decode("trailing bytes"));
+        }
+        Ok(())
+    }
+
+    fn read_slice(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < len {
+            return Err(Error::Decode("unexpected end of input"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.read_slice(N)?);
+        Ok(array)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        Ok(self.read_array::<1>()?[0])
+    }
+
+    /// Read a vector length prefix, and make sure there is enough input for that many items
+    fn read_len(&mut self, min_item_len: usize) -> Result<usize, Error> {
+        let len = u32::from_be_bytes(self.read_array()?) as usize;
+        if len.saturating_mul(min_item_len) > self.bytes.len() {
+            return Err(Error::Decode("length prefix exceeds input"));
+        }
+        Ok(len)
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>, Error> {
+        let len = self.read_len(1)?;
+        Ok(self.read_slice(len)?.to_vec())
+    }
+
+    fn read_hashes(&mut self, count: usize) -> Result<Vec<TrieHash>, Error> {
+        let mut hashes = Vec::with_capacity(count);
+        for _ in 0..count {
+            hashes.push(self.read_array()?);
+        }
+        Ok(hashes)
+    }
+
+    fn read_hash_vec(&mut self) -> Result<Vec<TrieHash>, Error> {
+        let len = self.read_len(TRIE_HASH_LEN)?;
+        self.read_hashes(len)
+    }
+
+    fn read_node(&mut self) -> Result<ProofNode, Error> {
+        let id = self.read_u8()?;
+        let path = self.read_bytes()?;
+        let num_ptrs = self.read_len(2 + TRIE_HASH_LEN)?;
+        let mut ptrs = Vec::with_capacity(num_ptrs);
+        for _ in 0..num_ptrs {
+            ptrs.push(ProofPtr {
+                id: self.read_u8()?,
+                chr: self.read_u8()?,
+                back_block: self.read_array()?,
+            });
+        }
+        Ok(ProofNode { id, path, ptrs })
+    }
+
+    fn read_leaf(&mut self) -> Result<ProofLeaf, Error> {
+        let path = self.read_bytes()?;
+        let value = self.read_array()?;
+        Ok(ProofLeaf { path, value })
+    }
+
+    fn read_entry(&mut self) -> Result<ProofEntry, Error> {
+        let num_hashes = match self.read_u8()? {
+            ENTRY_NODE4 => 3,
+            ENTRY_NODE16 => 15,
+            ENTRY_NODE48 => 47,
+            ENTRY_NODE256 => 255,
+            ENTRY_LEAF => {
+                let chr = self.read_u8()?;
+                let leaf = self.read_leaf()?;
+                return Ok(ProofEntry::Leaf { chr, leaf });
+            }
+            ENTRY_SHUNT => {
+                let idx = i64::from_be_bytes(self.read_array()?);
+                let hashes = self.read_hash_vec()?;
+                return Ok(ProofEntry::Shunt { idx, hashes });
+            }
+            _ => return Err(Error::Decode("bad proof entry type")),
+        };
+        let chr = self.read_u8()?;
+        let node = self.read_node()?;
+        let hashes = self.read_hashes(num_hashes)?;
+        Ok(ProofEntry::Node { chr, node, hashes })
+    }
+
+    fn read_entries(&mut self) -> Result<Vec<ProofEntry>, Error> {
+        let len = self.read_len(1)?;
+        let mut entries = Vec::with_capacity(len);
+        for _ in 0..len {
+            entries.push(self.read_entry()?);
+        }
+        Ok(entries)
+    }
+}
@@ -0,0 +1,327 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use sha2::{Digest, Sha512_256};
+
+use crate::*;
+
+fn empty_hash() -> TrieHash {
+    Sha512_256::digest([]).into()
+}
+
+fn make_path(first: u8, rest: u8) -> TrieHash {
+    let mut path = [rest; 32];
+    path[0] = first;
+    path
+}
+
+/// A single trie whose root has one leaf, at the given path
+fn make_trie(path: &TrieHash, value: &MarfValue) -> (ProofNode, ProofLeaf) {
+    let mut ptrs: Vec<ProofPtr> = (0..256)
+        .map(|_| ProofPtr {
+            id: NODE_ID_EMPTY,
+            chr: 0,
+            back_block: [0u8; 32],
+        })
+        .collect();
+    ptrs[path[0] as usize] = ProofPtr {
+        id: NODE_ID_LEAF,
+        chr: path[0],
+        back_block: [0u8; 32],
+    };
+    let root = ProofNode {
+        id: NODE_ID_NODE256,
+        path: vec![],
+        ptrs,
+    };
+    let leaf = ProofLeaf {
+        path: path[1..].to_vec(),
+        value: *value,
+    };
+    (root, leaf)
+}
+
+fn root_child_hashes(root: &ProofNode, leaf: &ProofLeaf) -> Vec<TrieHash> {
+    root.ptrs
+        .iter()
+        .map(|ptr| {
+            if ptr.is_empty() {
+                empty_hash()
+            } else {
+                leaf.hash()
+            }
+        })
+        .collect()
+}
+
+fn root_segment(root: &ProofNode, chr: u8) -> ProofEntry {
+    ProofEntry::Node {
+        chr,
+        node: root.clone(),
+        hashes: vec![empty_hash(); 255],
+    }
+}
+
+fn no_ancestors(_root: &TrieHash) -> Option<TrieHash> {
+    None
+}
+
+fn encode_node(node: &ProofNode, out: &mut Vec<u8>) {
+    out.push(node.id);
+    out.extend_from_slice(&(node.path.len() as u32).to_be_bytes());
+    out.extend_from_slice(&node.path);
+    out.extend_from_slice(&(node.ptrs.len() as u32).to_be_bytes());
+    for ptr in node.ptrs.iter() {
+        out.push(ptr.id);
+        out.push(ptr.chr);
+        out.extend_from_slice(&ptr.back_block);
+    }
+}
+
+fn encode_leaf(leaf: &ProofLeaf, out: &mut Vec<u8>) {
+    out.extend_from_slice(&(leaf.path.len() as u32).to_be_bytes());
+    out.extend_from_slice(&leaf.path);
+    out.extend_from_slice(&leaf.value);
+}
+
+fn encode_entries(entries: &[ProofEntry], out: &mut Vec<u8>) {
+    out.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+    for entry in entries.iter() {
+        match entry {
+            ProofEntry::Node { chr, node, hashes } => {
+                out.push(match node.id {
+                    NODE_ID_NODE4 => 0,
+                    NODE_ID_NODE16 => 1,
+                    NODE_ID_NODE48 => 2,
+                    _ => 3,
+                });
+                out.push(*chr);
+                encode_node(node, out);
+                for hash in hashes.iter() {
+                    out.extend_from_slice(hash);
+                }
+            }
+            ProofEntry::Leaf { chr, leaf } => {
+                out.push(4);
+                out.push(*chr);
+                encode_leaf(leaf, out);
+            }
+            ProofEntry::Shunt { idx, hashes } => {
+                out.push(5);
+                out.extend_from_slice(&idx.to_be_bytes());
+                out.extend_from_slice(&(hashes.len() as u32).to_be_bytes());
+                for hash in hashes.iter() {
+                    out.extend_from_slice(hash);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_verify_inclusion() {
+    let path = make_path(0x01, 0x02);
+    let value = [0x03; 40];
+    let (root, leaf) = make_trie(&path, &value);
+    let root_hash = root.hash(&root_child_hashes(&root, &leaf));
+
+    let proof = InclusionProof {
+        entries: vec![
+            ProofEntry::Leaf {
+                chr: 0x01,
+                leaf: leaf.clone(),
+            },
+            root_segment(&root, 0x01),
+            ProofEntry::Shunt {
+                idx: 0,
+                hashes: vec![],
+            },
+        ],
+    };
+
+    proof
+        .verify(&path, &value, &root_hash, no_ancestors)
+        .unwrap();
+
+    // round-trips through the wire format
+    let mut bytes = vec![];
+    encode_entries(&proof.entries, &mut bytes);
+    assert_eq!(InclusionProof::from_bytes(&bytes).unwrap(), proof);
+
+    // wrong value
+    assert_eq!(
+        proof.verify(&path, &[0x04; 40], &root_hash, no_ancestors),
+        Err(Error::ValueMismatch)
+    );
+
+    // wrong key
+    assert_eq!(
+        proof.verify(&make_path(0x01, 0x05), &value, &root_hash, no_ancestors),
+        Err(Error::PathMismatch)
+    );
+
+    // wrong root
+    assert!(matches!(
+        proof.verify(&path, &value, &[0x06; 32], no_ancestors),
+        Err(Error::RootMismatch { .. })
+    ));
+
+    // tampered sibling hash
+    let mut bad_proof = proof.clone();
+    if let ProofEntry::Node { hashes, .. } = &mut bad_proof.entries[1] {
+        hashes[0] = [0x07; 32];
+    }
+    assert!(matches!(
+        bad_proof.verify(&path, &value, &root_hash, no_ancestors),
+        Err(Error::RootMismatch { .. })
+    ));
+
+    // missing shunt proof
+    let mut bad_proof = proof.clone();
+    bad_proof.entries.pop();
+    assert!(matches!(
+        bad_proof.verify(&path, &value, &root_hash, no_ancestors),
+        Err(Error::Malformed(_))
+    ));
+}
+
+#[test]
+fn test_verify_non_inclusion() {
+    let path = make_path(0x01, 0x02);
+    let value = [0x03; 40];
+    let (root, leaf) = make_trie(&path, &value);
+    let root_hash = root.hash(&root_child_hashes(&root, &leaf));
+    let shunt = ProofEntry::Shunt {
+        idx: 0,
+        hashes: vec![],
+    };
+
+    // the root has no child for this key
+    let missing_path = make_path(0x02, 0x02);
+    let proof = NonInclusionProof {
+        terminal: TerminalNode::Node {
+            node: root.clone(),
+            hashes: root_child_hashes(&root, &leaf),
+        },
+        entries: vec![shunt.clone()],
+    };
+    proof
+        .verify(&missing_path, &root_hash, no_ancestors)
+        .unwrap();
+    assert_eq!(
+        proof.verify(&path, &root_hash, no_ancestors),
+        Err(Error::KeyPresent)
+    );
+
+    // round-trips through the wire format
+    let mut bytes = vec![TERMINAL_NODE];
+    encode_node(&root, &mut bytes);
+    bytes.extend_from_slice(&256u32.to_be_bytes());
+    for hash in root_child_hashes(&root, &leaf) {
+        bytes.extend_from_slice(&hash);
+    }
+    encode_entries(&proof.entries, &mut bytes);
+    assert_eq!(NonInclusionProof::from_bytes(&bytes).unwrap(), proof);
+
+    // the key's path leads to a leaf for a different key
+    let diverged_path = make_path(0x01, 0x05);
+    let proof = NonInclusionProof {
+        terminal: TerminalNode::Leaf(leaf.clone()),
+        entries: vec![root_segment(&root, 0x01), shunt.clone()],
+    };
+    proof
+        .verify(&diverged_path, &root_hash, no_ancestors)
+        .unwrap();
+    assert_eq!(
+        proof.verify(&path, &root_hash, no_ancestors),
+        Err(Error::KeyPresent)
+    );
+
+    let mut bytes = vec![TERMINAL_LEAF];
+    encode_leaf(&leaf, &mut bytes);
+    encode_entries(&proof.entries, &mut bytes);
+    assert_eq!(NonInclusionProof::from_bytes(&bytes).unwrap(), proof);
+
+    // the segment proof must walk the key's path
+    assert_eq!(
+        proof.verify(&make_path(0x02, 0x05), &root_hash, no_ancestors),
+        Err(Error::PathMismatch)
+    );
+
+    // a leaf for a different value does not hash to the root
+    let other_leaf = ProofLeaf {
+        path: leaf.path.clone(),
+        value: [0x04; 40],
+    };
+    let proof = NonInclusionProof {
+        terminal: TerminalNode::Leaf(other_leaf),
+        entries: vec![root_segment(&root, 0x01), shunt],
+    };
+    assert!(matches!(
+        proof.verify(&diverged_path, &root_hash, no_ancestors),
+        Err(Error::RootMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_decode_errors() {
+    let path = make_path(0x01, 0x02);
+    let (root, leaf) = make_trie(&path, &[0x03; 40]);
+    let entries = vec![
+        ProofEntry::Leaf { chr: 0x01, leaf },
+        root_segment(&root, 0x01),
+        ProofEntry::Shunt {
+            idx: 0,
+            hashes: vec![],
+        },
+    ];
+    let mut bytes = vec![];
+    encode_entries(&entries, &mut bytes);
+
+    // truncated
+    for len in [0, 3, 4, 10, bytes.len() - 1] {
+        assert!(matches!(
+            InclusionProof::from_bytes(&bytes[..len]),
+            Err(Error::Decode(_))
+        ));
+    }
+
+    // trailing bytes
+    let mut long_bytes = bytes.clone();
+    long_bytes.push(0);
+    assert!(matches!(
+        InclusionProof::from_bytes(&long_bytes),
+        Err(Error::Decode(_))
+    ));
+
+    // huge length prefix
+    assert!(matches!(
+        InclusionProof::from_bytes(&[0xff, 0xff, 0xff, 0xff]),
+        Err(Error::Decode(_))
+    ));
+
+    // bad entry type
+    assert!(matches!(
+        InclusionProof::from_bytes(&[0, 0, 0, 1, 6]),
+        Err(Error::Decode(_))
+    ));
+
+    // bad terminal node type
+    assert!(matches!(
+        NonInclusionProof::from_bytes(&[2]),
+        Err(Error::Decode(_))
+    ));
+}
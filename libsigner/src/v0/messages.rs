@@ -76,6 +76,10 @@ use crate::{
 /// Maximum size of the [BlockResponseData] serialized bytes
 pub const BLOCK_RESPONSE_DATA_MAX_SIZE: u32 = 2 * 1024 * 1024; // 2MB
 
+/// Maximum length of the rule carried by a [RejectReason::PolicyViolation], in bytes.
+/// Longer rules are truncated when serialized.
+pub const POLICY_VIOLATION_RULE_MAX_LEN: u32 = 1024;
+
 define_u8_enum!(
 /// Enum representing the stackerdb message identifier: this is
 ///  the contract index in the signers contracts (i.e., X in signers-0-X)
@@ -325,6 +329,18 @@ impl StacksMessageCodec for PeerInfo {
     }
 }
 
+/// Signs signer messages with the signer's Stacks private key, which may be held elsewhere
+pub trait MessageSigner {
+    /// Sign the 32-byte message hash, returning a recoverable signature
+    fn sign_message(&self, message_hash: &[u8]) -> Result<MessageSignature, String>;
+}
+
+impl MessageSigner for StacksPrivateKey {
+    fn sign_message(&self, message_hash: &[u8]) -> Result<MessageSignature, String> {
+        self.sign(message_hash).map_err(|e| e.to_string())
+    }
+}
+
 /// A mock block proposal for Epoch 2.5 mock signing
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MockProposal {
@@ -448,22 +464,25 @@ pub struct MockSignature {
 }
 
 impl MockSignature {
-    /// Create a new mock signature from the provided proposal and signer private key.
-    pub fn new(mock_proposal: MockProposal, stacks_private_key: &StacksPrivateKey) -> Self {
+    /// Create a new mock signature from the provided proposal and signer key.
+    /// Fails if the signer fails to sign it.
+    pub fn new(
+        mock_proposal: MockProposal,
+        signer: &(impl MessageSigner + ?Sized),
+    ) -> Result<Self, String> {
         let mut sig = Self {
             signature: MessageSignature::empty(),
             mock_proposal,
             metadata: SignerMessageMetadata::default(),
         };
-        sig.sign(stacks_private_key)
-            .expect("Failed to sign MockSignature");
-        sig
+        sig.sign(signer)?;
+        Ok(sig)
     }
 
     /// Sign the mock signature and set the internal signature field
-    fn sign(&mut self, private_key: &StacksPrivateKey) -> Result<(), String> {
+    fn sign(&mut self, signer: &(impl MessageSigner + ?Sized)) -> Result<(), String> {
         let signature_hash = self.mock_proposal.signer_signature_hash();
-        self.signature = private_key.sign(signature_hash.as_bytes())?;
+        self.signature = signer.sign_message(signature_hash.as_bytes())?;
         Ok(())
     }
 
@@ -581,6 +600,7 @@ impl From<&RejectReason> for RejectReasonPrefix {
             RejectReason::InvalidParentBlock => RejectReasonPrefix::InvalidParentBlock,
             RejectReason::DuplicateBlockFound => RejectReasonPrefix::DuplicateBlockFound,
             RejectReason::InvalidTenureExtend => RejectReasonPrefix::InvalidTenureExtend,
+            RejectReason::PolicyViolation(_) => RejectReasonPrefix::PolicyViolation,
             RejectReason::Unknown(_) => RejectReasonPrefix::Unknown,
             RejectReason::NotRejected => RejectReasonPrefix::NotRejected,
         }
@@ -655,6 +675,8 @@ pub enum RejectReason {
     /// The block attempted a tenure extend but the burn view has not changed
     /// and not enough time has passed for a time-based tenure extend
     InvalidTenureExtend,
+    /// The block violates the signer operator's block policy
+    PolicyViolation(String),
     /// The block was approved, no rejection details needed
     NotRejected,
     /// Handle unknown codes gracefully
@@ -696,6 +718,8 @@ pub enum RejectReasonPrefix {
     /// The block attempted a tenure extend but the burn view has not changed
     /// and not enough time has passed for a time-based tenure extend
     InvalidTenureExtend = 13,
+    /// The block violates the signer operator's block policy
+    PolicyViolation = 14,
     /// Unknown reject code, for forward compatibility
     Unknown = 254,
     /// The block was approved, no rejection details needed
@@ -720,6 +744,7 @@ impl RejectReasonPrefix {
             Self::InvalidParentBlock => 11,
             Self::DuplicateBlockFound => 12,
             Self::InvalidTenureExtend => 13,
+            Self::PolicyViolation => 14,
             Self::Unknown => 254,
             Self::NotRejected => 255,
         }
@@ -743,6 +768,7 @@ impl From<u8> for RejectReasonPrefix {
             11 => Self::InvalidParentBlock,
             12 => Self::DuplicateBlockFound,
             13 => Self::InvalidTenureExtend,
+            14 => Self::PolicyViolation,
             255 => Self::NotRejected,
             // For forward compatibility, all other values are unknown
             _ => Self::Unknown,
@@ -842,21 +868,16 @@ impl BlockResponse {
         })
     }
 
-    /// Create a new rejected BlockResponse for the provided block signer signature hash and rejection code and sign it with the provided private key
+    /// Create a new rejected BlockResponse for the provided block signer signature hash and rejection code and sign it with the provided signer key.
+    /// Fails if the signer fails to sign it.
     pub fn rejected(
         hash: Sha512Trunc256Sum,
         reject_reason: RejectReason,
-        private_key: &StacksPrivateKey,
+        signer: &(impl MessageSigner + ?Sized),
         mainnet: bool,
         timestamp: u64,
-    ) -> Self {
-        Self::Rejected(BlockRejection::new(
-            hash,
-            reject_reason,
-            private_key,
-            mainnet,
-            timestamp,
-        ))
+    ) -> Result<Self, String> {
+        BlockRejection::new(hash, reject_reason, signer, mainnet, timestamp).map(Self::Rejected)
     }
 
     /// Get the tenure extend timestamp from the block response
@@ -1135,14 +1156,15 @@ pub struct BlockRejection {
 }
 
 impl BlockRejection {
-    /// Create a new BlockRejection for the provided block and reason code
+    /// Create a new BlockRejection for the provided block and reason code.
+    /// Fails if the signer fails to sign it.
     pub fn new(
         signer_signature_hash: Sha512Trunc256Sum,
         reject_reason: RejectReason,
-        private_key: &StacksPrivateKey,
+        signer: &(impl MessageSigner + ?Sized),
         mainnet: bool,
         timestamp: u64,
-    ) -> Self {
+    ) -> Result<Self, String> {
         let chain_id = if mainnet {
             CHAIN_ID_MAINNET
         } else {
@@ -1157,19 +1179,18 @@ impl BlockRejection {
             metadata: SignerMessageMetadata::default(),
             response_data: BlockResponseData::new(timestamp, reject_reason),
         };
-        rejection
-            .sign(private_key)
-            .expect("Failed to sign BlockRejection");
-        rejection
+        rejection.sign(signer)?;
+        Ok(rejection)
     }
 
-    /// Create a new BlockRejection from a BlockValidateRejection
+    /// Create a new BlockRejection from a BlockValidateRejection.
+    /// Fails if the signer fails to sign it.
     pub fn from_validate_rejection(
         reject: BlockValidateReject,
-        private_key: &StacksPrivateKey,
+        signer: &(impl MessageSigner + ?Sized),
         mainnet: bool,
         timestamp: u64,
-    ) -> Self {
+    ) -> Result<Self, String> {
         let chain_id = if mainnet {
             CHAIN_ID_MAINNET
         } else {
@@ -1185,10 +1206,8 @@ impl BlockRejection {
             metadata: SignerMessageMetadata::default(),
             response_data: BlockResponseData::new(timestamp, (&reject_code).into()),
         };
-        rejection
-            .sign(private_key)
-            .expect("Failed to sign BlockRejection");
-        rejection
+        rejection.sign(signer)?;
+        Ok(rejection)
     }
 
     /// The signature hash for the block rejection
@@ -1199,9 +1218,9 @@ impl BlockRejection {
     }
 
     /// Sign the block rejection and set the internal signature field
-    fn sign(&mut self, private_key: &StacksPrivateKey) -> Result<(), String> {
+    fn sign(&mut self, signer: &(impl MessageSigner + ?Sized)) -> Result<(), String> {
         let signature_hash = self.hash();
-        self.signature = private_key.sign(signature_hash.as_bytes())?;
+        self.signature = signer.sign_message(signature_hash.as_bytes())?;
         Ok(())
     }
 
@@ -1325,6 +1344,13 @@ impl StacksMessageCodec for RejectReason {
             | RejectReason::NotRejected => {
                 // No additional data to serialize / deserialize
             }
+            RejectReason::PolicyViolation(rule) => {
+                let mut len = rule.len().min(POLICY_VIOLATION_RULE_MAX_LEN as usize);
+                while !rule.is_char_boundary(len) {
+                    len -= 1;
+                }
+                write_next(fd, &rule.as_bytes()[..len].to_vec())?;
+            }
         };
         Ok(())
     }
@@ -1358,6 +1384,15 @@ impl StacksMessageCodec for RejectReason {
             RejectReasonPrefix::InvalidParentBlock => RejectReason::InvalidParentBlock,
             RejectReasonPrefix::DuplicateBlockFound => RejectReason::DuplicateBlockFound,
             RejectReasonPrefix::InvalidTenureExtend => RejectReason::InvalidTenureExtend,
+            RejectReasonPrefix::PolicyViolation => {
+                let rule_bytes: Vec<u8> = read_next_at_most(fd, POLICY_VIOLATION_RULE_MAX_LEN)?;
+                let rule = String::from_utf8(rule_bytes).map_err(|e| {
+                    CodecError::DeserializeError(format!(
+                        "Failed to decode policy violation rule: {e}"
+                    ))
+                })?;
+                RejectReason::PolicyViolation(rule)
+            }
             RejectReasonPrefix::Unknown => RejectReason::Unknown(type_prefix_byte),
             RejectReasonPrefix::NotRejected => RejectReason::NotRejected,
         };
@@ -1458,6 +1493,12 @@ impl std::fmt::Display for RejectReason {
                     "The block attempted a tenure extend but the burn view has not changed and not enough time has passed for a time-based tenure extend."
                 )
             }
+            RejectReason::PolicyViolation(rule) => {
+                write!(
+                    f,
+                    "The block was rejected by the signer's block policy: {rule}"
+                )
+            }
             RejectReason::Unknown(code) => {
                 write!(f, "Unknown reject code: {}", code)
             }
@@ -1531,7 +1572,8 @@ mod test {
             &StacksPrivateKey::random(),
             thread_rng().gen_bool(0.5),
             thread_rng().next_u64(),
-        );
+        )
+        .expect("Failed to sign BlockRejection");
         let serialized_rejection = rejection.serialize_to_vec();
         let deserialized_rejection = read_next::<BlockRejection, _>(&mut &serialized_rejection[..])
             .expect("Failed to deserialize BlockRejection");
@@ -1543,11 +1585,71 @@ mod test {
             &StacksPrivateKey::random(),
             thread_rng().gen_bool(0.5),
             thread_rng().next_u64(),
-        );
+        )
+        .expect("Failed to sign BlockRejection");
         let serialized_rejection = rejection.serialize_to_vec();
         let deserialized_rejection = read_next::<BlockRejection, _>(&mut &serialized_rejection[..])
             .expect("Failed to deserialize BlockRejection");
         assert_eq!(rejection, deserialized_rejection);
+
+        // The policy rule survives the round trip
+        let rejection = BlockRejection::new(
+            Sha512Trunc256Sum([2u8; 32]),
+            RejectReason::PolicyViolation("max_transactions".to_string()),
+            &StacksPrivateKey::random(),
+            thread_rng().gen_bool(0.5),
+            thread_rng().next_u64(),
+        )
+        .expect("Failed to sign BlockRejection");
+        assert!(rejection.reason.contains("max_transactions"));
+        let serialized_rejection = rejection.serialize_to_vec();
+        let deserialized_rejection = read_next::<BlockRejection, _>(&mut &serialized_rejection[..])
+            .expect("Failed to deserialize BlockRejection");
+        assert_eq!(deserialized_rejection.reason, rejection.reason);
+        assert_eq!(
+            deserialized_rejection.response_data.reject_reason,
+            RejectReason::PolicyViolation("max_transactions".to_string())
+        );
+        assert_eq!(rejection, deserialized_rejection);
+
+        // Overlong rules are truncated
+        let long_rule = "é".repeat(POLICY_VIOLATION_RULE_MAX_LEN as usize);
+        let serialized_reason = RejectReason::PolicyViolation(long_rule.clone()).serialize_to_vec();
+        let RejectReason::PolicyViolation(rule) =
+            read_next::<RejectReason, _>(&mut &serialized_reason[..])
+                .expect("Failed to deserialize RejectReason")
+        else {
+            panic!("Expected a policy violation");
+        };
+        assert_eq!(rule.len(), POLICY_VIOLATION_RULE_MAX_LEN as usize);
+        assert!(long_rule.starts_with(&rule));
+    }
+
+    struct FailingSigner;
+
+    impl MessageSigner for FailingSigner {
+        fn sign_message(&self, _message_hash: &[u8]) -> Result<MessageSignature, String> {
+            Err("signer unavailable".to_string())
+        }
+    }
+
+    #[test]
+    fn signing_failure_is_an_error() {
+        assert!(BlockRejection::new(
+            Sha512Trunc256Sum([0u8; 32]),
+            RejectReason::NoSortitionView,
+            &FailingSigner,
+            false,
+            0,
+        )
+        .is_err());
+        let reject = BlockValidateReject {
+            signer_signature_hash: Sha512Trunc256Sum([0u8; 32]),
+            reason: "bad block".to_string(),
+            reason_code: ValidateRejectCode::InvalidBlock,
+        };
+        assert!(BlockRejection::from_validate_rejection(reject, &FailingSigner, false, 0).is_err());
+        assert!(MockSignature::new(random_mock_proposal(), &FailingSigner).is_err());
     }
 
     #[test]
@@ -1567,13 +1669,14 @@ mod test {
             .expect("Failed to deserialize BlockResponse");
         assert_eq!(response, deserialized_response);
 
-        let response = BlockResponse::Rejected(BlockRejection::new(
+        let response = BlockResponse::rejected(
             Sha512Trunc256Sum([1u8; 32]),
             RejectReason::ValidationFailed(ValidateRejectCode::InvalidBlock),
             &StacksPrivateKey::random(),
             thread_rng().gen_bool(0.5),
             thread_rng().next_u64(),
-        ));
+        )
+        .expect("Failed to sign BlockRejection");
         let serialized_response = response.serialize_to_vec();
         let deserialized_response = read_next::<BlockResponse, _>(&mut &serialized_response[..])
             .expect("Failed to deserialize BlockResponse");
@@ -1723,9 +1826,11 @@ mod test {
     fn serde_mock_block() {
         let mock_proposal = random_mock_proposal();
         let mock_signature_1 =
-            MockSignature::new(mock_proposal.clone(), &StacksPrivateKey::random());
+            MockSignature::new(mock_proposal.clone(), &StacksPrivateKey::random())
+                .expect("Failed to sign MockSignature");
         let mock_signature_2 =
-            MockSignature::new(mock_proposal.clone(), &StacksPrivateKey::random());
+            MockSignature::new(mock_proposal.clone(), &StacksPrivateKey::random())
+                .expect("Failed to sign MockSignature");
         let mock_block = MockBlock {
             mock_proposal,
             mock_signatures: vec![mock_signature_1, mock_signature_2],
@@ -157,7 +157,7 @@ impl SlotMetadata {
     }
 
     /// Get the digest to sign that authenticates this chunk data and metadata
-    fn auth_digest(&self) -> Sha512Trunc256Sum {
+    pub fn auth_digest(&self) -> Sha512Trunc256Sum {
         let mut hasher = Sha512_256::new();
         hasher.update(self.slot_id.to_be_bytes());
         hasher.update(self.slot_version.to_be_bytes());
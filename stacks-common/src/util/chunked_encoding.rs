@@ -399,6 +399,15 @@ impl<'a, 'state, W: Write> HttpChunkedTransferWriter<'a, 'state, W> {
     pub fn corked(&self) -> bool {
         self.state.corked
     }
+
+    /// Send any buffered data as a chunk of its own, without ending the stream.
+    /// Long-lived streams use this so that their data does not sit in the buffer until it fills.
+    pub fn flush_buffered_chunk(&mut self) -> io::Result<()> {
+        if !self.state.corked && !self.state.chunk_buf.is_empty() {
+            self.flush_chunk()?;
+        }
+        Ok(())
+    }
 }
 
 impl<W: Write> Write for HttpChunkedTransferWriter<'_, '_, W> {
@@ -575,6 +584,30 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_http_chunked_encode_flush_buffered() {
+        let mut bytes = vec![];
+        {
+            let mut write_state = HttpChunkedTransferWriterState::new(10);
+            let mut encoder =
+                HttpChunkedTransferWriter::from_writer_state(&mut bytes, &mut write_state);
+            encoder.write_all(b"abc").unwrap();
+            encoder.flush_buffered_chunk().unwrap();
+
+            // nothing buffered, so nothing sent
+            encoder.flush_buffered_chunk().unwrap();
+
+            encoder.write_all(b"defghijklmn").unwrap();
+            encoder.flush_buffered_chunk().unwrap();
+            encoder.flush().unwrap();
+        }
+
+        assert_eq!(
+            bytes,
+            b"3\r\nabc\r\na\r\ndefghijklm\r\n1\r\nn\r\n0\r\n\r\n".to_vec()
+        );
+    }
+
     #[test]
     fn test_http_chunked_decode() {
         let tests = [
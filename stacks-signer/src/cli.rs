@@ -21,10 +21,10 @@ use blockstack_lib::util_lib::signed_structured_data::pox4::Pox4SignatureTopic;
 use blockstack_lib::util_lib::signed_structured_data::{
     make_structured_data_domain, structured_data_message_hash,
 };
-use clap::{ArgAction, Parser, ValueEnum};
+use clap::{ArgAction, ArgGroup, Parser, ValueEnum};
 use clarity::consts::CHAIN_ID_MAINNET;
 use clarity::types::chainstate::StacksPublicKey;
-use clarity::types::{PrivateKey, PublicKey};
+use clarity::types::PublicKey;
 use clarity::util::hash::Sha256Sum;
 use clarity::util::secp256k1::MessageSignature;
 use clarity::vm::types::{QualifiedContractIdentifier, TupleData};
@@ -37,7 +37,12 @@ use stacks_common::address::{
     C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
 };
 use stacks_common::define_u8_enum;
-use stacks_common::types::chainstate::StacksPrivateKey;
+use stacks_common::types::chainstate::{ConsensusHash, StacksPrivateKey};
+use stacks_common::util::get_epoch_time_secs;
+use stacks_common::util::hash::Sha512Trunc256Sum;
+
+use crate::decision_log::DecisionQuery;
+use crate::signing::{SigningBackend, SigningError};
 
 extern crate alloc;
 
@@ -73,6 +78,16 @@ pub enum Command {
     VerifyVote(VerifyVoteArgs),
     /// Verify signer signatures by checking stackerdb slots contain the correct data
     MonitorSigners(MonitorSignersArgs),
+    /// Encrypt a Stacks private key into a keystore file, for use as a signing backend
+    CreateKeystore(CreateKeystoreArgs),
+    /// Export the signer database to a signed archive, for moving the signer to another host
+    ExportDb(ExportDbArgs),
+    /// Verify the signature and contents of a signer database archive
+    VerifyDbArchive(VerifyDbArchiveArgs),
+    /// Import a signer database archive exported by the same signer in the current reward cycle
+    ImportDb(ImportDbArgs),
+    /// Query the signer's log of decisions on block proposals, printing matching entries as JSON
+    QueryDecisions(QueryDecisionsArgs),
 }
 
 /// Basic arguments for all cyrptographic and stacker-db functionality
@@ -191,9 +206,12 @@ impl VoteInfo {
     }
 
     /// Sign the vote data and return the signature
-    pub fn sign(&self, private_key: &StacksPrivateKey) -> Result<MessageSignature, &'static str> {
+    pub fn sign(
+        &self,
+        signing_backend: &dyn SigningBackend,
+    ) -> Result<MessageSignature, SigningError> {
         let digest = self.digest();
-        private_key.sign(digest.as_bytes())
+        signing_backend.sign(digest.as_bytes())
     }
 
     /// Verify the vote data against the provided public key and signature
@@ -234,6 +252,99 @@ impl TryFrom<u8> for Vote {
     }
 }
 
+#[derive(Parser, Debug, Clone)]
+/// Arguments for the CreateKeystore command
+pub struct CreateKeystoreArgs {
+    /// The Stacks private key to encrypt in hexademical format
+    #[arg(short, long, value_parser = parse_private_key)]
+    pub private_key: StacksPrivateKey,
+    /// Path of the keystore file to create
+    #[arg(long, short, value_name = "FILE")]
+    pub output: PathBuf,
+    /// Path to a file holding the passphrase. If not given, the passphrase is read from the
+    /// `STACKS_SIGNER_KEYSTORE_PASSPHRASE` environment variable.
+    #[arg(long, value_name = "FILE")]
+    pub passphrase_file: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+/// Arguments for the ExportDb command
+pub struct ExportDbArgs {
+    /// Path to signer config file
+    #[arg(long, short, value_name = "FILE")]
+    pub config: PathBuf,
+    /// Path of the archive file to create
+    #[arg(long, short, value_name = "FILE")]
+    pub output: PathBuf,
+}
+
+#[derive(Parser, Debug, Clone)]
+/// Arguments for the VerifyDbArchive command
+pub struct VerifyDbArchiveArgs {
+    /// Path of the archive file to verify
+    #[arg(long, short, value_name = "FILE")]
+    pub archive: PathBuf,
+    /// The Stacks public key which must have signed the archive
+    #[arg(long, short, value_parser = parse_public_key)]
+    pub public_key: Option<StacksPublicKey>,
+}
+
+#[derive(Parser, Debug, Clone)]
+/// Arguments for the ImportDb command
+pub struct ImportDbArgs {
+    /// Path to signer config file. The database is imported to its `db_path`, which must not exist.
+    #[arg(long, short, value_name = "FILE")]
+    pub config: PathBuf,
+    /// Path of the archive file to import
+    #[arg(long, short, value_name = "FILE")]
+    pub archive: PathBuf,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[command(group(ArgGroup::new("filter").required(true)))]
+/// Arguments for the QueryDecisions command
+pub struct QueryDecisionsArgs {
+    /// Path to the signer database
+    #[arg(long, value_name = "FILE")]
+    pub db_path: PathBuf,
+    /// Decisions on the block with this signer signature hash or block ID
+    #[arg(long, group = "filter", value_parser = parse_block_hash)]
+    pub block_hash: Option<Sha512Trunc256Sum>,
+    /// Decisions on blocks in the tenure with this consensus hash
+    #[arg(long, group = "filter", value_parser = parse_consensus_hash)]
+    pub tenure: Option<ConsensusHash>,
+    /// Decisions on blocks at this Stacks block height
+    #[arg(long, group = "filter")]
+    pub height: Option<u64>,
+    /// Decisions made at or after this time (epoch time in seconds)
+    #[arg(long, group = "filter")]
+    pub start: Option<u64>,
+    /// Decisions made at or before this time (epoch time in seconds). Defaults to now.
+    #[arg(long, requires = "start")]
+    pub end: Option<u64>,
+    /// The maximum number of decisions to print
+    #[arg(long, default_value = "100")]
+    pub limit: u64,
+}
+
+impl QueryDecisionsArgs {
+    /// Get the decision log query described by the arguments
+    pub fn query(&self) -> DecisionQuery {
+        if let Some(block_hash) = self.block_hash {
+            DecisionQuery::BlockHash(block_hash)
+        } else if let Some(tenure) = self.tenure {
+            DecisionQuery::Tenure(tenure)
+        } else if let Some(height) = self.height {
+            DecisionQuery::Height(height)
+        } else {
+            DecisionQuery::TimeRange {
+                start: self.start.unwrap_or(0),
+                end: self.end.unwrap_or_else(get_epoch_time_secs),
+            }
+        }
+    }
+}
+
 #[derive(Parser, Debug, Clone)]
 /// Arguments for the MonitorSigners command
 pub struct MonitorSignersArgs {
@@ -363,6 +474,16 @@ fn parse_public_key(public_key: &str) -> Result<StacksPublicKey, String> {
     StacksPublicKey::from_hex(public_key).map_err(|e| format!("Invalid public key: {}", e))
 }
 
+/// Parse a hexadecimal signer signature hash or block ID
+fn parse_block_hash(block_hash: &str) -> Result<Sha512Trunc256Sum, String> {
+    Sha512Trunc256Sum::from_hex(block_hash).map_err(|e| format!("Invalid block hash: {}", e))
+}
+
+/// Parse a hexadecimal consensus hash
+fn parse_consensus_hash(consensus_hash: &str) -> Result<ConsensusHash, String> {
+    ConsensusHash::from_hex(consensus_hash).map_err(|e| format!("Invalid consensus hash: {}", e))
+}
+
 /// Parse the vote
 fn parse_vote(vote: &str) -> Result<Vote, String> {
     vote.try_into()
@@ -31,6 +31,8 @@ pub use stacks_client::*;
 use stacks_common::codec::Error as CodecError;
 use stacks_common::debug;
 
+use crate::signing::SigningError;
+
 /// Backoff timer initial interval in milliseconds
 const BACKOFF_INITIAL_INTERVAL: u64 = 128;
 /// Backoff timer max interval in milliseconds
@@ -95,6 +97,9 @@ pub enum ClientError {
     /// An RPC libsigner error occurred
     #[error("A libsigner RPC error occurred: {0}")]
     RPCError(#[from] RPCError),
+    /// The signing backend failed to sign
+    #[error("Signing backend error: {0}")]
+    SigningError(#[from] SigningError),
 }
 
 /// Retry a function F with an exponential backoff and notification on transient failure
@@ -373,12 +378,11 @@ pub(crate) mod tests {
         let mut signer_addresses = Vec::new();
 
         for signer_id in 0..num_signers {
-            let private_key = if signer_id == 0 {
-                config.stacks_private_key
+            let public_key = if signer_id == 0 {
+                config.stacks_public_key
             } else {
-                StacksPrivateKey::random()
+                StacksPublicKey::from_private(&StacksPrivateKey::random())
             };
-            let public_key = StacksPublicKey::from_private(&private_key);
 
             signer_id_to_pk.insert(signer_id, public_key);
             signer_pk_to_id.insert(public_key, signer_id);
@@ -407,7 +411,7 @@ pub(crate) mod tests {
                 signer_addresses,
             },
             signer_slot_ids,
-            stacks_private_key: config.stacks_private_key,
+            signing_backend: config.signing_backend.clone(),
             node_host: config.node_host.to_string(),
             mainnet: config.network.is_mainnet(),
             db_path: config.db_path.clone(),
@@ -419,6 +423,8 @@ pub(crate) mod tests {
             tenure_idle_timeout_buffer: config.tenure_idle_timeout_buffer,
             block_proposal_max_age_secs: config.block_proposal_max_age_secs,
             reorg_attempts_activity_timeout: config.reorg_attempts_activity_timeout,
+            high_availability: config.high_availability.clone(),
+            block_policy: config.block_policy.clone(),
         }
     }
 
@@ -14,18 +14,24 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 //
+use std::sync::Arc;
+
 use blockstack_lib::net::api::poststackerdbchunk::StackerDBErrorCodes;
 use clarity::codec::read_next;
 use hashbrown::HashMap;
 use libsigner::{MessageSlotID, SignerMessage, SignerSession, StackerDBSession};
 use libstackerdb::{StackerDBChunkAckData, StackerDBChunkData};
 use slog::{slog_debug, slog_info, slog_warn};
+#[cfg(any(test, feature = "testing"))]
 use stacks_common::types::chainstate::StacksPrivateKey;
 use stacks_common::util::hash::to_hex;
 use stacks_common::{debug, info, warn};
 
 use crate::client::{retry_with_exponential_backoff, ClientError};
 use crate::config::{SignerConfig, SignerConfigMode};
+#[cfg(any(test, feature = "testing"))]
+use crate::signing::LocalSigningBackend;
+use crate::signing::SigningBackend;
 
 /// The signer StackerDB slot ID, purposefully wrapped to prevent conflation with SignerID
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, PartialOrd, Ord)]
@@ -49,8 +55,8 @@ pub struct StackerDB<M: MessageSlotID + std::cmp::Eq> {
     /// The stacker-db sessions for each signer set and message type.
     /// Maps message ID to the DB session.
     signers_message_stackerdb_sessions: HashMap<M, StackerDBSession>,
-    /// The private key used in all stacks node communications
-    stacks_private_key: StacksPrivateKey,
+    /// The backend which signs all stacks node communications
+    signing_backend: Arc<dyn SigningBackend>,
     /// A map of a message ID to last chunk version for each session
     slot_versions: HashMap<M, HashMap<SignerSlotID, u32>>,
     /// The running mode of the stackerdb (whether the signer is running in dry-run or
@@ -73,7 +79,7 @@ impl<M: MessageSlotID + 'static> From<&SignerConfig> for StackerDB<M> {
 
         Self::new(
             &config.node_host,
-            config.stacks_private_key,
+            config.signing_backend.clone(),
             config.mainnet,
             config.reward_cycle,
             mode,
@@ -93,7 +99,7 @@ impl<M: MessageSlotID + 'static> StackerDB<M> {
     ) -> Self {
         Self::new(
             host,
-            stacks_private_key,
+            Arc::new(LocalSigningBackend::new(stacks_private_key)),
             is_mainnet,
             reward_cycle,
             StackerDBMode::Normal { signer_slot_id },
@@ -103,7 +109,7 @@ impl<M: MessageSlotID + 'static> StackerDB<M> {
     /// Create a new StackerDB client
     fn new(
         host: &str,
-        stacks_private_key: StacksPrivateKey,
+        signing_backend: Arc<dyn SigningBackend>,
         is_mainnet: bool,
         reward_cycle: u64,
         signer_mode: StackerDBMode,
@@ -117,7 +123,7 @@ impl<M: MessageSlotID + 'static> StackerDB<M> {
 
         Self {
             signers_message_stackerdb_sessions,
-            stacks_private_key,
+            signing_backend,
             slot_versions: HashMap::new(),
             mode: signer_mode,
             reward_cycle,
@@ -177,7 +183,9 @@ impl<M: MessageSlotID + 'static> StackerDB<M> {
             };
 
             let mut chunk = StackerDBChunkData::new(slot_id.0, slot_version, message_bytes.clone());
-            chunk.sign(&self.stacks_private_key)?;
+            chunk.sig = self
+                .signing_backend
+                .sign(chunk.get_slot_metadata().auth_digest().as_bytes())?;
 
             let Some(session) = self.signers_message_stackerdb_sessions.get_mut(msg_id) else {
                 panic!("FATAL: would loop forever trying to send a message with ID {msg_id:?}, for which we don't have a session");
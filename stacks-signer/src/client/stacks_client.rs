@@ -15,13 +15,15 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 use std::collections::{HashMap, VecDeque};
 use std::fmt::Display;
+use std::ops::Range;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use blockstack_lib::chainstate::nakamoto::NakamotoBlock;
 use blockstack_lib::chainstate::stacks::boot::{NakamotoSignerEntry, SIGNERS_NAME};
 use blockstack_lib::chainstate::stacks::db::StacksBlockHeaderTypes;
 use blockstack_lib::chainstate::stacks::{
-    StacksTransaction, StacksTransactionSigner, TransactionAnchorMode, TransactionAuth,
+    StacksTransaction, TransactionAnchorMode, TransactionAuth, TransactionAuthFlags,
     TransactionContractCall, TransactionPayload, TransactionPostConditionMode,
     TransactionSpendingCondition, TransactionVersion,
 };
@@ -37,6 +39,7 @@ use blockstack_lib::net::api::postblock::StacksBlockAcceptedData;
 use blockstack_lib::net::api::postblock_proposal::NakamotoBlockProposal;
 use blockstack_lib::net::api::postblock_v3;
 use blockstack_lib::util_lib::boot::boot_code_id;
+use clarity::vm::costs::ExecutionCost;
 use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier};
 use clarity::vm::{ClarityName, ContractName, Value as ClarityValue};
 use libsigner::v0::messages::PeerInfo;
@@ -56,14 +59,15 @@ use super::SignerSlotID;
 use crate::client::{retry_with_exponential_backoff, ClientError};
 use crate::config::GlobalConfig;
 use crate::runloop::RewardCycleInfo;
+use crate::signing::{LocalSigningBackend, SigningBackend};
 
 /// The Stacks signer client used to communicate with the stacks node
 #[derive(Clone, Debug)]
 pub struct StacksClient {
     /// The stacks address of the signer
     stacks_address: StacksAddress,
-    /// The private key used in all stacks node communications
-    stacks_private_key: StacksPrivateKey,
+    /// The backend which signs all stacks node communications
+    signing_backend: Arc<dyn SigningBackend>,
     /// The stacks node HTTP base endpoint
     http_origin: String,
     /// The types of transactions
@@ -95,7 +99,7 @@ pub struct CurrentAndLastSortition {
 impl From<&GlobalConfig> for StacksClient {
     fn from(config: &GlobalConfig) -> Self {
         Self {
-            stacks_private_key: config.stacks_private_key,
+            signing_backend: config.signing_backend.clone(),
             stacks_address: config.stacks_address,
             http_origin: format!("http://{}", config.node_host),
             tx_version: config.network.to_transaction_version(),
@@ -124,7 +128,7 @@ impl StacksClient {
         };
         let stacks_address = StacksAddress::p2pkh(mainnet, &pubkey);
         Self {
-            stacks_private_key,
+            signing_backend: Arc::new(LocalSigningBackend::new(stacks_private_key)),
             stacks_address,
             http_origin: format!("http://{}", node_host),
             tx_version,
@@ -312,6 +316,23 @@ impl StacksClient {
         }
     }
 
+    /// Get the block limit of the epoch containing the given burn block height, along with the
+    /// burn block heights which the epoch spans
+    pub fn get_epoch_block_limit(
+        &self,
+        burn_block_height: u64,
+    ) -> Result<(Range<u64>, ExecutionCost), ClientError> {
+        debug!("StacksClient: Getting the block limit at burn block height {burn_block_height}");
+        self.get_pox_data()?
+            .epochs
+            .into_iter()
+            .map(|epoch| (epoch.start_height..epoch.end_height, epoch.block_limit))
+            .find(|(heights, _)| heights.contains(&burn_block_height))
+            .ok_or(ClientError::UnsupportedStacksFeature(format!(
+                "/v2/pox must report the epoch at burn block height {burn_block_height}"
+            )))
+    }
+
     /// Submit the block proposal to the stacks node. The block will be validated and returned via the HTTP endpoint for Block events.
     pub fn submit_block_for_validation(&self, block: NakamotoBlock) -> Result<(), ClientError> {
         debug!("StacksClient: Submitting block for validation";
@@ -795,21 +816,33 @@ impl StacksClient {
         Ok(unsigned_tx)
     }
 
-    /// Sign an unsigned transaction
+    /// Sign an unsigned standard single-signature transaction
     pub fn sign_transaction(
         &self,
-        unsigned_tx: StacksTransaction,
+        mut unsigned_tx: StacksTransaction,
     ) -> Result<StacksTransaction, ClientError> {
-        let mut tx_signer = StacksTransactionSigner::new(&unsigned_tx);
-        tx_signer
-            .sign_origin(&self.stacks_private_key)
-            .map_err(|e| ClientError::TransactionGenerationFailure(e.to_string()))?;
-
-        tx_signer
-            .get_tx()
-            .ok_or(ClientError::TransactionGenerationFailure(
-                "Failed to generate transaction from a transaction signer".to_string(),
-            ))
+        let TransactionAuth::Standard(TransactionSpendingCondition::Singlesig(ref origin)) =
+            unsigned_tx.auth
+        else {
+            return Err(ClientError::TransactionGenerationFailure(
+                "Only standard single-signature transactions can be signed".to_string(),
+            ));
+        };
+        let mut initial_sighash_tx = unsigned_tx.clone();
+        initial_sighash_tx.auth = initial_sighash_tx.auth.into_initial_sighash_auth();
+        let sighash = TransactionSpendingCondition::make_sighash_presign(
+            &initial_sighash_tx.txid(),
+            &TransactionAuthFlags::AuthStandard,
+            origin.tx_fee,
+            origin.nonce,
+        );
+        let signature = self.signing_backend.sign(sighash.as_bytes())?;
+        if let TransactionAuth::Standard(TransactionSpendingCondition::Singlesig(ref mut origin)) =
+            unsigned_tx.auth
+        {
+            origin.set_signature(signature);
+        }
+        Ok(unsigned_tx)
     }
 }
 
@@ -824,6 +857,7 @@ mod tests {
     use blockstack_lib::chainstate::stacks::boot::{
         NakamotoSignerEntry, PoxStartCycleInfo, RewardSet,
     };
+    use blockstack_lib::chainstate::stacks::StacksTransactionSigner;
     use clarity::types::chainstate::{StacksBlockId, TrieHash};
     use clarity::util::hash::Sha512Trunc256Sum;
     use clarity::util::secp256k1::MessageSignature;
@@ -834,7 +868,7 @@ mod tests {
     use rand::thread_rng;
     use rand_core::RngCore;
     use stacks_common::bitvec::BitVec;
-    use stacks_common::consts::SIGNER_SLOTS_PER_USER;
+    use stacks_common::consts::{CHAIN_ID_TESTNET, SIGNER_SLOTS_PER_USER};
 
     use super::*;
     use crate::client::tests::{
@@ -1265,4 +1299,34 @@ mod tests {
         );
         assert_eq!(mock.client.chain_id, 0x80000100);
     }
+
+    #[test]
+    fn sign_transaction_should_verify() {
+        let private_key = StacksPrivateKey::random();
+        let client = StacksClient::new(
+            private_key,
+            "127.0.0.1:20443".to_string(),
+            "password".to_string(),
+            false,
+            CHAIN_ID_TESTNET,
+        );
+        let unsigned_tx = StacksClient::build_unsigned_contract_call_transaction(
+            &client.stacks_address,
+            ContractName::from("contract-name"),
+            ClarityName::from("function-name"),
+            &[],
+            &private_key,
+            client.tx_version,
+            client.chain_id,
+            1,
+        )
+        .unwrap();
+        let signed_tx = client.sign_transaction(unsigned_tx.clone()).unwrap();
+        signed_tx.verify().unwrap();
+
+        // the backend signs exactly as the private key would
+        let mut tx_signer = StacksTransactionSigner::new(&unsigned_tx);
+        tx_signer.sign_origin(&private_key).unwrap();
+        assert_eq!(tx_signer.get_tx().unwrap(), signed_tx);
+    }
 }
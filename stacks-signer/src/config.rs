@@ -17,7 +17,8 @@
 use std::fmt::{Debug, Display};
 use std::fs;
 use std::net::{SocketAddr, ToSocketAddrs};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
 use blockstack_lib::chainstate::stacks::TransactionVersion;
@@ -33,6 +34,8 @@ use stacks_common::types::chainstate::{StacksAddress, StacksPrivateKey, StacksPu
 use stacks_common::util::hash::Hash160;
 
 use crate::client::SignerSlotID;
+use crate::policy::BlockPolicy;
+use crate::signing::{LocalSigningBackend, SigningBackend, SigningBackendConfig};
 
 const EVENT_TIMEOUT_MS: u64 = 5000;
 const BLOCK_PROPOSAL_TIMEOUT_MS: u64 = 120_000;
@@ -45,6 +48,7 @@ const DEFAULT_REORG_ATTEMPTS_ACTIVITY_TIMEOUT_MS: u64 = 200_000;
 /// Default number of seconds to add to the tenure extend time, after computing the idle timeout,
 /// to allow for clock skew between the signer and the miner
 const DEFAULT_TENURE_IDLE_TIMEOUT_BUFFER_SECS: u64 = 2;
+const DEFAULT_HA_LEASE_DURATION_MS: u64 = 30_000;
 
 #[derive(thiserror::Error, Debug)]
 /// An error occurred parsing the provided configuration
@@ -136,6 +140,16 @@ impl std::fmt::Display for SignerConfigMode {
     }
 }
 
+/// Settings for running the signer as one of an active/standby pair which share a database.
+/// Only the instance holding the lease in the database submits block responses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighAvailabilityConfig {
+    /// The name of this instance, which must differ from that of its peer
+    pub instance_id: String,
+    /// How long the active instance's lease lasts unless renewed
+    pub lease_duration: Duration,
+}
+
 /// The Configuration info needed for an individual signer per reward cycle
 #[derive(Debug, Clone)]
 pub struct SignerConfig {
@@ -145,8 +159,8 @@ pub struct SignerConfig {
     pub signer_entries: SignerEntries,
     /// The signer slot ids of all signers registered for this reward cycle
     pub signer_slot_ids: Vec<SignerSlotID>,
-    /// The private key for this signer
-    pub stacks_private_key: StacksPrivateKey,
+    /// The backend which signs with this signer's private key
+    pub signing_backend: Arc<dyn SigningBackend>,
     /// The node host for this signer
     pub node_host: String,
     /// Whether this signer is running on mainnet or not
@@ -175,6 +189,10 @@ pub struct SignerConfig {
     pub reorg_attempts_activity_timeout: Duration,
     /// The running mode for the signer (dry-run or normal)
     pub signer_mode: SignerConfigMode,
+    /// The high-availability settings, if this signer is one of an active/standby pair
+    pub high_availability: Option<HighAvailabilityConfig>,
+    /// The operator's block policy, if any
+    pub block_policy: Option<BlockPolicy>,
 }
 
 /// The parsed configuration for the signer
@@ -184,8 +202,10 @@ pub struct GlobalConfig {
     pub node_host: String,
     /// endpoint to the event receiver
     pub endpoint: SocketAddr,
-    /// The signer's Stacks private key
-    pub stacks_private_key: StacksPrivateKey,
+    /// The backend which signs with the signer's Stacks private key
+    pub signing_backend: Arc<dyn SigningBackend>,
+    /// The signer's Stacks public key
+    pub stacks_public_key: StacksPublicKey,
     /// The signer's Stacks address
     pub stacks_address: StacksAddress,
     /// The network to use. One of "mainnet" or "testnet".
@@ -223,6 +243,19 @@ pub struct GlobalConfig {
     pub reorg_attempts_activity_timeout: Duration,
     /// Is this signer binary going to be running in dry-run mode?
     pub dry_run: bool,
+    /// The high-availability settings, if this signer is one of an active/standby pair
+    pub high_availability: Option<HighAvailabilityConfig>,
+    /// The operator's block policy, if any
+    pub block_policy: Option<BlockPolicy>,
+}
+
+/// Internal struct for loading up the high-availability section of the config file
+#[derive(Deserialize, Debug)]
+struct RawHighAvailabilityConfig {
+    /// The name of this instance, which must differ from that of its peer
+    pub instance_id: String,
+    /// How long (in millisecs) the active instance's lease lasts unless renewed
+    pub lease_duration_ms: Option<u64>,
 }
 
 /// Internal struct for loading up the config file
@@ -234,7 +267,11 @@ struct RawConfigFile {
     pub endpoint: String,
     /// The hex representation of the signer's Stacks private key used for communicating
     /// with the Stacks Node, including writing to the Stacker DB instance.
-    pub stacks_private_key: String,
+    /// Exactly one of this and `signing_backend` must be set.
+    pub stacks_private_key: Option<String>,
+    /// The backend which holds the signer's Stacks private key, used instead of
+    /// `stacks_private_key`
+    pub signing_backend: Option<SigningBackendConfig>,
     /// The network to use. One of "mainnet" or "testnet".
     pub network: Network,
     /// The time to wait (in millisecs) for a response from the stacker-db instance
@@ -270,6 +307,10 @@ struct RawConfigFile {
     pub reorg_attempts_activity_timeout_ms: Option<u64>,
     /// Is this signer binary going to be running in dry-run mode?
     pub dry_run: Option<bool>,
+    /// Run as one of an active/standby pair of signers which share `db_path`
+    pub high_availability: Option<RawHighAvailabilityConfig>,
+    /// The path to a file with additional rules which block proposals must follow
+    pub block_policy_file: Option<String>,
 }
 
 impl RawConfigFile {
@@ -315,9 +356,26 @@ impl TryFrom<RawConfigFile> for GlobalConfig {
                 ConfigError::BadField("endpoint".to_string(), raw_data.endpoint.clone())
             })?;
 
-        let stacks_private_key = StacksPrivateKey::from_hex(&raw_data.stacks_private_key)
-            .map_err(|e| ConfigError::BadField("stacks_private_key".to_string(), e.into()))?;
-        let stacks_public_key = StacksPublicKey::from_private(&stacks_private_key);
+        let signing_backend: Arc<dyn SigningBackend> =
+            match (raw_data.stacks_private_key, raw_data.signing_backend) {
+                (Some(stacks_private_key), None) => {
+                    let stacks_private_key = StacksPrivateKey::from_hex(&stacks_private_key)
+                        .map_err(|e| {
+                            ConfigError::BadField("stacks_private_key".to_string(), e.into())
+                        })?;
+                    Arc::new(LocalSigningBackend::new(stacks_private_key))
+                }
+                (None, Some(signing_backend)) => signing_backend.load().map_err(|e| {
+                    ConfigError::BadField("signing_backend".to_string(), e.to_string())
+                })?,
+                _ => {
+                    return Err(ConfigError::InvalidConfig(
+                        "exactly one of stacks_private_key and signing_backend must be set"
+                            .to_string(),
+                    ))
+                }
+            };
+        let stacks_public_key = signing_backend.public_key();
         let signer_hash = Hash160::from_data(stacks_public_key.to_bytes_compressed().as_slice());
         let stacks_address =
             StacksAddress::p2pkh_from_hash(raw_data.network.is_mainnet(), signer_hash);
@@ -328,7 +386,7 @@ impl TryFrom<RawConfigFile> for GlobalConfig {
                 .first_proposal_burn_block_timing_secs
                 .unwrap_or(DEFAULT_FIRST_PROPOSAL_BURN_BLOCK_TIMING_SECS),
         );
-        let db_path = raw_data.db_path.into();
+        let db_path: PathBuf = raw_data.db_path.into();
 
         let metrics_endpoint = match raw_data.metrics_endpoint {
             Some(endpoint) => Some(
@@ -385,10 +443,54 @@ impl TryFrom<RawConfigFile> for GlobalConfig {
                 .unwrap_or(DEFAULT_TENURE_IDLE_TIMEOUT_BUFFER_SECS),
         );
 
+        let high_availability = match raw_data.high_availability {
+            Some(raw_ha) => {
+                if raw_ha.instance_id.is_empty()
+                    || raw_ha.instance_id == crate::signerdb::MIGRATED_LEASE_HOLDER
+                {
+                    return Err(ConfigError::BadField(
+                        "high_availability.instance_id".to_string(),
+                        raw_ha.instance_id,
+                    ));
+                }
+                if db_path.to_str() == Some(":memory:") {
+                    return Err(ConfigError::InvalidConfig(
+                        "high_availability requires a db_path shared by both instances".to_string(),
+                    ));
+                }
+                let lease_duration = Duration::from_millis(
+                    raw_ha
+                        .lease_duration_ms
+                        .unwrap_or(DEFAULT_HA_LEASE_DURATION_MS),
+                );
+                // The active instance renews its lease at least once per event timeout
+                if lease_duration <= event_timeout {
+                    return Err(ConfigError::BadField(
+                        "high_availability.lease_duration_ms".to_string(),
+                        format!(
+                            "{} (must exceed event_timeout_ms)",
+                            lease_duration.as_millis()
+                        ),
+                    ));
+                }
+                Some(HighAvailabilityConfig {
+                    instance_id: raw_ha.instance_id,
+                    lease_duration,
+                })
+            }
+            None => None,
+        };
+
+        let block_policy = raw_data
+            .block_policy_file
+            .map(|path| BlockPolicy::load_from_file(Path::new(&path)))
+            .transpose()?;
+
         Ok(Self {
             node_host: raw_data.node_host,
             endpoint,
-            stacks_private_key,
+            signing_backend,
+            stacks_public_key,
             stacks_address,
             network: raw_data.network,
             event_timeout,
@@ -405,6 +507,8 @@ impl TryFrom<RawConfigFile> for GlobalConfig {
             reorg_attempts_activity_timeout,
             dry_run,
             tenure_idle_timeout_buffer,
+            high_availability,
+            block_policy,
         })
     }
 }
@@ -450,9 +554,7 @@ Metrics endpoint: {metrics_endpoint}
             node_host = self.node_host,
             endpoint = self.endpoint,
             stacks_address = self.stacks_address,
-            public_key = to_hex(
-                &StacksPublicKey::from_private(&self.stacks_private_key).to_bytes_compressed()
-            ),
+            public_key = to_hex(&self.stacks_public_key.to_bytes_compressed()),
             network = self.network,
             db_path = self.db_path.to_str().unwrap_or_default(),
             metrics_endpoint = metrics_endpoint,
@@ -687,6 +789,87 @@ db_path = ":memory:"
         assert_eq!(config.to_chain_id(), CHAIN_ID_MAINNET);
     }
 
+    #[test]
+    fn test_signing_backend_or_private_key() {
+        let base_toml = r#"
+node_host = "localhost"
+endpoint = "localhost:30000"
+network = "mainnet"
+auth_password = "abcd"
+db_path = ":memory:"
+"#;
+        let signing_backend_toml = r#"
+[signing_backend]
+type = "remote"
+url = "http://127.0.0.1:30100"
+public_key = "03bc489f27da3701d9f9e577c88de5567cf4023111b7577042d55cde4d823a3505"
+"#;
+        let private_key_toml = r#"
+stacks_private_key = "2de4e77aab89c0c2570bb8bb90824f5cf2a5204a975905fee450ff9dad0fcf28"
+"#;
+
+        assert!(matches!(
+            GlobalConfig::load_from_str(base_toml),
+            Err(ConfigError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            GlobalConfig::load_from_str(&format!(
+                "{private_key_toml}{base_toml}{signing_backend_toml}"
+            )),
+            Err(ConfigError::InvalidConfig(_))
+        ));
+
+        let config =
+            GlobalConfig::load_from_str(&format!("{base_toml}{signing_backend_toml}")).unwrap();
+        assert_eq!(
+            config.stacks_public_key.to_hex(),
+            "03bc489f27da3701d9f9e577c88de5567cf4023111b7577042d55cde4d823a3505"
+        );
+    }
+
+    #[test]
+    fn test_high_availability() {
+        let base_toml = r#"
+stacks_private_key = "2de4e77aab89c0c2570bb8bb90824f5cf2a5204a975905fee450ff9dad0fcf28"
+node_host = "localhost"
+endpoint = "localhost:30000"
+network = "mainnet"
+auth_password = "abcd"
+db_path = "/var/lib/stacks-signer/signerdb.sqlite"
+"#;
+        let config = GlobalConfig::load_from_str(base_toml).unwrap();
+        assert!(config.high_availability.is_none());
+
+        let config = GlobalConfig::load_from_str(&format!(
+            "{base_toml}\n[high_availability]\ninstance_id = \"signer-a\"\n"
+        ))
+        .unwrap();
+        assert_eq!(
+            config.high_availability,
+            Some(HighAvailabilityConfig {
+                instance_id: "signer-a".to_string(),
+                lease_duration: Duration::from_millis(DEFAULT_HA_LEASE_DURATION_MS),
+            })
+        );
+
+        // The lease must outlive the event timeout
+        assert!(matches!(
+            GlobalConfig::load_from_str(&format!(
+                "event_timeout_ms = 5000\n{base_toml}\n[high_availability]\ninstance_id = \"signer-a\"\nlease_duration_ms = 5000\n"
+            )),
+            Err(ConfigError::BadField(..))
+        ));
+
+        // Both instances must share a database file
+        assert!(matches!(
+            GlobalConfig::load_from_str(&format!(
+                "{}\n[high_availability]\ninstance_id = \"signer-a\"\n",
+                base_toml.replace("/var/lib/stacks-signer/signerdb.sqlite", ":memory:")
+            )),
+            Err(ConfigError::InvalidConfig(_))
+        ));
+    }
+
     #[test]
     fn test_custom_chain_id() {
         let pk = StacksPrivateKey::from_hex(
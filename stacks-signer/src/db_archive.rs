@@ -0,0 +1,480 @@
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use blockstack_lib::util_lib::db::Error as DBError;
+use serde::{Deserialize, Serialize};
+use slog::slog_error;
+use stacks_common::error;
+use stacks_common::types::chainstate::StacksPublicKey;
+use stacks_common::util::hash::Sha512Trunc256Sum;
+use stacks_common::util::secp256k1::MessageSignature;
+use stacks_common::util::{get_epoch_time_ms, get_epoch_time_secs};
+
+use crate::signerdb::SignerDb;
+use crate::signing::{verify_signature, SigningBackend, SigningError};
+
+/// The current signer database archive format version
+pub const SIGNER_DB_ARCHIVE_VERSION: u32 = 1;
+const SIGNER_DB_ARCHIVE_MAGIC: &[u8; 8] = b"STXSGNDB";
+const SIGNATURE_LEN: usize = 65;
+/// Prefixed to the header before hashing it for signing, so that an archive signature can never
+/// be mistaken for a signature over a block or any other message the signer signs
+const SIGNER_DB_ARCHIVE_SIGNING_DOMAIN: &[u8] = b"STACKS-SIGNER-DB-ARCHIVE\x00";
+
+#[derive(thiserror::Error, Debug)]
+/// An error occurred exporting, verifying or importing a signer database archive
+pub enum ArchiveError {
+    /// Failed to read or write a file
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    /// A signer database error
+    #[error("Signer database error: {0}")]
+    Database(#[from] DBError),
+    /// Failed to sign the archive
+    #[error("Signing error: {0}")]
+    Signing(#[from] SigningError),
+    /// The archive is not well-formed
+    #[error("Malformed archive: {0}")]
+    Malformed(String),
+    /// The archive was written in a format this binary does not support
+    #[error("Unsupported archive version {0}")]
+    UnsupportedVersion(u32),
+    /// The archive signature or database digest does not check out
+    #[error("Archive failed verification: {0}")]
+    VerificationFailed(String),
+    /// The archive belongs to another signer
+    #[error("Archive is for public key {archive}, but the signer is configured with {expected}")]
+    PublicKeyMismatch {
+        /// The public key recorded in the archive
+        archive: String,
+        /// The public key of the importing signer
+        expected: String,
+    },
+    /// The archive was exported on another network
+    #[error(
+        "Archive is for mainnet = {archive}, but the signer is configured for mainnet = {expected}"
+    )]
+    NetworkMismatch {
+        /// Whether the archive was exported on mainnet
+        archive: bool,
+        /// Whether the importing signer runs on mainnet
+        expected: bool,
+    },
+    /// The archive was exported in another reward cycle
+    #[error(
+        "Archive was exported in reward cycle {archive}, but the current reward cycle is {current}"
+    )]
+    RewardCycleMismatch {
+        /// The reward cycle recorded in the archive
+        archive: u64,
+        /// The current reward cycle
+        current: u64,
+    },
+    /// Refusing to overwrite an existing database
+    #[error("A signer database already exists at {0}")]
+    DestinationExists(PathBuf),
+    /// Refusing to export the database of a running high-availability signer
+    #[error(
+        "Signer instance {holder} holds the lease until {expires_at} (epoch ms); stop it first"
+    )]
+    SignerActive {
+        /// The instance ID of the signer holding the lease
+        holder: String,
+        /// When the lease expires (epoch time in milliseconds)
+        expires_at: u64,
+    },
+}
+
+/// Metadata describing the database in a signer database archive
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignerDbArchiveHeader {
+    /// The archive format version
+    pub version: u32,
+    /// The schema version of the archived database
+    pub schema_version: u32,
+    /// Whether the signer ran on mainnet
+    pub mainnet: bool,
+    /// The hex-encoded compressed public key of the signer which exported the database
+    pub public_key: String,
+    /// The reward cycle in which the database was exported
+    pub reward_cycle: u64,
+    /// When the database was exported (epoch time in seconds)
+    pub created_at: u64,
+    /// The length of the archived database in bytes
+    pub db_length: u64,
+    /// The SHA512/256 digest of the archived database
+    pub db_digest: Sha512Trunc256Sum,
+}
+
+/// A verified signer database archive. On disk, an archive is laid out as:
+///
+/// | field     | size                                                  |
+/// |-----------|-------------------------------------------------------|
+/// | magic     | 8 bytes, `STXSGNDB`                                   |
+/// | length    | 4 bytes, big-endian length of the header              |
+/// | header    | JSON-encoded `SignerDbArchiveHeader`                  |
+/// | signature | 65 bytes, over the domain-separated header digest     |
+/// | database  | `db_length` bytes, a copy of the SQLite database file |
+///
+/// The header commits to the database through `db_digest`, so the signature covers both.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignerDbArchive {
+    /// The archive's metadata
+    pub header: SignerDbArchiveHeader,
+    /// The archived SQLite database file
+    pub db_bytes: Vec<u8>,
+}
+
+fn temp_path_next_to(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{}.{suffix}", rand::random::<u32>()));
+    path.with_file_name(file_name)
+}
+
+/// Export the signer database to a new archive at `output`, signed by `signer`. The database is
+/// marked as migrated first, so that no signer uses it again; this is refused while a
+/// high-availability signer instance holds the lease.
+pub fn export_signer_db(
+    signer_db: &mut SignerDb,
+    signer: &dyn SigningBackend,
+    mainnet: bool,
+    reward_cycle: u64,
+    output: &Path,
+) -> Result<SignerDbArchiveHeader, ArchiveError> {
+    // Never overwrite an existing archive
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(output)?;
+    let was_migrated = signer_db.is_migrated()?;
+    let now_ms = u64::try_from(get_epoch_time_ms()).unwrap_or(u64::MAX);
+    if !signer_db.mark_migrated(now_ms)? {
+        let _ = fs::remove_file(output);
+        let (holder, expires_at) = signer_db.get_lease()?.unwrap_or_default();
+        return Err(ArchiveError::SignerActive { holder, expires_at });
+    }
+    let result = write_archive(&mut file, signer_db, signer, mainnet, reward_cycle, output);
+    if result.is_err() {
+        let _ = fs::remove_file(output);
+        if !was_migrated {
+            if let Err(e) = signer_db.clear_lease() {
+                error!("Failed to unmark the signer db as migrated after a failed export: {e}");
+            }
+        }
+    }
+    result
+}
+
+/// The digest that an archive's signature is over: the SHA512/256 digest of the header, prefixed
+/// with a domain separator
+fn header_signing_digest(header_bytes: &[u8]) -> Sha512Trunc256Sum {
+    let mut data = Vec::with_capacity(SIGNER_DB_ARCHIVE_SIGNING_DOMAIN.len() + header_bytes.len());
+    data.extend_from_slice(SIGNER_DB_ARCHIVE_SIGNING_DOMAIN);
+    data.extend_from_slice(header_bytes);
+    Sha512Trunc256Sum::from_data(&data)
+}
+
+fn write_archive(
+    file: &mut fs::File,
+    signer_db: &SignerDb,
+    signer: &dyn SigningBackend,
+    mainnet: bool,
+    reward_cycle: u64,
+    output: &Path,
+) -> Result<SignerDbArchiveHeader, ArchiveError> {
+    let snapshot_path = temp_path_next_to(output, "sqlite");
+    signer_db.copy_to(&snapshot_path)?;
+    let db_bytes = fs::read(&snapshot_path);
+    fs::remove_file(&snapshot_path)?;
+    let db_bytes = db_bytes?;
+
+    let header = SignerDbArchiveHeader {
+        version: SIGNER_DB_ARCHIVE_VERSION,
+        schema_version: SignerDb::SCHEMA_VERSION,
+        mainnet,
+        public_key: signer.public_key().to_hex(),
+        reward_cycle,
+        created_at: get_epoch_time_secs(),
+        db_length: db_bytes.len() as u64,
+        db_digest: Sha512Trunc256Sum::from_data(&db_bytes),
+    };
+    let header_bytes = serde_json::to_vec(&header)
+        .map_err(|e| ArchiveError::Malformed(format!("failed to serialize header: {e}")))?;
+    let header_len = u32::try_from(header_bytes.len())
+        .map_err(|_| ArchiveError::Malformed("header too long".into()))?;
+    let signature = signer.sign(header_signing_digest(&header_bytes).as_bytes())?;
+
+    file.write_all(SIGNER_DB_ARCHIVE_MAGIC)?;
+    file.write_all(&header_len.to_be_bytes())?;
+    file.write_all(&header_bytes)?;
+    file.write_all(signature.as_bytes())?;
+    file.write_all(&db_bytes)?;
+    file.sync_all()?;
+    Ok(header)
+}
+
+impl SignerDbArchive {
+    /// Parse an archive, and check its signature and database digest
+    pub fn verify(data: &[u8]) -> Result<Self, ArchiveError> {
+        let malformed = |what: &str| ArchiveError::Malformed(what.to_string());
+        let data = data
+            .strip_prefix(SIGNER_DB_ARCHIVE_MAGIC.as_slice())
+            .ok_or_else(|| malformed("not a signer database archive"))?;
+        if data.len() < 4 {
+            return Err(malformed("truncated header length"));
+        }
+        let (header_len, data) = data.split_at(4);
+        let header_len =
+            u32::from_be_bytes([header_len[0], header_len[1], header_len[2], header_len[3]])
+                as usize;
+        if data.len() < header_len.saturating_add(SIGNATURE_LEN) {
+            return Err(malformed("truncated header"));
+        }
+        let (header_bytes, data) = data.split_at(header_len);
+        let (signature_bytes, db_bytes) = data.split_at(SIGNATURE_LEN);
+
+        let header: SignerDbArchiveHeader = serde_json::from_slice(header_bytes)
+            .map_err(|e| ArchiveError::Malformed(format!("bad header: {e}")))?;
+        if header.version != SIGNER_DB_ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(header.version));
+        }
+        let public_key = StacksPublicKey::from_hex(&header.public_key)
+            .map_err(|e| ArchiveError::Malformed(format!("bad public key: {e}")))?;
+        let signature = MessageSignature::from_bytes(signature_bytes)
+            .ok_or_else(|| malformed("bad signature"))?;
+        verify_signature(
+            &public_key,
+            header_signing_digest(header_bytes).as_bytes(),
+            &signature,
+        )
+        .map_err(|_| ArchiveError::VerificationFailed("bad signature".into()))?;
+
+        if db_bytes.len() as u64 != header.db_length
+            || Sha512Trunc256Sum::from_data(db_bytes) != header.db_digest
+        {
+            return Err(ArchiveError::VerificationFailed(
+                "database does not match its digest".into(),
+            ));
+        }
+        Ok(Self {
+            header,
+            db_bytes: db_bytes.to_vec(),
+        })
+    }
+
+    /// Read an archive from `path`, and check its signature and database digest
+    pub fn verify_file(path: &Path) -> Result<Self, ArchiveError> {
+        Self::verify(&fs::read(path)?)
+    }
+
+    /// Check that the archive belongs to the importing signer, and was exported in the current
+    /// reward cycle
+    pub fn check_importable(
+        &self,
+        public_key: &StacksPublicKey,
+        mainnet: bool,
+        current_reward_cycle: u64,
+    ) -> Result<(), ArchiveError> {
+        if self.header.public_key != public_key.to_hex() {
+            return Err(ArchiveError::PublicKeyMismatch {
+                archive: self.header.public_key.clone(),
+                expected: public_key.to_hex(),
+            });
+        }
+        if self.header.mainnet != mainnet {
+            return Err(ArchiveError::NetworkMismatch {
+                archive: self.header.mainnet,
+                expected: mainnet,
+            });
+        }
+        if self.header.reward_cycle != current_reward_cycle {
+            return Err(ArchiveError::RewardCycleMismatch {
+                archive: self.header.reward_cycle,
+                current: current_reward_cycle,
+            });
+        }
+        Ok(())
+    }
+
+    /// Write the archived database to `db_path`, migrating it to this binary's schema. Never
+    /// overwrites an existing database.
+    pub fn import_to(&self, db_path: &Path) -> Result<(), ArchiveError> {
+        if db_path.exists() {
+            return Err(ArchiveError::DestinationExists(db_path.to_path_buf()));
+        }
+        let import_path = temp_path_next_to(db_path, "import");
+        fs::write(&import_path, &self.db_bytes)?;
+        // Opening the database checks it, and migrates it if it has an older schema. The lease
+        // belonged to the exporting host, so drop it.
+        if let Err(e) = SignerDb::new(&import_path).and_then(|mut db| db.clear_lease()) {
+            let _ = fs::remove_file(&import_path);
+            return Err(e.into());
+        }
+        fs::rename(&import_path, db_path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stacks_common::types::chainstate::{ConsensusHash, StacksPrivateKey};
+
+    use super::*;
+    use crate::signing::LocalSigningBackend;
+
+    fn tmp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "stacks-signer-archive-test-{}",
+            rand::random::<u64>()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn export_verify_import() {
+        let dir = tmp_dir();
+        let db_path = dir.join("signerdb.sqlite");
+        let archive_path = dir.join("signerdb.archive");
+        let signer = LocalSigningBackend::new(StacksPrivateKey::random());
+        let public_key = signer.public_key();
+
+        let mut signer_db = SignerDb::new(&db_path).unwrap();
+        signer_db
+            .update_last_activity_time(&ConsensusHash([1; 20]), 7)
+            .unwrap();
+        let header = export_signer_db(&mut signer_db, &signer, false, 42, &archive_path).unwrap();
+        assert_eq!(header.reward_cycle, 42);
+        // The source database is fenced off
+        assert!(signer_db.is_migrated().unwrap());
+        // Never overwrite an existing archive
+        assert!(export_signer_db(&mut signer_db, &signer, false, 42, &archive_path).is_err());
+
+        let archive = SignerDbArchive::verify_file(&archive_path).unwrap();
+        assert_eq!(archive.header, header);
+        archive.check_importable(&public_key, false, 42).unwrap();
+        assert!(matches!(
+            archive.check_importable(&public_key, false, 43),
+            Err(ArchiveError::RewardCycleMismatch { .. })
+        ));
+        assert!(matches!(
+            archive.check_importable(&public_key, true, 42),
+            Err(ArchiveError::NetworkMismatch { .. })
+        ));
+        let other_key = StacksPublicKey::from_private(&StacksPrivateKey::random());
+        assert!(matches!(
+            archive.check_importable(&other_key, false, 42),
+            Err(ArchiveError::PublicKeyMismatch { .. })
+        ));
+
+        // Never overwrite an existing database
+        assert!(matches!(
+            archive.import_to(&db_path),
+            Err(ArchiveError::DestinationExists(_))
+        ));
+        let imported_path = dir.join("imported.sqlite");
+        archive.import_to(&imported_path).unwrap();
+        let imported_db = SignerDb::new(&imported_path).unwrap();
+        assert!(imported_db.get_lease().unwrap().is_none());
+        assert_eq!(
+            imported_db
+                .get_last_activity_time(&ConsensusHash([1; 20]))
+                .unwrap(),
+            Some(7)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn export_refused_while_signer_holds_lease() {
+        let dir = tmp_dir();
+        let archive_path = dir.join("signerdb.archive");
+        let signer = LocalSigningBackend::new(StacksPrivateKey::random());
+        let mut signer_db = SignerDb::new(dir.join("signerdb.sqlite")).unwrap();
+        let now_ms = u64::try_from(get_epoch_time_ms()).unwrap();
+        assert!(signer_db
+            .try_acquire_lease("signer-a", now_ms, std::time::Duration::from_secs(60))
+            .unwrap());
+
+        assert!(matches!(
+            export_signer_db(&mut signer_db, &signer, false, 42, &archive_path),
+            Err(ArchiveError::SignerActive { holder, .. }) if holder == "signer-a"
+        ));
+        assert!(!archive_path.exists());
+        assert!(!signer_db.is_migrated().unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tampered_archive_fails_verification() {
+        let dir = tmp_dir();
+        let archive_path = dir.join("signerdb.archive");
+        let signer = LocalSigningBackend::new(StacksPrivateKey::random());
+        let mut signer_db = SignerDb::new(dir.join("signerdb.sqlite")).unwrap();
+        export_signer_db(&mut signer_db, &signer, false, 42, &archive_path).unwrap();
+        let data = fs::read(&archive_path).unwrap();
+        SignerDbArchive::verify(&data).unwrap();
+
+        // Flip a bit of the database
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            SignerDbArchive::verify(&tampered),
+            Err(ArchiveError::VerificationFailed(_))
+        ));
+
+        // Change the reward cycle in the header
+        let header_start = SIGNER_DB_ARCHIVE_MAGIC.len() + 4;
+        let header_len =
+            u32::from_be_bytes(data[header_start - 4..header_start].try_into().unwrap()) as usize;
+        let header_json = String::from_utf8(data[header_start..header_start + header_len].to_vec())
+            .unwrap()
+            .replace("\"reward_cycle\":42", "\"reward_cycle\":43");
+        let mut tampered = data[..header_start].to_vec();
+        tampered.extend_from_slice(header_json.as_bytes());
+        tampered.extend_from_slice(&data[header_start + header_len..]);
+        assert!(matches!(
+            SignerDbArchive::verify(&tampered),
+            Err(ArchiveError::VerificationFailed(_))
+        ));
+
+        // A signature over the header's plain digest, as the signer might make for another
+        // purpose, is not an archive signature
+        let header_end = header_start + header_len;
+        let plain_signature = signer
+            .sign(Sha512Trunc256Sum::from_data(&data[header_start..header_end]).as_bytes())
+            .unwrap();
+        let mut tampered = data[..header_end].to_vec();
+        tampered.extend_from_slice(plain_signature.as_bytes());
+        tampered.extend_from_slice(&data[header_end + SIGNATURE_LEN..]);
+        assert!(matches!(
+            SignerDbArchive::verify(&tampered),
+            Err(ArchiveError::VerificationFailed(_))
+        ));
+
+        assert!(matches!(
+            SignerDbArchive::verify(&data[..20]),
+            Err(ArchiveError::Malformed(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
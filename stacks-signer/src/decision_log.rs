@@ -0,0 +1,212 @@
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use libsigner::v0::messages::{BlockResponse, RejectReason};
+use serde::{Deserialize, Serialize};
+use stacks_common::types::chainstate::{BurnchainHeaderHash, ConsensusHash, StacksBlockId};
+use stacks_common::util::get_epoch_time_secs;
+use stacks_common::util::hash::{Hash160, Sha512Trunc256Sum};
+
+use crate::chainstate::{SortitionState, SortitionsView};
+use crate::signerdb::BlockInfo;
+
+/// How many reward cycles of decisions to keep in the decision log, counting the current one
+pub const DECISION_LOG_RETENTION_CYCLES: u64 = 6;
+
+/// What the signer decided to do with a block proposal
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DecisionOutcome {
+    /// The proposal passed the signer's own checks, and was submitted to the node for validation
+    PendingValidation,
+    /// The signer signed the block
+    Accepted,
+    /// The signer rejected the block
+    Rejected,
+    /// The signer did not respond to the proposal, because it was stale or for another reward
+    /// cycle
+    Ignored,
+}
+
+/// A sortition, as the signer saw it when deciding on a block
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SortitionSnapshot {
+    /// The sortition's consensus hash
+    pub consensus_hash: ConsensusHash,
+    /// The burn block which performed the sortition
+    pub burn_block_hash: BurnchainHeaderHash,
+    /// The tenure which the sortition's miner committed to building on
+    pub parent_tenure_id: ConsensusHash,
+    /// The miner's public key hash
+    pub miner_pkh: Hash160,
+    /// The signer's view of the miner, e.g. `Valid`
+    pub miner_status: String,
+}
+
+impl From<&SortitionState> for SortitionSnapshot {
+    fn from(sortition: &SortitionState) -> Self {
+        Self {
+            consensus_hash: sortition.consensus_hash,
+            burn_block_hash: sortition.burn_block_hash,
+            parent_tenure_id: sortition.parent_tenure_id,
+            miner_pkh: sortition.miner_pkh,
+            miner_status: format!("{:?}", sortition.miner_status),
+        }
+    }
+}
+
+/// The signer's sortition view when deciding on a block
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SortitionViewSnapshot {
+    /// The current sortition
+    pub current: SortitionSnapshot,
+    /// The prior sortition, if known
+    pub last: Option<SortitionSnapshot>,
+}
+
+impl From<&SortitionsView> for SortitionViewSnapshot {
+    fn from(view: &SortitionsView) -> Self {
+        Self {
+            current: (&view.cur_sortition).into(),
+            last: view.last_sortition.as_ref().map(SortitionSnapshot::from),
+        }
+    }
+}
+
+/// An entry in the signer's decision log, recording one decision on a block proposal
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockDecision {
+    /// The signer signature hash of the block
+    pub signer_signature_hash: Sha512Trunc256Sum,
+    /// The block ID (index block hash) of the block
+    pub block_id: StacksBlockId,
+    /// The consensus hash of the block's tenure
+    pub consensus_hash: ConsensusHash,
+    /// The Stacks block height of the block
+    pub block_height: u64,
+    /// The burn block height at which the block was proposed
+    pub burn_height: u64,
+    /// The reward cycle the block belongs to
+    pub reward_cycle: u64,
+    /// The decision
+    pub outcome: DecisionOutcome,
+    /// Why the block was rejected, if it was
+    pub reject_reason: Option<RejectReason>,
+    /// The rejection message sent to miners, which includes the node's explanation when the
+    /// block failed validation
+    pub reject_message: Option<String>,
+    /// Time at which the proposal was received by this signer (epoch time in seconds)
+    pub proposed_at: u64,
+    /// Time at which the decision was made (epoch time in seconds)
+    pub decided_at: u64,
+    /// How long the node reported spending on validating the block, in milliseconds
+    pub validation_time_ms: Option<u64>,
+    /// How long the signer waited for the node's validation response, in milliseconds
+    pub validation_wait_ms: Option<u64>,
+    /// The signer's sortition view when making the decision, if it had one
+    pub sortition_view: Option<SortitionViewSnapshot>,
+    /// Whether the signer's response reached stacker-db, for outcomes with a response
+    #[serde(default)]
+    pub broadcast: bool,
+    /// Why the response was not broadcast, or why the proposal was ignored
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl BlockDecision {
+    /// Record a decision on `block_info`, made now
+    pub fn new(
+        block_info: &BlockInfo,
+        outcome: DecisionOutcome,
+        sortition_view: Option<&SortitionsView>,
+    ) -> Self {
+        Self {
+            signer_signature_hash: block_info.signer_signature_hash(),
+            block_id: block_info.block.block_id(),
+            consensus_hash: block_info.block.header.consensus_hash,
+            block_height: block_info.block.header.chain_length,
+            burn_height: block_info.burn_block_height,
+            reward_cycle: block_info.reward_cycle,
+            outcome,
+            reject_reason: None,
+            reject_message: None,
+            proposed_at: block_info.proposed_time,
+            decided_at: get_epoch_time_secs(),
+            validation_time_ms: None,
+            validation_wait_ms: None,
+            sortition_view: sortition_view.map(SortitionViewSnapshot::from),
+            broadcast: false,
+            error: None,
+        }
+    }
+
+    /// Record that the response was broadcast if `error` is `None`, and why it was not otherwise
+    pub fn with_broadcast(self, error: Option<String>) -> Self {
+        Self {
+            broadcast: error.is_none(),
+            error,
+            ..self
+        }
+    }
+
+    /// Record the decision to reject `block_info` for `reject_reason`, without a response
+    pub fn from_reject_reason(
+        block_info: &BlockInfo,
+        reject_reason: RejectReason,
+        sortition_view: Option<&SortitionsView>,
+    ) -> Self {
+        Self {
+            reject_message: Some(reject_reason.to_string()),
+            reject_reason: Some(reject_reason),
+            ..Self::new(block_info, DecisionOutcome::Rejected, sortition_view)
+        }
+    }
+
+    /// Record the decision to send `response` for `block_info`
+    pub fn from_response(
+        block_info: &BlockInfo,
+        response: &BlockResponse,
+        sortition_view: Option<&SortitionsView>,
+    ) -> Self {
+        match response {
+            BlockResponse::Accepted(_) => {
+                Self::new(block_info, DecisionOutcome::Accepted, sortition_view)
+            }
+            BlockResponse::Rejected(rejection) => Self {
+                reject_reason: Some(rejection.response_data.reject_reason.clone()),
+                reject_message: Some(rejection.reason.clone()),
+                ..Self::new(block_info, DecisionOutcome::Rejected, sortition_view)
+            },
+        }
+    }
+}
+
+/// Which entries to fetch from the decision log
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecisionQuery {
+    /// Decisions on the block with this signer signature hash or block ID
+    BlockHash(Sha512Trunc256Sum),
+    /// Decisions on blocks in the tenure with this consensus hash
+    Tenure(ConsensusHash),
+    /// Decisions on blocks at this Stacks block height
+    Height(u64),
+    /// Decisions made in this time range (epoch time in seconds, inclusive)
+    TimeRange {
+        /// The start of the range
+        start: u64,
+        /// The end of the range
+        end: u64,
+    },
+}
@@ -29,14 +29,22 @@ pub mod cli;
 pub mod client;
 /// The configuration module for the signer
 pub mod config;
+/// Portable, signed archives of the signer database, for moving a signer between hosts
+pub mod db_archive;
+/// The log of the signer's decisions on block proposals
+pub mod decision_log;
 /// The signer monitor for observing signer behaviours in the network
 pub mod monitor_signers;
 /// The monitoring server for the signer
 pub mod monitoring;
+/// Operator-defined block acceptance policies
+pub mod policy;
 /// The primary runloop for the signer
 pub mod runloop;
 /// The signer state module
 pub mod signerdb;
+/// Pluggable backends which hold the signer's private key and sign on its behalf
+pub mod signing;
 /// The util module for the signer
 pub mod utils;
 /// The v0 implementation of the signer.
@@ -58,6 +66,7 @@ use stacks_common::{info, warn};
 use crate::client::StacksClient;
 use crate::config::SignerConfig;
 use crate::runloop::RunLoop;
+use crate::signerdb::SignerDb;
 
 /// A trait which provides a common `Signer` interface for `v0` and `v1`
 pub trait Signer<T: SignerEventTrait>: Debug + Display {
@@ -123,6 +132,15 @@ impl<S: Signer<T> + Send + 'static, T: SignerEventTrait + 'static> SpawnedSigner
             For more information, check the documentation at \
             https://docs.stacks.co/guides-and-tutorials/running-a-signer#preflight-setup"
         );
+        let signer_db = SignerDb::new(&config.db_path).expect("Failed to connect to signer Db");
+        assert!(
+            !signer_db
+                .is_migrated()
+                .expect("Failed to read the signer Db"),
+            "The signer db at {} was exported to another host. Refusing to start.",
+            config.db_path.display()
+        );
+        drop(signer_db);
         let (res_send, res_recv) = channel();
         let ev = SignerEventReceiver::new(config.network.is_mainnet());
         crate::monitoring::actions::start_serving_monitoring_metrics(config.clone()).ok();
@@ -26,11 +26,12 @@ extern crate serde;
 extern crate serde_json;
 extern crate toml;
 
+use std::fmt::Display;
 use std::io::{self, Write};
+use std::process;
 
-use blockstack_lib::util_lib::signed_structured_data::pox4::make_pox_4_signer_key_signature;
+use blockstack_lib::util_lib::signed_structured_data::pox4::make_pox_4_signer_key_message_hash;
 use clap::Parser;
-use clarity::types::chainstate::StacksPublicKey;
 use clarity::util::sleep_ms;
 use libsigner::{SignerSession, VERSION_STRING};
 use libstackerdb::StackerDBChunkData;
@@ -39,12 +40,18 @@ use stacks_common::util::hash::to_hex;
 use stacks_common::util::secp256k1::MessageSignature;
 use stacks_common::{debug, error};
 use stacks_signer::cli::{
-    Cli, Command, GenerateStackingSignatureArgs, GenerateVoteArgs, GetChunkArgs,
-    GetLatestChunkArgs, MonitorSignersArgs, PutChunkArgs, RunSignerArgs, StackerDBArgs,
+    Cli, Command, CreateKeystoreArgs, ExportDbArgs, GenerateStackingSignatureArgs,
+    GenerateVoteArgs, GetChunkArgs, GetLatestChunkArgs, ImportDbArgs, MonitorSignersArgs,
+    PutChunkArgs, QueryDecisionsArgs, RunSignerArgs, StackerDBArgs, VerifyDbArchiveArgs,
     VerifyVoteArgs,
 };
+use stacks_signer::client::StacksClient;
 use stacks_signer::config::GlobalConfig;
+use stacks_signer::db_archive::{export_signer_db, SignerDbArchive};
 use stacks_signer::monitor_signers::SignerMonitor;
+use stacks_signer::signerdb::SignerDb;
+use stacks_signer::signing::keystore::DEFAULT_KEYSTORE_KDF_ITERATIONS;
+use stacks_signer::signing::{read_keystore_passphrase, KeystoreFile};
 use stacks_signer::utils::stackerdb_session;
 use stacks_signer::v0::SpawnedSigner;
 use tracing_subscriber::prelude::*;
@@ -112,21 +119,21 @@ fn handle_generate_stacking_signature(
 ) -> MessageSignature {
     let config = GlobalConfig::try_from(&args.config).unwrap();
 
-    let private_key = config.stacks_private_key;
-    let public_key = StacksPublicKey::from_private(&private_key);
-    let pk_hex = to_hex(&public_key.to_bytes_compressed());
+    let pk_hex = to_hex(&config.stacks_public_key.to_bytes_compressed());
 
-    let signature = make_pox_4_signer_key_signature(
+    let message_hash = make_pox_4_signer_key_message_hash(
         &args.pox_address,
-        &private_key, //
         args.reward_cycle.into(),
         args.method.topic(),
         config.to_chain_id(),
         args.period.into(),
         args.max_amount,
         args.auth_id,
-    )
-    .expect("Failed to generate signature");
+    );
+    let signature = config
+        .signing_backend
+        .sign(message_hash.as_bytes())
+        .expect("Failed to generate signature");
 
     let output_str = if args.json {
         serde_json::to_string(&serde_json::json!({
@@ -162,7 +169,10 @@ fn handle_check_config(args: RunSignerArgs) {
 
 fn handle_generate_vote(args: GenerateVoteArgs, do_print: bool) -> MessageSignature {
     let config = GlobalConfig::try_from(&args.config).unwrap();
-    let message_signature = args.vote_info.sign(&config.stacks_private_key).unwrap();
+    let message_signature = args
+        .vote_info
+        .sign(config.signing_backend.as_ref())
+        .unwrap();
     if do_print {
         println!("{}", to_hex(message_signature.as_bytes()));
     }
@@ -184,6 +194,109 @@ fn handle_verify_vote(args: VerifyVoteArgs, do_print: bool) -> bool {
     valid_vote
 }
 
+fn handle_create_keystore(args: CreateKeystoreArgs) {
+    let passphrase = read_keystore_passphrase(args.passphrase_file.as_deref()).unwrap();
+    let keystore = KeystoreFile::encrypt(
+        &args.private_key,
+        &passphrase,
+        DEFAULT_KEYSTORE_KDF_ITERATIONS,
+    )
+    .unwrap();
+    keystore.save(&args.output).unwrap();
+    println!(
+        "Keystore for public key {} written to {}",
+        keystore.public_key,
+        args.output.display()
+    );
+}
+
+/// Unwrap the result of a signer database archive step, or print its error and exit
+fn or_exit<T, E: Display>(result: Result<T, E>, what: &str) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("Failed to {what}: {e}");
+        process::exit(1);
+    })
+}
+
+fn handle_export_db(args: ExportDbArgs) {
+    let config = or_exit(GlobalConfig::try_from(&args.config), "load the config");
+    let mut signer_db = or_exit(SignerDb::new(&config.db_path), "open the signer database");
+    let reward_cycle = or_exit(
+        StacksClient::from(&config).get_current_reward_cycle_info(),
+        "fetch the current reward cycle",
+    )
+    .reward_cycle;
+    let header = or_exit(
+        export_signer_db(
+            &mut signer_db,
+            config.signing_backend.as_ref(),
+            config.network.is_mainnet(),
+            reward_cycle,
+            &args.output,
+        ),
+        "export the signer database",
+    );
+    println!("{}", serde_json::to_string_pretty(&header).unwrap());
+}
+
+fn handle_verify_db_archive(args: VerifyDbArchiveArgs) {
+    let archive = or_exit(
+        SignerDbArchive::verify_file(&args.archive),
+        "verify the archive",
+    );
+    if let Some(public_key) = args.public_key {
+        if archive.header.public_key != public_key.to_hex() {
+            eprintln!(
+                "Archive was signed by {}, not by the expected public key {}",
+                archive.header.public_key,
+                public_key.to_hex()
+            );
+            process::exit(1);
+        }
+    }
+    println!("{}", serde_json::to_string_pretty(&archive.header).unwrap());
+}
+
+fn handle_import_db(args: ImportDbArgs) {
+    let config = or_exit(GlobalConfig::try_from(&args.config), "load the config");
+    let archive = or_exit(
+        SignerDbArchive::verify_file(&args.archive),
+        "verify the archive",
+    );
+    let reward_cycle = or_exit(
+        StacksClient::from(&config).get_current_reward_cycle_info(),
+        "fetch the current reward cycle",
+    )
+    .reward_cycle;
+    or_exit(
+        archive.check_importable(
+            &config.stacks_public_key,
+            config.network.is_mainnet(),
+            reward_cycle,
+        ),
+        "import the archive",
+    );
+    or_exit(archive.import_to(&config.db_path), "import the archive");
+    println!(
+        "Signer database from reward cycle {} imported to {}",
+        archive.header.reward_cycle,
+        config.db_path.display()
+    );
+}
+
+fn handle_query_decisions(args: QueryDecisionsArgs) {
+    assert!(
+        args.db_path.exists(),
+        "No signer database at {}",
+        args.db_path.display()
+    );
+    let signer_db = SignerDb::new(&args.db_path).unwrap();
+    let decisions = signer_db
+        .get_block_decisions(&args.query(), args.limit)
+        .unwrap();
+    println!("{}", serde_json::to_string_pretty(&decisions).unwrap());
+}
+
 fn handle_monitor_signers(args: MonitorSignersArgs) {
     // Verify that the host is a valid URL
     let mut signer_monitor = SignerMonitor::new(args);
@@ -237,6 +350,21 @@ fn main() {
         Command::MonitorSigners(args) => {
             handle_monitor_signers(args);
         }
+        Command::CreateKeystore(args) => {
+            handle_create_keystore(args);
+        }
+        Command::ExportDb(args) => {
+            handle_export_db(args);
+        }
+        Command::VerifyDbArchive(args) => {
+            handle_verify_db_archive(args);
+        }
+        Command::ImportDb(args) => {
+            handle_import_db(args);
+        }
+        Command::QueryDecisions(args) => {
+            handle_query_decisions(args);
+        }
     }
 }
 
@@ -247,6 +375,7 @@ pub mod tests {
     use blockstack_lib::util_lib::signed_structured_data::pox4::{
         make_pox_4_signer_key_message_hash, Pox4SignatureTopic,
     };
+    use clarity::types::chainstate::StacksPublicKey;
     use clarity::util::secp256k1::Secp256k1PrivateKey;
     use clarity::vm::{execute_v2, Value};
     use rand::{Rng, RngCore};
@@ -254,6 +383,7 @@ pub mod tests {
     use stacks_common::types::PublicKey;
     use stacks_common::util::secp256k1::Secp256k1PublicKey;
     use stacks_signer::cli::{parse_pox_addr, VerifyVoteArgs, Vote, VoteInfo};
+    use stacks_signer::signing::LocalSigningBackend;
 
     use super::{handle_generate_stacking_signature, *};
     use crate::{GenerateStackingSignatureArgs, GlobalConfig};
@@ -311,7 +441,7 @@ pub mod tests {
         };
 
         let signature = handle_generate_stacking_signature(args.clone(), false);
-        let public_key = Secp256k1PublicKey::from_private(&config.stacks_private_key);
+        let public_key = config.stacks_public_key;
 
         let valid = call_verify_signer_sig(
             &args.pox_address,
@@ -334,7 +464,7 @@ pub mod tests {
         args.max_amount = 100;
 
         let signature = handle_generate_stacking_signature(args.clone(), false);
-        let public_key = Secp256k1PublicKey::from_private(&config.stacks_private_key);
+        let public_key = config.stacks_public_key;
 
         let valid = call_verify_signer_sig(
             &args.pox_address,
@@ -367,7 +497,7 @@ pub mod tests {
 
         let signature = handle_generate_stacking_signature(args.clone(), false);
 
-        let public_key = Secp256k1PublicKey::from_private(&config.stacks_private_key);
+        let public_key = config.stacks_public_key;
 
         let message_hash = make_pox_4_signer_key_message_hash(
             &args.pox_address,
@@ -393,8 +523,7 @@ pub mod tests {
         };
         let config_file = "./src/tests/conf/signer-0.toml";
         let config = GlobalConfig::load_from_file(config_file).unwrap();
-        let private_key = config.stacks_private_key;
-        let public_key = StacksPublicKey::from_private(&private_key);
+        let public_key = config.stacks_public_key;
         let args = GenerateVoteArgs {
             config: config_file.into(),
             vote_info,
@@ -423,7 +552,9 @@ pub mod tests {
 
         let args = VerifyVoteArgs {
             public_key,
-            signature: vote_info.sign(&private_key).unwrap(),
+            signature: vote_info
+                .sign(&LocalSigningBackend::new(private_key))
+                .unwrap(),
             vote_info,
         };
         let valid = handle_verify_vote(args, false);
@@ -431,7 +562,9 @@ pub mod tests {
 
         let args = VerifyVoteArgs {
             public_key: invalid_public_key,
-            signature: vote_info.sign(&private_key).unwrap(), // Invalid corresponding public key
+            signature: vote_info
+                .sign(&LocalSigningBackend::new(private_key))
+                .unwrap(), // Invalid corresponding public key
             vote_info,
         };
         let valid = handle_verify_vote(args, false);
@@ -439,7 +572,9 @@ pub mod tests {
 
         let args = VerifyVoteArgs {
             public_key,
-            signature: vote_info.sign(&private_key).unwrap(),
+            signature: vote_info
+                .sign(&LocalSigningBackend::new(private_key))
+                .unwrap(),
             vote_info: VoteInfo {
                 vote: Vote::Yes, // Invalid vote
                 sip,
@@ -450,7 +585,9 @@ pub mod tests {
 
         let args = VerifyVoteArgs {
             public_key,
-            signature: vote_info.sign(&private_key).unwrap(),
+            signature: vote_info
+                .sign(&LocalSigningBackend::new(private_key))
+                .unwrap(),
             vote_info: VoteInfo {
                 vote: Vote::No,
                 sip: sip.wrapping_add(1), // Invalid sip number
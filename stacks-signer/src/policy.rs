@@ -0,0 +1,491 @@
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use blockstack_lib::chainstate::nakamoto::NakamotoBlock;
+use blockstack_lib::chainstate::stacks::TransactionPayload;
+use clarity::vm::costs::ExecutionCost;
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier};
+use serde::Deserialize;
+use stacks_common::types::chainstate::StacksAddress;
+use stacks_common::types::Address;
+
+use crate::config::ConfigError;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Internal struct for loading up the policy file
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RawBlockPolicy {
+    /// Only log the blocks which the policy would reject
+    dry_run: Option<bool>,
+    /// Maximum percentage of the tenure's budget (the epoch's block limit) which may be consumed
+    /// once a block is added, in any dimension
+    max_tenure_cost_percentage: Option<u64>,
+    /// Maximum number of transactions in a block
+    max_transactions: Option<usize>,
+    /// Addresses which may not send transactions or receive STX transfers
+    blocked_principals: Option<Vec<String>>,
+    /// Contracts which may not be called, deployed or receive STX transfers
+    blocked_contracts: Option<Vec<String>>,
+    /// The time of day in which block proposals are accepted
+    arrival_window: Option<RawArrivalWindow>,
+}
+
+/// Internal struct for loading up the arrival window of the policy file
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RawArrivalWindow {
+    /// Start of the window, as "HH:MM" in UTC
+    start: String,
+    /// End of the window, as "HH:MM" in UTC
+    end: String,
+}
+
+/// A daily window of time, in UTC, in which block proposals may arrive. A window whose end is
+/// before its start wraps around midnight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArrivalWindow {
+    /// Seconds after midnight at which the window opens
+    pub start_secs: u64,
+    /// Seconds after midnight at which the window closes
+    pub end_secs: u64,
+}
+
+impl ArrivalWindow {
+    /// Parse a "HH:MM" time of day into seconds after midnight
+    fn parse_time_of_day(field: &str, value: &str) -> Result<u64, ConfigError> {
+        let bad_field = || ConfigError::BadField(field.to_string(), value.to_string());
+        let (hours, minutes) = value.split_once(':').ok_or_else(bad_field)?;
+        let hours: u64 = hours.parse().map_err(|_| bad_field())?;
+        let minutes: u64 = minutes.parse().map_err(|_| bad_field())?;
+        if hours >= 24 || minutes >= 60 {
+            return Err(bad_field());
+        }
+        Ok(hours * 3600 + minutes * 60)
+    }
+
+    /// Whether the given time (epoch time in seconds) is within the window
+    pub fn contains(&self, time_secs: u64) -> bool {
+        let time_of_day = time_secs % SECONDS_PER_DAY;
+        if self.start_secs <= self.end_secs {
+            self.start_secs <= time_of_day && time_of_day < self.end_secs
+        } else {
+            time_of_day >= self.start_secs || time_of_day < self.end_secs
+        }
+    }
+}
+
+/// Operator-defined rules which block proposals must follow, on top of the signer's
+/// own validity checks. Loaded from the TOML file named by `block_policy_file`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockPolicy {
+    /// Only log the blocks which the policy would reject, without rejecting them
+    pub dry_run: bool,
+    /// Maximum percentage of the tenure's budget (the epoch's block limit) which may be consumed
+    /// once a block is added, in any dimension
+    pub max_tenure_cost_percentage: Option<u64>,
+    /// Maximum number of transactions in a block
+    pub max_transactions: Option<usize>,
+    /// Addresses which may not send (or sponsor) transactions or receive STX transfers
+    pub blocked_principals: HashSet<StacksAddress>,
+    /// Contracts which may not be called, deployed or receive STX transfers
+    pub blocked_contracts: HashSet<QualifiedContractIdentifier>,
+    /// The time of day in which block proposals are accepted
+    pub arrival_window: Option<ArrivalWindow>,
+}
+
+impl TryFrom<RawBlockPolicy> for BlockPolicy {
+    type Error = ConfigError;
+
+    fn try_from(raw: RawBlockPolicy) -> Result<Self, Self::Error> {
+        if let Some(max_tenure_cost_percentage) = raw.max_tenure_cost_percentage {
+            if max_tenure_cost_percentage == 0 || max_tenure_cost_percentage > 100 {
+                return Err(ConfigError::BadField(
+                    "max_tenure_cost_percentage".to_string(),
+                    max_tenure_cost_percentage.to_string(),
+                ));
+            }
+        }
+        let blocked_principals = raw
+            .blocked_principals
+            .unwrap_or_default()
+            .into_iter()
+            .map(|principal| {
+                StacksAddress::from_string(&principal).ok_or(ConfigError::BadField(
+                    "blocked_principals".to_string(),
+                    principal,
+                ))
+            })
+            .collect::<Result<_, _>>()?;
+        let blocked_contracts = raw
+            .blocked_contracts
+            .unwrap_or_default()
+            .into_iter()
+            .map(|contract| {
+                QualifiedContractIdentifier::parse(&contract)
+                    .map_err(|_| ConfigError::BadField("blocked_contracts".to_string(), contract))
+            })
+            .collect::<Result<_, _>>()?;
+        let arrival_window = match raw.arrival_window {
+            Some(window) => Some(ArrivalWindow {
+                start_secs: ArrivalWindow::parse_time_of_day(
+                    "arrival_window.start",
+                    &window.start,
+                )?,
+                end_secs: ArrivalWindow::parse_time_of_day("arrival_window.end", &window.end)?,
+            }),
+            None => None,
+        };
+        Ok(Self {
+            dry_run: raw.dry_run.unwrap_or(false),
+            max_tenure_cost_percentage: raw.max_tenure_cost_percentage,
+            max_transactions: raw.max_transactions,
+            blocked_principals,
+            blocked_contracts,
+            arrival_window,
+        })
+    }
+}
+
+impl BlockPolicy {
+    /// Load the policy from a string
+    pub fn load_from_str(data: &str) -> Result<Self, ConfigError> {
+        let raw: RawBlockPolicy =
+            toml::from_str(data).map_err(|e| ConfigError::ParseError(format!("{e:?}")))?;
+        Self::try_from(raw)
+    }
+
+    /// Load the policy from a file
+    pub fn load_from_file(path: &Path) -> Result<Self, ConfigError> {
+        let data = fs::read_to_string(path).map_err(|e| {
+            ConfigError::InvalidConfig(format!(
+                "failed to read block policy file {}: {e:?}",
+                path.display()
+            ))
+        })?;
+        Self::load_from_str(&data)
+    }
+
+    /// Whether the policy needs the block's execution cost
+    pub fn checks_cost(&self) -> bool {
+        self.max_tenure_cost_percentage.is_some()
+    }
+
+    fn check_principal(&self, principal: &PrincipalData) -> Result<(), String> {
+        match principal {
+            PrincipalData::Standard(standard) => {
+                let address = StacksAddress::from(standard.clone());
+                if self.blocked_principals.contains(&address) {
+                    return Err(format!("blocked principal {address}"));
+                }
+            }
+            PrincipalData::Contract(contract) => {
+                if self.blocked_contracts.contains(contract) {
+                    return Err(format!("blocked contract {contract}"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a proposed block's transactions and arrival time (epoch time in seconds) against
+    /// the policy. Returns a description of the first violated rule.
+    pub fn check_proposal(&self, block: &NakamotoBlock, arrival_time: u64) -> Result<(), String> {
+        if let Some(window) = &self.arrival_window {
+            if !window.contains(arrival_time) {
+                return Err(format!(
+                    "arrival_window: proposal arrived at {arrival_time}, outside of the window"
+                ));
+            }
+        }
+        if let Some(max_transactions) = self.max_transactions {
+            if block.txs.len() > max_transactions {
+                return Err(format!(
+                    "max_transactions: block has {} transactions, more than {max_transactions}",
+                    block.txs.len()
+                ));
+            }
+        }
+        for tx in block.txs.iter() {
+            let txid = tx.txid();
+            let origin = tx.origin_address();
+            if self.blocked_principals.contains(&origin) {
+                return Err(format!(
+                    "blocked_principals: transaction {txid} is sent by {origin}"
+                ));
+            }
+            if let Some(sponsor) = tx.sponsor_address() {
+                if self.blocked_principals.contains(&sponsor) {
+                    return Err(format!(
+                        "blocked_principals: transaction {txid} is sponsored by {sponsor}"
+                    ));
+                }
+            }
+            match &tx.payload {
+                TransactionPayload::TokenTransfer(recipient, ..) => {
+                    self.check_principal(recipient)
+                        .map_err(|e| format!("blocked_principals: transaction {txid} pays {e}"))?;
+                }
+                TransactionPayload::ContractCall(call) => {
+                    let contract = call.contract_identifier();
+                    if self.blocked_contracts.contains(&contract) {
+                        return Err(format!(
+                            "blocked_contracts: transaction {txid} calls {contract}"
+                        ));
+                    }
+                }
+                TransactionPayload::SmartContract(smart_contract, _) => {
+                    let contract = QualifiedContractIdentifier::new(
+                        origin.into(),
+                        smart_contract.name.clone(),
+                    );
+                    if self.blocked_contracts.contains(&contract) {
+                        return Err(format!(
+                            "blocked_contracts: transaction {txid} deploys {contract}"
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a validated block's tenure cost against the policy, given the block limit of the
+    /// block's epoch. The node reports the cost of the whole tenure up to and including the
+    /// block, so this rule bounds how much of its budget a tenure may consume, rather than the
+    /// cost of any one block. Returns a description of the first violated rule.
+    pub fn check_cost(
+        &self,
+        tenure_cost: &ExecutionCost,
+        block_limit: &ExecutionCost,
+    ) -> Result<(), String> {
+        if let Some(max_tenure_cost_percentage) = self.max_tenure_cost_percentage {
+            let cost_percentage = block_limit.proportion_largest_dimension(tenure_cost);
+            if cost_percentage > max_tenure_cost_percentage {
+                return Err(format!(
+                    "max_tenure_cost_percentage: the tenure would consume {cost_percentage}% of the block limit, more than {max_tenure_cost_percentage}%"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blockstack_lib::chainstate::nakamoto::NakamotoBlockHeader;
+    use blockstack_lib::chainstate::stacks::{
+        StacksTransaction, TokenTransferMemo, TransactionAuth, TransactionContractCall,
+        TransactionSmartContract, TransactionVersion,
+    };
+    use blockstack_lib::util_lib::strings::StacksString;
+    use clarity::vm::{ClarityName, ContractName};
+    use stacks_common::types::chainstate::{StacksPrivateKey, StacksPublicKey};
+
+    use super::*;
+
+    const POLICY_TOML: &str = r#"
+dry_run = true
+max_tenure_cost_percentage = 50
+max_transactions = 2
+blocked_principals = ["ST2ZRX0K27GW0SP3GJCEMHD95TQGJMKB7G9Y0X1MH"]
+blocked_contracts = ["ST000000000000000000002AMW42H.pox-4"]
+
+[arrival_window]
+start = "22:00"
+end = "06:00"
+"#;
+
+    fn make_tx(payload: TransactionPayload) -> StacksTransaction {
+        let private_key = StacksPrivateKey::random();
+        StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&private_key).unwrap(),
+            payload,
+        )
+    }
+
+    fn make_block(txs: Vec<StacksTransaction>) -> NakamotoBlock {
+        NakamotoBlock {
+            header: NakamotoBlockHeader::empty(),
+            txs,
+        }
+    }
+
+    #[test]
+    fn load_block_policy() {
+        let policy = BlockPolicy::load_from_str(POLICY_TOML).unwrap();
+        assert!(policy.dry_run);
+        assert_eq!(policy.max_tenure_cost_percentage, Some(50));
+        assert_eq!(policy.max_transactions, Some(2));
+        assert_eq!(policy.blocked_principals.len(), 1);
+        assert_eq!(policy.blocked_contracts.len(), 1);
+        assert_eq!(
+            policy.arrival_window,
+            Some(ArrivalWindow {
+                start_secs: 22 * 3600,
+                end_secs: 6 * 3600,
+            })
+        );
+
+        assert_eq!(
+            BlockPolicy::load_from_str("").unwrap(),
+            BlockPolicy::default()
+        );
+        assert!(matches!(
+            BlockPolicy::load_from_str("max_tenure_cost_percentage = 101"),
+            Err(ConfigError::BadField(..))
+        ));
+        assert!(matches!(
+            BlockPolicy::load_from_str("blocked_principals = [\"not-an-address\"]"),
+            Err(ConfigError::BadField(..))
+        ));
+        assert!(matches!(
+            BlockPolicy::load_from_str("[arrival_window]\nstart = \"24:00\"\nend = \"01:00\""),
+            Err(ConfigError::BadField(..))
+        ));
+        assert!(matches!(
+            BlockPolicy::load_from_str("max_transaction = 2"),
+            Err(ConfigError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn arrival_window_wraps_around_midnight() {
+        let day_window = ArrivalWindow {
+            start_secs: 6 * 3600,
+            end_secs: 22 * 3600,
+        };
+        let night_window = ArrivalWindow {
+            start_secs: 22 * 3600,
+            end_secs: 6 * 3600,
+        };
+        let midnight = 20_000 * SECONDS_PER_DAY;
+        for (time, in_day) in [
+            (midnight, false),
+            (midnight + 6 * 3600, true),
+            (midnight + 12 * 3600, true),
+            (midnight + 22 * 3600, false),
+            (midnight + 23 * 3600, false),
+        ] {
+            assert_eq!(day_window.contains(time), in_day);
+            assert_eq!(night_window.contains(time), !in_day);
+        }
+    }
+
+    #[test]
+    fn check_proposal_transactions() {
+        let policy = BlockPolicy::load_from_str(POLICY_TOML).unwrap();
+        let midnight = 20_000 * SECONDS_PER_DAY;
+        let blocked_address =
+            StacksAddress::from_string("ST2ZRX0K27GW0SP3GJCEMHD95TQGJMKB7G9Y0X1MH").unwrap();
+        let transfer = |recipient: PrincipalData| {
+            make_tx(TransactionPayload::TokenTransfer(
+                recipient,
+                1,
+                TokenTransferMemo([0; 34]),
+            ))
+        };
+        let call = |contract: &str| {
+            let contract = QualifiedContractIdentifier::parse(contract).unwrap();
+            make_tx(TransactionPayload::ContractCall(TransactionContractCall {
+                address: StacksAddress::from(contract.issuer),
+                contract_name: ContractName::from(contract.name.as_str()),
+                function_name: ClarityName::from("stack-stx"),
+                function_args: vec![],
+            }))
+        };
+        let other_address = StacksAddress::p2pkh(
+            false,
+            &StacksPublicKey::from_private(&StacksPrivateKey::random()),
+        );
+
+        let block = make_block(vec![transfer(other_address.into())]);
+        policy.check_proposal(&block, midnight).unwrap();
+        // outside of the arrival window
+        policy
+            .check_proposal(&block, midnight + 12 * 3600)
+            .unwrap_err();
+
+        let block = make_block(vec![transfer(blocked_address.into())]);
+        let err = policy.check_proposal(&block, midnight).unwrap_err();
+        assert!(err.starts_with("blocked_principals"), "{err}");
+
+        let block = make_block(vec![call("ST000000000000000000002AMW42H.pox-4")]);
+        let err = policy.check_proposal(&block, midnight).unwrap_err();
+        assert!(err.starts_with("blocked_contracts"), "{err}");
+        let block = make_block(vec![call("ST000000000000000000002AMW42H.pox-3")]);
+        policy.check_proposal(&block, midnight).unwrap();
+
+        let deploy = make_tx(TransactionPayload::SmartContract(
+            TransactionSmartContract {
+                name: ContractName::from("blocked"),
+                code_body: StacksString::from_str("(define-read-only (foo) u1)").unwrap(),
+            },
+            None,
+        ));
+        let block = make_block(vec![deploy.clone()]);
+        policy.check_proposal(&block, midnight).unwrap();
+        let mut deploy_policy = policy.clone();
+        deploy_policy
+            .blocked_contracts
+            .insert(QualifiedContractIdentifier::new(
+                deploy.origin_address().into(),
+                ContractName::from("blocked"),
+            ));
+        let err = deploy_policy.check_proposal(&block, midnight).unwrap_err();
+        assert!(err.starts_with("blocked_contracts"), "{err}");
+
+        let block = make_block(vec![
+            transfer(other_address.into()),
+            transfer(other_address.into()),
+            transfer(other_address.into()),
+        ]);
+        let err = policy.check_proposal(&block, midnight).unwrap_err();
+        assert!(err.starts_with("max_transactions"), "{err}");
+    }
+
+    #[test]
+    fn check_block_cost() {
+        let policy = BlockPolicy::load_from_str(POLICY_TOML).unwrap();
+        assert!(policy.checks_cost());
+        let block_limit = ExecutionCost {
+            write_length: 1000,
+            write_count: 1000,
+            read_length: 1000,
+            read_count: 1000,
+            runtime: 1000,
+        };
+        let mut cost = ExecutionCost {
+            write_length: 500,
+            write_count: 10,
+            read_length: 10,
+            read_count: 10,
+            runtime: 10,
+        };
+        policy.check_cost(&cost, &block_limit).unwrap();
+        cost.read_count = 510;
+        let err = policy.check_cost(&cost, &block_limit).unwrap_err();
+        assert!(err.starts_with("max_tenure_cost_percentage"), "{err}");
+
+        assert!(!BlockPolicy::default().checks_cost());
+    }
+}
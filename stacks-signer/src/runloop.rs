@@ -306,7 +306,7 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug> RunLo
             signer_entries,
             signer_slot_ids: signer_slot_ids.into_values().collect(),
             first_proposal_burn_block_timing: self.config.first_proposal_burn_block_timing,
-            stacks_private_key: self.config.stacks_private_key,
+            signing_backend: self.config.signing_backend.clone(),
             node_host: self.config.node_host.to_string(),
             mainnet: self.config.network.is_mainnet(),
             db_path: self.config.db_path.clone(),
@@ -317,6 +317,8 @@ impl<Signer: SignerTrait<T>, T: StacksMessageCodec + Clone + Send + Debug> RunLo
             tenure_idle_timeout_buffer: self.config.tenure_idle_timeout_buffer,
             block_proposal_max_age_secs: self.config.block_proposal_max_age_secs,
             reorg_attempts_activity_timeout: self.config.reorg_attempts_activity_timeout,
+            high_availability: self.config.high_availability.clone(),
+            block_policy: self.config.block_policy.clone(),
         }))
     }
 
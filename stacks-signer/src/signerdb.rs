@@ -43,6 +43,8 @@ use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::util::secp256k1::MessageSignature;
 use stacks_common::{debug, define_u8_enum, error};
 
+use crate::decision_log::{BlockDecision, DecisionQuery};
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 /// A vote across the signer set for a block
 pub struct NakamotoBlockVote {
@@ -286,6 +288,10 @@ impl BlockInfo {
     }
 }
 
+/// The high-availability lease holder recorded in a signer database once it has been exported to
+/// another host. Its lease never expires, so no signer instance can use the database again.
+pub const MIGRATED_LEASE_HOLDER: &str = "<migrated>";
+
 /// This struct manages a SQLite database connection
 /// for the signer.
 #[derive(Debug)]
@@ -507,6 +513,39 @@ ALTER TABLE block_rejection_signer_addrs
     ADD COLUMN reject_code INTEGER;
 "#;
 
+static CREATE_SIGNER_LEASE_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS signer_lease (
+    -- there is only ever one lease
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    -- the instance ID of the high-availability signer which holds the lease
+    holder TEXT NOT NULL,
+    -- the time (epoch time in milliseconds) at which the lease expires unless renewed
+    expires_at INTEGER NOT NULL
+) STRICT;"#;
+
+static CREATE_BLOCK_DECISIONS_TABLE: &str = r#"
+CREATE TABLE IF NOT EXISTS block_decisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    signer_signature_hash TEXT NOT NULL,
+    block_id TEXT NOT NULL,
+    consensus_hash TEXT NOT NULL,
+    stacks_height INTEGER NOT NULL,
+    reward_cycle INTEGER NOT NULL,
+    -- the time (epoch time in seconds) at which the decision was made
+    decided_at INTEGER NOT NULL,
+    -- the serialized BlockDecision
+    decision TEXT NOT NULL
+) STRICT;"#;
+
+static CREATE_INDEXES_11: &str = r#"
+CREATE INDEX IF NOT EXISTS block_decisions_on_signer_signature_hash ON block_decisions(signer_signature_hash);
+CREATE INDEX IF NOT EXISTS block_decisions_on_block_id ON block_decisions(block_id);
+CREATE INDEX IF NOT EXISTS block_decisions_on_consensus_hash ON block_decisions(consensus_hash);
+CREATE INDEX IF NOT EXISTS block_decisions_on_stacks_height ON block_decisions(stacks_height);
+CREATE INDEX IF NOT EXISTS block_decisions_on_decided_at ON block_decisions(decided_at);
+CREATE INDEX IF NOT EXISTS block_decisions_on_reward_cycle ON block_decisions(reward_cycle);
+"#;
+
 static SCHEMA_1: &[&str] = &[
     DROP_SCHEMA_0,
     CREATE_DB_CONFIG,
@@ -576,9 +615,20 @@ static SCHEMA_9: &[&str] = &[
     "INSERT INTO db_config (version) VALUES (9);",
 ];
 
+static SCHEMA_10: &[&str] = &[
+    CREATE_SIGNER_LEASE_TABLE,
+    "INSERT INTO db_config (version) VALUES (10);",
+];
+
+static SCHEMA_11: &[&str] = &[
+    CREATE_BLOCK_DECISIONS_TABLE,
+    CREATE_INDEXES_11,
+    "INSERT INTO db_config (version) VALUES (11);",
+];
+
 impl SignerDb {
     /// The current schema version used in this build of the signer binary.
-    pub const SCHEMA_VERSION: u32 = 9;
+    pub const SCHEMA_VERSION: u32 = 11;
 
     /// Create a new `SignerState` instance.
     /// This will create a new SQLite database at the given path
@@ -734,6 +784,34 @@ impl SignerDb {
         Ok(())
     }
 
+    /// Migrate from schema 9 to schema 10
+    fn schema_10_migration(tx: &Transaction) -> Result<(), DBError> {
+        if Self::get_schema_version(tx)? >= 10 {
+            // no migration necessary
+            return Ok(());
+        }
+
+        for statement in SCHEMA_10.iter() {
+            tx.execute_batch(statement)?;
+        }
+
+        Ok(())
+    }
+
+    /// Migrate from schema 10 to schema 11
+    fn schema_11_migration(tx: &Transaction) -> Result<(), DBError> {
+        if Self::get_schema_version(tx)? >= 11 {
+            // no migration necessary
+            return Ok(());
+        }
+
+        for statement in SCHEMA_11.iter() {
+            tx.execute_batch(statement)?;
+        }
+
+        Ok(())
+    }
+
     /// Register custom scalar functions used by the database
     fn register_scalar_functions(&self) -> Result<(), DBError> {
         // Register helper function for determining if a block is a tenure change transaction
@@ -776,7 +854,9 @@ impl SignerDb {
                 6 => Self::schema_7_migration(&sql_tx)?,
                 7 => Self::schema_8_migration(&sql_tx)?,
                 8 => Self::schema_9_migration(&sql_tx)?,
-                9 => break,
+                9 => Self::schema_10_migration(&sql_tx)?,
+                10 => Self::schema_11_migration(&sql_tx)?,
+                11 => break,
                 x => return Err(DBError::Other(format!(
                     "Database schema is newer than supported by this binary. Expected version = {}, Database version = {x}",
                     Self::SCHEMA_VERSION,
@@ -796,6 +876,15 @@ impl SignerDb {
         )
     }
 
+    /// Write a consistent, compacted copy of the database to a new file at `path`
+    pub fn copy_to(&self, path: &Path) -> Result<(), DBError> {
+        let path = path
+            .to_str()
+            .ok_or_else(|| DBError::Other(format!("Non-UTF-8 path: {}", path.display())))?;
+        self.db.execute("VACUUM INTO ?1", params![path])?;
+        Ok(())
+    }
+
     /// Get the signer state for the provided reward cycle if it exists in the database
     pub fn get_encrypted_signer_state(
         &self,
@@ -1268,6 +1357,168 @@ impl SignerDb {
         })?;
         Ok(Some(last_activity_time))
     }
+
+    /// Acquire or renew the high-availability lease for the signer instance `holder`, so that
+    /// it lasts until `lease_duration` after `now_ms` (epoch time in milliseconds). The lease is
+    /// granted if nobody holds it, if `holder` already holds it, or if the current holder's lease
+    /// has expired. Returns whether `holder` holds the lease.
+    pub fn try_acquire_lease(
+        &mut self,
+        holder: &str,
+        now_ms: u64,
+        lease_duration: Duration,
+    ) -> Result<bool, DBError> {
+        let tx = tx_begin_immediate(&mut self.db)?;
+        let current_lease: Option<(String, i64)> = tx
+            .query_row(
+                "SELECT holder, expires_at FROM signer_lease WHERE id = 0",
+                params![],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        if let Some((current_holder, expires_at)) = current_lease {
+            if current_holder != holder && u64::try_from(expires_at).unwrap_or(0) > now_ms {
+                // Another instance holds an unexpired lease
+                return Ok(false);
+            }
+        }
+        let lease_duration_ms = u64::try_from(lease_duration.as_millis()).unwrap_or(u64::MAX);
+        let expires_at = now_ms.saturating_add(lease_duration_ms);
+        tx.execute(
+            "INSERT OR REPLACE INTO signer_lease (id, holder, expires_at) VALUES (0, ?1, ?2)",
+            params![holder, u64_to_sql(expires_at)?],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Get the holder of the high-availability lease and the time (epoch time in milliseconds)
+    /// at which the lease expires, if any signer instance has ever held it
+    pub fn get_lease(&self) -> Result<Option<(String, u64)>, DBError> {
+        let lease: Option<(String, i64)> = self
+            .db
+            .query_row(
+                "SELECT holder, expires_at FROM signer_lease WHERE id = 0",
+                params![],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        lease
+            .map(|(holder, expires_at)| {
+                let expires_at = u64::try_from(expires_at).map_err(|e| {
+                    error!("Failed to parse db lease expiry as u64: {e}");
+                    DBError::Corruption
+                })?;
+                Ok((holder, expires_at))
+            })
+            .transpose()
+    }
+
+    /// Fence the signer off from this database after it has been exported to another host, by
+    /// giving the high-availability lease to [`MIGRATED_LEASE_HOLDER`] forever. Refused, returning
+    /// `false`, while a signer instance holds an unexpired lease.
+    pub fn mark_migrated(&mut self, now_ms: u64) -> Result<bool, DBError> {
+        let tx = tx_begin_immediate(&mut self.db)?;
+        let current_lease: Option<(String, i64)> = tx
+            .query_row(
+                "SELECT holder, expires_at FROM signer_lease WHERE id = 0",
+                params![],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        if let Some((current_holder, expires_at)) = current_lease {
+            if current_holder != MIGRATED_LEASE_HOLDER
+                && u64::try_from(expires_at).unwrap_or(0) > now_ms
+            {
+                return Ok(false);
+            }
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO signer_lease (id, holder, expires_at) VALUES (0, ?1, ?2)",
+            params![MIGRATED_LEASE_HOLDER, i64::MAX],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Whether this database has been exported to another host, so that no signer may use it
+    pub fn is_migrated(&self) -> Result<bool, DBError> {
+        Ok(self
+            .get_lease()?
+            .is_some_and(|(holder, _)| holder == MIGRATED_LEASE_HOLDER))
+    }
+
+    /// Drop the high-availability lease, whoever holds it
+    pub fn clear_lease(&mut self) -> Result<(), DBError> {
+        self.db.execute("DELETE FROM signer_lease", params![])?;
+        Ok(())
+    }
+
+    /// Append a decision on a block proposal to the decision log
+    pub fn insert_block_decision(&self, decision: &BlockDecision) -> Result<(), DBError> {
+        let decision_json =
+            serde_json::to_string(decision).expect("Unable to serialize block decision");
+        self.db.execute(
+            "INSERT INTO block_decisions (signer_signature_hash, block_id, consensus_hash, stacks_height, reward_cycle, decided_at, decision) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                decision.signer_signature_hash.to_string(),
+                decision.block_id.to_string(),
+                decision.consensus_hash.to_hex(),
+                u64_to_sql(decision.block_height)?,
+                u64_to_sql(decision.reward_cycle)?,
+                u64_to_sql(decision.decided_at)?,
+                decision_json,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Delete the decision log entries for blocks from before `reward_cycle`. Returns the number
+    /// of deleted entries.
+    pub fn prune_block_decisions(&self, reward_cycle: u64) -> Result<usize, DBError> {
+        let deleted = self.db.execute(
+            "DELETE FROM block_decisions WHERE reward_cycle < ?1",
+            params![u64_to_sql(reward_cycle)?],
+        )?;
+        Ok(deleted)
+    }
+
+    /// Fetch at most `limit` entries of the decision log which match `query`, oldest first
+    pub fn get_block_decisions(
+        &self,
+        query: &DecisionQuery,
+        limit: u64,
+    ) -> Result<Vec<BlockDecision>, DBError> {
+        // Open-ended queries may pass values which SQLite cannot represent
+        let clamp = |value: u64| u64_to_sql(value.min(i64::MAX as u64));
+        let limit = clamp(limit)?;
+        let decisions: Vec<String> = match query {
+            DecisionQuery::BlockHash(hash) => query_rows(
+                &self.db,
+                "SELECT decision FROM block_decisions WHERE signer_signature_hash = ?1 OR block_id = ?1 ORDER BY id ASC LIMIT ?2",
+                params![hash.to_hex(), limit],
+            )?,
+            DecisionQuery::Tenure(consensus_hash) => query_rows(
+                &self.db,
+                "SELECT decision FROM block_decisions WHERE consensus_hash = ?1 ORDER BY id ASC LIMIT ?2",
+                params![consensus_hash.to_hex(), limit],
+            )?,
+            DecisionQuery::Height(height) => query_rows(
+                &self.db,
+                "SELECT decision FROM block_decisions WHERE stacks_height = ?1 ORDER BY id ASC LIMIT ?2",
+                params![u64_to_sql(*height)?, limit],
+            )?,
+            DecisionQuery::TimeRange { start, end } => query_rows(
+                &self.db,
+                "SELECT decision FROM block_decisions WHERE decided_at BETWEEN ?1 AND ?2 ORDER BY id ASC LIMIT ?3",
+                params![clamp(*start)?, clamp(*end)?, limit],
+            )?,
+        };
+        decisions
+            .iter()
+            .map(|decision| serde_json::from_str(decision).map_err(DBError::SerializationError))
+            .collect()
+    }
 }
 
 fn try_deserialize<T>(s: Option<String>) -> Result<Option<T>, DBError>
@@ -1340,6 +1591,7 @@ mod tests {
     use libsigner::{BlockProposal, BlockProposalData};
 
     use super::*;
+    use crate::decision_log::DecisionOutcome;
     use crate::signerdb::NakamotoBlockVote;
 
     fn _wipe_db(db_path: &PathBuf) {
@@ -2193,4 +2445,161 @@ mod tests {
             .unwrap()
             .is_none());
     }
+
+    #[test]
+    fn signer_lease() {
+        let db_path = tmp_db_path();
+        let mut db_a = SignerDb::new(&db_path).expect("Failed to create signer db");
+        let mut db_b = SignerDb::new(&db_path).expect("Failed to create signer db");
+        let lease_duration = Duration::from_secs(30);
+        let now_ms = 1_000_000;
+
+        assert!(db_a.get_lease().unwrap().is_none());
+        assert!(db_a
+            .try_acquire_lease("signer-a", now_ms, lease_duration)
+            .unwrap());
+        assert_eq!(
+            db_b.get_lease().unwrap(),
+            Some(("signer-a".to_string(), now_ms + 30_000))
+        );
+
+        // The standby cannot take an unexpired lease
+        assert!(!db_b
+            .try_acquire_lease("signer-b", now_ms + 10_000, lease_duration)
+            .unwrap());
+
+        // The leader renews its lease
+        assert!(db_a
+            .try_acquire_lease("signer-a", now_ms + 20_000, lease_duration)
+            .unwrap());
+        assert!(!db_b
+            .try_acquire_lease("signer-b", now_ms + 40_000, lease_duration)
+            .unwrap());
+
+        // The standby takes over once the lease expires, and the old leader is locked out
+        assert!(db_b
+            .try_acquire_lease("signer-b", now_ms + 50_001, lease_duration)
+            .unwrap());
+        assert!(!db_a
+            .try_acquire_lease("signer-a", now_ms + 50_002, lease_duration)
+            .unwrap());
+        assert_eq!(
+            db_a.get_lease().unwrap(),
+            Some(("signer-b".to_string(), now_ms + 80_001))
+        );
+    }
+
+    #[test]
+    fn migrated_lease() {
+        let db_path = tmp_db_path();
+        let mut db = SignerDb::new(&db_path).expect("Failed to create signer db");
+        let lease_duration = Duration::from_secs(30);
+        let now_ms = 1_000_000;
+
+        assert!(!db.is_migrated().unwrap());
+        assert!(db
+            .try_acquire_lease("signer-a", now_ms, lease_duration)
+            .unwrap());
+        // Cannot fence while a signer instance holds the lease
+        assert!(!db.mark_migrated(now_ms + 10_000).unwrap());
+        assert!(!db.is_migrated().unwrap());
+
+        // Once the lease expires, the database is fenced for good
+        assert!(db.mark_migrated(now_ms + 30_001).unwrap());
+        assert!(db.is_migrated().unwrap());
+        assert!(db.mark_migrated(now_ms + 30_002).unwrap());
+        assert!(!db
+            .try_acquire_lease("signer-a", now_ms + 1_000_000_000, lease_duration)
+            .unwrap());
+
+        db.clear_lease().unwrap();
+        assert!(db.get_lease().unwrap().is_none());
+        assert!(!db.is_migrated().unwrap());
+    }
+
+    #[test]
+    fn block_decisions() {
+        let db_path = tmp_db_path();
+        let db = SignerDb::new(db_path).expect("Failed to create signer db");
+        let (block_info_1, _) = create_block_override(|b| {
+            b.block.header.consensus_hash = ConsensusHash([0x01; 20]);
+            b.block.header.chain_length = 1;
+        });
+        let (block_info_2, _) = create_block_override(|b| {
+            b.block.header.consensus_hash = ConsensusHash([0x02; 20]);
+            b.block.header.chain_length = 2;
+        });
+        let pending = BlockDecision::new(&block_info_1, DecisionOutcome::PendingValidation, None);
+        let accepted = BlockDecision {
+            decided_at: pending.decided_at + 1,
+            validation_time_ms: Some(250),
+            validation_wait_ms: Some(300),
+            ..BlockDecision::new(&block_info_1, DecisionOutcome::Accepted, None)
+        };
+        let rejected = BlockDecision {
+            decided_at: pending.decided_at + 10,
+            reject_reason: Some(RejectReason::InvalidParentBlock),
+            reject_message: Some(RejectReason::InvalidParentBlock.to_string()),
+            ..BlockDecision::new(&block_info_2, DecisionOutcome::Rejected, None)
+        };
+        for decision in [&pending, &accepted, &rejected] {
+            db.insert_block_decision(decision).unwrap();
+        }
+
+        // Blocks can be looked up by signer signature hash or block ID
+        let by_sighash = DecisionQuery::BlockHash(block_info_1.signer_signature_hash());
+        assert_eq!(
+            db.get_block_decisions(&by_sighash, 10).unwrap(),
+            vec![pending.clone(), accepted.clone()]
+        );
+        let by_block_id =
+            DecisionQuery::BlockHash(Sha512Trunc256Sum(block_info_2.block.block_id().0));
+        assert_eq!(
+            db.get_block_decisions(&by_block_id, 10).unwrap(),
+            vec![rejected.clone()]
+        );
+        assert_eq!(
+            db.get_block_decisions(&DecisionQuery::Tenure(ConsensusHash([0x02; 20])), 10)
+                .unwrap(),
+            vec![rejected.clone()]
+        );
+        assert_eq!(
+            db.get_block_decisions(&DecisionQuery::Height(1), 1)
+                .unwrap(),
+            vec![pending.clone()]
+        );
+        let time_range = DecisionQuery::TimeRange {
+            start: accepted.decided_at,
+            end: rejected.decided_at,
+        };
+        assert_eq!(
+            db.get_block_decisions(&time_range, 10).unwrap(),
+            vec![accepted, rejected]
+        );
+        assert!(db
+            .get_block_decisions(&DecisionQuery::Height(3), 10)
+            .unwrap()
+            .is_empty());
+
+        // Pruning removes the decisions on blocks from earlier reward cycles
+        let (block_info_3, _) = create_block_override(|b| {
+            b.reward_cycle = 43;
+        });
+        let next_cycle =
+            BlockDecision::new(&block_info_3, DecisionOutcome::PendingValidation, None);
+        db.insert_block_decision(&next_cycle).unwrap();
+        assert_eq!(db.prune_block_decisions(42).unwrap(), 0);
+        assert_eq!(db.prune_block_decisions(43).unwrap(), 3);
+        assert_eq!(
+            db.get_block_decisions(
+                &DecisionQuery::TimeRange {
+                    start: 0,
+                    end: u64::MAX
+                },
+                10
+            )
+            .unwrap(),
+            vec![next_cycle]
+        );
+    }
 }
@@ -0,0 +1,248 @@
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use stacks_common::types::chainstate::{StacksPrivateKey, StacksPublicKey};
+use stacks_common::types::PrivateKey;
+use stacks_common::util::hash::{hex_bytes, to_hex};
+
+use super::SigningError;
+
+/// The current keystore file format version
+pub const KEYSTORE_VERSION: u32 = 1;
+/// Default number of PBKDF2 iterations used to derive the encryption key from the passphrase
+pub const DEFAULT_KEYSTORE_KDF_ITERATIONS: u32 = 600_000;
+const KEYSTORE_SALT_LEN: usize = 16;
+const KEYSTORE_NONCE_LEN: usize = 12;
+
+/// A Stacks private key encrypted with a passphrase.  The encryption key is derived from the
+/// passphrase with PBKDF2-HMAC-SHA256, and the private key is encrypted with AES-256-GCM,
+/// authenticating the stored public key as well.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KeystoreFile {
+    /// The keystore file format version
+    pub version: u32,
+    /// The hex-encoded compressed public key of the encrypted private key
+    pub public_key: String,
+    /// Number of PBKDF2 iterations
+    pub kdf_iterations: u32,
+    /// Hex-encoded PBKDF2 salt
+    pub salt: String,
+    /// Hex-encoded AES-GCM nonce
+    pub nonce: String,
+    /// Hex-encoded encrypted private key, including the AES-GCM tag
+    pub ciphertext: String,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+fn decode_hex_field(name: &str, value: &str) -> Result<Vec<u8>, SigningError> {
+    hex_bytes(value).map_err(|_| SigningError::Keystore(format!("malformed `{name}`")))
+}
+
+impl KeystoreFile {
+    /// Encrypt `private_key` with `passphrase`
+    pub fn encrypt(
+        private_key: &StacksPrivateKey,
+        passphrase: &str,
+        kdf_iterations: u32,
+    ) -> Result<Self, SigningError> {
+        if passphrase.is_empty() {
+            return Err(SigningError::Keystore(
+                "passphrase must not be empty".into(),
+            ));
+        }
+        let mut salt = [0u8; KEYSTORE_SALT_LEN];
+        let mut nonce = [0u8; KEYSTORE_NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let public_key = to_hex(&StacksPublicKey::from_private(private_key).to_bytes_compressed());
+        let key = derive_key(passphrase, &salt, kdf_iterations);
+        let cipher = Aes256Gcm::new(&key.into());
+        let ciphertext = cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &private_key.to_bytes(),
+                    aad: public_key.as_bytes(),
+                },
+            )
+            .map_err(|_| SigningError::Keystore("failed to encrypt private key".into()))?;
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            public_key,
+            kdf_iterations,
+            salt: to_hex(&salt),
+            nonce: to_hex(&nonce),
+            ciphertext: to_hex(&ciphertext),
+        })
+    }
+
+    /// Decrypt the private key with `passphrase`
+    pub fn decrypt(&self, passphrase: &str) -> Result<StacksPrivateKey, SigningError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(SigningError::Keystore(format!(
+                "unsupported keystore version {}",
+                self.version
+            )));
+        }
+        let salt = decode_hex_field("salt", &self.salt)?;
+        let nonce = decode_hex_field("nonce", &self.nonce)?;
+        let ciphertext = decode_hex_field("ciphertext", &self.ciphertext)?;
+        if nonce.len() != KEYSTORE_NONCE_LEN {
+            return Err(SigningError::Keystore("malformed `nonce`".into()));
+        }
+
+        let key = derive_key(passphrase, &salt, self.kdf_iterations);
+        let cipher = Aes256Gcm::new(&key.into());
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: self.public_key.as_bytes(),
+                },
+            )
+            .map_err(|_| SigningError::Keystore("wrong passphrase or corrupted keystore".into()))?;
+        let private_key = StacksPrivateKey::from_slice(&plaintext)
+            .map_err(|e| SigningError::Keystore(format!("malformed private key: {e}")))?;
+
+        let public_key = to_hex(&StacksPublicKey::from_private(&private_key).to_bytes_compressed());
+        if public_key != self.public_key {
+            return Err(SigningError::PublicKeyMismatch(self.public_key.clone()));
+        }
+        Ok(private_key)
+    }
+
+    /// Read a keystore file
+    pub fn load(path: &Path) -> Result<Self, SigningError> {
+        let data = std::fs::read_to_string(path).map_err(|e| {
+            SigningError::Keystore(format!("failed to read {}: {e:?}", path.display()))
+        })?;
+        serde_json::from_str(&data).map_err(|e| {
+            SigningError::Keystore(format!("failed to parse {}: {e:?}", path.display()))
+        })
+    }
+
+    /// Write a new keystore file, readable only by its owner.  Fails if the file already
+    /// exists, so that an existing key is never overwritten.
+    pub fn save(&self, path: &Path) -> Result<(), SigningError> {
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| SigningError::Keystore(format!("failed to serialize keystore: {e:?}")))?;
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path).map_err(|e| {
+            SigningError::Keystore(format!("failed to create {}: {e:?}", path.display()))
+        })?;
+        file.write_all(data.as_bytes()).map_err(|e| {
+            SigningError::Keystore(format!("failed to write {}: {e:?}", path.display()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::signing::{SigningBackend, SigningBackendConfig};
+
+    /// Few iterations, so that tests run quickly
+    const TEST_KDF_ITERATIONS: u32 = 16;
+
+    #[test]
+    fn keystore_roundtrip() {
+        let private_key = StacksPrivateKey::random();
+        let keystore = KeystoreFile::encrypt(&private_key, "hunter2", TEST_KDF_ITERATIONS).unwrap();
+        assert_eq!(keystore.decrypt("hunter2").unwrap(), private_key);
+        assert!(matches!(
+            keystore.decrypt("hunter3"),
+            Err(SigningError::Keystore(_))
+        ));
+
+        // the public key is authenticated
+        let mut tampered = keystore.clone();
+        tampered.public_key = to_hex(
+            &StacksPublicKey::from_private(&StacksPrivateKey::random()).to_bytes_compressed(),
+        );
+        assert!(tampered.decrypt("hunter2").is_err());
+
+        assert!(KeystoreFile::encrypt(&private_key, "", TEST_KDF_ITERATIONS).is_err());
+    }
+
+    #[test]
+    fn keystore_signing_backend() {
+        let dir = std::env::temp_dir().join(format!(
+            "stacks-signer-keystore-{}",
+            rand::thread_rng().next_u64()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("keystore.json");
+        let passphrase_file = dir.join("passphrase");
+
+        let private_key = StacksPrivateKey::random();
+        let keystore =
+            KeystoreFile::encrypt(&private_key, "correct horse", TEST_KDF_ITERATIONS).unwrap();
+        keystore.save(&path).unwrap();
+        // never overwrite an existing keystore
+        assert!(keystore.save(&path).is_err());
+        std::fs::write(&passphrase_file, "correct horse\n").unwrap();
+
+        let backend: Arc<dyn SigningBackend> = SigningBackendConfig::Keystore {
+            path: path.clone(),
+            passphrase_file: Some(passphrase_file.clone()),
+        }
+        .load()
+        .unwrap();
+        assert_eq!(
+            backend.public_key(),
+            StacksPublicKey::from_private(&private_key)
+        );
+        let message_hash = [0x01; 32];
+        assert_eq!(
+            backend.sign(&message_hash).unwrap(),
+            private_key.sign(&message_hash).unwrap()
+        );
+
+        std::fs::write(&passphrase_file, "wrong horse").unwrap();
+        assert!(SigningBackendConfig::Keystore {
+            path,
+            passphrase_file: Some(passphrase_file),
+        }
+        .load()
+        .is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
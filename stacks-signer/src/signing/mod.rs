@@ -0,0 +1,271 @@
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+/// The passphrase-encrypted keystore file
+pub mod keystore;
+/// The remote signing backend and its wire protocol
+pub mod remote;
+
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use libsigner::v0::messages::MessageSigner;
+use serde::Deserialize;
+use stacks_common::types::chainstate::{StacksPrivateKey, StacksPublicKey};
+use stacks_common::types::{PrivateKey, PublicKey};
+use stacks_common::util::secp256k1::MessageSignature;
+
+pub use self::keystore::KeystoreFile;
+pub use self::remote::RemoteSigningBackend;
+
+/// Environment variable holding the keystore passphrase, if the config does not name a
+/// passphrase file
+pub const KEYSTORE_PASSPHRASE_ENV: &str = "STACKS_SIGNER_KEYSTORE_PASSPHRASE";
+/// Default time to wait for a remote signer to respond.  Signing blocks the signer's event loop,
+/// so this is kept well below the time a miner waits for signatures.
+const DEFAULT_REMOTE_SIGNER_TIMEOUT_MS: u64 = 1_000;
+
+#[derive(thiserror::Error, Debug)]
+/// An error occurred using a signing backend
+pub enum SigningError {
+    /// The keystore file could not be read, written or decrypted
+    #[error("Keystore error: {0}")]
+    Keystore(String),
+    /// The remote signer could not be reached or returned an error
+    #[error("Remote signer error: {0}")]
+    Remote(String),
+    /// The signature was not made by the signer's key
+    #[error("Signature does not match the signer public key {0}")]
+    PublicKeyMismatch(String),
+    /// The underlying crypto library failed to sign
+    #[error("Failed to sign: {0}")]
+    SigningFailed(String),
+}
+
+/// Holds the signer's Stacks private key and signs with it on the signer's behalf.  The key
+/// itself need not be available to the signer process.
+pub trait SigningBackend: Debug + Send + Sync {
+    /// The public key of the signer's Stacks private key
+    fn public_key(&self) -> StacksPublicKey;
+    /// Sign a 32-byte message hash with the signer's Stacks private key, returning a
+    /// recoverable signature
+    fn sign(&self, message_hash: &[u8]) -> Result<MessageSignature, SigningError>;
+}
+
+impl MessageSigner for dyn SigningBackend {
+    fn sign_message(&self, message_hash: &[u8]) -> Result<MessageSignature, String> {
+        self.sign(message_hash).map_err(|e| e.to_string())
+    }
+}
+
+/// A signing backend which holds the private key in memory
+pub struct LocalSigningBackend {
+    private_key: StacksPrivateKey,
+    public_key: StacksPublicKey,
+}
+
+impl LocalSigningBackend {
+    /// Create a signing backend for the given private key
+    pub fn new(private_key: StacksPrivateKey) -> Self {
+        Self {
+            private_key,
+            public_key: StacksPublicKey::from_private(&private_key),
+        }
+    }
+}
+
+impl Debug for LocalSigningBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never log the private key
+        f.debug_struct("LocalSigningBackend")
+            .field("public_key", &self.public_key.to_hex())
+            .finish()
+    }
+}
+
+impl SigningBackend for LocalSigningBackend {
+    fn public_key(&self) -> StacksPublicKey {
+        self.public_key
+    }
+
+    fn sign(&self, message_hash: &[u8]) -> Result<MessageSignature, SigningError> {
+        self.private_key
+            .sign(message_hash)
+            .map_err(|e| SigningError::SigningFailed(e.to_string()))
+    }
+}
+
+/// Check that `signature` over `message_hash` was made by `public_key`
+pub fn verify_signature(
+    public_key: &StacksPublicKey,
+    message_hash: &[u8],
+    signature: &MessageSignature,
+) -> Result<(), SigningError> {
+    match public_key.verify(message_hash, signature) {
+        Ok(true) => Ok(()),
+        _ => Err(SigningError::PublicKeyMismatch(public_key.to_hex())),
+    }
+}
+
+/// Read the keystore passphrase from `passphrase_file`, ignoring a trailing newline, or from
+/// the `STACKS_SIGNER_KEYSTORE_PASSPHRASE` environment variable if no file is given
+pub fn read_keystore_passphrase(passphrase_file: Option<&Path>) -> Result<String, SigningError> {
+    match passphrase_file {
+        Some(passphrase_file) => Ok(std::fs::read_to_string(passphrase_file)
+            .map_err(|e| {
+                SigningError::Keystore(format!(
+                    "failed to read passphrase file {}: {e:?}",
+                    passphrase_file.display()
+                ))
+            })?
+            .trim_end_matches(['\r', '\n'])
+            .to_string()),
+        None => std::env::var(KEYSTORE_PASSPHRASE_ENV).map_err(|_| {
+            SigningError::Keystore(format!(
+                "no passphrase file is given and {KEYSTORE_PASSPHRASE_ENV} is not set"
+            ))
+        }),
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+/// The `[signing_backend]` section of the signer config file, used instead of
+/// `stacks_private_key`
+pub enum SigningBackendConfig {
+    /// Load the private key from a passphrase-encrypted keystore file
+    Keystore {
+        /// The path to the keystore file
+        path: PathBuf,
+        /// The path to a file holding the passphrase.  If not set, the passphrase is read from
+        /// the `STACKS_SIGNER_KEYSTORE_PASSPHRASE` environment variable.
+        passphrase_file: Option<PathBuf>,
+    },
+    /// Sign with a remote signer, which holds the private key
+    Remote {
+        /// The remote signer's base URL, e.g. `http://127.0.0.1:30100`
+        url: String,
+        /// The hex-encoded public key of the private key held by the remote signer
+        public_key: String,
+        /// A bearer token to authenticate to the remote signer.  Requires an https `url`, unless
+        /// the remote signer runs on a loopback address.
+        auth_token: Option<String>,
+        /// How long to wait (in millisecs) for the remote signer to respond, including retries.
+        /// The signer's event loop is blocked meanwhile.  Defaults to 1000.
+        timeout_ms: Option<u64>,
+    },
+}
+
+impl SigningBackendConfig {
+    /// Instantiate the configured signing backend.  This unlocks the keystore, so the passphrase
+    /// must be available.
+    pub fn load(&self) -> Result<Arc<dyn SigningBackend>, SigningError> {
+        match self {
+            Self::Keystore {
+                path,
+                passphrase_file,
+            } => {
+                let passphrase = read_keystore_passphrase(passphrase_file.as_deref())?;
+                let private_key = KeystoreFile::load(path)?.decrypt(&passphrase)?;
+                Ok(Arc::new(LocalSigningBackend::new(private_key)))
+            }
+            Self::Remote {
+                url,
+                public_key,
+                auth_token,
+                timeout_ms,
+            } => {
+                let public_key = StacksPublicKey::from_hex(public_key).map_err(|e| {
+                    SigningError::Remote(format!("invalid remote signer public key: {e}"))
+                })?;
+                let timeout =
+                    Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_REMOTE_SIGNER_TIMEOUT_MS));
+                Ok(Arc::new(RemoteSigningBackend::new(
+                    url,
+                    public_key,
+                    auth_token.clone(),
+                    timeout,
+                )?))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_backend_signs_with_its_key() {
+        let private_key = StacksPrivateKey::random();
+        let backend = LocalSigningBackend::new(private_key);
+        assert_eq!(
+            backend.public_key(),
+            StacksPublicKey::from_private(&private_key)
+        );
+
+        let message_hash = [0x42; 32];
+        let signature = backend.sign(&message_hash).unwrap();
+        verify_signature(&backend.public_key(), &message_hash, &signature).unwrap();
+
+        let other_key = StacksPublicKey::from_private(&StacksPrivateKey::random());
+        assert!(matches!(
+            verify_signature(&other_key, &message_hash, &signature),
+            Err(SigningError::PublicKeyMismatch(_))
+        ));
+
+        // the private key never appears in logs
+        let debug_str = format!("{backend:?}");
+        assert!(!debug_str.contains(&private_key.to_hex()));
+    }
+
+    #[test]
+    fn parse_signing_backend_config() {
+        let keystore: SigningBackendConfig = toml::from_str(
+            r#"
+type = "keystore"
+path = "/var/lib/signer/keystore.json"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            keystore,
+            SigningBackendConfig::Keystore {
+                path: "/var/lib/signer/keystore.json".into(),
+                passphrase_file: None,
+            }
+        );
+
+        let remote: SigningBackendConfig = toml::from_str(
+            r#"
+type = "remote"
+url = "http://127.0.0.1:30100"
+public_key = "03a6e0aa4ae9df6bcf4bc72ba7bc7c2a5e8ae3e31a4e3e0e0bd1f6da9b4ab5d1f0"
+timeout_ms = 1000
+"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            remote,
+            SigningBackendConfig::Remote {
+                timeout_ms: Some(1000),
+                auth_token: None,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,361 @@
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use slog::slog_debug;
+use stacks_common::debug;
+use stacks_common::types::chainstate::StacksPublicKey;
+use stacks_common::util::hash::to_hex;
+use stacks_common::util::secp256k1::MessageSignature;
+use url::{Host, Url};
+
+use super::{verify_signature, SigningBackend, SigningError};
+
+/// The remote signer's signing endpoint, relative to its base URL
+pub const REMOTE_SIGN_PATH: &str = "/v1/sign";
+const REMOTE_SIGNER_BACKOFF_INITIAL_INTERVAL_MS: u64 = 50;
+const REMOTE_SIGNER_BACKOFF_MAX_INTERVAL_MS: u64 = 1_000;
+
+/// The request body sent to a remote signer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemoteSignRequest {
+    /// The hex-encoded public key of the key to sign with
+    pub public_key: String,
+    /// The hex-encoded 32-byte message hash to sign
+    pub message_hash: String,
+}
+
+/// The response body returned by a remote signer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemoteSignResponse {
+    /// The hex-encoded recoverable signature over the message hash
+    pub signature: MessageSignature,
+}
+
+/// A signing backend which asks a remote signer (e.g. an HSM or KMS gateway) to sign.  Each
+/// request is a `POST` of a `RemoteSignRequest` to `{url}/v1/sign`, which must answer with a
+/// `RemoteSignResponse`.  Connection failures and server errors are retried until the timeout
+/// elapses, and every signature is checked against the configured public key.
+///
+/// `sign()` blocks until the remote signer answers or the timeout elapses.  The signer calls it
+/// from its event loop, so an unresponsive remote signer stalls the handling of every other
+/// event (block proposals, validation responses, ...) for up to the timeout per signature.
+pub struct RemoteSigningBackend {
+    sign_url: String,
+    public_key: StacksPublicKey,
+    auth_token: Option<String>,
+    timeout: Duration,
+    client: Client,
+}
+
+impl Debug for RemoteSigningBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // never log the auth token
+        f.debug_struct("RemoteSigningBackend")
+            .field("sign_url", &self.sign_url)
+            .field(
+                "public_key",
+                &to_hex(&self.public_key.to_bytes_compressed()),
+            )
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl RemoteSigningBackend {
+    /// Create a backend for the remote signer at `url`, which holds the private key of
+    /// `public_key`.  An `auth_token` is only sent over https, or to a loopback address.
+    pub fn new(
+        url: &str,
+        public_key: StacksPublicKey,
+        auth_token: Option<String>,
+        timeout: Duration,
+    ) -> Result<Self, SigningError> {
+        let parsed_url = Url::parse(url)
+            .map_err(|e| SigningError::Remote(format!("invalid remote signer URL {url}: {e}")))?;
+        if auth_token.is_some() && parsed_url.scheme() != "https" && !is_loopback(&parsed_url) {
+            return Err(SigningError::Remote(format!(
+                "refusing to send the auth token to {url} in the clear: use an https URL"
+            )));
+        }
+        let client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| SigningError::Remote(format!("failed to build HTTP client: {e}")))?;
+        Ok(Self {
+            sign_url: format!("{}{REMOTE_SIGN_PATH}", url.trim_end_matches('/')),
+            public_key,
+            auth_token,
+            timeout,
+            client,
+        })
+    }
+
+    fn send_sign_request(
+        &self,
+        request: &RemoteSignRequest,
+    ) -> Result<MessageSignature, backoff::Error<SigningError>> {
+        let mut builder = self.client.post(&self.sign_url).json(request);
+        if let Some(auth_token) = self.auth_token.as_ref() {
+            builder = builder.bearer_auth(auth_token);
+        }
+        let response = builder
+            .send()
+            .map_err(|e| backoff::Error::transient(SigningError::Remote(e.to_string())))?;
+        let status = response.status();
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(backoff::Error::transient(SigningError::Remote(format!(
+                "remote signer responded with {status}"
+            ))));
+        }
+        if !status.is_success() {
+            return Err(backoff::Error::permanent(SigningError::Remote(format!(
+                "remote signer responded with {status}"
+            ))));
+        }
+        let response: RemoteSignResponse = response.json().map_err(|e| {
+            backoff::Error::permanent(SigningError::Remote(format!(
+                "malformed remote signer response: {e}"
+            )))
+        })?;
+        Ok(response.signature)
+    }
+}
+
+/// Does `url` point at this host?
+fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+impl SigningBackend for RemoteSigningBackend {
+    fn public_key(&self) -> StacksPublicKey {
+        self.public_key
+    }
+
+    fn sign(&self, message_hash: &[u8]) -> Result<MessageSignature, SigningError> {
+        let request = RemoteSignRequest {
+            public_key: to_hex(&self.public_key.to_bytes_compressed()),
+            message_hash: to_hex(message_hash),
+        };
+        let notify = |err, dur| {
+            debug!("Remote signer request failed: {err:?}. Next attempt in {dur:?}");
+        };
+        let backoff_timer = backoff::ExponentialBackoffBuilder::new()
+            .with_initial_interval(Duration::from_millis(
+                REMOTE_SIGNER_BACKOFF_INITIAL_INTERVAL_MS,
+            ))
+            .with_max_interval(Duration::from_millis(REMOTE_SIGNER_BACKOFF_MAX_INTERVAL_MS))
+            .with_max_elapsed_time(Some(self.timeout))
+            .build();
+        let signature =
+            backoff::retry_notify(backoff_timer, || self.send_sign_request(&request), notify)
+                .map_err(|e| match e {
+                    backoff::Error::Permanent(e) | backoff::Error::Transient { err: e, .. } => e,
+                })?;
+        verify_signature(&self.public_key, message_hash, &signature)?;
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+
+    use stacks_common::types::chainstate::StacksPrivateKey;
+    use stacks_common::types::PrivateKey;
+    use stacks_common::util::hash::hex_bytes;
+
+    use super::*;
+
+    /// A remote signer which signs with `private_key`, for as long as it is in scope
+    struct MockRemoteSigner {
+        addr: SocketAddr,
+        stop: Arc<AtomicBool>,
+        handle: Option<JoinHandle<()>>,
+    }
+
+    impl MockRemoteSigner {
+        fn start(private_key: StacksPrivateKey, auth_token: Option<&'static str>) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.set_nonblocking(true).unwrap();
+            let addr = listener.local_addr().unwrap();
+            let stop = Arc::new(AtomicBool::new(false));
+            let thread_stop = stop.clone();
+            let handle = std::thread::spawn(move || {
+                while !thread_stop.load(Ordering::SeqCst) {
+                    let Ok((stream, _)) = listener.accept() else {
+                        std::thread::sleep(Duration::from_millis(10));
+                        continue;
+                    };
+                    stream.set_nonblocking(false).unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let mut content_length = 0;
+                    let mut authorized = auth_token.is_none();
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        let line = line.trim_end();
+                        if line.is_empty() {
+                            break;
+                        }
+                        let Some((name, value)) = line.split_once(':') else {
+                            continue;
+                        };
+                        let value = value.trim();
+                        match name.to_lowercase().as_str() {
+                            "content-length" => content_length = value.parse().unwrap(),
+                            "authorization" => {
+                                authorized |= auth_token
+                                    .is_some_and(|token| value == format!("Bearer {token}"))
+                            }
+                            _ => {}
+                        }
+                    }
+                    let mut body = vec![0u8; content_length];
+                    reader.read_exact(&mut body).unwrap();
+
+                    let response = if authorized {
+                        let request: RemoteSignRequest = serde_json::from_slice(&body).unwrap();
+                        let message_hash = hex_bytes(&request.message_hash).unwrap();
+                        let signature = private_key.sign(&message_hash).unwrap();
+                        let body =
+                            serde_json::to_string(&RemoteSignResponse { signature }).unwrap();
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                            body.len()
+                        )
+                    } else {
+                        "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                            .to_string()
+                    };
+                    reader.get_mut().write_all(response.as_bytes()).unwrap();
+                }
+            });
+            Self {
+                addr,
+                stop,
+                handle: Some(handle),
+            }
+        }
+
+        fn url(&self) -> String {
+            format!("http://{}", self.addr)
+        }
+    }
+
+    impl Drop for MockRemoteSigner {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::SeqCst);
+            if let Some(handle) = self.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+
+    #[test]
+    fn remote_backend_signs() {
+        let private_key = StacksPrivateKey::random();
+        let public_key = StacksPublicKey::from_private(&private_key);
+        let signer = MockRemoteSigner::start(private_key, Some("secret"));
+        let backend = RemoteSigningBackend::new(
+            &signer.url(),
+            public_key,
+            Some("secret".into()),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert!(!format!("{backend:?}").contains("secret"));
+
+        let message_hash = [0x07; 32];
+        let signature = backend.sign(&message_hash).unwrap();
+        assert_eq!(signature, private_key.sign(&message_hash).unwrap());
+    }
+
+    #[test]
+    fn remote_backend_rejects_bad_signers() {
+        let private_key = StacksPrivateKey::random();
+        let public_key = StacksPublicKey::from_private(&private_key);
+        let message_hash = [0x07; 32];
+
+        // unauthorized requests are not retried
+        let signer = MockRemoteSigner::start(private_key, Some("secret"));
+        let backend = RemoteSigningBackend::new(
+            &signer.url(),
+            public_key,
+            Some("wrong".into()),
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert!(matches!(
+            backend.sign(&message_hash),
+            Err(SigningError::Remote(_))
+        ));
+
+        // signatures by another key are refused
+        let other_signer = MockRemoteSigner::start(StacksPrivateKey::random(), None);
+        let backend = RemoteSigningBackend::new(
+            &other_signer.url(),
+            public_key,
+            None,
+            Duration::from_secs(5),
+        )
+        .unwrap();
+        assert!(matches!(
+            backend.sign(&message_hash),
+            Err(SigningError::PublicKeyMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn remote_backend_sends_auth_token_only_over_https() {
+        let public_key = StacksPublicKey::from_private(&StacksPrivateKey::random());
+        let timeout = Duration::from_secs(1);
+        let new_backend = |url: &str, auth_token: Option<&str>| {
+            RemoteSigningBackend::new(url, public_key, auth_token.map(String::from), timeout)
+        };
+
+        assert!(matches!(
+            new_backend("http://10.0.0.1:30100", Some("secret")),
+            Err(SigningError::Remote(_))
+        ));
+        assert!(matches!(
+            new_backend("http://signer.example.com", Some("secret")),
+            Err(SigningError::Remote(_))
+        ));
+        new_backend("https://signer.example.com", Some("secret")).unwrap();
+        new_backend("http://127.0.0.1:30100", Some("secret")).unwrap();
+        new_backend("http://[::1]:30100", Some("secret")).unwrap();
+        new_backend("http://localhost:30100", Some("secret")).unwrap();
+        new_backend("http://10.0.0.1:30100", None).unwrap();
+        assert!(matches!(
+            new_backend("not a url", None),
+            Err(SigningError::Remote(_))
+        ));
+    }
+}
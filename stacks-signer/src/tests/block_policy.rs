@@ -0,0 +1,428 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::channel;
+use std::thread::{spawn, JoinHandle};
+
+use blockstack_lib::chainstate::nakamoto::{NakamotoBlock, NakamotoBlockHeader};
+use blockstack_lib::chainstate::stacks::{
+    StacksTransaction, TokenTransferMemo, TransactionAuth, TransactionPayload, TransactionVersion,
+};
+use blockstack_lib::net::api::postblock_proposal::{BlockValidateOk, BlockValidateResponse};
+use clarity::codec::StacksMessageCodec;
+use clarity::vm::costs::ExecutionCost;
+use clarity::vm::types::PrincipalData;
+use libsigner::v0::messages::{BlockResponse, RejectReason, SignerMessage};
+use libsigner::{BlockProposal, BlockProposalData, SignerEvent};
+use libstackerdb::{StackerDBChunkAckData, StackerDBChunkData};
+use stacks_common::bitvec::BitVec;
+use stacks_common::types::chainstate::{
+    BurnchainHeaderHash, ConsensusHash, StacksAddress, StacksPrivateKey, StacksPublicKey,
+};
+use stacks_common::util::get_epoch_time_secs;
+use stacks_common::util::hash::Hash160;
+
+use crate::chainstate::{SortitionMinerStatus, SortitionState, SortitionsView};
+use crate::client::tests::{
+    build_get_pox_data_response, generate_signer_config, mock_server_random,
+};
+use crate::client::StacksClient;
+use crate::config::GlobalConfig;
+use crate::decision_log::{DecisionOutcome, DecisionQuery};
+use crate::policy::BlockPolicy;
+use crate::signerdb::{BlockInfo, BlockState};
+use crate::v0::signer::Signer;
+use crate::Signer as _;
+
+/// The burn block height at which the test blocks are proposed, in epoch 3.0
+const BURN_BLOCK_HEIGHT: u64 = 7;
+
+/// The epoch 3.0 block limit reported by the mock node
+const BLOCK_LIMIT: ExecutionCost = ExecutionCost {
+    write_length: 1000,
+    write_count: 1000,
+    read_length: 1000,
+    read_count: 1000,
+    runtime: 1000,
+};
+
+/// Serve the given responses, one per connection, returning the requests received
+fn serve_responses(server: TcpListener, responses: Vec<String>) -> JoinHandle<Vec<String>> {
+    spawn(move || {
+        responses
+            .into_iter()
+            .map(|response| {
+                let mut stream = server.accept().unwrap().0;
+                let mut request = vec![];
+                let mut buf = [0u8; 4096];
+                // Read the headers, then as much of the body as they announce
+                let body_start = loop {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(i) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break i + 4;
+                    }
+                };
+                let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+                let content_length = headers
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .map_or(0, |len| len.trim().parse::<usize>().unwrap());
+                while request.len() < body_start + content_length {
+                    let n = stream.read(&mut buf).unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+                String::from_utf8(request).unwrap()
+            })
+            .collect()
+    })
+}
+
+/// The mock node's response to a stackerdb chunk
+fn chunk_ack_response() -> String {
+    let ack = StackerDBChunkAckData {
+        accepted: true,
+        reason: None,
+        metadata: None,
+        code: None,
+    };
+    format!(
+        "HTTP/1.1 200 OK\n\n{}",
+        serde_json::to_string(&ack).unwrap()
+    )
+}
+
+/// Decode the block response which the signer sent to stackerdb
+fn sent_block_response(request: &str) -> BlockResponse {
+    assert!(request.starts_with("POST /v2/stackerdb/"), "{request}");
+    let body = &request[request.find("\r\n\r\n").unwrap() + 4..];
+    let chunk: StackerDBChunkData = serde_json::from_str(body).unwrap();
+    let SignerMessage::BlockResponse(response) =
+        SignerMessage::consensus_deserialize(&mut chunk.data.as_slice()).unwrap()
+    else {
+        panic!("Expected a block response");
+    };
+    response
+}
+
+/// A block proposal for a block which is not the first in its tenure: it holds a single STX
+/// transfer from the given sender
+fn make_proposal(reward_cycle: u64, sender: &StacksPrivateKey) -> BlockProposal {
+    let recipient = StacksAddress::p2pkh(false, &StacksPublicKey::from_private(sender));
+    let transfer = StacksTransaction::new(
+        TransactionVersion::Testnet,
+        TransactionAuth::from_p2pkh(sender).unwrap(),
+        TransactionPayload::TokenTransfer(
+            PrincipalData::from(recipient),
+            1,
+            TokenTransferMemo([0; 34]),
+        ),
+    );
+    let mut header = NakamotoBlockHeader::empty();
+    header.timestamp = get_epoch_time_secs();
+    header.chain_length = rand::random::<u32>().into();
+    let block = NakamotoBlock {
+        header,
+        txs: vec![transfer],
+    };
+    assert!(block.get_tenure_change_tx_payload().is_none());
+    BlockProposal {
+        block,
+        burn_height: BURN_BLOCK_HEIGHT,
+        reward_cycle,
+        block_proposal_data: BlockProposalData::empty(),
+    }
+}
+
+/// Build a signer with the given block policy, talking to a mock node
+fn setup_signer(policy: &str) -> (StacksClient, Signer, TcpListener) {
+    let mut config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+    let (server, mock_server_addr) = mock_server_random();
+    config.node_host = mock_server_addr.to_string();
+    config.db_path = std::env::temp_dir().join(format!(
+        "stacks-signer-policy-test-{}.sqlite",
+        rand::random::<u64>()
+    ));
+    let mut signer_config = generate_signer_config(&config, 5);
+    // The signer db stores reward cycles as signed integers
+    signer_config.reward_cycle %= i64::MAX as u64;
+    signer_config.block_policy = Some(BlockPolicy::load_from_str(policy).unwrap());
+    (
+        StacksClient::from(&config),
+        Signer::from(signer_config),
+        server,
+    )
+}
+
+/// Process the node's validation of the given block, with the given tenure cost
+fn process_validate_ok(
+    signer: &mut Signer,
+    stacks_client: &StacksClient,
+    block_info: &BlockInfo,
+    tenure_cost: ExecutionCost,
+) {
+    let reward_cycle = signer.reward_cycle;
+    let (res_send, _res_recv) = channel();
+    let validate_ok =
+        SignerEvent::BlockValidationResponse(BlockValidateResponse::Ok(BlockValidateOk {
+            signer_signature_hash: block_info.signer_signature_hash(),
+            cost: tenure_cost,
+            size: 0,
+            validation_time_ms: 0,
+        }));
+    signer.process_event(
+        stacks_client,
+        &mut None,
+        Some(&validate_ok),
+        &res_send,
+        reward_cycle,
+    );
+}
+
+#[test]
+fn tenure_cost_rule_rejects_small_block_late_in_tenure() {
+    let (stacks_client, mut signer, server) = setup_signer("max_tenure_cost_percentage = 50");
+    let (_, mut pox_info) = build_get_pox_data_response(None, None, Some(0), Some(5));
+    for epoch in pox_info.epochs.iter_mut() {
+        epoch.block_limit = BLOCK_LIMIT;
+    }
+    let pox_response = format!(
+        "HTTP/1.1 200 OK\n\n{}",
+        serde_json::to_string(&pox_info).unwrap()
+    );
+    let requests = serve_responses(
+        server,
+        vec![pox_response, chunk_ack_response(), chunk_ack_response()],
+    );
+
+    // A block holding a single transfer, which brings its tenure to 60% of the block limit
+    let late_block = BlockInfo::from(make_proposal(
+        signer.reward_cycle,
+        &StacksPrivateKey::random(),
+    ));
+    signer.signer_db.insert_block(&late_block).unwrap();
+    process_validate_ok(
+        &mut signer,
+        &stacks_client,
+        &late_block,
+        ExecutionCost {
+            runtime: 600,
+            ..ExecutionCost::ZERO
+        },
+    );
+    let stored = signer
+        .signer_db
+        .block_lookup(&late_block.signer_signature_hash())
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.state, BlockState::LocallyRejected);
+
+    // A block which leaves its tenure under the limit is accepted. The block limit of the epoch
+    // is cached, so the node is only asked for it once.
+    let early_block = BlockInfo::from(make_proposal(
+        signer.reward_cycle,
+        &StacksPrivateKey::random(),
+    ));
+    signer.signer_db.insert_block(&early_block).unwrap();
+    process_validate_ok(
+        &mut signer,
+        &stacks_client,
+        &early_block,
+        ExecutionCost {
+            runtime: 400,
+            ..ExecutionCost::ZERO
+        },
+    );
+    let stored = signer
+        .signer_db
+        .block_lookup(&early_block.signer_signature_hash())
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.state, BlockState::LocallyAccepted);
+
+    let requests = requests.join().unwrap();
+    assert!(requests[0].starts_with("GET /v2/pox"), "{}", requests[0]);
+    let BlockResponse::Rejected(rejection) = sent_block_response(&requests[1]) else {
+        panic!("Expected a rejection");
+    };
+    assert_eq!(
+        rejection.signer_signature_hash,
+        late_block.signer_signature_hash()
+    );
+    let RejectReason::PolicyViolation(rule) = rejection.response_data.reject_reason else {
+        panic!(
+            "Expected a policy violation, got {:?}",
+            rejection.response_data.reject_reason
+        );
+    };
+    assert!(rule.starts_with("max_tenure_cost_percentage"), "{rule}");
+    assert!(matches!(
+        sent_block_response(&requests[2]),
+        BlockResponse::Accepted(accepted) if accepted.signer_signature_hash == early_block.signer_signature_hash()
+    ));
+}
+
+/// Process a miner's proposal of a block which breaks the given block policy. The block passes
+/// the signer's checks against its sortition view. Returns the request made to the mock node.
+fn process_proposal_against_policy(
+    policy: &str,
+    response: String,
+) -> (Signer, BlockProposal, String) {
+    let sender = StacksPrivateKey::random();
+    let sender_address = StacksAddress::p2pkh(false, &StacksPublicKey::from_private(&sender));
+    let (stacks_client, mut signer, server) = setup_signer(&format!(
+        "{policy}\nblocked_principals = [\"{sender_address}\"]"
+    ));
+    let requests = serve_responses(server, vec![response]);
+
+    let miner_sk = StacksPrivateKey::random();
+    let miner_pk = StacksPublicKey::from_private(&miner_sk);
+    let mut block_proposal = make_proposal(signer.reward_cycle, &sender);
+    block_proposal.block.header.consensus_hash = ConsensusHash([1; 20]);
+    block_proposal.block.header.pox_treatment = BitVec::ones(1).unwrap();
+    block_proposal.block.header.sign_miner(&miner_sk).unwrap();
+    let mut sortition_state = Some(SortitionsView {
+        cur_sortition: SortitionState {
+            miner_pkh: Hash160::from_node_public_key(&miner_pk),
+            miner_pubkey: None,
+            prior_sortition: ConsensusHash([0; 20]),
+            parent_tenure_id: ConsensusHash([0; 20]),
+            consensus_hash: ConsensusHash([1; 20]),
+            miner_status: SortitionMinerStatus::Valid,
+            burn_header_timestamp: get_epoch_time_secs(),
+            burn_block_hash: BurnchainHeaderHash([1; 32]),
+        },
+        last_sortition: None,
+        config: signer.proposal_config.clone(),
+    });
+
+    let reward_cycle = signer.reward_cycle;
+    let (res_send, _res_recv) = channel();
+    let proposal =
+        SignerEvent::MinerMessages(vec![SignerMessage::BlockProposal(block_proposal.clone())]);
+    signer.process_event(
+        &stacks_client,
+        &mut sortition_state,
+        Some(&proposal),
+        &res_send,
+        reward_cycle,
+    );
+    let mut requests = requests.join().unwrap();
+    (signer, block_proposal, requests.remove(0))
+}
+
+#[test]
+fn policy_violation_rejects_block_proposal() {
+    let (signer, block_proposal, request) =
+        process_proposal_against_policy("", chunk_ack_response());
+    let signer_signature_hash = block_proposal.block.header.signer_signature_hash();
+
+    // The proposal is rejected without being validated by the node, and is not stored
+    let BlockResponse::Rejected(rejection) = sent_block_response(&request) else {
+        panic!("Expected a rejection");
+    };
+    assert_eq!(rejection.signer_signature_hash, signer_signature_hash);
+    let RejectReason::PolicyViolation(rule) = rejection.response_data.reject_reason else {
+        panic!(
+            "Expected a policy violation, got {:?}",
+            rejection.response_data.reject_reason
+        );
+    };
+    assert!(rule.starts_with("blocked_principals"), "{rule}");
+    assert!(signer
+        .signer_db
+        .block_lookup(&signer_signature_hash)
+        .unwrap()
+        .is_none());
+
+    // The decision log records the rejection, and that it was broadcast
+    let decisions = signer
+        .signer_db
+        .get_block_decisions(&DecisionQuery::BlockHash(signer_signature_hash), 10)
+        .unwrap();
+    assert_eq!(decisions.len(), 1);
+    assert_eq!(decisions[0].outcome, DecisionOutcome::Rejected);
+    assert!(decisions[0].broadcast);
+    assert!(decisions[0].error.is_none());
+}
+
+#[test]
+fn stale_and_other_cycle_proposals_are_logged_as_ignored() {
+    let (stacks_client, mut signer, _server) = setup_signer("");
+    let reward_cycle = signer.reward_cycle;
+    let (res_send, _res_recv) = channel();
+    let sender = StacksPrivateKey::random();
+
+    let miner_sk = StacksPrivateKey::random();
+    let mut other_cycle = make_proposal(reward_cycle + 1, &sender);
+    other_cycle.block.header.sign_miner(&miner_sk).unwrap();
+    let mut stale = make_proposal(reward_cycle, &sender);
+    stale.block.header.timestamp = 0;
+    stale.block.header.sign_miner(&miner_sk).unwrap();
+    let proposals = SignerEvent::MinerMessages(vec![
+        SignerMessage::BlockProposal(other_cycle.clone()),
+        SignerMessage::BlockProposal(stale.clone()),
+    ]);
+    signer.process_event(
+        &stacks_client,
+        &mut None,
+        Some(&proposals),
+        &res_send,
+        reward_cycle,
+    );
+
+    for (proposal, error) in [(other_cycle, "reward cycle"), (stale, "secs old")] {
+        let signer_signature_hash = proposal.block.header.signer_signature_hash();
+        let decisions = signer
+            .signer_db
+            .get_block_decisions(&DecisionQuery::BlockHash(signer_signature_hash), 10)
+            .unwrap();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions[0].outcome, DecisionOutcome::Ignored);
+        assert!(!decisions[0].broadcast);
+        assert!(decisions[0].error.as_ref().unwrap().contains(error));
+        // Neither is stored or validated
+        assert!(signer
+            .signer_db
+            .block_lookup(&signer_signature_hash)
+            .unwrap()
+            .is_none());
+    }
+    assert!(signer.submitted_block_proposal.is_none());
+}
+
+#[test]
+fn policy_dry_run_accepts_block_proposal() {
+    let (signer, block_proposal, request) =
+        process_proposal_against_policy("dry_run = true", "HTTP/1.1 200 OK\n\n".to_string());
+    let signer_signature_hash = block_proposal.block.header.signer_signature_hash();
+
+    // The violation is only logged. The proposal is submitted to the node for validation, as if
+    // there were no policy.
+    assert!(request.starts_with("POST /v3/block_proposal"), "{request}");
+    let stored = signer
+        .signer_db
+        .block_lookup(&signer_signature_hash)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.state, BlockState::Unprocessed);
+    assert_eq!(
+        signer.submitted_block_proposal.map(|(hash, _)| hash),
+        Some(signer_signature_hash)
+    );
+}
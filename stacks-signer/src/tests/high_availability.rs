@@ -0,0 +1,241 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::mpsc::channel;
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+use blockstack_lib::chainstate::nakamoto::{NakamotoBlock, NakamotoBlockHeader};
+use blockstack_lib::net::api::postblock_proposal::{BlockValidateOk, BlockValidateResponse};
+use clarity::util::hash::MerkleHashFunc;
+use clarity::vm::costs::ExecutionCost;
+use libsigner::v0::messages::{
+    BlockAccepted, BlockResponse, BlockResponseData, SignerMessage, SignerMessageMetadata,
+};
+use libsigner::{BlockProposal, BlockProposalData, SignerEvent};
+use stacks_common::util::get_epoch_time_secs;
+
+use crate::client::tests::{generate_signer_config, mock_server_random, write_response};
+use crate::client::StacksClient;
+use crate::config::{GlobalConfig, HighAvailabilityConfig};
+use crate::decision_log::DecisionQuery;
+use crate::signerdb::{BlockInfo, BlockState};
+use crate::v0::signer::Signer;
+use crate::Signer as _;
+
+/// Build the active/standby pair, sharing one signer db
+fn setup_signer_pair(lease_duration: Duration, num_signers: u32) -> (GlobalConfig, Signer, Signer) {
+    let mut config = GlobalConfig::load_from_file("./src/tests/conf/signer-0.toml").unwrap();
+    config.db_path = std::env::temp_dir().join(format!(
+        "stacks-signer-ha-test-{}.sqlite",
+        rand::random::<u64>()
+    ));
+    let mut signer_config = generate_signer_config(&config, num_signers);
+    // The signer db stores reward cycles as signed integers
+    signer_config.reward_cycle %= i64::MAX as u64;
+    let make_signer = |instance_id: &str| {
+        let mut signer_config = signer_config.clone();
+        signer_config.high_availability = Some(HighAvailabilityConfig {
+            instance_id: instance_id.to_string(),
+            lease_duration,
+        });
+        Signer::from(signer_config)
+    };
+    let signer_a = make_signer("signer-a");
+    let signer_b = make_signer("signer-b");
+    (config, signer_a, signer_b)
+}
+
+#[test]
+fn standby_does_not_respond_and_takes_over_after_lease_expiry() {
+    let lease_duration = Duration::from_secs(1);
+    let (mut config, mut signer_a, mut signer_b) = setup_signer_pair(lease_duration, 5);
+    let (server, mock_server_addr) = mock_server_random();
+    config.node_host = mock_server_addr.to_string();
+    let stacks_client = StacksClient::from(&config);
+    let reward_cycle = signer_a.reward_cycle;
+    let (res_send, _res_recv) = channel();
+
+    // The first instance to process an event becomes the active signer
+    signer_a.process_event(&stacks_client, &mut None, None, &res_send, reward_cycle);
+    assert!(signer_a.is_active);
+    signer_b.process_event(&stacks_client, &mut None, None, &res_send, reward_cycle);
+    assert!(!signer_b.is_active);
+
+    // A block awaiting validation by the active signer
+    let mut header = NakamotoBlockHeader::empty();
+    header.timestamp = get_epoch_time_secs();
+    let block_info = BlockInfo::from(BlockProposal {
+        block: NakamotoBlock {
+            header,
+            txs: vec![],
+        },
+        burn_height: 7,
+        reward_cycle,
+        block_proposal_data: BlockProposalData::empty(),
+    });
+    let signer_signature_hash = block_info.signer_signature_hash();
+    signer_a.signer_db.insert_block(&block_info).unwrap();
+    signer_a
+        .signer_db
+        .insert_pending_block_validation(&signer_signature_hash, get_epoch_time_secs())
+        .unwrap();
+
+    // The standby ignores the validation result. Had it responded, it would have signed and
+    // broadcast the block, and recorded the decision.
+    let validate_ok =
+        SignerEvent::BlockValidationResponse(BlockValidateResponse::Ok(BlockValidateOk {
+            signer_signature_hash,
+            cost: ExecutionCost::ZERO,
+            size: 0,
+            validation_time_ms: 0,
+        }));
+    signer_b.process_event(
+        &stacks_client,
+        &mut None,
+        Some(&validate_ok),
+        &res_send,
+        reward_cycle,
+    );
+    assert!(!signer_b.is_active);
+    let stored = signer_b
+        .signer_db
+        .block_lookup(&signer_signature_hash)
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.state, BlockState::Unprocessed);
+    assert!(stored.valid.is_none());
+    assert!(signer_b
+        .signer_db
+        .get_block_decisions(&DecisionQuery::BlockHash(signer_signature_hash), 10)
+        .unwrap()
+        .is_empty());
+
+    // The active signer stops renewing its lease. Once it expires, the standby takes over, and
+    // submits the validation which the old active signer left pending.
+    sleep(lease_duration + Duration::from_millis(100));
+    let h = spawn(move || write_response(server, b"HTTP/1.1 200 OK\n\n"));
+    signer_b.process_event(&stacks_client, &mut None, None, &res_send, reward_cycle);
+    let request_bytes = h.join().unwrap();
+    assert!(signer_b.is_active);
+    assert!(String::from_utf8_lossy(&request_bytes).starts_with("POST /v3/block_proposal"));
+    assert_eq!(
+        signer_b.submitted_block_proposal.map(|(hash, _)| hash),
+        Some(signer_signature_hash)
+    );
+
+    // The old active signer is now locked out
+    signer_a.process_event(&stacks_client, &mut None, None, &res_send, reward_cycle);
+    assert!(!signer_a.is_active);
+}
+
+#[test]
+fn standby_does_not_overwrite_block_state() {
+    // A single signer, so that its own signature reaches the signing threshold
+    let (mut config, mut signer_a, mut signer_b) = setup_signer_pair(Duration::from_secs(30), 1);
+    let (_server, mock_server_addr) = mock_server_random();
+    config.node_host = mock_server_addr.to_string();
+    let stacks_client = StacksClient::from(&config);
+    let reward_cycle = signer_a.reward_cycle;
+    let (res_send, _res_recv) = channel();
+
+    signer_a.process_event(&stacks_client, &mut None, None, &res_send, reward_cycle);
+    assert!(signer_a.is_active);
+    signer_b.process_event(&stacks_client, &mut None, None, &res_send, reward_cycle);
+    assert!(!signer_b.is_active);
+
+    let mut header = NakamotoBlockHeader::empty();
+    header.timestamp = get_epoch_time_secs();
+    let mut block_info = BlockInfo::from(BlockProposal {
+        block: NakamotoBlock {
+            header,
+            txs: vec![],
+        },
+        burn_height: 7,
+        reward_cycle,
+        block_proposal_data: BlockProposalData::empty(),
+    });
+    let signer_signature_hash = block_info.signer_signature_hash();
+    signer_a.signer_db.insert_block(&block_info).unwrap();
+    signer_a
+        .signer_db
+        .insert_pending_block_validation(&signer_signature_hash, get_epoch_time_secs())
+        .unwrap();
+
+    // The active signer validates and signs the block
+    block_info.valid = Some(true);
+    block_info.mark_locally_accepted(false).unwrap();
+    signer_a.signer_db.insert_block(&block_info).unwrap();
+
+    // Meanwhile, the standby sees the signature which completes the signing threshold, and then
+    // the block being processed by the node
+    let accepted = BlockAccepted {
+        signer_signature_hash,
+        signature: config
+            .signing_backend
+            .sign(signer_signature_hash.bits())
+            .unwrap(),
+        metadata: SignerMessageMetadata::default(),
+        response_data: BlockResponseData::empty(),
+    };
+    let signer_messages = SignerEvent::SignerMessages(
+        u32::try_from(reward_cycle % 2).unwrap(),
+        vec![SignerMessage::BlockResponse(BlockResponse::Accepted(
+            accepted,
+        ))],
+    );
+    signer_b.process_event(
+        &stacks_client,
+        &mut None,
+        Some(&signer_messages),
+        &res_send,
+        reward_cycle,
+    );
+    let new_block = SignerEvent::NewBlock {
+        block_hash: signer_signature_hash,
+        block_height: block_info.block.header.chain_length,
+    };
+    signer_b.process_event(
+        &stacks_client,
+        &mut None,
+        Some(&new_block),
+        &res_send,
+        reward_cycle,
+    );
+    assert!(!signer_b.is_active);
+
+    // The standby stored the signature for the active signer to use, but left the block state,
+    // and its pending validation, to the active signer
+    assert_eq!(
+        signer_b
+            .signer_db
+            .get_block_signatures(&signer_signature_hash)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(
+        signer_a
+            .signer_db
+            .block_lookup(&signer_signature_hash)
+            .unwrap()
+            .unwrap(),
+        block_info
+    );
+    assert!(signer_a
+        .signer_db
+        .has_pending_block_validation(&signer_signature_hash)
+        .unwrap());
+}
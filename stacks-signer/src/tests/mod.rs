@@ -1 +1,3 @@
+mod block_policy;
 mod chainstate;
+mod high_availability;
@@ -14,7 +14,9 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::ops::Range;
 use std::sync::mpsc::Sender;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use blockstack_lib::chainstate::nakamoto::{NakamotoBlock, NakamotoBlockHeader};
@@ -22,26 +24,30 @@ use blockstack_lib::net::api::postblock_proposal::{
     BlockValidateOk, BlockValidateReject, BlockValidateResponse, TOO_MANY_REQUESTS_STATUS,
 };
 use blockstack_lib::util_lib::db::Error as DBError;
-use clarity::types::chainstate::StacksPrivateKey;
-use clarity::types::{PrivateKey, StacksEpochId};
+use clarity::types::StacksEpochId;
 use clarity::util::hash::{MerkleHashFunc, Sha512Trunc256Sum};
 use clarity::util::secp256k1::Secp256k1PublicKey;
+use clarity::vm::costs::ExecutionCost;
 use libsigner::v0::messages::{
     BlockAccepted, BlockRejection, BlockResponse, MessageSlotID, MockProposal, MockSignature,
     RejectReason, RejectReasonPrefix, SignerMessage,
 };
 use libsigner::{BlockProposal, SignerEvent};
+use libstackerdb::StackerDBChunkAckData;
 use slog::{slog_debug, slog_error, slog_info, slog_warn};
 use stacks_common::types::chainstate::StacksAddress;
-use stacks_common::util::get_epoch_time_secs;
 use stacks_common::util::secp256k1::MessageSignature;
+use stacks_common::util::{get_epoch_time_ms, get_epoch_time_secs};
 use stacks_common::{debug, error, info, warn};
 
 use crate::chainstate::{ProposalEvalConfig, SortitionMinerStatus, SortitionsView};
 use crate::client::{ClientError, SignerSlotID, StackerDB, StacksClient};
-use crate::config::{SignerConfig, SignerConfigMode};
+use crate::config::{HighAvailabilityConfig, SignerConfig, SignerConfigMode};
+use crate::decision_log::{BlockDecision, DecisionOutcome, DECISION_LOG_RETENTION_CYCLES};
+use crate::policy::BlockPolicy;
 use crate::runloop::SignerResult;
 use crate::signerdb::{BlockInfo, BlockState, SignerDb};
+use crate::signing::SigningBackend;
 use crate::Signer as SignerTrait;
 
 /// Signer running mode (whether dry-run or real)
@@ -61,12 +67,12 @@ pub enum SignerMode {
 /// The stacks signer registered for the reward cycle
 #[derive(Debug)]
 pub struct Signer {
-    /// The private key of the signer
+    /// The backend which signs with the signer's private key
     #[cfg(any(test, feature = "testing"))]
-    pub private_key: StacksPrivateKey,
+    pub signing_backend: Arc<dyn SigningBackend>,
     #[cfg(not(any(test, feature = "testing")))]
-    /// The private key of the signer
-    private_key: StacksPrivateKey,
+    /// The backend which signs with the signer's private key
+    signing_backend: Arc<dyn SigningBackend>,
     /// The stackerdb client
     pub stackerdb: StackerDB<MessageSlotID>,
     /// Whether the signer is a mainnet signer or not
@@ -92,6 +98,16 @@ pub struct Signer {
     pub submitted_block_proposal: Option<(Sha512Trunc256Sum, Instant)>,
     /// Maximum age of a block proposal in seconds before it is dropped without processing
     pub block_proposal_max_age_secs: u64,
+    /// The high-availability settings, if this signer is one of an active/standby pair
+    pub high_availability: Option<HighAvailabilityConfig>,
+    /// Whether this instance held the high-availability lease when it last checked.
+    /// Always true outside of high-availability mode.
+    pub is_active: bool,
+    /// The operator's block policy, if any
+    pub block_policy: Option<BlockPolicy>,
+    /// The block limit last fetched for the block policy, and the burn block heights of the
+    /// epoch which it applies to
+    pub epoch_block_limit: Option<(Range<u64>, ExecutionCost)>,
 }
 
 impl std::fmt::Display for SignerMode {
@@ -144,7 +160,10 @@ impl SignerTrait<SignerMessage> for Signer {
         if event_parity == Some(other_signer_parity) {
             return;
         }
-        self.check_submitted_block_proposal();
+        let is_active = self.refresh_lease(stacks_client);
+        if is_active {
+            self.check_submitted_block_proposal(sortition_state.as_ref());
+        }
         debug!("{self}: Processing event: {event:?}");
         let Some(event) = event else {
             // No event. Do nothing.
@@ -164,6 +183,11 @@ impl SignerTrait<SignerMessage> for Signer {
         }
         match event {
             SignerEvent::BlockValidationResponse(block_validate_response) => {
+                if !is_active {
+                    // The active instance records the outcome in the shared signer db
+                    debug!("{self}: On standby. Ignoring block proposal result.");
+                    return;
+                }
                 debug!("{self}: Received a block proposal result from the stacks node...");
                 self.handle_block_validate_response(
                     stacks_client,
@@ -196,6 +220,10 @@ impl SignerTrait<SignerMessage> for Signer {
                             if self.test_ignore_all_block_proposals(block_proposal) {
                                 continue;
                             }
+                            if !is_active {
+                                self.keep_standby_warm(stacks_client, sortition_state);
+                                continue;
+                            }
                             let Some(miner_pubkey) = block_proposal.block.header.recover_miner_pk()
                             else {
                                 warn!("{self}: Failed to recover miner pubkey";
@@ -226,6 +254,9 @@ impl SignerTrait<SignerMessage> for Signer {
                             stacks_client.post_block_until_ok(self, b);
                         }
                         SignerMessage::MockProposal(mock_proposal) => {
+                            if !is_active {
+                                continue;
+                            }
                             let epoch = match stacks_client.get_node_epoch() {
                                 Ok(epoch) => epoch,
                                 Err(e) => {
@@ -279,6 +310,11 @@ impl SignerTrait<SignerMessage> for Signer {
                     "block_hash" => %block_hash,
                     "block_height" => block_height
                 );
+                if !is_active {
+                    // The active instance records the block's new state in the shared signer db
+                    debug!("{self}: On standby. Leaving the block state to the active signer.");
+                    return;
+                }
                 if let Ok(Some(mut block_info)) = self
                     .signer_db
                     .block_lookup(block_hash)
@@ -323,10 +359,24 @@ impl From<SignerConfig> for Signer {
 
         let signer_db =
             SignerDb::new(&signer_config.db_path).expect("Failed to connect to signer Db");
+        let prune_before = signer_config
+            .reward_cycle
+            .saturating_sub(DECISION_LOG_RETENTION_CYCLES - 1);
+        match signer_db.prune_block_decisions(prune_before) {
+            Ok(0) => {}
+            Ok(pruned) => debug!(
+                "Reward cycle #{}: Pruned {pruned} decision log entries from before reward cycle {prune_before}",
+                signer_config.reward_cycle
+            ),
+            Err(e) => warn!(
+                "Reward cycle #{}: Failed to prune the decision log: {e:?}",
+                signer_config.reward_cycle
+            ),
+        }
         let proposal_config = ProposalEvalConfig::from(&signer_config);
 
         Self {
-            private_key: signer_config.stacks_private_key,
+            signing_backend: signer_config.signing_backend.clone(),
             stackerdb,
             mainnet: signer_config.mainnet,
             mode,
@@ -339,15 +389,22 @@ impl From<SignerConfig> for Signer {
             submitted_block_proposal: None,
             block_proposal_validation_timeout: signer_config.block_proposal_validation_timeout,
             block_proposal_max_age_secs: signer_config.block_proposal_max_age_secs,
+            is_active: signer_config.high_availability.is_none(),
+            high_availability: signer_config.high_availability,
+            block_policy: signer_config.block_policy,
+            epoch_block_limit: None,
         }
     }
 }
 
 impl Signer {
     /// Determine this signers response to a proposed block
-    /// Returns a BlockResponse if we have already validated the block
+    /// Returns a BlockResponse if we have already validated the block, or an error if we failed to sign it
     /// Returns None otherwise
-    fn determine_response(&mut self, block_info: &BlockInfo) -> Option<BlockResponse> {
+    fn determine_response(
+        &mut self,
+        block_info: &BlockInfo,
+    ) -> Option<Result<BlockResponse, String>> {
         let valid = block_info.valid?;
         let response = if valid {
             debug!("{self}: Accepting block {}", block_info.block.block_id());
@@ -359,13 +416,13 @@ impl Signer {
         Some(response)
     }
 
-    /// Create a block acceptance response for a block
-    pub fn create_block_acceptance(&self, block: &NakamotoBlock) -> BlockResponse {
+    /// Create a block acceptance response for a block. Fails if the signing backend fails to sign it.
+    pub fn create_block_acceptance(&self, block: &NakamotoBlock) -> Result<BlockResponse, String> {
         let signature = self
-            .private_key
+            .signing_backend
             .sign(block.header.signer_signature_hash().bits())
-            .expect("Failed to sign block");
-        BlockResponse::accepted(
+            .map_err(|e| e.to_string())?;
+        Ok(BlockResponse::accepted(
             block.header.signer_signature_hash(),
             signature,
             self.signer_db.calculate_tenure_extend_timestamp(
@@ -375,18 +432,19 @@ impl Signer {
                 block,
                 true,
             ),
-        )
+        ))
     }
-    /// Create a block rejection response for a block with the given reject code
+    /// Create a block rejection response for a block with the given reject code. Fails if the
+    /// signing backend fails to sign it.
     pub fn create_block_rejection(
         &self,
         reject_reason: RejectReason,
         block: &NakamotoBlock,
-    ) -> BlockResponse {
+    ) -> Result<BlockResponse, String> {
         BlockResponse::rejected(
             block.header.signer_signature_hash(),
             reject_reason,
-            &self.private_key,
+            self.signing_backend.as_ref(),
             self.mainnet,
             self.signer_db.calculate_tenure_extend_timestamp(
                 self.proposal_config
@@ -398,14 +456,14 @@ impl Signer {
         )
     }
     /// Check if block should be rejected based on sortition state
-    /// Will return a BlockResponse::Rejection if the block is invalid, none otherwise.
+    /// Will return the reason to reject the block if it is invalid, none otherwise.
     fn check_block_against_sortition_state(
         &mut self,
         stacks_client: &StacksClient,
         sortition_state: &mut Option<SortitionsView>,
         block: &NakamotoBlock,
         miner_pubkey: &Secp256k1PublicKey,
-    ) -> Option<BlockResponse> {
+    ) -> Option<RejectReason> {
         let signer_signature_hash = block.header.signer_signature_hash();
         let block_id = block.block_id();
         // Get sortition view if we don't have it
@@ -438,7 +496,7 @@ impl Signer {
                         "signer_sighash" => %signer_signature_hash,
                         "block_id" => %block_id,
                     );
-                    Some(self.create_block_rejection(RejectReason::ConnectivityIssues(e), block))
+                    Some(RejectReason::ConnectivityIssues(e))
                 }
                 // Block proposal is bad
                 Err(reject_code) => {
@@ -449,7 +507,7 @@ impl Signer {
                         "reject_reason" => %reject_code,
                         "reject_code" => ?reject_code,
                     );
-                    Some(self.create_block_rejection(reject_code, block))
+                    Some(reject_code)
                 }
                 // Block proposal passed check, still don't know if valid
                 Ok(_) => None,
@@ -460,7 +518,7 @@ impl Signer {
                 "signer_sighash" => %signer_signature_hash,
                 "block_id" => %block_id,
             );
-            Some(self.create_block_rejection(RejectReason::NoSortitionView, block))
+            Some(RejectReason::NoSortitionView)
         }
     }
 
@@ -479,6 +537,17 @@ impl Signer {
                 "{self}: Received a block proposal for a different reward cycle. Ignore it.";
                 "requested_reward_cycle" => block_proposal.reward_cycle
             );
+            self.record_decision(
+                BlockDecision::new(
+                    &BlockInfo::from(block_proposal.clone()),
+                    DecisionOutcome::Ignored,
+                    None,
+                )
+                .with_broadcast(Some(format!(
+                    "proposal is for reward cycle {}, but this signer signs for reward cycle {}",
+                    block_proposal.reward_cycle, self.reward_cycle
+                ))),
+            );
             return;
         }
 
@@ -497,6 +566,17 @@ impl Signer {
                 "burn_height" => block_proposal.burn_height,
                 "timestamp" => block_proposal.block.header.timestamp,
             );
+            self.record_decision(
+                BlockDecision::new(
+                    &BlockInfo::from(block_proposal.clone()),
+                    DecisionOutcome::Ignored,
+                    None,
+                )
+                .with_broadcast(Some(format!(
+                    "proposal is more than {} secs old",
+                    self.block_proposal_max_age_secs
+                ))),
+            );
             return;
         }
 
@@ -512,13 +592,44 @@ impl Signer {
                 );
                 return;
             };
+            let block_response = match block_response {
+                Ok(block_response) => block_response,
+                Err(e) => {
+                    warn!("{self}: Failed to sign block response. Will retry if the block is proposed again: {e}";
+                        "signer_sighash" => %signer_signature_hash,
+                        "block_id" => %block_proposal.block.block_id()
+                    );
+                    let decision = if block_info.valid == Some(true) {
+                        BlockDecision::new(
+                            &block_info,
+                            DecisionOutcome::Accepted,
+                            sortition_state.as_ref(),
+                        )
+                    } else {
+                        BlockDecision::from_reject_reason(
+                            &block_info,
+                            RejectReason::RejectedInPriorRound,
+                            sortition_state.as_ref(),
+                        )
+                    };
+                    self.record_decision(
+                        decision.with_broadcast(Some(format!("failed to sign the response: {e}"))),
+                    );
+                    return;
+                }
+            };
             // Submit a proposal response to the .signers contract for miners
             debug!("{self}: Broadcasting a block response to stacks node: {block_response:?}");
             let accepted = matches!(block_response, BlockResponse::Accepted(..));
-            match self
+            let decision = BlockDecision::from_response(
+                &block_info,
+                &block_response,
+                sortition_state.as_ref(),
+            );
+            let res = self
                 .stackerdb
-                .send_message_with_retry::<SignerMessage>(block_response.into())
-            {
+                .send_message_with_retry::<SignerMessage>(block_response.into());
+            match &res {
                 Ok(_) => {
                     crate::monitoring::actions::increment_block_responses_sent(accepted);
                     crate::monitoring::actions::record_block_response_latency(
@@ -529,6 +640,7 @@ impl Signer {
                     warn!("{self}: Failed to send block response to stacker-db: {e:?}",);
                 }
             }
+            self.record_decision(decision.with_broadcast(broadcast_error(&res)));
             return;
         }
 
@@ -561,23 +673,51 @@ impl Signer {
         }
 
         // Check if proposal can be rejected now if not valid against sortition view
-        let block_response = self.check_block_against_sortition_state(
-            stacks_client,
-            sortition_state,
-            &block_proposal.block,
-            miner_pubkey,
-        );
+        let reject_reason = self
+            .check_block_against_sortition_state(
+                stacks_client,
+                sortition_state,
+                &block_proposal.block,
+                miner_pubkey,
+            )
+            .or_else(|| self.check_block_against_policy(&block_proposal.block));
 
         #[cfg(any(test, feature = "testing"))]
-        let block_response =
-            self.test_reject_block_proposal(block_proposal, &mut block_info, block_response);
+        let reject_reason =
+            self.test_reject_block_proposal(block_proposal, &mut block_info, reject_reason);
 
-        if let Some(block_response) = block_response {
+        if let Some(reject_reason) = reject_reason {
             // We know proposal is invalid. Send rejection message, do not do further validation and do not store it.
+            let block_response = match self
+                .create_block_rejection(reject_reason.clone(), &block_proposal.block)
+            {
+                Ok(block_response) => block_response,
+                Err(e) => {
+                    warn!("{self}: Failed to sign block rejection. Not responding to the block proposal: {e}";
+                        "signer_sighash" => %signer_signature_hash,
+                        "block_id" => %block_proposal.block.block_id(),
+                    );
+                    self.record_decision(
+                        BlockDecision::from_reject_reason(
+                            &block_info,
+                            reject_reason,
+                            sortition_state.as_ref(),
+                        )
+                        .with_broadcast(Some(format!("failed to sign the rejection: {e}"))),
+                    );
+                    return;
+                }
+            };
+            let decision = BlockDecision::from_response(
+                &block_info,
+                &block_response,
+                sortition_state.as_ref(),
+            );
             debug!("{self}: Broadcasting a block response to stacks node: {block_response:?}");
             let res = self
                 .stackerdb
                 .send_message_with_retry::<SignerMessage>(block_response.into());
+            self.record_decision(decision.with_broadcast(broadcast_error(&res)));
 
             match res {
                 Err(e) => warn!("{self}: Failed to send block rejection to stacker-db: {e:?}"),
@@ -588,8 +728,13 @@ impl Signer {
                 Ok(_) => debug!("{self}: Block rejection accepted by stacker-db"),
             }
         } else {
+            self.record_decision(BlockDecision::new(
+                &block_info,
+                DecisionOutcome::PendingValidation,
+                sortition_state.as_ref(),
+            ));
             // Just in case check if the last block validation submission timed out.
-            self.check_submitted_block_proposal();
+            self.check_submitted_block_proposal(sortition_state.as_ref());
             if self.submitted_block_proposal.is_none() {
                 // We don't know if proposal is valid, submit to stacks-node for further checks and store it locally.
                 info!(
@@ -650,7 +795,7 @@ impl Signer {
         &mut self,
         stacks_client: &StacksClient,
         proposed_block: &NakamotoBlock,
-    ) -> Option<BlockResponse> {
+    ) -> Option<RejectReason> {
         let signer_signature_hash = proposed_block.header.signer_signature_hash();
         let proposed_block_consensus_hash = proposed_block.header.consensus_hash;
         // If this is a tenure change block, ensure that it confirms the correct number of blocks from the parent tenure.
@@ -665,22 +810,14 @@ impl Signer {
                 self.proposal_config.reorg_attempts_activity_timeout,
             ) {
                 Ok(true) => {}
-                Ok(false) => {
-                    return Some(self.create_block_rejection(
-                        RejectReason::SortitionViewMismatch,
-                        proposed_block,
-                    ))
-                }
+                Ok(false) => return Some(RejectReason::SortitionViewMismatch),
                 Err(e) => {
                     warn!("{self}: Error checking block proposal: {e}";
                         "signer_sighash" => %signer_signature_hash,
                         "block_id" => %proposed_block.block_id()
                     );
-                    return Some(self.create_block_rejection(
-                        RejectReason::ConnectivityIssues(
-                            "error checking block proposal".to_string(),
-                        ),
-                        proposed_block,
+                    return Some(RejectReason::ConnectivityIssues(
+                        "error checking block proposal".to_string(),
                     ));
                 }
             }
@@ -700,10 +837,7 @@ impl Signer {
                         "proposed_chain_length" => proposed_block.header.chain_length,
                         "expected_at_least" => last_block_info.block.header.chain_length + 1,
                     );
-                    return Some(self.create_block_rejection(
-                        RejectReason::SortitionViewMismatch,
-                        proposed_block,
-                    ));
+                    return Some(RejectReason::SortitionViewMismatch);
                 }
             }
             Ok(_) => {}
@@ -712,17 +846,91 @@ impl Signer {
                     "signer_sighash" => %signer_signature_hash,
                     "block_id" => %proposed_block.block_id()
                 );
-                return Some(self.create_block_rejection(
-                    RejectReason::ConnectivityIssues(
-                        "failed to check block against signer db".to_string(),
-                    ),
-                    proposed_block,
+                return Some(RejectReason::ConnectivityIssues(
+                    "failed to check block against signer db".to_string(),
                 ));
             }
         }
         None
     }
 
+    /// Check a block proposal's transactions and arrival time against the operator's block policy
+    fn check_block_against_policy(&self, block: &NakamotoBlock) -> Option<RejectReason> {
+        let policy = self.block_policy.as_ref()?;
+        let violation = policy.check_proposal(block, get_epoch_time_secs());
+        self.reject_policy_violation(block, violation)
+    }
+
+    /// Check a validated block's tenure cost against the operator's block policy
+    fn check_block_cost_against_policy(
+        &mut self,
+        stacks_client: &StacksClient,
+        block_info: &BlockInfo,
+        tenure_cost: &ExecutionCost,
+    ) -> Option<RejectReason> {
+        let policy = self
+            .block_policy
+            .as_ref()
+            .filter(|policy| policy.checks_cost())?;
+        let block = &block_info.block;
+        let cached_block_limit = self
+            .epoch_block_limit
+            .as_ref()
+            .filter(|(heights, _)| heights.contains(&block_info.burn_block_height));
+        let block_limit = match cached_block_limit {
+            Some((_, block_limit)) => block_limit.clone(),
+            None => match stacks_client.get_epoch_block_limit(block_info.burn_block_height) {
+                Ok((heights, block_limit)) => {
+                    self.epoch_block_limit = Some((heights, block_limit.clone()));
+                    block_limit
+                }
+                Err(e) => {
+                    warn!("{self}: Failed to get the block limit, so cannot check the block cost against the block policy: {e:?}";
+                        "signer_sighash" => %block.header.signer_signature_hash(),
+                        "block_id" => %block.block_id(),
+                    );
+                    if policy.dry_run {
+                        return None;
+                    }
+                    return Some(RejectReason::ConnectivityIssues(
+                        "failed to get the block limit to check the block policy".to_string(),
+                    ));
+                }
+            },
+        };
+        let violation = policy.check_cost(tenure_cost, &block_limit);
+        self.reject_policy_violation(block, violation)
+    }
+
+    /// Turn a block policy violation into a reason to reject the block, unless the policy is a dry run
+    fn reject_policy_violation(
+        &self,
+        block: &NakamotoBlock,
+        violation: Result<(), String>,
+    ) -> Option<RejectReason> {
+        let Err(rule) = violation else {
+            return None;
+        };
+        if self
+            .block_policy
+            .as_ref()
+            .is_some_and(|policy| policy.dry_run)
+        {
+            info!("{self}: Block policy dry run: would reject block proposal";
+                "signer_sighash" => %block.header.signer_signature_hash(),
+                "block_id" => %block.block_id(),
+                "rule" => %rule,
+            );
+            return None;
+        }
+        warn!("{self}: Block proposal violates the block policy. Rejecting.";
+            "signer_sighash" => %block.header.signer_signature_hash(),
+            "block_id" => %block.block_id(),
+            "rule" => %rule,
+        );
+        Some(RejectReason::PolicyViolation(rule))
+    }
+
     /// Handle the block validate ok response. Returns our block response if we have one
     fn handle_block_validate_ok(
         &mut self,
@@ -749,8 +957,15 @@ impl Signer {
             return None;
         }
 
-        if let Some(block_response) =
-            self.check_block_against_signer_db_state(stacks_client, &block_info.block)
+        if let Some(reject_reason) = self
+            .check_block_against_signer_db_state(stacks_client, &block_info.block)
+            .or_else(|| {
+                self.check_block_cost_against_policy(
+                    stacks_client,
+                    &block_info,
+                    &block_validate_ok.cost,
+                )
+            })
         {
             // The signer db state has changed. We no longer view this block as valid. Override the validation response.
             if let Err(e) = block_info.mark_locally_rejected() {
@@ -758,25 +973,15 @@ impl Signer {
                     warn!("{self}: Failed to mark block as locally rejected: {e:?}");
                 }
             };
-            debug!("{self}: Broadcasting a block response to stacks node: {block_response:?}");
-            let res = self
-                .stackerdb
-                .send_message_with_retry::<SignerMessage>(block_response.into());
-
-            crate::monitoring::actions::record_block_response_latency(&block_info.block);
-
-            match res {
-                Err(e) => warn!("{self}: Failed to send block rejection to stacker-db: {e:?}"),
-                Ok(ack) if !ack.accepted => warn!(
-                    "{self}: Block rejection not accepted by stacker-db: {:?}",
-                    ack.reason
-                ),
-                Ok(_) => debug!("{self}: Block rejection accepted by stacker-db"),
-            }
             self.signer_db
                 .insert_block(&block_info)
                 .unwrap_or_else(|e| self.handle_insert_block_error(e));
-            None
+            // The block is recorded as rejected, so the response is sent if it is proposed again.
+            // The caller broadcasts it, once it has confirmed that this instance still holds the
+            // high-availability lease.
+            self.create_block_rejection(reject_reason, &block_info.block)
+                .inspect_err(|e| warn!("{self}: Failed to sign block rejection: {e}"))
+                .ok()
         } else {
             if let Err(e) = block_info.mark_locally_accepted(false) {
                 if !block_info.has_reached_consensus() {
@@ -795,7 +1000,11 @@ impl Signer {
             self.signer_db
                 .insert_block(&block_info)
                 .unwrap_or_else(|e| self.handle_insert_block_error(e));
-            let block_response = self.create_block_acceptance(&block_info.block);
+            // The block is recorded as accepted, so the response is sent if it is proposed again
+            let block_response = self
+                .create_block_acceptance(&block_info.block)
+                .inspect_err(|e| warn!("{self}: Failed to sign block: {e}"))
+                .ok()?;
             // have to save the signature _after_ the block info
             self.handle_block_signature(stacks_client, block_response.as_block_accepted()?);
             Some(block_response)
@@ -834,7 +1043,7 @@ impl Signer {
         }
         let block_rejection = BlockRejection::from_validate_rejection(
             block_validate_reject.clone(),
-            &self.private_key,
+            self.signing_backend.as_ref(),
             self.mainnet,
             self.signer_db.calculate_tenure_extend_timestamp(
                 self.proposal_config
@@ -847,6 +1056,10 @@ impl Signer {
         self.signer_db
             .insert_block(&block_info)
             .unwrap_or_else(|e| self.handle_insert_block_error(e));
+        // The block is recorded as rejected, so the response is sent if it is proposed again
+        let block_rejection = block_rejection
+            .inspect_err(|e| warn!("{self}: Failed to sign block rejection: {e}"))
+            .ok()?;
         self.handle_block_rejection(&block_rejection, sortition_state);
         Some(BlockResponse::Rejected(block_rejection))
     }
@@ -859,6 +1072,14 @@ impl Signer {
         sortition_state: &mut Option<SortitionsView>,
     ) {
         info!("{self}: Received a block validate response: {block_validate_response:?}");
+        let validation_wait_ms = self
+            .submitted_block_proposal
+            .filter(|(proposal_hash, _)| {
+                *proposal_hash == block_validate_response.signer_signature_hash()
+            })
+            .map(|(_, submitted_at)| {
+                u64::try_from(submitted_at.elapsed().as_millis()).unwrap_or(u64::MAX)
+            });
         let block_response = match block_validate_response {
             BlockValidateResponse::Ok(block_validate_ok) => {
                 crate::monitoring::actions::record_block_validation_latency(
@@ -877,33 +1098,53 @@ impl Signer {
             .unwrap_or_else(|e| warn!("{self}: Failed to remove pending block validation: {e:?}"));
 
         if let Some(response) = block_response {
-            // Submit a proposal response to the .signers contract for miners
-            info!(
-                "{self}: Broadcasting a block response to stacks node: {response:?}";
-            );
             let accepted = matches!(response, BlockResponse::Accepted(..));
-            match self
-                .stackerdb
-                .send_message_with_retry::<SignerMessage>(response.into())
-            {
-                Ok(_) => {
-                    crate::monitoring::actions::increment_block_responses_sent(accepted);
-                    if let Ok(Some(block_info)) = self
-                        .signer_db
-                        .block_lookup(&block_validate_response.signer_signature_hash())
-                    {
-                        crate::monitoring::actions::record_block_response_latency(
-                            &block_info.block,
-                        );
+            let block_info = self.signer_db.block_lookup(&signer_sig_hash).ok().flatten();
+            let decision = block_info.as_ref().map(|block_info| BlockDecision {
+                validation_time_ms: match block_validate_response {
+                    BlockValidateResponse::Ok(ok) => Some(ok.validation_time_ms),
+                    BlockValidateResponse::Reject(_) => None,
+                },
+                validation_wait_ms,
+                ..BlockDecision::from_response(block_info, &response, sortition_state.as_ref())
+            });
+            // Validation may take a while. Make sure that no other instance took over in the meantime.
+            let broadcast_error = if self.refresh_lease(stacks_client) {
+                // Submit a proposal response to the .signers contract for miners
+                info!(
+                    "{self}: Broadcasting a block response to stacks node: {response:?}";
+                );
+                let res = self
+                    .stackerdb
+                    .send_message_with_retry::<SignerMessage>(response.into());
+                match &res {
+                    Ok(_) => {
+                        crate::monitoring::actions::increment_block_responses_sent(accepted);
+                        if let Some(block_info) = block_info {
+                            crate::monitoring::actions::record_block_response_latency(
+                                &block_info.block,
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        warn!("{self}: Failed to send block response to stacker-db: {e:?}",);
                     }
                 }
-                Err(e) => {
-                    warn!("{self}: Failed to send block rejection to stacker-db: {e:?}",);
-                }
+                broadcast_error(&res)
+            } else {
+                warn!("{self}: Lost the high-availability lease during block validation. Not broadcasting a block response.");
+                Some("not the active signer instance".to_string())
+            };
+            if let Some(decision) = decision {
+                self.record_decision(decision.with_broadcast(broadcast_error));
             }
         };
 
-        // Check if there is a pending block validation that we need to submit to the node
+        self.submit_pending_block_validation(stacks_client);
+    }
+
+    /// Check if there is a pending block validation that we need to submit to the node
+    fn submit_pending_block_validation(&mut self, stacks_client: &StacksClient) {
         match self.signer_db.get_and_remove_pending_block_validation() {
             Ok(Some(signer_sig_hash)) => {
                 info!("{self}: Found a pending block validation: {signer_sig_hash:?}");
@@ -927,7 +1168,7 @@ impl Signer {
 
     /// Check the current tracked submitted block proposal to see if it has timed out.
     /// Broadcasts a rejection and marks the block locally rejected if it has.
-    fn check_submitted_block_proposal(&mut self) {
+    fn check_submitted_block_proposal(&mut self, sortition_state: Option<&SortitionsView>) {
         let Some((proposal_signer_sighash, block_submission)) =
             self.submitted_block_proposal.take()
         else {
@@ -978,10 +1219,42 @@ impl Signer {
                 warn!("{self}: Failed to mark block as locally rejected: {e:?}");
             }
         };
+        let validation_wait_ms =
+            Some(u64::try_from(block_submission.elapsed().as_millis()).unwrap_or(u64::MAX));
+        let rejection = match rejection {
+            Ok(rejection) => rejection,
+            Err(e) => {
+                warn!("{self}: Failed to sign block rejection: {e}";
+                    "signer_sighash" => %proposal_signer_sighash,
+                );
+                self.record_decision(
+                    BlockDecision {
+                        validation_wait_ms,
+                        ..BlockDecision::from_reject_reason(
+                            &block_info,
+                            RejectReason::ConnectivityIssues(
+                                "failed to receive block validation response in time".to_string(),
+                            ),
+                            sortition_state,
+                        )
+                    }
+                    .with_broadcast(Some(format!("failed to sign the rejection: {e}"))),
+                );
+                self.signer_db
+                    .insert_block(&block_info)
+                    .unwrap_or_else(|e| self.handle_insert_block_error(e));
+                return;
+            }
+        };
+        let decision = BlockDecision {
+            validation_wait_ms,
+            ..BlockDecision::from_response(&block_info, &rejection, sortition_state)
+        };
         debug!("{self}: Broadcasting a block response to stacks node: {rejection:?}");
         let res = self
             .stackerdb
             .send_message_with_retry::<SignerMessage>(rejection.into());
+        self.record_decision(decision.with_broadcast(broadcast_error(&res)));
 
         crate::monitoring::actions::record_block_response_latency(&block_info.block);
 
@@ -998,6 +1271,13 @@ impl Signer {
             .unwrap_or_else(|e| self.handle_insert_block_error(e));
     }
 
+    /// Append a decision on a block proposal to the decision log
+    fn record_decision(&self, decision: BlockDecision) {
+        self.signer_db
+            .insert_block_decision(&decision)
+            .unwrap_or_else(|e| warn!("{self}: Failed to record block decision: {e:?}"));
+    }
+
     /// Compute the signing weight, given a list of signatures
     fn compute_signature_signing_weight<'a>(
         &self,
@@ -1109,12 +1389,16 @@ impl Signer {
             return;
         }
         debug!("{self}: {total_reject_weight}/{total_weight} signers voted to reject the block {block_hash}");
-        if let Err(e) = self.signer_db.mark_block_globally_rejected(&mut block_info) {
-            warn!("{self}: Failed to mark block as globally rejected: {e:?}",);
-        }
-        if let Err(e) = self.signer_db.insert_block(&block_info) {
-            error!("{self}: Failed to update block state: {e:?}",);
-            panic!("{self} Failed to update block state: {e}");
+        // A standby's copy of the block may be stale. Writing it back could overwrite the
+        // active instance's updates in the shared signer db.
+        if self.is_active {
+            if let Err(e) = self.signer_db.mark_block_globally_rejected(&mut block_info) {
+                warn!("{self}: Failed to mark block as globally rejected: {e:?}",);
+            }
+            if let Err(e) = self.signer_db.insert_block(&block_info) {
+                error!("{self}: Failed to update block state: {e:?}",);
+                panic!("{self} Failed to update block state: {e}");
+            }
         }
         if self
             .submitted_block_proposal
@@ -1196,6 +1480,13 @@ impl Signer {
             .add_block_signature(block_hash, signature)
             .unwrap_or_else(|_| panic!("{self}: Failed to save block signature"));
 
+        if !self.is_active {
+            // A standby's copy of the block may be stale. Writing it back could overwrite the
+            // active instance's updates in the shared signer db, so leave the block state, and
+            // its broadcast, to the active instance.
+            return;
+        }
+
         // do we have enough signatures to broadcast?
         // i.e. is the threshold reached?
         let signatures = self
@@ -1327,10 +1618,90 @@ impl Signer {
         }
     }
 
+    /// Acquire or renew the high-availability lease, and return whether this instance is the
+    /// active signer. Outside of high-availability mode, the signer is always active.
+    fn refresh_lease(&mut self, stacks_client: &StacksClient) -> bool {
+        let Some(HighAvailabilityConfig {
+            instance_id,
+            lease_duration,
+        }) = self.high_availability.clone()
+        else {
+            // The db may have been exported to another host since the signer started
+            let is_migrated = self.signer_db.is_migrated().unwrap_or_else(|e| {
+                warn!("{self}: Failed to check whether the signer db was exported: {e:?}");
+                true
+            });
+            if is_migrated {
+                if self.is_active {
+                    error!("{self}: The signer db was exported to another host. No longer responding to block proposals.");
+                }
+                self.is_active = false;
+                self.submitted_block_proposal = None;
+            }
+            return !is_migrated;
+        };
+        let now_ms = u64::try_from(get_epoch_time_ms()).unwrap_or(u64::MAX);
+        let is_active = self
+            .signer_db
+            .try_acquire_lease(&instance_id, now_ms, lease_duration)
+            .unwrap_or_else(|e| {
+                // Never risk a conflicting response
+                warn!("{self}: Failed to refresh the high-availability lease: {e:?}");
+                false
+            });
+        match (self.is_active, is_active) {
+            (false, true) => {
+                info!("{self}: Acquired the high-availability lease. Now the active signer.";
+                    "instance_id" => %instance_id,
+                );
+                self.is_active = true;
+                // Pick up any validation which the previous active instance left pending
+                if self.submitted_block_proposal.is_none() {
+                    self.submit_pending_block_validation(stacks_client);
+                }
+            }
+            (true, false) => {
+                warn!("{self}: Lost the high-availability lease. Now on standby.";
+                    "instance_id" => %instance_id,
+                    "lease" => ?self.signer_db.get_lease().ok().flatten(),
+                );
+                self.is_active = false;
+                // The active instance will respond to this proposal
+                self.submitted_block_proposal = None;
+            }
+            _ => {}
+        }
+        is_active
+    }
+
+    /// While on standby, keep the sortition view up to date, so that it is ready on takeover.
+    /// Decisions about block proposals are left to the active instance, which records them in
+    /// the shared signer db.
+    fn keep_standby_warm(
+        &self,
+        stacks_client: &StacksClient,
+        sortition_state: &mut Option<SortitionsView>,
+    ) {
+        debug!("{self}: On standby. Leaving block proposal to the active signer.");
+        if sortition_state.is_none() {
+            *sortition_state =
+                SortitionsView::fetch_view(self.proposal_config.clone(), stacks_client)
+                    .inspect_err(|e| warn!("{self}: Failed to update sortition view: {e:?}"))
+                    .ok();
+        }
+    }
+
     /// Send a mock signature to stackerdb to prove we are still alive
     fn mock_sign(&mut self, mock_proposal: MockProposal) {
         info!("{self}: Mock signing mock proposal: {mock_proposal:?}");
-        let mock_signature = MockSignature::new(mock_proposal, &self.private_key);
+        let mock_signature = match MockSignature::new(mock_proposal, self.signing_backend.as_ref())
+        {
+            Ok(mock_signature) => mock_signature,
+            Err(e) => {
+                warn!("{self}: Failed to sign mock proposal: {e}");
+                return;
+            }
+        };
         let message = SignerMessage::MockSignature(mock_signature);
         if let Err(e) = self
             .stackerdb
@@ -1366,3 +1737,12 @@ impl Signer {
         }
     }
 }
+
+/// Why a block response was not broadcast, given the result of sending it to stacker-db
+fn broadcast_error(res: &Result<StackerDBChunkAckData, ClientError>) -> Option<String> {
+    match res {
+        Ok(ack) if ack.accepted => None,
+        Ok(ack) => Some(format!("not accepted by stacker-db: {:?}", ack.reason)),
+        Err(e) => Some(format!("failed to send to stacker-db: {e:?}")),
+    }
+}
@@ -16,7 +16,7 @@
 use std::sync::LazyLock;
 
 use blockstack_lib::chainstate::nakamoto::NakamotoBlock;
-use libsigner::v0::messages::{BlockResponse, RejectReason};
+use libsigner::v0::messages::RejectReason;
 use libsigner::BlockProposal;
 use slog::{slog_info, slog_warn};
 use stacks_common::types::chainstate::StacksPublicKey;
@@ -76,12 +76,10 @@ impl Signer {
         &mut self,
         block_proposal: &BlockProposal,
         block_info: &mut BlockInfo,
-        block_response: Option<BlockResponse>,
-    ) -> Option<BlockResponse> {
+        reject_reason: Option<RejectReason>,
+    ) -> Option<RejectReason> {
         let public_keys = TEST_REJECT_ALL_BLOCK_PROPOSAL.get();
-        if public_keys.contains(
-            &stacks_common::types::chainstate::StacksPublicKey::from_private(&self.private_key),
-        ) {
+        if public_keys.contains(&self.signing_backend.public_key()) {
             warn!("{self}: Rejecting block proposal automatically due to testing directive";
                 "block_id" => %block_proposal.block.block_id(),
                 "height" => block_proposal.block.header.chain_length,
@@ -98,9 +96,9 @@ impl Signer {
             self.signer_db
                 .insert_block(block_info)
                 .unwrap_or_else(|e| self.handle_insert_block_error(e));
-            Some(self.create_block_rejection(RejectReason::TestingDirective, &block_proposal.block))
+            Some(RejectReason::TestingDirective)
         } else {
-            block_response
+            reject_reason
         }
     }
 
@@ -125,9 +123,7 @@ impl Signer {
     /// Ignore block proposals if the TEST_IGNORE_ALL_BLOCK_PROPOSALS flag is set for the signer's public key
     pub fn test_ignore_all_block_proposals(&self, block_proposal: &BlockProposal) -> bool {
         let public_keys = TEST_IGNORE_ALL_BLOCK_PROPOSALS.get();
-        if public_keys.contains(
-            &stacks_common::types::chainstate::StacksPublicKey::from_private(&self.private_key),
-        ) {
+        if public_keys.contains(&self.signing_backend.public_key()) {
             warn!("{self}: Ignoring block proposal due to testing directive";
                 "block_id" => %block_proposal.block.block_id(),
                 "height" => block_proposal.block.header.chain_length,
@@ -1209,6 +1209,16 @@ impl<
             last_processed_ancestor = sortition_id;
         }
 
+        // if this is a pruned node, then discard block data that has fallen out of the retention
+        // window.  This happens in the background, and a failed run is retried on a later burn
+        // block.
+        if let Err(e) = self
+            .chain_state_db
+            .start_pruning(&self.burnchain, canonical_burnchain_tip.block_height)
+        {
+            warn!("Failed to start pruning chainstate: {:?}", &e);
+        }
+
         Ok(true)
     }
 }
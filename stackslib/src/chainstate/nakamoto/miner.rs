@@ -58,6 +58,7 @@ use crate::chainstate::stacks::address::StacksAddressExtensions;
 use crate::chainstate::stacks::boot::MINERS_NAME;
 use crate::chainstate::stacks::db::accounts::MinerReward;
 use crate::chainstate::stacks::db::blocks::{DummyEventDispatcher, MemPoolRejection};
+use crate::chainstate::stacks::db::parallel::ParallelTxBatch;
 use crate::chainstate::stacks::db::transactions::{
     handle_clarity_runtime_error, ClarityRuntimeTxError,
 };
@@ -130,6 +131,8 @@ pub struct NakamotoBlockBuilder {
     pub header: NakamotoBlockHeader,
     /// Optional soft limit for this block's budget usage
     soft_limit: Option<ExecutionCost>,
+    /// If set, the speculatively-executed transactions to (try to) mine
+    parallel_txs: Option<ParallelTxBatch>,
 }
 
 pub struct MinerTenureInfo<'a> {
@@ -181,6 +184,7 @@ impl NakamotoBlockBuilder {
             txs: vec![],
             header: NakamotoBlockHeader::genesis(),
             soft_limit: None,
+            parallel_txs: None,
         }
     }
 
@@ -250,6 +254,7 @@ impl NakamotoBlockBuilder {
                     .unwrap_or(0),
             ),
             soft_limit,
+            parallel_txs: None,
         })
     }
 
@@ -679,6 +684,12 @@ impl NakamotoBlockBuilder {
     pub fn get_bytes_so_far(&self) -> u64 {
         self.bytes_so_far
     }
+
+    /// Mine transactions using the results of speculatively executing them with
+    /// `ParallelTxBatch::execute()`.  The transactions must then be mined in the same order.
+    pub fn set_parallel_txs(&mut self, batch: ParallelTxBatch) {
+        self.parallel_txs = Some(batch);
+    }
 }
 
 impl BlockBuilder for NakamotoBlockBuilder {
@@ -737,13 +748,16 @@ impl BlockBuilder for NakamotoBlockBuilder {
             }
 
             let cost_before = clarity_tx.cost_so_far();
-            let (fee, receipt) =
-                match StacksChainState::process_transaction(clarity_tx, tx, quiet, ast_rules) {
-                    Ok(x) => x,
-                    Err(e) => {
-                        return parse_process_transaction_error(clarity_tx, tx, e);
-                    }
-                };
+            let result = match self.parallel_txs.as_mut() {
+                Some(batch) => batch.process_transaction(clarity_tx, tx, quiet, ast_rules),
+                None => StacksChainState::process_transaction(clarity_tx, tx, quiet, ast_rules),
+            };
+            let (fee, receipt) = match result {
+                Ok(x) => x,
+                Err(e) => {
+                    return parse_process_transaction_error(clarity_tx, tx, e);
+                }
+            };
             let cost_after = clarity_tx.cost_so_far();
             let mut soft_limit_reached = false;
             // We only attempt to apply the soft limit to non-boot code contract calls.
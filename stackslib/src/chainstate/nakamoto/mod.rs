@@ -2791,6 +2791,105 @@ impl NakamotoChainState {
         )
     }
 
+    /// DO NOT CALL IN CONSENSUS CODE.
+    /// Load the header of the ancestor of `tip_block_id` at the given Stacks block height
+    /// (either epoch-2 rules or Nakamoto).
+    /// Returns Ok(None) if the fork does not reach that height.
+    pub fn get_ancestor_block_header_at_height(
+        chainstate_conn: &StacksDBConn,
+        tip_block_id: &StacksBlockId,
+        block_height: u64,
+    ) -> Result<Option<StacksHeaderInfo>, ChainstateError> {
+        if block_height > u64::from(u32::MAX) {
+            return Ok(None);
+        }
+        let Some(block_id) = chainstate_conn.get_ancestor_block_hash(block_height, tip_block_id)?
+        else {
+            return Ok(None);
+        };
+        Self::get_block_header(chainstate_conn.conn(), &block_id)
+    }
+
+    /// DO NOT CALL IN CONSENSUS CODE.
+    /// Load the header of the highest ancestor of `tip_block_id` (inclusive) which was mined
+    /// in a burnchain block at or below `burn_height`.  This is the Stacks chain state as it
+    /// stood once burnchain block `burn_height` had been processed.
+    ///
+    /// Burnchain heights never decrease along a Stacks fork, so this is a binary search over
+    /// the fork's Stacks block heights.
+    /// Returns Ok(None) if the fork has no such block.
+    pub fn get_ancestor_block_header_at_burn_height(
+        chainstate_conn: &StacksDBConn,
+        tip_block_id: &StacksBlockId,
+        burn_height: u64,
+    ) -> Result<Option<StacksHeaderInfo>, ChainstateError> {
+        let Some(tip_header) = Self::get_block_header(chainstate_conn.conn(), tip_block_id)? else {
+            return Ok(None);
+        };
+        if u64::from(tip_header.burn_header_height) <= burn_height {
+            return Ok(Some(tip_header));
+        }
+
+        let header_at = |height: u64| -> Result<StacksHeaderInfo, ChainstateError> {
+            Self::get_ancestor_block_header_at_height(chainstate_conn, tip_block_id, height)?
+                .ok_or(ChainstateError::NoSuchBlockError)
+        };
+
+        let genesis_header = header_at(0)?;
+        if u64::from(genesis_header.burn_header_height) > burn_height {
+            return Ok(None);
+        }
+
+        // invariant: the block at `lo` is at or below `burn_height`, and the block at `hi` is not
+        let mut lo = 0;
+        let mut hi = tip_header.stacks_block_height;
+        let mut best = genesis_header;
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            let header = header_at(mid)?;
+            if u64::from(header.burn_header_height) <= burn_height {
+                lo = mid;
+                best = header;
+            } else {
+                hi = mid;
+            }
+        }
+        Ok(Some(best))
+    }
+
+    /// DO NOT CALL IN CONSENSUS CODE.
+    /// Load the header of the highest ancestor of `tip_block_id` (inclusive) in the tenure
+    /// identified by `consensus_hash` (either epoch-2 rules or Nakamoto).  For epoch-2 tenures,
+    /// this is the one block which won that sortition.
+    /// Returns Ok(None) if the fork has no block in that tenure.
+    pub fn get_ancestor_block_header_in_tenure(
+        chainstate_conn: &mut StacksDBConn,
+        tip_block_id: &StacksBlockId,
+        consensus_hash: &ConsensusHash,
+    ) -> Result<Option<StacksHeaderInfo>, ChainstateError> {
+        // nakamoto?
+        if let Some(header) =
+            Self::get_highest_block_header_in_tenure(chainstate_conn, tip_block_id, consensus_hash)?
+        {
+            return Ok(Some(header));
+        }
+
+        // epoch2?
+        let Some(header) = StacksChainState::get_stacks_block_header_info_by_consensus_hash(
+            chainstate_conn.sqlite(),
+            consensus_hash,
+        )?
+        else {
+            return Ok(None);
+        };
+        let ancestor_block_id =
+            chainstate_conn.get_ancestor_block_hash(header.stacks_block_height, tip_block_id)?;
+        if ancestor_block_id != Some(header.index_block_hash()) {
+            return Ok(None);
+        }
+        Ok(Some(header))
+    }
+
     /// Get the tenure-start block header of a given consensus hash.
     /// For Nakamoto blocks, this is the first block in the tenure identified by the consensus
     /// hash.
@@ -4282,6 +4381,7 @@ impl NakamotoChainState {
         );
 
         let ast_rules = ASTRules::PrecheckSize;
+        let tx_execution_threads = chainstate_tx.tx_execution_threads;
         let next_block_height = block.header.chain_length;
         let first_block_height = burn_dbconn.context.first_block_height;
 
@@ -4501,6 +4601,7 @@ impl NakamotoChainState {
             &block.txs,
             0,
             ast_rules,
+            tx_execution_threads,
         ) {
             Err(e) => {
                 let msg = format!("Invalid Stacks block {}: {:?}", &block_hash, &e);
@@ -4905,7 +5006,7 @@ impl NakamotoChainState {
     /// Generate a "phantom" transaction to include STXMintEvents for
     /// lockups that could not be attached to a Coinbase transaction
     /// (because the block doesn't have a Coinbase transaction).
-    fn generate_phantom_unlock_tx(
+    pub(crate) fn generate_phantom_unlock_tx(
         events: Vec<StacksTransactionEvent>,
         config: &ChainstateConfig,
         stacks_block_height: u64,
@@ -160,7 +160,13 @@ pub const NAKAMOTO_STAGING_DB_SCHEMA_3: &[&str] = &[
     r#"UPDATE db_version SET version = 3"#,
 ];
 
-pub const NAKAMOTO_STAGING_DB_SCHEMA_LATEST: u32 = 3;
+pub const NAKAMOTO_STAGING_DB_SCHEMA_4: &[&str] = &[
+    // set to 1 once a processed block's data has been discarded by a pruned node
+    r#"ALTER TABLE nakamoto_staging_blocks ADD COLUMN pruned INT NOT NULL DEFAULT 0;"#,
+    r#"UPDATE db_version SET version = 4"#,
+];
+
+pub const NAKAMOTO_STAGING_DB_SCHEMA_LATEST: u32 = 4;
 
 pub struct NakamotoStagingBlocksConn(rusqlite::Connection);
 
@@ -304,7 +310,8 @@ impl<'a> NakamotoStagingBlocksConnRef<'a> {
         &self,
         index_block_hash: &StacksBlockId,
     ) -> Result<Option<i64>, ChainstateError> {
-        let sql = "SELECT rowid FROM nakamoto_staging_blocks WHERE index_block_hash = ?1";
+        let sql =
+            "SELECT rowid FROM nakamoto_staging_blocks WHERE index_block_hash = ?1 AND pruned = 0";
         let args = params![index_block_hash];
         let res: Option<i64> = query_row(self, sql, args)?;
         Ok(res)
@@ -333,13 +340,14 @@ impl<'a> NakamotoStagingBlocksConnRef<'a> {
     /// Get a Nakamoto block by index block hash, as well as its size.
     /// Verifies its integrity.
     /// Returns Ok(Some(block, size)) if the block was present
-    /// Returns Ok(None) if there was no such block
+    /// Returns Ok(None) if there was no such block, or if it was pruned
     /// Returns Err(..) on DB error, including block corruption
     pub fn get_nakamoto_block(
         &self,
         index_block_hash: &StacksBlockId,
     ) -> Result<Option<(NakamotoBlock, u64)>, ChainstateError> {
-        let qry = "SELECT data FROM nakamoto_staging_blocks WHERE index_block_hash = ?1";
+        let qry =
+            "SELECT data FROM nakamoto_staging_blocks WHERE index_block_hash = ?1 AND pruned = 0";
         let args = params![index_block_hash];
         let res: Option<Vec<u8>> = query_row(self, qry, args)?;
         let Some(block_bytes) = res else {
@@ -394,7 +402,7 @@ impl<'a> NakamotoStagingBlocksConnRef<'a> {
         &self,
         index_block_hash: &StacksBlockId,
     ) -> Result<Option<u64>, ChainstateError> {
-        let qry = "SELECT length(data) FROM nakamoto_staging_blocks WHERE index_block_hash = ?1";
+        let qry = "SELECT length(data) FROM nakamoto_staging_blocks WHERE index_block_hash = ?1 AND pruned = 0";
         let args = params![index_block_hash];
         let res = query_row(self, qry, args)?
             .map(|size: i64| u64::try_from(size).expect("FATAL: block size exceeds i64::MAX"));
@@ -410,7 +418,7 @@ impl<'a> NakamotoStagingBlocksConnRef<'a> {
         &self,
         consensus_hash: &ConsensusHash,
     ) -> Result<Vec<NakamotoBlock>, ChainstateError> {
-        let qry = "SELECT data FROM nakamoto_staging_blocks WHERE is_tenure_start = 1 AND consensus_hash = ?1";
+        let qry = "SELECT data FROM nakamoto_staging_blocks WHERE is_tenure_start = 1 AND consensus_hash = ?1 AND pruned = 0";
         let args = params![consensus_hash];
         let block_data: Vec<Vec<u8>> = query_rows(self, qry, args)?;
         Ok(block_data
@@ -547,6 +555,17 @@ impl NakamotoStagingBlocksTx<'_> {
         Ok(())
     }
 
+    /// Discard the data of a processed block, keeping its row so that its children remain
+    /// processable and it is not downloaded again.  Used by pruned nodes.
+    /// Returns Ok(true) if the block's data was discarded
+    /// Returns Ok(false) if there is no such processed, unpruned block
+    pub fn set_block_pruned(&self, block: &StacksBlockId) -> Result<bool, ChainstateError> {
+        let prune_staged_block = "UPDATE nakamoto_staging_blocks SET data = X'', pruned = 1
+                                  WHERE index_block_hash = ?1 AND processed = 1 AND pruned = 0";
+        let updated = self.execute(prune_staged_block, params![block])?;
+        Ok(updated > 0)
+    }
+
     /// Notify the staging database that a given burn block has been processed.
     /// This is required for staged blocks to be eligible for processing.
     pub fn set_burn_block_processed(
@@ -796,6 +815,15 @@ impl StacksChainState {
                     assert_eq!(version, 3, "Nakamoto staging DB migration failure");
                     debug!("Migrated Nakamoto staging blocks DB to schema 3");
                 }
+                3 => {
+                    debug!("Migrate Nakamoto staging blocks DB to schema 4");
+                    for cmd in NAKAMOTO_STAGING_DB_SCHEMA_4.iter() {
+                        conn.execute(cmd, NO_PARAMS)?;
+                    }
+                    let version = Self::get_nakamoto_staging_blocks_db_version(conn)?;
+                    assert_eq!(version, 4, "Nakamoto staging DB migration failure");
+                    debug!("Migrated Nakamoto staging blocks DB to schema 4");
+                }
                 NAKAMOTO_STAGING_DB_SCHEMA_LATEST => {
                     break;
                 }
@@ -834,7 +862,8 @@ impl StacksChainState {
             for cmd in NAKAMOTO_STAGING_DB_SCHEMA_2.iter() {
                 conn.execute(cmd, NO_PARAMS)?;
             }
-        } else if readwrite {
+        }
+        if readwrite {
             Self::migrate_nakamoto_staging_blocks(&conn)?;
         }
 
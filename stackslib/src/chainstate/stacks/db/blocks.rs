@@ -60,6 +60,7 @@ use crate::chainstate::nakamoto::signer_set::{NakamotoSigners, SignerCalculation
 use crate::chainstate::nakamoto::NakamotoChainState;
 use crate::chainstate::stacks::address::{PoxAddress, StacksAddressExtensions};
 use crate::chainstate::stacks::db::accounts::MinerReward;
+use crate::chainstate::stacks::db::parallel::ParallelTxBatch;
 use crate::chainstate::stacks::db::transactions::TransactionNonceMismatch;
 use crate::chainstate::stacks::db::*;
 use crate::chainstate::stacks::events::StacksBlockEventData;
@@ -135,6 +136,14 @@ pub enum MemPoolRejection {
     NoTenureChangeViaMempool,
     NoSuchChainTip(ConsensusHash, BlockHeaderHash),
     ConflictingNonceInMempool,
+    /// A replace-by-fee which does not raise the fee enough: (actual, expected)
+    ReplaceFeeTooLow(u64, u64),
+    TooManyPendingTransactions {
+        principal: PrincipalData,
+        is_origin: bool,
+        max_pending: u64,
+    },
+    MemPoolFull,
     TooMuchChaining {
         max_nonce: u64,
         actual_nonce: u64,
@@ -281,6 +290,29 @@ impl MemPoolRejection {
                 Some(json!({"message": e.to_string()})),
             ),
             ConflictingNonceInMempool => ("ConflictingNonceInMempool", None),
+            ReplaceFeeTooLow(actual, expected) => (
+                "ReplaceFeeTooLow",
+                Some(json!({
+                    "expected": expected,
+                    "actual": actual})),
+            ),
+            TooManyPendingTransactions {
+                principal,
+                is_origin,
+                max_pending,
+            } => (
+                "TooManyPendingTransactions",
+                Some(json!({
+                    "message": "Principal has too many pending transactions in mempool",
+                    "principal": principal.to_string(),
+                    "is_origin": is_origin,
+                    "max_pending": max_pending})),
+            ),
+            MemPoolFull => (
+                "MemPoolFull",
+                Some(json!({
+                    "message": "Mempool is full and the transaction's fee rate is too low to evict others"})),
+            ),
             ContractAlreadyExists(id) => (
                 "ContractAlreadyExists",
                 Some(json!({ "contract_identifier": id.to_string() })),
@@ -2049,6 +2081,16 @@ impl StacksChainState {
         let mut microblock_bits = vec![false; header_hashes.len()];
         let mut num_rows = 0;
 
+        if Self::is_reward_cycle_pruned(self.db(), reward_cycle)? {
+            // this is a pruned node, and it no longer has this reward cycle's blocks
+            return Ok(BlocksInvData {
+                bitlen: u16::try_from(block_bits.len())
+                    .expect("FATAL: block bits has more than 2^16 members"),
+                block_bitvec: BlocksInvData::compress_bools(&block_bits),
+                microblocks_bitvec: BlocksInvData::compress_bools(&microblock_bits),
+            });
+        }
+
         let mut ch_lookup: HashMap<&ConsensusHash, _> = HashMap::new();
         for (i, (ch, _)) in header_hashes.iter().enumerate() {
             ch_lookup.insert(ch, i);
@@ -4556,18 +4598,26 @@ impl StacksChainState {
 
     /// Process a single anchored block.
     /// Return the fees and burns.
+    /// If `tx_execution_threads` is greater than 1, the transactions are speculatively executed
+    /// in parallel first (see `ParallelTxBatch`); the outcome is the same either way.
     pub fn process_block_transactions(
         clarity_tx: &mut ClarityTx,
         block_txs: &[StacksTransaction],
         mut tx_index: u32,
         ast_rules: ASTRules,
+        tx_execution_threads: usize,
     ) -> Result<(u128, u128, Vec<StacksTransactionReceipt>), Error> {
         let mut fees = 0u128;
         let mut burns = 0u128;
         let mut receipts = vec![];
+        let mut parallel_batch = (tx_execution_threads > 1).then(|| {
+            ParallelTxBatch::execute(clarity_tx, block_txs, tx_execution_threads, ast_rules)
+        });
         for tx in block_txs.iter() {
-            let (tx_fee, mut tx_receipt) =
-                StacksChainState::process_transaction(clarity_tx, tx, false, ast_rules)?;
+            let (tx_fee, mut tx_receipt) = match parallel_batch.as_mut() {
+                Some(batch) => batch.process_transaction(clarity_tx, tx, false, ast_rules)?,
+                None => StacksChainState::process_transaction(clarity_tx, tx, false, ast_rules)?,
+            };
             fees = fees.checked_add(u128::from(tx_fee)).expect("Fee overflow");
             tx_receipt.tx_index = tx_index;
             burns = burns
@@ -4576,6 +4626,13 @@ impl StacksChainState {
             receipts.push(tx_receipt);
             tx_index += 1;
         }
+        if let Some(batch) = parallel_batch {
+            debug!(
+                "Processed {} transactions in parallel, re-executing {}",
+                block_txs.len(),
+                batch.num_reexecuted()
+            );
+        }
         Ok((fees, burns, receipts))
     }
 
@@ -5430,6 +5487,7 @@ impl StacksChainState {
             SortitionDB::get_ast_rules(burn_dbconn.tx(), chain_tip_burn_header_height.into())?;
 
         let mainnet = chainstate_tx.get_config().mainnet;
+        let tx_execution_threads = chainstate_tx.tx_execution_threads;
         let next_block_height = block.header.total_work.work;
 
         // NEW in 2.05
@@ -5629,6 +5687,7 @@ impl StacksChainState {
                     u32::try_from(microblock_txs_receipts.len())
                         .expect("more than 2^32 tx receipts"),
                     ast_rules,
+                    tx_execution_threads,
                 ) {
                     Err(e) => {
                         let msg = format!("Invalid Stacks block {}: {:?}", block.block_hash(), &e);
@@ -6662,6 +6721,22 @@ impl StacksChainState {
         }
     }
 
+    /// Get the nonce of `principal` at the chain tip used by `will_admit_mempool_tx()`
+    pub fn get_mempool_account_nonce(
+        &mut self,
+        burn_state_db: &dyn BurnStateDB,
+        current_consensus_hash: &ConsensusHash,
+        current_block: &BlockHeaderHash,
+        principal: &PrincipalData,
+    ) -> Result<u64, MemPoolRejection> {
+        let current_tip =
+            StacksChainState::get_parent_index_block(current_consensus_hash, current_block);
+        self.with_read_only_clarity_tx(burn_state_db, &current_tip, |conn| {
+            StacksChainState::get_nonce(conn, principal)
+        })
+        .ok_or_else(|| MemPoolRejection::NoSuchChainTip(*current_consensus_hash, *current_block))
+    }
+
     /// Given an outstanding clarity connection, can we append the tx to the chain state?
     /// Used when determining whether a transaction can be added to the mempool.
     fn can_include_tx<T: ClarityConnection>(
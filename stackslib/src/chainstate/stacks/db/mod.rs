@@ -19,7 +19,7 @@ use std::collections::{BTreeMap, HashSet};
 use std::io::prelude::*;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
-use std::{fmt, fs, io};
+use std::{fmt, fs, io, thread};
 
 use clarity::vm::analysis::analysis_db::AnalysisDatabase;
 use clarity::vm::analysis::run_analysis;
@@ -86,14 +86,18 @@ use crate::net::atlas::BNS_CHARS_REGEX;
 use crate::net::Error as net_error;
 use crate::util_lib::boot::{boot_code_acc, boot_code_addr, boot_code_id, boot_code_tx_auth};
 use crate::util_lib::db::{
-    query_count, query_row, tx_begin_immediate, tx_busy_handler, DBConn, DBTx, Error as db_error,
-    FromColumn, FromRow, IndexDBConn, IndexDBTx,
+    query_count, query_row, query_rows, tx_begin_immediate, tx_busy_handler, DBConn, DBTx,
+    Error as db_error, FromColumn, FromRow, IndexDBConn, IndexDBTx,
 };
 
 pub mod accounts;
 pub mod blocks;
 pub mod contracts;
 pub mod headers;
+pub mod parallel;
+pub mod pruning;
+pub mod repair;
+pub mod snapshot;
 pub mod transactions;
 pub mod unconfirmed;
 
@@ -127,9 +131,35 @@ pub struct StacksChainState {
     pub root_path: String,
     pub unconfirmed_state: Option<UnconfirmedState>,
     pub fault_injection: StacksChainStateFaults,
+    /// If true, then maintain the `transaction_index` table when appending blocks
+    pub txindex: bool,
+    /// If set, then this is a pruned node which only keeps the block data of this many recent
+    /// reward cycles
+    pub prune_reward_cycles: Option<u64>,
+    /// Number of threads on which to speculatively execute a block's transactions when
+    /// validating it.  1 means they are executed serially.
+    pub tx_execution_threads: usize,
+    /// The background thread started by `start_pruning()`, if it has been started
+    prune_thread: Option<thread::JoinHandle<()>>,
     marf_opts: Option<MARFOpenOpts>,
 }
 
+/// A confirmed transaction, as recorded in the `transaction_index` table
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedTransaction {
+    pub txid: Txid,
+    /// index block hash of the block which contains this transaction
+    pub index_block_hash: StacksBlockId,
+    /// position of this transaction in its block
+    pub tx_index: u32,
+    /// hex-encoded transaction
+    pub tx_hex: String,
+    /// hex-encoded consensus serialization of the transaction's result
+    pub result_hex: String,
+    /// the transaction's events, encoded the same way as in the event observer interface
+    pub events: serde_json::Value,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct StacksAccount {
     pub principal: PrincipalData,
@@ -299,15 +329,15 @@ impl DBConfig {
         });
         match epoch_id {
             StacksEpochId::Epoch10 => true,
-            StacksEpochId::Epoch20 => version_u32 >= 1 && version_u32 <= 8,
-            StacksEpochId::Epoch2_05 => version_u32 >= 2 && version_u32 <= 8,
-            StacksEpochId::Epoch21 => version_u32 >= 3 && version_u32 <= 8,
-            StacksEpochId::Epoch22 => version_u32 >= 3 && version_u32 <= 8,
-            StacksEpochId::Epoch23 => version_u32 >= 3 && version_u32 <= 8,
-            StacksEpochId::Epoch24 => version_u32 >= 3 && version_u32 <= 8,
-            StacksEpochId::Epoch25 => version_u32 >= 3 && version_u32 <= 8,
-            StacksEpochId::Epoch30 => version_u32 >= 3 && version_u32 <= 8,
-            StacksEpochId::Epoch31 => version_u32 >= 3 && version_u32 <= 8,
+            StacksEpochId::Epoch20 => version_u32 >= 1 && version_u32 <= 10,
+            StacksEpochId::Epoch2_05 => version_u32 >= 2 && version_u32 <= 10,
+            StacksEpochId::Epoch21 => version_u32 >= 3 && version_u32 <= 10,
+            StacksEpochId::Epoch22 => version_u32 >= 3 && version_u32 <= 10,
+            StacksEpochId::Epoch23 => version_u32 >= 3 && version_u32 <= 10,
+            StacksEpochId::Epoch24 => version_u32 >= 3 && version_u32 <= 10,
+            StacksEpochId::Epoch25 => version_u32 >= 3 && version_u32 <= 10,
+            StacksEpochId::Epoch30 => version_u32 >= 3 && version_u32 <= 10,
+            StacksEpochId::Epoch31 => version_u32 >= 3 && version_u32 <= 10,
         }
     }
 }
@@ -429,6 +459,27 @@ impl FromRow<DBConfig> for DBConfig {
     }
 }
 
+impl FromRow<IndexedTransaction> for IndexedTransaction {
+    fn from_row(row: &Row) -> Result<IndexedTransaction, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let index_block_hash = StacksBlockId::from_column(row, "index_block_hash")?;
+        let tx_index: u32 = row.get_unwrap("tx_index");
+        let tx_hex: String = row.get_unwrap("tx_hex");
+        let result_hex: String = row.get_unwrap("result");
+        let events_json: String = row.get_unwrap("events");
+        let events = serde_json::from_str(&events_json).map_err(db_error::SerializationError)?;
+
+        Ok(IndexedTransaction {
+            txid,
+            index_block_hash,
+            tx_index,
+            tx_hex,
+            result_hex,
+            events,
+        })
+    }
+}
+
 impl FromRow<StacksHeaderInfo> for StacksHeaderInfo {
     fn from_row(row: &Row) -> Result<StacksHeaderInfo, db_error> {
         let block_height: u64 = u64::from_column(row, "block_height")?;
@@ -607,6 +658,10 @@ pub struct ChainstateTx<'a> {
     pub blocks_path: String,
     pub tx: StacksDBTx<'a>,
     pub root_path: String,
+    /// If true, then processed transactions are recorded in the `transaction_index` table
+    pub txindex: bool,
+    /// Number of threads on which to speculatively execute a block's transactions
+    pub tx_execution_threads: usize,
 }
 
 impl<'a> ChainstateTx<'a> {
@@ -621,6 +676,8 @@ impl<'a> ChainstateTx<'a> {
             blocks_path,
             tx,
             root_path,
+            txindex: false,
+            tx_execution_threads: 1,
         }
     }
 
@@ -654,6 +711,11 @@ impl<'a> ChainstateTx<'a> {
                 }
             }
         }
+        if self.txindex {
+            if let Err(e) = self.index_transactions(block_id, events) {
+                warn!("Failed to index transactions: {:?}", e; "block_id" => %block_id);
+            }
+        }
         for tx_event in events.iter() {
             let txid = tx_event.transaction.txid();
             if let Err(e) = monitoring::log_transaction_processed(&txid, &self.root_path) {
@@ -663,6 +725,48 @@ impl<'a> ChainstateTx<'a> {
     }
 }
 
+impl ChainstateTx<'_> {
+    /// Record the given transaction receipts in the `transaction_index` table.
+    /// Events are numbered across the whole block, just as they are in the `/new_block` payload
+    /// sent to event observers.
+    fn index_transactions(
+        &self,
+        block_id: &StacksBlockId,
+        receipts: &[StacksTransactionReceipt],
+    ) -> Result<(), Error> {
+        let insert = "INSERT OR REPLACE INTO transaction_index (txid, index_block_hash, tx_index, tx_hex, result, events) VALUES (?1, ?2, ?3, ?4, ?5, ?6)";
+        let mut event_index = 0;
+        for receipt in receipts.iter() {
+            let txid = receipt.transaction.txid();
+            let committed = !receipt.post_condition_aborted;
+            let mut events = Vec::with_capacity(receipt.events.len());
+            for event in receipt.events.iter() {
+                let event_json = event
+                    .json_serialize(event_index, &txid, committed)
+                    .map_err(|e| db_error::Other(e.to_string()))?;
+                events.push(event_json);
+                event_index += 1;
+            }
+            let events_json =
+                serde_json::to_string(&events).map_err(db_error::SerializationError)?;
+            let result_hex = receipt
+                .result
+                .serialize_to_hex()
+                .map_err(|e| db_error::Other(format!("{e:?}")))?;
+            let params = params![
+                txid,
+                block_id,
+                receipt.tx_index,
+                receipt.transaction.serialize_to_dbstring(),
+                result_hex,
+                events_json,
+            ];
+            self.tx.tx().execute(insert, params)?;
+        }
+        Ok(())
+    }
+}
+
 impl<'a> Deref for ChainstateTx<'a> {
     type Target = StacksDBTx<'a>;
     fn deref(&self) -> &StacksDBTx<'a> {
@@ -676,7 +780,7 @@ impl<'a> DerefMut for ChainstateTx<'a> {
     }
 }
 
-pub const CHAINSTATE_VERSION: &str = "8";
+pub const CHAINSTATE_VERSION: &str = "10";
 
 const CHAINSTATE_INITIAL_SCHEMA: &[&str] = &[
     "PRAGMA foreign_keys = ON;",
@@ -875,6 +979,55 @@ const CHAINSTATE_SCHEMA_3: &[&str] = &[
     "#,
 ];
 
+const CHAINSTATE_SCHEMA_4: &[&str] = &[
+    // optional index of confirmed transactions by txid (schema version 9).
+    // only populated if the node is configured to maintain it.
+    r#"
+    CREATE TABLE transaction_index(
+        txid TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,
+        tx_index INTEGER NOT NULL,
+        tx_hex TEXT NOT NULL,
+        result TEXT NOT NULL,       -- hex-encoded consensus serialization of the result
+        events TEXT NOT NULL,       -- JSON-encoded list of events
+
+        -- a transaction can be mined in more than one fork
+        PRIMARY KEY(txid,index_block_hash)
+    );"#,
+    r#"
+    CREATE INDEX IF NOT EXISTS index_transaction_index_by_block ON transaction_index(index_block_hash);
+    "#,
+    r#"
+    UPDATE db_config SET version = "9";
+    "#,
+];
+
+const CHAINSTATE_SCHEMA_5: &[&str] = &[
+    // how far a pruned node has pruned its block data (schema version 10).
+    // there is at most one row, and none on archival nodes.
+    r#"
+    CREATE TABLE chainstate_pruning(
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        -- blocks from tenures which started before this reward cycle have been pruned
+        reward_cycle INTEGER NOT NULL,
+        -- first burnchain block height of `reward_cycle`
+        burn_height INTEGER NOT NULL,
+        -- the MARF tries of blocks from burnchain blocks before this height have been pruned
+        marf_burn_height INTEGER NOT NULL DEFAULT 0
+    );"#,
+    // set to 1 once a processed epoch 2.x block's file has been deleted by a pruned node
+    r#"
+    ALTER TABLE staging_blocks ADD COLUMN pruned INT NOT NULL DEFAULT 0;
+    "#,
+    // pruning finds the blocks to discard by their burnchain height
+    r#"
+    CREATE INDEX IF NOT EXISTS nakamoto_block_headers_burn_header_height ON nakamoto_block_headers(burn_header_height);
+    "#,
+    r#"
+    UPDATE db_config SET version = "10";
+    "#,
+];
+
 const CHAINSTATE_INDEXES: &[&str] = &[
     "CREATE INDEX IF NOT EXISTS index_block_hash_to_primary_key ON block_headers(index_block_hash,consensus_hash,block_hash);",
     "CREATE INDEX IF NOT EXISTS block_headers_hash_index ON block_headers(block_hash,block_height);",
@@ -1126,6 +1279,18 @@ impl StacksChainState {
                         tx.execute_batch(cmd)?;
                     }
                 }
+                "8" => {
+                    info!("Migrating chainstate schema from version 8 to 9: add transaction index");
+                    for cmd in CHAINSTATE_SCHEMA_4.iter() {
+                        tx.execute_batch(cmd)?;
+                    }
+                }
+                "9" => {
+                    info!("Migrating chainstate schema from version 9 to 10: add pruning state");
+                    for cmd in CHAINSTATE_SCHEMA_5.iter() {
+                        tx.execute_batch(cmd)?;
+                    }
+                }
                 _ => {
                     error!(
                         "Invalid chain state database: expected version = {}, got {}",
@@ -1742,12 +1907,14 @@ impl StacksChainState {
     /// Re-open the chainstate -- i.e. to get a new handle to it using an existing chain state's
     /// parameters
     pub fn reopen(&self) -> Result<(StacksChainState, Vec<StacksTransactionReceipt>), Error> {
-        StacksChainState::open(
+        let (mut chainstate, receipts) = StacksChainState::open(
             self.mainnet,
             self.chain_id,
             &self.root_path,
             self.marf_opts.clone(),
-        )
+        )?;
+        chainstate.tx_execution_threads = self.tx_execution_threads;
+        Ok((chainstate, receipts))
     }
 
     /// Re-open the chainstate DB
@@ -1872,6 +2039,10 @@ impl StacksChainState {
             root_path: path_str.to_string(),
             unconfirmed_state: None,
             fault_injection: StacksChainStateFaults::new(),
+            txindex: false,
+            prune_reward_cycles: None,
+            tx_execution_threads: 1,
+            prune_thread: None,
             marf_opts,
         };
 
@@ -1924,11 +2095,15 @@ impl StacksChainState {
     ) -> Result<(ChainstateTx<'_>, &mut ClarityInstance), Error> {
         let config = self.config();
         let blocks_path = self.blocks_path.clone();
+        let txindex = self.txindex;
+        let tx_execution_threads = self.tx_execution_threads;
         let clarity_instance = &mut self.clarity_state;
         let inner_tx = StacksDBTx::new(&mut self.state_index, ());
 
-        let chainstate_tx =
+        let mut chainstate_tx =
             ChainstateTx::new(inner_tx, blocks_path, self.root_path.clone(), config);
+        chainstate_tx.txindex = txindex;
+        chainstate_tx.tx_execution_threads = tx_execution_threads;
 
         Ok((chainstate_tx, clarity_instance))
     }
@@ -2513,6 +2688,19 @@ impl StacksChainState {
         Ok(txids)
     }
 
+    /// Get every copy of a confirmed transaction recorded in the `transaction_index` table.
+    /// There can be more than one if the transaction was mined in several forks.
+    /// The table is empty unless the node maintains the transaction index.
+    pub fn get_indexed_transactions(
+        conn: &Connection,
+        txid: &Txid,
+    ) -> Result<Vec<IndexedTransaction>, Error> {
+        let sql = "SELECT * FROM transaction_index WHERE txid = ?1";
+        let args = params![txid];
+        let rows = query_rows(conn, sql, args)?;
+        Ok(rows)
+    }
+
     /// Get the txids of the burnchain operations applied in the past N Stacks blocks.
     /// Only works for epoch 2.x
     pub fn get_burnchain_txids_in_ancestors(
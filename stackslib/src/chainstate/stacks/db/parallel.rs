@@ -0,0 +1,588 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Speculative parallel execution of a block's transactions.
+//!
+//! `ParallelTxBatch::execute()` runs each of a block's transactions on a worker thread, against a
+//! snapshot of the block's state as of the start of the batch, and records what each one read and
+//! wrote.  The transactions are then processed in order with `ParallelTxBatch::process_transaction()`:
+//! a speculative result is used only if nothing it read has been written since the snapshot, and
+//! the block's cost tracker would not have overflowed running it.  Otherwise the transaction is
+//! re-executed against the block's current state.  Either way, the outcome is identical to
+//! processing the transactions serially with `StacksChainState::process_transaction()`.
+//!
+//! Only evaluation runs in parallel.  Every read a worker makes is served by the thread which owns
+//! the block's state, one at a time (`serve_snapshot()`), and checking and applying the results
+//! is serial.  So a block gains from more threads only to the extent that its transactions spend
+//! their time evaluating Clarity code rather than reading state, and only with as many cores:
+//! compute-heavy contract calls can speed up, whereas read-bound transactions like STX transfers
+//! cannot, and pay a channel round trip per read on top.  `test::parallel_execution_speedup`
+//! measures both kinds.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+
+use clarity::vm::ast::ASTRules;
+use clarity::vm::costs::{ExecutionCost, LimitedCostTracker};
+use clarity::vm::types::QualifiedContractIdentifier;
+use stacks_common::types::chainstate::TrieHash;
+
+use crate::burnchains::Txid;
+use crate::chainstate::stacks::db::{ClarityTx, DBConfig, StacksChainState};
+use crate::chainstate::stacks::events::StacksTransactionReceipt;
+use crate::chainstate::stacks::{Error, StacksTransaction};
+use crate::clarity_vm::clarity::ClarityTransactionConnection;
+use crate::clarity_vm::database::speculative::{
+    serve_snapshot, snapshot_channel, SnapshotClient, SnapshotRequest, SpeculativeEffects,
+    SpeculativeStore,
+};
+use crate::core::StacksEpochId;
+
+/// The outcome of running a transaction against the batch's snapshot
+struct SpeculativeTxResult {
+    fee: u64,
+    receipt: StacksTransactionReceipt,
+    effects: SpeculativeEffects,
+    /// How much the transaction added to the block's execution cost
+    cost: ExecutionCost,
+}
+
+/// What a worker needs to run transactions against the snapshot
+struct SpeculativeContext<'a> {
+    config: &'a DBConfig,
+    epoch: StacksEpochId,
+    ast_rules: ASTRules,
+    cost_track: Option<LimitedCostTracker>,
+    snapshot_cost: ExecutionCost,
+}
+
+impl SpeculativeContext<'_> {
+    /// Run `tx` against the snapshot, with the worker's own copy of the block's cost tracker.
+    /// Returns None if its result cannot be used: it failed, or it did something that a snapshot
+    /// cannot do.
+    fn run(
+        &self,
+        requests: &Sender<SnapshotRequest>,
+        cost_track: &mut Option<LimitedCostTracker>,
+        tx: &StacksTransaction,
+    ) -> Option<SpeculativeTxResult> {
+        StacksChainState::process_transaction_prechecks(self.config, tx, self.epoch, true).ok()?;
+
+        if let Some(track @ LimitedCostTracker::Limited(_)) = cost_track.as_mut() {
+            track.set_total(self.snapshot_cost.clone());
+        }
+        let mut base = SnapshotClient::new(requests.clone());
+        let dbs = SnapshotClient::new(requests.clone());
+        let mut store = SpeculativeStore::new(&mut base);
+        let result = {
+            let transaction = ClarityTransactionConnection::new(
+                &mut store,
+                &dbs,
+                &dbs,
+                cost_track,
+                self.config.mainnet,
+                self.config.chain_id,
+                self.epoch,
+            );
+            StacksChainState::process_transaction_in(
+                transaction,
+                tx,
+                self.epoch,
+                true,
+                self.ast_rules,
+            )
+        };
+        let effects = store.into_effects();
+        if base.unsupported() {
+            return None;
+        }
+        let (fee, receipt) = result.ok()?;
+
+        let mut cost = cost_track
+            .as_ref()
+            .map(|track| track.get_total())
+            .unwrap_or(ExecutionCost::ZERO);
+        cost.sub(&self.snapshot_cost).ok()?;
+        Some(SpeculativeTxResult {
+            fee,
+            receipt,
+            effects,
+            cost,
+        })
+    }
+}
+
+/// A batch of a block's transactions which were speculatively executed in parallel
+#[derive(Default)]
+pub struct ParallelTxBatch {
+    /// Speculative results of the transactions which have yet to be processed, in order
+    results: VecDeque<(Txid, Option<SpeculativeTxResult>)>,
+    /// MARF paths written since the snapshot
+    written: HashSet<TrieHash>,
+    /// Contract metadata written since the snapshot
+    metadata_written: HashSet<(QualifiedContractIdentifier, String)>,
+    /// How many transactions had to be re-executed
+    num_reexecuted: u64,
+}
+
+impl ParallelTxBatch {
+    /// Speculatively execute `txs` on `num_threads` threads, against the current state of
+    /// `clarity_tx`.  Nothing is written to `clarity_tx`; the transactions must then be processed
+    /// in order with `process_transaction()`.
+    pub fn execute(
+        clarity_tx: &mut ClarityTx,
+        txs: &[StacksTransaction],
+        num_threads: usize,
+        ast_rules: ASTRules,
+    ) -> ParallelTxBatch {
+        let num_threads = num_threads.min(txs.len());
+        if num_threads == 0 {
+            return ParallelTxBatch::default();
+        }
+
+        let config = clarity_tx.config.clone();
+        let ctx = SpeculativeContext {
+            config: &config,
+            epoch: clarity_tx.get_epoch(),
+            ast_rules,
+            cost_track: clarity_tx.connection().cost_tracker().cloned(),
+            snapshot_cost: clarity_tx.cost_so_far(),
+        };
+        let next_tx = AtomicUsize::new(0);
+        let (requests_tx, requests_rx) = snapshot_channel();
+        let (results_tx, results_rx) = mpsc::channel();
+
+        clarity_tx
+            .connection()
+            .with_backing_store(|store, headers_db, burn_state_db| {
+                thread::scope(|scope| {
+                    for _ in 0..num_threads {
+                        let (ctx, next_tx) = (&ctx, &next_tx);
+                        let requests = requests_tx.clone();
+                        let results = results_tx.clone();
+                        scope.spawn(move || {
+                            let mut cost_track = ctx.cost_track.clone();
+                            loop {
+                                let i = next_tx.fetch_add(1, Ordering::SeqCst);
+                                let Some(tx) = txs.get(i) else {
+                                    break;
+                                };
+                                let result = ctx.run(&requests, &mut cost_track, tx);
+                                let _ = results.send((i, result));
+                            }
+                        });
+                    }
+                    drop(requests_tx);
+                    drop(results_tx);
+                    serve_snapshot(requests_rx, store, headers_db, burn_state_db);
+                })
+            });
+
+        let mut results: Vec<_> = txs.iter().map(|tx| (tx.txid(), None)).collect();
+        for (i, result) in results_rx.try_iter() {
+            results[i].1 = result;
+        }
+        ParallelTxBatch {
+            results: results.into(),
+            ..ParallelTxBatch::default()
+        }
+    }
+
+    /// How many transactions so far could not use their speculative result
+    pub fn num_reexecuted(&self) -> u64 {
+        self.num_reexecuted
+    }
+
+    /// Process the next transaction of the batch, using its speculative result if it is still
+    /// valid.  Behaves exactly like `StacksChainState::process_transaction()`.
+    ///
+    /// If `tx` is not the next transaction of the batch (e.g. because the caller skipped one),
+    /// the rest of the batch is discarded and `tx` is processed serially.
+    pub fn process_transaction(
+        &mut self,
+        clarity_tx: &mut ClarityTx,
+        tx: &StacksTransaction,
+        quiet: bool,
+        ast_rules: ASTRules,
+    ) -> Result<(u64, StacksTransactionReceipt), Error> {
+        let speculative = match self.results.pop_front() {
+            Some((txid, result)) if txid == tx.txid() => result,
+            _ => {
+                self.results.clear();
+                None
+            }
+        };
+        if let Some(result) = speculative {
+            if let Some(applied) = self.try_apply(clarity_tx, result) {
+                return applied;
+            }
+        }
+        self.reexecute(clarity_tx, tx, quiet, ast_rules)
+    }
+
+    /// Apply a speculative result to `clarity_tx`, if it is still valid
+    fn try_apply(
+        &mut self,
+        clarity_tx: &mut ClarityTx,
+        result: SpeculativeTxResult,
+    ) -> Option<Result<(u64, StacksTransactionReceipt), Error>> {
+        if result
+            .effects
+            .conflicts_with(&self.written, &self.metadata_written)
+        {
+            return None;
+        }
+        let mut total = clarity_tx.cost_so_far();
+        total.add(&result.cost).ok()?;
+        if let Some(limit) = clarity_tx.block_limit() {
+            if total.exceeds(&limit) {
+                return None;
+            }
+        }
+
+        self.record_writes(&result.effects);
+        let applied = clarity_tx
+            .connection()
+            .with_backing_store(|store, _, _| result.effects.apply(store));
+        if let Err(e) = applied {
+            return Some(Err(Error::ClarityError(e.into())));
+        }
+        if clarity_tx
+            .connection()
+            .cost_tracker()
+            .is_some_and(|track| !matches!(track, LimitedCostTracker::Free))
+        {
+            clarity_tx.reset_cost(total);
+        }
+        Some(Ok((result.fee, result.receipt)))
+    }
+
+    /// Process `tx` against the current state of `clarity_tx`
+    fn reexecute(
+        &mut self,
+        clarity_tx: &mut ClarityTx,
+        tx: &StacksTransaction,
+        quiet: bool,
+        ast_rules: ASTRules,
+    ) -> Result<(u64, StacksTransactionReceipt), Error> {
+        self.num_reexecuted += 1;
+        let epoch = clarity_tx.get_epoch();
+        StacksChainState::process_transaction_prechecks(&clarity_tx.config, tx, epoch, true)?;
+
+        let (mainnet, chain_id) = (clarity_tx.config.mainnet, clarity_tx.config.chain_id);
+        let mut cost_track = Some(clarity_tx.set_cost_tracker(LimitedCostTracker::new_free()));
+        let (result, effects) =
+            clarity_tx
+                .connection()
+                .with_backing_store(|store, headers_db, burn_state_db| {
+                    let mut spec_store = SpeculativeStore::new(store);
+                    let transaction = ClarityTransactionConnection::new(
+                        &mut spec_store,
+                        headers_db,
+                        burn_state_db,
+                        &mut cost_track,
+                        mainnet,
+                        chain_id,
+                        epoch,
+                    );
+                    let result = StacksChainState::process_transaction_in(
+                        transaction,
+                        tx,
+                        epoch,
+                        quiet,
+                        ast_rules,
+                    );
+                    (result, spec_store.into_effects())
+                });
+        clarity_tx.set_cost_tracker(cost_track.expect("BUG: lost cost tracker"));
+        let result = result?;
+
+        self.record_writes(&effects);
+        clarity_tx
+            .connection()
+            .with_backing_store(|store, _, _| effects.apply(store))
+            .map_err(|e| Error::ClarityError(e.into()))?;
+        Ok(result)
+    }
+
+    fn record_writes(&mut self, effects: &SpeculativeEffects) {
+        if self.results.is_empty() {
+            // nothing left to check for conflicts
+            return;
+        }
+        self.written.extend(effects.write_set());
+        self.metadata_written.extend(
+            effects
+                .metadata_writes
+                .iter()
+                .map(|(contract_key, _)| contract_key.clone()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use clarity::vm::types::PrincipalData;
+    use stacks_common::types::chainstate::{BlockHeaderHash, ConsensusHash, StacksAddress};
+    use stacks_common::util::hash::Hash160;
+
+    use super::*;
+    use crate::chainstate::stacks::db::test::instantiate_chainstate;
+    use crate::chainstate::stacks::db::transactions::test::TestBurnStateDB_30;
+    use crate::chainstate::stacks::*;
+    use crate::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
+
+    fn make_tx(
+        privk: &StacksPrivateKey,
+        nonce: u64,
+        payload: TransactionPayload,
+    ) -> StacksTransaction {
+        let auth = TransactionAuth::from_p2pkh(privk).unwrap();
+        let mut tx = StacksTransaction::new(TransactionVersion::Testnet, auth, payload);
+        tx.chain_id = 0x80000000;
+        tx.post_condition_mode = TransactionPostConditionMode::Allow;
+        tx.set_tx_fee(100);
+        tx.set_origin_nonce(nonce);
+        let mut signer = StacksTransactionSigner::new(&tx);
+        signer.sign_origin(privk).unwrap();
+        signer.get_tx().unwrap()
+    }
+
+    fn transfer(to: u8, amount: u64) -> TransactionPayload {
+        let recipient = StacksAddress::new(1, Hash160([to; 20])).unwrap();
+        TransactionPayload::TokenTransfer(recipient.into(), amount, TokenTransferMemo([0u8; 34]))
+    }
+
+    #[test]
+    fn parallel_execution_matches_serial() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let privks: Vec<_> = (0..6).map(|_| StacksPrivateKey::random()).collect();
+        let addrs: Vec<PrincipalData> = privks
+            .iter()
+            .map(|privk| {
+                let auth = TransactionAuth::from_p2pkh(privk).unwrap();
+                auth.origin().address_testnet().into()
+            })
+            .collect();
+        let deployer = match &addrs[0] {
+            PrincipalData::Standard(addr) => StacksAddress::from(addr.clone()),
+            _ => unreachable!(),
+        };
+
+        let contract = "
+            (define-data-var counter uint u0)
+            (define-public (incr) (begin (var-set counter (+ (var-get counter) u1)) (ok (var-get counter))))";
+        let txs = vec![
+            make_tx(
+                &privks[0],
+                0,
+                TransactionPayload::new_smart_contract("counter", contract, None).unwrap(),
+            ),
+            // reads the contract deployed just before it
+            make_tx(
+                &privks[1],
+                0,
+                TransactionPayload::new_contract_call(deployer.clone(), "counter", "incr", vec![])
+                    .unwrap(),
+            ),
+            make_tx(
+                &privks[2],
+                0,
+                TransactionPayload::new_contract_call(deployer, "counter", "incr", vec![]).unwrap(),
+            ),
+            // independent transfers
+            make_tx(&privks[3], 0, transfer(0xf0, 1000)),
+            make_tx(&privks[4], 0, transfer(0xf1, 1000)),
+            // depends on the nonce written by the transfer before it
+            make_tx(&privks[3], 1, transfer(0xf2, 1000)),
+            // bad nonce
+            make_tx(&privks[5], 3, transfer(0xf3, 1000)),
+        ];
+
+        let mut run = |tx_execution_threads: usize| {
+            let mut conn = chainstate.block_begin(
+                &TestBurnStateDB_30,
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+                &ConsensusHash([0x01; 20]),
+                &BlockHeaderHash([0x01; 32]),
+            );
+            for addr in addrs.iter() {
+                conn.connection()
+                    .as_transaction(|tx| StacksChainState::account_credit(tx, addr, 1_000_000));
+            }
+
+            let mut batch = (tx_execution_threads > 1).then(|| {
+                ParallelTxBatch::execute(
+                    &mut conn,
+                    &txs,
+                    tx_execution_threads,
+                    ASTRules::PrecheckSize,
+                )
+            });
+            let results: Vec<_> = txs
+                .iter()
+                .map(|tx| {
+                    match batch.as_mut() {
+                        Some(batch) => {
+                            batch.process_transaction(&mut conn, tx, false, ASTRules::PrecheckSize)
+                        }
+                        None => StacksChainState::process_transaction(
+                            &mut conn,
+                            tx,
+                            false,
+                            ASTRules::PrecheckSize,
+                        ),
+                    }
+                    .map_err(|e| e.to_string())
+                })
+                .collect();
+            let cost = conn.cost_so_far();
+            let root_hash = conn.seal();
+            conn.rollback_block();
+            (results, cost, root_hash, batch.map(|b| b.num_reexecuted()))
+        };
+
+        let (serial_results, serial_cost, serial_root_hash, _) = run(1);
+        assert!(serial_results[..6].iter().all(|result| result.is_ok()));
+        assert!(serial_results[6].is_err());
+
+        let (results, cost, root_hash, num_reexecuted) = run(4);
+        assert_eq!(results, serial_results);
+        assert_eq!(cost, serial_cost);
+        assert_eq!(root_hash, serial_root_hash);
+
+        // the contract calls, the dependent transfer, and the failed transaction
+        assert_eq!(num_reexecuted, Some(4));
+    }
+
+    /// Compare the time it takes to process a batch of independent transactions serially and in
+    /// parallel, for transactions dominated by evaluation and for transactions dominated by
+    /// reads.  Run with `cargo test -p stackslib --release -- --ignored --nocapture
+    /// parallel_execution_speedup`.
+    #[test]
+    #[ignore]
+    fn parallel_execution_speedup() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, function_name!());
+        let num_txs = 64;
+        let deployer_privk = StacksPrivateKey::random();
+        let privks: Vec<_> = (0..num_txs).map(|_| StacksPrivateKey::random()).collect();
+        let addrs: Vec<PrincipalData> = privks
+            .iter()
+            .chain([&deployer_privk])
+            .map(|privk| {
+                let auth = TransactionAuth::from_p2pkh(privk).unwrap();
+                auth.origin().address_testnet().into()
+            })
+            .collect();
+        let deployer = match addrs.last().unwrap() {
+            PrincipalData::Standard(addr) => StacksAddress::from(addr.clone()),
+            _ => unreachable!(),
+        };
+
+        let steps: Vec<_> = (0..500).map(|i| i.to_string()).collect();
+        let contract = format!(
+            "
+            (define-private (step (i int) (acc (buff 32))) (sha256 (concat acc acc)))
+            (define-public (work) (ok (fold step (list {}) (sha256 0x00))))",
+            steps.join(" ")
+        );
+        let deploy = make_tx(
+            &deployer_privk,
+            0,
+            TransactionPayload::new_smart_contract("work", &contract, None).unwrap(),
+        );
+        let compute_txs: Vec<_> = privks
+            .iter()
+            .map(|privk| {
+                let payload =
+                    TransactionPayload::new_contract_call(deployer, "work", "work", vec![])
+                        .unwrap();
+                make_tx(privk, 0, payload)
+            })
+            .collect();
+        let transfer_txs: Vec<_> = privks
+            .iter()
+            .enumerate()
+            .map(|(i, privk)| make_tx(privk, 0, transfer(i as u8, 1000)))
+            .collect();
+
+        let mut run = |txs: &[StacksTransaction], tx_execution_threads: usize| {
+            let mut conn = chainstate.block_begin(
+                &TestBurnStateDB_30,
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+                &ConsensusHash([0x01; 20]),
+                &BlockHeaderHash([0x01; 32]),
+            );
+            for addr in addrs.iter() {
+                conn.connection()
+                    .as_transaction(|tx| StacksChainState::account_credit(tx, addr, 1_000_000));
+            }
+            StacksChainState::process_transaction(
+                &mut conn,
+                &deploy,
+                false,
+                ASTRules::PrecheckSize,
+            )
+            .unwrap();
+
+            let start = Instant::now();
+            let mut batch = (tx_execution_threads > 1).then(|| {
+                ParallelTxBatch::execute(
+                    &mut conn,
+                    txs,
+                    tx_execution_threads,
+                    ASTRules::PrecheckSize,
+                )
+            });
+            for tx in txs.iter() {
+                match batch.as_mut() {
+                    Some(batch) => {
+                        batch.process_transaction(&mut conn, tx, false, ASTRules::PrecheckSize)
+                    }
+                    None => StacksChainState::process_transaction(
+                        &mut conn,
+                        tx,
+                        false,
+                        ASTRules::PrecheckSize,
+                    ),
+                }
+                .unwrap();
+            }
+            let elapsed = start.elapsed();
+            let root_hash = conn.seal();
+            conn.rollback_block();
+            assert_eq!(batch.map_or(0, |b| b.num_reexecuted()), 0);
+            (elapsed, root_hash)
+        };
+
+        for (name, txs) in [("compute", &compute_txs), ("transfer", &transfer_txs)] {
+            let (serial_time, serial_root_hash) = run(txs, 1);
+            for tx_execution_threads in [2, 4, 8] {
+                let (time, root_hash) = run(txs, tx_execution_threads);
+                assert_eq!(root_hash, serial_root_hash);
+                eprintln!(
+                    "{name}: {num_txs} txs, {tx_execution_threads} threads: {time:?} (serial: {serial_time:?}, speedup {:.2}x)",
+                    serial_time.as_secs_f64() / time.as_secs_f64()
+                );
+            }
+        }
+    }
+}
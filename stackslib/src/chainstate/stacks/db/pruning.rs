@@ -0,0 +1,473 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Pruned-node support.
+//!
+//! A pruned node discards the bodies of processed blocks from tenures which started before a
+//! configurable number of reward cycles ago: Nakamoto blocks in the staging blocks DB, and
+//! epoch 2.x block files in the chunk store.  Either way, the block's staging row is kept and
+//! marked as pruned.  Block headers are always kept, so the node can still validate new blocks
+//! and serve headers.
+//!
+//! The same blocks' tries are then pruned from the Clarity and headers MARFs (see
+//! `chainstate::stacks::index::prune`), which frees the parts of the chainstate that only
+//! those blocks could see.  The node can no longer read state as of these blocks, nor process
+//! blocks which build on them.  The tries of the blocks from the last `MIN_PRUNE_REWARD_CYCLES`
+//! reward cycles before the highest processed block are always kept, even if the Stacks chain
+//! stalls, so the node can always build on its chain tip.
+
+use std::path::PathBuf;
+use std::{fs, io, thread};
+
+use rusqlite::{params, Connection, Row};
+use stacks_common::types::chainstate::{BlockHeaderHash, StacksBlockId};
+use stacks_common::types::sqlite::NO_PARAMS;
+
+use crate::burnchains::Burnchain;
+use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::index::prune::prune_marf_tries;
+use crate::chainstate::stacks::Error;
+use crate::util_lib::db::{
+    query_row, query_row_columns, u64_to_sql, Error as db_error, FromColumn, FromRow,
+};
+
+/// The fewest reward cycles a pruned node will keep, counting the current one.  The current
+/// reward cycle's PoX anchor block is mined in the previous reward cycle's prepare phase, so the
+/// previous reward cycle is always kept.
+pub const MIN_PRUNE_REWARD_CYCLES: u64 = 2;
+
+/// How many blocks to discard per staging DB transaction
+const PRUNE_BATCH_SIZE: usize = 1000;
+
+/// How far a pruned node has pruned its block data
+#[derive(Debug, Clone, PartialEq)]
+pub struct PruneHorizon {
+    /// The bodies of blocks from tenures which started before this reward cycle are gone
+    pub reward_cycle: u64,
+    /// The first burnchain block height of `reward_cycle`
+    pub burn_height: u64,
+}
+
+impl FromRow<PruneHorizon> for PruneHorizon {
+    fn from_row(row: &Row) -> Result<PruneHorizon, db_error> {
+        let reward_cycle = u64::from_column(row, "reward_cycle")?;
+        let burn_height = u64::from_column(row, "burn_height")?;
+        Ok(PruneHorizon {
+            reward_cycle,
+            burn_height,
+        })
+    }
+}
+
+/// What a call to `StacksChainState::prune_chainstate()` discarded
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PruneStats {
+    pub nakamoto_blocks: u64,
+    pub epoch2_blocks: u64,
+    /// Number of tries pruned from the Clarity and headers MARFs
+    pub marf_tries: u64,
+    /// Number of bytes those tries released
+    pub marf_bytes_released: u64,
+}
+
+impl StacksChainState {
+    /// Get the prune horizon, if this node has pruned anything
+    pub fn get_prune_horizon(conn: &Connection) -> Result<Option<PruneHorizon>, Error> {
+        let sql = "SELECT reward_cycle,burn_height FROM chainstate_pruning WHERE id = 0";
+        Ok(query_row(conn, sql, NO_PARAMS)?)
+    }
+
+    /// Has the block data for the tenures which started in this reward cycle been pruned?
+    pub fn is_reward_cycle_pruned(conn: &Connection, reward_cycle: u64) -> Result<bool, Error> {
+        Ok(Self::get_prune_horizon(conn)?
+            .is_some_and(|horizon| reward_cycle < horizon.reward_cycle))
+    }
+
+    /// Has the data of this processed block been pruned?  Returns false for unknown blocks.
+    pub fn is_block_pruned(conn: &Connection, block_id: &StacksBlockId) -> Result<bool, Error> {
+        let Some(horizon) = Self::get_prune_horizon(conn)? else {
+            return Ok(false);
+        };
+        let Some(header) = NakamotoChainState::get_block_header(conn, block_id)? else {
+            return Ok(false);
+        };
+        Ok(u64::from(header.burn_header_height) < horizon.burn_height)
+    }
+
+    /// Has the MARF state as of this processed block been pruned?  Returns false for unknown
+    /// blocks.
+    pub fn is_block_state_pruned(
+        conn: &Connection,
+        block_id: &StacksBlockId,
+    ) -> Result<bool, Error> {
+        let marf_burn_height = Self::get_marf_prune_height(conn)?;
+        if marf_burn_height == 0 {
+            return Ok(false);
+        }
+        let Some(header) = NakamotoChainState::get_block_header(conn, block_id)? else {
+            return Ok(false);
+        };
+        Ok(u64::from(header.burn_header_height) < marf_burn_height)
+    }
+
+    /// Get the burnchain height before which blocks' MARF tries have been pruned, or 0
+    fn get_marf_prune_height(conn: &Connection) -> Result<u64, Error> {
+        let sql = "SELECT marf_burn_height FROM chainstate_pruning WHERE id = 0";
+        let height: Option<u64> = query_row(conn, sql, NO_PARAMS)?;
+        Ok(height.unwrap_or(0))
+    }
+
+    fn set_prune_horizon(conn: &Connection, horizon: &PruneHorizon) -> Result<(), Error> {
+        let sql = "INSERT INTO chainstate_pruning (id,reward_cycle,burn_height) VALUES (0,?1,?2)
+                   ON CONFLICT(id) DO UPDATE SET reward_cycle = excluded.reward_cycle, burn_height = excluded.burn_height";
+        let args = params![
+            u64_to_sql(horizon.reward_cycle)?,
+            u64_to_sql(horizon.burn_height)?
+        ];
+        conn.execute(sql, args)?;
+        Ok(())
+    }
+
+    /// If this is a pruned node, then get the reward cycle before which block data should be
+    /// discarded as of the given burnchain height
+    fn prune_target_reward_cycle(
+        &self,
+        burnchain: &Burnchain,
+        burn_tip_height: u64,
+    ) -> Option<u64> {
+        let keep_reward_cycles = self.prune_reward_cycles?.max(MIN_PRUNE_REWARD_CYCLES);
+        let tip_reward_cycle = burnchain.block_height_to_reward_cycle(burn_tip_height)?;
+        // `None` if nothing is old enough yet
+        (tip_reward_cycle + 1).checked_sub(keep_reward_cycles)
+    }
+
+    /// If this is a pruned node, then discard the bodies of the processed blocks which have
+    /// fallen out of the retention window as of the given burnchain height.  Does nothing
+    /// unless the window has moved since the last call.
+    ///
+    /// Returns the new prune horizon and what was discarded, if anything was pruned.
+    pub fn prune_chainstate(
+        &mut self,
+        burnchain: &Burnchain,
+        burn_tip_height: u64,
+    ) -> Result<Option<(PruneHorizon, PruneStats)>, Error> {
+        let Some(reward_cycle) = self.prune_target_reward_cycle(burnchain, burn_tip_height) else {
+            return Ok(None);
+        };
+        let bodies_pruned = self.prune_to_reward_cycle(burnchain, reward_cycle)?;
+        let Some(horizon) = Self::get_prune_horizon(self.db())? else {
+            return Ok(bodies_pruned);
+        };
+        let (bodies_moved, mut stats) = match bodies_pruned {
+            Some((_, stats)) => (true, stats),
+            None => (false, PruneStats::default()),
+        };
+        let marf_moved = match self.marf_prune_range(burnchain, &horizon)? {
+            Some((start_height, end_height)) => {
+                self.prune_marf_tries_in_range(start_height, end_height, &mut stats)?;
+                true
+            }
+            None => false,
+        };
+        Ok((bodies_moved || marf_moved).then_some((horizon, stats)))
+    }
+
+    /// Get the range of burnchain heights whose blocks' MARF tries are due to be pruned, given
+    /// the prune horizon, if there are any.  The tries of the blocks from the last
+    /// `MIN_PRUNE_REWARD_CYCLES` reward cycles before the highest processed block are kept.
+    fn marf_prune_range(
+        &self,
+        burnchain: &Burnchain,
+        horizon: &PruneHorizon,
+    ) -> Result<Option<(u64, u64)>, Error> {
+        // both tables are indexed by burnchain height, so this is cheap
+        let sql = "SELECT MAX(IFNULL((SELECT MAX(burn_header_height) FROM block_headers), 0),
+                              IFNULL((SELECT MAX(burn_header_height) FROM nakamoto_block_headers), 0))";
+        let highest_burn_height: Option<u64> = query_row(self.db(), sql, NO_PARAMS)?;
+        let Some(highest_reward_cycle) =
+            highest_burn_height.and_then(|height| burnchain.block_height_to_reward_cycle(height))
+        else {
+            return Ok(None);
+        };
+        let Some(keep_reward_cycle) =
+            (highest_reward_cycle + 1).checked_sub(MIN_PRUNE_REWARD_CYCLES)
+        else {
+            return Ok(None);
+        };
+        let start_height = Self::get_marf_prune_height(self.db())?;
+        let end_height = horizon
+            .burn_height
+            .min(burnchain.nakamoto_first_block_of_cycle(keep_reward_cycle));
+        Ok((end_height > start_height).then_some((start_height, end_height)))
+    }
+
+    /// Prune the Clarity and headers MARF tries of the processed blocks from burnchain blocks
+    /// in the given range of heights.  Adds what was pruned to `stats`.
+    fn prune_marf_tries_in_range(
+        &mut self,
+        start_height: u64,
+        end_height: u64,
+        stats: &mut PruneStats,
+    ) -> Result<(), Error> {
+        // the boot block's trie is kept, since it holds the genesis state
+        let sql = "SELECT index_block_hash FROM block_headers WHERE block_height > 0 AND burn_header_height >= ?1 AND burn_header_height < ?2
+                   UNION SELECT index_block_hash FROM nakamoto_block_headers WHERE burn_header_height >= ?1 AND burn_header_height < ?2";
+        let args = params![u64_to_sql(start_height)?, u64_to_sql(end_height)?];
+        let block_ids: Vec<StacksBlockId> =
+            query_row_columns(self.db(), sql, args, "index_block_hash")?;
+        let headers_path = Self::header_index_root_path(PathBuf::from(&self.root_path));
+        let headers_path = headers_path.to_str().ok_or(Error::InvalidChainstateDB)?;
+        for marf_path in [self.clarity_state_index_path.as_str(), headers_path] {
+            let marf_stats = prune_marf_tries(marf_path, &block_ids)?;
+            stats.marf_tries += marf_stats.num_tries;
+            stats.marf_bytes_released += marf_stats.bytes_released;
+        }
+
+        let sql = "UPDATE chainstate_pruning SET marf_burn_height = ?1 WHERE id = 0";
+        self.db().execute(sql, params![u64_to_sql(end_height)?])?;
+        info!("Pruned chainstate MARFs";
+              "marf_burn_height" => end_height,
+              "marf_tries" => stats.marf_tries,
+              "marf_bytes_released" => stats.marf_bytes_released);
+        Ok(())
+    }
+
+    /// If this is a pruned node and the retention window has moved as of the given burnchain
+    /// height, then run `prune_chainstate()` on a background thread with its own connections to
+    /// the chainstate, so the caller is not held up.  Does nothing while an earlier run is still
+    /// going.
+    ///
+    /// Returns true if a run was started.
+    pub fn start_pruning(
+        &mut self,
+        burnchain: &Burnchain,
+        burn_tip_height: u64,
+    ) -> Result<bool, Error> {
+        let Some(reward_cycle) = self.prune_target_reward_cycle(burnchain, burn_tip_height) else {
+            return Ok(false);
+        };
+        if self
+            .prune_thread
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+        {
+            return Ok(false);
+        }
+        if let Some(horizon) = Self::get_prune_horizon(self.db())? {
+            // the MARFs lag behind the horizon while the Stacks chain is stalled
+            if horizon.reward_cycle >= reward_cycle
+                && self.marf_prune_range(burnchain, &horizon)?.is_none()
+            {
+                return Ok(false);
+            }
+        }
+
+        let mainnet = self.mainnet;
+        let chain_id = self.chain_id;
+        let root_path = self.root_path.clone();
+        let marf_opts = self.marf_opts.clone();
+        let prune_reward_cycles = self.prune_reward_cycles;
+        let burnchain = burnchain.clone();
+        let handle = thread::Builder::new()
+            .name("chainstate-pruner".into())
+            .spawn(move || {
+                let result = StacksChainState::open(mainnet, chain_id, &root_path, marf_opts)
+                    .and_then(|(mut chainstate, _)| {
+                        chainstate.prune_reward_cycles = prune_reward_cycles;
+                        chainstate.prune_chainstate(&burnchain, burn_tip_height)
+                    });
+                // a failed run is retried on the next call
+                if let Err(e) = result {
+                    warn!("Failed to prune chainstate: {e:?}");
+                }
+            })
+            .map_err(|e| Error::DBError(db_error::IOError(e)))?;
+        self.prune_thread = Some(handle);
+        Ok(true)
+    }
+
+    /// Wait for the run started by `start_pruning()`, if there is one, to finish
+    pub fn wait_for_pruning(&mut self) {
+        if let Some(handle) = self.prune_thread.take() {
+            if handle.join().is_err() {
+                warn!("Chainstate pruning thread panicked");
+            }
+        }
+    }
+
+    /// Discard the bodies of the processed blocks from tenures which started before the given
+    /// reward cycle, regardless of this node's prune setting.  Does nothing if they are already
+    /// gone.
+    ///
+    /// Returns the new prune horizon and what was discarded, if anything was pruned.
+    pub fn prune_to_reward_cycle(
+        &mut self,
+        burnchain: &Burnchain,
+        reward_cycle: u64,
+    ) -> Result<Option<(PruneHorizon, PruneStats)>, Error> {
+        let last_horizon = Self::get_prune_horizon(self.db())?;
+        if last_horizon
+            .as_ref()
+            .is_some_and(|horizon| horizon.reward_cycle >= reward_cycle)
+        {
+            return Ok(None);
+        }
+        let start_height = last_horizon.map_or(0, |horizon| horizon.burn_height);
+        let horizon = PruneHorizon {
+            reward_cycle,
+            burn_height: burnchain.nakamoto_first_block_of_cycle(reward_cycle),
+        };
+        let args = params![u64_to_sql(start_height)?, u64_to_sql(horizon.burn_height)?];
+        let mut stats = PruneStats::default();
+
+        // Nakamoto blocks
+        let sql = "SELECT index_block_hash FROM nakamoto_block_headers WHERE burn_header_height >= ?1 AND burn_header_height < ?2";
+        let block_ids: Vec<StacksBlockId> =
+            query_row_columns(self.db(), sql, args, "index_block_hash")?;
+        // commit in batches, so the block processing thread is never kept waiting for long
+        for batch in block_ids.chunks(PRUNE_BATCH_SIZE) {
+            let staging_tx = self.staging_db_tx_begin()?;
+            for block_id in batch.iter() {
+                if staging_tx.set_block_pruned(block_id)? {
+                    stats.nakamoto_blocks += 1;
+                }
+            }
+            staging_tx.commit().map_err(db_error::SqliteError)?;
+        }
+
+        // epoch 2.x blocks.  Each block file is deleted before its block is marked as pruned, so
+        // an interrupted run leaves no file behind once it is retried.
+        let sql = "SELECT consensus_hash,anchored_block_hash FROM staging_blocks
+                   WHERE processed = 1 AND pruned = 0 AND index_block_hash IN
+                   (SELECT index_block_hash FROM block_headers WHERE burn_header_height >= ?1 AND burn_header_height < ?2)";
+        let mut stmt = self.db().prepare(sql)?;
+        let blocks = stmt
+            .query_and_then(args, |row| {
+                let consensus_hash = ConsensusHash::from_column(row, "consensus_hash")?;
+                let block_hash = BlockHeaderHash::from_column(row, "anchored_block_hash")?;
+                Ok((consensus_hash, block_hash))
+            })?
+            .collect::<Result<Vec<_>, db_error>>()?;
+        drop(stmt);
+        for (consensus_hash, block_hash) in blocks.iter() {
+            let block_path =
+                StacksChainState::get_block_path(&self.blocks_path, consensus_hash, block_hash)?;
+            match fs::remove_file(&block_path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(Error::DBError(db_error::IOError(e))),
+            }
+            let sql = "UPDATE staging_blocks SET pruned = 1 WHERE consensus_hash = ?1 AND anchored_block_hash = ?2";
+            self.db()
+                .execute(sql, params![consensus_hash, block_hash])?;
+            stats.epoch2_blocks += 1;
+        }
+
+        Self::set_prune_horizon(self.db(), &horizon)?;
+        info!("Pruned chainstate";
+              "reward_cycle" => horizon.reward_cycle,
+              "burn_height" => horizon.burn_height,
+              "nakamoto_blocks" => stats.nakamoto_blocks,
+              "epoch2_blocks" => stats.epoch2_blocks);
+        Ok(Some((horizon, stats)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::burn::db::sortdb::SortitionDB;
+    use crate::chainstate::nakamoto::coordinator::tests::simple_nakamoto_coordinator_10_tenures_10_sortitions;
+    use crate::chainstate::stacks::index::verify::verify_marf_blobs;
+
+    #[test]
+    fn test_start_pruning() {
+        let mut peer = simple_nakamoto_coordinator_10_tenures_10_sortitions();
+        let burnchain = peer.config.burnchain.clone();
+        let tip = SortitionDB::get_canonical_burn_chain_tip(peer.sortdb().conn()).unwrap();
+        let chainstate = &mut peer.stacks_node.as_mut().unwrap().chainstate;
+        let sort_db = peer.sortdb.as_mut().unwrap();
+        let stacks_tip = NakamotoChainState::get_canonical_block_header(chainstate.db(), sort_db)
+            .unwrap()
+            .unwrap()
+            .index_block_hash();
+        let first_block_id: StacksBlockId = chainstate
+            .db()
+            .query_row(
+                "SELECT index_block_hash FROM block_headers WHERE block_height = 1",
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap();
+
+        // an archival node prunes nothing
+        assert!(!chainstate
+            .start_pruning(&burnchain, tip.block_height)
+            .unwrap());
+
+        chainstate.prune_reward_cycles = Some(2);
+        assert!(chainstate
+            .start_pruning(&burnchain, tip.block_height)
+            .unwrap());
+        chainstate.wait_for_pruning();
+        let horizon = StacksChainState::get_prune_horizon(chainstate.db())
+            .unwrap()
+            .unwrap();
+        assert_eq!(horizon.reward_cycle, 8);
+
+        // the old blocks' MARF tries were pruned too, and state as of the tip is intact
+        assert_eq!(
+            StacksChainState::get_marf_prune_height(chainstate.db()).unwrap(),
+            horizon.burn_height
+        );
+        assert!(StacksChainState::is_block_state_pruned(chainstate.db(), &first_block_id).unwrap());
+        assert!(!StacksChainState::is_block_state_pruned(chainstate.db(), &stacks_tip).unwrap());
+        let ancestor = NakamotoChainState::get_ancestor_block_header_at_height(
+            &chainstate.index_conn(),
+            &stacks_tip,
+            1,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(ancestor.index_block_hash(), first_block_id);
+        assert!(NakamotoChainState::get_ancestor_block_header_at_height(
+            &chainstate.index_conn(),
+            &first_block_id,
+            1
+        )
+        .is_err());
+        for marf_path in [
+            chainstate.clarity_state_index_path.clone(),
+            StacksChainState::header_index_root_path(PathBuf::from(&chainstate.root_path))
+                .to_str()
+                .unwrap()
+                .to_string(),
+        ] {
+            let report = verify_marf_blobs::<StacksBlockId, _>(&marf_path, |_, _| None).unwrap();
+            assert!(report.is_ok());
+        }
+
+        // nothing more to do until the next reward cycle
+        assert!(!chainstate
+            .start_pruning(&burnchain, tip.block_height)
+            .unwrap());
+        assert!(chainstate
+            .prune_chainstate(&burnchain, tip.block_height)
+            .unwrap()
+            .is_none());
+    }
+}
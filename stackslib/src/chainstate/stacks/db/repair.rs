@@ -0,0 +1,350 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Repairing a stopped node's chainstate after a crash left its MARFs inconsistent.
+//!
+//! `verify_marf_blobs()` finds the tries at the end of the Clarity and headers MARFs which a
+//! crash left behind half-written.  Truncating them away is not enough on its own: the node
+//! still has the blocks' headers and other records, so it would consider them processed and
+//! never process them again.  `repair_chainstate_marfs()` forgets that these blocks were
+//! processed, so the node processes them again from its staging blocks DB (or downloads them
+//! again, if it no longer has them) once it restarts.
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection, OpenFlags};
+use stacks_common::types::chainstate::StacksBlockId;
+
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::chainstate::stacks::db::{StacksBlockHeaderTypes, StacksChainState, StacksHeaderInfo};
+use crate::chainstate::stacks::index::verify::{
+    truncate_marf_blobs, truncate_marf_tries, MarfVerifyReport,
+};
+use crate::chainstate::stacks::index::Error as MARFError;
+use crate::chainstate::stacks::Error;
+use crate::util_lib::db::{sqlite_open, tx_begin_immediate, u64_to_sql};
+
+/// Tables in the headers DB with a row per processed block, and the column holding its block ID
+const PROCESSED_BLOCK_TABLES: &[(&str, &str)] = &[
+    ("block_headers", "index_block_hash"),
+    ("nakamoto_block_headers", "index_block_hash"),
+    ("payments", "index_block_hash"),
+    ("transactions", "index_block_hash"),
+    ("transaction_index", "index_block_hash"),
+    ("burnchain_txids", "index_block_hash"),
+    ("epoch_transitions", "block_id"),
+    ("nakamoto_tenure_events", "block_id"),
+    ("nakamoto_reward_sets", "index_block_hash"),
+];
+
+/// Repair a stopped node's Clarity and headers MARFs, given `verify_marf_blobs()` reports for
+/// both of them.  The blocks whose tries are in either MARF's inconsistent tail are forgotten:
+/// their headers and other per-block records are deleted, their contract metadata is deleted,
+/// their staging blocks are marked as unprocessed, and any canonical Stacks tip in the sortition
+/// DB (at `sortdb_path`) which is one of them is moved back to its closest remaining ancestor.
+/// Then both MARFs are truncated to before these blocks' tries, so they stay consistent with
+/// each other.
+///
+/// The records are forgotten before the MARFs are truncated, so if this is interrupted, the
+/// blocks' remaining tries no longer have headers, and verifying and repairing again finishes
+/// the job.
+///
+/// Returns the IDs of the blocks which must be processed again.
+pub fn repair_chainstate_marfs(
+    chainstate_path: &str,
+    sortdb_path: &str,
+    clarity_report: &MarfVerifyReport<StacksBlockId>,
+    headers_report: &MarfVerifyReport<StacksBlockId>,
+) -> Result<Vec<StacksBlockId>, Error> {
+    if !clarity_report.is_repairable() || !headers_report.is_repairable() {
+        return Err(Error::MARFError(MARFError::CorruptionError(
+            "MARF has inconsistent tries before its last good trie, so it cannot be truncated"
+                .to_string(),
+        )));
+    }
+    let mut block_ids = vec![];
+    for bad in clarity_report
+        .tail_tries()
+        .chain(headers_report.tail_tries())
+    {
+        if !block_ids.contains(&bad.block_hash) {
+            block_ids.push(bad.block_hash);
+        }
+    }
+
+    let chainstate_path = PathBuf::from(chainstate_path);
+    let headers_path = StacksChainState::header_index_root_path(chainstate_path.clone());
+    let clarity_path = StacksChainState::vm_state_index_marf_path(chainstate_path.clone());
+    let mut staging_path = StacksChainState::blocks_path(chainstate_path);
+    staging_path.push("nakamoto.sqlite");
+    let sortdb_path = PathBuf::from(sortdb_path).join("marf.sqlite");
+    let (Some(headers_path), Some(clarity_path)) = (headers_path.to_str(), clarity_path.to_str())
+    else {
+        return Err(Error::InvalidChainstateDB);
+    };
+
+    forget_processed_blocks(headers_path, &sortdb_path, &block_ids)?;
+    let staging_db = sqlite_open(&staging_path, OpenFlags::SQLITE_OPEN_READ_WRITE, false)?;
+    let clarity_db = sqlite_open(clarity_path, OpenFlags::SQLITE_OPEN_READ_WRITE, false)?;
+    for block_id in block_ids.iter() {
+        staging_db.execute(
+            "UPDATE nakamoto_staging_blocks SET processed = 0, orphaned = 0, processed_time = 0 WHERE index_block_hash = ?1",
+            params![block_id],
+        )?;
+        clarity_db.execute(
+            "DELETE FROM metadata_table WHERE blockhash = ?1",
+            params![block_id],
+        )?;
+    }
+    drop(clarity_db);
+
+    if !clarity_report.is_ok() {
+        truncate_marf_blobs(clarity_path, clarity_report)?;
+    }
+    if !headers_report.is_ok() {
+        truncate_marf_blobs(headers_path, headers_report)?;
+    }
+    truncate_marf_tries(clarity_path, &block_ids)?;
+    truncate_marf_tries(headers_path, &block_ids)?;
+
+    info!("Repaired chainstate MARFs";
+          "blocks_to_reprocess" => block_ids.len());
+    Ok(block_ids)
+}
+
+/// Forget that `block_ids` were processed: delete their records from the headers DB at
+/// `headers_path`, mark their Stacks 2.x staging blocks as unprocessed, and move any canonical
+/// Stacks tip in the sortition DB at `sortdb_path` which is one of them back to its closest
+/// ancestor which is not.  Blocks without a header are skipped.
+fn forget_processed_blocks(
+    headers_path: &str,
+    sortdb_path: &Path,
+    block_ids: &[StacksBlockId],
+) -> Result<(), Error> {
+    let mut headers_db = sqlite_open(headers_path, OpenFlags::SQLITE_OPEN_READ_WRITE, true)?;
+    let mut sortdb = sqlite_open(sortdb_path, OpenFlags::SQLITE_OPEN_READ_WRITE, true)?;
+
+    let sort_tx = tx_begin_immediate(&mut sortdb)?;
+    for block_id in block_ids.iter() {
+        let Some(header) = NakamotoChainState::get_block_header(&headers_db, block_id)? else {
+            continue;
+        };
+        let ancestor = closest_remaining_ancestor(&headers_db, &header, block_ids)?;
+        let args = params![
+            ancestor.consensus_hash,
+            ancestor.anchored_header.block_hash(),
+            u64_to_sql(ancestor.stacks_block_height)?,
+            header.consensus_hash,
+            header.anchored_header.block_hash(),
+        ];
+        sort_tx.execute(
+            "UPDATE stacks_chain_tips SET consensus_hash = ?1, block_hash = ?2, block_height = ?3 WHERE consensus_hash = ?4 AND block_hash = ?5",
+            args,
+        )?;
+        sort_tx.execute(
+            "UPDATE snapshots SET canonical_stacks_tip_consensus_hash = ?1, canonical_stacks_tip_hash = ?2, canonical_stacks_tip_height = ?3 WHERE canonical_stacks_tip_consensus_hash = ?4 AND canonical_stacks_tip_hash = ?5",
+            args,
+        )?;
+    }
+    sort_tx.commit()?;
+
+    let headers_tx = tx_begin_immediate(&mut headers_db)?;
+    for block_id in block_ids.iter() {
+        for (table, column) in PROCESSED_BLOCK_TABLES.iter() {
+            headers_tx.execute(
+                &format!("DELETE FROM {table} WHERE {column} = ?1"),
+                params![block_id],
+            )?;
+        }
+        headers_tx.execute(
+            "UPDATE staging_blocks SET processed = 0, orphaned = 0, attachable = 1, processed_time = 0 WHERE index_block_hash = ?1",
+            params![block_id],
+        )?;
+    }
+    headers_tx.commit()?;
+    Ok(())
+}
+
+/// Get the closest ancestor of `header` which is not one of `block_ids`
+fn closest_remaining_ancestor(
+    headers_db: &Connection,
+    header: &StacksHeaderInfo,
+    block_ids: &[StacksBlockId],
+) -> Result<StacksHeaderInfo, Error> {
+    let mut header = header.clone();
+    loop {
+        let parent_block_id = match &header.anchored_header {
+            StacksBlockHeaderTypes::Nakamoto(nakamoto_header) => nakamoto_header.parent_block_id,
+            StacksBlockHeaderTypes::Epoch2(_) => {
+                StacksChainState::get_parent_block_id(headers_db, &header.index_block_hash())?
+                    .ok_or(Error::NoSuchBlockError)?
+            }
+        };
+        header = NakamotoChainState::get_block_header(headers_db, &parent_block_id)?
+            .ok_or(Error::NoSuchBlockError)?;
+        if !block_ids.contains(&parent_block_id) {
+            return Ok(header);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::OpenOptions;
+
+    use stacks_common::types::chainstate::TrieHash;
+    use stacks_common::types::sqlite::NO_PARAMS;
+
+    use super::*;
+    use crate::chainstate::burn::db::sortdb::SortitionDB;
+    use crate::chainstate::nakamoto::coordinator::tests::simple_nakamoto_coordinator_10_tenures_10_sortitions;
+    use crate::chainstate::stacks::db::blocks::DummyEventDispatcher;
+    use crate::chainstate::stacks::index::verify::verify_marf_blobs;
+
+    fn state_root(header: &StacksHeaderInfo) -> TrieHash {
+        match &header.anchored_header {
+            StacksBlockHeaderTypes::Epoch2(header) => header.state_index_root,
+            StacksBlockHeaderTypes::Nakamoto(header) => header.state_index_root,
+        }
+    }
+
+    fn verify(
+        path: &str,
+        headers_path: &str,
+        header_root: fn(&StacksHeaderInfo) -> TrieHash,
+    ) -> MarfVerifyReport<StacksBlockId> {
+        let headers_db = Connection::open(headers_path).unwrap();
+        verify_marf_blobs::<StacksBlockId, _>(path, |block_id, root_hash| {
+            match NakamotoChainState::get_block_header(&headers_db, block_id).unwrap() {
+                Some(header) if header.stacks_block_height == 0 => None,
+                Some(header) if &header_root(&header) == root_hash => None,
+                Some(_) => Some("root hash mismatch".into()),
+                None => Some("no block header".into()),
+            }
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_repair_and_reprocess() {
+        let mut peer = simple_nakamoto_coordinator_10_tenures_10_sortitions();
+        let burnchain = peer.config.burnchain.clone();
+        let chainstate_path = peer.chainstate_path.clone();
+        let mainnet = peer.chainstate().mainnet;
+        let chain_id = peer.chainstate().chain_id;
+        let (consensus_hash, block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(peer.sortdb().conn()).unwrap();
+        let tip = StacksBlockId::new(&consensus_hash, &block_hash);
+        let tip_header = NakamotoChainState::get_block_header(peer.chainstate().db(), &tip)
+            .unwrap()
+            .unwrap();
+        drop(peer);
+
+        let headers_path = StacksChainState::header_index_root_path(chainstate_path.clone().into());
+        let headers_path = headers_path.to_str().unwrap();
+        let clarity_path =
+            StacksChainState::vm_state_index_marf_path(chainstate_path.clone().into());
+        let clarity_path = clarity_path.to_str().unwrap();
+
+        // a crash tore the Clarity MARF's last three tries
+        let offsets: Vec<i64> = {
+            let conn = Connection::open(clarity_path).unwrap();
+            let mut stmt = conn
+                .prepare(
+                    "SELECT external_offset FROM marf_data WHERE unconfirmed = 0 ORDER BY block_id",
+                )
+                .unwrap();
+            let rows = stmt.query_map(NO_PARAMS, |row| row.get(0)).unwrap();
+            rows.collect::<Result<_, _>>().unwrap()
+        };
+        let torn_offset = u64::try_from(offsets[offsets.len() - 3]).unwrap() + 1;
+        OpenOptions::new()
+            .write(true)
+            .open(format!("{clarity_path}.blobs"))
+            .unwrap()
+            .set_len(torn_offset)
+            .unwrap();
+
+        let clarity_report = verify(clarity_path, headers_path, state_root);
+        let headers_report = verify(headers_path, headers_path, |header| header.index_root);
+        assert!(!clarity_report.is_ok());
+        assert!(clarity_report.is_repairable());
+        assert!(headers_report.is_ok());
+
+        let block_ids = repair_chainstate_marfs(
+            &chainstate_path,
+            &burnchain.get_db_path(),
+            &clarity_report,
+            &headers_report,
+        )
+        .unwrap();
+        assert_eq!(block_ids.len(), 3);
+        assert!(block_ids.contains(&tip));
+
+        // both MARFs are consistent with the forgotten blocks' headers gone
+        assert!(verify(clarity_path, headers_path, state_root).is_ok());
+        assert!(verify(headers_path, headers_path, |header| header.index_root).is_ok());
+
+        // the restarted node falls back to the last remaining block, and processes the
+        // forgotten blocks again
+        let mut sortdb = SortitionDB::open(
+            &burnchain.get_db_path(),
+            true,
+            burnchain.pox_constants.clone(),
+        )
+        .unwrap();
+        let (mut chainstate, _) =
+            StacksChainState::open(mainnet, chain_id, &chainstate_path, None).unwrap();
+        let (consensus_hash, block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn()).unwrap();
+        let repaired_tip = StacksBlockId::new(&consensus_hash, &block_hash);
+        let repaired_header = NakamotoChainState::get_block_header(chainstate.db(), &repaired_tip)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            repaired_header.stacks_block_height + 3,
+            tip_header.stacks_block_height
+        );
+
+        let sortition_id = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())
+            .unwrap()
+            .sortition_id;
+        let mut num_processed = 0;
+        while NakamotoChainState::process_next_nakamoto_block(
+            &mut chainstate,
+            &mut sortdb,
+            &sortition_id,
+            None::<&DummyEventDispatcher>,
+        )
+        .unwrap()
+        .is_some()
+        {
+            num_processed += 1;
+        }
+        assert_eq!(num_processed, 3);
+
+        let (consensus_hash, block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn()).unwrap();
+        assert_eq!(StacksBlockId::new(&consensus_hash, &block_hash), tip);
+        let header = NakamotoChainState::get_block_header(chainstate.db(), &tip)
+            .unwrap()
+            .unwrap();
+        assert_eq!(state_root(&header), state_root(&tip_header));
+        drop(chainstate);
+        drop(sortdb);
+        assert!(verify(clarity_path, headers_path, state_root).is_ok());
+        assert!(verify(headers_path, headers_path, |header| header.index_root).is_ok());
+    }
+}
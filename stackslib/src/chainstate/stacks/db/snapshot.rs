@@ -0,0 +1,1672 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Chainstate snapshots, for fast-syncing a new node.
+//!
+//! A snapshot is a directory holding copies of a stopped node's burnchain DB, sortition DB,
+//! headers DB, Clarity MARF and side-store (with their MARF `.blobs` files) and Nakamoto staging
+//! blocks DB, along with a `manifest.json` which names the Nakamoto block at the node's canonical
+//! Stacks tip and lists the size and SHA512/256 digest of every file.  The bodies of blocks from
+//! tenures before the snapshot block's reward cycle are stripped, so a node started from a
+//! snapshot is a pruned node (see the `pruning` module).
+//!
+//! Everything in a snapshot, including its manifest, comes from whoever made it, so importing a
+//! snapshot requires the ID of the snapshot block and the reward set for its reward cycle, both
+//! from a source the operator trusts.  Importing checks every file against the manifest, then
+//! checks the snapshot's contents against those two anchors:
+//!
+//! * The snapshot block must be the snapshot's canonical Stacks tip, its header must hash to the
+//!   trusted block ID, and it must be signed by the trusted reward set's signers.  The reward set
+//!   which the snapshot's own state computes for that cycle must have the same signers.
+//! * Each canonical sortition's consensus hash is recomputed from its burnchain block, its
+//!   accepted operations, its total burn, its PoX ID and its ancestors' consensus hashes, so the
+//!   header's consensus hash commits to the whole sortition history up to its tenure.  Sortitions
+//!   after that tenure are only checked for consistency.  The burnchain DB must hold every one of
+//!   these burnchain blocks and their accepted operations.
+//! * Every block header is checked against the parent its child commits to, back to genesis.
+//! * Every trie in the Clarity and headers MARFs is re-hashed, and each root hash must be the one
+//!   its block's header commits to.  Every value in the MARFs' side stores must hash to its key.
+//! * Contract metadata is not committed to by the MARF, so each contract's source is checked
+//!   against the hash which the MARF commits to, its analysis is recomputed from the source, and
+//!   its stored functions and definitions are checked against the source and the analysis.
+//!   Constants which are not literals are computed at deploy time, so their values are not
+//!   checked.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::{error, fmt};
+
+use clarity::vm::analysis::{run_analysis, AnalysisDatabase, ContractAnalysis};
+use clarity::vm::ast::{build_ast_with_rules, ASTRules};
+use clarity::vm::callables::DefineType;
+use clarity::vm::clarity::ClarityConnection;
+use clarity::vm::contracts::Contract;
+use clarity::vm::costs::LimitedCostTracker;
+use clarity::vm::database::clarity_db::ContractDataVarName;
+use clarity::vm::database::clarity_store::{make_contract_hash_key, ContractCommitment};
+use clarity::vm::database::{
+    ClarityDatabase, ClarityDeserializable, ClaritySerializable, SqliteConnection, StoreType,
+    NULL_BURN_STATE_DB, NULL_HEADER_DB,
+};
+use clarity::vm::functions::define::DefineFunctionsParsed;
+use clarity::vm::types::{parse_name_type_pairs, QualifiedContractIdentifier};
+use rusqlite::{params, Connection, OpenFlags};
+use sha2::{Digest, Sha512_256};
+use stacks_common::types::chainstate::{BurnchainHeaderHash, StacksBlockId, TrieHash};
+use stacks_common::types::sqlite::NO_PARAMS;
+use stacks_common::util::hash::Sha512Trunc256Sum;
+
+use crate::burnchains::db::BurnchainDB;
+use crate::burnchains::{Burnchain, Error as BurnchainError, Txid};
+use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::burn::{BlockSnapshot, ConsensusHash, ConsensusHashExtensions, OpsHash};
+use crate::chainstate::coordinator::{Error as CoordinatorError, OnChainRewardSetProvider};
+use crate::chainstate::nakamoto::coordinator::load_nakamoto_reward_set;
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::chainstate::stacks::boot::RewardSet;
+use crate::chainstate::stacks::db::{StacksBlockHeaderTypes, StacksChainState, StacksHeaderInfo};
+use crate::chainstate::stacks::index::marf::MarfConnection;
+use crate::chainstate::stacks::index::verify::verify_marf_blobs;
+use crate::chainstate::stacks::index::{Error as MARFError, MARFValue};
+use crate::chainstate::stacks::Error as ChainstateError;
+use crate::clarity_vm::clarity::ClarityReadOnlyConnection;
+use crate::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
+use crate::util_lib::db::{query_int, sqlite_open, Error as db_error};
+
+/// Version of the snapshot format
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Name of the manifest file in a snapshot directory
+pub const SNAPSHOT_MANIFEST: &str = "manifest.json";
+
+/// Size of the buffer used to copy files
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    IOError(io::Error),
+    DBError(db_error),
+    ChainstateError(ChainstateError),
+    CoordinatorError(CoordinatorError),
+    MARFError(MARFError),
+    BurnchainError(BurnchainError),
+    /// The node already has chainstate, so a snapshot cannot be imported into it
+    AlreadyInitialized(String),
+    /// The snapshot (or the node it is being taken from) is malformed
+    InvalidSnapshot(String),
+    /// The snapshot's contents did not pass verification
+    VerificationFailed(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::IOError(e) => fmt::Display::fmt(e, f),
+            SnapshotError::DBError(e) => fmt::Display::fmt(e, f),
+            SnapshotError::ChainstateError(e) => fmt::Display::fmt(e, f),
+            SnapshotError::CoordinatorError(e) => write!(f, "{e:?}"),
+            SnapshotError::MARFError(e) => fmt::Display::fmt(e, f),
+            SnapshotError::BurnchainError(e) => fmt::Display::fmt(e, f),
+            SnapshotError::AlreadyInitialized(s) => {
+                write!(f, "Node already has chainstate at {s}")
+            }
+            SnapshotError::InvalidSnapshot(s) => write!(f, "Invalid snapshot: {s}"),
+            SnapshotError::VerificationFailed(s) => {
+                write!(f, "Snapshot verification failed: {s}")
+            }
+        }
+    }
+}
+
+impl error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SnapshotError::IOError(e) => Some(e),
+            SnapshotError::DBError(e) => Some(e),
+            SnapshotError::ChainstateError(e) => Some(e),
+            SnapshotError::MARFError(e) => Some(e),
+            SnapshotError::BurnchainError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> SnapshotError {
+        SnapshotError::IOError(e)
+    }
+}
+
+impl From<db_error> for SnapshotError {
+    fn from(e: db_error) -> SnapshotError {
+        SnapshotError::DBError(e)
+    }
+}
+
+impl From<rusqlite::Error> for SnapshotError {
+    fn from(e: rusqlite::Error) -> SnapshotError {
+        SnapshotError::DBError(db_error::SqliteError(e))
+    }
+}
+
+impl From<ChainstateError> for SnapshotError {
+    fn from(e: ChainstateError) -> SnapshotError {
+        SnapshotError::ChainstateError(e)
+    }
+}
+
+impl From<BurnchainError> for SnapshotError {
+    fn from(e: BurnchainError) -> SnapshotError {
+        SnapshotError::BurnchainError(e)
+    }
+}
+
+impl From<CoordinatorError> for SnapshotError {
+    fn from(e: CoordinatorError) -> SnapshotError {
+        SnapshotError::CoordinatorError(e)
+    }
+}
+
+impl From<MARFError> for SnapshotError {
+    fn from(e: MARFError) -> SnapshotError {
+        SnapshotError::MARFError(e)
+    }
+}
+
+/// A file in a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotFile {
+    /// Path of the file, relative to the snapshot directory
+    pub name: String,
+    pub size: u64,
+    pub sha512_256: Sha512Trunc256Sum,
+}
+
+/// Describes the contents of a snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub version: u32,
+    pub mainnet: bool,
+    pub chain_id: u32,
+    /// The tenure of the snapshot block
+    pub consensus_hash: ConsensusHash,
+    /// The snapshot block, which is the canonical Stacks tip of the snapshot
+    pub block_id: StacksBlockId,
+    pub stacks_block_height: u64,
+    pub burn_block_height: u64,
+    /// The snapshot block's Clarity MARF root hash
+    pub state_index_root: TrieHash,
+    pub files: Vec<SnapshotFile>,
+}
+
+impl SnapshotManifest {
+    pub fn load(snapshot_dir: &Path) -> Result<SnapshotManifest, SnapshotError> {
+        let file = File::open(snapshot_dir.join(SNAPSHOT_MANIFEST))?;
+        serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+            SnapshotError::InvalidSnapshot(format!("could not decode {SNAPSHOT_MANIFEST}: {e}"))
+        })
+    }
+
+    fn store(&self, snapshot_dir: &Path) -> Result<(), SnapshotError> {
+        let mut file = BufWriter::new(File::create(snapshot_dir.join(SNAPSHOT_MANIFEST))?);
+        serde_json::to_writer_pretty(&mut file, self).map_err(|e| {
+            SnapshotError::InvalidSnapshot(format!("could not encode {SNAPSHOT_MANIFEST}: {e}"))
+        })?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// A file which can go into a snapshot
+struct SnapshotEntry {
+    /// Path of the file in the snapshot directory
+    name: &'static str,
+    /// Path of the file in the node
+    node_path: PathBuf,
+    /// Is this a sqlite DB, as opposed to a MARF `.blobs` file?
+    is_sqlite: bool,
+    /// Must a node have this file?
+    required: bool,
+}
+
+/// Get the files that make up a snapshot, given the node's burnchain working directory (via
+/// `burnchain`) and chainstate directory.
+fn snapshot_entries(burnchain: &Burnchain, chainstate_path: &str) -> Vec<SnapshotEntry> {
+    let sortdb_path = PathBuf::from(burnchain.get_db_path()).join("marf.sqlite");
+    let headers_path = StacksChainState::header_index_root_path(PathBuf::from(chainstate_path));
+    let clarity_path = StacksChainState::vm_state_index_marf_path(PathBuf::from(chainstate_path));
+    let mut staging_path = StacksChainState::blocks_path(PathBuf::from(chainstate_path));
+    staging_path.push("nakamoto.sqlite");
+
+    let blobs = |path: &Path| PathBuf::from(format!("{}.blobs", path.display()));
+    let entry = |name, node_path, is_sqlite, required| SnapshotEntry {
+        name,
+        node_path,
+        is_sqlite,
+        required,
+    };
+    vec![
+        entry(
+            "burnchain/burnchain.sqlite",
+            PathBuf::from(burnchain.get_burnchaindb_path()),
+            true,
+            true,
+        ),
+        entry(
+            "burnchain/sortition/marf.sqlite",
+            sortdb_path.clone(),
+            true,
+            true,
+        ),
+        entry(
+            "burnchain/sortition/marf.sqlite.blobs",
+            blobs(&sortdb_path),
+            false,
+            false,
+        ),
+        entry(
+            "chainstate/vm/index.sqlite",
+            headers_path.clone(),
+            true,
+            true,
+        ),
+        entry(
+            "chainstate/vm/index.sqlite.blobs",
+            blobs(&headers_path),
+            false,
+            false,
+        ),
+        entry(
+            "chainstate/vm/clarity/marf.sqlite",
+            clarity_path.clone(),
+            true,
+            true,
+        ),
+        entry(
+            "chainstate/vm/clarity/marf.sqlite.blobs",
+            blobs(&clarity_path),
+            false,
+            false,
+        ),
+        entry(
+            "chainstate/blocks/nakamoto.sqlite",
+            staging_path,
+            true,
+            true,
+        ),
+    ]
+}
+
+/// Copy `src` to `dest`, creating `dest`'s directory if need be.
+/// Returns the size and SHA512/256 digest of the copied bytes.
+fn copy_and_hash(src: &Path, dest: &Path) -> Result<(u64, Sha512Trunc256Sum), SnapshotError> {
+    if let Some(dir) = dest.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut reader = File::open(src)?;
+    let mut writer = BufWriter::new(File::create(dest)?);
+    let mut hasher = Sha512_256::new();
+    let mut buf = vec![0u8; COPY_BUFFER_SIZE];
+    let mut size = 0u64;
+    loop {
+        let nread = reader.read(&mut buf)?;
+        if nread == 0 {
+            break;
+        }
+        hasher.update(&buf[..nread]);
+        writer.write_all(&buf[..nread])?;
+        size += nread as u64;
+    }
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok((size, Sha512Trunc256Sum::from_hasher(hasher)))
+}
+
+/// Get the size and SHA512/256 digest of a file
+fn hash_file(path: &Path) -> Result<(u64, Sha512Trunc256Sum), SnapshotError> {
+    let mut reader = File::open(path)?;
+    let mut hasher = Sha512_256::new();
+    let size = io::copy(&mut reader, &mut hasher)?;
+    Ok((size, Sha512Trunc256Sum::from_hasher(hasher)))
+}
+
+/// Move everything in a sqlite DB's write-ahead log into the DB file, so the file can be copied
+/// on its own.  Fails if another process (such as a running node) has the DB open.
+fn checkpoint_sqlite(path: &Path) -> Result<(), SnapshotError> {
+    let conn = sqlite_open(path, OpenFlags::SQLITE_OPEN_READ_WRITE, false)?;
+    let busy: i64 = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", NO_PARAMS, |row| {
+        row.get(0)
+    })?;
+    if busy != 0 {
+        return Err(SnapshotError::InvalidSnapshot(format!(
+            "{} is in use; stop the node before taking a snapshot",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Take a snapshot of a stopped node's chainstate, as of its canonical Stacks tip, and store it
+/// in `snapshot_dir`, which must be empty or not exist.  If `tenure` is given, then the
+/// canonical Stacks tip must be in that tenure.
+///
+/// Returns the snapshot's manifest.
+pub fn export_snapshot(
+    burnchain: &Burnchain,
+    chainstate_path: &str,
+    snapshot_dir: &Path,
+    tenure: Option<&ConsensusHash>,
+) -> Result<SnapshotManifest, SnapshotError> {
+    let entries = snapshot_entries(burnchain, chainstate_path);
+    if let Some(entry) = entries
+        .iter()
+        .find(|entry| entry.required && !entry.node_path.exists())
+    {
+        return Err(SnapshotError::InvalidSnapshot(format!(
+            "missing {}",
+            entry.node_path.display()
+        )));
+    }
+    if snapshot_dir.exists() && fs::read_dir(snapshot_dir)?.next().is_some() {
+        return Err(SnapshotError::InvalidSnapshot(format!(
+            "{} is not empty",
+            snapshot_dir.display()
+        )));
+    }
+
+    for entry in entries.iter().filter(|entry| entry.is_sqlite) {
+        checkpoint_sqlite(&entry.node_path)?;
+    }
+
+    // find the snapshot block
+    let sortdb_conn = sqlite_open(
+        PathBuf::from(burnchain.get_db_path()).join("marf.sqlite"),
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+        false,
+    )?;
+    let (consensus_hash, block_hash) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(&sortdb_conn)?;
+    let block_id = StacksBlockId::new(&consensus_hash, &block_hash);
+    if let Some(tenure) = tenure {
+        if tenure != &consensus_hash {
+            return Err(SnapshotError::InvalidSnapshot(format!(
+                "the canonical Stacks tip {block_id} is in tenure {consensus_hash}, not {tenure}"
+            )));
+        }
+    }
+
+    let headers_conn = sqlite_open(
+        StacksChainState::header_index_root_path(PathBuf::from(chainstate_path)),
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+        false,
+    )?;
+    let db_config = StacksChainState::load_db_config(&headers_conn)?;
+    let header =
+        NakamotoChainState::get_block_header(&headers_conn, &block_id)?.ok_or_else(|| {
+            SnapshotError::InvalidSnapshot(format!("no header for Stacks tip {block_id}"))
+        })?;
+    let Some(nakamoto_header) = header.anchored_header.as_stacks_nakamoto() else {
+        return Err(SnapshotError::InvalidSnapshot(format!(
+            "the canonical Stacks tip {block_id} is not a Nakamoto block"
+        )));
+    };
+    let reward_cycle = burnchain
+        .block_height_to_reward_cycle(header.burn_header_height.into())
+        .ok_or_else(|| {
+            SnapshotError::InvalidSnapshot(format!("no reward cycle for Stacks tip {block_id}"))
+        })?;
+    drop(sortdb_conn);
+    drop(headers_conn);
+
+    info!("Exporting chainstate snapshot";
+          "block_id" => %block_id,
+          "consensus_hash" => %consensus_hash,
+          "stacks_block_height" => header.stacks_block_height,
+          "snapshot_dir" => %snapshot_dir.display());
+
+    for entry in entries.iter() {
+        if !entry.node_path.exists() {
+            continue;
+        }
+        copy_and_hash(&entry.node_path, &snapshot_dir.join(entry.name))?;
+    }
+
+    // strip the block bodies which a node that starts from this snapshot won't need
+    let snapshot_chainstate_path = snapshot_dir.join("chainstate");
+    let snapshot_chainstate_path = snapshot_chainstate_path
+        .to_str()
+        .ok_or_else(|| SnapshotError::InvalidSnapshot("snapshot path is not valid UTF-8".into()))?;
+    let (mut chainstate, _) = StacksChainState::open(
+        db_config.mainnet,
+        db_config.chain_id,
+        snapshot_chainstate_path,
+        None,
+    )?;
+    if let Some((horizon, stats)) = chainstate.prune_to_reward_cycle(burnchain, reward_cycle)? {
+        debug!("Stripped block bodies from snapshot";
+               "reward_cycle" => horizon.reward_cycle,
+               "nakamoto_blocks" => stats.nakamoto_blocks);
+    }
+    let staging_blocks_path = chainstate.get_nakamoto_staging_blocks_path()?;
+    drop(chainstate);
+    sqlite_open(
+        &staging_blocks_path,
+        OpenFlags::SQLITE_OPEN_READ_WRITE,
+        false,
+    )?
+    .execute_batch("VACUUM")?;
+
+    let mut files = vec![];
+    for entry in entries.iter() {
+        let path = snapshot_dir.join(entry.name);
+        if !path.exists() {
+            continue;
+        }
+        let (size, sha512_256) = hash_file(&path)?;
+        files.push(SnapshotFile {
+            name: entry.name.to_string(),
+            size,
+            sha512_256,
+        });
+    }
+
+    let manifest = SnapshotManifest {
+        version: SNAPSHOT_VERSION,
+        mainnet: db_config.mainnet,
+        chain_id: db_config.chain_id,
+        consensus_hash,
+        block_id,
+        stacks_block_height: header.stacks_block_height,
+        burn_block_height: header.burn_header_height.into(),
+        state_index_root: nakamoto_header.state_index_root.clone(),
+        files,
+    };
+    manifest.store(snapshot_dir)?;
+    Ok(manifest)
+}
+
+/// Import the snapshot in `snapshot_dir` into a node which has no chainstate yet, given the
+/// node's burnchain working directory (via `burnchain`) and chainstate directory.  The snapshot
+/// block must be `trusted_block_id`, and its header must be signed by the signers of
+/// `trusted_reward_set`, the reward set for the snapshot block's reward cycle.  The operator must
+/// get both from a source other than the snapshot.  The node's files are removed again if the snapshot fails verification.
+///
+/// Returns the snapshot's manifest.
+pub fn import_snapshot(
+    snapshot_dir: &Path,
+    burnchain: &Burnchain,
+    chainstate_path: &str,
+    mainnet: bool,
+    chain_id: u32,
+    trusted_block_id: &StacksBlockId,
+    trusted_reward_set: &RewardSet,
+) -> Result<SnapshotManifest, SnapshotError> {
+    let manifest = SnapshotManifest::load(snapshot_dir)?;
+    if manifest.version != SNAPSHOT_VERSION {
+        return Err(SnapshotError::InvalidSnapshot(format!(
+            "unsupported snapshot version {}",
+            manifest.version
+        )));
+    }
+    if manifest.mainnet != mainnet || manifest.chain_id != chain_id {
+        return Err(SnapshotError::InvalidSnapshot(format!(
+            "snapshot is for chain ID {} (mainnet: {}), not {chain_id} (mainnet: {mainnet})",
+            manifest.chain_id, manifest.mainnet
+        )));
+    }
+    if &manifest.block_id != trusted_block_id {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "snapshot block {} is not the trusted block {trusted_block_id}",
+            &manifest.block_id
+        )));
+    }
+
+    let entries = snapshot_entries(burnchain, chainstate_path);
+    if let Some(entry) = entries.iter().find(|entry| entry.node_path.exists()) {
+        return Err(SnapshotError::AlreadyInitialized(
+            entry.node_path.display().to_string(),
+        ));
+    }
+
+    info!("Importing chainstate snapshot";
+          "block_id" => %manifest.block_id,
+          "consensus_hash" => %manifest.consensus_hash,
+          "stacks_block_height" => manifest.stacks_block_height,
+          "snapshot_dir" => %snapshot_dir.display());
+
+    let result = copy_snapshot_files(&manifest, &entries, snapshot_dir).and_then(|_| {
+        verify_imported_snapshot(&manifest, burnchain, chainstate_path, trusted_reward_set)
+    });
+    if let Err(e) = result {
+        warn!("Failed to import chainstate snapshot: {e}");
+        for entry in entries.iter() {
+            for suffix in ["", "-wal", "-shm"] {
+                let path = PathBuf::from(format!("{}{suffix}", entry.node_path.display()));
+                if path.exists() {
+                    fs::remove_file(&path)?;
+                }
+            }
+        }
+        return Err(e);
+    }
+
+    info!("Imported chainstate snapshot";
+          "block_id" => %manifest.block_id,
+          "stacks_block_height" => manifest.stacks_block_height);
+    Ok(manifest)
+}
+
+/// Copy a snapshot's files into the node, checking them against the manifest as we go
+fn copy_snapshot_files(
+    manifest: &SnapshotManifest,
+    entries: &[SnapshotEntry],
+    snapshot_dir: &Path,
+) -> Result<(), SnapshotError> {
+    if let Some(entry) = entries
+        .iter()
+        .find(|entry| entry.required && !manifest.files.iter().any(|f| f.name == entry.name))
+    {
+        return Err(SnapshotError::InvalidSnapshot(format!(
+            "missing {}",
+            entry.name
+        )));
+    }
+    for file in manifest.files.iter() {
+        let Some(entry) = entries.iter().find(|entry| entry.name == file.name) else {
+            return Err(SnapshotError::InvalidSnapshot(format!(
+                "unrecognized file {}",
+                file.name
+            )));
+        };
+        let (size, sha512_256) = copy_and_hash(&snapshot_dir.join(&file.name), &entry.node_path)?;
+        if size != file.size || sha512_256 != file.sha512_256 {
+            return Err(SnapshotError::VerificationFailed(format!(
+                "{} does not match the manifest",
+                file.name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Check an imported snapshot against the trusted block ID and reward set.  The snapshot block's
+/// header must hash to the trusted block ID and be signed by the trusted reward set's signers,
+/// and everything else in the snapshot is checked against what that header commits to, directly
+/// or through the chains of hashes described in the module documentation.
+fn verify_imported_snapshot(
+    manifest: &SnapshotManifest,
+    burnchain: &Burnchain,
+    chainstate_path: &str,
+    trusted_reward_set: &RewardSet,
+) -> Result<(), SnapshotError> {
+    let mut sortdb = SortitionDB::open(
+        &burnchain.get_db_path(),
+        true,
+        burnchain.pox_constants.clone(),
+    )?;
+    let (mut chainstate, _) =
+        StacksChainState::open(manifest.mainnet, manifest.chain_id, chainstate_path, None)?;
+    let block_id = &manifest.block_id;
+
+    // nothing past the snapshot block has been processed, so everything the node will treat
+    // as processed is covered by the checks below
+    let (consensus_hash, block_hash) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+    if &StacksBlockId::new(&consensus_hash, &block_hash) != block_id {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "canonical Stacks tip {consensus_hash}/{block_hash} is not snapshot block {block_id}"
+        )));
+    }
+    let max_height = query_int(
+        chainstate.db(),
+        "SELECT IFNULL(MAX(block_height),0) FROM nakamoto_block_headers",
+        NO_PARAMS,
+    )?;
+    if u64::try_from(max_height).unwrap_or(0) > manifest.stacks_block_height {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "found a processed block at height {max_height}, above the snapshot block"
+        )));
+    }
+
+    let header = NakamotoChainState::get_block_header(chainstate.db(), block_id)?
+        .ok_or_else(|| SnapshotError::VerificationFailed(format!("no header for {block_id}")))?;
+    let Some(nakamoto_header) = header.anchored_header.as_stacks_nakamoto() else {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "{block_id} is not a Nakamoto block"
+        )));
+    };
+    if header.consensus_hash != manifest.consensus_hash
+        || header.stacks_block_height != manifest.stacks_block_height
+        || nakamoto_header.state_index_root != manifest.state_index_root
+    {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "header for {block_id} does not match the manifest"
+        )));
+    }
+    // the header's contents, including its state_index_root, must be what the block ID commits to
+    if &nakamoto_header.block_id() != block_id {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "header for {block_id} hashes to {}",
+            nakamoto_header.block_id()
+        )));
+    }
+
+    // the header must be signed by the trusted signers, and the snapshot's own reward set for
+    // the header's reward cycle must be the trusted one
+    let Some(trusted_signers) = trusted_reward_set.signers.as_ref() else {
+        return Err(SnapshotError::InvalidSnapshot(
+            "the trusted reward set has no signers".into(),
+        ));
+    };
+    nakamoto_header
+        .verify_signer_signatures(trusted_reward_set)
+        .map_err(|e| {
+            SnapshotError::VerificationFailed(format!(
+                "signer signatures on {block_id} are invalid: {e}"
+            ))
+        })?;
+
+    verify_sortitions(&mut sortdb, burnchain, &header.consensus_hash)?;
+    verify_burnchain_db(&sortdb, burnchain)?;
+    verify_block_headers(&chainstate, &sortdb, &header)?;
+
+    // every trie must hash to its stored root hash, and the root hashes must be the ones the
+    // block headers commit to
+    let headers_path = StacksChainState::header_index_root_path(PathBuf::from(chainstate_path));
+    let clarity_path = StacksChainState::vm_state_index_marf_path(PathBuf::from(chainstate_path));
+    verify_marf_tries(&clarity_path, chainstate.db(), |header| {
+        header_state_index_root(header)
+    })?;
+    verify_marf_tries(&headers_path, chainstate.db(), |header| header.index_root)?;
+    let state_index_root = chainstate.with_clarity_marf(|marf| marf.get_root_hash_at(block_id))?;
+    if state_index_root != nakamoto_header.state_index_root {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "Clarity MARF root hash {state_index_root} at {block_id} does not match the header's state_index_root {}",
+            &nakamoto_header.state_index_root
+        )));
+    }
+    let index_root = chainstate.state_index.get_root_hash_at(block_id)?;
+    if index_root != header.index_root {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "headers MARF root hash {index_root} at {block_id} does not match {}",
+            &header.index_root
+        )));
+    }
+
+    // the MARFs only store value hashes; the values themselves must hash to them
+    verify_side_store(sortdb.conn(), "__fork_storage", "value_hash")?;
+    verify_side_store(chainstate.db(), "__fork_storage", "value_hash")?;
+    let clarity_conn = sqlite_open(&clarity_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)?;
+    verify_side_store(&clarity_conn, "data_table", "key")?;
+    verify_contract_metadata(&mut chainstate, &clarity_conn, block_id)?;
+
+    let tenure_sn =
+        SortitionDB::get_block_snapshot_consensus(sortdb.conn(), &header.consensus_hash)?
+            .ok_or_else(|| {
+                SnapshotError::VerificationFailed(format!(
+                    "no sortition for tenure {}",
+                    &header.consensus_hash
+                ))
+            })?;
+    let reward_cycle = burnchain
+        .block_height_to_reward_cycle(tenure_sn.block_height)
+        .ok_or_else(|| {
+            SnapshotError::VerificationFailed(format!("no reward cycle for {block_id}"))
+        })?;
+    let Some((reward_info, _)) = load_nakamoto_reward_set(
+        reward_cycle,
+        &tenure_sn.sortition_id,
+        burnchain,
+        &mut chainstate,
+        block_id,
+        &sortdb,
+        &OnChainRewardSetProvider::new(),
+    )?
+    else {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "no reward set for reward cycle {reward_cycle}"
+        )));
+    };
+    let Some(reward_set) = reward_info.known_selected_anchor_block_owned() else {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "no signers for reward cycle {reward_cycle}"
+        )));
+    };
+    if reward_set.signers.as_ref() != Some(trusted_signers) {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "the snapshot's signers for reward cycle {reward_cycle} are not the trusted signers"
+        )));
+    }
+    Ok(())
+}
+
+/// Check the canonical sortition history, from the first burnchain block up to the canonical
+/// burnchain tip.  Each sortition's consensus hash is recomputed from its burnchain block hash,
+/// the hash of its accepted operations (which is itself recomputed from the stored operations),
+/// its total burn, its PoX ID and its ancestors' consensus hashes, so the consensus hash of the
+/// snapshot block's tenure, which the trusted block ID commits to, commits to all of the history
+/// before it.  That tenure's sortition must be on the canonical sortition history.
+fn verify_sortitions(
+    sortdb: &mut SortitionDB,
+    burnchain: &Burnchain,
+    tenure_consensus_hash: &ConsensusHash,
+) -> Result<(), SnapshotError> {
+    let mut sortitions = vec![];
+    let mut sn = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+    while sn.block_height > burnchain.first_block_height {
+        let parent_sortition_id = sn.parent_sortition_id;
+        sortitions.push(sn);
+        sn = SortitionDB::get_block_snapshot(sortdb.conn(), &parent_sortition_id)?.ok_or_else(
+            || {
+                SnapshotError::VerificationFailed(format!(
+                    "no parent sortition {parent_sortition_id}"
+                ))
+            },
+        )?;
+    }
+    if sn.block_height != burnchain.first_block_height
+        || sn.burn_header_hash != burnchain.first_block_hash
+        || sn.consensus_hash != FIRST_BURNCHAIN_CONSENSUS_HASH
+    {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "the first sortition {} is not the first burnchain block {}",
+            &sn.sortition_id, &burnchain.first_block_hash
+        )));
+    }
+    sortitions.push(sn);
+    sortitions.reverse();
+
+    if !sortitions
+        .iter()
+        .any(|sn| &sn.consensus_hash == tenure_consensus_hash)
+    {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "tenure {tenure_consensus_hash} is not on the canonical sortition history"
+        )));
+    }
+
+    for (i, pair) in sortitions.windows(2).enumerate() {
+        let (parent, sn) = (&pair[0], &pair[1]);
+        if i > 0 && i % 10_000 == 0 {
+            info!("Verified {i} of {} sortitions", sortitions.len());
+        }
+        if sn.block_height != parent.block_height + 1
+            || sn.parent_burn_header_hash != parent.burn_header_hash
+        {
+            return Err(SnapshotError::VerificationFailed(format!(
+                "sortition {} does not follow its parent {}",
+                &sn.sortition_id, &parent.sortition_id
+            )));
+        }
+
+        let mut ops = sortition_ops(sortdb.conn(), sn)?;
+        ops.sort_by_key(|(vtxindex, _)| *vtxindex);
+        let txids: Vec<Txid> = ops.into_iter().map(|(_, txid)| txid).collect();
+        if OpsHash::from_txids(&txids) != sn.ops_hash {
+            return Err(SnapshotError::VerificationFailed(format!(
+                "the operations in sortition {} do not hash to its ops hash {}",
+                &sn.sortition_id, &sn.ops_hash
+            )));
+        }
+        if sn.sortition {
+            let commits = SortitionDB::get_block_commits_by_block(sortdb.conn(), &sn.sortition_id)?;
+            if !commits.iter().any(|commit| {
+                commit.txid == sn.winning_block_txid
+                    && commit.block_header_hash == sn.winning_stacks_block_hash
+            }) {
+                return Err(SnapshotError::VerificationFailed(format!(
+                    "the winner of sortition {} is not one of its block commits",
+                    &sn.sortition_id
+                )));
+            }
+        }
+
+        // the same ancestors `ConsensusHash::get_prev_consensus_hashes()` uses
+        let parent_height = sn.block_height - 1;
+        let prev_consensus_hashes: Vec<ConsensusHash> = (0..64)
+            .map_while(|i| {
+                parent_height
+                    .checked_sub((1u64 << i) - 1)
+                    .filter(|height| *height >= burnchain.first_block_height)
+            })
+            .map(|height| {
+                let index = height - burnchain.first_block_height;
+                sortitions[index as usize].consensus_hash
+            })
+            .collect();
+        let pox_id = sortdb.get_pox_id(&sn.sortition_id)?;
+        let consensus_hash = ConsensusHash::from_ops(
+            &sn.burn_header_hash,
+            &sn.ops_hash,
+            sn.total_burn,
+            &prev_consensus_hashes,
+            &pox_id,
+        );
+        if consensus_hash != sn.consensus_hash {
+            return Err(SnapshotError::VerificationFailed(format!(
+                "sortition {} has consensus hash {}, but its contents hash to {consensus_hash}",
+                &sn.sortition_id, &sn.consensus_hash
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Get the vtxindex and txid of every burnchain operation a sortition accepted
+fn sortition_ops(conn: &Connection, sn: &BlockSnapshot) -> Result<Vec<(u32, Txid)>, SnapshotError> {
+    let mut ops = vec![];
+    ops.extend(
+        SortitionDB::get_leader_keys_by_block(conn, &sn.sortition_id)?
+            .into_iter()
+            .map(|op| (op.vtxindex, op.txid)),
+    );
+    ops.extend(
+        SortitionDB::get_block_commits_by_block(conn, &sn.sortition_id)?
+            .into_iter()
+            .map(|op| (op.vtxindex, op.txid)),
+    );
+    ops.extend(
+        SortitionDB::get_stack_stx_ops(conn, &sn.burn_header_hash)?
+            .into_iter()
+            .map(|op| (op.vtxindex, op.txid)),
+    );
+    ops.extend(
+        SortitionDB::get_transfer_stx_ops(conn, &sn.burn_header_hash)?
+            .into_iter()
+            .map(|op| (op.vtxindex, op.txid)),
+    );
+    ops.extend(
+        SortitionDB::get_delegate_stx_ops(conn, &sn.burn_header_hash)?
+            .into_iter()
+            .map(|op| (op.vtxindex, op.txid)),
+    );
+    ops.extend(
+        SortitionDB::get_vote_for_aggregate_key_ops(conn, &sn.burn_header_hash)?
+            .into_iter()
+            .map(|op| (op.vtxindex, op.txid)),
+    );
+    Ok(ops)
+}
+
+/// Check that the burnchain DB has every burnchain block in the (already verified) canonical
+/// sortition history, with the same parent and timestamp, and with every operation the sortition
+/// accepted.
+fn verify_burnchain_db(sortdb: &SortitionDB, burnchain: &Burnchain) -> Result<(), SnapshotError> {
+    let burnchain_db = BurnchainDB::open(&burnchain.get_burnchaindb_path(), false)?;
+    let mut sn = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+    while sn.block_height > burnchain.first_block_height {
+        let block = BurnchainDB::get_burnchain_block(burnchain_db.conn(), &sn.burn_header_hash)
+            .map_err(|e| {
+                SnapshotError::VerificationFailed(format!(
+                    "no burnchain block {} for sortition {}: {e:?}",
+                    &sn.burn_header_hash, &sn.sortition_id
+                ))
+            })?;
+        if block.header.block_height != sn.block_height
+            || block.header.parent_block_hash != sn.parent_burn_header_hash
+            || block.header.timestamp != sn.burn_header_timestamp
+        {
+            return Err(SnapshotError::VerificationFailed(format!(
+                "burnchain block {} does not match sortition {}",
+                &sn.burn_header_hash, &sn.sortition_id
+            )));
+        }
+        let burnchain_txids: HashSet<Txid> = block.ops.iter().map(|op| op.txid()).collect();
+        if let Some((_, txid)) = sortition_ops(sortdb.conn(), &sn)?
+            .into_iter()
+            .find(|(_, txid)| !burnchain_txids.contains(txid))
+        {
+            return Err(SnapshotError::VerificationFailed(format!(
+                "burnchain block {} does not have operation {txid} from sortition {}",
+                &sn.burn_header_hash, &sn.sortition_id
+            )));
+        }
+        sn = SortitionDB::get_block_snapshot(sortdb.conn(), &sn.parent_sortition_id)?.ok_or_else(
+            || {
+                SnapshotError::VerificationFailed(format!(
+                    "no parent sortition {}",
+                    &sn.parent_sortition_id
+                ))
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Walk back from the snapshot block's header to the genesis block, checking that each header is
+/// the one its child commits to.  Nakamoto headers commit to their parent's block ID; Stacks 2.x
+/// headers commit to their parent's block hash, and must be the winner of their (already
+/// verified) sortition.
+fn verify_block_headers(
+    chainstate: &StacksChainState,
+    sortdb: &SortitionDB,
+    snapshot_header: &StacksHeaderInfo,
+) -> Result<(), SnapshotError> {
+    let genesis_block_id =
+        StacksBlockId::new(&FIRST_BURNCHAIN_CONSENSUS_HASH, &FIRST_STACKS_BLOCK_HASH);
+    let mut block_id = snapshot_header.index_block_hash();
+    let mut header = snapshot_header.clone();
+    while header.stacks_block_height > 0 {
+        let parent_block_id = match &header.anchored_header {
+            StacksBlockHeaderTypes::Nakamoto(nakamoto_header) => nakamoto_header.parent_block_id,
+            StacksBlockHeaderTypes::Epoch2(epoch2_header) => {
+                let sn = SortitionDB::get_block_snapshot_consensus(
+                    sortdb.conn(),
+                    &header.consensus_hash,
+                )?;
+                if sn.map(|sn| sn.winning_stacks_block_hash) != Some(epoch2_header.block_hash()) {
+                    return Err(SnapshotError::VerificationFailed(format!(
+                        "{block_id} is not the winner of its sortition"
+                    )));
+                }
+                StacksChainState::get_parent_block_id(chainstate.db(), &block_id)?.ok_or_else(
+                    || SnapshotError::VerificationFailed(format!("no parent for {block_id}")),
+                )?
+            }
+        };
+        let parent = NakamotoChainState::get_block_header(chainstate.db(), &parent_block_id)?
+            .ok_or_else(|| {
+                SnapshotError::VerificationFailed(format!(
+                    "no header for {parent_block_id}, the parent of {block_id}"
+                ))
+            })?;
+        let parent_matches = if parent.stacks_block_height == 0 {
+            parent_block_id == genesis_block_id
+                && header
+                    .anchored_header
+                    .as_stacks_epoch2()
+                    .map_or(true, |child| child.parent_block == FIRST_STACKS_BLOCK_HASH)
+        } else {
+            match (&header.anchored_header, &parent.anchored_header) {
+                (StacksBlockHeaderTypes::Nakamoto(_), StacksBlockHeaderTypes::Nakamoto(p)) => {
+                    p.block_id() == parent_block_id
+                }
+                (child, StacksBlockHeaderTypes::Epoch2(p)) => {
+                    StacksBlockId::new(&parent.consensus_hash, &p.block_hash()) == parent_block_id
+                        && child
+                            .as_stacks_epoch2()
+                            .map_or(true, |child| child.parent_block == p.block_hash())
+                }
+                (StacksBlockHeaderTypes::Epoch2(_), StacksBlockHeaderTypes::Nakamoto(_)) => false,
+            }
+        };
+        if !parent_matches || parent.stacks_block_height + 1 != header.stacks_block_height {
+            return Err(SnapshotError::VerificationFailed(format!(
+                "{parent_block_id} is not the parent that {block_id} commits to"
+            )));
+        }
+        block_id = parent_block_id;
+        header = parent;
+    }
+    if block_id != genesis_block_id {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "{block_id} is not the genesis block"
+        )));
+    }
+    Ok(())
+}
+
+/// Get the state root hash which a block header commits to
+fn header_state_index_root(header: &StacksHeaderInfo) -> TrieHash {
+    match &header.anchored_header {
+        StacksBlockHeaderTypes::Epoch2(header) => header.state_index_root,
+        StacksBlockHeaderTypes::Nakamoto(header) => header.state_index_root,
+    }
+}
+
+/// Re-derive every node hash of every trie in the MARF at `db_path` (see `verify_marf_blobs()`),
+/// and check each trie's root hash against the block header for the trie's block, as given by
+/// `header_root`.
+fn verify_marf_tries<F>(
+    db_path: &Path,
+    headers_conn: &Connection,
+    header_root: F,
+) -> Result<(), SnapshotError>
+where
+    F: Fn(&StacksHeaderInfo) -> TrieHash,
+{
+    let db_path_str = db_path
+        .to_str()
+        .ok_or_else(|| SnapshotError::InvalidSnapshot("snapshot path is not valid UTF-8".into()))?;
+    let report = verify_marf_blobs(db_path_str, |block_id: &StacksBlockId, root_hash| {
+        match NakamotoChainState::get_block_header(headers_conn, block_id) {
+            // the genesis block's header does not commit to its tries' root hashes
+            Ok(Some(header)) if header.stacks_block_height == 0 => None,
+            Ok(Some(header)) if &header_root(&header) == root_hash => None,
+            Ok(Some(header)) => Some(format!(
+                "root hash {root_hash} does not match {}",
+                header_root(&header)
+            )),
+            Ok(None) => Some(format!("no header for {block_id}")),
+            Err(e) => Some(format!("failed to load header for {block_id}: {e:?}")),
+        }
+    })?;
+    if let Some(bad) = report.bad_tries.first() {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "trie for {} in {} is invalid: {}",
+            &bad.block_hash,
+            db_path.display(),
+            &bad.reason
+        )));
+    }
+    if !report.is_ok() {
+        return Err(SnapshotError::VerificationFailed(format!(
+            "{} has {} bytes which belong to no trie",
+            db_path.display(),
+            report.blobs_len - report.tail_offset
+        )));
+    }
+    Ok(())
+}
+
+/// Check that every value in a MARF's side store is keyed by its hash, which is what the MARF
+/// stores
+fn verify_side_store(
+    conn: &Connection,
+    table: &str,
+    key_column: &str,
+) -> Result<(), SnapshotError> {
+    let mut stmt = conn.prepare(&format!("SELECT {key_column}, value FROM {table}"))?;
+    let mut rows = stmt.query(NO_PARAMS)?;
+    while let Some(row) = rows.next()? {
+        let key: String = row.get(0)?;
+        let value: String = row.get(1)?;
+        if MARFValue::from_value(&value).to_hex() != key {
+            return Err(SnapshotError::VerificationFailed(format!(
+                "{table} has a value which does not hash to its key {key}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Check the contract metadata which the snapshot block's state uses.  Contract metadata is
+/// not committed to by the MARF, except for the hash of each contract's source code, so each
+/// contract's source is checked against that hash, and its analysis is recomputed from the
+/// source and compared with the stored one.  The stored contract's functions, traits and
+/// definitions are checked against the source and the analysis.  The values of constants are
+/// computed when a contract is deployed, so only those which are literals are checked.
+fn verify_contract_metadata(
+    chainstate: &mut StacksChainState,
+    clarity_conn: &Connection,
+    block_id: &StacksBlockId,
+) -> Result<(), SnapshotError> {
+    let mut contracts = BTreeSet::new();
+    {
+        let mut stmt = clarity_conn.prepare("SELECT DISTINCT key FROM metadata_table")?;
+        let mut rows = stmt.query(NO_PARAMS)?;
+        while let Some(row) = rows.next()? {
+            let key: String = row.get(0)?;
+            let contract = key
+                .strip_prefix("clr-meta::")
+                .and_then(|key| key.split_once("::"))
+                .map(|(contract, _)| contract.to_string())
+                .ok_or_else(|| {
+                    SnapshotError::VerificationFailed(format!("unrecognized metadata key {key}"))
+                })?;
+            contracts.insert(contract);
+        }
+    }
+
+    let num_contracts = contracts.len();
+    for (i, contract) in contracts.into_iter().enumerate() {
+        if i > 0 && i % 1_000 == 0 {
+            info!("Verified metadata of {i} of {num_contracts} contracts");
+        }
+        let contract_id = QualifiedContractIdentifier::parse(&contract).map_err(|_| {
+            SnapshotError::VerificationFailed(format!("invalid contract ID {contract}"))
+        })?;
+
+        // only the metadata stored at the block which deployed the contract on the snapshot
+        // block's fork is reachable
+        let commitment_key = make_contract_hash_key(&contract_id);
+        let Some(marf_value) =
+            chainstate.with_clarity_marf(|marf| marf.get(block_id, &commitment_key))?
+        else {
+            continue;
+        };
+        let commitment = SqliteConnection::get(clarity_conn, &marf_value.to_hex())
+            .ok()
+            .flatten()
+            .and_then(|value| ContractCommitment::deserialize(&value).ok())
+            .ok_or_else(|| {
+                SnapshotError::VerificationFailed(format!("no commitment for {contract}"))
+            })?;
+        let deploy_block_id = chainstate
+            .with_clarity_marf(|marf| marf.get_block_at_height(commitment.block_height, block_id))?
+            .ok_or_else(|| {
+                SnapshotError::VerificationFailed(format!(
+                    "no block at height {} for {contract}",
+                    commitment.block_height
+                ))
+            })?;
+
+        let mut metadata = HashMap::new();
+        {
+            let prefix = format!("clr-meta::{contract}::");
+            let mut stmt = clarity_conn
+                .prepare("SELECT key, value FROM metadata_table WHERE blockhash = ?1")?;
+            let mut rows = stmt.query(params![deploy_block_id])?;
+            while let Some(row) = rows.next()? {
+                let key: String = row.get(0)?;
+                let value: String = row.get(1)?;
+                if let Some(key) = key.strip_prefix(&prefix) {
+                    metadata.insert(key.to_string(), value);
+                }
+            }
+        }
+
+        let mut clarity_conn = chainstate.clarity_state.read_only_connection(
+            block_id,
+            &NULL_HEADER_DB,
+            &NULL_BURN_STATE_DB,
+        );
+        verify_contract(&contract_id, &commitment.hash, &metadata, &mut clarity_conn).map_err(
+            |e| {
+                SnapshotError::VerificationFailed(format!("metadata of {contract} is invalid: {e}"))
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Check one contract's metadata (see `verify_contract_metadata()`).
+/// Returns why the metadata is invalid, if it is.
+fn verify_contract(
+    contract_id: &QualifiedContractIdentifier,
+    content_hash: &Sha512Trunc256Sum,
+    metadata: &HashMap<String, String>,
+    clarity_conn: &mut ClarityReadOnlyConnection,
+) -> Result<(), String> {
+    let contract_key = |name: ContractDataVarName| {
+        ClarityDatabase::make_metadata_key(StoreType::Contract, name.as_str())
+    };
+    let get = |key: &str| {
+        metadata
+            .get(key)
+            .ok_or_else(|| format!("no {key}"))
+            .map(String::as_str)
+    };
+
+    let src = get(&contract_key(ContractDataVarName::ContractSrc))?;
+    if &Sha512Trunc256Sum::from_data(src.as_bytes()) != content_hash {
+        return Err("source code does not match the committed hash".into());
+    }
+    if get(&contract_key(ContractDataVarName::ContractSize))? != (src.len() as u64).serialize() {
+        return Err("size does not match the source code".into());
+    }
+
+    // the analysis must be the one the source code produces
+    let mut analysis = ContractAnalysis::deserialize(get(AnalysisDatabase::storage_key())?)
+        .map_err(|e| format!("could not decode analysis: {e}"))?;
+    let ast = build_ast_with_rules(
+        contract_id,
+        src,
+        &mut (),
+        analysis.clarity_version,
+        analysis.epoch,
+        ASTRules::Typical,
+    )
+    .map_err(|e| format!("could not parse source code: {e}"))?;
+    let mut expected_analysis = clarity_conn
+        .with_analysis_db_readonly(|db| {
+            run_analysis(
+                contract_id,
+                &ast.expressions,
+                db,
+                false,
+                LimitedCostTracker::new_free(),
+                analysis.epoch,
+                analysis.clarity_version,
+                false,
+            )
+        })
+        .map_err(|(e, _)| format!("could not analyze source code: {e}"))?;
+    // the interface is derived from the rest of the analysis, and is not used by the VM
+    analysis.contract_interface = None;
+    expected_analysis.contract_interface = None;
+    expected_analysis.expressions = vec![];
+    expected_analysis.type_map = None;
+    expected_analysis.cost_track = None;
+    if analysis != expected_analysis {
+        return Err("analysis does not match the source code".into());
+    }
+
+    // the contract must be the one the source code and analysis describe
+    let contract = Contract::deserialize(get(&contract_key(ContractDataVarName::Contract))?)
+        .map_err(|e| format!("could not decode contract: {e}"))?;
+    let context = &contract.contract_context;
+    if &context.contract_identifier != contract_id
+        || context.get_clarity_version() != &analysis.clarity_version
+    {
+        return Err("contract identifier or Clarity version does not match".into());
+    }
+    if get(&contract_key(ContractDataVarName::ContractDataSize)).ok()
+        != Some(context.data_size.serialize().as_str())
+    {
+        return Err("data size does not match the contract".into());
+    }
+
+    let mut num_functions = 0;
+    let mut num_constants = 0;
+    for expr in ast.expressions.iter() {
+        let Ok(Some(define)) = DefineFunctionsParsed::try_parse(expr) else {
+            continue;
+        };
+        let (signature, body, define_type) = match define {
+            DefineFunctionsParsed::PrivateFunction { signature, body } => {
+                (signature, body, DefineType::Private)
+            }
+            DefineFunctionsParsed::ReadOnlyFunction { signature, body } => {
+                (signature, body, DefineType::ReadOnly)
+            }
+            DefineFunctionsParsed::PublicFunction { signature, body } => {
+                (signature, body, DefineType::Public)
+            }
+            DefineFunctionsParsed::Constant { name, value } => {
+                num_constants += 1;
+                let Some(stored) = context.variables.get(name) else {
+                    return Err(format!("no constant {name}"));
+                };
+                let literal = value.match_literal_value().or(value.match_atom_value());
+                if literal.is_some_and(|literal| literal != stored) {
+                    return Err(format!("constant {name} does not match the source code"));
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        num_functions += 1;
+        let name = signature
+            .first()
+            .and_then(|name| name.match_atom())
+            .ok_or("function without a name")?;
+        let arguments = parse_name_type_pairs(analysis.epoch, &signature[1..], &mut ())
+            .map_err(|e| format!("could not parse arguments of {name}: {e}"))?;
+        let Some(function) = context.functions.get(name) else {
+            return Err(format!("no function {name}"));
+        };
+        if function.define_type != define_type
+            || function.get_body() != body
+            || function
+                .get_arguments()
+                .iter()
+                .ne(arguments.iter().map(|(name, _)| name))
+            || function
+                .get_arg_types()
+                .iter()
+                .ne(arguments.iter().map(|(_, ty)| ty))
+        {
+            return Err(format!("function {name} does not match the source code"));
+        }
+    }
+    if context.functions.len() != num_functions || context.variables.len() != num_constants {
+        return Err("functions or constants do not match the source code".into());
+    }
+
+    if context
+        .defined_traits
+        .clone()
+        .into_iter()
+        .collect::<BTreeMap<_, _>>()
+        != analysis.defined_traits
+        || context
+            .implemented_traits
+            .iter()
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            != analysis.implemented_traits
+    {
+        return Err("traits do not match the analysis".into());
+    }
+    if context.meta_data_var.len() != analysis.persisted_variable_types.len()
+        || context.meta_data_var.iter().any(|(name, meta)| {
+            analysis.persisted_variable_types.get(name) != Some(&meta.value_type)
+        })
+        || context.meta_data_map.len() != analysis.map_types.len()
+        || context.meta_data_map.iter().any(|(name, meta)| {
+            analysis.map_types.get(name) != Some(&(meta.key_type.clone(), meta.value_type.clone()))
+        })
+        || context.meta_nft.len() != analysis.non_fungible_tokens.len()
+        || context
+            .meta_nft
+            .iter()
+            .any(|(name, meta)| analysis.non_fungible_tokens.get(name) != Some(&meta.key_type))
+        || context.meta_ft.len() != analysis.fungible_tokens.len()
+        || context
+            .meta_ft
+            .keys()
+            .any(|name| !analysis.fungible_tokens.contains(name))
+    {
+        return Err("data definitions do not match the analysis".into());
+    }
+
+    // every other metadata entry must be the contract's own description of a definition
+    for (key, value) in metadata.iter() {
+        let expected = match key.split_once("::").map(|(_, rest)| rest) {
+            _ if key == AnalysisDatabase::storage_key() => continue,
+            _ if key == "::state_summary" => continue,
+            Some(rest) => {
+                let (store_type, name) = rest.split_once("::").unwrap_or((rest, ""));
+                match store_type.parse::<u8>() {
+                    Ok(x) if x == StoreType::Contract as u8 => continue,
+                    Ok(x) if x == StoreType::VariableMeta as u8 => {
+                        context.meta_data_var.get(name).map(|meta| meta.serialize())
+                    }
+                    Ok(x) if x == StoreType::DataMapMeta as u8 => {
+                        context.meta_data_map.get(name).map(|meta| meta.serialize())
+                    }
+                    Ok(x) if x == StoreType::FungibleTokenMeta as u8 => {
+                        context.meta_ft.get(name).map(|meta| meta.serialize())
+                    }
+                    Ok(x) if x == StoreType::NonFungibleTokenMeta as u8 => {
+                        context.meta_nft.get(name).map(|meta| meta.serialize())
+                    }
+                    _ => None,
+                }
+            }
+            None => None,
+        };
+        if expected.as_ref() != Some(value) {
+            return Err(format!("unexpected metadata entry {key}"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::chainstate::nakamoto::coordinator::tests::simple_nakamoto_coordinator_10_tenures_10_sortitions;
+
+    #[test]
+    fn test_snapshot_export_import() {
+        let mut peer = simple_nakamoto_coordinator_10_tenures_10_sortitions();
+        let burnchain = peer.config.burnchain.clone();
+        let chainstate_path = peer.chainstate_path.clone();
+        let mainnet = peer.chainstate().mainnet;
+        let chain_id = peer.chainstate().chain_id;
+        let (consensus_hash, block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(peer.sortdb().conn()).unwrap();
+        let tip = StacksBlockId::new(&consensus_hash, &block_hash);
+
+        // the operator gets the reward set from a node they trust
+        let mut stacks_node = peer.stacks_node.take().unwrap();
+        let sortdb = peer.sortdb.take().unwrap();
+        let tenure_sn = SortitionDB::get_block_snapshot_consensus(sortdb.conn(), &consensus_hash)
+            .unwrap()
+            .unwrap();
+        let reward_set = load_nakamoto_reward_set(
+            burnchain
+                .block_height_to_reward_cycle(tenure_sn.block_height)
+                .unwrap(),
+            &tenure_sn.sortition_id,
+            &burnchain,
+            &mut stacks_node.chainstate,
+            &tip,
+            &sortdb,
+            &OnChainRewardSetProvider::new(),
+        )
+        .unwrap()
+        .unwrap()
+        .0
+        .known_selected_anchor_block_owned()
+        .unwrap();
+        drop(sortdb);
+        drop(stacks_node);
+        drop(peer);
+
+        let snapshot_dir = PathBuf::from(format!("{chainstate_path}.snapshot"));
+        if snapshot_dir.exists() {
+            fs::remove_dir_all(&snapshot_dir).unwrap();
+        }
+
+        // the canonical Stacks tip must be in the requested tenure
+        let err = export_snapshot(
+            &burnchain,
+            &chainstate_path,
+            &snapshot_dir,
+            Some(&ConsensusHash([0x01; 20])),
+        )
+        .unwrap_err();
+        assert!(matches!(err, SnapshotError::InvalidSnapshot(_)), "{err}");
+
+        let manifest = export_snapshot(
+            &burnchain,
+            &chainstate_path,
+            &snapshot_dir,
+            Some(&consensus_hash),
+        )
+        .unwrap();
+        assert_eq!(manifest.block_id, tip);
+        assert_eq!(manifest.consensus_hash, consensus_hash);
+        assert_eq!(manifest, SnapshotManifest::load(&snapshot_dir).unwrap());
+
+        let node_paths = |name: &str| {
+            let root = format!("{chainstate_path}.{name}");
+            if fs::metadata(&root).is_ok() {
+                fs::remove_dir_all(&root).unwrap();
+            }
+            let mut burnchain = burnchain.clone();
+            burnchain.working_dir = format!("{root}/burnchain");
+            (burnchain, format!("{root}/chainstate"))
+        };
+
+        // import into a new node
+        let (import_burnchain, import_chainstate_path) = node_paths("imported");
+        let imported = import_snapshot(
+            &snapshot_dir,
+            &import_burnchain,
+            &import_chainstate_path,
+            mainnet,
+            chain_id,
+            &tip,
+            &reward_set,
+        )
+        .unwrap();
+        assert_eq!(imported, manifest);
+
+        // the new node has the snapshot block's state, and is a pruned node
+        let (mut chainstate, _) =
+            StacksChainState::open(mainnet, chain_id, &import_chainstate_path, None).unwrap();
+        let state_index_root = chainstate
+            .with_clarity_marf(|marf| marf.get_root_hash_at(&tip))
+            .unwrap();
+        assert_eq!(state_index_root, manifest.state_index_root);
+        assert!(StacksChainState::get_prune_horizon(chainstate.db())
+            .unwrap()
+            .is_some());
+        drop(chainstate);
+
+        // a node with chainstate is left alone
+        let err = import_snapshot(
+            &snapshot_dir,
+            &import_burnchain,
+            &import_chainstate_path,
+            mainnet,
+            chain_id,
+            &tip,
+            &reward_set,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SnapshotError::AlreadyInitialized(_)), "{err}");
+
+        // a snapshot for another chain is rejected
+        let (other_burnchain, other_chainstate_path) = node_paths("other");
+        let err = import_snapshot(
+            &snapshot_dir,
+            &other_burnchain,
+            &other_chainstate_path,
+            mainnet,
+            chain_id + 1,
+            &tip,
+            &reward_set,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SnapshotError::InvalidSnapshot(_)), "{err}");
+
+        // a snapshot of a block other than the trusted one is rejected
+        let (untrusted_burnchain, untrusted_chainstate_path) = node_paths("untrusted");
+        let err = import_snapshot(
+            &snapshot_dir,
+            &untrusted_burnchain,
+            &untrusted_chainstate_path,
+            mainnet,
+            chain_id,
+            &StacksBlockId([0x01; 32]),
+            &reward_set,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SnapshotError::VerificationFailed(_)), "{err}");
+        for entry in snapshot_entries(&untrusted_burnchain, &untrusted_chainstate_path) {
+            assert!(!entry.node_path.exists());
+        }
+
+        // a snapshot block which is not signed by the trusted signers is rejected
+        let mut other_reward_set = reward_set.clone();
+        other_reward_set.signers.as_mut().unwrap()[0].signing_key = [0x02; 33];
+        let (unsigned_burnchain, unsigned_chainstate_path) = node_paths("unsigned");
+        let err = import_snapshot(
+            &snapshot_dir,
+            &unsigned_burnchain,
+            &unsigned_chainstate_path,
+            mainnet,
+            chain_id,
+            &tip,
+            &other_reward_set,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SnapshotError::VerificationFailed(_)), "{err}");
+
+        // rewrite a file's entry in the manifest, as a malicious snapshot maker would
+        let rewrite_manifest = |name: &str| {
+            let mut manifest = SnapshotManifest::load(&snapshot_dir).unwrap();
+            let (size, sha512_256) = hash_file(&snapshot_dir.join(name)).unwrap();
+            let file = manifest
+                .files
+                .iter_mut()
+                .find(|file| file.name == name)
+                .unwrap();
+            file.size = size;
+            file.sha512_256 = sha512_256;
+            manifest.store(&snapshot_dir).unwrap();
+        };
+
+        // a side-store value which the MARF does not commit to is rejected
+        let clarity_db_name = "chainstate/vm/clarity/marf.sqlite";
+        let clarity_db_path = snapshot_dir.join(clarity_db_name);
+        let clarity_db = fs::read(&clarity_db_path).unwrap();
+        let manifest_bytes = fs::read(snapshot_dir.join(SNAPSHOT_MANIFEST)).unwrap();
+        let conn = Connection::open(&clarity_db_path).unwrap();
+        conn.execute(
+            "UPDATE data_table SET value = value || '00' WHERE rowid = (SELECT MIN(rowid) FROM data_table)",
+            NO_PARAMS,
+        )
+        .unwrap();
+        drop(conn);
+        rewrite_manifest(clarity_db_name);
+
+        let (side_store_burnchain, side_store_chainstate_path) = node_paths("side-store");
+        let err = import_snapshot(
+            &snapshot_dir,
+            &side_store_burnchain,
+            &side_store_chainstate_path,
+            mainnet,
+            chain_id,
+            &tip,
+            &reward_set,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SnapshotError::VerificationFailed(_)), "{err}");
+        fs::write(&clarity_db_path, &clarity_db).unwrap();
+        fs::write(snapshot_dir.join(SNAPSHOT_MANIFEST), &manifest_bytes).unwrap();
+
+        // contract metadata which does not match the contract the MARF commits to is rejected
+        let conn = Connection::open(&clarity_db_path).unwrap();
+        conn.execute(
+            "UPDATE metadata_table SET value = value || ' ' WHERE rowid = (SELECT MIN(rowid) FROM metadata_table WHERE key LIKE '%::contract-src')",
+            NO_PARAMS,
+        )
+        .unwrap();
+        drop(conn);
+        rewrite_manifest(clarity_db_name);
+
+        let (metadata_burnchain, metadata_chainstate_path) = node_paths("metadata");
+        let err = import_snapshot(
+            &snapshot_dir,
+            &metadata_burnchain,
+            &metadata_chainstate_path,
+            mainnet,
+            chain_id,
+            &tip,
+            &reward_set,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SnapshotError::VerificationFailed(_)), "{err}");
+        fs::write(&clarity_db_path, &clarity_db).unwrap();
+        fs::write(snapshot_dir.join(SNAPSHOT_MANIFEST), &manifest_bytes).unwrap();
+
+        // a corrupt snapshot is rejected, and leaves nothing behind.  Corrupt the stored root
+        // hash of the last trie, which follows the trie's parent block hash and block ID.
+        let last_trie_offset: i64 = Connection::open(&clarity_db_path)
+            .unwrap()
+            .query_row(
+                "SELECT external_offset FROM marf_data ORDER BY block_id DESC LIMIT 1",
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap();
+        let blobs_path = snapshot_dir.join("chainstate/vm/clarity/marf.sqlite.blobs");
+        let mut blobs = fs::read(&blobs_path).unwrap();
+        blobs[usize::try_from(last_trie_offset).unwrap() + 36] ^= 0xff;
+        fs::write(&blobs_path, &blobs).unwrap();
+
+        let (corrupt_burnchain, corrupt_chainstate_path) = node_paths("corrupt");
+        let err = import_snapshot(
+            &snapshot_dir,
+            &corrupt_burnchain,
+            &corrupt_chainstate_path,
+            mainnet,
+            chain_id,
+            &tip,
+            &reward_set,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SnapshotError::VerificationFailed(_)), "{err}");
+        for entry in snapshot_entries(&corrupt_burnchain, &corrupt_chainstate_path) {
+            assert!(!entry.node_path.exists());
+        }
+
+        // a corrupt snapshot is rejected even if its manifest matches it
+        rewrite_manifest("chainstate/vm/clarity/marf.sqlite.blobs");
+        let (rewritten_burnchain, rewritten_chainstate_path) = node_paths("rewritten");
+        let err = import_snapshot(
+            &snapshot_dir,
+            &rewritten_burnchain,
+            &rewritten_chainstate_path,
+            mainnet,
+            chain_id,
+            &tip,
+            &reward_set,
+        )
+        .unwrap_err();
+        assert!(matches!(err, SnapshotError::VerificationFailed(_)), "{err}");
+        for entry in snapshot_entries(&rewritten_burnchain, &rewritten_chainstate_path) {
+            assert!(!entry.node_path.exists());
+        }
+    }
+}
@@ -523,6 +523,17 @@ impl StacksChainState {
         config: &DBConfig,
         tx: &StacksTransaction,
         epoch_id: StacksEpochId,
+    ) -> Result<(), Error> {
+        Self::process_transaction_precheck_ex(config, tx, epoch_id, true)
+    }
+
+    /// Pre-check a transaction, optionally without verifying its signatures.
+    /// Signatures may only be skipped if the transaction is being simulated.
+    fn process_transaction_precheck_ex(
+        config: &DBConfig,
+        tx: &StacksTransaction,
+        epoch_id: StacksEpochId,
+        verify_signatures: bool,
     ) -> Result<(), Error> {
         // valid auth?
         if !tx.auth.is_supported_in_epoch(epoch_id) {
@@ -534,7 +545,9 @@ impl StacksChainState {
 
             return Err(Error::InvalidStacksTransaction(msg, false));
         }
-        tx.verify().map_err(Error::NetError)?;
+        if verify_signatures {
+            tx.verify().map_err(Error::NetError)?;
+        }
 
         // destined for us?
         if config.chain_id != tx.chain_id {
@@ -1452,17 +1465,44 @@ impl StacksChainState {
         clarity_block: &mut ClarityTx,
         tx: &StacksTransaction,
     ) -> Result<ClarityVersion, Error> {
-        let clarity_version = match &tx.payload {
+        Ok(Self::tx_clarity_version(tx, clarity_block.get_epoch()))
+    }
+
+    /// Deduce the Clarity version to run in the given epoch
+    fn tx_clarity_version(tx: &StacksTransaction, epoch: StacksEpochId) -> ClarityVersion {
+        match &tx.payload {
             TransactionPayload::SmartContract(_, ref version_opt) => {
                 // did the caller want to run a particular version of Clarity?
-                version_opt.unwrap_or(ClarityVersion::default_for_epoch(clarity_block.get_epoch()))
+                version_opt.unwrap_or(ClarityVersion::default_for_epoch(epoch))
             }
             _ => {
                 // whatever the epoch default is, since no Clarity code will be executed anyway
-                ClarityVersion::default_for_epoch(clarity_block.get_epoch())
+                ClarityVersion::default_for_epoch(epoch)
             }
-        };
-        Ok(clarity_version)
+        }
+    }
+
+    /// Check that a transaction can be processed at all in the given epoch, before touching
+    /// any chainstate.
+    pub(crate) fn process_transaction_prechecks(
+        config: &DBConfig,
+        tx: &StacksTransaction,
+        epoch: StacksEpochId,
+        verify_signatures: bool,
+    ) -> Result<(), Error> {
+        StacksChainState::process_transaction_precheck_ex(config, tx, epoch, verify_signatures)?;
+
+        // what version of Clarity did the transaction caller want? And, is it valid now?
+        let clarity_version = StacksChainState::tx_clarity_version(tx, epoch);
+        if clarity_version == ClarityVersion::Clarity2 {
+            // requires 2.1 and higher
+            if epoch < StacksEpochId::Epoch21 {
+                let msg = format!("Invalid transaction {}: asks for Clarity2, but not in Stacks epoch 2.1 or later", tx.txid());
+                info!("{}", &msg);
+                return Err(Error::InvalidStacksTransaction(msg, false));
+            }
+        }
+        Ok(())
     }
 
     /// Process a transaction.  Return the fee and the transaction receipt
@@ -1475,21 +1515,38 @@ impl StacksChainState {
         debug!("Process transaction {} ({})", tx.txid(), tx.payload.name());
         let epoch = clarity_block.get_epoch();
 
-        StacksChainState::process_transaction_precheck(&clarity_block.config, tx, epoch)?;
+        StacksChainState::process_transaction_prechecks(&clarity_block.config, tx, epoch, true)?;
 
-        // what version of Clarity did the transaction caller want? And, is it valid now?
-        let clarity_version = StacksChainState::get_tx_clarity_version(clarity_block, tx)?;
-        if clarity_version == ClarityVersion::Clarity2 {
-            // requires 2.1 and higher
-            if clarity_block.get_epoch() < StacksEpochId::Epoch21 {
-                let msg = format!("Invalid transaction {}: asks for Clarity2, but not in Stacks epoch 2.1 or later", tx.txid());
-                info!("{}", &msg);
-                return Err(Error::InvalidStacksTransaction(msg, false));
-            }
-        }
+        let transaction = clarity_block.connection().start_transaction_processing();
+        StacksChainState::process_transaction_in(transaction, tx, epoch, quiet, ast_rules)
+    }
 
-        let mut transaction = clarity_block.connection().start_transaction_processing();
+    /// Simulate a transaction in the given Clarity transaction.  This processes it just like
+    /// `process_transaction()`, except that its signatures are not verified, so unsigned
+    /// transactions can be simulated too.  `transaction` should discard its writes (see
+    /// `ClarityReadOnlyConnection::as_simulated_transaction()`).
+    /// Return the fee and the transaction receipt.
+    pub fn simulate_transaction(
+        config: &DBConfig,
+        transaction: ClarityTransactionConnection,
+        tx: &StacksTransaction,
+        epoch: StacksEpochId,
+        ast_rules: ASTRules,
+    ) -> Result<(u64, StacksTransactionReceipt), Error> {
+        debug!("Simulate transaction {} ({})", tx.txid(), tx.payload.name());
+        StacksChainState::process_transaction_prechecks(config, tx, epoch, false)?;
+        StacksChainState::process_transaction_in(transaction, tx, epoch, true, ast_rules)
+    }
 
+    /// Process a transaction which has passed `process_transaction_prechecks()` in the given
+    /// Clarity transaction, and commit it.  Return the fee and the transaction receipt.
+    pub(crate) fn process_transaction_in(
+        mut transaction: ClarityTransactionConnection,
+        tx: &StacksTransaction,
+        epoch: StacksEpochId,
+        quiet: bool,
+        ast_rules: ASTRules,
+    ) -> Result<(u64, StacksTransactionReceipt), Error> {
         let fee = tx.get_tx_fee();
         let tx_receipt = if epoch >= StacksEpochId::Epoch21 {
             // 2.1 and later: pay tx fee, then process transaction
@@ -40,11 +40,14 @@ use crate::chainstate::stacks::index::bits::{
     read_nodetype, read_nodetype_at_head, read_nodetype_at_head_nohash, read_root_hash,
     write_nodetype_bytes,
 };
+use crate::chainstate::stacks::index::lsm::LsmStore;
 use crate::chainstate::stacks::index::node::{
     clear_backptr, is_backptr, set_backptr, TrieNode, TrieNode16, TrieNode256, TrieNode4,
     TrieNode48, TrieNodeID, TrieNodeType, TriePtr,
 };
-use crate::chainstate::stacks::index::storage::{NodeHashReader, TrieStorageConnection};
+use crate::chainstate::stacks::index::storage::{
+    MARFStorageBackend, NodeHashReader, TrieStorageConnection,
+};
 use crate::chainstate::stacks::index::{trie_sql, ClarityMarfTrieId, Error, MarfTrieId, TrieLeaf};
 use crate::util_lib::db::{
     sql_pragma, sql_vacuum, sqlite_open, tx_begin_immediate, tx_busy_handler, Error as db_error,
@@ -68,15 +71,34 @@ pub struct TrieFileRAM {
     trie_offsets: TrieIdOffsets,
 }
 
+/// Handle to trie blobs in a MARF's LSM store.  Each blob is stored under the offset it would
+/// have in a flat file, so that the offsets in `marf_data` mean the same thing for every
+/// backend.  Reading at an offset reads from the blob which contains it.  Writes are buffered,
+/// and stored as one blob on `flush()`.
+pub struct TrieFileLsm {
+    store: LsmStore,
+    path: String,
+    readonly: bool,
+    pos: u64,
+    /// The blob which was read last, and its offset
+    cur_blob: Option<(u64, sled::IVec)>,
+    /// Bytes written since the last flush, and the offset they were written at
+    pending: Vec<u8>,
+    pending_offset: u64,
+    trie_offsets: TrieIdOffsets,
+}
+
 /// This is flat-file storage for a MARF's tries.  All tries are stored as contiguous byte arrays
 /// within a larger byte array.  The variants differ in how those bytes are backed.  The `RAM`
-/// variant stores data in RAM in a byte buffer, and the `Disk` variant stores data in a flat file
-/// on disk.  This structure is used to support external trie blobs, so that the tries don't need
+/// variant stores data in RAM in a byte buffer, the `Disk` variant stores data in a flat file
+/// on disk, and the `Lsm` variant stores each trie as a value in an embedded key/value store.
+/// This structure is used to support external trie blobs, so that the tries don't need
 /// to be stored in sqlite blobs (which incurs a sqlite paging overhead).  This is useful for when
 /// the tries are too big to fit into a single page, such as the Stacks chainstate.
 pub enum TrieFile {
     RAM(TrieFileRAM),
     Disk(TrieFileDisk),
+    Lsm(TrieFileLsm),
 }
 
 impl TrieFile {
@@ -103,6 +125,29 @@ impl TrieFile {
         })
     }
 
+    /// Make a new TrieFile backed by the MARF's LSM store
+    fn new_lsm(db_path: &str, readonly: bool) -> Result<TrieFile, Error> {
+        let store = LsmStore::open(db_path, !readonly)?;
+        Ok(TrieFile::Lsm(TrieFileLsm {
+            store,
+            path: LsmStore::path(db_path),
+            readonly,
+            pos: 0,
+            cur_blob: None,
+            pending: vec![],
+            pending_offset: 0,
+            trie_offsets: TrieIdOffsets::new(),
+        }))
+    }
+
+    /// Does the TrieFile for the given storage backend exist for the given DB path?
+    pub fn exists_with_backend(path: &str, backend: MARFStorageBackend) -> Result<bool, Error> {
+        match backend {
+            MARFStorageBackend::Sqlite => TrieFile::exists(path),
+            MARFStorageBackend::Lsm => Ok(path != ":memory:" && LsmStore::exists(path)),
+        }
+    }
+
     /// Does the TrieFile exist at the expected path?
     pub fn exists(path: &str) -> Result<bool, Error> {
         if path == ":memory:" {
@@ -128,6 +173,23 @@ impl TrieFile {
         match self {
             TrieFile::RAM(_) => ":memory:".to_string(),
             TrieFile::Disk(ref disk) => disk.path.clone(),
+            TrieFile::Lsm(ref lsm) => lsm.path.clone(),
+        }
+    }
+
+    /// Which storage backend holds this TrieFile's blobs
+    pub fn backend(&self) -> MARFStorageBackend {
+        match self {
+            TrieFile::RAM(_) | TrieFile::Disk(_) => MARFStorageBackend::Sqlite,
+            TrieFile::Lsm(_) => MARFStorageBackend::Lsm,
+        }
+    }
+
+    /// Get the LSM store which holds this TrieFile's blobs, if it is in one
+    pub fn lsm_store(&self) -> Option<&LsmStore> {
+        match self {
+            TrieFile::Lsm(ref lsm) => Some(&lsm.store),
+            _ => None,
         }
     }
 
@@ -143,6 +205,19 @@ impl TrieFile {
         }
     }
 
+    /// Instantiate a TrieFile for the given storage backend, given the associated DB path.
+    /// If path is ':memory:', then it'll be an in-RAM TrieFile regardless of the backend.
+    pub fn from_db_path_with_backend(
+        path: &str,
+        readonly: bool,
+        backend: MARFStorageBackend,
+    ) -> Result<TrieFile, Error> {
+        match backend {
+            MARFStorageBackend::Lsm if path != ":memory:" => TrieFile::new_lsm(path, readonly),
+            _ => TrieFile::from_db_path(path, readonly),
+        }
+    }
+
     /// Append a new trie blob to external storage, and add the offset and length to the trie DB.
     /// Return the trie ID
     pub fn store_trie_blob<T: MarfTrieId>(
@@ -354,6 +429,7 @@ impl TrieFile {
         let offset_opt = match self {
             TrieFile::RAM(ref ram) => ram.trie_offsets.get(&block_id),
             TrieFile::Disk(ref disk) => disk.trie_offsets.get(&block_id),
+            TrieFile::Lsm(ref lsm) => lsm.trie_offsets.get(&block_id),
         };
         match offset_opt {
             Some(offset) => Ok(*offset),
@@ -362,6 +438,7 @@ impl TrieFile {
                 match self {
                     TrieFile::RAM(ref mut ram) => ram.trie_offsets.insert(block_id, offset),
                     TrieFile::Disk(ref mut disk) => disk.trie_offsets.insert(block_id, offset),
+                    TrieFile::Lsm(ref mut lsm) => lsm.trie_offsets.insert(block_id, offset),
                 };
                 Ok(offset)
             }
@@ -454,6 +531,12 @@ impl TrieFile {
     pub fn append_trie_blob(&mut self, db: &Connection, buf: &[u8]) -> Result<u64, Error> {
         let offset = trie_sql::get_external_blobs_length(db)?;
         test_debug!("Write trie of {} bytes at {}", buf.len(), offset);
+        self.write_blob(offset, buf)?;
+        Ok(offset)
+    }
+
+    /// Durably write a trie blob at the given offset
+    pub fn write_blob(&mut self, offset: u64, buf: &[u8]) -> Result<(), Error> {
         self.seek(SeekFrom::Start(offset))?;
         self.write_all(buf)?;
         self.flush()?;
@@ -461,7 +544,208 @@ impl TrieFile {
         if let TrieFile::Disk(ref mut data) = self {
             data.fd.sync_data()?;
         }
-        Ok(offset)
+        Ok(())
+    }
+
+    /// Read the trie blob at the given offset
+    pub fn read_blob(&mut self, offset: u64, length: u64) -> Result<Vec<u8>, Error> {
+        self.seek(SeekFrom::Start(offset))?;
+        let mut buf = vec![0u8; length as usize];
+        self.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Get the length of the byte array holding the trie blobs
+    pub fn len(&mut self) -> Result<u64, Error> {
+        Ok(self.seek(SeekFrom::End(0))?)
+    }
+
+    /// Free the bytes of the trie blob at `offset` with length `length` which are outside of
+    /// `keep`, a sorted list of non-overlapping `(start, length)` ranges within the blob.  Kept
+    /// bytes stay where they are, so every offset into the blob stays valid.  Freed bytes either
+    /// read back as zeros or cannot be read at all, so nothing may be read from them again.
+    ///
+    /// Returns the number of bytes freed.
+    pub fn retain_blob_ranges(
+        &mut self,
+        offset: u64,
+        length: u64,
+        keep: &[(u64, u64)],
+    ) -> Result<u64, Error> {
+        let mut gaps = vec![];
+        let mut pos = 0;
+        for (start, len) in keep.iter() {
+            if *start > pos {
+                gaps.push((pos, *start - pos));
+            }
+            pos = pos.max(start + len);
+        }
+        if pos < length {
+            gaps.push((pos, length - pos));
+        }
+        let released = gaps.iter().map(|(_, len)| len).sum();
+
+        match self {
+            TrieFile::RAM(ref mut ram) => {
+                let buf = ram.fd.get_mut();
+                for (start, len) in gaps.into_iter() {
+                    let start = (offset + start) as usize;
+                    buf.get_mut(start..start + len as usize)
+                        .ok_or_else(|| {
+                            Error::CorruptionError("Trie blob is out of bounds".to_string())
+                        })?
+                        .fill(0);
+                }
+            }
+            TrieFile::Disk(ref mut disk) => {
+                for (start, len) in gaps.into_iter() {
+                    disk.release(offset + start, len)?;
+                }
+                disk.fd.sync_data()?;
+            }
+            TrieFile::Lsm(ref mut lsm) => lsm.retain(offset, length, keep)?,
+        }
+        Ok(released)
+    }
+
+    /// Discard everything from the given offset onwards
+    pub fn truncate(&mut self, len: u64) -> Result<(), Error> {
+        match self {
+            TrieFile::RAM(ref mut ram) => {
+                ram.fd.get_mut().truncate(len as usize);
+            }
+            TrieFile::Disk(ref mut disk) => {
+                disk.fd.set_len(len)?;
+                disk.fd.sync_all()?;
+            }
+            TrieFile::Lsm(ref mut lsm) => lsm.truncate(len)?,
+        }
+        Ok(())
+    }
+}
+
+impl TrieFileDisk {
+    /// Free `len` bytes of the file at `offset`, so that they read back as zeros.  Punches a hole
+    /// in the file where the OS and file system support it, and overwrites the bytes with zeros
+    /// otherwise (which keeps them allocated).
+    fn release(&mut self, offset: u64, len: u64) -> Result<(), Error> {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            use nix::errno::Errno;
+            use nix::fcntl::{fallocate, FallocateFlags};
+
+            let flags = FallocateFlags::FALLOC_FL_PUNCH_HOLE | FallocateFlags::FALLOC_FL_KEEP_SIZE;
+            let (Ok(hole_offset), Ok(hole_len)) = (i64::try_from(offset), i64::try_from(len))
+            else {
+                return Err(Error::BadSeekValue);
+            };
+            match fallocate(self.fd.as_raw_fd(), flags, hole_offset, hole_len) {
+                Ok(()) => return Ok(()),
+                Err(Errno::EOPNOTSUPP) => {}
+                Err(e) => return Err(Error::IOError(io::Error::from(e))),
+            }
+        }
+
+        let zeros = vec![0u8; len.min(1 << 20) as usize];
+        self.fd.seek(SeekFrom::Start(offset))?;
+        let mut remaining = len;
+        while remaining > 0 {
+            let chunk = remaining.min(zeros.len() as u64);
+            self.fd.write_all(&zeros[..chunk as usize])?;
+            remaining -= chunk;
+        }
+        Ok(())
+    }
+}
+
+impl TrieFileLsm {
+    fn tree(&self) -> &sled::Tree {
+        self.store.trie_blobs()
+    }
+
+    /// Find the blob which contains the given offset, if there is one
+    fn load_blob_at(&mut self, pos: u64) -> io::Result<Option<(u64, sled::IVec)>> {
+        if let Some((offset, ref blob)) = self.cur_blob {
+            if offset <= pos && pos < offset + blob.len() as u64 {
+                return Ok(self.cur_blob.clone());
+            }
+        }
+        let Some(entry) = self.tree().range(..=pos.to_be_bytes()).next_back() else {
+            return Ok(None);
+        };
+        let (key, blob) = entry?;
+        let offset = u64::from_be_bytes(key.as_ref().try_into().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "Malformed trie blob offset")
+        })?);
+        if pos >= offset + blob.len() as u64 {
+            return Ok(None);
+        }
+        self.cur_blob = Some((offset, blob));
+        Ok(self.cur_blob.clone())
+    }
+
+    /// Offset of the end of the last blob
+    fn end(&self) -> io::Result<u64> {
+        let Some((key, blob)) = self.tree().last()? else {
+            return Ok(0);
+        };
+        let offset = u64::from_be_bytes(key.as_ref().try_into().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidData, "Malformed trie blob offset")
+        })?);
+        Ok(offset + blob.len() as u64)
+    }
+
+    /// Replace the blob (or blobs) at `offset` with length `length` by the given ranges of it,
+    /// each stored as a blob of its own.  Reading from the rest of the range then fails.
+    fn retain(&mut self, offset: u64, length: u64, keep: &[(u64, u64)]) -> Result<(), Error> {
+        if self.readonly {
+            return Err(Error::ReadOnlyError);
+        }
+        let mut segments = vec![];
+        for (start, len) in keep.iter() {
+            let mut buf = vec![0u8; *len as usize];
+            self.pos = offset + start;
+            self.read_exact(&mut buf)?;
+            segments.push((offset + start, buf));
+        }
+        self.cur_blob = None;
+
+        // the last operation on a key in a batch wins, so the removals go first
+        let mut batch = sled::Batch::default();
+        let end = offset + length;
+        for entry in self.tree().range(offset.to_be_bytes()..end.to_be_bytes()) {
+            let (key, _) = entry.map_err(io::Error::from)?;
+            batch.remove(key);
+        }
+        for (segment_offset, buf) in segments.into_iter() {
+            batch.insert(&segment_offset.to_be_bytes(), buf);
+        }
+        self.tree().apply_batch(batch).map_err(io::Error::from)?;
+        self.store.flush()
+    }
+
+    fn truncate(&mut self, len: u64) -> Result<(), Error> {
+        if self.readonly {
+            return Err(Error::ReadOnlyError);
+        }
+        self.cur_blob = None;
+        let straddling = match self.load_blob_at(len)? {
+            Some((offset, blob)) if offset < len => Some((offset, blob)),
+            _ => None,
+        };
+        self.cur_blob = None;
+        let mut batch = sled::Batch::default();
+        for entry in self.tree().range(len.to_be_bytes()..) {
+            let (key, _) = entry.map_err(io::Error::from)?;
+            batch.remove(key);
+        }
+        if let Some((offset, blob)) = straddling {
+            batch.insert(&offset.to_be_bytes(), &blob[..(len - offset) as usize]);
+        }
+        self.tree().apply_batch(batch).map_err(io::Error::from)?;
+        self.store.flush()
     }
 }
 
@@ -487,12 +771,58 @@ impl Write for TrieFileRAM {
     }
 }
 
+/// Write implementation for TrieFileLsm.  Only contiguous writes are supported; they are
+/// stored as one blob at the offset of the first one on `flush()`, replacing everything after it.
+impl Write for TrieFileLsm {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.readonly {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "LSM trie store is read-only",
+            ));
+        }
+        if self.pending.is_empty() {
+            self.pending_offset = self.pos;
+        } else if self.pending_offset + self.pending.len() as u64 != self.pos {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Non-contiguous write to LSM trie store",
+            ));
+        }
+        self.pending.extend_from_slice(buf);
+        self.pos += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        // anything after this blob was left behind by a trie which was never committed
+        let mut batch = sled::Batch::default();
+        for entry in self.tree().range(self.pending_offset.to_be_bytes()..) {
+            let (key, _) = entry?;
+            batch.remove(key);
+        }
+        batch.insert(
+            &self.pending_offset.to_be_bytes(),
+            std::mem::take(&mut self.pending),
+        );
+        self.tree().apply_batch(batch)?;
+        self.cur_blob = None;
+        self.store
+            .flush()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+    }
+}
+
 /// Boilerplate Write implementation for TrieFile enum.  Plumbs through to the inner struct.
 impl Write for TrieFile {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self {
             TrieFile::RAM(ref mut ram) => ram.write(buf),
             TrieFile::Disk(ref mut disk) => disk.write(buf),
+            TrieFile::Lsm(ref mut lsm) => lsm.write(buf),
         }
     }
 
@@ -500,6 +830,7 @@ impl Write for TrieFile {
         match self {
             TrieFile::RAM(ref mut ram) => ram.flush(),
             TrieFile::Disk(ref mut disk) => disk.flush(),
+            TrieFile::Lsm(ref mut lsm) => lsm.flush(),
         }
     }
 }
@@ -518,12 +849,27 @@ impl Read for TrieFileRAM {
     }
 }
 
+/// Read implementation for TrieFileLsm.  Reads from the blob containing the current position.
+impl Read for TrieFileLsm {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some((offset, blob)) = self.load_blob_at(self.pos)? else {
+            return Ok(0);
+        };
+        let start = (self.pos - offset) as usize;
+        let len = buf.len().min(blob.len() - start);
+        buf[..len].copy_from_slice(&blob[start..start + len]);
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+
 /// Boilerplate Read implementation for TrieFile enum.  Plumbs through to the inner struct.
 impl Read for TrieFile {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         match self {
             TrieFile::RAM(ref mut ram) => ram.read(buf),
             TrieFile::Disk(ref mut disk) => disk.read(buf),
+            TrieFile::Lsm(ref mut lsm) => lsm.read(buf),
         }
     }
 }
@@ -542,11 +888,30 @@ impl Seek for TrieFileRAM {
     }
 }
 
+/// Seek implementation for TrieFileLsm.  The end is the end of the last blob.
+impl Seek for TrieFileLsm {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.end()?.checked_add_signed(delta),
+        };
+        self.pos = new_pos.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid seek to a negative offset",
+            )
+        })?;
+        Ok(self.pos)
+    }
+}
+
 impl Seek for TrieFile {
     fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
         match self {
             TrieFile::RAM(ref mut ram) => ram.seek(pos),
             TrieFile::Disk(ref mut disk) => disk.seek(pos),
+            TrieFile::Lsm(ref mut lsm) => lsm.seek(pos),
         }
     }
 }
@@ -0,0 +1,188 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Embedded log-structured key/value storage for a MARF.
+//!
+//! With the `Lsm` storage backend, a MARF keeps its trie blobs in a sled database in the `.lsm`
+//! directory next to its SQLite DB, instead of in a flat `.blobs` file.  Everything else stays in
+//! SQLite: the `marf_data` index, and the Clarity side-store's `data_table`, so the values a
+//! block writes are committed in the same transaction as its trie.  A trie blob is written and
+//! flushed before its `marf_data` row is committed, just like with a `.blobs` file, so a crash in
+//! between leaves an unreferenced blob behind and nothing else.
+//!
+//! sled (pinned to 0.34.7) is the only pure-Rust LSM store that is mature enough here, although
+//! it has not had a release since 2021 and its on-disk format is not stable across releases.  It
+//! only ever holds immutable trie blobs, `verify-marf-blobs` checks all of
+//! them, and `migrate-marf-storage` moves them back to a `.blobs` file, so a node can leave this
+//! backend at any time.  The backend is opt-in; SQLite remains the default.
+//!
+//! sled only lets a database be opened once per process, so open stores are shared by path.
+//! Likewise, only one process can have a store open at a time, so offline tools can only be used
+//! on a stopped node.
+
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use std::{fs, io};
+
+use crate::chainstate::stacks::index::file::TrieFile;
+use crate::chainstate::stacks::index::storage::MARFStorageBackend;
+use crate::chainstate::stacks::index::{trie_sql, Error};
+use crate::util_lib::db::sqlite_open;
+
+/// Suffix of the directory which holds a MARF's LSM store
+pub const LSM_DIR_SUFFIX: &str = ".lsm";
+
+const TRIE_BLOBS_TREE: &[u8] = b"trie_blobs";
+
+/// Open stores, by path
+static OPEN_STORES: LazyLock<Mutex<HashMap<String, Weak<LsmStoreInner>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct LsmStoreInner {
+    db: sled::Db,
+    trie_blobs: sled::Tree,
+}
+
+/// Handle to a MARF's LSM store.  Cheap to clone; the store is closed once every handle to it
+/// has been dropped.
+#[derive(Clone)]
+pub struct LsmStore {
+    inner: Arc<LsmStoreInner>,
+}
+
+fn sled_err(e: sled::Error) -> Error {
+    Error::IOError(io::Error::from(e))
+}
+
+impl LsmStore {
+    /// Path to the LSM store of the MARF whose SQLite DB is at `db_path`
+    pub fn path(db_path: &str) -> String {
+        format!("{db_path}{LSM_DIR_SUFFIX}")
+    }
+
+    /// Does the MARF whose SQLite DB is at `db_path` have an LSM store?
+    pub fn exists(db_path: &str) -> bool {
+        fs::metadata(Self::path(db_path)).is_ok()
+    }
+
+    /// Open the LSM store of the MARF whose SQLite DB is at `db_path`, creating it if `create` is
+    /// set.  Returns the handle that is already open in this process, if there is one.
+    pub fn open(db_path: &str, create: bool) -> Result<LsmStore, Error> {
+        let path = Self::path(db_path);
+        let mut open_stores = OPEN_STORES
+            .lock()
+            .expect("FATAL: LSM store registry lock is poisoned");
+        if let Some(inner) = open_stores.get(&path).and_then(Weak::upgrade) {
+            return Ok(LsmStore { inner });
+        }
+        if !create && !Self::exists(db_path) {
+            return Err(Error::IOError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No LSM store at {path}"),
+            )));
+        }
+
+        debug!("Open LSM store at {path}");
+        let db = sled::open(&path).map_err(sled_err)?;
+        let trie_blobs = db.open_tree(TRIE_BLOBS_TREE).map_err(sled_err)?;
+        let inner = Arc::new(LsmStoreInner { db, trie_blobs });
+        open_stores.retain(|_, store| store.strong_count() > 0);
+        open_stores.insert(path, Arc::downgrade(&inner));
+        Ok(LsmStore { inner })
+    }
+
+    /// The trie blobs, keyed by their big-endian offsets
+    pub(crate) fn trie_blobs(&self) -> &sled::Tree {
+        &self.inner.trie_blobs
+    }
+
+    /// Make all writes so far durable
+    pub fn flush(&self) -> Result<(), Error> {
+        self.inner.db.flush().map_err(sled_err)?;
+        Ok(())
+    }
+}
+
+/// What `migrate_marf_storage()` moved
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MigrationStats {
+    pub tries: u64,
+    pub trie_bytes: u64,
+}
+
+/// Move the trie blobs of the MARF whose SQLite DB is at `db_path` to the storage backend `to`.  Trie offsets are preserved, so
+/// `marf_data` does not change.  The old copy is deleted once the new one is durable.
+///
+/// The node must be stopped.
+pub fn migrate_marf_storage(
+    db_path: &str,
+    to: MARFStorageBackend,
+) -> Result<MigrationStats, Error> {
+    let from = MARFStorageBackend::detect(db_path);
+    if from == to {
+        return Err(Error::CorruptionError(format!(
+            "MARF at {db_path} already uses the {} storage backend",
+            to.as_str()
+        )));
+    }
+    if !TrieFile::exists_with_backend(db_path, from)? {
+        return Err(Error::NotFoundError);
+    }
+
+    let db = sqlite_open(db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_WRITE, false)?;
+    let mut src = TrieFile::from_db_path_with_backend(db_path, true, from)?;
+    let mut dest = TrieFile::from_db_path_with_backend(db_path, false, to)?;
+    let mut stats = MigrationStats::default();
+
+    let tries = trie_sql::get_external_trie_offsets_lengths(&db)?;
+    for (i, (offset, length)) in tries.iter().enumerate() {
+        if i % 1000 == 0 {
+            info!(
+                "Migrate trie {} of {} to the {} storage backend",
+                i,
+                tries.len(),
+                to.as_str()
+            );
+        }
+        let blob = src.read_blob(*offset, *length)?;
+        dest.write_blob(*offset, &blob)?;
+        stats.tries += 1;
+        stats.trie_bytes += length;
+    }
+
+    // the new copy is durable, so the old one can go
+    drop(src);
+    drop(dest);
+    match from {
+        MARFStorageBackend::Sqlite => fs::remove_file(format!("{db_path}.blobs"))?,
+        MARFStorageBackend::Lsm => {
+            if OPEN_STORES
+                .lock()
+                .expect("FATAL: LSM store registry lock is poisoned")
+                .get(&LsmStore::path(db_path))
+                .is_some_and(|store| store.strong_count() > 0)
+            {
+                return Err(Error::InProgressError);
+            }
+            fs::remove_dir_all(LsmStore::path(db_path))?
+        }
+    }
+
+    info!("Migrated MARF to the {} storage backend", to.as_str();
+          "db_path" => db_path,
+          "tries" => stats.tries,
+          "trie_bytes" => stats.trie_bytes);
+    Ok(stats)
+}
@@ -26,16 +26,19 @@ use stacks_common::util::hash::Sha512Trunc256Sum;
 use stacks_common::util::log;
 
 use crate::chainstate::stacks::index::bits::{get_leaf_hash, get_node_hash, read_root_hash};
+use crate::chainstate::stacks::index::lsm::LsmStore;
 use crate::chainstate::stacks::index::node::{
     clear_backptr, is_backptr, set_backptr, CursorError, TrieCursor, TrieNode, TrieNode16,
     TrieNode256, TrieNode4, TrieNode48, TrieNodeID, TrieNodeType, TriePtr, TRIEPTR_SIZE,
 };
 use crate::chainstate::stacks::index::storage::{
-    TrieFileStorage, TrieHashCalculationMode, TrieStorageConnection, TrieStorageTransaction,
+    MARFStorageBackend, TrieFileStorage, TrieHashCalculationMode, TrieStorageConnection,
+    TrieStorageTransaction,
 };
 use crate::chainstate::stacks::index::trie::Trie;
 use crate::chainstate::stacks::index::{
     ClarityMarfTrieId, Error, MARFValue, MarfTrieId, TrieLeaf, TrieMerkleProof,
+    TrieNonInclusionProof,
 };
 use crate::util_lib::db::Error as db_error;
 
@@ -71,6 +74,8 @@ pub struct MARFOpenOpts {
     pub external_blobs: bool,
     /// unconditionally do a DB migration (used for testing)
     pub force_db_migrate: bool,
+    /// where to store trie blobs, if they are stored externally
+    pub storage_backend: MARFStorageBackend,
 }
 
 impl MARFOpenOpts {
@@ -80,6 +85,7 @@ impl MARFOpenOpts {
             cache_strategy: "noop".to_string(),
             external_blobs: false,
             force_db_migrate: false,
+            storage_backend: MARFStorageBackend::Sqlite,
         }
     }
 
@@ -93,6 +99,7 @@ impl MARFOpenOpts {
             cache_strategy: cache_strategy.to_string(),
             external_blobs,
             force_db_migrate: false,
+            storage_backend: MARFStorageBackend::Sqlite,
         }
     }
 
@@ -122,6 +129,9 @@ pub trait MarfConnection<T: MarfTrieId> {
 
     fn sqlite_conn(&self) -> &Connection;
 
+    /// The LSM store holding the trie blobs, if the MARF uses the LSM storage backend
+    fn lsm_store(&self) -> Option<&LsmStore>;
+
     /// Get and check a value against get_from_hash
     /// (test only)
     #[cfg(test)]
@@ -185,6 +195,30 @@ pub trait MarfConnection<T: MarfTrieId> {
         })
     }
 
+    /// Make a proof that a key has no value with respect to the given block.
+    /// Returns Ok(None) if the key has a value.
+    fn get_non_inclusion_proof(
+        &mut self,
+        block_hash: &T,
+        key: &str,
+    ) -> Result<Option<TrieNonInclusionProof<T>>, Error> {
+        self.get_non_inclusion_proof_from_hash(block_hash, &TrieHash::from_key(key))
+    }
+
+    fn get_non_inclusion_proof_from_hash(
+        &mut self,
+        block_hash: &T,
+        hash: &TrieHash,
+    ) -> Result<Option<TrieNonInclusionProof<T>>, Error> {
+        self.with_conn(
+            |conn| match TrieNonInclusionProof::from_path(conn, hash, block_hash) {
+                Ok(proof) => Ok(Some(proof)),
+                Err(Error::ExistsError) => Ok(None),
+                Err(e) => Err(e),
+            },
+        )
+    }
+
     fn get_block_at_height(&mut self, height: u32, tip: &T) -> Result<Option<T>, Error> {
         self.with_conn(|c| MARF::get_block_at_height(c, height, tip))
     }
@@ -249,6 +283,9 @@ impl<T: MarfTrieId> MarfConnection<T> for MarfTransaction<'_, T> {
     fn sqlite_conn(&self) -> &Connection {
         self.storage.sqlite_tx()
     }
+    fn lsm_store(&self) -> Option<&LsmStore> {
+        self.storage.lsm_store()
+    }
 }
 
 impl<T: MarfTrieId> MarfConnection<T> for MARF<T> {
@@ -262,6 +299,9 @@ impl<T: MarfTrieId> MarfConnection<T> for MARF<T> {
     fn sqlite_conn(&self) -> &Connection {
         self.storage.sqlite_conn()
     }
+    fn lsm_store(&self) -> Option<&LsmStore> {
+        self.storage.lsm_store()
+    }
 }
 
 ///
@@ -750,6 +790,7 @@ impl<T: MarfTrieId> MARF<T> {
     fn root_copy(storage: &mut TrieStorageConnection<T>, prev_block_hash: &T) -> Result<(), Error> {
         let (cur_block_hash, cur_block_id) = storage.get_cur_block_and_id();
         storage.open_block(prev_block_hash)?;
+        storage.check_not_pruned()?;
         let prev_block_identifier = storage.get_cur_block_identifier().unwrap_or_else(|_| {
             panic!(
                 "called open_block on {}, but found no identifier",
@@ -927,6 +968,7 @@ impl<T: MarfTrieId> MARF<T> {
         path: &TrieHash,
     ) -> Result<(TrieCursor<T>, TrieNodeType), Error> {
         storage.open_block(block_hash)?;
+        storage.check_not_pruned()?;
 
         let mut cursor = TrieCursor::new(path, storage.root_trieptr());
 
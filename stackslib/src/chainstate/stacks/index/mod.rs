@@ -31,13 +31,16 @@ use crate::util_lib::db::Error as db_error;
 pub mod bits;
 pub mod cache;
 pub mod file;
+pub mod lsm;
 pub mod marf;
 pub mod node;
 pub mod profile;
 pub mod proofs;
+pub mod prune;
 pub mod storage;
 pub mod trie;
 pub mod trie_sql;
+pub mod verify;
 
 #[cfg(test)]
 pub mod test;
@@ -45,6 +48,24 @@ pub mod test;
 #[derive(Debug)]
 pub struct TrieMerkleProof<T: MarfTrieId>(pub Vec<TrieMerkleProofType<T>>);
 
+/// Merkle proof that a key has no value.
+/// The proof entries are laid out like those of a `TrieMerkleProof`, except that the first
+/// segment proof ends at the parent of `terminal` (and is empty if `terminal` is a trie root).
+#[derive(Debug)]
+pub struct TrieNonInclusionProof<T: MarfTrieId> {
+    pub terminal: TrieNonInclusionTerminal<T>,
+    pub proof: Vec<TrieMerkleProofType<T>>,
+}
+
+/// The node at which a lookup for a key stops, because the key is not in the trie
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrieNonInclusionTerminal<T> {
+    /// A leaf for a different key
+    Leaf(TrieLeaf),
+    /// An intermediate node with no child for the key, and the hashes of all of its children
+    Node((ProofTrieNode<T>, Vec<TrieHash>)),
+}
+
 pub trait ClarityMarfTrieId:
     PartialEq + Clone + std::fmt::Display + std::fmt::Debug + std::convert::From<[u8; 32]>
 {
@@ -258,6 +279,7 @@ pub enum Error {
     CursorError(node::CursorError),
     RestoreMarfBlockError(Box<Error>),
     NonMatchingForks([u8; 32], [u8; 32]),
+    PrunedError,
 }
 
 impl From<io::Error> for Error {
@@ -323,6 +345,7 @@ impl fmt::Display for Error {
             Error::RequestedIdentifierForExtensionTrie => {
                 write!(f, "BUG: MARF requested the identifier for a RAM trie")
             }
+            Error::PrunedError => write!(f, "Trie has been pruned"),
         }
     }
 }
@@ -22,6 +22,7 @@ use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use std::{error, fmt, fs, io};
 
+use libmarfproof::{ProofEntry, ProofLeaf, ProofNode, ProofPtr, TerminalNode};
 use sha2::{Digest, Sha512_256 as TrieHasher};
 use stacks_common::codec::{read_next, Error as codec_error, StacksMessageCodec};
 use stacks_common::types::chainstate::{
@@ -41,7 +42,8 @@ use crate::chainstate::stacks::index::storage::{TrieFileStorage, TrieStorageConn
 use crate::chainstate::stacks::index::trie::Trie;
 use crate::chainstate::stacks::index::{
     BlockMap, ClarityMarfTrieId, Error, MARFValue, MarfTrieId, ProofTrieNode, ProofTriePtr,
-    TrieLeaf, TrieMerkleProof, TrieMerkleProofType,
+    TrieLeaf, TrieMerkleProof, TrieMerkleProofType, TrieNonInclusionProof,
+    TrieNonInclusionTerminal,
 };
 
 impl<T: MarfTrieId> ConsensusSerializable<()> for ProofTrieNode<T> {
@@ -104,6 +106,35 @@ impl<T: MarfTrieId> ProofTrieNode<T> {
             ptrs: ptrs?,
         })
     }
+
+    /// Convert to the proof verifier's representation
+    fn to_proof_node(&self) -> ProofNode {
+        ProofNode {
+            id: self.id,
+            path: self.path.clone(),
+            ptrs: self
+                .ptrs
+                .iter()
+                .map(|ptr| ProofPtr {
+                    id: ptr.id,
+                    chr: ptr.chr,
+                    back_block: ptr.back_block.clone().to_bytes(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Convert a leaf to the proof verifier's representation
+fn leaf_to_proof_leaf(leaf: &TrieLeaf) -> ProofLeaf {
+    ProofLeaf {
+        path: leaf.path.clone(),
+        value: leaf.data.0,
+    }
+}
+
+fn hashes_to_bytes(hashes: &[TrieHash]) -> Vec<[u8; 32]> {
+    hashes.iter().map(|hash| hash.0).collect()
 }
 
 define_u8_enum!( TrieMerkleProofTypeIndicator {
@@ -208,6 +239,42 @@ impl<T: MarfTrieId> fmt::Debug for TrieMerkleProofType<T> {
     }
 }
 
+impl<T: MarfTrieId> TrieMerkleProofType<T> {
+    /// Convert to the proof verifier's representation
+    fn to_proof_entry(&self) -> ProofEntry {
+        match self {
+            TrieMerkleProofType::Node4((chr, node, hashes)) => ProofEntry::Node {
+                chr: *chr,
+                node: node.to_proof_node(),
+                hashes: hashes_to_bytes(hashes),
+            },
+            TrieMerkleProofType::Node16((chr, node, hashes)) => ProofEntry::Node {
+                chr: *chr,
+                node: node.to_proof_node(),
+                hashes: hashes_to_bytes(hashes),
+            },
+            TrieMerkleProofType::Node48((chr, node, hashes)) => ProofEntry::Node {
+                chr: *chr,
+                node: node.to_proof_node(),
+                hashes: hashes_to_bytes(hashes),
+            },
+            TrieMerkleProofType::Node256((chr, node, hashes)) => ProofEntry::Node {
+                chr: *chr,
+                node: node.to_proof_node(),
+                hashes: hashes_to_bytes(hashes),
+            },
+            TrieMerkleProofType::Leaf((chr, leaf)) => ProofEntry::Leaf {
+                chr: *chr,
+                leaf: leaf_to_proof_leaf(leaf),
+            },
+            TrieMerkleProofType::Shunt((idx, hashes)) => ProofEntry::Shunt {
+                idx: *idx,
+                hashes: hashes_to_bytes(hashes),
+            },
+        }
+    }
+}
+
 impl<T: MarfTrieId> Deref for TrieMerkleProof<T> {
     type Target = Vec<TrieMerkleProofType<T>>;
     fn deref(&self) -> &Vec<TrieMerkleProofType<T>> {
@@ -359,6 +426,39 @@ impl<T: MarfTrieId> StacksMessageCodec for TrieMerkleProof<T> {
     }
 }
 
+/// Where a walk down a single trie towards a path stops
+enum TrieWalkEnd<T: MarfTrieId> {
+    /// Reached the path's leaf, or a back-pointer to the rest of the path in an ancestor trie
+    Found(TrieCursor<T>, TrieNodeType, TriePtr),
+    /// The path is not in this trie.  The node is where the walk stopped.
+    Diverged(TrieCursor<T>, TrieNodeType),
+}
+
+/// Find the back-pointers that a proof's segment proofs end at, and return their blocks
+fn get_backptr_blocks<T: MarfTrieId>(proof: &[TrieMerkleProofType<T>]) -> Vec<T> {
+    let mut blocks = vec![];
+    for proof_node in proof.iter() {
+        let (chr, node) = match proof_node {
+            TrieMerkleProofType::Node4((chr, node, _)) => (chr, node),
+            TrieMerkleProofType::Node16((chr, node, _)) => (chr, node),
+            TrieMerkleProofType::Node48((chr, node, _)) => (chr, node),
+            TrieMerkleProofType::Node256((chr, node, _)) => (chr, node),
+            TrieMerkleProofType::Leaf(_) | TrieMerkleProofType::Shunt(_) => {
+                continue;
+            }
+        };
+        let backptr_opt = node
+            .ptrs()
+            .iter()
+            .find(|ptr| ptr.id != TrieNodeID::Empty as u8 && ptr.chr == *chr)
+            .filter(|ptr| is_backptr(ptr.id));
+        if let Some(backptr) = backptr_opt {
+            blocks.push(backptr.back_block.clone());
+        }
+    }
+    blocks
+}
+
 impl<T: MarfTrieId> TrieMerkleProof<T> {
     pub fn to_hex(&self) -> String {
         let mut marf_proof = vec![];
@@ -664,173 +764,6 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         Ok(proof)
     }
 
-    fn next_shunt_hash(hash: &TrieHash, idx: i64, hashes: &[TrieHash]) -> Option<TrieHash> {
-        let mut all_hashes = Vec::with_capacity(hashes.len() + 1);
-        let mut hash_idx = 0;
-        for i in 0..hashes.len() + 1 {
-            if idx == 0 {
-                trace!("Intermediate shunt proof entry must have idx > 0");
-                return None;
-            }
-
-            if idx - 1 == (i as i64) {
-                all_hashes.push(hash.clone());
-            } else {
-                if hash_idx >= hashes.len() {
-                    trace!(
-                        "Invalid proof: hash_idx = {}, hashes.len() = {}",
-                        hash_idx,
-                        hashes.len()
-                    );
-                    return None;
-                }
-                all_hashes.push(hashes[hash_idx].clone());
-                hash_idx += 1;
-            }
-        }
-        trace!("Shunt proof node: idx={}, all_hashes={:?}", idx, all_hashes);
-        let next_hash = TrieHash::from_data_array(&all_hashes);
-        Some(next_hash)
-    }
-
-    /// Verify the head of a shunt proof
-    fn verify_shunt_proof_head(
-        node_root_hash: &TrieHash,
-        shunt_proof_head: &TrieMerkleProofType<T>,
-    ) -> Option<TrieHash> {
-        // ancestor hashes are always the first item
-        let hash = match shunt_proof_head {
-            TrieMerkleProofType::Shunt((ref idx, ref hashes)) => {
-                if *idx != 0 {
-                    trace!("First shunt proof entry must have idx == 0");
-                    return None;
-                }
-
-                if hashes.is_empty() {
-                    // special case -- if this shunt proof has no hashes (i.e. this is a leaf from the first
-                    // block), then we can safely skip this step
-                    trace!(
-                        "Special case for a 0-ancestor node: hash is just the trie hash: {:?}",
-                        node_root_hash
-                    );
-                    node_root_hash.clone()
-                } else {
-                    let mut all_hashes = Vec::with_capacity(hashes.len() + 1);
-                    all_hashes.push(node_root_hash.clone());
-                    for h in hashes {
-                        all_hashes.push(h.clone());
-                    }
-                    let ret = TrieHash::from_data_array(&all_hashes);
-                    trace!(
-                        "Shunt proof head: hash = {:?}, all_hashes = {:?}",
-                        &ret,
-                        &all_hashes
-                    );
-                    ret
-                }
-            }
-            _ => {
-                trace!("Shunt proof head is not a shunt proof node");
-                return None;
-            }
-        };
-
-        Some(hash)
-    }
-
-    /// Verify the tail of a shunt proof, given the backptr root hash.
-    /// Calculate the root hash of the next segment proof.
-    fn verify_shunt_proof_tail(
-        initial_hash: &TrieHash,
-        shunt_proof: &[TrieMerkleProofType<T>],
-    ) -> Option<TrieHash> {
-        let mut hash = initial_hash.clone();
-
-        // walk subsequent legs of a shunt proof, except for the last (since we need the next
-        // segment proof for that)
-        for i in 0..shunt_proof.len() {
-            let proof_node = &shunt_proof[i];
-            hash = match proof_node {
-                TrieMerkleProofType::Shunt((ref idx, ref hashes)) => {
-                    if *idx == 0 {
-                        trace!("Invalid shunt proof tail: idx == 0");
-                        return None;
-                    }
-
-                    match TrieMerkleProof::<T>::next_shunt_hash(&hash, *idx, hashes) {
-                        Some(h) => h,
-                        None => {
-                            return None;
-                        }
-                    }
-                }
-                _ => {
-                    trace!("Shunt proof item is not a shunt proof node");
-                    return None;
-                }
-            };
-        }
-        Some(hash)
-    }
-
-    /// Verify a shunt juncture, where a shunt proof tail and a segment proof meet.
-    /// Returns the hash of the root of the junction
-    fn verify_shunt_proof_junction(
-        node_root_hash: &TrieHash,
-        penultimate_trie_hash: &TrieHash,
-        shunt_proof_junction: &TrieMerkleProofType<T>,
-    ) -> Option<TrieHash> {
-        // at the juncture, we include the node root hash (from the subsequent segment proof) as
-        // the first hash, and include the penultimate trie hash in its idx
-        let hash = match shunt_proof_junction {
-            TrieMerkleProofType::Shunt((ref idx, ref hashes)) => {
-                if *idx == 0 {
-                    trace!("Shunt proof junction entry must not have idx == 0");
-                    return None;
-                }
-
-                let mut all_hashes = Vec::with_capacity(hashes.len() + 1);
-                let mut hash_idx = 0;
-
-                all_hashes.push(node_root_hash.clone());
-
-                for i in 0..hashes.len() + 1 {
-                    if *idx - 1 == (i as i64) {
-                        all_hashes.push(penultimate_trie_hash.clone());
-                    } else {
-                        if hash_idx >= hashes.len() {
-                            trace!(
-                                "ran out of hashes: hash_idx = {}, hashes.len() = {}",
-                                hash_idx,
-                                hashes.len()
-                            );
-                            return None;
-                        }
-
-                        all_hashes.push(hashes[hash_idx].clone());
-                        hash_idx += 1;
-                    }
-                }
-
-                trace!(
-                    "idx = {}, hashes = {:?}, penultimate = {:?}, node root = {:?}",
-                    *idx,
-                    hashes,
-                    penultimate_trie_hash,
-                    node_root_hash
-                );
-                trace!("Shunt proof junction: all_hashes = {:?}", &all_hashes);
-                TrieHash::from_data_array(&all_hashes)
-            }
-            _ => {
-                trace!("Shunt proof junction is not a shunt proof node");
-                return None;
-            }
-        };
-
-        Some(hash)
-    }
-
     /// Given a list of non-backptr ptrs and a root block header hash, calculate a Merkle proof.
     fn make_segment_proof(
         storage: &mut TrieStorageConnection<T>,
@@ -876,237 +809,6 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         Ok(proof_segment)
     }
 
-    /// Given a node in a segment proof, find the hash
-    fn get_segment_proof_hash(
-        node: &ProofTrieNode<T>,
-        hash: &TrieHash,
-        chr: u8,
-        hashes: &[TrieHash],
-        count: usize,
-    ) -> Option<TrieHash> {
-        let mut all_hashes = vec![];
-        let mut ih = 0;
-
-        assert!(node.ptrs().len() == count);
-        assert!(count > 0 && hashes.len() == count - 1);
-
-        for child_ptr in node.ptrs() {
-            if child_ptr.id != TrieNodeID::Empty as u8 && child_ptr.chr == chr {
-                all_hashes.push(hash.clone());
-            } else if ih >= hashes.len() {
-                trace!("verify_get_hash: {} >= {}", ih, hashes.len());
-                return None;
-            } else {
-                all_hashes.push(hashes[ih].clone());
-                ih += 1;
-            }
-        }
-        if all_hashes.len() != count {
-            trace!("verify_get_hash: {} != {}", all_hashes.len(), count);
-            return None;
-        }
-
-        Some(get_node_hash(node, &all_hashes, &mut ()))
-    }
-
-    /// Given a segment proof, the deepest node's hash, and the hash of the trie root, verify that
-    /// the segment proof is well-formed.
-    /// If so, calculate the root hash of the segment and return it.
-    fn verify_segment_proof(
-        proof: &[TrieMerkleProofType<T>],
-        node_hash: &TrieHash,
-    ) -> Option<TrieHash> {
-        let mut hash = node_hash.clone();
-        for i in 0..proof.len() {
-            let hash_opt = match proof[i] {
-                TrieMerkleProofType::Leaf((ref _chr, ref node)) => {
-                    // special case the leaf hash -- it doesn't
-                    //   have any child hashes to check.
-                    Some(get_leaf_hash(node))
-                }
-                TrieMerkleProofType::Node4((ref chr, ref node, ref hashes)) => {
-                    TrieMerkleProof::get_segment_proof_hash(node, &hash, *chr, hashes, 4)
-                }
-                TrieMerkleProofType::Node16((ref chr, ref node, ref hashes)) => {
-                    TrieMerkleProof::get_segment_proof_hash(node, &hash, *chr, hashes, 16)
-                }
-                TrieMerkleProofType::Node48((ref chr, ref node, ref hashes)) => {
-                    TrieMerkleProof::get_segment_proof_hash(node, &hash, *chr, hashes, 48)
-                }
-                TrieMerkleProofType::Node256((ref chr, ref node, ref hashes)) => {
-                    TrieMerkleProof::get_segment_proof_hash(node, &hash, *chr, hashes, 256)
-                }
-                _ => {
-                    trace!("Invalid proof -- encountered a non-node proof type");
-                    return None;
-                }
-            };
-            hash = match hash_opt {
-                None => {
-                    return None;
-                }
-                Some(h) => h,
-            };
-        }
-
-        trace!("verify segment: calculated root hash = {:?}", hash);
-        Some(hash)
-    }
-
-    /// Given a segment proof, extract the path prefix it encodes
-    fn get_segment_proof_path_prefix(segment_proof: &[TrieMerkleProofType<T>]) -> Option<Vec<u8>> {
-        let mut path_parts = vec![];
-        for proof_node in segment_proof {
-            match proof_node {
-                TrieMerkleProofType::Leaf((ref _chr, ref node)) => {
-                    // path_parts.push(vec![*chr]);
-                    path_parts.push(node.path.clone());
-                }
-                TrieMerkleProofType::Node4((ref chr, ref node, _)) => {
-                    path_parts.push(vec![*chr]);
-                    path_parts.push(node.path.clone());
-                }
-                TrieMerkleProofType::Node16((ref chr, ref node, _)) => {
-                    path_parts.push(vec![*chr]);
-                    path_parts.push(node.path.clone());
-                }
-                TrieMerkleProofType::Node48((ref chr, ref node, _)) => {
-                    path_parts.push(vec![*chr]);
-                    path_parts.push(node.path.clone());
-                }
-                TrieMerkleProofType::Node256((ref chr, ref node, _)) => {
-                    path_parts.push(vec![*chr]);
-                    path_parts.push(node.path.clone());
-                }
-                _ => {
-                    trace!("Not a valid segment proof: got a non-node proof node");
-                    return None;
-                }
-            }
-        }
-
-        let mut path = vec![];
-        for i in 0..path_parts.len() {
-            let idx = path_parts.len() - 1 - i;
-            path.extend_from_slice(&path_parts[idx]);
-        }
-        Some(path)
-    }
-
-    /// Verify that a proof is well-formed:
-    /// * it must have the same number of segment and shunt proofs
-    /// * segment proof i+1 must be a prefix of segment proof i
-    /// * segment proof 0 must end in a leaf
-    /// * all segment proofs must end in a Node256 (a root)
-    fn is_proof_well_formed(proof: &[TrieMerkleProofType<T>], expected_path: &TrieHash) -> bool {
-        if proof.is_empty() {
-            trace!("Proof is empty");
-            return false;
-        }
-
-        match proof[0] {
-            TrieMerkleProofType::Leaf(_) => {}
-            _ => {
-                trace!("First proof node is not a leaf");
-                return false;
-            }
-        }
-
-        // must be alternating segment and shunt proofs
-        let mut i = 0;
-        let mut path_bytes = vec![];
-
-        while i < proof.len() {
-            // next segment proof
-            let mut j = i + 1;
-            while j < proof.len() {
-                match proof[j] {
-                    TrieMerkleProofType::Shunt(_) => {
-                        break;
-                    }
-                    _ => {
-                        j += 1;
-                    }
-                }
-            }
-
-            let segment_proof = &proof[i..j];
-
-            if i == 0 {
-                // detect the path
-                path_bytes = match TrieMerkleProof::get_segment_proof_path_prefix(segment_proof) {
-                    Some(bytes) => bytes,
-                    None => {
-                        trace!("Failed to get the path from the proof");
-                        return false;
-                    }
-                };
-
-                // first path bytes must be the expected TrieHash
-                if expected_path.as_bytes().to_vec() != path_bytes {
-                    trace!(
-                        "Invalid proof -- path bytes {:?} differs from the expected path {:?}",
-                        &path_bytes,
-                        expected_path
-                    );
-                    return false;
-                }
-            } else {
-                // make sure that this segment proof is a prefix of the last
-                let new_path_bytes =
-                    match TrieMerkleProof::get_segment_proof_path_prefix(segment_proof) {
-                        Some(bytes) => bytes,
-                        None => {
-                            trace!("Failed to et the path prefix from the proof");
-                            return false;
-                        }
-                    };
-
-                if path_bytes.len() < new_path_bytes.len() {
-                    trace!("Segment proof path is {}, which is longer than the previous segment proof length {}", path_bytes.len(), new_path_bytes.len());
-                    trace!("path_bytes: {:?}", &path_bytes);
-                    trace!("new path bytes: {:?}", &new_path_bytes);
-                    return false;
-                }
-
-                for i in 0..new_path_bytes.len() {
-                    if path_bytes[i] != new_path_bytes[i] {
-                        trace!(
-                            "Segment path {:?} is not a prefix of previous segment path {:?}",
-                            &new_path_bytes,
-                            &path_bytes
-                        );
-                        return false;
-                    }
-                }
-            }
-
-            // next shunt proof
-            i = j;
-            if i >= proof.len() {
-                trace!("Proof is incomplete -- must end with a shunt proof");
-                return false;
-            }
-
-            j = i + 1;
-            while j < proof.len() {
-                match proof[j] {
-                    TrieMerkleProofType::Shunt(_) => {
-                        j += 1;
-                    }
-                    _ => {
-                        break;
-                    }
-                }
-            }
-
-            // end of shunt proof
-            i = j;
-        }
-
-        true
-    }
-
     /// Given a value and the root hash from which this proof was
     /// (supposedly) generated go and verify whether or not it is consistent with the root hash.
     /// For the proof validation to work, the verifier needs to know which Trie roots correspond to
@@ -1120,226 +822,27 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         root_hash: &TrieHash,
         root_to_block: &HashMap<TrieHash, T>,
     ) -> bool {
-        if !TrieMerkleProof::is_proof_well_formed(proof, path) {
-            test_debug!("Invalid proof -- proof is not well-formed");
-            return false;
-        }
-
-        let (mut node_hash, node_data) = match proof[0] {
-            TrieMerkleProofType::Leaf((_, ref node)) => (get_leaf_hash(node), node.data.clone()),
-            _ => unreachable!(),
-        };
-
-        // proof must be for this value
-        if node_data != *value {
-            test_debug!(
-                "Invalid proof -- not for value hash {:?}",
-                value.to_value_hash()
-            );
-            return false;
-        }
-
-        let mut i = 0;
-
-        // verify the very first segment proof
-        let mut j = i + 1;
-        while j < proof.len() {
-            match proof[j] {
-                TrieMerkleProofType::Shunt(_) => {
-                    break;
-                }
-                _ => {
-                    j += 1;
-                }
-            }
-        }
-
-        trace!("verify segment proof in range {}..{}", i, j);
-        let node_root_hash = match TrieMerkleProof::verify_segment_proof(&proof[i..j], &node_hash) {
-            Some(h) => h,
-            None => {
-                test_debug!("Unable to verify segment proof in range {}...{}", i, j);
-                return false;
-            }
-        };
-
-        i = j;
-        if i >= proof.len() {
-            test_debug!(
-                "Proof is too short -- needed at least one shunt proof for the first segment"
-            );
-            return false;
-        }
-
-        // verify the very first shunt proof head.
-        trace!("verify shunt proof head at {}: {:?}", i, &proof[i]);
-        let mut trie_hash =
-            match TrieMerkleProof::verify_shunt_proof_head(&node_root_hash, &proof[i]) {
-                Some(h) => h,
-                None => {
-                    test_debug!(
-                        "Unable to verify shunt proof head at {}: {:?}",
-                        i,
-                        &proof[i]
-                    );
-                    return false;
-                }
-            };
-        trace!("shunt proof head hash: {:?}", &trie_hash);
-
-        i += 1;
-        if i >= proof.len() {
-            // done -- no further shunts
-            test_debug!("Verify proof: {:?} =?= {:?}", root_hash, &trie_hash);
-            return *root_hash == trie_hash;
-        }
-
-        // next node hash is the hash of the block from which its root came
-        node_hash = match root_to_block.get(&trie_hash) {
-            Some(bhh) => {
-                trace!("Block hash for {:?} is {:?}", &trie_hash, bhh);
-
-                // safe because block header hashes are 32 bytes long
-                TrieHash(bhh.clone().to_bytes())
-            }
-            None => {
-                test_debug!("Trie hash not found in root-to-block map: {:?}", &trie_hash);
-                trace!("root-to-block map: {:?}", &root_to_block);
-                return false;
-            }
-        };
-
-        // next proof item should be part of a segment proof
-        if let TrieMerkleProofType::Shunt(_) = proof[i] {
-            test_debug!(
-                "Malformed proof -- exepcted segment proof following first shunt proof head at {}",
-                i
-            );
+        let entries: Vec<_> = proof
+            .iter()
+            .map(TrieMerkleProofType::to_proof_entry)
+            .collect();
+        let res = libmarfproof::verify_inclusion(
+            &entries,
+            &path.0,
+            &value.0,
+            &root_hash.0,
+            |trie_hash| {
+                root_to_block
+                    .get(&TrieHash(*trie_hash))
+                    .map(|block| block.clone().to_bytes())
+            },
+        );
+        if let Err(_e) = res {
+            test_debug!("Invalid proof: {}", &_e);
+            trace!("root-to-block map: {:?}", &root_to_block);
             return false;
         }
-
-        while i < proof.len() {
-            // find the next segment proof
-            j = i + 1;
-            while j < proof.len() {
-                match proof[j] {
-                    TrieMerkleProofType::Shunt(_) => {
-                        break;
-                    }
-                    _ => {
-                        j += 1;
-                    }
-                }
-            }
-
-            trace!("verify segment proof in range {}..{}", i, j);
-            let next_node_root_hash =
-                match TrieMerkleProof::verify_segment_proof(&proof[i..j], &node_hash) {
-                    Some(h) => h,
-                    None => {
-                        test_debug!("Unable to verify segment proof in range {}..{}", i, j);
-                        return false;
-                    }
-                };
-
-            i = j;
-            if i >= proof.len() {
-                test_debug!("Proof to short -- no shunt proof tail");
-                return false;
-            }
-
-            // find the tail end
-            j = i;
-            while j < proof.len() {
-                match proof[j] {
-                    TrieMerkleProofType::Shunt((ref idx, _)) => {
-                        if *idx == 0 {
-                            break;
-                        }
-                        j += 1;
-                    }
-                    _ => {
-                        break;
-                    }
-                }
-            }
-            j -= 1;
-
-            if j < i {
-                test_debug!("Proof is malformed -- no tail or junction proof");
-                return false;
-            }
-
-            trace!(
-                "verify shunt proof tail in range {}..{} initial hash = {:?}: {:?}",
-                i,
-                j,
-                &trie_hash,
-                &proof[i..j]
-            );
-            let penultimate_trie_hash =
-                match TrieMerkleProof::verify_shunt_proof_tail(&trie_hash, &proof[i..j]) {
-                    Some(h) => h,
-                    None => {
-                        test_debug!("Unable to verify shunt proof tail");
-                        return false;
-                    }
-                };
-            trace!(
-                "verify shunt proof tail in range {}..{}: penultimate trie hash is {:?}",
-                i,
-                j,
-                &penultimate_trie_hash
-            );
-
-            i = j;
-            if i >= proof.len() {
-                test_debug!("Proof to short -- no junction proof");
-                return false;
-            }
-
-            trace!("verify shunt junction proof at {} next_node_root_hash = {:?} penultimate hash = {:?}: {:?}", i, &next_node_root_hash, &penultimate_trie_hash, &proof[i]);
-            let next_trie_hash = match TrieMerkleProof::verify_shunt_proof_junction(
-                &next_node_root_hash,
-                &penultimate_trie_hash,
-                &proof[i],
-            ) {
-                Some(h) => h,
-                None => {
-                    test_debug!("Unable to verify shunt junction proof at {} next_node_root_hash = {:?} penultimate hash = {:?}: {:?}", i, &next_node_root_hash, &penultimate_trie_hash, &proof[i]);
-                    return false;
-                }
-            };
-
-            // next node hash is the hash of the block from which its root came
-            trie_hash = next_trie_hash;
-            node_hash = match root_to_block.get(&trie_hash) {
-                Some(bhh) => {
-                    trace!("Block hash for {:?} is {:?}", &trie_hash, bhh);
-
-                    // safe because block header hashes are 32 bytes long
-                    TrieHash(bhh.clone().to_bytes())
-                }
-                None => {
-                    test_debug!("Trie hash not found in root-to-block map: {:?}", &trie_hash);
-                    test_debug!("root-to-block map: {:?}", &root_to_block);
-                    return false;
-                }
-            };
-
-            i += 1;
-
-            if trie_hash == *root_hash {
-                trace!(
-                    "Appeared to find the root hash early, with the remaining proof:\n{:?}",
-                    &proof[i..]
-                );
-                break;
-            }
-        }
-
-        test_debug!("Verify proof: {:?} =?= {:?}", root_hash, &trie_hash);
-        *root_hash == trie_hash
+        true
     }
 
     /// Verify this proof
@@ -1353,11 +856,12 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         TrieMerkleProof::<T>::verify_proof(&self.0, path, marf_value, root_hash, root_to_block)
     }
 
-    /// Walk down the trie pointed to by s until we reach a backptr or a leaf
-    fn walk_to_leaf_or_backptr(
+    /// Walk down the trie pointed to by s until we reach a backptr or a leaf, or until the path
+    /// diverges from the trie.
+    fn walk_path(
         storage: &mut TrieStorageConnection<T>,
         path: &TrieHash,
-    ) -> Result<(TrieCursor<T>, TrieNodeType, TriePtr), Error> {
+    ) -> Result<TrieWalkEnd<T>, Error> {
         trace!(
             "Walk path {:?} from {:?} to the first backptr",
             path,
@@ -1382,7 +886,7 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
                         None => {
                             // end of path.
                             trace!("Found leaf {:?}", &node);
-                            return Ok((cursor, node, node_ptr));
+                            return Ok(TrieWalkEnd::Found(cursor, node, node_ptr));
                         }
                     }
                 }
@@ -1393,12 +897,12 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
                                 CursorError::PathDiverged => {
                                     // we're done -- path diverged.  No backptr-walking can help us.
                                     trace!("Path diverged -- we're done.");
-                                    return Err(Error::NotFoundError);
+                                    return Ok(TrieWalkEnd::Diverged(cursor, node));
                                 }
                                 CursorError::ChrNotFound => {
                                     // node isn't present
                                     trace!("Failed to walk from {:?}", &node);
-                                    return Err(Error::NotFoundError);
+                                    return Ok(TrieWalkEnd::Diverged(cursor, node));
                                 }
                                 CursorError::BackptrEncountered(ptr) => {
                                     // expect backptr
@@ -1411,7 +915,7 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
 
                                     // we're done -- we found a backptr
                                     trace!("Found backptr {:?}", &ptr);
-                                    return Ok((cursor, node, ptr));
+                                    return Ok(TrieWalkEnd::Found(cursor, node, ptr));
                                 }
                             }
                         }
@@ -1428,6 +932,23 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         return Err(Error::CorruptionError("Trie has a cycle".to_string()));
     }
 
+    /// Walk down the trie pointed to by s until we reach a backptr or a leaf
+    fn walk_to_leaf_or_backptr(
+        storage: &mut TrieStorageConnection<T>,
+        path: &TrieHash,
+    ) -> Result<(TrieCursor<T>, TrieNodeType, TriePtr), Error> {
+        match TrieMerkleProof::walk_path(storage, path)? {
+            TrieWalkEnd::Found(cursor, node, ptr) => Ok((cursor, node, ptr)),
+            TrieWalkEnd::Diverged(..) => Err(Error::NotFoundError),
+        }
+    }
+
+    /// Get the IDs of the blocks whose tries this proof reaches through back-pointers.  A verifier
+    /// needs the trie root hashes of these blocks, and of the block the proof was made for.
+    pub fn get_backptr_blocks(&self) -> Vec<T> {
+        get_backptr_blocks(&self.0)
+    }
+
     /// Make a merkle proof of inclusion from a path.
     /// If the path doesn't resolve, return an error (NotFoundError)
     pub fn from_path(
@@ -1442,6 +963,10 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         let mut shunt_proofs = vec![];
         let mut block_header = root_block_header.clone();
 
+        // the tip's trie must be intact, but the ancestor tries it points to need not be
+        storage.open_block(root_block_header)?;
+        storage.check_not_pruned()?;
+
         loop {
             storage.open_block(&block_header)?;
 
@@ -1570,3 +1095,241 @@ impl<T: MarfTrieId> TrieMerkleProof<T> {
         TrieMerkleProof::from_path(storage, &path, value, root_block_header)
     }
 }
+
+define_u8_enum!( TrieNonInclusionTerminalIndicator {
+    Leaf = 0, Node = 1
+});
+
+impl<T: MarfTrieId> StacksMessageCodec for TrieNonInclusionTerminal<T> {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        match self {
+            TrieNonInclusionTerminal::Leaf(leaf) => {
+                (TrieNonInclusionTerminalIndicator::Leaf as u8).consensus_serialize(fd)?;
+                leaf.consensus_serialize(fd)
+            }
+            TrieNonInclusionTerminal::Node((node, hashes)) => {
+                (TrieNonInclusionTerminalIndicator::Node as u8).consensus_serialize(fd)?;
+                node.consensus_serialize(fd)?;
+                hashes.consensus_serialize(fd)
+            }
+        }
+    }
+
+    fn consensus_deserialize<R: Read>(
+        fd: &mut R,
+    ) -> Result<TrieNonInclusionTerminal<T>, codec_error> {
+        let type_byte =
+            TrieNonInclusionTerminalIndicator::from_u8(read_next(fd)?).ok_or_else(|| {
+                codec_error::DeserializeError("Bad type byte in Trie non-inclusion proof".into())
+            })?;
+        let terminal = match type_byte {
+            TrieNonInclusionTerminalIndicator::Leaf => {
+                TrieNonInclusionTerminal::Leaf(read_next(fd)?)
+            }
+            TrieNonInclusionTerminalIndicator::Node => {
+                let node = read_next(fd)?;
+                let hashes = read_next(fd)?;
+                TrieNonInclusionTerminal::Node((node, hashes))
+            }
+        };
+        Ok(terminal)
+    }
+}
+
+impl<T: MarfTrieId> StacksMessageCodec for TrieNonInclusionProof<T> {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        self.terminal.consensus_serialize(fd)?;
+        self.proof.consensus_serialize(fd)
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<TrieNonInclusionProof<T>, codec_error> {
+        let terminal = read_next(fd)?;
+        let proof = read_next(fd)?;
+        Ok(TrieNonInclusionProof { terminal, proof })
+    }
+}
+
+impl<T: MarfTrieId> TrieNonInclusionTerminal<T> {
+    /// Convert to the proof verifier's representation
+    fn to_terminal_node(&self) -> TerminalNode {
+        match self {
+            TrieNonInclusionTerminal::Leaf(leaf) => TerminalNode::Leaf(leaf_to_proof_leaf(leaf)),
+            TrieNonInclusionTerminal::Node((node, hashes)) => TerminalNode::Node {
+                node: node.to_proof_node(),
+                hashes: hashes_to_bytes(hashes),
+            },
+        }
+    }
+}
+
+impl<T: MarfTrieId> TrieNonInclusionProof<T> {
+    pub fn to_hex(&self) -> String {
+        let mut marf_proof = vec![];
+        self.consensus_serialize(&mut marf_proof)
+            .expect("Write error on memory buffer");
+        to_hex(&marf_proof)
+    }
+
+    /// Make the terminal node of a non-inclusion proof from the node at which the walk for a
+    /// path stopped.
+    fn make_terminal(
+        storage: &mut TrieStorageConnection<T>,
+        node: TrieNodeType,
+    ) -> Result<TrieNonInclusionTerminal<T>, Error> {
+        let proof_node = match node {
+            TrieNodeType::Leaf(leaf) => {
+                return Ok(TrieNonInclusionTerminal::Leaf(leaf));
+            }
+            TrieNodeType::Node4(ref data) => ProofTrieNode::try_from_trie_node(data, storage)?,
+            TrieNodeType::Node16(ref data) => ProofTrieNode::try_from_trie_node(data, storage)?,
+            TrieNodeType::Node48(ref data) => {
+                ProofTrieNode::try_from_trie_node(data.as_ref(), storage)?
+            }
+            TrieNodeType::Node256(ref data) => {
+                ProofTrieNode::try_from_trie_node(data.as_ref(), storage)?
+            }
+        };
+        let all_hashes = Trie::get_children_hashes(storage, &node)?;
+        Ok(TrieNonInclusionTerminal::Node((proof_node, all_hashes)))
+    }
+
+    /// Make a merkle proof of non-inclusion from a path.
+    /// If the path resolves to a value, return an error (ExistsError)
+    pub fn from_path(
+        storage: &mut TrieStorageConnection<T>,
+        path: &TrieHash,
+        root_block_header: &T,
+    ) -> Result<TrieNonInclusionProof<T>, Error> {
+        // accumulate proofs in reverse order, like TrieMerkleProof::from_path() does
+        let mut segment_proofs = vec![];
+        let mut shunt_proofs = vec![];
+        let mut block_header = root_block_header.clone();
+
+        // the tip's trie must be intact, but the ancestor tries it points to need not be
+        storage.open_block(root_block_header)?;
+        storage.check_not_pruned()?;
+
+        let terminal = loop {
+            storage.open_block(&block_header)?;
+
+            trace!(
+                "Walk {:?} path {:?} to the end of the path",
+                &storage.get_cur_block(),
+                path
+            );
+            match TrieMerkleProof::walk_path(storage, path)? {
+                TrieWalkEnd::Found(cursor, reached_node, backptr) => {
+                    if !is_backptr(backptr.id()) {
+                        if reached_node.is_leaf() {
+                            trace!("Found leaf at {:?}", path);
+                            return Err(Error::ExistsError);
+                        }
+                        return Err(Error::CorruptionError(format!(
+                            "Path {:?} ends at a non-leaf node",
+                            path
+                        )));
+                    }
+
+                    // the rest of the path is in an ancestor trie
+                    let segment_proof = TrieMerkleProof::make_segment_proof(
+                        storage,
+                        &cursor.node_ptrs,
+                        cursor.chr().unwrap(),
+                    )?;
+                    segment_proofs.push(segment_proof);
+
+                    let shunt_proof = TrieMerkleProof::make_backptr_shunt_proof(storage, &backptr)?;
+                    shunt_proofs.push(shunt_proof);
+
+                    storage.open_block(&block_header)?;
+                    block_header = storage
+                        .get_block_from_local_id(backptr.back_block())?
+                        .clone();
+                }
+                TrieWalkEnd::Diverged(cursor, reached_node) => {
+                    // prove the path to the node at which the walk stopped, unless it's the root
+                    let num_ptrs = cursor.node_ptrs.len();
+                    let segment_proof = if num_ptrs > 1 {
+                        TrieMerkleProof::make_segment_proof(
+                            storage,
+                            &cursor.node_ptrs[..num_ptrs - 1],
+                            cursor.node_ptrs[num_ptrs - 1].chr(),
+                        )?
+                    } else {
+                        vec![]
+                    };
+                    segment_proofs.push(segment_proof);
+
+                    let first_shunt_proof = TrieMerkleProof::make_initial_shunt_proof(storage)?;
+                    shunt_proofs.push(first_shunt_proof);
+
+                    break TrieNonInclusionProof::make_terminal(storage, reached_node)?;
+                }
+            }
+        };
+
+        assert_eq!(shunt_proofs.len(), segment_proofs.len());
+
+        // terminal node's segment proof needs to be first
+        segment_proofs.reverse();
+        shunt_proofs.reverse();
+
+        let mut proof = Vec::with_capacity(segment_proofs.len() + shunt_proofs.len());
+        for i in 0..shunt_proofs.len() {
+            proof.append(&mut segment_proofs[i]);
+            proof.append(&mut shunt_proofs[i]);
+        }
+
+        Ok(TrieNonInclusionProof { terminal, proof })
+    }
+
+    /// Make a merkle proof of non-inclusion from a key.
+    /// If the key resolves to a value, return an error (ExistsError)
+    pub fn from_key(
+        storage: &mut TrieStorageConnection<T>,
+        key: &str,
+        root_block_header: &T,
+    ) -> Result<TrieNonInclusionProof<T>, Error> {
+        let path = TrieHash::from_key(key);
+        TrieNonInclusionProof::from_path(storage, &path, root_block_header)
+    }
+
+    /// Verify that this proof shows that the path has no value in the trie with the given root
+    /// hash.  As with TrieMerkleProof::verify_proof(), the verifier needs to know which trie roots
+    /// correspond to which blocks.
+    pub fn verify(
+        &self,
+        path: &TrieHash,
+        root_hash: &TrieHash,
+        root_to_block: &HashMap<TrieHash, T>,
+    ) -> bool {
+        let entries: Vec<_> = self
+            .proof
+            .iter()
+            .map(TrieMerkleProofType::to_proof_entry)
+            .collect();
+        let res = libmarfproof::verify_non_inclusion(
+            &self.terminal.to_terminal_node(),
+            &entries,
+            &path.0,
+            &root_hash.0,
+            |trie_hash| {
+                root_to_block
+                    .get(&TrieHash(*trie_hash))
+                    .map(|block| block.clone().to_bytes())
+            },
+        );
+        if let Err(_e) = res {
+            test_debug!("Invalid non-inclusion proof: {}", &_e);
+            trace!("root-to-block map: {:?}", &root_to_block);
+            return false;
+        }
+        true
+    }
+
+    /// Get the IDs of the blocks whose tries this proof reaches through back-pointers.  A verifier
+    /// needs the trie root hashes of these blocks, and of the block the proof was made for.
+    pub fn get_backptr_blocks(&self) -> Vec<T> {
+        get_backptr_blocks(&self.proof)
+    }
+}
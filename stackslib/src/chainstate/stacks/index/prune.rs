@@ -0,0 +1,207 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Discarding the parts of old tries which no later trie needs.
+//!
+//! A trie only holds the nodes which its block changed, and back-points into the tries of
+//! earlier blocks for everything else.  So an old trie cannot simply be deleted, but most of its
+//! nodes are usually no longer reachable from any recent trie, because later blocks replaced
+//! them.  `prune_marf_tries()` frees the bytes of those nodes.
+//!
+//! This happens in two steps.  First, the tries are marked as pruned in the `pruned_tries`
+//! table, after which the MARF refuses to look up keys in them or to build new tries on top of
+//! them (`Error::PrunedError`), although it still follows back-pointers into them.  Then every
+//! node which is reachable from a trie that is not pruned is found, and the bytes of the pruned
+//! tries' other nodes are freed.  Nodes are never moved, so every back-pointer stays valid.  A
+//! pruned trie's root node is always kept, since the root hash of a trie commits to the root
+//! hashes of its ancestors.
+//!
+//! Each trie is marked as released once its bytes are freed.  If pruning is interrupted, the
+//! next run finds the marked tries which were not released yet and frees their bytes then.
+
+use std::collections::{HashMap, HashSet};
+
+use stacks_common::types::sqlite::NO_PARAMS;
+
+use crate::chainstate::stacks::index::bits::get_node_byte_len;
+use crate::chainstate::stacks::index::file::TrieFile;
+use crate::chainstate::stacks::index::marf::MARFOpenOpts;
+use crate::chainstate::stacks::index::node::{is_backptr, TrieNodeID, TriePtr};
+use crate::chainstate::stacks::index::storage::{
+    MARFStorageBackend, TrieFileStorage, TrieHashCalculationMode, TrieStorageConnection,
+};
+use crate::chainstate::stacks::index::{trie_sql, Error, MarfTrieId};
+
+/// What `prune_marf_tries()` did
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarfPruneStats {
+    /// Number of tries whose unneeded nodes were discarded
+    pub num_tries: u64,
+    /// Number of bytes freed
+    pub bytes_released: u64,
+}
+
+/// Prune the tries of `block_hashes` in the MARF at `db_path`: discard each of their nodes
+/// which no trie outside of the pruned ones can reach.  Afterwards, the MARF can no longer be
+/// read at these blocks, nor extended from them.  Blocks without a trie are skipped, as are
+/// MARFs which keep their tries in SQLite.
+///
+/// The caller must make sure that no block will be built on top of these blocks, because its
+/// trie could back-point into the nodes being discarded.
+pub fn prune_marf_tries<T: MarfTrieId>(
+    db_path: &str,
+    block_hashes: &[T],
+) -> Result<MarfPruneStats, Error> {
+    let backend = MARFStorageBackend::detect(db_path);
+    if !TrieFile::exists_with_backend(db_path, backend)? {
+        return Ok(MarfPruneStats::default());
+    }
+    let mut marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    marf_opts.storage_backend = backend;
+    let mut storage = TrieFileStorage::<T>::open(db_path, marf_opts)?;
+
+    let tries = {
+        let sql = "SELECT block_id,block_hash,external_offset,external_length FROM marf_data WHERE unconfirmed = 0 ORDER BY block_id";
+        let mut stmt = storage.sqlite_conn().prepare(sql)?;
+        let rows = stmt.query_map(NO_PARAMS, |row| {
+            let block_id: u32 = row.get(0)?;
+            let block_hash: T = row.get(1)?;
+            let offset: i64 = row.get(2)?;
+            let length: i64 = row.get(3)?;
+            Ok((block_id, block_hash, offset as u64, length as u64))
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    // mark the tries as pruned before discarding anything, so nothing reads from them meanwhile
+    trie_sql::create_pruned_tries_table_if_needed(storage.sqlite_conn())?;
+    let mut pruned = trie_sql::get_pruned_tries(storage.sqlite_conn())?;
+    let block_hashes: HashSet<&T> = block_hashes.iter().collect();
+    let to_release: HashSet<u32> = tries
+        .iter()
+        .filter(|(block_id, block_hash, ..)| match pruned.get(block_id) {
+            Some(released) => !released,
+            None => block_hashes.contains(block_hash),
+        })
+        .map(|(block_id, ..)| *block_id)
+        .collect();
+    if to_release.is_empty() {
+        return Ok(MarfPruneStats::default());
+    }
+    let tx = storage.sqlite_tx()?;
+    trie_sql::set_tries_pruned(&tx, &to_release.iter().copied().collect::<Vec<_>>())?;
+    tx.commit()?;
+    for block_id in to_release.iter() {
+        pruned.entry(*block_id).or_insert(false);
+    }
+
+    // find the nodes of the tries being pruned which are still reachable
+    let block_hashes_by_id: HashMap<u32, T> = tries
+        .iter()
+        .map(|(block_id, block_hash, ..)| (*block_id, block_hash.clone()))
+        .collect();
+    let root_ptr = TriePtr::new(
+        TrieNodeID::Node256 as u8,
+        0,
+        TrieStorageConnection::<T>::root_ptr_disk(),
+    );
+    let mut live_ranges: HashMap<u32, Vec<(u64, u64)>> = HashMap::new();
+    let mut visited = HashSet::new();
+    let mut conn = storage.connection();
+    for (block_id, ..) in tries.iter() {
+        if to_release.contains(block_id) {
+            // keep the root node, and the parent block hash and block ID before it
+            let block_hash = &block_hashes_by_id[block_id];
+            conn.open_block_known_id(block_hash, *block_id)?;
+            let root = conn.read_nodetype_nohash(&root_ptr)?;
+            let root_end = u64::from(root_ptr.ptr()) + get_node_byte_len(&root) as u64;
+            live_ranges
+                .entry(*block_id)
+                .or_default()
+                .push((0, root_end));
+        }
+        if pruned.contains_key(block_id) {
+            continue;
+        }
+
+        let mut frontier = vec![(*block_id, root_ptr)];
+        while let Some((node_block_id, ptr)) = frontier.pop() {
+            let block_hash = block_hashes_by_id.get(&node_block_id).ok_or_else(|| {
+                Error::CorruptionError(format!("Back-pointer to unknown trie {node_block_id}"))
+            })?;
+            conn.open_block_known_id(block_hash, node_block_id)?;
+            let node = conn.read_nodetype_nohash(&ptr)?;
+            if to_release.contains(&node_block_id) {
+                live_ranges
+                    .entry(node_block_id)
+                    .or_default()
+                    .push((u64::from(ptr.ptr()), get_node_byte_len(&node) as u64));
+            }
+            for child_ptr in node.ptrs().iter() {
+                if child_ptr.id() == TrieNodeID::Empty as u8 {
+                    continue;
+                }
+                let (child_block_id, child_ptr) = if is_backptr(child_ptr.id()) {
+                    (child_ptr.back_block(), child_ptr.from_backptr())
+                } else {
+                    (node_block_id, *child_ptr)
+                };
+                if !pruned.contains_key(&child_block_id) {
+                    // a trie which is not pruned is walked from its own root
+                    if child_block_id != node_block_id {
+                        continue;
+                    }
+                } else if !visited.insert((child_block_id, child_ptr.ptr())) {
+                    // a pruned trie's node can be reached from many tries
+                    continue;
+                }
+                frontier.push((child_block_id, child_ptr));
+            }
+        }
+    }
+    drop(conn);
+    drop(visited);
+
+    // discard everything else
+    let mut blobs = TrieFile::from_db_path_with_backend(db_path, false, backend)?;
+    let mut stats = MarfPruneStats::default();
+    for (block_id, block_hash, offset, length) in tries.iter() {
+        let Some(mut ranges) = live_ranges.remove(block_id) else {
+            continue;
+        };
+        ranges.sort_unstable();
+        let mut keep: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+        for (start, len) in ranges.into_iter() {
+            match keep.last_mut() {
+                Some((last_start, last_len)) if *last_start + *last_len >= start => {
+                    *last_len = (*last_len).max(start + len - *last_start);
+                }
+                _ => keep.push((start, len)),
+            }
+        }
+        stats.bytes_released += blobs.retain_blob_ranges(*offset, *length, &keep)?;
+        trie_sql::set_trie_released(storage.sqlite_conn(), *block_id)?;
+        stats.num_tries += 1;
+        trace!("Pruned MARF trie";
+               "block_hash" => %block_hash,
+               "block_id" => block_id);
+    }
+
+    info!("Pruned MARF";
+          "db_path" => db_path,
+          "num_tries" => stats.num_tries,
+          "bytes_released" => stats.bytes_released);
+    Ok(stats)
+}
@@ -21,6 +21,7 @@ use std::io::{BufWriter, Cursor, Read, Seek, SeekFrom, Write};
 use std::marker::PhantomData;
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::SystemTime;
 use std::{cmp, env, error, fmt, fs, io, os};
 
@@ -38,11 +39,12 @@ use stacks_common::util::hash::to_hex;
 use stacks_common::util::log;
 
 use crate::chainstate::stacks::index::bits::{
-    get_node_byte_len, get_node_hash, read_block_identifier, read_hash_bytes, read_node_hash_bytes,
-    read_nodetype, read_root_hash, write_nodetype_bytes,
+    get_leaf_hash, get_node_byte_len, get_node_hash, read_block_identifier, read_hash_bytes,
+    read_node_hash_bytes, read_nodetype, read_root_hash, write_nodetype_bytes,
 };
 use crate::chainstate::stacks::index::cache::*;
 use crate::chainstate::stacks::index::file::{TrieFile, TrieFileNodeHashReader};
+use crate::chainstate::stacks::index::lsm::LsmStore;
 use crate::chainstate::stacks::index::marf::MARFOpenOpts;
 use crate::chainstate::stacks::index::node::{
     clear_backptr, is_backptr, set_backptr, TrieNode, TrieNode16, TrieNode256, TrieNode4,
@@ -1192,6 +1194,47 @@ pub enum TrieHashCalculationMode {
     All,
 }
 
+/// Where a MARF with external blobs keeps its trie blobs.  The `marf_data` index, and the Clarity
+/// MARF's side-store values, are in SQLite either way.
+#[derive(Debug, Clone, PartialEq, Copy, Default)]
+pub enum MARFStorageBackend {
+    /// Trie blobs in a flat `.blobs` file
+    #[default]
+    Sqlite,
+    /// Trie blobs in an embedded LSM store in the `.lsm` directory
+    Lsm,
+}
+
+impl MARFStorageBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MARFStorageBackend::Sqlite => "sqlite",
+            MARFStorageBackend::Lsm => "lsm",
+        }
+    }
+
+    /// Which backend the MARF whose SQLite DB is at `db_path` was created with
+    pub fn detect(db_path: &str) -> MARFStorageBackend {
+        if LsmStore::exists(db_path) {
+            MARFStorageBackend::Lsm
+        } else {
+            MARFStorageBackend::Sqlite
+        }
+    }
+}
+
+impl FromStr for MARFStorageBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sqlite" => Ok(MARFStorageBackend::Sqlite),
+            "lsm" => Ok(MARFStorageBackend::Lsm),
+            _ => Err(format!("Unknown MARF storage backend '{s}'")),
+        }
+    }
+}
+
 ///
 ///  TrieStorageConnection is a pointer to an open TrieFileStorage,
 ///    with either a SQLite &Connection (non-mut, so it cannot start a TX)
@@ -1255,6 +1298,9 @@ pub struct TrieStorageTransientData<T: MarfTrieId> {
     /// query more than once.
     trie_ancestor_hash_bytes_cache: Option<(T, Vec<TrieHash>)>,
 
+    /// The last block ID which `check_not_pruned()` found was not pruned
+    unpruned_block_id: Option<u32>,
+
     /// Is the trie opened read-only?
     readonly: bool,
 
@@ -1352,6 +1398,11 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         &self.db
     }
 
+    /// The LSM store holding the trie blobs, if the MARF uses the LSM storage backend
+    pub fn lsm_store(&self) -> Option<&LsmStore> {
+        self.blobs.as_ref().and_then(|blobs| blobs.lsm_store())
+    }
+
     pub fn sqlite_tx(&mut self) -> Result<Transaction<'_>, db_error> {
         tx_begin_immediate(&mut self.db)
     }
@@ -1408,7 +1459,28 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
         }
 
         let mut blobs = if marf_opts.external_blobs {
-            Some(TrieFile::from_db_path(&db_path, readonly)?)
+            let backend = marf_opts.storage_backend;
+            let other = match backend {
+                MARFStorageBackend::Sqlite => MARFStorageBackend::Lsm,
+                MARFStorageBackend::Lsm => MARFStorageBackend::Sqlite,
+            };
+            if !create_flag
+                && !TrieFile::exists_with_backend(&db_path, backend)?
+                && TrieFile::exists_with_backend(&db_path, other)?
+            {
+                // don't silently start a fresh blob store next to the existing one
+                return Err(Error::CorruptionError(format!(
+                    "MARF at {} uses the {} storage backend, but {} was configured; run `stacks-inspect migrate-marf-storage {} {}` first",
+                    &db_path,
+                    other.as_str(),
+                    backend.as_str(),
+                    &db_path,
+                    backend.as_str()
+                )));
+            }
+            Some(TrieFile::from_db_path_with_backend(
+                &db_path, readonly, backend,
+            )?)
         } else {
             None
         };
@@ -1457,6 +1529,7 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
                 write_leaf_count: 0,
 
                 trie_ancestor_hash_bytes_cache: None,
+                unpruned_block_id: None,
 
                 readonly,
                 unconfirmed,
@@ -1514,8 +1587,12 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
     pub fn reopen_readonly(&self) -> Result<TrieFileStorage<T>, Error> {
         let db = marf_sqlite_open(&self.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)?;
         let cache = TrieCache::default();
-        let blobs = if self.blobs.is_some() {
-            Some(TrieFile::from_db_path(&self.db_path, true)?)
+        let blobs = if let Some(blobs) = self.blobs.as_ref() {
+            Some(TrieFile::from_db_path_with_backend(
+                &self.db_path,
+                true,
+                blobs.backend(),
+            )?)
         } else {
             None
         };
@@ -1546,6 +1623,7 @@ impl<T: MarfTrieId> TrieFileStorage<T> {
                 write_leaf_count: 0,
 
                 trie_ancestor_hash_bytes_cache: None,
+                unpruned_block_id: None,
 
                 readonly: true,
                 unconfirmed: self.unconfirmed(),
@@ -1578,8 +1656,12 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
     ///  _does not_ preserve the cur_block/open tip
     pub fn reopen_readonly(&self) -> Result<TrieFileStorage<T>, Error> {
         let db = marf_sqlite_open(&self.db_path, OpenFlags::SQLITE_OPEN_READ_ONLY, false)?;
-        let blobs = if self.blobs.is_some() {
-            Some(TrieFile::from_db_path(self.db_path, true)?)
+        let blobs = if let Some(blobs) = self.blobs.as_ref() {
+            Some(TrieFile::from_db_path_with_backend(
+                self.db_path,
+                true,
+                blobs.backend(),
+            )?)
         } else {
             None
         };
@@ -1615,6 +1697,7 @@ impl<'a, T: MarfTrieId> TrieStorageTransaction<'a, T> {
                 write_leaf_count: 0,
 
                 trie_ancestor_hash_bytes_cache: None,
+                unpruned_block_id: None,
 
                 readonly: true,
                 unconfirmed: self.unconfirmed(),
@@ -1949,6 +2032,11 @@ impl<T: MarfTrieId> TrieStorageConnection<'_, T> {
         self.data.readonly
     }
 
+    /// The LSM store holding the trie blobs, if the MARF uses the LSM storage backend
+    pub fn lsm_store(&self) -> Option<&LsmStore> {
+        self.blobs.as_deref().and_then(|blobs| blobs.lsm_store())
+    }
+
     pub fn unconfirmed(&self) -> bool {
         self.data.unconfirmed
     }
@@ -2224,6 +2312,25 @@ impl<T: MarfTrieId> TrieStorageConnection<'_, T> {
         Ok(())
     }
 
+    /// Fail with `PrunedError` if the currently-open block's trie has been pruned, so that
+    /// only its root node can be relied upon.  Reading any other node of it could yield zeros
+    /// instead of an error.  Tries are only pruned once they are far behind the chain tip, so a
+    /// trie which was found not to be pruned is not checked again the next time it is opened.
+    pub fn check_not_pruned(&mut self) -> Result<(), Error> {
+        let Some(block_id) = self.data.cur_block_id else {
+            // a trie which is still being built
+            return Ok(());
+        };
+        if self.data.unpruned_block_id == Some(block_id) {
+            return Ok(());
+        }
+        if trie_sql::is_trie_pruned(&self.db, block_id)? {
+            return Err(Error::PrunedError);
+        }
+        self.data.unpruned_block_id = Some(block_id);
+        Ok(())
+    }
+
     /// Return the block_identifier / row_id for a given bhh. If that bhh
     ///  is currently being extended, return None, since the row_id won't
     ///  be known until the extended trie is flushed.
@@ -2444,6 +2551,82 @@ impl<T: MarfTrieId> TrieStorageConnection<'_, T> {
         Ok(())
     }
 
+    /// Re-derive the hash of every node in the committed trie for `bhh` from what is in the
+    /// `.blobs` file, and check it against the hash stored with the node.  The root node's
+    /// hash also commits to the root hashes of ancestor tries, which are taken as stored.
+    ///
+    /// Works on read-only storage.  Returns the trie's root hash on success, and a
+    /// `CorruptionError` describing the first inconsistent node otherwise.
+    pub fn verify_trie_blob(&mut self, bhh: &T) -> Result<TrieHash, Error> {
+        if self.blobs.is_none() {
+            return Err(Error::CorruptionError(
+                "MARF does not store its tries in a .blobs file".to_string(),
+            ));
+        }
+        self.open_block(bhh)?;
+        let block_id = self.get_cur_block_identifier()?;
+        let root_ptr = self.root_ptr();
+        let (root, root_hash) = Trie::read_root(self)?;
+
+        let mut frontier = vec![(root_ptr, root, root_hash)];
+        while let Some((ptr, node, stored_hash)) = frontier.pop() {
+            for child_ptr in node.ptrs().iter() {
+                if child_ptr.id() == TrieNodeID::Empty as u8 || is_backptr(child_ptr.id()) {
+                    continue;
+                }
+                let (child, child_hash) = self.read_nodetype(child_ptr)?;
+                frontier.push((child_ptr.ptr(), child, child_hash));
+            }
+
+            let mut hash = if let TrieNodeType::Leaf(ref leaf) = node {
+                get_leaf_hash(leaf)
+            } else {
+                self.recompute_node_hash(block_id, &node)?
+            };
+            if ptr == root_ptr {
+                hash = Trie::get_trie_root_hash(self, &hash)?;
+            }
+            if hash != stored_hash {
+                return Err(Error::CorruptionError(format!(
+                    "Node at offset {ptr} of trie {bhh} (block ID {block_id}) has hash {stored_hash}, but its contents hash to {hash}"
+                )));
+            }
+        }
+        Ok(root_hash)
+    }
+
+    /// Hash an intermediate node of the committed trie with the given block ID from its
+    /// consensus bytes and its children's stored hashes.
+    fn recompute_node_hash(
+        &mut self,
+        block_id: u32,
+        node: &TrieNodeType,
+    ) -> Result<TrieHash, Error> {
+        // resolve back-pointers up front, so a dangling one is an error instead of a panic
+        for child_ptr in node.ptrs().iter() {
+            if is_backptr(child_ptr.id()) {
+                self.get_block_hash_caching(child_ptr.back_block())?;
+            }
+        }
+        let mut hasher = TrieHasher::new();
+        node.write_consensus_bytes(self, &mut hasher)?;
+        let blobs = self.blobs.as_mut().ok_or(Error::NotFoundError)?;
+        let mut map = TrieSqlHashMapCursor {
+            db: &self.db,
+            cache: self.cache,
+            unconfirmed: self.data.unconfirmed,
+        };
+        let mut cursor = TrieFileNodeHashReader::new(&self.db, blobs, block_id);
+        TrieStorageConnection::<T>::inner_write_children_hashes(
+            &mut cursor,
+            &mut map,
+            node,
+            &mut hasher,
+            self.bench,
+        )?;
+        Ok(TrieHash(hasher.finalize().into()))
+    }
+
     /// read a persisted node's hash
     fn inner_read_persisted_node_hash(
         &mut self,
@@ -0,0 +1,175 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fs;
+
+use clarity::vm::database::SqliteConnection;
+
+use super::*;
+use crate::chainstate::stacks::index::cache::test::make_test_insert_data;
+use crate::chainstate::stacks::index::lsm::*;
+use crate::chainstate::stacks::index::marf::*;
+use crate::chainstate::stacks::index::storage::*;
+use crate::chainstate::stacks::index::verify::*;
+use crate::chainstate::stacks::index::*;
+
+fn lsm_test_opts(backend: MARFStorageBackend) -> MARFOpenOpts {
+    let mut marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    marf_opts.storage_backend = backend;
+    marf_opts
+}
+
+/// Append the blocks in `data` to the MARF, starting after `parent`, and return their root hashes
+fn append_test_blocks(
+    marf: &mut MARF<BlockHeaderHash>,
+    parent: &BlockHeaderHash,
+    first_height: u64,
+    data: &[Vec<(String, MARFValue)>],
+) -> Vec<(BlockHeaderHash, TrieHash)> {
+    let mut roots = vec![];
+    let mut last_block_header = parent.clone();
+    for (i, block_data) in data.iter().enumerate() {
+        let mut block_hash_bytes = [0u8; 32];
+        block_hash_bytes[0..8].copy_from_slice(&(first_height + i as u64).to_be_bytes());
+        let block_header = BlockHeaderHash(block_hash_bytes);
+
+        marf.begin(&last_block_header, &block_header).unwrap();
+        for (key, value) in block_data.iter() {
+            let path = TrieHash::from_key(key);
+            let leaf = TrieLeaf::from_value(&[], value.clone());
+            marf.insert_raw(path, leaf).unwrap();
+        }
+        marf.commit().unwrap();
+        roots.push((
+            block_header.clone(),
+            marf.get_root_hash_at(&block_header).unwrap(),
+        ));
+        last_block_header = block_header;
+    }
+    roots
+}
+
+fn check_test_blocks(
+    marf: &mut MARF<BlockHeaderHash>,
+    data: &[Vec<(String, MARFValue)>],
+    roots: &[(BlockHeaderHash, TrieHash)],
+) {
+    let (tip, _) = roots.last().unwrap();
+    for block_data in data.iter() {
+        for (key, value) in block_data.iter() {
+            assert_eq!(marf.get(tip, key).unwrap().as_ref(), Some(value));
+        }
+    }
+    for (block_header, root_hash) in roots.iter() {
+        assert_eq!(&marf.get_root_hash_at(block_header).unwrap(), root_hash);
+    }
+}
+
+#[test]
+fn test_lsm_storage_backend_and_migration() {
+    let test_file = "/tmp/test_lsm_storage_backend_and_migration.sqlite";
+    let test_blobs_file = "/tmp/test_lsm_storage_backend_and_migration.sqlite.blobs";
+    for path in [test_file, test_blobs_file] {
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path).unwrap();
+        }
+    }
+    if LsmStore::exists(test_file) {
+        fs::remove_dir_all(LsmStore::path(test_file)).unwrap();
+    }
+
+    let data = make_test_insert_data(16, 8);
+    let (side_key, side_value) = ("test-side-key", "test-side-value");
+
+    // start out with the SQLite backend and a Clarity side-store
+    let mut roots = {
+        let f =
+            TrieFileStorage::open(test_file, lsm_test_opts(MARFStorageBackend::Sqlite)).unwrap();
+        let mut marf = MARF::from_storage(f);
+        {
+            let tx = marf.storage_tx().unwrap();
+            SqliteConnection::initialize_conn(&tx).unwrap();
+            SqliteConnection::put(&tx, side_key, side_value).unwrap();
+            tx.commit().unwrap();
+        }
+        append_test_blocks(&mut marf, &BlockHeaderHash::sentinel(), 0, &data[0..4])
+    };
+
+    let stats = migrate_marf_storage(test_file, MARFStorageBackend::Lsm).unwrap();
+    assert_eq!(stats.tries, 4);
+    assert!(fs::metadata(test_blobs_file).is_err());
+    assert_eq!(
+        MARFStorageBackend::detect(test_file),
+        MARFStorageBackend::Lsm
+    );
+    assert!(migrate_marf_storage(test_file, MARFStorageBackend::Lsm).is_err());
+
+    // the configured backend must match the one on disk
+    assert!(TrieFileStorage::<BlockHeaderHash>::open(
+        test_file,
+        lsm_test_opts(MARFStorageBackend::Sqlite)
+    )
+    .is_err());
+
+    {
+        let f = TrieFileStorage::open(test_file, lsm_test_opts(MARFStorageBackend::Lsm)).unwrap();
+        let mut marf = MARF::from_storage(f);
+        check_test_blocks(&mut marf, &data[0..4], &roots);
+
+        // the side-store stays in SQLite, so it commits together with each block's trie
+        assert_eq!(
+            SqliteConnection::get(marf.sqlite_conn(), side_key)
+                .unwrap()
+                .as_deref(),
+            Some(side_value)
+        );
+
+        let parent = roots.last().unwrap().0.clone();
+        roots.extend(append_test_blocks(&mut marf, &parent, 4, &data[4..]));
+        check_test_blocks(&mut marf, &data, &roots);
+
+        let mut ro_marf = marf.reopen_readonly().unwrap();
+        assert!(ro_marf.lsm_store().is_some());
+        check_test_blocks(&mut ro_marf, &data, &roots);
+    }
+
+    let root_map: HashMap<_, _> = roots.iter().cloned().collect();
+    let report = verify_marf_blobs(test_file, |block_hash: &BlockHeaderHash, root_hash| {
+        (root_map.get(block_hash) != Some(root_hash)).then(|| "wrong root".to_string())
+    })
+    .unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.num_tries, data.len() as u64);
+
+    // and back again
+    let stats = migrate_marf_storage(test_file, MARFStorageBackend::Sqlite).unwrap();
+    assert_eq!(stats.tries, data.len() as u64);
+    assert!(!LsmStore::exists(test_file));
+    assert_eq!(
+        fs::metadata(test_blobs_file).unwrap().len(),
+        report.blobs_len
+    );
+
+    let f = TrieFileStorage::open(test_file, lsm_test_opts(MARFStorageBackend::Sqlite)).unwrap();
+    let mut marf = MARF::from_storage(f);
+    check_test_blocks(&mut marf, &data, &roots);
+    assert_eq!(
+        SqliteConnection::get(marf.sqlite_conn(), side_key)
+            .unwrap()
+            .as_deref(),
+        Some(side_value)
+    );
+}
@@ -36,11 +36,14 @@ use crate::chainstate::stacks::{BlockHeaderHash, TrieHash};
 
 pub mod cache;
 pub mod file;
+pub mod lsm;
 pub mod marf;
 pub mod node;
 pub mod proofs;
+pub mod prune;
 pub mod storage;
 pub mod trie;
+pub mod verify;
 
 /// Print out a trie to stderr
 pub fn dump_trie<T>(s: &mut TrieStorageConnection<T>)
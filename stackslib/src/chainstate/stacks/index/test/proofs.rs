@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use stacks_common::codec::StacksMessageCodec;
+
 use super::*;
 use crate::chainstate::stacks::index::marf::*;
 use crate::chainstate::stacks::index::test::*;
@@ -197,3 +199,99 @@ fn ncc_verifier_catches_stale_proof() {
     println!("DEBUG: verify(old_v)");
     assert!(!proof_5.verify(&triepath_4, &marf_value_4, &root_hash_5, &root_to_block));
 }
+
+#[test]
+fn non_inclusion_proofs() {
+    let marf_opts = MARFOpenOpts::default();
+    let mut m = MARF::from_path(":memory:", marf_opts).unwrap();
+
+    let sentinel_block = BlockHeaderHash::sentinel();
+    let mut parent = BlockHeaderHash([0u8; 32]);
+    m.begin(&sentinel_block, &parent).unwrap();
+    m.commit().unwrap();
+
+    // spread keys across several blocks, so proofs have to follow back-pointers
+    let mut blocks = vec![];
+    for i in 1..9u8 {
+        let block = BlockHeaderHash([i; 32]);
+        m.begin(&parent, &block).unwrap();
+        for j in 0..16 {
+            let key = format!("key-{}-{}", i, j);
+            m.insert(&key, MARFValue::from_value(&key)).unwrap();
+        }
+        m.commit().unwrap();
+        blocks.push(block.clone());
+        parent = block;
+    }
+
+    let tip = blocks.last().unwrap().clone();
+    let root_hash = m.get_root_hash_at(&tip).unwrap();
+    let root_to_block = m
+        .borrow_storage_backend()
+        .read_root_to_block_table()
+        .unwrap();
+
+    let mut num_backptr_proofs = 0;
+    for j in 0..64 {
+        let key = format!("absent-{}", j);
+        let path = TrieHash::from_key(&key);
+        let proof = m.get_non_inclusion_proof(&tip, &key).unwrap().unwrap();
+        assert!(proof.verify(&path, &root_hash, &root_to_block));
+
+        // the proof only needs the trie roots of the tip and the blocks it reaches through
+        // back-pointers
+        let backptr_blocks = proof.get_backptr_blocks();
+        if !backptr_blocks.is_empty() {
+            num_backptr_proofs += 1;
+            assert!(!proof.verify(&path, &root_hash, &HashMap::new()));
+        }
+        let mut visited_roots = HashMap::new();
+        for block in backptr_blocks.iter().chain([&tip]) {
+            visited_roots.insert(m.get_root_hash_at(block).unwrap(), block.clone());
+        }
+        assert!(proof.verify(&path, &root_hash, &visited_roots));
+
+        // round-trips through the wire format
+        let bytes = proof.serialize_to_vec();
+        let decoded =
+            TrieNonInclusionProof::<BlockHeaderHash>::consensus_deserialize(&mut &bytes[..])
+                .unwrap();
+        assert_eq!(decoded.terminal, proof.terminal);
+        assert!(decoded.verify(&path, &root_hash, &root_to_block));
+
+        // not valid for a different root
+        assert!(!proof.verify(&path, &TrieHash([0x11; 32]), &root_to_block));
+    }
+    assert!(num_backptr_proofs > 0);
+
+    // keys with values have no non-inclusion proof, and proofs for absent keys don't show that
+    // they have no value
+    let absent_proof = m
+        .get_non_inclusion_proof(&tip, "absent-0")
+        .unwrap()
+        .unwrap();
+    for i in 1..9 {
+        for j in 0..16 {
+            let key = format!("key-{}-{}", i, j);
+            assert!(m.get_non_inclusion_proof(&tip, &key).unwrap().is_none());
+            assert!(!absent_proof.verify(&TrieHash::from_key(&key), &root_hash, &root_to_block));
+        }
+    }
+
+    // keys inserted after a block have no value as of that block
+    let block_1 = blocks[0].clone();
+    let root_hash_1 = m.get_root_hash_at(&block_1).unwrap();
+    let key = "key-8-0";
+    let proof = m.get_non_inclusion_proof(&block_1, key).unwrap().unwrap();
+    assert!(proof.verify(&TrieHash::from_key(key), &root_hash_1, &root_to_block));
+    assert!(!proof.verify(&TrieHash::from_key(key), &root_hash, &root_to_block));
+
+    // inclusion proofs also report the blocks they reach through back-pointers
+    let key = "key-1-0";
+    let (value, proof) = m.get_with_proof(&tip, key).unwrap().unwrap();
+    let mut visited_roots = HashMap::new();
+    for block in proof.get_backptr_blocks().iter().chain([&tip]) {
+        visited_roots.insert(m.get_root_hash_at(block).unwrap(), block.clone());
+    }
+    assert!(proof.verify(&TrieHash::from_key(key), &value, &root_hash, &visited_roots));
+}
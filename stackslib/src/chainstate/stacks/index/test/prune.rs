@@ -0,0 +1,141 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fs;
+
+use super::*;
+use crate::chainstate::stacks::index::lsm::LsmStore;
+use crate::chainstate::stacks::index::marf::*;
+use crate::chainstate::stacks::index::prune::*;
+use crate::chainstate::stacks::index::storage::*;
+use crate::chainstate::stacks::index::verify::*;
+use crate::chainstate::stacks::index::*;
+
+fn test_prune_marf_tries_with_backend(test_file: &str, backend: MARFStorageBackend) {
+    for path in [test_file.to_string(), format!("{test_file}.blobs")] {
+        if fs::metadata(&path).is_ok() {
+            fs::remove_file(&path).unwrap();
+        }
+    }
+    if fs::metadata(LsmStore::path(test_file)).is_ok() {
+        fs::remove_dir_all(LsmStore::path(test_file)).unwrap();
+    }
+    let mut marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    marf_opts.storage_backend = backend;
+
+    // every block writes keys of its own, and overwrites keys which every block writes
+    let num_blocks = 16;
+    let mut block_hashes = vec![];
+    let mut roots = HashMap::new();
+    let mut marf = MARF::from_storage(TrieFileStorage::open(test_file, marf_opts.clone()).unwrap());
+    let mut last_block_header = BlockHeaderHash::sentinel();
+    for i in 0..num_blocks {
+        let mut block_hash_bytes = [0u8; 32];
+        block_hash_bytes[0..8].copy_from_slice(&(i as u64).to_be_bytes());
+        let block_header = BlockHeaderHash(block_hash_bytes);
+
+        marf.begin(&last_block_header, &block_header).unwrap();
+        for j in 0..32 {
+            marf.insert(
+                &format!("own-{i}-{j}"),
+                MARFValue::from_value(&format!("{i}")),
+            )
+            .unwrap();
+            marf.insert(
+                &format!("shared-{j}"),
+                MARFValue::from_value(&format!("{i}")),
+            )
+            .unwrap();
+        }
+        marf.commit().unwrap();
+        roots.insert(
+            block_header.clone(),
+            marf.get_root_hash_at(&block_header).unwrap(),
+        );
+        block_hashes.push(block_header.clone());
+        last_block_header = block_header;
+    }
+    drop(marf);
+
+    let stats = prune_marf_tries(test_file, &block_hashes[..8]).unwrap();
+    assert_eq!(stats.num_tries, 8);
+    assert!(stats.bytes_released > 0);
+
+    // pruning them again does nothing
+    let stats = prune_marf_tries(test_file, &block_hashes[..8]).unwrap();
+    assert_eq!(stats, MarfPruneStats::default());
+
+    let mut marf = MARF::from_storage(TrieFileStorage::open(test_file, marf_opts).unwrap());
+    let tip = block_hashes.last().unwrap().clone();
+    for i in 0..num_blocks {
+        for j in 0..32 {
+            assert_eq!(
+                marf.get(&tip, &format!("own-{i}-{j}")).unwrap(),
+                Some(MARFValue::from_value(&format!("{i}")))
+            );
+        }
+    }
+    for j in 0..32 {
+        assert_eq!(
+            marf.get(&tip, &format!("shared-{j}")).unwrap(),
+            Some(MARFValue::from_value(&format!("{}", num_blocks - 1)))
+        );
+    }
+    // the oldest trie which was not pruned is intact
+    assert_eq!(
+        marf.get(&block_hashes[8], "shared-0").unwrap(),
+        Some(MARFValue::from_value("8"))
+    );
+
+    // pruned tries cannot be read from or built upon, but their root hashes are kept
+    for block_hash in block_hashes[..8].iter() {
+        assert!(matches!(
+            marf.get(block_hash, "shared-0"),
+            Err(Error::PrunedError)
+        ));
+        assert_eq!(
+            &marf.get_root_hash_at(block_hash).unwrap(),
+            roots.get(block_hash).unwrap()
+        );
+    }
+    let mut fork_hash_bytes = [0xffu8; 32];
+    fork_hash_bytes[0] = 0;
+    let fork_header = BlockHeaderHash(fork_hash_bytes);
+    let res = marf
+        .begin(&block_hashes[3], &fork_header)
+        .and_then(|_| marf.insert("own-fork", MARFValue::from_value("fork")));
+    assert!(matches!(res, Err(Error::PrunedError)));
+    drop(marf);
+
+    let check_root = |block_hash: &BlockHeaderHash, root_hash: &TrieHash| {
+        (roots.get(block_hash) != Some(root_hash)).then(|| "wrong root".to_string())
+    };
+    let report = verify_marf_blobs(test_file, check_root).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.num_tries, num_blocks as u64);
+}
+
+#[test]
+fn test_prune_marf_tries() {
+    test_prune_marf_tries_with_backend(
+        "/tmp/test_prune_marf_tries.sqlite",
+        MARFStorageBackend::Sqlite,
+    );
+    test_prune_marf_tries_with_backend(
+        "/tmp/test_prune_marf_tries_lsm.sqlite",
+        MARFStorageBackend::Lsm,
+    );
+}
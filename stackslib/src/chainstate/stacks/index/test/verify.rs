@@ -0,0 +1,132 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+
+use super::*;
+use crate::chainstate::stacks::index::cache::test::make_test_insert_data;
+use crate::chainstate::stacks::index::marf::*;
+use crate::chainstate::stacks::index::storage::*;
+use crate::chainstate::stacks::index::verify::*;
+use crate::chainstate::stacks::index::*;
+
+#[test]
+fn test_verify_and_truncate_marf_blobs() {
+    let test_file = "/tmp/test_verify_and_truncate_marf_blobs.sqlite";
+    let test_blobs_file = "/tmp/test_verify_and_truncate_marf_blobs.sqlite.blobs";
+    for path in [test_file, test_blobs_file] {
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    let data = make_test_insert_data(16, 8);
+    let mut roots = HashMap::new();
+    let mut last_block_header = BlockHeaderHash::sentinel();
+    {
+        let marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+        let f = TrieFileStorage::open(test_file, marf_opts).unwrap();
+        let mut marf = MARF::from_storage(f);
+        for (i, block_data) in data.iter().enumerate() {
+            let mut block_hash_bytes = [0u8; 32];
+            block_hash_bytes[0..8].copy_from_slice(&(i as u64).to_be_bytes());
+            let block_header = BlockHeaderHash(block_hash_bytes);
+
+            marf.begin(&last_block_header, &block_header).unwrap();
+            for (key, value) in block_data.iter() {
+                let path = TrieHash::from_key(key);
+                let leaf = TrieLeaf::from_value(&[], value.clone());
+                marf.insert_raw(path, leaf).unwrap();
+            }
+            marf.commit().unwrap();
+            roots.insert(
+                block_header.clone(),
+                marf.get_root_hash_at(&block_header).unwrap(),
+            );
+            last_block_header = block_header;
+        }
+    }
+    let check_root = |block_hash: &BlockHeaderHash, root_hash: &TrieHash| {
+        (roots.get(block_hash) != Some(root_hash)).then(|| "wrong root".to_string())
+    };
+
+    let report = verify_marf_blobs(test_file, check_root).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.num_tries, data.len() as u64);
+    let blobs_len = report.blobs_len;
+
+    // a crash after appending a trie, but before committing its row, leaves trailing bytes
+    let mut blobs = fs::OpenOptions::new()
+        .append(true)
+        .open(test_blobs_file)
+        .unwrap();
+    blobs.write_all(&[0xff; 100]).unwrap();
+    drop(blobs);
+
+    let report = verify_marf_blobs(test_file, check_root).unwrap();
+    assert!(!report.is_ok());
+    assert!(report.bad_tries.is_empty());
+    assert_eq!(report.tail_offset, blobs_len);
+    assert_eq!(report.blobs_len, blobs_len + 100);
+
+    // corrupt a leaf value in the last trie
+    let (last_offset, last_length) = {
+        let storage = TrieFileStorage::<BlockHeaderHash>::open_readonly(
+            test_file,
+            MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true),
+        )
+        .unwrap();
+        let last_id =
+            trie_sql::get_block_identifier(storage.sqlite_conn(), &last_block_header).unwrap();
+        trie_sql::get_external_trie_offset_length(storage.sqlite_conn(), last_id).unwrap()
+    };
+    let mut bytes = fs::read(test_blobs_file).unwrap();
+    let value = data.last().unwrap()[0].1 .0;
+    let value_pos = bytes[last_offset as usize..(last_offset + last_length) as usize]
+        .windows(value.len())
+        .position(|window| window == value)
+        .unwrap();
+    bytes[last_offset as usize + value_pos] ^= 0x01;
+    fs::write(test_blobs_file, &bytes).unwrap();
+
+    let report = verify_marf_blobs(test_file, check_root).unwrap();
+    assert!(report.is_repairable());
+    assert_eq!(report.bad_tries.len(), 1);
+    assert_eq!(report.bad_tries[0].block_hash, last_block_header);
+    assert_eq!(report.tail_offset, last_offset);
+
+    assert_eq!(truncate_marf_blobs(test_file, &report).unwrap(), 1);
+    let report = verify_marf_blobs(test_file, check_root).unwrap();
+    assert!(report.is_ok());
+    assert_eq!(report.num_tries, data.len() as u64 - 1);
+    assert_eq!(report.blobs_len, last_offset);
+
+    // corruption in the middle cannot be truncated away
+    let mut bytes = fs::read(test_blobs_file).unwrap();
+    let value = data[0][0].1 .0;
+    let value_pos = bytes
+        .windows(value.len())
+        .position(|window| window == value)
+        .unwrap();
+    bytes[value_pos] ^= 0x01;
+    fs::write(test_blobs_file, &bytes).unwrap();
+
+    let report = verify_marf_blobs(test_file, check_root).unwrap();
+    assert!(!report.is_repairable());
+    assert_eq!(report.bad_tries.len(), 1);
+    assert!(truncate_marf_blobs(test_file, &report).is_err());
+}
@@ -50,7 +50,7 @@ use crate::chainstate::stacks::index::node::{
 use crate::chainstate::stacks::index::storage::{TrieFileStorage, TrieStorageConnection};
 use crate::chainstate::stacks::index::{trie_sql, BlockMap, Error, MarfTrieId, TrieLeaf};
 use crate::util_lib::db::{
-    query_count, query_row, query_rows, sql_pragma, tx_begin_immediate, u64_to_sql,
+    query_count, query_row, query_rows, sql_pragma, table_exists, tx_begin_immediate, u64_to_sql,
 };
 
 static SQL_MARF_DATA_TABLE: &str = "
@@ -97,6 +97,15 @@ INSERT OR REPLACE INTO schema_version (version) VALUES (2);
 INSERT OR REPLACE INTO migrated_version (version) VALUES (1);
 ";
 
+static SQL_PRUNED_TRIES_TABLE: &str = "
+-- tries whose nodes which no later trie needs have been discarded.  Only their root nodes are
+-- guaranteed to still be readable.  `released` is set once the nodes' bytes have been freed.
+CREATE TABLE IF NOT EXISTS pruned_tries (
+    block_id INTEGER PRIMARY KEY,
+    released INTEGER NOT NULL
+);
+";
+
 pub static SQL_MARF_SCHEMA_VERSION: u64 = 2;
 
 pub fn create_tables_if_needed(conn: &mut Connection) -> Result<(), Error> {
@@ -105,10 +114,17 @@ pub fn create_tables_if_needed(conn: &mut Connection) -> Result<(), Error> {
     tx.execute_batch(SQL_MARF_DATA_TABLE)?;
     tx.execute_batch(SQL_MARF_MINED_TABLE)?;
     tx.execute_batch(SQL_EXTENSION_LOCKS_TABLE)?;
+    tx.execute_batch(SQL_PRUNED_TRIES_TABLE)?;
 
     tx.commit().map_err(|e| e.into())
 }
 
+/// Create the table of pruned tries, if this MARF predates it
+pub fn create_pruned_tries_table_if_needed(conn: &Connection) -> Result<(), Error> {
+    conn.execute_batch(SQL_PRUNED_TRIES_TABLE)?;
+    Ok(())
+}
+
 fn get_schema_version(conn: &Connection) -> u64 {
     // if the table doesn't exist, then the version is 1.
     let sql = "SELECT version FROM schema_version";
@@ -531,6 +547,13 @@ pub fn get_external_trie_offset_length_by_bhh<T: MarfTrieId>(
     Ok((offset, length))
 }
 
+/// Get the offsets and lengths of all trie blobs in the trie blobs file, in the order they appear.
+pub fn get_external_trie_offsets_lengths(conn: &Connection) -> Result<Vec<(u64, u64)>, Error> {
+    let qry = "SELECT external_offset, external_length FROM marf_data WHERE external_length > 0 ORDER BY external_offset";
+    let rows = query_rows(conn, qry, NO_PARAMS)?;
+    Ok(rows)
+}
+
 /// Determine the offset in the blobs file at which the last trie ends.  This is also the offset at
 /// which the next trie will be appended.
 pub fn get_external_blobs_length(conn: &Connection) -> Result<u64, Error> {
@@ -670,6 +693,54 @@ pub fn is_unconfirmed_block(conn: &Connection, block_id: u32) -> Result<bool, Er
     Ok(res != 0)
 }
 
+/// Has the trie with the given block ID been pruned?
+pub fn is_trie_pruned(conn: &Connection, block_id: u32) -> Result<bool, Error> {
+    if !table_exists(conn, "pruned_tries")? {
+        return Ok(false);
+    }
+    let count = query_count(
+        conn,
+        "SELECT COUNT(*) FROM pruned_tries WHERE block_id = ?1",
+        params![block_id],
+    )?;
+    Ok(count > 0)
+}
+
+/// Get the block IDs of all pruned tries, and whether or not their nodes' bytes have been freed
+pub fn get_pruned_tries(conn: &Connection) -> Result<HashMap<u32, bool>, Error> {
+    if !table_exists(conn, "pruned_tries")? {
+        return Ok(HashMap::new());
+    }
+    let mut stmt = conn.prepare("SELECT block_id, released FROM pruned_tries")?;
+    let rows = stmt.query_map(NO_PARAMS, |row| {
+        let block_id: u32 = row.get(0)?;
+        let released: bool = row.get(1)?;
+        Ok((block_id, released))
+    })?;
+    let pruned = rows.collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(pruned)
+}
+
+/// Mark the tries with the given block IDs as pruned, so they can no longer be read from
+pub fn set_tries_pruned(conn: &Connection, block_ids: &[u32]) -> Result<(), Error> {
+    for block_id in block_ids.iter() {
+        conn.execute(
+            "INSERT OR IGNORE INTO pruned_tries (block_id, released) VALUES (?1, 0)",
+            params![block_id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Record that the bytes of a pruned trie's discarded nodes have been freed
+pub fn set_trie_released(conn: &Connection, block_id: u32) -> Result<(), Error> {
+    conn.execute(
+        "UPDATE pruned_tries SET released = 1 WHERE block_id = ?1",
+        params![block_id],
+    )?;
+    Ok(())
+}
+
 pub fn drop_lock<T: MarfTrieId>(conn: &Connection, bhh: &T) -> Result<(), Error> {
     conn.execute(
         "DELETE FROM block_extension_locks WHERE block_hash = ?",
@@ -697,5 +768,8 @@ pub fn clear_tables(tx: &Transaction) -> Result<(), Error> {
     tx.execute("DELETE FROM block_extension_locks", NO_PARAMS)?;
     tx.execute("DELETE FROM marf_data", NO_PARAMS)?;
     tx.execute("DELETE FROM mined_blocks", NO_PARAMS)?;
+    if table_exists(tx, "pruned_tries")? {
+        tx.execute("DELETE FROM pruned_tries", NO_PARAMS)?;
+    }
     Ok(())
 }
@@ -0,0 +1,294 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Offline integrity checking for MARFs which store their tries in a `.blobs` file (or, with the
+//! `Lsm` storage backend, in an LSM store laid out the same way).
+//!
+//! A trie is appended to the `.blobs` file before its `marf_data` row is committed, so a node
+//! which crashes mid-commit can leave behind trailing bytes which no trie refers to, or (if the
+//! file system reorders writes) a committed row whose blob was never fully written.  The
+//! verifier walks every confirmed trie, re-derives each node's hash from the blob, and checks
+//! each trie's root hash against an external source of truth such as the block headers.
+//! Inconsistencies at the end of the file can then be truncated away, so the node can re-process
+//! the affected blocks (see `chainstate::stacks::db::repair`, which also makes the node forget
+//! that it processed them).
+
+use rusqlite::{params, OpenFlags};
+use stacks_common::types::chainstate::TrieHash;
+use stacks_common::types::sqlite::NO_PARAMS;
+
+use crate::chainstate::stacks::index::file::TrieFile;
+use crate::chainstate::stacks::index::marf::MARFOpenOpts;
+use crate::chainstate::stacks::index::storage::{
+    MARFStorageBackend, TrieFileStorage, TrieHashCalculationMode,
+};
+use crate::chainstate::stacks::index::trie::Trie;
+use crate::chainstate::stacks::index::{trie_sql, Error, MarfTrieId};
+use crate::util_lib::db::{sqlite_open, tx_begin_immediate};
+
+/// A trie which failed verification
+#[derive(Debug, Clone, PartialEq)]
+pub struct BadTrie<T> {
+    /// The trie's row ID in `marf_data`
+    pub block_id: u32,
+    pub block_hash: T,
+    /// Where the trie's blob is in the `.blobs` file
+    pub offset: u64,
+    pub length: u64,
+    /// What is wrong with it
+    pub reason: String,
+}
+
+/// The outcome of `verify_marf_blobs()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarfVerifyReport<T> {
+    /// Number of confirmed tries checked
+    pub num_tries: u64,
+    pub bad_tries: Vec<BadTrie<T>>,
+    /// Length of the `.blobs` file
+    pub blobs_len: u64,
+    /// Where the inconsistent tail of the `.blobs` file starts: everything from here on belongs
+    /// either to a bad trie or to no trie at all.  Equal to `blobs_len` if there is no such tail.
+    pub tail_offset: u64,
+}
+
+impl<T> MarfVerifyReport<T> {
+    /// Did every trie check out, with no trailing bytes?
+    pub fn is_ok(&self) -> bool {
+        self.bad_tries.is_empty() && self.tail_offset == self.blobs_len
+    }
+
+    /// Bad tries in the inconsistent tail, which `truncate_marf_blobs()` would remove
+    pub fn tail_tries(&self) -> impl Iterator<Item = &BadTrie<T>> {
+        self.bad_tries
+            .iter()
+            .filter(move |bad| bad.offset >= self.tail_offset)
+    }
+
+    /// Can `truncate_marf_blobs()` make this MARF consistent again?  It cannot if a bad trie is
+    /// followed by a good one.
+    pub fn is_repairable(&self) -> bool {
+        self.bad_tries
+            .iter()
+            .all(|bad| bad.offset >= self.tail_offset)
+    }
+}
+
+/// Verify every confirmed trie in the MARF at `db_path`, whose tries are stored in
+/// `<db_path>.blobs`.  Each trie's node hashes are recomputed from its blob, and its root hash is
+/// passed to `check_root`, which returns why the root hash is wrong, if it is (e.g. because it
+/// does not match the state root in the corresponding block header).  Only the root nodes of
+/// pruned tries are checked, since their other nodes may have been discarded.
+///
+/// Only opens the MARF read-only, so the node must be stopped for the results to be meaningful.
+pub fn verify_marf_blobs<T, F>(
+    db_path: &str,
+    mut check_root: F,
+) -> Result<MarfVerifyReport<T>, Error>
+where
+    T: MarfTrieId,
+    F: FnMut(&T, &TrieHash) -> Option<String>,
+{
+    let backend = MARFStorageBackend::detect(db_path);
+    let blobs_len = TrieFile::from_db_path_with_backend(db_path, true, backend)
+        .and_then(|mut blobs| blobs.len())
+        .inspect_err(|e| error!("Failed to open MARF blobs"; "db_path" => db_path, "err" => ?e))?;
+
+    let mut marf_opts = MARFOpenOpts::new(TrieHashCalculationMode::Deferred, "noop", true);
+    marf_opts.storage_backend = backend;
+    let mut storage = TrieFileStorage::<T>::open_readonly(db_path, marf_opts)?;
+
+    let tries = {
+        let sql = "SELECT block_id,block_hash,external_offset,external_length FROM marf_data WHERE unconfirmed = 0 ORDER BY block_id";
+        let mut stmt = storage.sqlite_conn().prepare(sql)?;
+        let rows = stmt.query_map(NO_PARAMS, |row| {
+            let block_id: u32 = row.get(0)?;
+            let block_hash: T = row.get(1)?;
+            let offset: i64 = row.get(2)?;
+            let length: i64 = row.get(3)?;
+            Ok((block_id, block_hash, offset, length))
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    let pruned = trie_sql::get_pruned_tries(storage.sqlite_conn())?;
+    let num_tries = tries.len();
+    let mut bad_tries = vec![];
+    let mut good_end = 0;
+    let mut conn = storage.connection();
+    for (i, (block_id, block_hash, offset, length)) in tries.into_iter().enumerate() {
+        if i > 0 && i % 10_000 == 0 {
+            info!("Verified {i} of {num_tries} MARF tries");
+        }
+        let (Ok(offset), Ok(length)) = (u64::try_from(offset), u64::try_from(length)) else {
+            bad_tries.push(BadTrie {
+                block_id,
+                block_hash,
+                offset: 0,
+                length: 0,
+                reason: format!("invalid blob offset {offset} and length {length}"),
+            });
+            continue;
+        };
+        let reason = if offset.saturating_add(length) > blobs_len {
+            Some(format!(
+                "blob at offset {offset} with length {length} extends past the end of the .blobs file"
+            ))
+        } else {
+            let root_hash = if pruned.contains_key(&block_id) {
+                // only its root node is left to check
+                conn.open_block(&block_hash)
+                    .and_then(|_| Trie::read_root(&mut conn))
+                    .map(|(_, root_hash)| root_hash)
+            } else {
+                conn.verify_trie_blob(&block_hash)
+            };
+            match root_hash {
+                Ok(root_hash) => check_root(&block_hash, &root_hash),
+                Err(e) => Some(e.to_string()),
+            }
+        };
+        match reason {
+            Some(reason) => {
+                warn!("Inconsistent MARF trie";
+                      "block_id" => block_id,
+                      "block_hash" => %block_hash,
+                      "reason" => &reason);
+                bad_tries.push(BadTrie {
+                    block_id,
+                    block_hash,
+                    offset,
+                    length,
+                    reason,
+                });
+            }
+            None => good_end = good_end.max(offset + length),
+        }
+    }
+
+    Ok(MarfVerifyReport {
+        num_tries: num_tries as u64,
+        bad_tries,
+        blobs_len,
+        tail_offset: good_end,
+    })
+}
+
+/// Remove the inconsistent tail found by `verify_marf_blobs()`: delete the `marf_data` rows of the
+/// bad tries in the tail, and truncate the `.blobs` file to `report.tail_offset`.  The blocks
+/// whose tries were removed must be re-processed; `repair_chainstate_marfs()` arranges that for
+/// the chainstate MARFs.
+///
+/// Returns the number of tries removed.
+pub fn truncate_marf_blobs<T: MarfTrieId>(
+    db_path: &str,
+    report: &MarfVerifyReport<T>,
+) -> Result<u64, Error> {
+    if !report.is_repairable() {
+        return Err(Error::CorruptionError(
+            "MARF has inconsistent tries before its last good trie, so it cannot be truncated"
+                .to_string(),
+        ));
+    }
+    let mut db = sqlite_open(db_path, OpenFlags::SQLITE_OPEN_READ_WRITE, false)?;
+    let tx = tx_begin_immediate(&mut db)?;
+    let mut num_removed = 0;
+    for bad in report.tail_tries() {
+        tx.execute(
+            "DELETE FROM marf_data WHERE block_id = ?1",
+            params![bad.block_id],
+        )?;
+        num_removed += 1;
+    }
+
+    // no remaining trie may refer to the part of the file about to go away
+    let blobs_end: i64 = tx.query_row(
+        "SELECT IFNULL(MAX(external_offset + external_length), 0) FROM marf_data WHERE unconfirmed = 0",
+        NO_PARAMS,
+        |row| row.get(0),
+    )?;
+    if u64::try_from(blobs_end).map_or(true, |end| end > report.tail_offset) {
+        return Err(Error::CorruptionError(format!(
+            "MARF has tries which end at offset {blobs_end}, past the truncation offset {}; it has changed since it was verified",
+            report.tail_offset
+        )));
+    }
+    tx.commit()?;
+
+    let mut blobs =
+        TrieFile::from_db_path_with_backend(db_path, false, MARFStorageBackend::detect(db_path))?;
+    blobs.truncate(report.tail_offset)?;
+
+    info!("Truncated MARF";
+          "db_path" => db_path,
+          "tries_removed" => num_removed,
+          "blobs_len" => report.tail_offset);
+    Ok(num_removed)
+}
+
+/// Remove the tries of `block_hashes` from the MARF at `db_path`, and truncate its `.blobs` file
+/// to where the first of them starts.  They must be the MARF's last confirmed tries; blocks
+/// without a trie are skipped.  This keeps a MARF consistent with another MARF of the same
+/// blocks, from which `truncate_marf_blobs()` removed these blocks' tries.
+///
+/// Returns the number of tries removed.
+pub fn truncate_marf_tries<T: MarfTrieId>(db_path: &str, block_hashes: &[T]) -> Result<u64, Error> {
+    let mut db = sqlite_open(db_path, OpenFlags::SQLITE_OPEN_READ_WRITE, false)?;
+    let tx = tx_begin_immediate(&mut db)?;
+    let tries = {
+        let sql = "SELECT block_id,block_hash,external_offset FROM marf_data WHERE unconfirmed = 0 ORDER BY block_id";
+        let mut stmt = tx.prepare(sql)?;
+        let rows = stmt.query_map(NO_PARAMS, |row| {
+            let block_id: u32 = row.get(0)?;
+            let block_hash: T = row.get(1)?;
+            let offset: i64 = row.get(2)?;
+            Ok((block_id, block_hash, offset))
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+    let Some(first) = tries
+        .iter()
+        .position(|(_, block_hash, _)| block_hashes.contains(block_hash))
+    else {
+        return Ok(0);
+    };
+    if let Some((_, block_hash, _)) = tries[first..]
+        .iter()
+        .find(|(_, block_hash, _)| !block_hashes.contains(block_hash))
+    {
+        return Err(Error::CorruptionError(format!(
+            "MARF has a trie for {block_hash} after the tries to remove, so it cannot be truncated"
+        )));
+    }
+    let (first_block_id, _, offset) = &tries[first];
+    let offset = u64::try_from(*offset)
+        .map_err(|_| Error::CorruptionError(format!("invalid blob offset {offset}")))?;
+    tx.execute(
+        "DELETE FROM marf_data WHERE unconfirmed = 0 AND block_id >= ?1",
+        params![first_block_id],
+    )?;
+    tx.commit()?;
+
+    let mut blobs =
+        TrieFile::from_db_path_with_backend(db_path, false, MARFStorageBackend::detect(db_path))?;
+    blobs.truncate(offset)?;
+
+    let num_removed = (tries.len() - first) as u64;
+    info!("Truncated MARF";
+          "db_path" => db_path,
+          "tries_removed" => num_removed,
+          "blobs_len" => offset);
+    Ok(num_removed)
+}
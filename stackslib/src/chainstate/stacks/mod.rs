@@ -100,6 +100,8 @@ pub enum Error {
     StacksTransactionSkipped(String),
     PostConditionFailed(String),
     NoSuchBlockError,
+    /// The block is known, but this is a pruned node and its data is gone
+    PrunedBlockError,
     /// The supplied Sortition IDs, consensus hashes, or stacks blocks are not in the same fork.
     NotInSameFork,
     InvalidChainstateDB,
@@ -166,6 +168,7 @@ impl fmt::Display for Error {
             Error::InvalidStacksTransaction(ref s, _) => fmt::Display::fmt(s, f),
             Error::PostConditionFailed(ref s) => fmt::Display::fmt(s, f),
             Error::NoSuchBlockError => write!(f, "No such Stacks block"),
+            Error::PrunedBlockError => write!(f, "Stacks block data has been pruned"),
             Error::InvalidChainstateDB => write!(f, "Invalid chainstate database"),
             Error::BlockTooBigError => write!(f, "Too much data in block"),
             Error::TransactionTooBigError(ref c) => {
@@ -246,6 +249,7 @@ impl error::Error for Error {
             Error::InvalidStacksTransaction(ref _s, _q) => None,
             Error::PostConditionFailed(ref _s) => None,
             Error::NoSuchBlockError => None,
+            Error::PrunedBlockError => None,
             Error::InvalidChainstateDB => None,
             Error::BlockTooBigError => None,
             Error::TransactionTooBigError(..) => None,
@@ -291,6 +295,7 @@ impl Error {
             Error::InvalidStacksTransaction(ref _s, _q) => "InvalidStacksTransaction",
             Error::PostConditionFailed(ref _s) => "PostConditionFailed",
             Error::NoSuchBlockError => "NoSuchBlockError",
+            Error::PrunedBlockError => "PrunedBlockError",
             Error::InvalidChainstateDB => "InvalidChainstateDB",
             Error::BlockTooBigError => "BlockTooBigError",
             Error::TransactionTooBigError(..) => "TransactionTooBigError",
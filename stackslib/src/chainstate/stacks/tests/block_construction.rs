@@ -58,7 +58,7 @@ use crate::chainstate::stacks::tests::*;
 use crate::chainstate::stacks::{
     Error as ChainstateError, C32_ADDRESS_VERSION_TESTNET_SINGLESIG, *,
 };
-use crate::core::mempool::MemPoolWalkSettings;
+use crate::core::mempool::{MemPoolImportStats, MemPoolWalkSettings};
 use crate::core::tests::make_block;
 use crate::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, *};
 use crate::cost_estimates::metrics::UnitMetric;
@@ -1631,6 +1631,165 @@ fn test_build_anchored_blocks_mempool_fee_transaction_too_low() {
     assert_eq!(stacks_block.txs.len(), 1);
 }
 
+#[test]
+fn test_mempool_export_import() {
+    let privk = StacksPrivateKey::from_hex(
+        "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+    )
+    .unwrap();
+    let addr = StacksAddress::from_public_keys(
+        C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        &AddressHashMode::SerializeP2PKH,
+        1,
+        &vec![StacksPublicKey::from_private(&privk)],
+    )
+    .unwrap();
+
+    let mut peer_config = TestPeerConfig::new(function_name!(), 2036, 2037);
+    peer_config.initial_balances = vec![(addr.to_account_principal(), 1000000000)];
+    let burnchain = peer_config.burnchain.clone();
+
+    let mut peer = TestPeer::new(peer_config);
+
+    let chainstate_path = peer.chainstate_path.clone();
+    let export_path = format!("{chainstate_path}/mempool-export.jsonl");
+
+    let recipient =
+        StacksAddress::from_string("ST1RFD5Q2QPK3E0F08HG9XDX7SSC7CNRS0QR0SGEV").unwrap();
+
+    let tip =
+        SortitionDB::get_canonical_burn_chain_tip(peer.sortdb.as_ref().unwrap().conn()).unwrap();
+
+    let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+        |ref mut miner,
+         ref mut sortdb,
+         ref mut chainstate,
+         vrf_proof,
+         ref parent_opt,
+         ref parent_microblock_header_opt| {
+            let parent_tip = StacksChainState::get_genesis_header_info(chainstate.db()).unwrap();
+            let parent_header_hash = parent_tip.anchored_header.block_hash();
+            let parent_consensus_hash = parent_tip.consensus_hash.clone();
+
+            let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+
+            let mut txids = vec![];
+            for nonce in 0..2 {
+                let tx = make_user_stacks_transfer(
+                    &privk,
+                    nonce,
+                    200 + nonce,
+                    &recipient.to_account_principal(),
+                    1000,
+                );
+                mempool
+                    .submit(
+                        chainstate,
+                        sortdb,
+                        &parent_consensus_hash,
+                        &parent_header_hash,
+                        &tx,
+                        None,
+                        &ExecutionCost::max_value(),
+                        &StacksEpochId::Epoch20,
+                    )
+                    .unwrap();
+                txids.push(tx.txid());
+            }
+
+            // inadmissible, since it doesn't pay a fee
+            let zero_fee_tx = make_user_stacks_transfer(
+                &StacksPrivateKey::random(),
+                0,
+                0,
+                &recipient.to_account_principal(),
+                1000,
+            );
+            mempool
+                .submit_raw(
+                    chainstate,
+                    sortdb,
+                    &parent_consensus_hash,
+                    &parent_header_hash,
+                    zero_fee_tx.serialize_to_vec(),
+                    &ExecutionCost::max_value(),
+                    &StacksEpochId::Epoch20,
+                )
+                .unwrap();
+
+            assert_eq!(mempool.export_txs(Path::new(&export_path)).unwrap(), 3);
+
+            let mut imported = MemPoolDB::open_db(
+                &format!("{chainstate_path}/mempool-import.sqlite"),
+                Box::new(UnitEstimator),
+                Box::new(UnitMetric),
+            )
+            .unwrap();
+            let stats = imported
+                .import_txs(
+                    chainstate,
+                    sortdb,
+                    &parent_consensus_hash,
+                    &parent_header_hash,
+                    Path::new(&export_path),
+                )
+                .unwrap();
+            assert_eq!(
+                stats,
+                MemPoolImportStats {
+                    imported: 2,
+                    already_present: 0,
+                    rejected: 1,
+                }
+            );
+            for txid in txids.iter() {
+                let original = MemPoolDB::get_tx(mempool.conn(), txid).unwrap().unwrap();
+                let copy = MemPoolDB::get_tx(imported.conn(), txid).unwrap().unwrap();
+                assert_eq!(original.tx, copy.tx);
+                assert_eq!(original.metadata.accept_time, copy.metadata.accept_time);
+            }
+            assert!(!MemPoolDB::db_has_tx(imported.conn(), &zero_fee_tx.txid()).unwrap());
+
+            // importing again is a no-op
+            let stats = imported
+                .import_txs(
+                    chainstate,
+                    sortdb,
+                    &parent_consensus_hash,
+                    &parent_header_hash,
+                    Path::new(&export_path),
+                )
+                .unwrap();
+            assert_eq!(stats.imported, 0);
+            assert_eq!(stats.already_present, 2);
+
+            let coinbase_tx = make_coinbase(miner, 0);
+            let anchored_block = StacksBlockBuilder::build_anchored_block(
+                chainstate,
+                &sortdb.index_handle_at_tip(),
+                &mut imported,
+                &parent_tip,
+                tip.total_burn,
+                vrf_proof,
+                Hash160([0; 20]),
+                &coinbase_tx,
+                BlockBuilderSettings::max_value(),
+                None,
+                &burnchain,
+            )
+            .unwrap();
+
+            (anchored_block.0, vec![])
+        },
+    );
+
+    peer.next_burnchain_block(burn_ops);
+    peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+    // the imported transactions are mineable
+    assert_eq!(stacks_block.txs.len(), 3);
+}
+
 #[test]
 fn test_build_anchored_blocks_zero_fee_transaction() {
     let privk = StacksPrivateKey::from_hex(
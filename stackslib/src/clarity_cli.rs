@@ -21,6 +21,8 @@ use std::str::FromStr;
 use std::{env, fs, io, process};
 
 use clarity::vm::coverage::CoverageReporter;
+use clarity::vm::debugger::{Breakpoint, Debugger};
+use clarity::vm::profiler::CostProfiler;
 use lazy_static::lazy_static;
 use rand::Rng;
 use rusqlite::types::ToSql;
@@ -58,8 +60,8 @@ use crate::clarity::vm::database::{
 use crate::clarity::vm::errors::{Error, InterpreterResult, RuntimeErrorType};
 use crate::clarity::vm::types::{OptionalData, PrincipalData, QualifiedContractIdentifier};
 use crate::clarity::vm::{
-    analysis, ast, eval_all, ClarityVersion, ContractContext, ContractName, SymbolicExpression,
-    SymbolicExpressionType, Value,
+    analysis, ast, eval_all, ClarityVersion, ContractContext, ContractName, EvalHook,
+    SymbolicExpression, SymbolicExpressionType, Value,
 };
 use crate::clarity_vm::database::marf::{MarfedKV, WritableMarfStore};
 use crate::clarity_vm::database::MemoryBackingStore;
@@ -121,6 +123,8 @@ where command is one of:
   eval_raw           to typecheck and evaluate an expression without a contract or database context.
   repl               to typecheck and evaluate expressions in a stdin/stdout loop.
   execute            to execute a public function of a defined contract.
+  debug              like `execute`, but steps through the call in an interactive debugger
+                     and does not commit its effects.
   generate_address   to generate a random Stacks public address for testing purposes.
 ",
         invoked_by
@@ -150,6 +154,16 @@ struct EvalInput {
     content: String,
 }
 
+/// The state and call which `execute` and `debug` share
+struct ExecuteInput {
+    header_db: CLIHeadersDB,
+    marf_kv: MarfedKV,
+    contract_identifier: QualifiedContractIdentifier,
+    tx_name: String,
+    sender: PrincipalData,
+    arguments: Vec<SymbolicExpression>,
+}
+
 fn parse(
     contract_identifier: &QualifiedContractIdentifier,
     source_code: &str,
@@ -414,6 +428,22 @@ fn with_env_costs<F, R>(
     coverage: Option<&mut CoverageReporter>,
     f: F,
 ) -> (R, ExecutionCost)
+where
+    F: FnOnce(&mut OwnedEnvironment) -> R,
+{
+    let eval_hooks = coverage
+        .map(|coverage| vec![coverage as &mut dyn EvalHook])
+        .unwrap_or_default();
+    with_env_costs_and_hooks(mainnet, header_db, marf, eval_hooks, f)
+}
+
+fn with_env_costs_and_hooks<F, R>(
+    mainnet: bool,
+    header_db: &CLIHeadersDB,
+    marf: &mut WritableMarfStore,
+    eval_hooks: Vec<&mut dyn EvalHook>,
+    f: F,
+) -> (R, ExecutionCost)
 where
     F: FnOnce(&mut OwnedEnvironment) -> R,
 {
@@ -437,8 +467,8 @@ where
         cost_track,
         DEFAULT_CLI_EPOCH,
     );
-    if let Some(coverage) = coverage {
-        vm_env.add_eval_hook(coverage);
+    for eval_hook in eval_hooks.into_iter() {
+        vm_env.add_eval_hook(eval_hook);
     }
     let result = f(&mut vm_env);
     let cost = vm_env.get_cost_total();
@@ -817,6 +847,58 @@ fn get_eval_input(invoked_by: &str, args: &[String]) -> EvalInput {
     };
 }
 
+/// Open the VM state and parse the call from `args`, which are
+/// `[command] [vm-state.db] [contract-identifier] [public-function-name] [sender-address] [args...]`
+fn get_execute_input(args: &[String]) -> ExecuteInput {
+    let vm_filename = &args[1];
+    let header_db = friendly_expect(CLIHeadersDB::resume(vm_filename), "Failed to open CLI DB");
+    let marf_kv = friendly_expect(
+        MarfedKV::open(vm_filename, None, None),
+        "Failed to open VM database.",
+    );
+    let contract_identifier = friendly_expect(
+        QualifiedContractIdentifier::parse(&args[2]),
+        "Failed to parse contract identifier.",
+    );
+
+    let tx_name = args[3].clone();
+    let sender_in = &args[4];
+
+    let sender = {
+        if let Ok(sender) = PrincipalData::parse_standard_principal(sender_in) {
+            PrincipalData::Standard(sender)
+        } else {
+            eprintln!("Unexpected result parsing sender: {}", sender_in);
+            panic_test!();
+        }
+    };
+
+    let arguments: Vec<_> = args[5..]
+        .iter()
+        .map(|argument| {
+            let clarity_version = ClarityVersion::default_for_epoch(DEFAULT_CLI_EPOCH);
+            let argument_parsed = friendly_expect(
+                vm_execute(argument, clarity_version),
+                &format!("Error parsing argument \"{}\"", argument),
+            );
+            let argument_value = friendly_expect_opt(
+                argument_parsed,
+                &format!("Failed to parse a value from the argument: {}", argument),
+            );
+            SymbolicExpression::atom_value(argument_value)
+        })
+        .collect();
+
+    ExecuteInput {
+        header_db,
+        marf_kv,
+        contract_identifier,
+        tx_name,
+        sender,
+        arguments,
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct InitialAllocation {
     principal: String,
@@ -1726,73 +1808,55 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
         "execute" => {
             let mut argv = args.to_vec();
             let coverage_folder = consume_arg(&mut argv, &["--c"], true).unwrap_or(None);
+            let profile_folder = consume_arg(&mut argv, &["--profile"], true).unwrap_or(None);
 
             let costs = matches!(consume_arg(&mut argv, &["--costs"], false), Ok(Some(_)));
             let assets = matches!(consume_arg(&mut argv, &["--assets"], false), Ok(Some(_)));
 
             if argv.len() < 5 {
-                eprintln!("Usage: {} {} [--costs] [--assets] [vm-state.db] [contract-identifier] [public-function-name] [sender-address] [args...]", invoked_by, argv[0]);
+                eprintln!("Usage: {} {} [--costs] [--assets] [--profile profile-folder] [vm-state.db] [contract-identifier] [public-function-name] [sender-address] [args...]", invoked_by, argv[0]);
                 panic_test!();
             }
 
-            let vm_filename = &argv[1];
-            let header_db =
-                friendly_expect(CLIHeadersDB::resume(vm_filename), "Failed to open CLI DB");
-            let marf_kv = friendly_expect(
-                MarfedKV::open(vm_filename, None, None),
-                "Failed to open VM database.",
-            );
+            let ExecuteInput {
+                header_db,
+                marf_kv,
+                contract_identifier,
+                tx_name,
+                sender,
+                arguments,
+            } = get_execute_input(&argv);
             let mainnet = header_db.is_mainnet();
-            let contract_identifier = friendly_expect(
-                QualifiedContractIdentifier::parse(&argv[2]),
-                "Failed to parse contract identifier.",
-            );
-
-            let tx_name = &argv[3];
-            let sender_in = &argv[4];
-
-            let sender = {
-                if let Ok(sender) = PrincipalData::parse_standard_principal(sender_in) {
-                    PrincipalData::Standard(sender)
-                } else {
-                    eprintln!("Unexpected result parsing sender: {}", sender_in);
-                    panic_test!();
-                }
-            };
-
-            let arguments: Vec<_> = argv[5..]
-                .iter()
-                .map(|argument| {
-                    let clarity_version = ClarityVersion::default_for_epoch(DEFAULT_CLI_EPOCH);
-                    let argument_parsed = friendly_expect(
-                        vm_execute(argument, clarity_version),
-                        &format!("Error parsing argument \"{}\"", argument),
-                    );
-                    let argument_value = friendly_expect_opt(
-                        argument_parsed,
-                        &format!("Failed to parse a value from the argument: {}", argument),
-                    );
-                    SymbolicExpression::atom_value(argument_value)
-                })
-                .collect();
 
             let mut coverage = if coverage_folder.is_some() {
                 Some(CoverageReporter::new())
             } else {
                 None
             };
+            let mut profiler = if profile_folder.is_some() {
+                Some(CostProfiler::new())
+            } else {
+                None
+            };
             let (_, _, result_and_cost) = in_block(header_db, marf_kv, |header_db, mut marf| {
-                let result_and_cost = with_env_costs(
+                let mut eval_hooks: Vec<&mut dyn EvalHook> = vec![];
+                if let Some(coverage) = coverage.as_mut() {
+                    eval_hooks.push(coverage);
+                }
+                if let Some(profiler) = profiler.as_mut() {
+                    eval_hooks.push(profiler);
+                }
+                let result_and_cost = with_env_costs_and_hooks(
                     mainnet,
                     &header_db,
                     &mut marf,
-                    coverage.as_mut(),
+                    eval_hooks,
                     |vm_env| {
                         vm_env.execute_transaction(
                             sender,
                             None,
                             contract_identifier,
-                            tx_name,
+                            &tx_name,
                             &arguments,
                         )
                     },
@@ -1801,6 +1865,14 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                 (header_db, marf, (result, cost))
             });
 
+            if let (Some(profile_folder), Some(profiler)) = (profile_folder, profiler) {
+                friendly_expect(
+                    profiler
+                        .to_folder(&profile_folder, &format!("execute_{}", get_epoch_time_ms())),
+                    "Failed to write the cost profile.",
+                );
+            }
+
             match result_and_cost {
                 (Ok((x, asset_map, events)), cost) => {
                     if let Value::Response(data) = x {
@@ -1861,6 +1933,76 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                 }
             }
         }
+        "debug" => {
+            let mut argv = args.to_vec();
+            let mut breakpoints = vec![];
+            while let Ok(Some(spec)) = consume_arg(&mut argv, &["--break"], true) {
+                breakpoints.push(friendly_expect(
+                    Breakpoint::parse(&spec),
+                    &format!("Failed to parse breakpoint \"{}\"", spec),
+                ));
+            }
+
+            if argv.len() < 5 {
+                eprintln!("Usage: {} {} [--break [contract:]line|function]... [vm-state.db] [contract-identifier] [public-function-name] [sender-address] [args...]", invoked_by, argv[0]);
+                eprintln!("   Stops at the first expression, unless breakpoints are given.  Type `help` at the debugger prompt for a list of commands.");
+                panic_test!();
+            }
+
+            let vm_filename = &argv[1];
+            let ExecuteInput {
+                header_db,
+                marf_kv,
+                contract_identifier,
+                tx_name,
+                sender,
+                arguments,
+            } = get_execute_input(&argv);
+            let mainnet = header_db.is_mainnet();
+
+            let mut debugger = Debugger::new(io::stdin().lock(), io::stdout(), breakpoints);
+            let result_and_cost = at_chaintip(vm_filename, marf_kv, |mut marf| {
+                let result_and_cost = with_env_costs_and_hooks(
+                    mainnet,
+                    &header_db,
+                    &mut marf,
+                    vec![&mut debugger],
+                    |vm_env| {
+                        vm_env.execute_transaction(
+                            sender,
+                            None,
+                            contract_identifier,
+                            &tx_name,
+                            &arguments,
+                        )
+                    },
+                );
+                (marf, result_and_cost)
+            });
+
+            match result_and_cost {
+                (Ok((x, _, _)), cost) => {
+                    let mut result = json!({
+                        "output": serde_json::to_value(&x).unwrap(),
+                        "success": true,
+                    });
+                    add_serialized_output(&mut result, x);
+                    add_costs(&mut result, true, cost);
+                    (0, Some(result))
+                }
+                (Err(error), cost) => {
+                    let mut result = json!({
+                        "error": {
+                            "runtime": "Transaction execution error.",
+                            "error": serde_json::to_value(format!("{error}")).unwrap()
+                        },
+                        "success": false,
+                    });
+                    add_costs(&mut result, true, cost);
+                    (1, Some(result))
+                }
+            }
+        }
         "make_lcov" => {
             let mut register_files = vec![];
             let mut coverage_files = vec![];
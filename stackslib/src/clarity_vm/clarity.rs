@@ -25,8 +25,8 @@ pub use clarity::vm::clarity::{ClarityConnection, Error};
 use clarity::vm::contexts::{AssetMap, Environment, OwnedEnvironment};
 use clarity::vm::costs::{CostTracker, ExecutionCost, LimitedCostTracker};
 use clarity::vm::database::{
-    BurnStateDB, ClarityDatabase, HeadersDB, RollbackWrapper, RollbackWrapperPersistedLog,
-    STXBalance, SqliteConnection, NULL_BURN_STATE_DB, NULL_HEADER_DB,
+    BurnStateDB, ClarityBackingStore, ClarityDatabase, HeadersDB, RollbackWrapper,
+    RollbackWrapperPersistedLog, STXBalance, SqliteConnection, NULL_BURN_STATE_DB, NULL_HEADER_DB,
 };
 use clarity::vm::errors::Error as InterpreterError;
 use clarity::vm::representations::SymbolicExpression;
@@ -62,7 +62,10 @@ use crate::chainstate::stacks::{
     TransactionPublicKeyEncoding, TransactionSmartContract, TransactionSpendingCondition,
     TransactionVersion,
 };
-use crate::clarity_vm::database::marf::{MarfedKV, ReadOnlyMarfStore, WritableMarfStore};
+use crate::clarity_vm::database::marf::{
+    DiscardingMarfStore, MarfedKV, ReadOnlyMarfStore, WritableMarfStore,
+};
+use crate::clarity_vm::database::speculative::SpeculativeStore;
 use crate::core::{StacksEpoch, StacksEpochId, FIRST_STACKS_BLOCK_ID, GENESIS_EPOCH};
 use crate::util_lib::boot::{boot_code_acc, boot_code_addr, boot_code_id, boot_code_tx_auth};
 use crate::util_lib::db::Error as DatabaseError;
@@ -132,7 +135,7 @@ pub struct ClarityBlockConnection<'a, 'b> {
 ///   rollback the transaction by dropping this struct.
 pub struct ClarityTransactionConnection<'a, 'b> {
     log: Option<RollbackWrapperPersistedLog>,
-    store: &'a mut WritableMarfStore<'b>,
+    store: TransactionStore<'a, 'b>,
     header_db: &'a dyn HeadersDB,
     burn_state_db: &'a dyn BurnStateDB,
     cost_track: &'a mut Option<LimitedCostTracker>,
@@ -141,6 +144,26 @@ pub struct ClarityTransactionConnection<'a, 'b> {
     epoch: StacksEpochId,
 }
 
+/// The backing store which a transaction is evaluated against
+enum TransactionStore<'a, 'b> {
+    /// The datastore of the block being processed
+    Block(&'a mut WritableMarfStore<'b>),
+    /// A read-only view of the chain tip, which discards the transaction's writes
+    Simulated(DiscardingMarfStore<'a, 'b>),
+    /// A store which records the transaction's writes for speculative execution
+    Speculative(&'a mut SpeculativeStore<'b>),
+}
+
+impl TransactionStore<'_, '_> {
+    fn as_backing_store(&mut self) -> &mut dyn ClarityBackingStore {
+        match self {
+            TransactionStore::Block(store) => &mut **store,
+            TransactionStore::Simulated(store) => store,
+            TransactionStore::Speculative(store) => &mut **store,
+        }
+    }
+}
+
 pub struct ClarityReadOnlyConnection<'a> {
     datastore: ReadOnlyMarfStore<'a>,
     header_db: &'a dyn HeadersDB,
@@ -219,6 +242,20 @@ impl ClarityBlockConnection<'_, '_> {
         }
     }
 
+    /// Get the block's cost tracker, if it has one
+    pub fn cost_tracker(&self) -> Option<&LimitedCostTracker> {
+        self.cost_track.as_ref()
+    }
+
+    /// Do something with the block's underlying datastore, headers DB, and burn state DB.
+    /// Used to run transactions against a wrapper of the datastore.
+    pub fn with_backing_store<F, R>(&mut self, to_do: F) -> R
+    where
+        F: FnOnce(&mut dyn ClarityBackingStore, &dyn HeadersDB, &dyn BurnStateDB) -> R,
+    {
+        to_do(&mut self.datastore, self.header_db, self.burn_state_db)
+    }
+
     /// Returns the block limit for the block being created.
     pub fn block_limit(&self) -> Option<ExecutionCost> {
         match self.cost_track {
@@ -711,6 +748,45 @@ impl ClarityConnection for ClarityReadOnlyConnection<'_> {
     }
 }
 
+impl ClarityReadOnlyConnection<'_> {
+    /// Evaluate `todo` as a transaction on top of this connection's chain tip, with its cost
+    /// limited to `cost_limit`.  The transaction may commit, but everything it writes is
+    /// discarded, so the chain state is never modified.
+    pub fn as_simulated_transaction<F, R>(
+        &mut self,
+        mainnet: bool,
+        chain_id: u32,
+        cost_limit: ExecutionCost,
+        todo: F,
+    ) -> Result<R, Error>
+    where
+        F: FnOnce(ClarityTransactionConnection) -> R,
+    {
+        let epoch = self.epoch;
+        let cost_track = {
+            let mut db = self
+                .datastore
+                .as_clarity_db(self.header_db, self.burn_state_db);
+            LimitedCostTracker::new(mainnet, chain_id, cost_limit, &mut db, epoch)
+                .map_err(InterpreterError::from)?
+        };
+        let mut cost_track = Some(cost_track);
+        let mut log = RollbackWrapperPersistedLog::new();
+        log.nest();
+        let transaction = ClarityTransactionConnection {
+            store: TransactionStore::Simulated(DiscardingMarfStore::new(&mut self.datastore)),
+            cost_track: &mut cost_track,
+            header_db: self.header_db,
+            burn_state_db: self.burn_state_db,
+            log: Some(log),
+            mainnet,
+            chain_id,
+            epoch,
+        };
+        Ok(todo(transaction))
+    }
+}
+
 impl PreCommitClarityBlock<'_> {
     pub fn commit(self) {
         debug!("Committing Clarity block connection"; "index_block" => %self.commit_to);
@@ -718,6 +794,12 @@ impl PreCommitClarityBlock<'_> {
             .commit_to(&self.commit_to)
             .expect("FATAL: failed to commit block");
     }
+
+    /// Drop all changes in the block instead of committing them
+    pub fn rollback(self) {
+        debug!("Rolling back Clarity block connection"; "index_block" => %self.commit_to);
+        self.datastore.rollback_block();
+    }
 }
 
 impl<'a> ClarityBlockConnection<'a, '_> {
@@ -1566,7 +1648,7 @@ impl<'a> ClarityBlockConnection<'a, '_> {
     }
 
     pub fn start_transaction_processing<'c>(&'c mut self) -> ClarityTransactionConnection<'c, 'a> {
-        let store = &mut self.datastore;
+        let store = TransactionStore::Block(&mut self.datastore);
         let cost_track = &mut self.cost_track;
         let header_db = self.header_db;
         let burn_state_db = self.burn_state_db;
@@ -1645,7 +1727,8 @@ impl ClarityConnection for ClarityTransactionConnection<'_, '_> {
         F: FnOnce(ClarityDatabase) -> (R, ClarityDatabase),
     {
         using!(self.log, "log", |log| {
-            let rollback_wrapper = RollbackWrapper::from_persisted_log(self.store, log);
+            let rollback_wrapper =
+                RollbackWrapper::from_persisted_log(self.store.as_backing_store(), log);
             let mut db = ClarityDatabase::new_with_rollback_wrapper(
                 rollback_wrapper,
                 self.header_db,
@@ -1710,7 +1793,8 @@ impl TransactionConnection for ClarityTransactionConnection<'_, '_> {
     {
         using!(self.log, "log", |log| {
             using!(self.cost_track, "cost tracker", |cost_track| {
-                let rollback_wrapper = RollbackWrapper::from_persisted_log(self.store, log);
+                let rollback_wrapper =
+                    RollbackWrapper::from_persisted_log(self.store.as_backing_store(), log);
                 let mut db = ClarityDatabase::new_with_rollback_wrapper(
                     rollback_wrapper,
                     self.header_db,
@@ -1762,7 +1846,8 @@ impl TransactionConnection for ClarityTransactionConnection<'_, '_> {
     {
         using!(self.cost_track, "cost tracker", |cost_track| {
             using!(self.log, "log", |log| {
-                let rollback_wrapper = RollbackWrapper::from_persisted_log(self.store, log);
+                let rollback_wrapper =
+                    RollbackWrapper::from_persisted_log(self.store.as_backing_store(), log);
                 let mut db = AnalysisDatabase::new_with_rollback_wrapper(rollback_wrapper);
                 let r = to_do(&mut db, cost_track);
                 (db.destroy().into(), r)
@@ -1771,6 +1856,33 @@ impl TransactionConnection for ClarityTransactionConnection<'_, '_> {
     }
 }
 
+impl<'a, 'b> ClarityTransactionConnection<'a, 'b> {
+    /// Begin processing a transaction against a speculative store, instead of against a block's
+    /// datastore.  Used for speculative execution of a block's transactions.
+    pub fn new(
+        store: &'a mut SpeculativeStore<'b>,
+        header_db: &'a dyn HeadersDB,
+        burn_state_db: &'a dyn BurnStateDB,
+        cost_track: &'a mut Option<LimitedCostTracker>,
+        mainnet: bool,
+        chain_id: u32,
+        epoch: StacksEpochId,
+    ) -> ClarityTransactionConnection<'a, 'b> {
+        let mut log = RollbackWrapperPersistedLog::new();
+        log.nest();
+        ClarityTransactionConnection {
+            store: TransactionStore::Speculative(store),
+            cost_track,
+            header_db,
+            burn_state_db,
+            log: Some(log),
+            mainnet,
+            chain_id,
+            epoch,
+        }
+    }
+}
+
 impl ClarityTransactionConnection<'_, '_> {
     /// Do something to the underlying DB that involves writing.
     pub fn with_clarity_db<F, R>(&mut self, to_do: F) -> Result<R, Error>
@@ -1778,7 +1890,8 @@ impl ClarityTransactionConnection<'_, '_> {
         F: FnOnce(&mut ClarityDatabase) -> Result<R, Error>,
     {
         using!(self.log, "log", |log| {
-            let rollback_wrapper = RollbackWrapper::from_persisted_log(self.store, log);
+            let rollback_wrapper =
+                RollbackWrapper::from_persisted_log(self.store.as_backing_store(), log);
             let mut db = ClarityDatabase::new_with_rollback_wrapper(
                 rollback_wrapper,
                 self.header_db,
@@ -1847,7 +1960,8 @@ impl ClarityTransactionConnection<'_, '_> {
             .log
             .take()
             .expect("BUG: Transaction Connection lost db log connection.");
-        let mut rollback_wrapper = RollbackWrapper::from_persisted_log(self.store, log);
+        let mut rollback_wrapper =
+            RollbackWrapper::from_persisted_log(self.store.as_backing_store(), log);
         if rollback_wrapper.depth() != 1 {
             panic!(
                 "Attempted to commit transaction with {} != 1 rollbacks",
@@ -544,6 +544,107 @@ impl ClarityBackingStore for ReadOnlyMarfStore<'_> {
     }
 }
 
+/// A read-only view of the MARF which silently drops writes, instead of panicking on them.
+/// Transactions evaluated against it can run to completion and commit, but nothing they write
+/// is ever persisted.  Used to simulate transactions from the RPC threads.
+pub struct DiscardingMarfStore<'a, 'b> {
+    inner: &'a mut ReadOnlyMarfStore<'b>,
+}
+
+impl<'a, 'b> DiscardingMarfStore<'a, 'b> {
+    pub fn new(inner: &'a mut ReadOnlyMarfStore<'b>) -> Self {
+        Self { inner }
+    }
+}
+
+impl ClarityBackingStore for DiscardingMarfStore<'_, '_> {
+    fn get_side_store(&mut self) -> &Connection {
+        self.inner.get_side_store()
+    }
+
+    fn get_cc_special_cases_handler(&self) -> Option<SpecialCaseHandler> {
+        self.inner.get_cc_special_cases_handler()
+    }
+
+    fn set_block_hash(&mut self, bhh: StacksBlockId) -> InterpreterResult<StacksBlockId> {
+        self.inner.set_block_hash(bhh)
+    }
+
+    fn get_current_block_height(&mut self) -> u32 {
+        self.inner.get_current_block_height()
+    }
+
+    fn get_block_at_height(&mut self, block_height: u32) -> Option<StacksBlockId> {
+        self.inner.get_block_at_height(block_height)
+    }
+
+    fn get_open_chain_tip(&mut self) -> StacksBlockId {
+        self.inner.get_open_chain_tip()
+    }
+
+    fn get_open_chain_tip_height(&mut self) -> u32 {
+        self.inner.get_open_chain_tip_height()
+    }
+
+    fn get_data_with_proof(&mut self, key: &str) -> InterpreterResult<Option<(String, Vec<u8>)>> {
+        self.inner.get_data_with_proof(key)
+    }
+
+    fn get_data_with_proof_from_path(
+        &mut self,
+        hash: &TrieHash,
+    ) -> InterpreterResult<Option<(String, Vec<u8>)>> {
+        self.inner.get_data_with_proof_from_path(hash)
+    }
+
+    fn get_data(&mut self, key: &str) -> InterpreterResult<Option<String>> {
+        self.inner.get_data(key)
+    }
+
+    fn get_data_from_path(&mut self, hash: &TrieHash) -> InterpreterResult<Option<String>> {
+        self.inner.get_data_from_path(hash)
+    }
+
+    fn put_all_data(&mut self, items: Vec<(String, String)>) -> InterpreterResult<()> {
+        trace!("Discard {} MARF writes", items.len());
+        Ok(())
+    }
+
+    fn get_contract_hash(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+    ) -> InterpreterResult<(StacksBlockId, Sha512Trunc256Sum)> {
+        self.inner.get_contract_hash(contract)
+    }
+
+    fn insert_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+        _value: &str,
+    ) -> InterpreterResult<()> {
+        trace!("Discard metadata write {}/{}", contract, key);
+        Ok(())
+    }
+
+    fn get_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        self.inner.get_metadata(contract, key)
+    }
+
+    fn get_metadata_manual(
+        &mut self,
+        at_height: u32,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        self.inner.get_metadata_manual(at_height, contract, key)
+    }
+}
+
 impl WritableMarfStore<'_> {
     pub fn as_clarity_db<'b>(
         &'b mut self,
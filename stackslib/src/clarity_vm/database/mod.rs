@@ -41,6 +41,7 @@ use crate::core::{StacksEpoch, StacksEpochId};
 use crate::util_lib::db::{DBConn, Error as DBError, FromColumn, FromRow};
 
 pub mod marf;
+pub mod speculative;
 
 pub trait GetTenureStartId {
     fn get_tenure_block_id(
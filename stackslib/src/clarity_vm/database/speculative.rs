@@ -0,0 +1,584 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Backing stores for speculative transaction execution.
+//!
+//! A `SpeculativeStore` runs a transaction against some other backing store, but instead of
+//! writing to it, it records the MARF keys and contract metadata the transaction read and
+//! buffers what it wrote.  Those `SpeculativeEffects` can then be checked for conflicts against
+//! whatever was written in the meantime, and applied to the real store.
+//!
+//! The block's `WritableMarfStore` cannot leave the thread which opened it, so transactions that
+//! execute on other threads use a `SnapshotClient`, which sends each read to the thread which owns
+//! the store (see `serve_snapshot()`).  Since the owning thread does not write to its store while
+//! it serves reads, every client sees the same snapshot of the block's state.
+
+use std::collections::HashSet;
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+
+use clarity::util::hash::Sha512Trunc256Sum;
+use clarity::vm::ast::ASTRules;
+use clarity::vm::database::sqlite::sqlite_get_contract_hash;
+use clarity::vm::database::{BurnStateDB, ClarityBackingStore, HeadersDB, SpecialCaseHandler};
+use clarity::vm::errors::{InterpreterError, InterpreterResult};
+use clarity::vm::types::{QualifiedContractIdentifier, TupleData};
+use rusqlite::Connection;
+use stacks_common::types::chainstate::{
+    BlockHeaderHash, BurnchainHeaderHash, ConsensusHash, SortitionId, StacksAddress, StacksBlockId,
+    TrieHash, VRFSeed,
+};
+
+use crate::clarity_vm::special::handle_contract_call_special_cases;
+use crate::core::{StacksEpoch, StacksEpochId};
+
+/// A read (or other operation) to run against the snapshot, on the thread which owns it
+pub type SnapshotRequest =
+    Box<dyn FnOnce(&mut dyn ClarityBackingStore, &dyn HeadersDB, &dyn BurnStateDB) + Send>;
+
+/// Create a channel over which `SnapshotClient`s send their requests
+pub fn snapshot_channel() -> (Sender<SnapshotRequest>, Receiver<SnapshotRequest>) {
+    mpsc::channel()
+}
+
+/// Serve snapshot requests until every `SnapshotClient` has been dropped
+pub fn serve_snapshot(
+    requests: Receiver<SnapshotRequest>,
+    store: &mut dyn ClarityBackingStore,
+    headers_db: &dyn HeadersDB,
+    burn_state_db: &dyn BurnStateDB,
+) {
+    for request in requests.iter() {
+        request(store, headers_db, burn_state_db);
+    }
+}
+
+/// Read-only view of a snapshot of a block's state, its headers DB, and its burn state DB, which
+/// is served by another thread.
+///
+/// Operations that cannot be done through a shared snapshot -- time-shifted evaluation and direct
+/// side-store access -- fail, and mark the client as `unsupported`.
+pub struct SnapshotClient {
+    requests: Sender<SnapshotRequest>,
+    unsupported: bool,
+    side_store: Option<Connection>,
+}
+
+impl SnapshotClient {
+    pub fn new(requests: Sender<SnapshotRequest>) -> SnapshotClient {
+        SnapshotClient {
+            requests,
+            unsupported: false,
+            side_store: None,
+        }
+    }
+
+    /// Did the caller try to do something which a snapshot cannot do?
+    pub fn unsupported(&self) -> bool {
+        self.unsupported
+    }
+
+    fn call<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut dyn ClarityBackingStore, &dyn HeadersDB, &dyn BurnStateDB) -> R
+            + Send
+            + 'static,
+        R: Send + 'static,
+    {
+        let (reply_tx, reply_rx): (SyncSender<R>, _) = mpsc::sync_channel(1);
+        self.requests
+            .send(Box::new(move |store, headers_db, burn_state_db| {
+                let _ = reply_tx.send(f(store, headers_db, burn_state_db));
+            }))
+            .expect("FATAL: snapshot server hung up");
+        reply_rx.recv().expect("FATAL: snapshot server hung up")
+    }
+}
+
+impl ClarityBackingStore for SnapshotClient {
+    fn put_all_data(&mut self, _items: Vec<(String, String)>) -> InterpreterResult<()> {
+        self.unsupported = true;
+        Err(InterpreterError::Expect("Cannot write to a snapshot".into()).into())
+    }
+
+    fn get_data(&mut self, key: &str) -> InterpreterResult<Option<String>> {
+        let key = key.to_string();
+        self.call(move |store, _, _| store.get_data(&key))
+    }
+
+    fn get_data_from_path(&mut self, hash: &TrieHash) -> InterpreterResult<Option<String>> {
+        let hash = hash.clone();
+        self.call(move |store, _, _| store.get_data_from_path(&hash))
+    }
+
+    fn get_data_with_proof(&mut self, key: &str) -> InterpreterResult<Option<(String, Vec<u8>)>> {
+        let key = key.to_string();
+        self.call(move |store, _, _| store.get_data_with_proof(&key))
+    }
+
+    fn get_data_with_proof_from_path(
+        &mut self,
+        hash: &TrieHash,
+    ) -> InterpreterResult<Option<(String, Vec<u8>)>> {
+        let hash = hash.clone();
+        self.call(move |store, _, _| store.get_data_with_proof_from_path(&hash))
+    }
+
+    fn set_block_hash(&mut self, _bhh: StacksBlockId) -> InterpreterResult<StacksBlockId> {
+        self.unsupported = true;
+        Err(InterpreterError::Expect("Cannot time-shift a snapshot".into()).into())
+    }
+
+    fn get_block_at_height(&mut self, height: u32) -> Option<StacksBlockId> {
+        self.call(move |store, _, _| store.get_block_at_height(height))
+    }
+
+    fn get_current_block_height(&mut self) -> u32 {
+        self.call(|store, _, _| store.get_current_block_height())
+    }
+
+    fn get_open_chain_tip_height(&mut self) -> u32 {
+        self.call(|store, _, _| store.get_open_chain_tip_height())
+    }
+
+    fn get_open_chain_tip(&mut self) -> StacksBlockId {
+        self.call(|store, _, _| store.get_open_chain_tip())
+    }
+
+    fn get_side_store(&mut self) -> &Connection {
+        self.unsupported = true;
+        self.side_store.get_or_insert_with(|| {
+            Connection::open_in_memory().expect("FATAL: failed to open in-memory DB")
+        })
+    }
+
+    fn get_cc_special_cases_handler(&self) -> Option<SpecialCaseHandler> {
+        Some(&handle_contract_call_special_cases)
+    }
+
+    fn get_contract_hash(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+    ) -> InterpreterResult<(StacksBlockId, Sha512Trunc256Sum)> {
+        let contract = contract.clone();
+        self.call(move |store, _, _| store.get_contract_hash(&contract))
+    }
+
+    fn insert_metadata(
+        &mut self,
+        _contract: &QualifiedContractIdentifier,
+        _key: &str,
+        _value: &str,
+    ) -> InterpreterResult<()> {
+        self.unsupported = true;
+        Err(InterpreterError::Expect("Cannot write to a snapshot".into()).into())
+    }
+
+    fn get_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        let (contract, key) = (contract.clone(), key.to_string());
+        self.call(move |store, _, _| store.get_metadata(&contract, &key))
+    }
+
+    fn get_metadata_manual(
+        &mut self,
+        at_height: u32,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        let (contract, key) = (contract.clone(), key.to_string());
+        self.call(move |store, _, _| store.get_metadata_manual(at_height, &contract, &key))
+    }
+}
+
+impl HeadersDB for SnapshotClient {
+    fn get_stacks_block_header_hash_for_block(
+        &self,
+        id_bhh: &StacksBlockId,
+        epoch: &StacksEpochId,
+    ) -> Option<BlockHeaderHash> {
+        let (id_bhh, epoch) = (*id_bhh, *epoch);
+        self.call(move |_, headers_db, _| {
+            headers_db.get_stacks_block_header_hash_for_block(&id_bhh, &epoch)
+        })
+    }
+
+    fn get_burn_header_hash_for_block(
+        &self,
+        id_bhh: &StacksBlockId,
+    ) -> Option<BurnchainHeaderHash> {
+        let id_bhh = *id_bhh;
+        self.call(move |_, headers_db, _| headers_db.get_burn_header_hash_for_block(&id_bhh))
+    }
+
+    fn get_consensus_hash_for_block(
+        &self,
+        id_bhh: &StacksBlockId,
+        epoch: &StacksEpochId,
+    ) -> Option<ConsensusHash> {
+        let (id_bhh, epoch) = (*id_bhh, *epoch);
+        self.call(move |_, headers_db, _| headers_db.get_consensus_hash_for_block(&id_bhh, &epoch))
+    }
+
+    fn get_vrf_seed_for_block(
+        &self,
+        id_bhh: &StacksBlockId,
+        epoch: &StacksEpochId,
+    ) -> Option<VRFSeed> {
+        let (id_bhh, epoch) = (*id_bhh, *epoch);
+        self.call(move |_, headers_db, _| headers_db.get_vrf_seed_for_block(&id_bhh, &epoch))
+    }
+
+    fn get_stacks_block_time_for_block(&self, id_bhh: &StacksBlockId) -> Option<u64> {
+        let id_bhh = *id_bhh;
+        self.call(move |_, headers_db, _| headers_db.get_stacks_block_time_for_block(&id_bhh))
+    }
+
+    fn get_burn_block_time_for_block(
+        &self,
+        id_bhh: &StacksBlockId,
+        epoch: Option<&StacksEpochId>,
+    ) -> Option<u64> {
+        let (id_bhh, epoch) = (*id_bhh, epoch.copied());
+        self.call(move |_, headers_db, _| {
+            headers_db.get_burn_block_time_for_block(&id_bhh, epoch.as_ref())
+        })
+    }
+
+    fn get_burn_block_height_for_block(&self, id_bhh: &StacksBlockId) -> Option<u32> {
+        let id_bhh = *id_bhh;
+        self.call(move |_, headers_db, _| headers_db.get_burn_block_height_for_block(&id_bhh))
+    }
+
+    fn get_miner_address(
+        &self,
+        id_bhh: &StacksBlockId,
+        epoch: &StacksEpochId,
+    ) -> Option<StacksAddress> {
+        let (id_bhh, epoch) = (*id_bhh, *epoch);
+        self.call(move |_, headers_db, _| headers_db.get_miner_address(&id_bhh, &epoch))
+    }
+
+    fn get_burnchain_tokens_spent_for_block(
+        &self,
+        id_bhh: &StacksBlockId,
+        epoch: &StacksEpochId,
+    ) -> Option<u128> {
+        let (id_bhh, epoch) = (*id_bhh, *epoch);
+        self.call(move |_, headers_db, _| {
+            headers_db.get_burnchain_tokens_spent_for_block(&id_bhh, &epoch)
+        })
+    }
+
+    fn get_burnchain_tokens_spent_for_winning_block(
+        &self,
+        id_bhh: &StacksBlockId,
+        epoch: &StacksEpochId,
+    ) -> Option<u128> {
+        let (id_bhh, epoch) = (*id_bhh, *epoch);
+        self.call(move |_, headers_db, _| {
+            headers_db.get_burnchain_tokens_spent_for_winning_block(&id_bhh, &epoch)
+        })
+    }
+
+    fn get_tokens_earned_for_block(
+        &self,
+        id_bhh: &StacksBlockId,
+        epoch: &StacksEpochId,
+    ) -> Option<u128> {
+        let (id_bhh, epoch) = (*id_bhh, *epoch);
+        self.call(move |_, headers_db, _| headers_db.get_tokens_earned_for_block(&id_bhh, &epoch))
+    }
+
+    fn get_stacks_height_for_tenure_height(
+        &self,
+        tip: &StacksBlockId,
+        tenure_height: u32,
+    ) -> Option<u32> {
+        let tip = *tip;
+        self.call(move |_, headers_db, _| {
+            headers_db.get_stacks_height_for_tenure_height(&tip, tenure_height)
+        })
+    }
+}
+
+impl BurnStateDB for SnapshotClient {
+    fn get_tip_burn_block_height(&self) -> Option<u32> {
+        self.call(|_, _, burn_state_db| burn_state_db.get_tip_burn_block_height())
+    }
+
+    fn get_tip_sortition_id(&self) -> Option<SortitionId> {
+        self.call(|_, _, burn_state_db| burn_state_db.get_tip_sortition_id())
+    }
+
+    fn get_v1_unlock_height(&self) -> u32 {
+        self.call(|_, _, burn_state_db| burn_state_db.get_v1_unlock_height())
+    }
+
+    fn get_v2_unlock_height(&self) -> u32 {
+        self.call(|_, _, burn_state_db| burn_state_db.get_v2_unlock_height())
+    }
+
+    fn get_v3_unlock_height(&self) -> u32 {
+        self.call(|_, _, burn_state_db| burn_state_db.get_v3_unlock_height())
+    }
+
+    fn get_pox_3_activation_height(&self) -> u32 {
+        self.call(|_, _, burn_state_db| burn_state_db.get_pox_3_activation_height())
+    }
+
+    fn get_pox_4_activation_height(&self) -> u32 {
+        self.call(|_, _, burn_state_db| burn_state_db.get_pox_4_activation_height())
+    }
+
+    fn get_burn_block_height(&self, sortition_id: &SortitionId) -> Option<u32> {
+        let sortition_id = *sortition_id;
+        self.call(move |_, _, burn_state_db| burn_state_db.get_burn_block_height(&sortition_id))
+    }
+
+    fn get_burn_start_height(&self) -> u32 {
+        self.call(|_, _, burn_state_db| burn_state_db.get_burn_start_height())
+    }
+
+    fn get_pox_prepare_length(&self) -> u32 {
+        self.call(|_, _, burn_state_db| burn_state_db.get_pox_prepare_length())
+    }
+
+    fn get_pox_reward_cycle_length(&self) -> u32 {
+        self.call(|_, _, burn_state_db| burn_state_db.get_pox_reward_cycle_length())
+    }
+
+    fn get_pox_rejection_fraction(&self) -> u64 {
+        self.call(|_, _, burn_state_db| burn_state_db.get_pox_rejection_fraction())
+    }
+
+    fn get_burn_header_hash(
+        &self,
+        height: u32,
+        sortition_id: &SortitionId,
+    ) -> Option<BurnchainHeaderHash> {
+        let sortition_id = *sortition_id;
+        self.call(move |_, _, burn_state_db| {
+            burn_state_db.get_burn_header_hash(height, &sortition_id)
+        })
+    }
+
+    fn get_sortition_id_from_consensus_hash(
+        &self,
+        consensus_hash: &ConsensusHash,
+    ) -> Option<SortitionId> {
+        let consensus_hash = *consensus_hash;
+        self.call(move |_, _, burn_state_db| {
+            burn_state_db.get_sortition_id_from_consensus_hash(&consensus_hash)
+        })
+    }
+
+    fn get_stacks_epoch(&self, height: u32) -> Option<StacksEpoch> {
+        self.call(move |_, _, burn_state_db| burn_state_db.get_stacks_epoch(height))
+    }
+
+    fn get_stacks_epoch_by_epoch_id(&self, epoch_id: &StacksEpochId) -> Option<StacksEpoch> {
+        let epoch_id = *epoch_id;
+        self.call(move |_, _, burn_state_db| burn_state_db.get_stacks_epoch_by_epoch_id(&epoch_id))
+    }
+
+    fn get_ast_rules(&self, height: u32) -> ASTRules {
+        self.call(move |_, _, burn_state_db| burn_state_db.get_ast_rules(height))
+    }
+
+    fn get_pox_payout_addrs(
+        &self,
+        height: u32,
+        sortition_id: &SortitionId,
+    ) -> Option<(Vec<TupleData>, u128)> {
+        let sortition_id = *sortition_id;
+        self.call(move |_, _, burn_state_db| {
+            burn_state_db.get_pox_payout_addrs(height, &sortition_id)
+        })
+    }
+}
+
+/// What a speculatively-executed transaction read and wrote
+#[derive(Debug, Clone, Default)]
+pub struct SpeculativeEffects {
+    /// MARF paths of the keys it read
+    pub read_set: HashSet<TrieHash>,
+    /// Contract metadata it read
+    pub metadata_read_set: HashSet<(QualifiedContractIdentifier, String)>,
+    /// Key/value pairs it wrote
+    pub data_writes: Vec<(String, String)>,
+    /// Contract metadata it wrote
+    pub metadata_writes: Vec<((QualifiedContractIdentifier, String), String)>,
+}
+
+impl SpeculativeEffects {
+    /// MARF paths of the keys this wrote
+    pub fn write_set(&self) -> impl Iterator<Item = TrieHash> + '_ {
+        self.data_writes
+            .iter()
+            .map(|(key, _)| TrieHash::from_key(key))
+    }
+
+    /// Did this read anything in the given sets of written keys and metadata?
+    pub fn conflicts_with(
+        &self,
+        written: &HashSet<TrieHash>,
+        metadata_written: &HashSet<(QualifiedContractIdentifier, String)>,
+    ) -> bool {
+        !self.read_set.is_disjoint(written) || !self.metadata_read_set.is_disjoint(metadata_written)
+    }
+
+    /// Write this to `store`
+    pub fn apply(self, store: &mut dyn ClarityBackingStore) -> InterpreterResult<()> {
+        if !self.data_writes.is_empty() {
+            store.put_all_data(self.data_writes)?;
+        }
+        if !self.metadata_writes.is_empty() {
+            store.put_all_metadata(self.metadata_writes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Backing store which reads through to `base`, and records reads and buffers writes as
+/// `SpeculativeEffects`
+pub struct SpeculativeStore<'a> {
+    base: &'a mut dyn ClarityBackingStore,
+    effects: SpeculativeEffects,
+}
+
+impl<'a> SpeculativeStore<'a> {
+    pub fn new(base: &'a mut dyn ClarityBackingStore) -> SpeculativeStore<'a> {
+        SpeculativeStore {
+            base,
+            effects: SpeculativeEffects::default(),
+        }
+    }
+
+    pub fn into_effects(self) -> SpeculativeEffects {
+        self.effects
+    }
+}
+
+impl ClarityBackingStore for SpeculativeStore<'_> {
+    fn put_all_data(&mut self, items: Vec<(String, String)>) -> InterpreterResult<()> {
+        self.effects.data_writes.extend(items);
+        Ok(())
+    }
+
+    fn get_data(&mut self, key: &str) -> InterpreterResult<Option<String>> {
+        self.effects.read_set.insert(TrieHash::from_key(key));
+        self.base.get_data(key)
+    }
+
+    fn get_data_from_path(&mut self, hash: &TrieHash) -> InterpreterResult<Option<String>> {
+        self.effects.read_set.insert(hash.clone());
+        self.base.get_data_from_path(hash)
+    }
+
+    fn get_data_with_proof(&mut self, key: &str) -> InterpreterResult<Option<(String, Vec<u8>)>> {
+        self.effects.read_set.insert(TrieHash::from_key(key));
+        self.base.get_data_with_proof(key)
+    }
+
+    fn get_data_with_proof_from_path(
+        &mut self,
+        hash: &TrieHash,
+    ) -> InterpreterResult<Option<(String, Vec<u8>)>> {
+        self.effects.read_set.insert(hash.clone());
+        self.base.get_data_with_proof_from_path(hash)
+    }
+
+    fn set_block_hash(&mut self, bhh: StacksBlockId) -> InterpreterResult<StacksBlockId> {
+        self.base.set_block_hash(bhh)
+    }
+
+    fn get_block_at_height(&mut self, height: u32) -> Option<StacksBlockId> {
+        self.base.get_block_at_height(height)
+    }
+
+    fn get_current_block_height(&mut self) -> u32 {
+        self.base.get_current_block_height()
+    }
+
+    fn get_open_chain_tip_height(&mut self) -> u32 {
+        self.base.get_open_chain_tip_height()
+    }
+
+    fn get_open_chain_tip(&mut self) -> StacksBlockId {
+        self.base.get_open_chain_tip()
+    }
+
+    fn get_side_store(&mut self) -> &Connection {
+        self.base.get_side_store()
+    }
+
+    fn get_cc_special_cases_handler(&self) -> Option<SpecialCaseHandler> {
+        self.base.get_cc_special_cases_handler()
+    }
+
+    fn get_contract_hash(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+    ) -> InterpreterResult<(StacksBlockId, Sha512Trunc256Sum)> {
+        sqlite_get_contract_hash(self, contract)
+    }
+
+    fn insert_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+        value: &str,
+    ) -> InterpreterResult<()> {
+        self.effects
+            .metadata_writes
+            .push(((contract.clone(), key.to_string()), value.to_string()));
+        Ok(())
+    }
+
+    fn put_all_metadata(
+        &mut self,
+        items: Vec<((QualifiedContractIdentifier, String), String)>,
+    ) -> InterpreterResult<()> {
+        self.effects.metadata_writes.extend(items);
+        Ok(())
+    }
+
+    fn get_metadata(
+        &mut self,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        self.effects
+            .metadata_read_set
+            .insert((contract.clone(), key.to_string()));
+        self.base.get_metadata(contract, key)
+    }
+
+    fn get_metadata_manual(
+        &mut self,
+        at_height: u32,
+        contract: &QualifiedContractIdentifier,
+        key: &str,
+    ) -> InterpreterResult<Option<String>> {
+        self.effects
+            .metadata_read_set
+            .insert((contract.clone(), key.to_string()));
+        self.base.get_metadata_manual(at_height, contract, key)
+    }
+}
@@ -31,6 +31,7 @@ use clarity::vm::database::{ClarityDatabase, MemoryBackingStore};
 use clarity::vm::errors::{CheckErrors, Error, RuntimeErrorType};
 use clarity::vm::events::StacksTransactionEvent;
 use clarity::vm::functions::NativeFunctions;
+use clarity::vm::profiler::{CostDimension, CostProfiler};
 use clarity::vm::representations::SymbolicExpression;
 use clarity::vm::test_util::{
     execute, execute_on_network, symbols_from_values, TEST_BURN_STATE_DB, TEST_BURN_STATE_DB_21,
@@ -186,10 +187,8 @@ fn execute_transaction(
     env.execute_transaction(issuer, None, contract_identifier.clone(), tx, args)
 }
 
-fn with_owned_env<F, R>(epoch: StacksEpochId, use_mainnet: bool, to_do: F) -> R
-where
-    F: Fn(OwnedEnvironment) -> R,
-{
+/// Make a MARF with the boot code for `epoch` installed, and return it along with its tip
+fn setup_boot_marf(epoch: StacksEpochId, use_mainnet: bool) -> (MarfedKV, StacksBlockId) {
     let marf_kv = MarfedKV::temporary();
     let chain_id = test_only_mainnet_to_chain_id(use_mainnet);
     let mut clarity_instance = ClarityInstance::new(use_mainnet, chain_id, marf_kv);
@@ -224,8 +223,14 @@ where
         tip = next_block.clone();
     }
 
-    let mut marf_kv = clarity_instance.destroy();
+    (clarity_instance.destroy(), tip)
+}
 
+fn with_owned_env<F, R>(epoch: StacksEpochId, use_mainnet: bool, to_do: F) -> R
+where
+    F: Fn(OwnedEnvironment) -> R,
+{
+    let (mut marf_kv, tip) = setup_boot_marf(epoch, use_mainnet);
     let mut store = marf_kv.begin(&tip, &StacksBlockId([3; 32]));
 
     to_do(OwnedEnvironment::new_max_limit(
@@ -1764,3 +1769,131 @@ fn test_cost_voting_integration_testnet() {
     test_cost_voting_integration(false, ClarityVersion::Clarity1);
     test_cost_voting_integration(false, ClarityVersion::Clarity2);
 }
+
+const PROFILED_CONTRACT: &str = "
+(define-data-var counter uint u0)
+(define-map seen uint bool)
+(define-private (double (x uint))
+  (* x u2))
+(define-private (record (x uint))
+  (map-set seen x true))
+(define-public (bump (n uint))
+  (begin
+    (record n)
+    (var-set counter (+ (var-get counter) (double n) (double u1)))
+    (ok (var-get counter))))";
+
+#[test]
+fn test_cost_profiler() {
+    let contract_id = QualifiedContractIdentifier::local("counter").unwrap();
+    let mut profiler = CostProfiler::new();
+    let (mut marf_kv, tip) = setup_boot_marf(StacksEpochId::Epoch21, false);
+    let mut store = marf_kv.begin(&tip, &StacksBlockId([3; 32]));
+    {
+        let mut owned_env = OwnedEnvironment::new_max_limit(
+            store.as_clarity_db(&TEST_HEADER_DB, &TEST_BURN_STATE_DB),
+            StacksEpochId::Epoch21,
+            false,
+        );
+        owned_env
+            .initialize_contract(
+                contract_id.clone(),
+                PROFILED_CONTRACT,
+                None,
+                ASTRules::PrecheckSize,
+            )
+            .unwrap();
+    }
+    // call with a fresh cost tracker, so that the profiler measures from zero
+    let cost = {
+        let mut owned_env = OwnedEnvironment::new_max_limit(
+            store.as_clarity_db(&TEST_HEADER_DB, &TEST_BURN_STATE_DB),
+            StacksEpochId::Epoch21,
+            false,
+        );
+        owned_env.add_eval_hook(&mut profiler);
+        owned_env
+            .execute_transaction(
+                PrincipalData::from(contract_id.issuer.clone()),
+                None,
+                contract_id.clone(),
+                "bump",
+                &[SymbolicExpression::atom_value(Value::UInt(3))],
+            )
+            .unwrap();
+        owned_env.get_cost_total()
+    };
+    assert!(cost.runtime > 0);
+
+    let bump = profiler
+        .functions()
+        .get(&format!("{}:bump", &contract_id))
+        .unwrap()
+        .clone();
+    let double = profiler
+        .functions()
+        .get(&format!("{}:double", &contract_id))
+        .unwrap()
+        .clone();
+    let record = profiler
+        .functions()
+        .get(&format!("{}:record", &contract_id))
+        .unwrap()
+        .clone();
+    assert_eq!(bump.calls, 1);
+    assert_eq!(double.calls, 2);
+    assert_eq!(record.calls, 1);
+
+    // the writes happen in `bump` and `record`
+    assert_eq!(record.cost.write_count, 1);
+    assert_eq!(record.self_cost, record.cost);
+    assert_eq!(bump.cost.write_count, 2);
+    assert_eq!(bump.self_cost.write_count, 1);
+    assert_eq!(double.cost.write_count, 0);
+
+    // a function's cost includes the cost of its callees
+    let mut callees = double.cost.clone();
+    callees.add(&record.cost).unwrap();
+    let mut bump_self = bump.cost.clone();
+    bump_self.sub(&callees).unwrap();
+    assert_eq!(bump_self, bump.self_cost);
+
+    // everything is attributed somewhere
+    assert_eq!(profiler.total(), cost);
+
+    let mut folded = vec![];
+    profiler
+        .write_folded(&mut folded, CostDimension::WriteCount)
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(folded).unwrap(),
+        format!(
+            "{0}:bump;begin;{0}:record;map-set 1\n{0}:bump;begin;var-set 1\n",
+            &contract_id
+        )
+    );
+
+    let mut folded = vec![];
+    profiler
+        .write_folded(&mut folded, CostDimension::Runtime)
+        .unwrap();
+    let folded = String::from_utf8(folded).unwrap();
+    assert!(folded.contains(&format!(
+        "{0}:bump;begin;var-set;+;{0}:double;* ",
+        &contract_id
+    )));
+    let runtime: u64 = folded
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().1.parse::<u64>().unwrap())
+        .sum();
+    assert_eq!(runtime, cost.runtime);
+
+    let mut table = vec![];
+    profiler.write_table(&mut table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    let lines: Vec<_> = table.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert!(lines[0].starts_with("function "));
+    assert!(lines[1].starts_with(&format!("{}:bump ", &contract_id)));
+    assert!(lines[4].starts_with("total "));
+}
@@ -27,7 +27,9 @@ use db::blocks::DummyEventDispatcher;
 use db::ChainstateTx;
 use regex::Regex;
 use rusqlite::{Connection, OpenFlags};
-use stacks_common::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash, StacksBlockId};
+use stacks_common::types::chainstate::{
+    BlockHeaderHash, BurnchainHeaderHash, StacksBlockId, TrieHash,
+};
 use stacks_common::types::sqlite::NO_PARAMS;
 use stacks_common::util::get_epoch_time_ms;
 use stacks_common::util::hash::Hash160;
@@ -39,19 +41,28 @@ use crate::chainstate::burn::db::sortdb::{
     get_ancestor_sort_id, SortitionDB, SortitionHandle, SortitionHandleContext,
 };
 use crate::chainstate::burn::{BlockSnapshot, ConsensusHash};
-use crate::chainstate::coordinator::OnChainRewardSetProvider;
+use crate::chainstate::coordinator::{
+    calculate_paid_rewards, BlockEventDispatcher, OnChainRewardSetProvider,
+};
 use crate::chainstate::nakamoto::miner::{BlockMetadata, NakamotoBlockBuilder, NakamotoTenureInfo};
 use crate::chainstate::nakamoto::{NakamotoBlock, NakamotoChainState};
+use crate::chainstate::stacks::boot::RewardSetData;
 use crate::chainstate::stacks::db::blocks::StagingBlock;
+use crate::chainstate::stacks::db::repair::repair_chainstate_marfs;
+use crate::chainstate::stacks::db::snapshot::export_snapshot;
 use crate::chainstate::stacks::db::{StacksBlockHeaderTypes, StacksChainState, StacksHeaderInfo};
+use crate::chainstate::stacks::index::lsm::migrate_marf_storage;
+use crate::chainstate::stacks::index::storage::MARFStorageBackend;
+use crate::chainstate::stacks::index::verify::verify_marf_blobs;
 use crate::chainstate::stacks::miner::*;
 use crate::chainstate::stacks::{Error as ChainstateError, *};
 use crate::clarity_vm::clarity::ClarityInstance;
 use crate::clarity_vm::database::GetTenureStartId;
-use crate::config::{Config, ConfigFile, DEFAULT_MAINNET_CONFIG};
+use crate::config::{Config, ConfigFile, EventObserverConfig, DEFAULT_MAINNET_CONFIG};
 use crate::core::*;
 use crate::cost_estimates::metrics::UnitMetric;
 use crate::cost_estimates::UnitEstimator;
+use crate::event_dispatcher::EventDispatcher;
 use crate::util_lib::db::IndexDBTx;
 
 /// Options common to many `stacks-inspect` subcommands
@@ -197,6 +208,337 @@ pub fn command_replay_block(argv: &[String], conf: Option<&Config>) {
     println!("Finished. run_time_seconds = {}", start.elapsed().as_secs());
 }
 
+/// Export a snapshot of a stopped node's chainstate, which other nodes can start from
+/// Terminates on error using `process::exit()`
+///
+/// Arguments:
+///  - `argv`: Args in CLI format: `<command-name> [args...]`
+pub fn command_export_snapshot(argv: &[String], conf: Option<&Config>) {
+    let print_help_and_exit = || -> ! {
+        let n = &argv[0];
+        eprintln!("Usage:");
+        eprintln!("  {n} <database-path> <snapshot-dir> [<tenure-consensus-hash>]");
+        process::exit(1);
+    };
+    let db_path = argv.get(1).unwrap_or_else(|| print_help_and_exit());
+    let snapshot_dir = argv.get(2).unwrap_or_else(|| print_help_and_exit());
+    let tenure = argv.get(3).map(|consensus_hash| {
+        ConsensusHash::from_hex(consensus_hash).unwrap_or_else(|_| print_help_and_exit())
+    });
+
+    let conf = conf.unwrap_or(&DEFAULT_MAINNET_CONFIG);
+    let mut burnchain = conf.get_burnchain();
+    burnchain.working_dir = format!("{db_path}/burnchain");
+    let chainstate_path = format!("{db_path}/chainstate");
+
+    match export_snapshot(
+        &burnchain,
+        &chainstate_path,
+        Path::new(snapshot_dir),
+        tenure.as_ref(),
+    ) {
+        Ok(manifest) => {
+            println!("{}", serde_json::to_string_pretty(&manifest).unwrap());
+        }
+        Err(e) => {
+            eprintln!("Failed to export snapshot: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Check a stopped node's chainstate MARFs for tries which were left inconsistent by a crash,
+/// and optionally truncate them away, so the node processes their blocks again when it restarts
+/// Terminates on error using `process::exit()`
+///
+/// Arguments:
+///  - `argv`: Args in CLI format: `<command-name> [args...]`
+pub fn command_marf_verify(argv: &[String]) {
+    let print_help_and_exit = || -> ! {
+        let n = &argv[0];
+        eprintln!("Usage:");
+        eprintln!("  {n} <database-path> [clarity|headers] [--truncate]");
+        process::exit(1);
+    };
+    let db_path = argv.get(1).unwrap_or_else(|| print_help_and_exit());
+    let mut truncate = false;
+    let mut check_clarity = true;
+    let mut check_headers = true;
+    for arg in argv[2..].iter() {
+        match arg.as_str() {
+            "--truncate" => truncate = true,
+            "clarity" => check_headers = false,
+            "headers" => check_clarity = false,
+            _ => print_help_and_exit(),
+        }
+    }
+    if !check_clarity && !check_headers {
+        print_help_and_exit();
+    }
+
+    let chainstate_path = PathBuf::from(format!("{db_path}/chainstate"));
+    let headers_path = StacksChainState::header_index_root_path(chainstate_path.clone());
+    let headers_path = headers_path.to_str().unwrap();
+    let clarity_path = StacksChainState::vm_state_index_marf_path(chainstate_path.clone());
+    let clarity_path = clarity_path.to_str().unwrap();
+    let headers_db = Connection::open_with_flags(headers_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to open {headers_path}: {e}");
+            process::exit(1);
+        });
+
+    let check_header_root = |block_id: &StacksBlockId,
+                             root_hash: &TrieHash,
+                             header_root: fn(&StacksHeaderInfo) -> TrieHash|
+     -> Option<String> {
+        match NakamotoChainState::get_block_header(&headers_db, block_id) {
+            // the genesis block's header does not commit to its tries' root hashes
+            Ok(Some(header)) if header.stacks_block_height == 0 => None,
+            Ok(Some(header)) if &header_root(&header) == root_hash => None,
+            Ok(Some(header)) => Some(format!(
+                "root hash {root_hash} does not match the block header's {}",
+                header_root(&header)
+            )),
+            Ok(None) => Some("no block header for this trie".into()),
+            Err(e) => Some(format!("failed to load block header: {e}")),
+        }
+    };
+
+    let verify = |path: &str, header_root: fn(&StacksHeaderInfo) -> TrieHash| {
+        println!("Verifying {path}");
+        let report = verify_marf_blobs::<StacksBlockId, _>(path, |block_id, root_hash| {
+            check_header_root(block_id, root_hash, header_root)
+        })
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to verify {path}: {e}");
+            process::exit(1);
+        });
+        for bad in report.bad_tries.iter() {
+            println!(
+                "  bad trie {} (block ID {}) at offset {} length {}: {}",
+                &bad.block_hash, bad.block_id, bad.offset, bad.length, &bad.reason
+            );
+        }
+        println!(
+            "  {} tries, {} bad; {} bytes, consistent up to {}",
+            report.num_tries,
+            report.bad_tries.len(),
+            report.blobs_len,
+            report.tail_offset
+        );
+        if !report.is_ok() && !report.is_repairable() {
+            println!(
+                "  inconsistent tries precede good ones, so truncation cannot repair this MARF"
+            );
+        }
+        report
+    };
+
+    let clarity_report = check_clarity.then(|| {
+        verify(clarity_path, |header| match &header.anchored_header {
+            StacksBlockHeaderTypes::Epoch2(header) => header.state_index_root,
+            StacksBlockHeaderTypes::Nakamoto(header) => header.state_index_root,
+        })
+    });
+    let headers_report = check_headers.then(|| verify(headers_path, |header| header.index_root));
+    drop(headers_db);
+    let all_ok = clarity_report
+        .iter()
+        .chain(headers_report.iter())
+        .all(|r| r.is_ok());
+    if all_ok {
+        return;
+    }
+    let (Some(clarity_report), Some(headers_report)) = (clarity_report, headers_report) else {
+        // truncating one MARF would leave the other one with tries for blocks which must be
+        // processed again
+        println!("Re-run without [clarity|headers] and with --truncate to repair the MARFs");
+        process::exit(1);
+    };
+    if !truncate {
+        println!("Re-run with --truncate to remove the inconsistent tails");
+        process::exit(1);
+    }
+    let sort_db_path = format!("{db_path}/burnchain/sortition");
+    match repair_chainstate_marfs(
+        chainstate_path.to_str().unwrap(),
+        &sort_db_path,
+        &clarity_report,
+        &headers_report,
+    ) {
+        Ok(block_ids) => {
+            println!(
+                "Truncated the MARFs; {} blocks will be processed again when the node restarts",
+                block_ids.len()
+            );
+        }
+        Err(e) => {
+            eprintln!("Failed to repair the MARFs: {e}");
+            process::exit(1);
+        }
+    }
+}
+
+/// Move the Clarity MARF's trie blobs to another storage backend
+/// Terminates on error using `process::exit()`
+///
+/// Arguments:
+///  - `argv`: Args in CLI format: `<command-name> [args...]`
+pub fn command_migrate_marf_storage(argv: &[String]) {
+    let print_help_and_exit = || -> ! {
+        let n = &argv[0];
+        eprintln!("Usage:");
+        eprintln!("  {n} <database-path> <sqlite|lsm>");
+        process::exit(1);
+    };
+    if argv.len() != 3 {
+        print_help_and_exit();
+    }
+    let db_path = &argv[1];
+    let backend = argv[2]
+        .parse::<MARFStorageBackend>()
+        .unwrap_or_else(|_| print_help_and_exit());
+
+    let chainstate_path = PathBuf::from(format!("{db_path}/chainstate"));
+    let clarity_path = StacksChainState::vm_state_index_marf_path(chainstate_path);
+    let clarity_path = clarity_path.to_str().unwrap();
+    let stats = migrate_marf_storage(clarity_path, backend).unwrap_or_else(|e| {
+        eprintln!("Failed to migrate {clarity_path}: {e}");
+        process::exit(1);
+    });
+    println!(
+        "Migrated {clarity_path} to the {} storage backend: {} tries ({} bytes)",
+        backend.as_str(),
+        stats.tries,
+        stats.trie_bytes
+    );
+    println!(
+        "Set `node.marf_storage_backend = \"{}\"` before restarting the node",
+        backend.as_str()
+    );
+}
+
+/// Write a stopped node's pending mempool transactions to a portable file
+/// Terminates on error using `process::exit()`
+///
+/// Arguments:
+///  - `argv`: Args in CLI format: `<command-name> [args...]`
+pub fn command_export_mempool(argv: &[String], conf: Option<&Config>) {
+    let print_help_and_exit = || -> ! {
+        let n = &argv[0];
+        eprintln!("Usage:");
+        eprintln!("  {n} <database-path> <export-file>");
+        process::exit(1);
+    };
+    if argv.len() != 3 {
+        print_help_and_exit();
+    }
+    let db_path = &argv[1];
+    let export_path = &argv[2];
+
+    let conf = conf.unwrap_or(&DEFAULT_MAINNET_CONFIG);
+    let chain_state_path = format!("{db_path}/chainstate/");
+    // `MemPoolDB::open()` would create an empty mempool (and chainstate) if there were none
+    let mempool_path = MemPoolDB::db_path(&chain_state_path).unwrap_or_else(|e| {
+        eprintln!("Invalid mempool db path under {chain_state_path}: {e}");
+        process::exit(1);
+    });
+    if !Path::new(&mempool_path).is_file() {
+        eprintln!("No mempool db at {mempool_path}");
+        process::exit(1);
+    }
+    let mempool_db =
+        MemPoolDB::open_db(&mempool_path, Box::new(UnitEstimator), Box::new(UnitMetric))
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to open mempool db: {e}");
+                process::exit(1);
+            });
+
+    let num_txs = mempool_db
+        .export_txs(Path::new(export_path))
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to export mempool to {export_path}: {e}");
+            process::exit(1);
+        });
+    println!("Exported {num_txs} mempool transactions to {export_path}");
+}
+
+/// Admit the transactions in a file written by `export-mempool` to a stopped node's mempool,
+/// at its canonical Stacks chain tip
+/// Terminates on error using `process::exit()`
+///
+/// Arguments:
+///  - `argv`: Args in CLI format: `<command-name> [args...]`
+pub fn command_import_mempool(argv: &[String], conf: Option<&Config>) {
+    let print_help_and_exit = || -> ! {
+        let n = &argv[0];
+        eprintln!("Usage:");
+        eprintln!("  {n} <database-path> <export-file>");
+        process::exit(1);
+    };
+    if argv.len() != 3 {
+        print_help_and_exit();
+    }
+    let db_path = &argv[1];
+    let export_path = &argv[2];
+
+    let conf = conf.unwrap_or(&DEFAULT_MAINNET_CONFIG);
+    let sort_db_path = format!("{db_path}/burnchain/sortition");
+    let chain_state_path = format!("{db_path}/chainstate/");
+
+    let burnchain = conf.get_burnchain();
+    let sort_db =
+        SortitionDB::open(&sort_db_path, false, burnchain.pox_constants).unwrap_or_else(|e| {
+            eprintln!("Failed to open {sort_db_path}: {e}");
+            process::exit(1);
+        });
+    let (mut chainstate, _) = StacksChainState::open(
+        conf.is_mainnet(),
+        conf.burnchain.chain_id,
+        &chain_state_path,
+        None,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("Failed to open stacks chain state: {e}");
+        process::exit(1);
+    });
+    let mut mempool_db = MemPoolDB::open(
+        conf.is_mainnet(),
+        conf.burnchain.chain_id,
+        &chain_state_path,
+        Box::new(UnitEstimator),
+        Box::new(UnitMetric),
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("Failed to open mempool db: {e}");
+        process::exit(1);
+    });
+    mempool_db.set_admission_policy(conf.node.mempool_admission_policy.clone());
+
+    let (consensus_hash, block_hash) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db.conn()).unwrap_or_else(|e| {
+            eprintln!("Failed to get canonical Stacks chain tip: {e}");
+            process::exit(1);
+        });
+
+    let stats = mempool_db
+        .import_txs(
+            &mut chainstate,
+            &sort_db,
+            &consensus_hash,
+            &block_hash,
+            Path::new(export_path),
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to import mempool from {export_path}: {e}");
+            process::exit(1);
+        });
+    println!(
+        "Imported {} mempool transactions at {consensus_hash}/{block_hash}: {} already present, {} rejected",
+        stats.imported, stats.already_present, stats.rejected
+    );
+}
+
 /// Replay blocks from chainstate database
 /// Terminates on error using `process::exit()`
 ///
@@ -285,6 +627,204 @@ pub fn command_replay_block_nakamoto(argv: &[String], conf: Option<&Config>) {
     println!("Finished. run_time_seconds = {}", start.elapsed().as_secs());
 }
 
+/// Re-send the `/new_block` or `/new_burn_block` payloads of processed blocks to the event
+/// observers in the config, so that they receive the same payloads that the node sent when it
+/// first processed them. Stacks blocks are re-executed against the chainstate (the results are
+/// discarded) in order to regenerate their receipts. Heights are inclusive, and blocks are taken
+/// from the canonical Stacks fork (or canonical burnchain fork, for burn blocks).
+/// Terminates on error using `process::exit()`
+///
+/// Arguments:
+///  - `argv`: Args in CLI format: `<command-name> [args...]`
+///  - `conf`: Config with the `[[events_observer]]` entries to send the payloads to
+pub fn command_replay_events(argv: &[String], conf: Option<&Config>) {
+    let print_help_and_exit = || -> ! {
+        let n = &argv[0];
+        eprintln!("Usage:");
+        eprintln!("  {n} <database-path> blocks <start-height> <end-height>");
+        eprintln!("  {n} <database-path> burn-blocks <start-height> <end-height>");
+        eprintln!("The event observers are taken from the config passed with --config");
+        process::exit(1);
+    };
+    let start = Instant::now();
+    let db_path = argv.get(1).unwrap_or_else(|| print_help_and_exit());
+    let mode = argv.get(2).map(String::as_str);
+    let start_height = argv
+        .get(3)
+        .map(|h| h.parse::<u64>().expect("<start-height> not a valid u64"))
+        .unwrap_or_else(|| print_help_and_exit());
+    let end_height = argv
+        .get(4)
+        .map(|h| h.parse::<u64>().expect("<end-height> not a valid u64"))
+        .unwrap_or_else(|| print_help_and_exit());
+
+    let Some(conf) = conf.filter(|conf| !conf.events_observers.is_empty()) else {
+        eprintln!("No [[events_observer]] entries in the config");
+        print_help_and_exit();
+    };
+
+    // keep replayed payloads out of the live node's pending-payloads database
+    let working_dir = PathBuf::from(db_path).join("replay-events");
+    fs::create_dir_all(&working_dir).unwrap_or_else(|e| {
+        panic!("Failed to create {}: {e}", working_dir.display());
+    });
+    let mut dispatcher = EventDispatcher::new();
+    for observer in conf.events_observers.iter() {
+        // deliver synchronously, so that every payload is sent before we exit
+        let observer = EventObserverConfig {
+            async_delivery: false,
+            ..observer.clone()
+        };
+        dispatcher.register_observer(&observer, working_dir.clone());
+    }
+
+    let (mut chainstate, mut sortdb, burnchain_blocks_db) = open_replay_dbs(db_path, conf);
+    match mode {
+        Some("blocks") => replay_block_events(
+            &mut chainstate,
+            &mut sortdb,
+            &burnchain_blocks_db,
+            start_height,
+            end_height,
+            &dispatcher,
+        ),
+        Some("burn-blocks") => replay_burn_block_events(
+            &sortdb,
+            &burnchain_blocks_db,
+            start_height,
+            end_height,
+            &dispatcher,
+        ),
+        _ => print_help_and_exit(),
+    }
+    println!("Finished. run_time_seconds = {}", start.elapsed().as_secs());
+}
+
+/// Open the chainstate, sortition DB and burnchain DB under `db_path`
+fn open_replay_dbs(db_path: &str, conf: &Config) -> (StacksChainState, SortitionDB, BurnchainDB) {
+    let chain_state_path = format!("{db_path}/chainstate/");
+    let sort_db_path = format!("{db_path}/burnchain/sortition");
+    let burn_db_path = format!("{db_path}/burnchain/burnchain.sqlite");
+    let burnchain_blocks_db = BurnchainDB::open(&burn_db_path, false).unwrap();
+
+    let (chainstate, _) = StacksChainState::open(
+        conf.is_mainnet(),
+        conf.burnchain.chain_id,
+        &chain_state_path,
+        None,
+    )
+    .unwrap();
+
+    let burnchain = conf.get_burnchain();
+    let epochs = conf.burnchain.get_epoch_list();
+    let sortdb = SortitionDB::connect(
+        &sort_db_path,
+        burnchain.first_block_height,
+        &burnchain.first_block_hash,
+        u64::from(burnchain.first_block_timestamp),
+        &epochs,
+        burnchain.pox_constants.clone(),
+        None,
+        true,
+    )
+    .unwrap();
+    (chainstate, sortdb, burnchain_blocks_db)
+}
+
+/// Re-execute the canonical Stacks blocks in `[start_height, end_height]` and announce them
+fn replay_block_events<T: BlockEventDispatcher>(
+    chainstate: &mut StacksChainState,
+    sortdb: &mut SortitionDB,
+    burnchain_blocks_db: &BurnchainDB,
+    start_height: u64,
+    end_height: u64,
+    dispatcher: &T,
+) {
+    let tip_block_id = sortdb.get_canonical_stacks_tip_block_id();
+    println!("Will replay blocks {start_height} through {end_height} from tip {tip_block_id}");
+    for height in start_height..=end_height {
+        let Some(block_id) = chainstate
+            .index_conn()
+            .get_ancestor_block_hash(height, &tip_block_id)
+            .unwrap()
+        else {
+            println!("No block at height {height} in the canonical fork; stopping");
+            break;
+        };
+        let header = NakamotoChainState::get_block_header(chainstate.db(), &block_id)
+            .unwrap()
+            .unwrap_or_else(|| panic!("No header for canonical block {block_id}"));
+
+        match header.anchored_header {
+            StacksBlockHeaderTypes::Nakamoto(_) => {
+                let (block, block_size) = chainstate
+                    .nakamoto_blocks_db()
+                    .get_nakamoto_block(&block_id)
+                    .unwrap()
+                    .unwrap_or_else(|| panic!("No stored Nakamoto block {block_id}"));
+                replay_block_nakamoto(sortdb, chainstate, &block, block_size, Some(dispatcher))
+                    .unwrap();
+            }
+            StacksBlockHeaderTypes::Epoch2(_) => {
+                replay_staging_block_in(
+                    chainstate,
+                    sortdb,
+                    burnchain_blocks_db,
+                    &block_id,
+                    Some(dispatcher),
+                );
+            }
+        }
+        println!("Replayed block {height} ({block_id})");
+    }
+}
+
+/// Announce the canonical burnchain blocks in `[start_height, end_height]`
+fn replay_burn_block_events<T: BlockEventDispatcher>(
+    sortdb: &SortitionDB,
+    burnchain_blocks_db: &BurnchainDB,
+    start_height: u64,
+    end_height: u64,
+    dispatcher: &T,
+) {
+    let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn()).unwrap();
+    let ic = sortdb.index_conn();
+    println!(
+        "Will replay burn blocks {start_height} through {end_height} from tip {}",
+        &tip.burn_header_hash
+    );
+    for height in start_height..=end_height {
+        let Some(snapshot) =
+            SortitionDB::get_ancestor_snapshot(&ic, height, &tip.sortition_id).unwrap()
+        else {
+            println!("No burn block at height {height} in the canonical fork; stopping");
+            break;
+        };
+        let burn_block = BurnchainDB::get_burnchain_block(
+            burnchain_blocks_db.conn(),
+            &snapshot.burn_header_hash,
+        )
+        .unwrap();
+        let paid_rewards = calculate_paid_rewards(&burn_block.ops);
+        let (reward_recipients, _) = ic
+            .get_reward_set_payouts_at(&snapshot.sortition_id)
+            .unwrap();
+
+        dispatcher.announce_burn_block(
+            &snapshot.burn_header_hash,
+            snapshot.block_height,
+            paid_rewards.pox,
+            paid_rewards.burns,
+            reward_recipients,
+            &snapshot.consensus_hash,
+        );
+        println!(
+            "Replayed burn block {height} ({})",
+            &snapshot.burn_header_hash
+        );
+    }
+}
+
 /// Replay mock mined blocks from JSON files
 /// Terminates on error using `process::exit()`
 ///
@@ -554,34 +1094,26 @@ pub fn command_try_mine(argv: &[String], conf: Option<&Config>) {
 /// Fetch and process a `StagingBlock` from database and call `replay_block()` to validate
 fn replay_staging_block(db_path: &str, index_block_hash_hex: &str, conf: Option<&Config>) {
     let block_id = StacksBlockId::from_hex(index_block_hash_hex).unwrap();
-    let chain_state_path = format!("{db_path}/chainstate/");
-    let sort_db_path = format!("{db_path}/burnchain/sortition");
-    let burn_db_path = format!("{db_path}/burnchain/burnchain.sqlite");
-    let burnchain_blocks_db = BurnchainDB::open(&burn_db_path, false).unwrap();
-
     let conf = conf.unwrap_or(&DEFAULT_MAINNET_CONFIG);
+    let (mut chainstate, mut sortdb, burnchain_blocks_db) = open_replay_dbs(db_path, conf);
+    replay_staging_block_in(
+        &mut chainstate,
+        &mut sortdb,
+        &burnchain_blocks_db,
+        &block_id,
+        None::<&DummyEventDispatcher>,
+    );
+}
 
-    let (mut chainstate, _) = StacksChainState::open(
-        conf.is_mainnet(),
-        conf.burnchain.chain_id,
-        &chain_state_path,
-        None,
-    )
-    .unwrap();
-
-    let burnchain = conf.get_burnchain();
-    let epochs = conf.burnchain.get_epoch_list();
-    let mut sortdb = SortitionDB::connect(
-        &sort_db_path,
-        burnchain.first_block_height,
-        &burnchain.first_block_hash,
-        u64::from(burnchain.first_block_timestamp),
-        &epochs,
-        burnchain.pox_constants.clone(),
-        None,
-        true,
-    )
-    .unwrap();
+/// Fetch a `StagingBlock` from the given databases and call `replay_block()` to validate it, and
+/// announce it to `dispatcher_opt` if given
+fn replay_staging_block_in<T: BlockEventDispatcher>(
+    chainstate: &mut StacksChainState,
+    sortdb: &mut SortitionDB,
+    burnchain_blocks_db: &BurnchainDB,
+    block_id: &StacksBlockId,
+    dispatcher_opt: Option<&T>,
+) {
     let sort_tx = sortdb.tx_begin_at_tip();
 
     let blocks_path = chainstate.blocks_path.clone();
@@ -589,7 +1121,7 @@ fn replay_staging_block(db_path: &str, index_block_hash_hex: &str, conf: Option<
         .chainstate_tx_begin()
         .expect("Failed to start chainstate tx");
     let mut next_staging_block =
-        StacksChainState::load_staging_block_info(&chainstate_tx.tx, &block_id)
+        StacksChainState::load_staging_block_info(&chainstate_tx.tx, block_id)
             .expect("Failed to load staging block data")
             .expect("No such index block hash in block database");
 
@@ -604,7 +1136,7 @@ fn replay_staging_block(db_path: &str, index_block_hash_hex: &str, conf: Option<
     let Some(parent_header_info) =
         StacksChainState::get_parent_header_info(&mut chainstate_tx, &next_staging_block).unwrap()
     else {
-        println!("Failed to load parent head info for block: {index_block_hash_hex}");
+        println!("Failed to load parent head info for block: {block_id}");
         return;
     };
 
@@ -616,17 +1148,18 @@ fn replay_staging_block(db_path: &str, index_block_hash_hex: &str, conf: Option<
         sort_tx,
         chainstate_tx,
         clarity_instance,
-        &burnchain_blocks_db,
+        burnchain_blocks_db,
         &parent_header_info,
         &next_staging_block.parent_microblock_hash,
         next_staging_block.parent_microblock_seq,
-        &block_id,
+        block_id,
         &block,
         block_size,
         &next_staging_block.consensus_hash,
         &next_staging_block.anchored_block_hash,
         next_staging_block.commit_burn,
         next_staging_block.sortition_burn,
+        dispatcher_opt,
     );
 }
 
@@ -702,11 +1235,13 @@ fn replay_mock_mined_block(db_path: &str, block: AssembledAnchorBlock, conf: Opt
         // I think the burn is used for miner rewards but not necessary for validation
         0,
         0,
+        None::<&DummyEventDispatcher>,
     );
 }
 
-/// Validate a block against chainstate
-fn replay_block(
+/// Validate a block against chainstate, and announce it to `dispatcher_opt` if given
+#[allow(clippy::too_many_arguments)]
+fn replay_block<T: BlockEventDispatcher>(
     mut sort_tx: IndexDBTx<SortitionHandleContext, SortitionId>,
     mut chainstate_tx: ChainstateTx,
     clarity_instance: &mut ClarityInstance,
@@ -721,6 +1256,7 @@ fn replay_block(
     block_hash: &BlockHeaderHash,
     block_commit_burn: u64,
     block_sortition_burn: u64,
+    dispatcher_opt: Option<&T>,
 ) {
     let parent_block_header = match &parent_header_info.anchored_header {
         StacksBlockHeaderTypes::Epoch2(bh) => bh,
@@ -748,7 +1284,7 @@ fn replay_block(
         return;
     };
 
-    let (burn_header_hash, burn_header_height, burn_header_timestamp, _winning_block_txid) =
+    let (burn_header_hash, burn_header_height, burn_header_timestamp, winning_block_txid) =
         match SortitionDB::get_block_snapshot_consensus(&sort_tx, block_consensus_hash).unwrap() {
             Some(sn) => (
                 sn.burn_header_hash,
@@ -812,6 +1348,21 @@ fn replay_block(
 
     let pox_constants = sort_tx.context.pox_constants.clone();
 
+    // `append_block()` reports a placeholder header and no reward set when it doesn't advance
+    // the chain tip, so announce the ones stored when the block was first processed
+    let header_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+        chainstate_tx.conn(),
+        block_id,
+    )
+    .unwrap()
+    .unwrap_or_else(|| panic!("No header info found for {block_id}"));
+    let reward_set_data = stored_reward_set_data(
+        chainstate_tx.conn(),
+        &pox_constants,
+        sort_tx.context.first_block_height,
+        &header_info,
+    );
+
     match StacksChainState::append_block(
         &mut chainstate_tx,
         clarity_instance,
@@ -830,7 +1381,9 @@ fn replay_block(
         block_am.weight(),
         true,
     ) {
-        Ok((receipt, _, _)) => {
+        Ok((receipt, clarity_commit, _)) => {
+            // the block has already been processed; don't leave the MARF open at it
+            clarity_commit.rollback();
             if receipt.anchored_block_cost != cost {
                 println!("Failed processing block! block = {block_id}. Unexpected cost. expected = {cost}, evaluated = {}",
                          receipt.anchored_block_cost);
@@ -838,6 +1391,31 @@ fn replay_block(
             }
 
             info!("Block processed successfully! block = {block_id}");
+
+            // same as in `StacksChainState::process_next_staging_block()`
+            if let Some(dispatcher) = dispatcher_opt {
+                let parent_id =
+                    StacksBlockId::new(&parent_header_info.consensus_hash, &parent_block_hash);
+                dispatcher.announce_block(
+                    &block.clone().into(),
+                    &header_info,
+                    &receipt.tx_receipts,
+                    &parent_id,
+                    winning_block_txid,
+                    &receipt.matured_rewards,
+                    receipt.matured_rewards_info.as_ref(),
+                    receipt.parent_burn_block_hash,
+                    receipt.parent_burn_block_height,
+                    receipt.parent_burn_block_timestamp,
+                    &receipt.anchored_block_cost,
+                    &receipt.parent_microblocks_cost,
+                    &pox_constants,
+                    &reward_set_data,
+                    &None,
+                    None,
+                    header_info.stacks_block_height,
+                );
+            }
         }
         Err(e) => {
             println!("Failed processing block! block = {block_id}, error = {e:?}");
@@ -879,14 +1457,23 @@ fn replay_naka_staging_block(db_path: &str, index_block_hash_hex: &str, conf: &C
         .get_nakamoto_block(&block_id)
         .unwrap()
         .unwrap();
-    replay_block_nakamoto(&mut sortdb, &mut chainstate, &block, block_size).unwrap();
+    replay_block_nakamoto(
+        &mut sortdb,
+        &mut chainstate,
+        &block,
+        block_size,
+        None::<&DummyEventDispatcher>,
+    )
+    .unwrap();
 }
 
-fn replay_block_nakamoto(
+/// Validate a Nakamoto block against chainstate, and announce it to `dispatcher_opt` if given
+fn replay_block_nakamoto<T: BlockEventDispatcher>(
     sort_db: &mut SortitionDB,
     stacks_chain_state: &mut StacksChainState,
     block: &NakamotoBlock,
     block_size: u64,
+    dispatcher_opt: Option<&T>,
 ) -> Result<(), ChainstateError> {
     // find corresponding snapshot
     let next_ready_block_snapshot =
@@ -963,6 +1550,7 @@ fn replay_block_nakamoto(
             );
             ChainstateError::NoSuchBlockError
         })?;
+    let chainstate_config = stacks_chain_state.config();
     let (mut chainstate_tx, clarity_instance) = stacks_chain_state.chainstate_tx_begin()?;
 
     // find parent header
@@ -1121,6 +1709,18 @@ fn replay_block_nakamoto(
     // to access `stacks_chain_state` again.  In the `Ok(..)` case, it's instead sufficient so
     // simply commit the block before beginning the second transaction to mark it processed.
     let block_id = block.block_id();
+
+    // `append_block()` reports a placeholder header and no reward set when it doesn't advance
+    // the chain tip, so announce the ones stored when the block was first processed
+    let header_info = NakamotoChainState::get_block_header(&chainstate_tx.tx, &block_id)?
+        .ok_or_else(|| ChainstateError::NoSuchBlockError)?;
+    let reward_set_data = stored_reward_set_data(
+        &chainstate_tx.tx,
+        &pox_constants,
+        sort_db.first_block_height,
+        &header_info,
+    );
+
     let mut burn_view_handle = sort_db.index_handle(&burnchain_view_sn.sortition_id);
     let (ok_opt, err_opt) = match NakamotoChainState::append_block(
         &mut chainstate_tx,
@@ -1142,17 +1742,56 @@ fn replay_block_nakamoto(
         &active_reward_set,
         true,
     ) {
-        Ok((receipt, _, _, _)) => (Some(receipt), None),
+        Ok((receipt, clarity_commit, _, phantom_unlock_events)) => {
+            // the block has already been processed; don't leave the MARF open at it
+            clarity_commit.rollback();
+            (Some((receipt, phantom_unlock_events)), None)
+        }
         Err(e) => (None, Some(e)),
     };
 
-    if let Some(receipt) = ok_opt {
+    if let Some((mut receipt, phantom_unlock_events)) = ok_opt {
         // check the cost
         let evaluated_cost = receipt.anchored_block_cost.clone();
         if evaluated_cost != expected_cost {
             println!("Failed processing block! block = {block_id}. Unexpected cost. expected = {expected_cost}, evaluated = {evaluated_cost}");
             process::exit(1);
         }
+
+        // same as in `NakamotoChainState::process_next_nakamoto_block()`
+        if let Some(dispatcher) = dispatcher_opt {
+            if let Some(unlock_receipt) = NakamotoChainState::generate_phantom_unlock_tx(
+                phantom_unlock_events,
+                &chainstate_config,
+                block.header.chain_length,
+            ) {
+                receipt.tx_receipts.push(unlock_receipt);
+            }
+            let block_event = (
+                block.clone(),
+                parent_header_info.anchored_header.block_hash(),
+            )
+                .into();
+            dispatcher.announce_block(
+                &block_event,
+                &header_info,
+                &receipt.tx_receipts,
+                &parent_block_id,
+                next_ready_block_snapshot.winning_block_txid,
+                &receipt.matured_rewards,
+                receipt.matured_rewards_info.as_ref(),
+                receipt.parent_burn_block_hash,
+                receipt.parent_burn_block_height,
+                receipt.parent_burn_block_timestamp,
+                &receipt.anchored_block_cost,
+                &receipt.parent_microblocks_cost,
+                &pox_constants,
+                &reward_set_data,
+                &Some(block.header.pox_treatment.clone()),
+                Some(block.header.timestamp),
+                receipt.coinbase_height,
+            );
+        }
     }
 
     if let Some(e) = err_opt {
@@ -1179,9 +1818,39 @@ fn replay_block_nakamoto(
     Ok(())
 }
 
+/// Rebuild the `RewardSetData` that was announced with a processed block, from the reward set
+/// stored for it (if the block calculated one)
+fn stored_reward_set_data(
+    chainstate_conn: &Connection,
+    pox_constants: &PoxConstants,
+    first_block_height: u64,
+    header_info: &StacksHeaderInfo,
+) -> Option<RewardSetData> {
+    let reward_set =
+        NakamotoChainState::get_reward_set(chainstate_conn, &header_info.index_block_hash())
+            .unwrap()?;
+    // same as in `append_block()`
+    let burn_height = u64::from(header_info.burn_header_height);
+    let cycle = pox_constants
+        .reward_cycle_of_prepare_phase(first_block_height, burn_height)
+        .or_else(|| {
+            pox_constants
+                .block_height_to_reward_cycle(first_block_height, burn_height)
+                .map(|cycle| cycle + 1)
+        })?;
+    Some(RewardSetData::new(reward_set, cycle))
+}
+
 #[cfg(test)]
 pub mod test {
+    use stacks_common::types::chainstate::BurnchainHeaderHash;
+    use tempfile::tempdir;
+
     use super::*;
+    use crate::chainstate::stacks::boot::test::instantiate_pox_peer_with_epoch;
+    use crate::config::{EventKeyType, EventSinkKind};
+    use crate::core::BITCOIN_REGTEST_FIRST_BLOCK_HASH;
+    use crate::net::test::TestEventObserver;
 
     fn parse_cli_command(s: &str) -> Vec<String> {
         s.split(' ').map(String::from).collect()
@@ -1210,4 +1879,82 @@ pub mod test {
         assert_eq!(argv, argv_expected);
         assert!(opts.config.is_some());
     }
+
+    /// Make a dispatcher with a single file-sink observer writing to `events_path`
+    fn file_sink_dispatcher(
+        events_path: &std::path::Path,
+        working_dir: PathBuf,
+    ) -> EventDispatcher {
+        let mut dispatcher = EventDispatcher::new();
+        dispatcher.register_observer(
+            &EventObserverConfig {
+                endpoint: events_path.to_str().unwrap().to_string(),
+                events_keys: vec![EventKeyType::AnyEvent],
+                timeout_ms: 1_000,
+                disable_retries: true,
+                sink: EventSinkKind::File,
+                async_delivery: false,
+                filter: None,
+            },
+            working_dir,
+        );
+        dispatcher
+    }
+
+    /// Read back the `/new_block` payloads written by a file sink
+    fn read_new_block_payloads(events_path: &std::path::Path) -> Vec<serde_json::Value> {
+        fs::read_to_string(events_path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .filter(|record| record["path"] == "new_block")
+            .map(|record| record["payload"].clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_replay_block_events_matches_original_payloads() {
+        let dir = tempdir().unwrap();
+        let original_path = dir.path().join("original.ndjson");
+        let replayed_path = dir.path().join("replayed.ndjson");
+
+        let burnchain = Burnchain::default_unittest(
+            0,
+            &BurnchainHeaderHash::from_hex(BITCOIN_REGTEST_FIRST_BLOCK_HASH).unwrap(),
+        );
+        let observer = TestEventObserver::forwarding_to(file_sink_dispatcher(
+            &original_path,
+            dir.path().to_path_buf(),
+        ));
+        let (mut peer, _keys) =
+            instantiate_pox_peer_with_epoch(&burnchain, function_name!(), None, Some(&observer));
+
+        let mut coinbase_nonce = 0;
+        for _ in 0..5 {
+            peer.tenure_with_txs(&[], &mut coinbase_nonce);
+        }
+
+        let original = read_new_block_payloads(&original_path);
+        assert_eq!(original.len(), 5);
+
+        let replay_dispatcher = file_sink_dispatcher(&replayed_path, dir.path().to_path_buf());
+        let burnchain_blocks_db =
+            BurnchainDB::open(&peer.config.burnchain.get_burnchaindb_path(), false).unwrap();
+        let mut sortdb = peer.sortdb.take().unwrap();
+        replay_block_events(
+            peer.chainstate(),
+            &mut sortdb,
+            &burnchain_blocks_db,
+            1,
+            5,
+            &replay_dispatcher,
+        );
+
+        let replayed = read_new_block_payloads(&replayed_path);
+        assert_eq!(replayed, original);
+        for (payload, height) in replayed.iter().zip(1..) {
+            assert_eq!(payload["block_height"], height);
+            assert_eq!(payload["tenure_height"], height);
+        }
+    }
 }
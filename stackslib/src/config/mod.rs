@@ -41,12 +41,14 @@ use crate::burnchains::bitcoin::BitcoinNetworkType;
 use crate::burnchains::{Burnchain, MagicBytes, PoxConstants, BLOCKSTACK_MAGIC_MAINNET};
 use crate::chainstate::nakamoto::signer_set::NakamotoSigners;
 use crate::chainstate::stacks::boot::MINERS_NAME;
+use crate::chainstate::stacks::db::pruning::MIN_PRUNE_REWARD_CYCLES;
 use crate::chainstate::stacks::index::marf::MARFOpenOpts;
-use crate::chainstate::stacks::index::storage::TrieHashCalculationMode;
+use crate::chainstate::stacks::index::storage::{MARFStorageBackend, TrieHashCalculationMode};
 use crate::chainstate::stacks::miner::{BlockBuilderSettings, MinerStatus};
 use crate::chainstate::stacks::MAX_BLOCK_LEN;
 use crate::config::chain_data::MinerStats;
-use crate::core::mempool::{MemPoolWalkSettings, MemPoolWalkTxTypes};
+use crate::core::mempool::{MemPoolAdmissionPolicy, MemPoolWalkSettings, MemPoolWalkTxTypes};
+use crate::core::selection::TxSelectionStrategy;
 use crate::core::{
     MemPoolDB, StacksEpoch, StacksEpochExtension, StacksEpochId,
     BITCOIN_TESTNET_FIRST_BLOCK_HEIGHT, BITCOIN_TESTNET_STACKS_25_BURN_HEIGHT,
@@ -590,13 +592,15 @@ impl Config {
             .make_cost_metric()
             .unwrap_or_else(|| Box::new(UnitMetric));
 
-        MemPoolDB::open(
+        let mut mempool = MemPoolDB::open(
             self.is_mainnet(),
             self.burnchain.chain_id,
             &self.get_chainstate_path_str(),
             cost_estimator,
             metric,
-        )
+        )?;
+        mempool.set_admission_policy(self.node.mempool_admission_policy.clone());
+        Ok(mempool)
     }
 
     /// Load up a Burnchain and apply config settings to it.
@@ -911,11 +915,28 @@ impl Config {
                         .map(|e| EventKeyType::from_string(e).unwrap())
                         .collect();
 
+                    let sink = match observer.sink {
+                        Some(raw_sink) => {
+                            EventSinkKind::from_string(&raw_sink).ok_or_else(|| {
+                                format!("Unknown event observer sink kind: {raw_sink}")
+                            })?
+                        }
+                        None => EventSinkKind::Http,
+                    };
+
+                    let filter = observer
+                        .filter
+                        .map(EventObserverFilterConfigFile::into_config)
+                        .transpose()?;
+
                     observers.insert(EventObserverConfig {
                         endpoint: observer.endpoint,
                         events_keys,
                         timeout_ms: observer.timeout_ms.unwrap_or(1_000),
                         disable_retries: observer.disable_retries.unwrap_or(false),
+                        sink,
+                        async_delivery: observer.async_delivery.unwrap_or(false),
+                        filter,
                     });
                 }
                 observers
@@ -930,6 +951,9 @@ impl Config {
                 events_keys: vec![EventKeyType::AnyEvent],
                 timeout_ms: 1_000,
                 disable_retries: false,
+                sink: EventSinkKind::Http,
+                async_delivery: false,
+                filter: None,
             });
         };
 
@@ -1101,6 +1125,8 @@ impl Config {
                 filter_origins: miner_config.filter_origins,
                 tenure_cost_limit_per_block_percentage: miner_config
                     .tenure_cost_limit_per_block_percentage,
+                selection_policy: miner_config.tx_selection_policy.make_policy(),
+                max_selection_candidates: miner_config.tx_selection_max_candidates,
             },
             miner_status,
             confirm_microblocks: false,
@@ -1143,6 +1169,8 @@ impl Config {
                 filter_origins: miner_config.filter_origins,
                 tenure_cost_limit_per_block_percentage: miner_config
                     .tenure_cost_limit_per_block_percentage,
+                selection_policy: None,
+                max_selection_candidates: miner_config.tx_selection_max_candidates,
             },
             miner_status,
             confirm_microblocks: true,
@@ -1693,6 +1721,28 @@ pub struct NodeConfig {
     pub chain_liveness_poll_time_secs: u64,
     /// stacker DBs we replicate
     pub stacker_dbs: Vec<QualifiedContractIdentifier>,
+    /// Maintain an index of confirmed transactions by txid, which backs the
+    /// `/v3/transactions/{txid}` RPC endpoint. Defaults to false.
+    pub txindex: bool,
+    /// If set, then run as a pruned node: only keep the block data of this many recent reward
+    /// cycles, counting the current one, and discard the older blocks' chain state from the
+    /// Clarity and headers MARFs (state which later blocks still use is kept). Must be at least
+    /// `MIN_PRUNE_REWARD_CYCLES`. Defaults to None (archival node).
+    pub prune_reward_cycles: Option<u64>,
+    /// Where the Clarity MARF keeps its trie blobs: `sqlite` (a flat `.blobs` file) or `lsm` (an
+    /// embedded LSM store). Its other data stays in SQLite either way. An existing chainstate
+    /// must first be converted with `stacks-inspect migrate-marf-storage`. Defaults to `sqlite`.
+    pub marf_storage_backend: MARFStorageBackend,
+    /// Number of threads on which to speculatively execute a block's transactions when
+    /// processing or validating it. Transactions which conflict are re-executed in order, so the
+    /// result is always the same as executing them serially. Only blocks of compute-heavy contract
+    /// calls can benefit, on a machine with as many cores; blocks of simple transfers get slower.
+    /// Defaults to 1 (serial).
+    pub tx_execution_threads: usize,
+    /// Limits on the transactions the mempool admits: the minimum fee bump for replace-by-fee,
+    /// the maximum number of pending transactions per origin and per sponsor, and the maximum
+    /// total size of the mempool. Defaults to no limits.
+    pub mempool_admission_policy: MemPoolAdmissionPolicy,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -1956,6 +2006,11 @@ impl Default for NodeConfig {
             fault_injection_hide_blocks: false,
             chain_liveness_poll_time_secs: 300,
             stacker_dbs: vec![],
+            txindex: false,
+            prune_reward_cycles: None,
+            marf_storage_backend: MARFStorageBackend::Sqlite,
+            tx_execution_threads: 1,
+            mempool_admission_policy: MemPoolAdmissionPolicy::default(),
         }
     }
 }
@@ -2098,11 +2153,13 @@ impl NodeConfig {
             TrieHashCalculationMode::Immediate
         };
 
-        MARFOpenOpts::new(
+        let mut marf_opts = MARFOpenOpts::new(
             hash_mode,
             self.marf_cache_strategy.as_deref().unwrap_or("noop"),
             false,
-        )
+        );
+        marf_opts.storage_backend = self.marf_storage_backend;
+        marf_opts
     }
 }
 
@@ -2150,6 +2207,15 @@ pub struct MinerConfig {
     /// Origin addresses to whitelist when doing a mempool walk.  This is used by boosted and
     /// neutral miners to push transactions through that are important to them.
     pub filter_origins: HashSet<StacksAddress>,
+    /// Order in which the Nakamoto miner considers mempool transactions.  Any policy other than
+    /// the default loads up to `tx_selection_max_candidates` of the highest fee rate mempool
+    /// transactions into memory at the start of each mempool walk, along with their bodies if
+    /// `priority_contracts` is set.  The `package` policy also loads up to as many of their
+    /// pending ancestors.
+    pub tx_selection_policy: TxSelectionStrategy,
+    /// How many mempool transactions `tx_selection_policy` orders at the start of each mempool
+    /// walk.  Defaults to 16384.
+    pub tx_selection_max_candidates: u64,
     /// When selecting the "nicest" tip, do not consider tips that are more than this many blocks
     /// behind the highest tip.
     pub max_reorg_depth: u64,
@@ -2204,6 +2270,8 @@ impl Default for MinerConfig {
             underperform_stop_threshold: None,
             txs_to_consider: MemPoolWalkTxTypes::all(),
             filter_origins: HashSet::new(),
+            tx_selection_policy: TxSelectionStrategy::default(),
+            tx_selection_max_candidates: 16 * 1024,
             max_reorg_depth: 3,
             pre_nakamoto_mock_signing: false, // Should only default true if mining key is set
             min_time_between_blocks_ms: DEFAULT_MIN_TIME_BETWEEN_BLOCKS_MS,
@@ -2280,6 +2348,14 @@ pub struct ConnectionOptionsFile {
     pub reject_blocks_pushed: Option<bool>,
     pub stackerdb_hint_replicas: Option<String>,
     pub block_proposal_max_age_secs: Option<u64>,
+    pub enable_transaction_simulation: Option<bool>,
+    pub simulate_cost_limit_write_length: Option<u64>,
+    pub simulate_cost_limit_read_length: Option<u64>,
+    pub simulate_cost_limit_write_count: Option<u64>,
+    pub simulate_cost_limit_read_count: Option<u64>,
+    pub simulate_cost_limit_runtime: Option<u64>,
+    pub max_event_stream_subscribers: Option<u64>,
+    pub event_stream_buffer_len: Option<u64>,
 }
 
 impl ConnectionOptionsFile {
@@ -2312,6 +2388,22 @@ impl ConnectionOptionsFile {
             read_only_call_limit.runtime = x;
         };
         let default = ConnectionOptions::default();
+        let mut simulate_cost_limit = default.simulate_cost_limit.clone();
+        if let Some(x) = self.simulate_cost_limit_write_length {
+            simulate_cost_limit.write_length = x;
+        }
+        if let Some(x) = self.simulate_cost_limit_write_count {
+            simulate_cost_limit.write_count = x;
+        }
+        if let Some(x) = self.simulate_cost_limit_read_length {
+            simulate_cost_limit.read_length = x;
+        }
+        if let Some(x) = self.simulate_cost_limit_read_count {
+            simulate_cost_limit.read_count = x;
+        }
+        if let Some(x) = self.simulate_cost_limit_runtime {
+            simulate_cost_limit.runtime = x;
+        }
         Ok(ConnectionOptions {
             read_only_call_limit,
             inbox_maxlen: self
@@ -2431,6 +2523,16 @@ impl ConnectionOptionsFile {
             block_proposal_max_age_secs: self
                 .block_proposal_max_age_secs
                 .unwrap_or(DEFAULT_BLOCK_PROPOSAL_MAX_AGE_SECS),
+            enable_transaction_simulation: self
+                .enable_transaction_simulation
+                .unwrap_or(default.enable_transaction_simulation),
+            simulate_cost_limit,
+            max_event_stream_subscribers: self
+                .max_event_stream_subscribers
+                .unwrap_or(default.max_event_stream_subscribers),
+            event_stream_buffer_len: self
+                .event_stream_buffer_len
+                .unwrap_or(default.event_stream_buffer_len),
             ..default
         })
     }
@@ -2474,6 +2576,22 @@ pub struct NodeConfigFile {
     pub stacker_dbs: Option<Vec<String>>,
     /// fault injection: fail to push blocks with this probability (0-100)
     pub fault_injection_block_push_fail_probability: Option<u8>,
+    /// Maintain an index of confirmed transactions by txid
+    pub txindex: Option<bool>,
+    /// Only keep the block data and chain state of this many recent reward cycles
+    pub prune_reward_cycles: Option<u64>,
+    /// Storage backend for the Clarity MARF: `sqlite` or `lsm`
+    pub marf_storage_backend: Option<String>,
+    /// Number of threads on which to speculatively execute a block's transactions
+    pub tx_execution_threads: Option<usize>,
+    /// Minimum fee increase, in percent, for a replace-by-fee in the mempool
+    pub mempool_rbf_fee_bump_percent: Option<u64>,
+    /// Maximum number of pending mempool transactions per origin
+    pub mempool_max_txs_per_origin: Option<u64>,
+    /// Maximum number of pending mempool transactions per sponsor
+    pub mempool_max_txs_per_sponsor: Option<u64>,
+    /// Maximum total size of the mempool's transactions, in bytes
+    pub mempool_max_bytes: Option<u64>,
 }
 
 impl NodeConfigFile {
@@ -2481,6 +2599,31 @@ impl NodeConfigFile {
         let rpc_bind = self.rpc_bind.unwrap_or(default_node_config.rpc_bind);
         let miner = self.miner.unwrap_or(default_node_config.miner);
         let stacker = self.stacker.unwrap_or(default_node_config.stacker);
+        if let Some(prune_reward_cycles) = self.prune_reward_cycles {
+            if prune_reward_cycles < MIN_PRUNE_REWARD_CYCLES {
+                return Err(format!(
+                    "node.prune_reward_cycles must be at least {MIN_PRUNE_REWARD_CYCLES}"
+                ));
+            }
+        }
+        let marf_storage_backend = match self.marf_storage_backend {
+            Some(backend) => backend.parse().map_err(|_| {
+                format!("node.marf_storage_backend must be `sqlite` or `lsm`, not `{backend}`")
+            })?,
+            None => default_node_config.marf_storage_backend,
+        };
+        if self.tx_execution_threads == Some(0) {
+            return Err("node.tx_execution_threads must be at least 1".into());
+        }
+        if self.mempool_max_txs_per_origin == Some(0)
+            || self.mempool_max_txs_per_sponsor == Some(0)
+            || self.mempool_max_bytes == Some(0)
+        {
+            return Err(
+                "node.mempool_max_txs_per_origin, node.mempool_max_txs_per_sponsor and node.mempool_max_bytes must be at least 1"
+                    .into(),
+            );
+        }
         let node_config = NodeConfig {
             name: self.name.unwrap_or(default_node_config.name),
             seed: match self.seed {
@@ -2572,6 +2715,30 @@ impl NodeConfigFile {
             } else {
                 default_node_config.fault_injection_block_push_fail_probability
             },
+            txindex: self.txindex.unwrap_or(default_node_config.txindex),
+            prune_reward_cycles: self
+                .prune_reward_cycles
+                .or(default_node_config.prune_reward_cycles),
+            marf_storage_backend,
+            tx_execution_threads: self
+                .tx_execution_threads
+                .unwrap_or(default_node_config.tx_execution_threads),
+            mempool_admission_policy: MemPoolAdmissionPolicy {
+                rbf_fee_bump_percent: self.mempool_rbf_fee_bump_percent.unwrap_or(
+                    default_node_config
+                        .mempool_admission_policy
+                        .rbf_fee_bump_percent,
+                ),
+                max_txs_per_origin: self.mempool_max_txs_per_origin.or(default_node_config
+                    .mempool_admission_policy
+                    .max_txs_per_origin),
+                max_txs_per_sponsor: self.mempool_max_txs_per_sponsor.or(default_node_config
+                    .mempool_admission_policy
+                    .max_txs_per_sponsor),
+                max_bytes: self
+                    .mempool_max_bytes
+                    .or(default_node_config.mempool_admission_policy.max_bytes),
+            },
         };
         Ok(node_config)
     }
@@ -2613,6 +2780,10 @@ pub struct MinerConfigFile {
     pub underperform_stop_threshold: Option<u64>,
     pub txs_to_consider: Option<String>,
     pub filter_origins: Option<String>,
+    pub tx_selection_policy: Option<String>,
+    pub tx_selection_max_candidates: Option<u64>,
+    pub priority_sponsors: Option<String>,
+    pub priority_contracts: Option<String>,
     pub max_reorg_depth: Option<u64>,
     pub pre_nakamoto_mock_signing: Option<bool>,
     pub min_time_between_blocks_ms: Option<u64>,
@@ -2658,6 +2829,54 @@ impl MinerConfigFile {
             } else {
                 miner_default_config.tenure_cost_limit_per_block_percentage
             };
+
+        let tx_selection_policy = match self.tx_selection_policy.as_deref() {
+            Some(policy) => {
+                match policy.parse()? {
+                    TxSelectionStrategy::Priority { .. } => {
+                        let sponsors = self
+                        .priority_sponsors
+                        .as_deref()
+                        .unwrap_or_default()
+                        .split(',')
+                        .filter(|addr| !addr.is_empty())
+                        .map(|addr| {
+                            StacksAddress::from_string(addr).ok_or_else(|| {
+                                format!("miner.priority_sponsors: could not parse '{addr}' into a Stacks address")
+                            })
+                        })
+                        .collect::<Result<_, _>>()?;
+                        let contracts = self
+                        .priority_contracts
+                        .as_deref()
+                        .unwrap_or_default()
+                        .split(',')
+                        .filter(|contract| !contract.is_empty())
+                        .map(|contract| {
+                            QualifiedContractIdentifier::parse(contract).map_err(|e| {
+                                format!("miner.priority_contracts: could not parse '{contract}': {e}")
+                            })
+                        })
+                        .collect::<Result<_, _>>()?;
+                        TxSelectionStrategy::Priority {
+                            sponsors,
+                            contracts,
+                        }
+                    }
+                    strategy => strategy,
+                }
+            }
+            None => miner_default_config.tx_selection_policy,
+        };
+        if !matches!(tx_selection_policy, TxSelectionStrategy::Priority { .. })
+            && (self.priority_sponsors.is_some() || self.priority_contracts.is_some())
+        {
+            return Err(
+                "miner.priority_sponsors and miner.priority_contracts require miner.tx_selection_policy = \"priority\""
+                    .to_string(),
+            );
+        }
+
         Ok(MinerConfig {
             first_attempt_time_ms: self
                 .first_attempt_time_ms
@@ -2749,6 +2968,10 @@ impl MinerConfigFile {
                     HashSet::new()
                 }
             },
+            tx_selection_policy,
+            tx_selection_max_candidates: self
+                .tx_selection_max_candidates
+                .unwrap_or(miner_default_config.tx_selection_max_candidates),
             max_reorg_depth: self
                 .max_reorg_depth
                 .unwrap_or(miner_default_config.max_reorg_depth),
@@ -2827,14 +3050,150 @@ pub struct EventObserverConfigFile {
     pub events_keys: Vec<String>,
     pub timeout_ms: Option<u64>,
     pub disable_retries: Option<bool>,
+    /// How payloads are delivered: `"http"` (default), `"file"` or `"unix_socket"`
+    pub sink: Option<String>,
+    /// Deliver payloads from a background thread instead of the block-processing thread
+    pub async_delivery: Option<bool>,
+    /// Restrict the transactions and events included in `/new_block` payloads
+    pub filter: Option<EventObserverFilterConfigFile>,
 }
 
-#[derive(Clone, Default, Debug, Hash, PartialEq, Eq, PartialOrd)]
+#[derive(Clone, Deserialize, Default, Debug, Hash, PartialEq, Eq, PartialOrd)]
+#[serde(deny_unknown_fields)]
+pub struct EventObserverFilterConfigFile {
+    /// Standard or contract principals, e.g. `"SP000000000000000000002Q6VF78"` or
+    /// `"SP000000000000000000002Q6VF78.pox-4"`
+    pub principals: Option<Vec<String>>,
+    /// Called contracts, as `"{contract id}"` or `"{contract id}::{function name}"`
+    pub contract_calls: Option<Vec<String>>,
+    /// Any of `"token_transfer"`, `"contract_call"`, `"smart_contract"`, `"coinbase"`,
+    /// `"poison_microblock"`, `"tenure_change"` or `"burn_op"`
+    pub tx_types: Option<Vec<String>>,
+}
+
+impl EventObserverFilterConfigFile {
+    fn into_config(self) -> Result<EventObserverFilter, String> {
+        let principals = self
+            .principals
+            .unwrap_or_default()
+            .iter()
+            .map(|raw| {
+                PrincipalData::parse(raw)
+                    .map_err(|e| format!("Invalid event observer filter principal {raw}: {e}"))
+            })
+            .collect::<Result<_, _>>()?;
+        let contract_calls = self
+            .contract_calls
+            .unwrap_or_default()
+            .iter()
+            .map(|raw| {
+                let (contract, function) = match raw.split_once("::") {
+                    Some((contract, function)) => (contract, Some(function.to_string())),
+                    None => (raw.as_str(), None),
+                };
+                let contract = QualifiedContractIdentifier::parse(contract).map_err(|e| {
+                    format!("Invalid event observer filter contract call {raw}: {e}")
+                })?;
+                Ok((contract, function))
+            })
+            .collect::<Result<_, String>>()?;
+        let tx_types = self
+            .tx_types
+            .unwrap_or_default()
+            .iter()
+            .map(|raw| {
+                EventTxType::from_string(raw)
+                    .ok_or_else(|| format!("Unknown event observer filter tx type: {raw}"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(EventObserverFilter {
+            principals,
+            contract_calls,
+            tx_types,
+        })
+    }
+}
+
+#[derive(Clone, Default, Debug, Hash, PartialEq, Eq)]
 pub struct EventObserverConfig {
+    /// For `EventSinkKind::Http`, the `host:port` to POST to. For the file and
+    /// socket sinks, the path of the file or Unix domain socket.
     pub endpoint: String,
     pub events_keys: Vec<EventKeyType>,
     pub timeout_ms: u64,
     pub disable_retries: bool,
+    pub sink: EventSinkKind,
+    /// If true, payloads are handed off to a dedicated delivery thread so that block
+    /// processing never waits on this observer. Pending payloads are still persisted
+    /// to the event observer database before being handed off.
+    pub async_delivery: bool,
+    /// If set, `/new_block` payloads only include the transactions (and their events) which
+    /// match this filter.
+    pub filter: Option<EventObserverFilter>,
+}
+
+/// Selects the transactions of a block which an event observer is sent. Each non-empty
+/// criterion must be satisfied, by matching any one of its entries.
+#[derive(Clone, Default, Debug, Hash, PartialEq, Eq)]
+pub struct EventObserverFilter {
+    /// The transaction's sender or sponsor, a token transfer recipient, the called or
+    /// deployed contract, or a principal in one of its STX or asset events
+    pub principals: Vec<PrincipalData>,
+    /// The transaction calls this contract, and if given, this function
+    pub contract_calls: Vec<(QualifiedContractIdentifier, Option<String>)>,
+    /// The transaction is one of these types
+    pub tx_types: Vec<EventTxType>,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub enum EventTxType {
+    TokenTransfer,
+    ContractCall,
+    SmartContract,
+    Coinbase,
+    PoisonMicroblock,
+    TenureChange,
+    /// A burnchain operation, such as a `stack-stx` or `transfer-stx` sent on Bitcoin
+    BurnOp,
+}
+
+impl EventTxType {
+    pub fn from_string(raw_type: &str) -> Option<EventTxType> {
+        match raw_type {
+            "token_transfer" => Some(EventTxType::TokenTransfer),
+            "contract_call" => Some(EventTxType::ContractCall),
+            "smart_contract" => Some(EventTxType::SmartContract),
+            "coinbase" => Some(EventTxType::Coinbase),
+            "poison_microblock" => Some(EventTxType::PoisonMicroblock),
+            "tenure_change" => Some(EventTxType::TenureChange),
+            "burn_op" => Some(EventTxType::BurnOp),
+            _ => None,
+        }
+    }
+}
+
+/// The transport used to deliver payloads to an event observer
+#[derive(Clone, Copy, Default, Debug, Hash, PartialEq, Eq, PartialOrd)]
+pub enum EventSinkKind {
+    /// POST each payload as JSON to `http://{endpoint}/{path}`
+    #[default]
+    Http,
+    /// Append each payload as a newline-delimited JSON record to the file at `endpoint`
+    File,
+    /// Write each payload as a newline-delimited JSON record to the Unix domain socket at
+    /// `endpoint`
+    UnixSocket,
+}
+
+impl EventSinkKind {
+    pub fn from_string(raw_kind: &str) -> Option<EventSinkKind> {
+        match raw_kind {
+            "http" => Some(EventSinkKind::Http),
+            "file" => Some(EventSinkKind::File),
+            "unix_socket" => Some(EventSinkKind::UnixSocket),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd)]
@@ -2936,6 +3295,8 @@ pub struct InitialBalanceFile {
 mod tests {
     use std::path::Path;
 
+    use stacks_common::util::hash::Hash160;
+
     use super::*;
 
     #[test]
@@ -3122,6 +3483,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_miner_tx_selection_policy() {
+        let mining_key =
+            Some("0000000000000000000000000000000000000000000000000000000000000001".to_string());
+        let sponsor = StacksAddress::new(26, Hash160([0x01; 20])).unwrap();
+        let contract = format!("{sponsor}.counter");
+
+        let miner_config = MinerConfigFile {
+            mining_key: mining_key.clone(),
+            ..MinerConfigFile::default()
+        }
+        .into_config_default(MinerConfig::default())
+        .unwrap();
+        assert_eq!(
+            miner_config.tx_selection_policy,
+            TxSelectionStrategy::FeeRateWithExploration
+        );
+
+        let miner_config = MinerConfigFile {
+            mining_key: mining_key.clone(),
+            tx_selection_policy: Some("priority".into()),
+            priority_sponsors: Some(sponsor.to_string()),
+            priority_contracts: Some(contract.clone()),
+            ..MinerConfigFile::default()
+        }
+        .into_config_default(MinerConfig::default())
+        .unwrap();
+        assert_eq!(
+            miner_config.tx_selection_policy,
+            TxSelectionStrategy::Priority {
+                sponsors: HashSet::from([sponsor.clone()]),
+                contracts: HashSet::from([QualifiedContractIdentifier::parse(&contract).unwrap()]),
+            }
+        );
+
+        assert!(MinerConfigFile {
+            mining_key: mining_key.clone(),
+            tx_selection_policy: Some("cheapest".into()),
+            ..MinerConfigFile::default()
+        }
+        .into_config_default(MinerConfig::default())
+        .is_err());
+
+        assert!(MinerConfigFile {
+            mining_key,
+            tx_selection_policy: Some("package".into()),
+            priority_sponsors: Some(sponsor.to_string()),
+            ..MinerConfigFile::default()
+        }
+        .into_config_default(MinerConfig::default())
+        .is_err());
+    }
+
     #[test]
     fn test_example_confs() {
         // For each config file in the ../conf/ directory, we should be able to parse it
@@ -3208,6 +3622,108 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_load_event_observer_sink() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [[events_observer]]
+                endpoint = "/var/lib/indexer/events.ndjson"
+                events_keys = ["*"]
+                sink = "file"
+                async_delivery = true
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .expect("Expected to be able to parse event observer sink from file");
+
+        let observer = config.events_observers.iter().next().unwrap();
+        assert_eq!(observer.sink, EventSinkKind::File);
+        assert!(observer.async_delivery);
+
+        let file = ConfigFile::from_str(
+            r#"
+            [[events_observer]]
+            endpoint = "localhost:3700"
+            events_keys = ["*"]
+            sink = "carrier_pigeon"
+            "#,
+        )
+        .unwrap();
+        assert!(Config::from_config_file(file, false).is_err());
+    }
+
+    #[test]
+    fn should_load_event_observer_filter() {
+        let config = Config::from_config_file(
+            ConfigFile::from_str(
+                r#"
+                [[events_observer]]
+                endpoint = "localhost:3700"
+                events_keys = ["*"]
+
+                [events_observer.filter]
+                principals = ["ST2CY5V39NHDPWSXMW9QDT3HC3GD6Q6XX4CFRK9AG"]
+                contract_calls = [
+                    "ST000000000000000000002AMW42H.pox-4::stack-stx",
+                    "ST000000000000000000002AMW42H.bns",
+                ]
+                tx_types = ["contract_call", "burn_op"]
+                "#,
+            )
+            .unwrap(),
+            false,
+        )
+        .expect("Expected to be able to parse event observer filter from file");
+
+        let filter = config
+            .events_observers
+            .iter()
+            .next()
+            .unwrap()
+            .filter
+            .clone()
+            .expect("Expected a filter");
+        assert_eq!(
+            filter.principals,
+            vec![PrincipalData::parse("ST2CY5V39NHDPWSXMW9QDT3HC3GD6Q6XX4CFRK9AG").unwrap()]
+        );
+        assert_eq!(
+            filter.contract_calls,
+            vec![
+                (
+                    QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.pox-4")
+                        .unwrap(),
+                    Some("stack-stx".to_string())
+                ),
+                (
+                    QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.bns")
+                        .unwrap(),
+                    None
+                ),
+            ]
+        );
+        assert_eq!(
+            filter.tx_types,
+            vec![EventTxType::ContractCall, EventTxType::BurnOp]
+        );
+
+        let file = ConfigFile::from_str(
+            r#"
+            [[events_observer]]
+            endpoint = "localhost:3700"
+            events_keys = ["*"]
+
+            [events_observer.filter]
+            tx_types = ["teleport"]
+            "#,
+        )
+        .unwrap();
+        assert!(Config::from_config_file(file, false).is_err());
+    }
+
     #[test]
     fn should_load_affirmation_map() {
         let affirmation_string = "nnnnnnnnnnnnnnnnnnnnnnnnnnnnnnnnppnnnnnnnnnnnnnnnnnnnnnnnnpppppnnnnnnnnnnnnnnnnnnnnnnnpppppppppppppppnnnnnnnnnnnnnnnnnnnnnnnppppppppppnnnnnnnnnnnnnnnnnnnppppnnnnnnnnnnnnnnnnnnnnnnnppppppppnnnnnnnnnnnnnnnnnnnnnnnppnppnnnnnnnnnnnnnnnnnnnnnnnppppnnnnnnnnnnnnnnnnnnnnnnnnnppppppnnnnnnnnnnnnnnnnnnnnnnnnnppnnnnnnnnnnnnnnnnnnnnnnnnnpppppppnnnnnnnnnnnnnnnnnnnnnnnnnnpnnnnnnnnnnnnnnnnnnnnnnnnnpppnppppppppppppppnnppppnpa";
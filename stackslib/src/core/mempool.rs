@@ -14,17 +14,18 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::cmp::{self, Ordering};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::cmp::{self, Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::hash::Hasher;
 use std::io::{Read, Write};
 use std::ops::{Deref, DerefMut};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime};
 use std::{fs, io};
 
-use clarity::vm::types::PrincipalData;
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier, StacksAddressExtensions};
 use rand::distributions::Uniform;
 use rand::prelude::Distribution;
 use rusqlite::types::ToSql;
@@ -35,10 +36,12 @@ use siphasher::sip::SipHasher; // this is SipHash-2-4
 use stacks_common::codec::{
     read_next, write_next, Error as codec_error, StacksMessageCodec, MAX_MESSAGE_LEN,
 };
-use stacks_common::types::chainstate::{BlockHeaderHash, StacksAddress, StacksBlockId};
+use stacks_common::types::chainstate::{
+    BlockHeaderHash, SortitionId, StacksAddress, StacksBlockId,
+};
 use stacks_common::types::sqlite::NO_PARAMS;
 use stacks_common::types::MempoolCollectionBehavior;
-use stacks_common::util::hash::{to_hex, Sha512Trunc256Sum};
+use stacks_common::util::hash::{hex_bytes, to_hex, Sha512Trunc256Sum};
 use stacks_common::util::retry::{BoundReader, RetryReader};
 use stacks_common::util::{get_epoch_time_ms, get_epoch_time_secs};
 
@@ -55,6 +58,7 @@ use crate::chainstate::stacks::{
     Error as ChainstateError, StacksBlock, StacksMicroblock, StacksTransaction, TransactionPayload,
 };
 use crate::clarity_vm::clarity::ClarityConnection;
+use crate::core::selection::{nonces_used, tx_contract, SelectionCandidate, TxSelectionPolicy};
 use crate::core::{
     ExecutionCost, StacksEpochId, FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH,
 };
@@ -65,9 +69,9 @@ use crate::net::api::postblock_proposal::{BlockValidateOk, BlockValidateReject};
 use crate::net::Error as net_error;
 use crate::util_lib::bloom::{BloomCounter, BloomFilter, BloomNodeHasher};
 use crate::util_lib::db::{
-    query_int, query_row, query_row_columns, query_rows, sql_pragma, sqlite_open, table_exists,
-    tx_begin_immediate, tx_busy_handler, u64_to_sql, DBConn, DBTx, Error as db_error, Error,
-    FromColumn, FromRow,
+    query_count, query_int, query_row, query_row_columns, query_rows, sql_pragma, sqlite_open,
+    table_exists, tx_begin_immediate, tx_busy_handler, u64_to_sql, DBConn, DBTx, Error as db_error,
+    Error, FromColumn, FromRow,
 };
 use crate::{cost_estimates, monitoring};
 
@@ -99,6 +103,9 @@ pub const DEFAULT_BLACKLIST_MAX_SIZE: u64 = 134217728; // 2**27 -- the blacklist
 // loading the bloom filter, even though the bloom filter is larger.
 const DEFAULT_MAX_TX_TAGS: u32 = 2048;
 
+// how many of the cheapest transactions to load at a time when looking for transactions to evict
+const EVICTION_CANDIDATES_PAGE_SIZE: u64 = 64;
+
 /// A node-specific transaction tag -- the first 8 bytes of siphash(local-seed,txid)
 #[derive(Debug, Clone, PartialEq, Hash, Eq)]
 pub struct TxTag(pub [u8; 8]);
@@ -302,9 +309,35 @@ pub fn decode_tx_stream<R: Read>(
     Ok((txs, page_id))
 }
 
+/// Limits on what the mempool will admit, beyond the validity checks of
+/// `MemPoolAdmitter::will_admit_tx()`.  The default imposes no limits.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MemPoolAdmissionPolicy {
+    /// Minimum fee increase, as a percentage of the replaced transaction's fee, for a transaction
+    /// to replace one with the same nonce in the same fork.  0 means any increase.
+    pub rbf_fee_bump_percent: u64,
+    /// Maximum number of pending transactions from the same origin
+    pub max_txs_per_origin: Option<u64>,
+    /// Maximum number of pending transactions paid for by the same sponsor
+    pub max_txs_per_sponsor: Option<u64>,
+    /// Maximum total size of the mempool's transactions, in bytes.  To make room for a new
+    /// transaction, the transactions paying the lowest fee per byte are evicted -- unless the new
+    /// transaction pays less, in which case it is rejected.
+    pub max_bytes: Option<u64>,
+}
+
+impl MemPoolAdmissionPolicy {
+    /// Minimum fee for a transaction to replace one which pays `prior_fee` in the same fork
+    pub fn min_replacement_fee(&self, prior_fee: u64) -> u64 {
+        let bump = u128::from(prior_fee) * u128::from(self.rbf_fee_bump_percent) / 100;
+        u64::try_from(u128::from(prior_fee) + bump.max(1)).unwrap_or(u64::MAX)
+    }
+}
+
 pub struct MemPoolAdmitter {
     cur_block: BlockHeaderHash,
     cur_consensus_hash: ConsensusHash,
+    policy: MemPoolAdmissionPolicy,
 }
 
 enum MemPoolWalkResult {
@@ -318,6 +351,7 @@ impl MemPoolAdmitter {
         MemPoolAdmitter {
             cur_block,
             cur_consensus_hash,
+            policy: MemPoolAdmissionPolicy::default(),
         }
     }
 
@@ -332,13 +366,7 @@ impl MemPoolAdmitter {
         tx: &StacksTransaction,
         tx_size: u64,
     ) -> Result<(), MemPoolRejection> {
-        let sortition_id = match SortitionDB::get_sortition_id_by_consensus(
-            sortdb.conn(),
-            &self.cur_consensus_hash,
-        ) {
-            Ok(Some(x)) => x,
-            _ => return Err(MemPoolRejection::DBError(db_error::NotFoundError)),
-        };
+        let sortition_id = self.get_sortition_id(sortdb)?;
         chainstate.will_admit_mempool_tx(
             &sortdb.index_handle(&sortition_id),
             &self.cur_consensus_hash,
@@ -347,6 +375,29 @@ impl MemPoolAdmitter {
             tx_size,
         )
     }
+
+    /// Get the nonce of `address` at the current block
+    pub fn get_account_nonce(
+        &self,
+        chainstate: &mut StacksChainState,
+        sortdb: &SortitionDB,
+        address: &StacksAddress,
+    ) -> Result<u64, MemPoolRejection> {
+        let sortition_id = self.get_sortition_id(sortdb)?;
+        chainstate.get_mempool_account_nonce(
+            &sortdb.index_handle(&sortition_id),
+            &self.cur_consensus_hash,
+            &self.cur_block,
+            &address.to_account_principal(),
+        )
+    }
+
+    fn get_sortition_id(&self, sortdb: &SortitionDB) -> Result<SortitionId, MemPoolRejection> {
+        match SortitionDB::get_sortition_id_by_consensus(sortdb.conn(), &self.cur_consensus_hash) {
+            Ok(Some(x)) => Ok(x),
+            _ => Err(MemPoolRejection::DBError(db_error::NotFoundError)),
+        }
+    }
 }
 
 pub enum MemPoolDropReason {
@@ -355,6 +406,7 @@ pub enum MemPoolDropReason {
     STALE_COLLECT,
     TOO_EXPENSIVE,
     PROBLEMATIC,
+    SIZE_LIMIT_EVICT,
 }
 
 pub struct ConsiderTransaction {
@@ -380,6 +432,7 @@ impl std::fmt::Display for MemPoolDropReason {
             MemPoolDropReason::REPLACE_ACROSS_FORK => write!(f, "ReplaceAcrossFork"),
             MemPoolDropReason::REPLACE_BY_FEE => write!(f, "ReplaceByFee"),
             MemPoolDropReason::PROBLEMATIC => write!(f, "Problematic"),
+            MemPoolDropReason::SIZE_LIMIT_EVICT => write!(f, "SizeLimitEviction"),
         }
     }
 }
@@ -535,6 +588,14 @@ pub struct MemPoolWalkSettings {
     /// What percentage of the remaining cost limit should we consume before stopping the walk
     /// None means we consume the entire cost limit ASAP
     pub tenure_cost_limit_per_block_percentage: Option<u8>,
+    /// Order in which to consider transactions.  None means fee rate order, with transactions
+    /// that have no fee rate estimate considered with probability `consider_no_estimate_tx_prob`.
+    /// A policy orders the `max_selection_candidates` transactions with the highest fee rates,
+    /// which are loaded into memory at the start of the walk.
+    pub selection_policy: Option<Arc<dyn TxSelectionPolicy>>,
+    /// How many of the highest fee rate transactions a selection policy orders.  A policy which
+    /// asks for the candidates' ancestors gets up to as many ancestors again.
+    pub max_selection_candidates: u64,
 }
 
 impl Default for MemPoolWalkSettings {
@@ -547,6 +608,8 @@ impl Default for MemPoolWalkSettings {
             txs_to_consider: MemPoolWalkTxTypes::all(),
             filter_origins: HashSet::new(),
             tenure_cost_limit_per_block_percentage: None,
+            selection_policy: None,
+            max_selection_candidates: 16 * 1024,
         }
     }
 }
@@ -560,6 +623,8 @@ impl MemPoolWalkSettings {
             txs_to_consider: MemPoolWalkTxTypes::all(),
             filter_origins: HashSet::new(),
             tenure_cost_limit_per_block_percentage: None,
+            selection_policy: None,
+            max_selection_candidates: 16 * 1024,
         }
     }
 }
@@ -644,6 +709,121 @@ impl FromRow<MemPoolTxInfoPartial> for MemPoolTxInfoPartial {
     }
 }
 
+/// Version of the mempool export file format written by `MemPoolDB::export_txs()`
+pub const MEMPOOL_EXPORT_VERSION: u32 = 1;
+
+/// First line of a mempool export file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemPoolExportHeader {
+    pub version: u32,
+    pub num_txs: u64,
+}
+
+/// A mempool transaction in a mempool export file, one per line after the header.
+/// Chain-tip-specific metadata is not exported, since it is re-derived on import.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemPoolExportEntry {
+    pub txid: Txid,
+    /// Hex-encoded, consensus-serialized transaction
+    pub tx: String,
+    pub accept_time: u64,
+    pub fee_rate: Option<f64>,
+    pub time_estimate_ms: Option<u64>,
+}
+
+impl FromRow<MemPoolExportEntry> for MemPoolExportEntry {
+    fn from_row(row: &Row) -> Result<MemPoolExportEntry, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let tx_bytes: Vec<u8> = row.get_unwrap("tx");
+        let accept_time = u64::from_column(row, "accept_time")?;
+        let fee_rate: Option<f64> = row.get("fee_rate")?;
+        let time_estimate_ms: Option<u64> = row.get("time_estimate_ms")?;
+
+        Ok(MemPoolExportEntry {
+            txid,
+            tx: to_hex(&tx_bytes),
+            accept_time,
+            fee_rate,
+            time_estimate_ms,
+        })
+    }
+}
+
+/// Outcome of `MemPoolDB::import_txs()`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MemPoolImportStats {
+    /// Transactions admitted to the mempool
+    pub imported: u64,
+    /// Transactions which were already in the mempool
+    pub already_present: u64,
+    /// Transactions which failed admission, e.g. because they were mined in the meantime
+    pub rejected: u64,
+}
+
+/// Filter for `MemPoolDB::find_txs()`.  Unset fields match every transaction.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MemPoolTxFilter {
+    pub origin: Option<StacksAddress>,
+    /// Matches on the origin address of transactions which are not sponsored
+    pub sponsor: Option<StacksAddress>,
+    /// The contract called or deployed by the transaction.  There is no index on this, so it
+    /// should be combined with `origin` or `sponsor`.
+    pub contract: Option<QualifiedContractIdentifier>,
+    /// Inclusive nonce range.  This applies to sponsor nonces if only `sponsor` is set, and to
+    /// origin nonces otherwise.
+    pub min_nonce: Option<u64>,
+    pub max_nonce: Option<u64>,
+}
+
+impl MemPoolTxFilter {
+    fn by_sponsor(&self) -> bool {
+        self.origin.is_none() && self.sponsor.is_some()
+    }
+
+    /// The address column and the nonce column which the filter's address and nonce range apply to
+    fn columns(&self) -> (&'static str, &'static str) {
+        if self.by_sponsor() {
+            ("sponsor_address", "sponsor_nonce")
+        } else {
+            ("origin_address", "origin_nonce")
+        }
+    }
+
+    /// The nonce of `md` which `min_nonce` and `max_nonce` apply to
+    pub fn nonce(&self, md: &MemPoolTxMetadata) -> u64 {
+        if self.by_sponsor() {
+            md.sponsor_nonce
+        } else {
+            md.origin_nonce
+        }
+    }
+}
+
+/// Upper bounds of all but the last bucket of `MemPoolStats::fee_rate_histogram`
+pub const MEMPOOL_FEE_RATE_BUCKETS: [f64; 12] = [
+    1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0,
+];
+
+/// Transactions whose fee rate estimates are in `[min_fee_rate, max_fee_rate)`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemPoolFeeRateBucket {
+    pub min_fee_rate: f64,
+    /// `None` for the highest bucket
+    pub max_fee_rate: Option<f64>,
+    pub tx_count: u64,
+    pub bytes: u64,
+}
+
+/// Summary of the mempool's contents, from `MemPoolDB::get_stats()`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemPoolStats {
+    pub tx_count: u64,
+    pub total_bytes: u64,
+    /// Transactions without a fee rate estimate, which are not in `fee_rate_histogram`
+    pub no_fee_rate_count: u64,
+    pub fee_rate_histogram: Vec<MemPoolFeeRateBucket>,
+}
+
 impl FromRow<(u64, u64)> for (u64, u64) {
     fn from_row(row: &Row) -> Result<(u64, u64), db_error> {
         let t1: i64 = row.get_unwrap(0);
@@ -803,6 +983,56 @@ const MEMPOOL_SCHEMA_7_TIME_ESTIMATES: &[&str] = &[
     "#,
 ];
 
+const MEMPOOL_SCHEMA_8_SIZE: &[&str] = &[
+    r#"
+    -- Total length of the transactions in the mempool
+    CREATE TABLE IF NOT EXISTS mempool_size(
+        size INTEGER NOT NULL
+    );
+    "#,
+    r#"
+    -- Maintain the total length of the transactions in the mempool.  The rows an
+    -- `INSERT OR REPLACE` replaces don't fire the delete trigger, so they're subtracted here.
+    CREATE TRIGGER IF NOT EXISTS mempool_size_replace
+    BEFORE INSERT ON mempool
+    BEGIN
+        UPDATE mempool_size SET size = size - IFNULL((
+            SELECT SUM(length) FROM mempool
+            WHERE txid = NEW.txid
+                OR (origin_address = NEW.origin_address AND origin_nonce = NEW.origin_nonce)
+                OR (sponsor_address = NEW.sponsor_address AND sponsor_nonce = NEW.sponsor_nonce)
+        ), 0);
+    END
+    "#,
+    r#"
+    CREATE TRIGGER IF NOT EXISTS mempool_size_inc
+    AFTER INSERT ON mempool
+    BEGIN
+        UPDATE mempool_size SET size = size + NEW.length;
+    END
+    "#,
+    r#"
+    CREATE TRIGGER IF NOT EXISTS mempool_size_dec
+    AFTER DELETE ON mempool
+    BEGIN
+        UPDATE mempool_size SET size = size - OLD.length;
+    END
+    "#,
+    r#"
+    CREATE TRIGGER IF NOT EXISTS mempool_size_update
+    AFTER UPDATE OF length ON mempool
+    BEGIN
+        UPDATE mempool_size SET size = size - OLD.length + NEW.length;
+    END
+    "#,
+    r#"
+    INSERT INTO mempool_size (size) SELECT IFNULL(SUM(length), 0) FROM mempool
+    "#,
+    r#"
+    INSERT INTO schema_version (version) VALUES (8)
+    "#,
+];
+
 const MEMPOOL_INDEXES: &[&str] = &[
     "CREATE INDEX IF NOT EXISTS by_txid ON mempool(txid);",
     "CREATE INDEX IF NOT EXISTS by_height ON mempool(height);",
@@ -815,6 +1045,7 @@ const MEMPOOL_INDEXES: &[&str] = &[
     "CREATE INDEX IF NOT EXISTS by_ordered_hashed_txid ON randomized_txids(hashed_txid ASC);",
     "CREATE INDEX IF NOT EXISTS by_hashed_txid ON randomized_txids(txid,hashed_txid);",
     "CREATE INDEX IF NOT EXISTS by_arrival_time_desc ON tx_blacklist(arrival_time DESC);",
+    "CREATE INDEX IF NOT EXISTS by_fee_per_byte ON mempool(tx_fee * 1000000 / length ASC, accept_time DESC);",
 ];
 
 pub struct MemPoolDB {
@@ -833,6 +1064,9 @@ pub struct MemPoolTx<'a> {
     tx: DBTx<'a>,
     admitter: &'a mut MemPoolAdmitter,
     bloom_counter: Option<&'a mut BloomCounter<BloomNodeHasher>>,
+    /// Transactions evicted to keep the mempool within its size limit, to be announced once
+    /// this transaction commits
+    evicted_txids: Vec<Txid>,
 }
 
 impl<'a> Deref for MemPoolTx<'a> {
@@ -858,6 +1092,7 @@ impl<'a> MemPoolTx<'a> {
             tx,
             admitter,
             bloom_counter: Some(bloom_counter),
+            evicted_txids: vec![],
         }
     }
 
@@ -875,7 +1110,26 @@ impl<'a> MemPoolTx<'a> {
     }
 
     pub fn commit(self) -> Result<(), db_error> {
-        self.tx.commit().map_err(db_error::SqliteError)
+        self.commit_and_announce(None)
+    }
+
+    /// Commit, and then tell `event_observer` about the transactions which were evicted to keep
+    /// the mempool within its size limit
+    pub fn commit_and_announce(
+        self,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+    ) -> Result<(), db_error> {
+        self.tx.commit().map_err(db_error::SqliteError)?;
+        if let Some(event_observer) = event_observer {
+            if !self.evicted_txids.is_empty() {
+                event_observer.mempool_txs_dropped(
+                    self.evicted_txids,
+                    None,
+                    MemPoolDropReason::SIZE_LIMIT_EVICT,
+                );
+            }
+        }
+        Ok(())
     }
 
     /// Remove all txids at the given coinbase height from the bloom counter.
@@ -1291,6 +1545,9 @@ impl MemPoolDB {
                     MemPoolDB::instantiate_schema_7(tx)?;
                 }
                 7 => {
+                    MemPoolDB::instantiate_mempool_size(tx)?;
+                }
+                8 => {
                     break;
                 }
                 _ => {
@@ -1377,6 +1634,16 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// Add the running total of the mempool's size
+    #[cfg_attr(test, mutants::skip)]
+    fn instantiate_mempool_size(tx: &DBTx) -> Result<(), db_error> {
+        for sql_exec in MEMPOOL_SCHEMA_8_SIZE {
+            tx.execute_batch(sql_exec)?;
+        }
+
+        Ok(())
+    }
+
     #[cfg_attr(test, mutants::skip)]
     pub fn db_path(chainstate_root_path: &str) -> Result<String, db_error> {
         let mut path = PathBuf::from(chainstate_root_path);
@@ -1485,6 +1752,11 @@ impl MemPoolDB {
         MemPoolDB::open_db(&db_path, cost_estimator, metric)
     }
 
+    /// Set the limits on what this mempool will admit
+    pub fn set_admission_policy(&mut self, policy: MemPoolAdmissionPolicy) {
+        self.admitter.policy = policy;
+    }
+
     #[cfg_attr(test, mutants::skip)]
     pub fn reset_nonce_cache(&mut self) -> Result<(), db_error> {
         debug!("reset nonce cache");
@@ -1583,6 +1855,142 @@ impl MemPoolDB {
         }
     }
 
+    /// Load up to `settings.max_selection_candidates` of the mempool's transactions, highest fee
+    /// rate first, and have `policy` put them in the order in which a mempool walk should
+    /// consider them.  If `policy` asks for the candidates' pending ancestors, up to as many
+    /// ancestors again are loaded from the mempool, whatever their fee rates.
+    fn order_candidates<C: ClarityConnection>(
+        &self,
+        policy: &dyn TxSelectionPolicy,
+        settings: &MemPoolWalkSettings,
+        clarity_tx: &mut C,
+        nonce_cache: &mut NonceCache,
+        retry_store: &mut HashMap<StacksAddress, u64>,
+    ) -> Result<VecDeque<MemPoolTxInfoPartial>, db_error> {
+        let needs_contracts = policy.needs_contracts();
+        let columns = if needs_contracts {
+            "txid, origin_nonce, origin_address, sponsor_nonce, sponsor_address, fee_rate, tx_fee, length, tx"
+        } else {
+            "txid, origin_nonce, origin_address, sponsor_nonce, sponsor_address, fee_rate, tx_fee, length"
+        };
+        let candidate_from_row = |row: &Row| -> Result<SelectionCandidate, db_error> {
+            let contract = if needs_contracts {
+                let tx_bytes: Vec<u8> = row.get_unwrap("tx");
+                StacksTransaction::consensus_deserialize(&mut &tx_bytes[..])
+                    .ok()
+                    .and_then(|tx| tx_contract(&tx))
+            } else {
+                None
+            };
+            Ok(SelectionCandidate {
+                info: MemPoolTxInfoPartial::from_row(row)?,
+                tx_fee: u64::from_column(row, "tx_fee")?,
+                len: u64::from_column(row, "length")?,
+                contract,
+            })
+        };
+
+        let max_candidates = settings.max_selection_candidates;
+        let sql = format!("SELECT {columns} FROM mempool ORDER BY fee_rate DESC LIMIT ?1");
+        let mut stmt = self.db.prepare(&sql)?;
+        let mut rows = stmt.query(params![u64_to_sql(max_candidates)?])?;
+        let mut candidates = vec![];
+        while let Some(row) = rows.next()? {
+            candidates.push(candidate_from_row(row)?);
+        }
+        let num_candidates = candidates.len();
+
+        let max_ancestors = policy.max_ancestors();
+        if max_ancestors > 0 {
+            // load the pending transactions which use the `max_ancestors` nonces of each
+            // candidate's accounts before its own, back to the accounts' current nonces
+            let sql = format!(
+                "SELECT {columns} FROM mempool WHERE (origin_address = ?1 AND origin_nonce >= ?2 AND origin_nonce < ?3) OR (sponsor_address = ?1 AND sponsor_nonce >= ?2 AND sponsor_nonce < ?3)"
+            );
+            let mut stmt = self.db.prepare(&sql)?;
+            let mut txids: HashSet<Txid> = candidates.iter().map(|c| c.info.txid).collect();
+            let mut account_nonces: HashMap<StacksAddress, u64> = HashMap::new();
+            // for each account, the nonce below which its transactions were loaded
+            let mut loaded_below: HashMap<StacksAddress, u64> = HashMap::new();
+            let mut pending: Vec<(StacksAddress, u64)> = candidates
+                .iter()
+                .flat_map(|c| nonces_used(&c.info))
+                .map(|(address, nonce)| (*address, nonce))
+                .collect();
+            let mut num_ancestors = 0;
+            while let Some((address, nonce)) = pending.pop() {
+                if num_ancestors >= max_candidates {
+                    break;
+                }
+                let loaded_nonce = loaded_below.get(&address).copied().unwrap_or(0);
+                if nonce <= loaded_nonce {
+                    continue;
+                }
+                let account_nonce = *account_nonces.entry(address).or_insert_with(|| {
+                    let (account_nonce, retry) = nonce_cache.get(&address, clarity_tx, self.conn());
+                    if retry {
+                        Self::save_nonce_for_retry(
+                            retry_store,
+                            settings.nonce_cache_size,
+                            address,
+                            account_nonce,
+                        );
+                    }
+                    account_nonce
+                });
+                let min_nonce = account_nonce
+                    .max(nonce.saturating_sub(max_ancestors))
+                    .max(loaded_nonce);
+                loaded_below.insert(address, nonce);
+                if min_nonce >= nonce {
+                    continue;
+                }
+                let args = params![
+                    address.to_string(),
+                    u64_to_sql(min_nonce)?,
+                    u64_to_sql(nonce)?
+                ];
+                let mut rows = stmt.query(args)?;
+                while let Some(row) = rows.next()? {
+                    let ancestor = candidate_from_row(row)?;
+                    if !txids.insert(ancestor.info.txid) {
+                        continue;
+                    }
+                    pending.extend(
+                        nonces_used(&ancestor.info)
+                            .into_iter()
+                            .map(|(address, nonce)| (*address, nonce)),
+                    );
+                    candidates.push(ancestor);
+                    num_ancestors += 1;
+                    if num_ancestors >= max_candidates {
+                        break;
+                    }
+                }
+            }
+
+            // transactions whose nonces were already used can no longer be mined
+            candidates.retain(|c| {
+                nonces_used(&c.info).into_iter().all(|(address, nonce)| {
+                    account_nonces
+                        .get(address)
+                        .map_or(true, |account_nonce| nonce >= *account_nonce)
+                })
+            });
+        }
+
+        let num_loaded = candidates.len();
+        let ordered: VecDeque<_> = policy.order_candidates(candidates).into();
+        debug!(
+            "Mempool: {} ordered {} of {} candidates ({} ancestors)",
+            policy.name(),
+            ordered.len(),
+            num_loaded,
+            num_loaded.saturating_sub(num_candidates)
+        );
+        Ok(ordered)
+    }
+
     /// Iterate over candidates in the mempool
     /// `todo` will be called once for each transaction that is a valid
     /// candidate for inclusion in the next block, meaning its origin and
@@ -1594,7 +2002,8 @@ impl MemPoolDB {
     /// When the candidate cache fills, a subsequent call to
     /// `iterate_candidates` will be needed to reconsider transactions which
     /// were skipped on the first pass, but become valid after some lower
-    /// fee-rate transactions are considered.
+    /// fee-rate transactions are considered.  If `settings.selection_policy`
+    /// is set, its order is used instead of fee-rate order.
     ///
     /// The size of the candidate cache and the nonce cache are configurable
     /// in the settings struct. This method is interruptable -- in the
@@ -1627,7 +2036,14 @@ impl MemPoolDB {
         let start_time = Instant::now();
         let mut total_considered = 0;
 
-        debug!("Mempool walk for {}ms", settings.max_walk_time_ms,);
+        debug!(
+            "Mempool walk for {}ms with the {} selection policy",
+            settings.max_walk_time_ms,
+            settings
+                .selection_policy
+                .as_ref()
+                .map_or("fee_rate_with_exploration", |policy| policy.name())
+        );
 
         let tx_consideration_sampler = Uniform::new(0, 100);
         let mut rng = rand::thread_rng();
@@ -1638,6 +2054,18 @@ impl MemPoolDB {
         // single transaction.  This cannot grow to more than `settings.nonce_cache_size` entries.
         let mut retry_store = HashMap::new();
 
+        // with a selection policy, the candidates are ordered up front
+        let mut ordered_candidates = match settings.selection_policy.as_ref() {
+            Some(policy) => Some(self.order_candidates(
+                policy.as_ref(),
+                &settings,
+                clarity_tx,
+                &mut nonce_cache,
+                &mut retry_store,
+            )?),
+            None => None,
+        };
+
         let sql = "
              SELECT txid, origin_nonce, origin_address, sponsor_nonce, sponsor_address, fee_rate
              FROM mempool
@@ -1675,6 +2103,18 @@ impl MemPoolDB {
                     let update_estimate = tx.fee_rate.is_none();
                     (tx, update_estimate)
                 }
+                None if ordered_candidates.is_some() => {
+                    match ordered_candidates.as_mut().and_then(|txs| txs.pop_front()) {
+                        Some(tx) => {
+                            let update_estimate = tx.fee_rate.is_none();
+                            (tx, update_estimate)
+                        }
+                        None => {
+                            debug!("No more transactions to consider in mempool");
+                            break MempoolIterationStopReason::NoMoreCandidates;
+                        }
+                    }
+                }
                 None => {
                     // When the retry list is empty, read from the mempool db,
                     // randomly selecting from either the null fee-rate transactions
@@ -2000,6 +2440,182 @@ impl MemPoolDB {
         query_row(conn, &sql, args)
     }
 
+    /// Get up to `limit` transactions matching `filter`, ordered by address and then by nonce.
+    /// Includes transactions which have been mined but not yet garbage-collected, unless
+    /// `filter.min_nonce` excludes them.
+    pub fn find_txs(
+        conn: &DBConn,
+        filter: &MemPoolTxFilter,
+        limit: u64,
+    ) -> Result<Vec<MemPoolTxInfo>, db_error> {
+        let (address_col, nonce_col) = filter.columns();
+        let origin = filter.origin.as_ref().map(|addr| addr.to_string());
+        let sponsor = filter.sponsor.as_ref().map(|addr| addr.to_string());
+        let min_nonce = filter.min_nonce.map(u64_to_sql).transpose()?;
+        let max_nonce = filter.max_nonce.map(u64_to_sql).transpose()?;
+
+        let mut clauses = vec![];
+        let mut args: Vec<&dyn ToSql> = vec![];
+        if let Some(origin) = origin.as_ref() {
+            args.push(origin);
+            clauses.push(format!("origin_address = ?{}", args.len()));
+        }
+        if let Some(sponsor) = sponsor.as_ref() {
+            args.push(sponsor);
+            clauses.push(format!("sponsor_address = ?{}", args.len()));
+        }
+        if let Some(min_nonce) = min_nonce.as_ref() {
+            args.push(min_nonce);
+            clauses.push(format!("{nonce_col} >= ?{}", args.len()));
+        }
+        if let Some(max_nonce) = max_nonce.as_ref() {
+            args.push(max_nonce);
+            clauses.push(format!("{nonce_col} <= ?{}", args.len()));
+        }
+        let where_clause = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", clauses.join(" AND "))
+        };
+        let sql = format!(
+            "SELECT * FROM mempool{where_clause} ORDER BY {address_col}, {nonce_col}, txid"
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(args.as_slice())?;
+        let mut txs = vec![];
+        while (txs.len() as u64) < limit {
+            let Some(row) = rows.next()? else {
+                break;
+            };
+            let txinfo = MemPoolTxInfo::from_row(row)?;
+            if let Some(contract) = filter.contract.as_ref() {
+                if tx_contract(&txinfo.tx).as_ref() != Some(contract) {
+                    continue;
+                }
+            }
+            txs.push(txinfo);
+        }
+        Ok(txs)
+    }
+
+    /// Get the nonces from `min_nonce` to `max_nonce` (inclusive) of the pending transactions of
+    /// `filter`'s origin (or sponsor), in ascending order.  Unlike `find_txs()`, this ignores
+    /// `filter.contract`, since any pending transaction fills its nonce.
+    pub fn find_tx_nonces(
+        conn: &DBConn,
+        filter: &MemPoolTxFilter,
+        min_nonce: u64,
+        max_nonce: u64,
+    ) -> Result<Vec<u64>, db_error> {
+        let (address_col, nonce_col) = filter.columns();
+        let Some(address) = filter.origin.as_ref().or(filter.sponsor.as_ref()) else {
+            return Ok(vec![]);
+        };
+        let sql = format!(
+            "SELECT DISTINCT {nonce_col} FROM mempool WHERE {address_col} = ?1 AND {nonce_col} >= ?2 AND {nonce_col} <= ?3 ORDER BY {nonce_col}"
+        );
+        let args = params![
+            address.to_string(),
+            u64_to_sql(min_nonce)?,
+            u64_to_sql(max_nonce)?
+        ];
+        query_rows(conn, &sql, args)
+    }
+
+    /// Summarize the mempool's contents, including transactions which have been mined but not yet
+    /// garbage-collected
+    pub fn get_stats(conn: &DBConn) -> Result<MemPoolStats, db_error> {
+        let mut fee_rate_histogram: Vec<_> = MEMPOOL_FEE_RATE_BUCKETS
+            .iter()
+            .enumerate()
+            .map(|(i, max_fee_rate)| MemPoolFeeRateBucket {
+                min_fee_rate: if i == 0 {
+                    0.0
+                } else {
+                    MEMPOOL_FEE_RATE_BUCKETS[i - 1]
+                },
+                max_fee_rate: Some(*max_fee_rate),
+                tx_count: 0,
+                bytes: 0,
+            })
+            .collect();
+        fee_rate_histogram.push(MemPoolFeeRateBucket {
+            min_fee_rate: MEMPOOL_FEE_RATE_BUCKETS[MEMPOOL_FEE_RATE_BUCKETS.len() - 1],
+            max_fee_rate: None,
+            tx_count: 0,
+            bytes: 0,
+        });
+
+        let mut stats = MemPoolStats {
+            tx_count: 0,
+            total_bytes: 0,
+            no_fee_rate_count: 0,
+            fee_rate_histogram,
+        };
+        let mut stmt = conn.prepare("SELECT fee_rate, length FROM mempool")?;
+        let mut rows = stmt.query(NO_PARAMS)?;
+        while let Some(row) = rows.next()? {
+            let fee_rate: Option<f64> = row.get(0)?;
+            let length: u64 = u64::from_column(row, "length")?;
+            stats.tx_count += 1;
+            stats.total_bytes += length;
+            let Some(fee_rate) = fee_rate else {
+                stats.no_fee_rate_count += 1;
+                continue;
+            };
+            let bucket = MEMPOOL_FEE_RATE_BUCKETS
+                .iter()
+                .position(|max_fee_rate| fee_rate < *max_fee_rate)
+                .unwrap_or(MEMPOOL_FEE_RATE_BUCKETS.len());
+            stats.fee_rate_histogram[bucket].tx_count += 1;
+            stats.fee_rate_histogram[bucket].bytes += length;
+        }
+        Ok(stats)
+    }
+
+    /// Get the fee rate and scalar cost of each mempool transaction with a fee rate estimate,
+    /// highest fee rate first.  The scalar cost is recovered from the fee rate, which is the
+    /// transaction fee divided by the scalar cost.
+    ///
+    /// Transactions stay in the mempool after they are mined, until garbage-collected, so those
+    /// whose origin or sponsor nonce is below the account nonce in the `nonces` cache are left
+    /// out.  The cache is filled in by mempool walks at the chain tip, and its nonces only lag
+    /// behind the tip's, so no pending transaction is left out.
+    ///
+    /// Stops once the transactions' total scalar cost reaches `max_cost`, since cheaper
+    /// transactions would not be mined within that budget anyway.
+    pub fn get_fee_rate_distribution(
+        conn: &DBConn,
+        max_cost: u64,
+    ) -> Result<Vec<(f64, u64)>, db_error> {
+        let mut stmt = conn.prepare(
+            "SELECT fee_rate, tx_fee FROM mempool
+             WHERE fee_rate > 0
+                AND NOT EXISTS (SELECT 1 FROM nonces
+                    WHERE nonces.address = mempool.origin_address
+                    AND nonces.nonce > mempool.origin_nonce)
+                AND NOT EXISTS (SELECT 1 FROM nonces
+                    WHERE nonces.address = mempool.sponsor_address
+                    AND nonces.nonce > mempool.sponsor_nonce)
+             ORDER BY fee_rate DESC",
+        )?;
+        let mut rows = stmt.query(NO_PARAMS)?;
+        let mut distribution = vec![];
+        let mut total_cost: u64 = 0;
+        while total_cost < max_cost {
+            let Some(row) = rows.next()? else {
+                break;
+            };
+            let fee_rate: f64 = row.get(0)?;
+            let tx_fee: u64 = u64::from_column(row, "tx_fee")?;
+            let cost = ((tx_fee as f64 / fee_rate).round() as u64).max(1);
+            total_cost = total_cost.saturating_add(cost);
+            distribution.push((fee_rate, cost));
+        }
+        Ok(distribution)
+    }
+
     /// Are the given fully-qualified blocks, identified by their (consensus-hash, block-header-hash) pairs, in the same fork?
     /// That is, is one block an ancestor of another?
     /// TODO: Nakamoto-ize
@@ -2113,7 +2729,8 @@ impl MemPoolDB {
 
         // if so, is this a replace-by-fee? or a replace-in-chain-tip?
         let add_tx = if let Some(ref prior_tx) = prior_tx {
-            if tx_fee > prior_tx.tx_fee {
+            let min_replacement_fee = tx.admitter.policy.min_replacement_fee(prior_tx.tx_fee);
+            if tx_fee >= min_replacement_fee {
                 // is this a replace-by-fee ?
                 debug!(
                     "Can replace {} with {} for {},{} by fee ({} < {})",
@@ -2135,6 +2752,12 @@ impl MemPoolDB {
                 );
                 replace_reason = MemPoolDropReason::REPLACE_ACROSS_FORK;
                 true
+            } else if tx_fee > prior_tx.tx_fee {
+                // a replace-by-fee, but not by enough
+                return Err(MemPoolRejection::ReplaceFeeTooLow(
+                    tx_fee,
+                    min_replacement_fee,
+                ));
             } else {
                 // there's a >= fee tx in this fork, cannot add
                 info!("TX conflicts with sponsor/origin nonce in same fork with >= fee";
@@ -2157,6 +2780,20 @@ impl MemPoolDB {
             return Err(MemPoolRejection::ConflictingNonceInMempool);
         }
 
+        if let Some(max_bytes) = tx.admitter.policy.max_bytes {
+            MemPoolDB::make_room(
+                tx,
+                max_bytes,
+                length,
+                tx_fee,
+                prior_tx.as_ref().map(|prior_tx| &prior_tx.txid),
+                origin_address,
+                origin_nonce,
+                sponsor_address,
+                sponsor_nonce,
+            )?;
+        }
+
         tx.update_bloom_counter(
             coinbase_height,
             &txid,
@@ -2206,6 +2843,341 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// Get the total length of the transactions in the mempool
+    pub fn get_total_bytes(conn: &DBConn) -> Result<u64, db_error> {
+        let size: i64 = conn
+            .query_row("SELECT size FROM mempool_size", NO_PARAMS, |row| row.get(0))
+            .map_err(db_error::SqliteError)?;
+        u64::try_from(size).map_err(|_| db_error::ParseError)
+    }
+
+    /// Evict the transactions which pay the lowest fee per byte until a new transaction of
+    /// `length` bytes fits in `max_bytes`.  The transaction it replaces, if any, does not count.
+    /// Only the last transaction of a nonce chain can be evicted -- the one with the highest
+    /// origin nonce from its origin and, if sponsored, the highest sponsor nonce from its sponsor
+    /// -- so that eviction never leaves a gap in front of the transactions which remain.  The new
+    /// transaction counts as part of its origin's and sponsor's chains.
+    /// Fails with `MemPoolFull` if the new transaction pays less per byte than the transactions
+    /// which would have to be evicted, in which case nothing is evicted.
+    ///
+    /// The evicted transactions are announced when `tx` commits.
+    fn make_room(
+        tx: &mut MemPoolTx,
+        max_bytes: u64,
+        length: u64,
+        tx_fee: u64,
+        replaces: Option<&Txid>,
+        origin_address: &StacksAddress,
+        origin_nonce: u64,
+        sponsor_address: &StacksAddress,
+        sponsor_nonce: u64,
+    ) -> Result<(), MemPoolRejection> {
+        let replaces = replaces.cloned().unwrap_or(Txid([0; 32]));
+        let replaced_bytes: Option<i64> = query_row(
+            tx,
+            "SELECT length FROM mempool WHERE txid = ?1",
+            params![replaces],
+        )?;
+        let replaced_bytes =
+            u64::try_from(replaced_bytes.unwrap_or(0)).map_err(|_| db_error::ParseError)?;
+        let total_bytes = MemPoolDB::get_total_bytes(tx)?.saturating_sub(replaced_bytes);
+        let Some(mut excess) = (total_bytes + length).checked_sub(max_bytes) else {
+            return Ok(());
+        };
+        if excess == 0 {
+            return Ok(());
+        }
+        if length > max_bytes {
+            return Err(MemPoolRejection::MemPoolFull);
+        }
+
+        /// Fee rate per byte, then how recently the transaction was accepted
+        type EvictionOrder = (u128, Reverse<u64>);
+
+        struct EvictionCandidate {
+            txid: Txid,
+            origin: (String, u64),
+            sponsor: Option<(String, u64)>,
+            tx_fee: u64,
+            length: u64,
+            accept_time: u64,
+        }
+
+        impl EvictionCandidate {
+            /// The candidate's chains, as (address, nonce) pairs.  An account's origin and
+            /// sponsor nonces are the same sequence.
+            fn chains(&self) -> impl Iterator<Item = &(String, u64)> {
+                [Some(&self.origin), self.sponsor.as_ref()]
+                    .into_iter()
+                    .flatten()
+            }
+
+            /// Cheapest first, and the most recently accepted first among equals.  Matches the
+            /// `by_fee_per_byte` index.
+            fn eviction_order(&self) -> EvictionOrder {
+                let fee_rate = u128::from(self.tx_fee) * 1_000_000 / u128::from(self.length.max(1));
+                (fee_rate, Reverse(self.accept_time))
+            }
+        }
+
+        let candidate_from_row = |row: &Row| -> Result<EvictionCandidate, db_error> {
+            let origin_address: String = row.get_unwrap("origin_address");
+            let sponsor_address: String = row.get_unwrap("sponsor_address");
+            let origin = (origin_address, u64::from_column(row, "origin_nonce")?);
+            let sponsor = (sponsor_address != origin.0)
+                .then(|| u64::from_column(row, "sponsor_nonce"))
+                .transpose()?
+                .map(|sponsor_nonce| (sponsor_address, sponsor_nonce));
+            Ok(EvictionCandidate {
+                txid: Txid::from_column(row, "txid")?,
+                origin,
+                sponsor,
+                tx_fee: u64::from_column(row, "tx_fee")?,
+                length: u64::from_column(row, "length")?,
+                accept_time: u64::from_column(row, "accept_time")?,
+            })
+        };
+        let columns = "txid, origin_address, origin_nonce, sponsor_address, sponsor_nonce, tx_fee, length, accept_time";
+
+        // The new transaction's chains.  It's never evicted, and covers the lower nonces of its
+        // chains.
+        let mut new_chains = vec![(origin_address.to_string(), origin_nonce)];
+        if sponsor_address != origin_address {
+            new_chains.push((sponsor_address.to_string(), sponsor_nonce));
+        }
+
+        // A candidate can be evicted once it is the last of its origin's and sponsor's chains
+        let mut evicted = HashSet::new();
+        let is_evictable = |tx: &MemPoolTx,
+                            evicted: &HashSet<Txid>,
+                            candidate: &EvictionCandidate|
+         -> Result<bool, db_error> {
+            for (address, nonce) in candidate.chains() {
+                if new_chains
+                    .iter()
+                    .any(|(new_address, new_nonce)| new_address == address && new_nonce >= nonce)
+                {
+                    return Ok(false);
+                }
+                let sql = "SELECT txid FROM mempool WHERE txid != ?1 AND txid != ?2 AND
+                           ((origin_address = ?3 AND origin_nonce > ?4) OR
+                            (sponsor_address = ?3 AND sponsor_address != origin_address AND sponsor_nonce > ?4))";
+                let args = params![replaces, candidate.txid, address, u64_to_sql(*nonce)?];
+                let later_txids: Vec<Txid> = query_rows(tx, sql, args)?;
+                if !later_txids.iter().all(|txid| evicted.contains(txid)) {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        };
+
+        // The eviction candidates are loaded a page at a time from the cheapest, and the
+        // evictable ones are evicted from a heap in the same order.  A transaction which
+        // becomes evictable once the ones after it are gone is looked up and added to the heap.
+        let mut candidates = vec![];
+        let mut evictable: BinaryHeap<Reverse<(EvictionOrder, usize)>> = BinaryHeap::new();
+        let mut num_loaded = 0;
+        let mut loaded_until = None;
+        let mut all_loaded = false;
+        let fee_per_byte = tx_fee as f64 / length.max(1) as f64;
+        let mut evict = vec![];
+        loop {
+            let next = evictable.peek().map(|Reverse((order, _))| *order);
+            if !all_loaded && (next.is_none() || next > loaded_until) {
+                let sql = format!(
+                    "SELECT {columns} FROM mempool WHERE txid != ?1
+                     ORDER BY tx_fee * 1000000 / length ASC, accept_time DESC LIMIT ?2 OFFSET ?3"
+                );
+                let args = params![
+                    replaces,
+                    u64_to_sql(EVICTION_CANDIDATES_PAGE_SIZE)?,
+                    u64_to_sql(num_loaded)?
+                ];
+                let mut page = vec![];
+                {
+                    let mut stmt = tx.prepare(&sql).map_err(db_error::SqliteError)?;
+                    let mut rows = stmt.query(args).map_err(db_error::SqliteError)?;
+                    while let Some(row) = rows.next().map_err(db_error::SqliteError)? {
+                        page.push(candidate_from_row(row)?);
+                    }
+                }
+                num_loaded += page.len() as u64;
+                all_loaded = (page.len() as u64) < EVICTION_CANDIDATES_PAGE_SIZE;
+                for candidate in page {
+                    loaded_until = Some(candidate.eviction_order());
+                    if is_evictable(tx, &evicted, &candidate)? {
+                        evictable.push(Reverse((candidate.eviction_order(), candidates.len())));
+                    }
+                    candidates.push(candidate);
+                }
+                continue;
+            }
+            let Some(Reverse((_, index))) = evictable.pop() else {
+                break;
+            };
+            let candidate = &candidates[index];
+            if evicted.contains(&candidate.txid) {
+                // exposed after it was loaded
+                continue;
+            }
+            if candidate.tx_fee as f64 / candidate.length.max(1) as f64 >= fee_per_byte {
+                debug!(
+                    "Mempool is full and tx {} pays too little to evict others",
+                    &candidate.txid
+                );
+                return Err(MemPoolRejection::MemPoolFull);
+            }
+            evicted.insert(candidate.txid);
+            evict.push(candidate.txid);
+            excess = excess.saturating_sub(candidate.length);
+            if excess == 0 {
+                break;
+            }
+
+            // the eviction exposes the previous transactions of its chains
+            let chains: Vec<_> = candidate.chains().cloned().collect();
+            for (address, nonce) in chains {
+                let sql = format!(
+                    "SELECT {columns} FROM mempool WHERE txid != ?1 AND
+                     ((origin_address = ?2 AND origin_nonce < ?3) OR
+                      (sponsor_address = ?2 AND sponsor_address != origin_address AND sponsor_nonce < ?3))
+                     ORDER BY CASE WHEN origin_address = ?2 THEN origin_nonce ELSE sponsor_nonce END DESC
+                     LIMIT 1"
+                );
+                let args = params![replaces, address, u64_to_sql(nonce)?];
+                let exposed = tx
+                    .query_row(&sql, args, |row| Ok(candidate_from_row(row)))
+                    .optional()
+                    .map_err(db_error::SqliteError)?
+                    .transpose()?;
+                let Some(exposed) = exposed else {
+                    continue;
+                };
+                if is_evictable(tx, &evicted, &exposed)? {
+                    evictable.push(Reverse((exposed.eviction_order(), candidates.len())));
+                    candidates.push(exposed);
+                }
+            }
+        }
+        if excess > 0 {
+            return Err(MemPoolRejection::MemPoolFull);
+        }
+
+        debug!(
+            "Evict {} transactions to stay within the mempool size limit",
+            evict.len()
+        );
+        MemPoolTx::with_bloom_state(tx, |ref mut dbtx, ref mut bloom_counter| {
+            for txid in evict.iter() {
+                let removed: Option<i64> = query_row(
+                    dbtx,
+                    "SELECT 1 FROM removed_txids WHERE txid = ?1",
+                    params![txid],
+                )?;
+                if removed.is_none() {
+                    bloom_counter.remove_raw(dbtx, &txid.0)?;
+                }
+                dbtx.execute("DELETE FROM mempool WHERE txid = ?1", params![txid])
+                    .map_err(db_error::SqliteError)?;
+            }
+            let res: Result<(), db_error> = Ok(());
+            res
+        })?;
+        tx.evicted_txids.extend(evict);
+        Ok(())
+    }
+
+    /// Count the pending transactions from `address` -- as origin if `is_origin`, or otherwise as
+    /// the sponsor of others' transactions -- with nonces of at least `account_nonce`, other than
+    /// the one with nonce `exclude_nonce`.
+    fn count_pending_txs(
+        conn: &DBConn,
+        is_origin: bool,
+        address: &StacksAddress,
+        account_nonce: u64,
+        exclude_nonce: u64,
+    ) -> Result<u64, db_error> {
+        let sql = if is_origin {
+            "SELECT COUNT(*) FROM mempool WHERE origin_address = ?1 AND origin_nonce >= ?2 AND origin_nonce != ?3"
+        } else {
+            "SELECT COUNT(*) FROM mempool WHERE sponsor_address = ?1 AND origin_address != sponsor_address AND sponsor_nonce >= ?2 AND sponsor_nonce != ?3"
+        };
+        let args = params![
+            address.to_string(),
+            u64_to_sql(account_nonce)?,
+            u64_to_sql(exclude_nonce)?
+        ];
+        let count = query_count(conn, sql, args)?;
+        Ok(u64::try_from(count).unwrap_or(0))
+    }
+
+    /// Enforce the admission policy's limits on pending transactions per origin and sponsor
+    fn check_pending_limits(
+        mempool_tx: &mut MemPoolTx,
+        chainstate: &mut StacksChainState,
+        sortdb: &SortitionDB,
+        tx: &StacksTransaction,
+    ) -> Result<(), MemPoolRejection> {
+        let policy = mempool_tx.admitter.policy.clone();
+        if let Some(max_pending) = policy.max_txs_per_origin {
+            let origin_address = tx.origin_address();
+            let account_nonce =
+                mempool_tx
+                    .admitter
+                    .get_account_nonce(chainstate, sortdb, &origin_address)?;
+            MemPoolDB::check_pending_limit(
+                mempool_tx,
+                max_pending,
+                true,
+                &origin_address,
+                account_nonce,
+                tx.get_origin_nonce(),
+            )?;
+        }
+        if let (Some(max_pending), Some(sponsor_address), Some(sponsor_nonce)) = (
+            policy.max_txs_per_sponsor,
+            tx.sponsor_address(),
+            tx.get_sponsor_nonce(),
+        ) {
+            let account_nonce =
+                mempool_tx
+                    .admitter
+                    .get_account_nonce(chainstate, sortdb, &sponsor_address)?;
+            MemPoolDB::check_pending_limit(
+                mempool_tx,
+                max_pending,
+                false,
+                &sponsor_address,
+                account_nonce,
+                sponsor_nonce,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Fail with `TooManyPendingTransactions` if `address` -- as origin if `is_origin`, or
+    /// otherwise as sponsor -- already has `max_pending` pending transactions other than the one
+    /// with nonce `tx_nonce`, which a new transaction would replace.
+    pub(crate) fn check_pending_limit(
+        conn: &DBConn,
+        max_pending: u64,
+        is_origin: bool,
+        address: &StacksAddress,
+        account_nonce: u64,
+        tx_nonce: u64,
+    ) -> Result<(), MemPoolRejection> {
+        let pending =
+            MemPoolDB::count_pending_txs(conn, is_origin, address, account_nonce, tx_nonce)?;
+        if pending >= max_pending {
+            return Err(MemPoolRejection::TooManyPendingTransactions {
+                principal: address.to_account_principal(),
+                is_origin,
+                max_pending,
+            });
+        }
+        Ok(())
+    }
+
     /// Garbage-collect the mempool according to the behavior specified in `behavior`.
     pub fn garbage_collect(
         &mut self,
@@ -2352,6 +3324,7 @@ impl MemPoolDB {
             mempool_tx
                 .admitter
                 .will_admit_tx(chainstate, sortdb, tx, len)?;
+            MemPoolDB::check_pending_limits(mempool_tx, chainstate, sortdb, tx)?;
         }
 
         MemPoolDB::try_add_tx(
@@ -2448,10 +3421,123 @@ impl MemPoolDB {
             event_observer,
             fee_rate,
         )?;
-        mempool_tx.commit().map_err(MemPoolRejection::DBError)?;
+        mempool_tx
+            .commit_and_announce(event_observer)
+            .map_err(MemPoolRejection::DBError)?;
         Ok(())
     }
 
+    /// Write every transaction in the mempool, with its accept time and fee rate estimate, to a
+    /// portable file at `path` (a JSON header line, then one JSON line per transaction in order of
+    /// arrival).  The file can be loaded into another node's mempool with `import_txs()`.
+    /// Returns the number of transactions written.
+    pub fn export_txs(&self, path: &Path) -> Result<u64, db_error> {
+        let sql = "SELECT * FROM mempool ORDER BY accept_time ASC, txid ASC";
+        let entries: Vec<MemPoolExportEntry> = query_rows(self.conn(), sql, NO_PARAMS)?;
+        let header = MemPoolExportHeader {
+            version: MEMPOOL_EXPORT_VERSION,
+            num_txs: entries.len() as u64,
+        };
+
+        let mut file = io::BufWriter::new(fs::File::create(path).map_err(db_error::IOError)?);
+        serde_json::to_writer(&mut file, &header).map_err(db_error::SerializationError)?;
+        file.write_all(b"\n").map_err(db_error::IOError)?;
+        for entry in entries.iter() {
+            serde_json::to_writer(&mut file, entry).map_err(db_error::SerializationError)?;
+            file.write_all(b"\n").map_err(db_error::IOError)?;
+        }
+        file.flush().map_err(db_error::IOError)?;
+        Ok(header.num_txs)
+    }
+
+    /// Load a file written by `export_txs()` into this mempool at the given chain tip.  Each
+    /// transaction goes through the usual admission checks in `MemPoolAdmitter::will_admit_tx()`,
+    /// so transactions which have since been mined or become invalid are rejected.  Admitted
+    /// transactions keep their exported accept time and fee rate estimate.
+    pub fn import_txs(
+        &mut self,
+        chainstate: &mut StacksChainState,
+        sortdb: &SortitionDB,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+        path: &Path,
+    ) -> Result<MemPoolImportStats, db_error> {
+        let file = io::BufReader::new(fs::File::open(path).map_err(db_error::IOError)?);
+        let mut lines = io::BufRead::lines(file);
+        let header_line = lines
+            .next()
+            .ok_or_else(|| db_error::Other("Mempool export file is empty".into()))?
+            .map_err(db_error::IOError)?;
+        let header: MemPoolExportHeader =
+            serde_json::from_str(&header_line).map_err(db_error::SerializationError)?;
+        if header.version != MEMPOOL_EXPORT_VERSION {
+            return Err(db_error::Other(format!(
+                "Unsupported mempool export version {} (expected {MEMPOOL_EXPORT_VERSION})",
+                header.version
+            )));
+        }
+
+        let mut stats = MemPoolImportStats::default();
+        for (i, line) in lines.enumerate() {
+            let line = line.map_err(db_error::IOError)?;
+            if line.is_empty() {
+                continue;
+            }
+            let entry: MemPoolExportEntry = serde_json::from_str(&line).map_err(|e| {
+                db_error::Other(format!(
+                    "Malformed mempool export entry on line {}: {e}",
+                    i + 2
+                ))
+            })?;
+            let tx_bytes = hex_bytes(&entry.tx).map_err(|_| db_error::ParseError)?;
+            let tx = StacksTransaction::consensus_deserialize(&mut &tx_bytes[..])
+                .map_err(|_| db_error::ParseError)?;
+            let txid = tx.txid();
+            if txid != entry.txid {
+                return Err(db_error::Other(format!(
+                    "Mempool export entry {} does not match its transaction {txid}",
+                    &entry.txid
+                )));
+            }
+
+            if MemPoolDB::db_has_tx(self.conn(), &txid)? {
+                stats.already_present += 1;
+                continue;
+            }
+            if self.is_tx_blacklisted(&txid)? {
+                debug!("Not importing blacklisted mempool transaction"; "txid" => %txid);
+                stats.rejected += 1;
+                continue;
+            }
+
+            let mut mempool_tx = self.tx_begin()?;
+            if let Err(e) = MemPoolDB::tx_submit(
+                &mut mempool_tx,
+                chainstate,
+                sortdb,
+                consensus_hash,
+                block_hash,
+                &tx,
+                true,
+                None,
+                entry.fee_rate,
+            ) {
+                debug!("Rejected imported mempool transaction";
+                       "txid" => %txid,
+                       "reason" => ?e);
+                stats.rejected += 1;
+                continue;
+            }
+            mempool_tx.execute(
+                "UPDATE mempool SET accept_time = ?1, time_estimate_ms = ?2 WHERE txid = ?3",
+                params![u64_to_sql(entry.accept_time)?, entry.time_estimate_ms, txid],
+            )?;
+            mempool_tx.commit()?;
+            stats.imported += 1;
+        }
+        Ok(stats)
+    }
+
     /// Miner-driven submit (e.g. for poison microblocks), where no checks are performed
     pub fn miner_submit(
         &mut self,
@@ -2478,7 +3564,9 @@ impl MemPoolDB {
             event_observer,
             fee_estimate,
         )?;
-        mempool_tx.commit().map_err(MemPoolRejection::DBError)?;
+        mempool_tx
+            .commit_and_announce(event_observer)
+            .map_err(MemPoolRejection::DBError)?;
         Ok(())
     }
 
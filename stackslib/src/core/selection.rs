@@ -0,0 +1,422 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Transaction selection policies for the miner's mempool walk.
+//!
+//! A `TxSelectionPolicy` decides the order in which `MemPoolDB::iterate_candidates` offers
+//! mempool transactions to the miner.  The walk itself still enforces nonce order (transactions
+//! whose nonces are not yet valid are retried after each mined transaction), as well as the
+//! `txs_to_consider` and `filter_origins` filters.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use clarity::vm::types::QualifiedContractIdentifier;
+use stacks_common::types::chainstate::StacksAddress;
+
+use crate::chainstate::stacks::{StacksTransaction, TransactionPayload};
+use crate::core::mempool::MemPoolTxInfoPartial;
+
+/// A mempool transaction, as seen by a `TxSelectionPolicy`
+#[derive(Debug, Clone)]
+pub struct SelectionCandidate {
+    pub info: MemPoolTxInfoPartial,
+    pub tx_fee: u64,
+    pub len: u64,
+    /// The contract this transaction calls or deploys.  Only loaded if the policy asks for it
+    /// with `TxSelectionPolicy::needs_contracts()`.
+    pub contract: Option<QualifiedContractIdentifier>,
+}
+
+impl SelectionCandidate {
+    /// The amount of block budget this transaction is expected to consume, in the units of its
+    /// fee rate.  Transactions without a fee rate estimate fall back to their length in bytes.
+    pub fn weight(&self) -> f64 {
+        match self.info.fee_rate {
+            Some(fee_rate) if fee_rate > 0.0 => self.tx_fee as f64 / fee_rate,
+            _ => self.len.max(1) as f64,
+        }
+    }
+}
+
+/// The contract called or deployed by `tx`, if any
+pub fn tx_contract(tx: &StacksTransaction) -> Option<QualifiedContractIdentifier> {
+    match &tx.payload {
+        TransactionPayload::ContractCall(cc) => Some(cc.to_clarity_contract_id()),
+        TransactionPayload::SmartContract(sc, ..) => Some(QualifiedContractIdentifier::new(
+            tx.origin_address().into(),
+            sc.name.clone(),
+        )),
+        _ => None,
+    }
+}
+
+/// Strategy for ordering mempool transactions during a miner's mempool walk
+pub trait TxSelectionPolicy: fmt::Debug + Send + Sync {
+    /// Name of this policy, for logging
+    fn name(&self) -> &'static str;
+
+    /// Does `order_candidates()` need each candidate's `contract`?  Loading it requires decoding
+    /// every candidate transaction.
+    fn needs_contracts(&self) -> bool {
+        false
+    }
+
+    /// How many of each candidate's pending ancestors -- the transactions which use lower
+    /// nonces of its origin or sponsor account -- `order_candidates()` needs among the
+    /// candidates, whatever their fee rates
+    fn max_ancestors(&self) -> u64 {
+        0
+    }
+
+    /// Order the mempool's transactions, most preferred first
+    fn order_candidates(&self, candidates: Vec<SelectionCandidate>) -> Vec<MemPoolTxInfoPartial>;
+}
+
+/// Higher fee rates first; transactions without a fee rate estimate go last
+fn cmp_fee_rate(a: &SelectionCandidate, b: &SelectionCandidate) -> Ordering {
+    match (a.info.fee_rate, b.info.fee_rate) {
+        (Some(a_rate), Some(b_rate)) => b_rate.total_cmp(&a_rate),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+    .then_with(|| a.info.origin_nonce.cmp(&b.info.origin_nonce))
+}
+
+/// Consider transactions strictly in order of their fee rates
+#[derive(Debug, Clone, Default)]
+pub struct FeeRatePolicy;
+
+impl TxSelectionPolicy for FeeRatePolicy {
+    fn name(&self) -> &'static str {
+        "fee_rate"
+    }
+
+    fn order_candidates(
+        &self,
+        mut candidates: Vec<SelectionCandidate>,
+    ) -> Vec<MemPoolTxInfoPartial> {
+        candidates.sort_by(cmp_fee_rate);
+        candidates.into_iter().map(|c| c.info).collect()
+    }
+}
+
+/// The most transactions a package can hold: a transaction and its pending ancestors
+pub const MAX_PACKAGE_TXS: usize = 25;
+
+/// The account nonces which `info` uses: its origin nonce, and its sponsor nonce if it is
+/// sponsored
+pub fn nonces_used(info: &MemPoolTxInfoPartial) -> Vec<(&StacksAddress, u64)> {
+    let mut nonces = vec![(&info.origin_address, info.origin_nonce)];
+    if (&info.sponsor_address, info.sponsor_nonce) != (&info.origin_address, info.origin_nonce) {
+        nonces.push((&info.sponsor_address, info.sponsor_nonce));
+    }
+    nonces
+}
+
+/// Max-heap entry for a transaction's package
+struct ScoredPackage {
+    fee_rate: f64,
+    tx: usize,
+    /// The transaction's score version when this entry was pushed; stale entries are skipped
+    version: u64,
+}
+
+impl PartialEq for ScoredPackage {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScoredPackage {}
+
+impl PartialOrd for ScoredPackage {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScoredPackage {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // ties go to the transaction loaded first, for determinism
+        self.fee_rate
+            .total_cmp(&other.fee_rate)
+            .then_with(|| other.tx.cmp(&self.tx))
+            .then_with(|| self.version.cmp(&other.version))
+    }
+}
+
+/// The candidates, each linked to its parents: the candidates which use the preceding nonce of
+/// its origin account and of its sponsor account, and so must be mined before it
+struct PackageGraph {
+    txs: Vec<SelectionCandidate>,
+    parents: Vec<Vec<usize>>,
+    children: Vec<Vec<usize>>,
+    selected: Vec<bool>,
+    versions: Vec<u64>,
+}
+
+impl PackageGraph {
+    fn new(txs: Vec<SelectionCandidate>) -> Self {
+        let mut by_nonce: HashMap<(&StacksAddress, u64), usize> = HashMap::new();
+        for (i, tx) in txs.iter().enumerate() {
+            for nonce in nonces_used(&tx.info) {
+                by_nonce.entry(nonce).or_insert(i);
+            }
+        }
+        let mut parents = vec![vec![]; txs.len()];
+        let mut children = vec![vec![]; txs.len()];
+        for (i, tx) in txs.iter().enumerate() {
+            for (address, nonce) in nonces_used(&tx.info) {
+                let Some(prev_nonce) = nonce.checked_sub(1) else {
+                    continue;
+                };
+                if let Some(&parent) = by_nonce.get(&(address, prev_nonce)) {
+                    if parent != i && !parents[i].contains(&parent) {
+                        parents[i].push(parent);
+                        children[parent].push(i);
+                    }
+                }
+            }
+        }
+        let num_txs = txs.len();
+        PackageGraph {
+            txs,
+            parents,
+            children,
+            selected: vec![false; num_txs],
+            versions: vec![0; num_txs],
+        }
+    }
+
+    /// The package of `tx`: its unselected ancestors and itself, parents first.  None if it
+    /// holds more than `MAX_PACKAGE_TXS` transactions.
+    fn package(&self, tx: usize) -> Option<Vec<usize>> {
+        let mut package = vec![];
+        let mut seen = HashSet::new();
+        let mut stack = vec![(tx, false)];
+        while let Some((i, parents_done)) = stack.pop() {
+            if parents_done {
+                package.push(i);
+                if package.len() > MAX_PACKAGE_TXS {
+                    return None;
+                }
+                continue;
+            }
+            if !seen.insert(i) {
+                continue;
+            }
+            stack.push((i, true));
+            for parent in self.parents[i].iter() {
+                if !self.selected[*parent] && !seen.contains(parent) {
+                    stack.push((*parent, false));
+                }
+            }
+        }
+        Some(package)
+    }
+
+    /// Push the current score of `tx`'s package, if it is small enough to be scored
+    fn rescore(&mut self, tx: usize, heap: &mut BinaryHeap<ScoredPackage>) {
+        self.versions[tx] += 1;
+        let Some(package) = self.package(tx) else {
+            return;
+        };
+        let (fees, weight) = package.iter().fold((0.0, 0.0), |(fees, weight), i| {
+            let tx = &self.txs[*i];
+            (fees + tx.tx_fee as f64, weight + tx.weight())
+        });
+        heap.push(ScoredPackage {
+            fee_rate: fees / weight,
+            tx,
+            version: self.versions[tx],
+        });
+    }
+
+    /// The unselected descendants of `package` whose packages could have changed by selecting
+    /// it.  A descendant more than `MAX_PACKAGE_TXS` generations away has too many ancestors
+    /// to be scored either way.
+    fn descendants(&self, package: &[usize]) -> Vec<usize> {
+        let mut descendants = vec![];
+        let mut seen: HashSet<usize> = package.iter().copied().collect();
+        let mut frontier = package.to_vec();
+        for _ in 0..MAX_PACKAGE_TXS {
+            let mut next = vec![];
+            for i in frontier.iter() {
+                for child in self.children[*i].iter() {
+                    if !self.selected[*child] && seen.insert(*child) {
+                        next.push(*child);
+                    }
+                }
+            }
+            descendants.extend(next.iter().copied());
+            frontier = next;
+        }
+        descendants
+    }
+
+    /// Select packages, highest combined fee rate first
+    fn order(mut self) -> Vec<MemPoolTxInfoPartial> {
+        let mut heap = BinaryHeap::new();
+        for i in 0..self.txs.len() {
+            self.rescore(i, &mut heap);
+        }
+        let mut ordered = Vec::with_capacity(self.txs.len());
+        while let Some(entry) = heap.pop() {
+            if self.selected[entry.tx] || self.versions[entry.tx] != entry.version {
+                continue;
+            }
+            let Some(package) = self.package(entry.tx) else {
+                continue;
+            };
+            for i in package.iter() {
+                self.selected[*i] = true;
+                ordered.push(self.txs[*i].info.clone());
+            }
+            for i in self.descendants(&package) {
+                self.rescore(i, &mut heap);
+            }
+        }
+        // transactions which never got a small enough package, e.g. because their ancestors
+        // depend on each other, go last
+        for (i, tx) in self.txs.iter().enumerate() {
+            if !self.selected[i] {
+                ordered.push(tx.info.clone());
+            }
+        }
+        ordered
+    }
+}
+
+/// Consider transactions as packages: a transaction and the pending transactions which must be
+/// mined before it, because they use lower nonces of its origin or its sponsor account.
+/// Packages are ordered by their combined fee rate, so a high-fee transaction can pull its
+/// cheaper ancestors into the block.  The mempool walk loads up to `MAX_PACKAGE_TXS - 1` pending
+/// ancestors of each candidate, even if their fee rates are too low for them to be candidates.
+#[derive(Debug, Clone, Default)]
+pub struct PackagePolicy;
+
+impl TxSelectionPolicy for PackagePolicy {
+    fn name(&self) -> &'static str {
+        "package"
+    }
+
+    fn max_ancestors(&self) -> u64 {
+        MAX_PACKAGE_TXS as u64 - 1
+    }
+
+    fn order_candidates(&self, candidates: Vec<SelectionCandidate>) -> Vec<MemPoolTxInfoPartial> {
+        PackageGraph::new(candidates).order()
+    }
+}
+
+/// Consider transactions from the given sponsors, or which call or deploy the given contracts,
+/// before all others.  Each group is ordered by fee rate.
+#[derive(Debug, Clone, Default)]
+pub struct PriorityPolicy {
+    /// Sponsor addresses to prioritize.  Transactions which are not sponsored match on their
+    /// origin address.
+    pub sponsors: HashSet<StacksAddress>,
+    pub contracts: HashSet<QualifiedContractIdentifier>,
+}
+
+impl PriorityPolicy {
+    fn is_priority(&self, candidate: &SelectionCandidate) -> bool {
+        self.sponsors.contains(&candidate.info.sponsor_address)
+            || candidate
+                .contract
+                .as_ref()
+                .is_some_and(|contract| self.contracts.contains(contract))
+    }
+}
+
+impl TxSelectionPolicy for PriorityPolicy {
+    fn name(&self) -> &'static str {
+        "priority"
+    }
+
+    fn needs_contracts(&self) -> bool {
+        !self.contracts.is_empty()
+    }
+
+    fn order_candidates(
+        &self,
+        mut candidates: Vec<SelectionCandidate>,
+    ) -> Vec<MemPoolTxInfoPartial> {
+        candidates.sort_by(|a, b| {
+            self.is_priority(b)
+                .cmp(&self.is_priority(a))
+                .then_with(|| cmp_fee_rate(a, b))
+        });
+        candidates.into_iter().map(|c| c.info).collect()
+    }
+}
+
+/// The miner's configured transaction selection strategy
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum TxSelectionStrategy {
+    /// Walk the mempool in fee rate order, considering a transaction without a fee rate estimate
+    /// with probability `probability_pick_no_estimate_tx`
+    #[default]
+    FeeRateWithExploration,
+    /// See `FeeRatePolicy`
+    FeeRate,
+    /// See `PackagePolicy`
+    Package,
+    /// See `PriorityPolicy`
+    Priority {
+        sponsors: HashSet<StacksAddress>,
+        contracts: HashSet<QualifiedContractIdentifier>,
+    },
+}
+
+impl FromStr for TxSelectionStrategy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fee_rate_with_exploration" => Ok(Self::FeeRateWithExploration),
+            "fee_rate" => Ok(Self::FeeRate),
+            "package" => Ok(Self::Package),
+            "priority" => Ok(Self::Priority {
+                sponsors: HashSet::new(),
+                contracts: HashSet::new(),
+            }),
+            _ => Err(format!("Unknown transaction selection policy '{s}'")),
+        }
+    }
+}
+
+impl TxSelectionStrategy {
+    /// Instantiate the policy for the mempool walk.  `None` means the mempool's built-in walk
+    /// (`FeeRateWithExploration`).
+    pub fn make_policy(&self) -> Option<Arc<dyn TxSelectionPolicy>> {
+        match self {
+            Self::FeeRateWithExploration => None,
+            Self::FeeRate => Some(Arc::new(FeeRatePolicy)),
+            Self::Package => Some(Arc::new(PackagePolicy)),
+            Self::Priority {
+                sponsors,
+                contracts,
+            } => Some(Arc::new(PriorityPolicy {
+                sponsors: sponsors.clone(),
+                contracts: contracts.clone(),
+            })),
+        }
+    }
+}
@@ -14,7 +14,9 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 use std::time::Duration;
 use std::{cmp, io};
 
@@ -42,6 +44,7 @@ use stacks_common::util::{get_epoch_time_ms, get_epoch_time_secs, log, sleep_ms}
 use super::MemPoolDB;
 use crate::burnchains::{Address, Txid};
 use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::nakamoto::NakamotoBlock;
 use crate::chainstate::stacks::db::blocks::MemPoolRejection;
 use crate::chainstate::stacks::db::test::{
     chainstate_path, instantiate_chainstate, instantiate_chainstate_with_balances,
@@ -49,21 +52,24 @@ use crate::chainstate::stacks::db::test::{
 use crate::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo};
 use crate::chainstate::stacks::events::StacksTransactionReceipt;
 use crate::chainstate::stacks::index::MarfTrieId;
-use crate::chainstate::stacks::miner::TransactionResult;
+use crate::chainstate::stacks::miner::{TransactionEvent, TransactionResult};
 use crate::chainstate::stacks::test::codec_all_transactions;
 use crate::chainstate::stacks::{
     CoinbasePayload, Error as ChainstateError, SinglesigHashMode, SinglesigSpendingCondition,
-    StacksBlockHeader, StacksMicroblockHeader, StacksPrivateKey, StacksPublicKey,
-    StacksTransaction, StacksTransactionSigner, TokenTransferMemo, TransactionAnchorMode,
-    TransactionAuth, TransactionContractCall, TransactionPayload, TransactionPostConditionMode,
-    TransactionPublicKeyEncoding, TransactionSmartContract, TransactionSpendingCondition,
-    TransactionVersion, C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+    StacksBlock, StacksBlockHeader, StacksMicroblock, StacksMicroblockHeader, StacksPrivateKey,
+    StacksPublicKey, StacksTransaction, StacksTransactionSigner, TokenTransferMemo,
+    TransactionAnchorMode, TransactionAuth, TransactionContractCall, TransactionPayload,
+    TransactionPostConditionMode, TransactionPublicKeyEncoding, TransactionSmartContract,
+    TransactionSpendingCondition, TransactionVersion, C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
     C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
 };
 use crate::core::mempool::{
-    db_get_all_nonces, MemPoolSyncData, MemPoolWalkSettings, MemPoolWalkTxTypes, TxTag,
-    BLOOM_COUNTER_DEPTH, BLOOM_COUNTER_ERROR_RATE, MAX_BLOOM_COUNTER_TXS,
+    db_get_all_nonces, MemPoolAdmissionPolicy, MemPoolDropReason, MemPoolEventDispatcher,
+    MemPoolSyncData, MemPoolTxFilter, MemPoolWalkSettings, MemPoolWalkTxTypes,
+    ProposalCallbackReceiver, TxTag, BLOOM_COUNTER_DEPTH, BLOOM_COUNTER_ERROR_RATE,
+    MAX_BLOOM_COUNTER_TXS,
 };
+use crate::core::selection::TxSelectionStrategy;
 use crate::core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
 use crate::net::Error as NetError;
 use crate::util_lib::bloom::test::setup_bloom_counter;
@@ -753,6 +759,250 @@ fn test_iterate_candidates_consider_no_estimate_tx_prob() {
     );
 }
 
+#[test]
+/// This test verifies that `iterate_candidates` considers transactions in the order of the
+/// configured selection policy, subject to nonce order.
+fn test_iterate_candidates_selection_policy() {
+    let mut chainstate =
+        instantiate_chainstate_with_balances(false, 0x80000000, function_name!(), vec![]);
+    let chainstate_path = chainstate_path(function_name!());
+    let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+    let b_1 = make_block(
+        &mut chainstate,
+        ConsensusHash([0x1; 20]),
+        &(
+            FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+            FIRST_STACKS_BLOCK_HASH.clone(),
+        ),
+        1,
+        1,
+    );
+    let b_2 = make_block(&mut chainstate, ConsensusHash([0x2; 20]), &b_1, 2, 2);
+
+    let mut txs = codec_all_transactions(
+        &TransactionVersion::Testnet,
+        0x80000000,
+        &TransactionAnchorMode::Any,
+        &TransactionPostConditionMode::Allow,
+        StacksEpochId::latest(),
+    );
+
+    let addr_a = StacksAddress::new(22, Hash160::from_data(&[0xa; 32])).unwrap();
+    let addr_b = StacksAddress::new(22, Hash160::from_data(&[0xb; 32])).unwrap();
+    let addr_c = StacksAddress::new(22, Hash160::from_data(&[0xc; 32])).unwrap();
+
+    // a0 is cheap, but unlocks the very profitable a1
+    let mut txids = HashMap::new();
+    for (name, addr, nonce, fee_rate) in [
+        ("a0", &addr_a, 0, 1.0),
+        ("a1", &addr_a, 1, 100.0),
+        ("b0", &addr_b, 0, 1.5),
+        ("c0", &addr_c, 0, 0.5),
+    ] {
+        let mut tx = txs.pop().unwrap();
+        tx.set_tx_fee(100);
+        let txid = tx.txid();
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        MemPoolDB::try_add_tx(
+            &mut mempool_tx,
+            &mut chainstate,
+            &b_1.0,
+            &b_1.1,
+            true,
+            txid,
+            tx.serialize_to_vec(),
+            tx.get_tx_fee(),
+            100,
+            addr,
+            nonce,
+            addr,
+            nonce,
+            None,
+        )
+        .unwrap();
+        mempool_tx
+            .execute(
+                "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
+                params![Some(fee_rate), txid],
+            )
+            .unwrap();
+        mempool_tx.commit().unwrap();
+        txids.insert(txid, name);
+    }
+
+    // only the `max_selection_candidates` highest fee rate transactions are ordered
+    let policies = [
+        (
+            TxSelectionStrategy::FeeRate,
+            64,
+            vec!["b0", "a0", "a1", "c0"],
+        ),
+        (TxSelectionStrategy::FeeRate, 3, vec!["b0", "a0", "a1"]),
+        (
+            TxSelectionStrategy::Package,
+            64,
+            vec!["a0", "a1", "b0", "c0"],
+        ),
+        (
+            TxSelectionStrategy::Priority {
+                sponsors: HashSet::from([addr_c.clone()]),
+                contracts: HashSet::new(),
+            },
+            64,
+            vec!["c0", "b0", "a0", "a1"],
+        ),
+    ];
+    for (strategy, max_candidates, expected) in policies {
+        mempool.reset_nonce_cache().unwrap();
+        let mut mempool_settings = MemPoolWalkSettings::default();
+        mempool_settings.selection_policy = strategy.make_policy();
+        mempool_settings.max_selection_candidates = max_candidates;
+        let mut tx_events = Vec::new();
+        let mut considered = vec![];
+        chainstate.with_read_only_clarity_tx(
+            &TEST_BURN_STATE_DB,
+            &StacksBlockHeader::make_index_block_hash(&b_2.0, &b_2.1),
+            |clarity_conn| {
+                mempool
+                    .iterate_candidates::<_, ChainstateError, _>(
+                        clarity_conn,
+                        &mut tx_events,
+                        mempool_settings,
+                        |_, available_tx, _| {
+                            considered.push(txids[&available_tx.tx.tx.txid()]);
+                            Ok(Some(
+                                TransactionResult::success(
+                                    &available_tx.tx.tx,
+                                    available_tx.tx.metadata.tx_fee,
+                                    StacksTransactionReceipt::from_stx_transfer(
+                                        available_tx.tx.tx.clone(),
+                                        vec![],
+                                        Value::okay(Value::Bool(true)).unwrap(),
+                                        ExecutionCost::ZERO,
+                                    ),
+                                )
+                                .convert_to_event(),
+                            ))
+                        },
+                    )
+                    .unwrap();
+            },
+        );
+        assert_eq!(considered, expected, "Unexpected order for {strategy:?}");
+    }
+}
+
+#[test]
+/// This test verifies that the package policy loads the pending ancestors of its candidates,
+/// by origin and by sponsor nonce, even if their fee rates are too low for them to be
+/// candidates themselves.
+fn test_iterate_candidates_package_ancestors() {
+    let mut chainstate =
+        instantiate_chainstate_with_balances(false, 0x80000000, function_name!(), vec![]);
+    let chainstate_path = chainstate_path(function_name!());
+    let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+    let b_1 = make_block(
+        &mut chainstate,
+        ConsensusHash([0x1; 20]),
+        &(FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH),
+        1,
+        1,
+    );
+    let b_2 = make_block(&mut chainstate, ConsensusHash([0x2; 20]), &b_1, 2, 2);
+
+    let mut txs = codec_all_transactions(
+        &TransactionVersion::Testnet,
+        0x80000000,
+        &TransactionAnchorMode::Any,
+        &TransactionPostConditionMode::Allow,
+        StacksEpochId::latest(),
+    );
+
+    let addr_a = StacksAddress::new(22, Hash160::from_data(&[0xa; 32])).unwrap();
+    let addr_d = StacksAddress::new(22, Hash160::from_data(&[0xd; 32])).unwrap();
+    let addr_s = StacksAddress::new(22, Hash160::from_data(&[0x5; 32])).unwrap();
+
+    // a1 needs a0, whose origin nonce precedes its own.  d0 is sponsored by s, so it needs s0,
+    // which uses the preceding nonce of its sponsor.  Only a1 and d0 are candidates.
+    let mut txids = HashMap::new();
+    for (name, origin, origin_nonce, sponsor, sponsor_nonce, fee_rate) in [
+        ("a0", &addr_a, 0, &addr_a, 0, 0.1),
+        ("a1", &addr_a, 1, &addr_a, 1, 100.0),
+        ("s0", &addr_s, 0, &addr_s, 0, 0.2),
+        ("d0", &addr_d, 0, &addr_s, 1, 50.0),
+    ] {
+        let mut tx = txs.pop().unwrap();
+        tx.set_tx_fee(100);
+        let txid = tx.txid();
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        MemPoolDB::try_add_tx(
+            &mut mempool_tx,
+            &mut chainstate,
+            &b_1.0,
+            &b_1.1,
+            true,
+            txid,
+            tx.serialize_to_vec(),
+            tx.get_tx_fee(),
+            100,
+            origin,
+            origin_nonce,
+            sponsor,
+            sponsor_nonce,
+            None,
+        )
+        .unwrap();
+        mempool_tx
+            .execute(
+                "UPDATE mempool SET fee_rate = ? WHERE txid = ?",
+                params![Some(fee_rate), txid],
+            )
+            .unwrap();
+        mempool_tx.commit().unwrap();
+        txids.insert(txid, name);
+    }
+
+    mempool.reset_nonce_cache().unwrap();
+    let mempool_settings = MemPoolWalkSettings {
+        selection_policy: TxSelectionStrategy::Package.make_policy(),
+        max_selection_candidates: 2,
+        ..MemPoolWalkSettings::default()
+    };
+    let mut tx_events = Vec::new();
+    let mut considered = vec![];
+    chainstate.with_read_only_clarity_tx(
+        &TEST_BURN_STATE_DB,
+        &StacksBlockHeader::make_index_block_hash(&b_2.0, &b_2.1),
+        |clarity_conn| {
+            mempool
+                .iterate_candidates::<_, ChainstateError, _>(
+                    clarity_conn,
+                    &mut tx_events,
+                    mempool_settings,
+                    |_, available_tx, _| {
+                        considered.push(txids[&available_tx.tx.tx.txid()]);
+                        Ok(Some(
+                            TransactionResult::success(
+                                &available_tx.tx.tx,
+                                available_tx.tx.metadata.tx_fee,
+                                StacksTransactionReceipt::from_stx_transfer(
+                                    available_tx.tx.tx.clone(),
+                                    vec![],
+                                    Value::okay(Value::Bool(true)).unwrap(),
+                                    ExecutionCost::ZERO,
+                                ),
+                            )
+                            .convert_to_event(),
+                        ))
+                    },
+                )
+                .unwrap();
+        },
+    );
+    // the package of d0 has the higher combined fee rate
+    assert_eq!(considered, vec!["s0", "d0", "a0", "a1"]);
+}
+
 #[test]
 /// This test verifies that when a transaction is skipped, other transactions
 /// from the same address with higher nonces are not considered for inclusion in a block.
@@ -1365,6 +1615,431 @@ fn mempool_do_not_replace_tx() {
     assert!(!MemPoolDB::db_has_tx(&mempool_tx, &txid).unwrap());
 }
 
+#[test]
+fn mempool_admission_policy() {
+    let mut chainstate =
+        instantiate_chainstate_with_balances(false, 0x80000000, function_name!(), vec![]);
+    let b_1 = make_block(
+        &mut chainstate,
+        ConsensusHash([0x1; 20]),
+        &(
+            FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+            FIRST_STACKS_BLOCK_HASH.clone(),
+        ),
+        1,
+        1,
+    );
+
+    let chainstate_path = chainstate_path(function_name!());
+    let mut mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+
+    let mut txs = codec_all_transactions(
+        &TransactionVersion::Testnet,
+        0x80000000,
+        &TransactionAnchorMode::Any,
+        &TransactionPostConditionMode::Allow,
+        StacksEpochId::latest(),
+    );
+    let mut tx = txs.pop().unwrap();
+    let tx_len = tx.serialize_to_vec().len() as u64;
+
+    let mut try_add = |mempool: &mut MemPoolDB, addr_byte: u8, tx_fee: u64| {
+        let addr = StacksAddress::new(22, Hash160::from_data(&[addr_byte; 32])).unwrap();
+        tx.set_tx_fee(tx_fee);
+        let txid = tx.txid();
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        let result = MemPoolDB::try_add_tx(
+            &mut mempool_tx,
+            &mut chainstate,
+            &b_1.0,
+            &b_1.1,
+            true,
+            txid.clone(),
+            tx.serialize_to_vec(),
+            tx_fee,
+            100,
+            &addr,
+            0,
+            &addr,
+            0,
+            None,
+        );
+        mempool_tx.commit().unwrap();
+        result.map(|_| txid)
+    };
+
+    // replace-by-fee needs a 10% bump
+    mempool.set_admission_policy(MemPoolAdmissionPolicy {
+        rbf_fee_bump_percent: 10,
+        ..MemPoolAdmissionPolicy::default()
+    });
+    let txid_a = try_add(&mut mempool, 0xa, 100).unwrap();
+    match try_add(&mut mempool, 0xa, 105).unwrap_err() {
+        MemPoolRejection::ReplaceFeeTooLow(105, 110) => {}
+        e => panic!("Unexpected rejection: {e:?}"),
+    }
+    assert!(MemPoolDB::db_has_tx(mempool.conn(), &txid_a).unwrap());
+    let txid_a = try_add(&mut mempool, 0xa, 110).unwrap();
+
+    // room for two transactions
+    mempool.set_admission_policy(MemPoolAdmissionPolicy {
+        max_bytes: Some(2 * tx_len),
+        ..MemPoolAdmissionPolicy::default()
+    });
+    let txid_b = try_add(&mut mempool, 0xb, 50).unwrap();
+
+    // too cheap to evict anything
+    match try_add(&mut mempool, 0xc, 10).unwrap_err() {
+        MemPoolRejection::MemPoolFull => {}
+        e => panic!("Unexpected rejection: {e:?}"),
+    }
+
+    // replacements don't need more room
+    let txid_b_2 = try_add(&mut mempool, 0xb, 60).unwrap();
+    assert!(!MemPoolDB::db_has_tx(mempool.conn(), &txid_b).unwrap());
+
+    // evicts the cheapest transaction
+    let txid_c = try_add(&mut mempool, 0xc, 80).unwrap();
+    assert!(MemPoolDB::db_has_tx(mempool.conn(), &txid_a).unwrap());
+    assert!(!MemPoolDB::db_has_tx(mempool.conn(), &txid_b_2).unwrap());
+    assert!(MemPoolDB::db_has_tx(mempool.conn(), &txid_c).unwrap());
+}
+
+/// Records the transactions dropped from the mempool
+#[derive(Default)]
+struct DroppedTxsObserver {
+    dropped: RefCell<Vec<(Vec<Txid>, String)>>,
+}
+
+impl MemPoolEventDispatcher for DroppedTxsObserver {
+    fn get_proposal_callback_receiver(&self) -> Option<Box<dyn ProposalCallbackReceiver>> {
+        None
+    }
+
+    fn mempool_txs_dropped(
+        &self,
+        txids: Vec<Txid>,
+        _new_txid: Option<Txid>,
+        reason: MemPoolDropReason,
+    ) {
+        self.dropped.borrow_mut().push((txids, reason.to_string()));
+    }
+
+    fn mined_block_event(
+        &self,
+        _target_burn_height: u64,
+        _block: &StacksBlock,
+        _block_size_bytes: u64,
+        _consumed: &ExecutionCost,
+        _confirmed_microblock_cost: &ExecutionCost,
+        _tx_results: Vec<TransactionEvent>,
+    ) {
+    }
+
+    fn mined_microblock_event(
+        &self,
+        _microblock: &StacksMicroblock,
+        _tx_results: Vec<TransactionEvent>,
+        _anchor_block_consensus_hash: ConsensusHash,
+        _anchor_block: BlockHeaderHash,
+    ) {
+    }
+
+    fn mined_nakamoto_block_event(
+        &self,
+        _target_burn_height: u64,
+        _block: &NakamotoBlock,
+        _block_size_bytes: u64,
+        _consumed: &ExecutionCost,
+        _tx_results: Vec<TransactionEvent>,
+    ) {
+    }
+}
+
+/// Set up a mempool with a helper to add transactions from the given origin and sponsor
+/// addresses (by address byte) and nonces, and an observer of the transactions it drops
+fn setup_admission_test(
+    test_name: &str,
+) -> (
+    MemPoolDB,
+    impl FnMut(&mut MemPoolDB, (u8, u64), (u8, u64), u64) -> Result<Txid, MemPoolRejection>,
+    u64,
+    Rc<DroppedTxsObserver>,
+) {
+    let mut chainstate = instantiate_chainstate_with_balances(false, 0x80000000, test_name, vec![]);
+    let b_1 = make_block(
+        &mut chainstate,
+        ConsensusHash([0x1; 20]),
+        &(
+            FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+            FIRST_STACKS_BLOCK_HASH.clone(),
+        ),
+        1,
+        1,
+    );
+
+    let chainstate_path = chainstate_path(test_name);
+    let mempool = MemPoolDB::open_test(false, 0x80000000, &chainstate_path).unwrap();
+
+    let mut txs = codec_all_transactions(
+        &TransactionVersion::Testnet,
+        0x80000000,
+        &TransactionAnchorMode::Any,
+        &TransactionPostConditionMode::Allow,
+        StacksEpochId::latest(),
+    );
+    let mut tx = txs.pop().unwrap();
+    let tx_len = tx.serialize_to_vec().len() as u64;
+    let observer = Rc::new(DroppedTxsObserver::default());
+    let tx_observer = observer.clone();
+
+    let try_add = move |mempool: &mut MemPoolDB,
+                        (origin_byte, origin_nonce): (u8, u64),
+                        (sponsor_byte, sponsor_nonce): (u8, u64),
+                        tx_fee: u64| {
+        let origin = StacksAddress::new(22, Hash160::from_data(&[origin_byte; 32])).unwrap();
+        let sponsor = StacksAddress::new(22, Hash160::from_data(&[sponsor_byte; 32])).unwrap();
+        // the fee and nonce keep the txids distinct
+        tx.set_tx_fee(tx_fee);
+        tx.set_origin_nonce(origin_nonce);
+        let txid = tx.txid();
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        let result = MemPoolDB::try_add_tx(
+            &mut mempool_tx,
+            &mut chainstate,
+            &b_1.0,
+            &b_1.1,
+            true,
+            txid.clone(),
+            tx.serialize_to_vec(),
+            tx_fee,
+            100,
+            &origin,
+            origin_nonce,
+            &sponsor,
+            sponsor_nonce,
+            None,
+        );
+        mempool_tx
+            .commit_and_announce(Some(tx_observer.as_ref()))
+            .unwrap();
+        result.map(|_| txid)
+    };
+    (mempool, try_add, tx_len, observer)
+}
+
+#[test]
+fn mempool_pending_limits() {
+    let (mut mempool, mut try_add, ..) = setup_admission_test(function_name!());
+    let addr = |byte: u8| StacksAddress::new(22, Hash160::from_data(&[byte; 32])).unwrap();
+
+    // three pending transactions from 0xa, one of which sponsors 0xb's transaction
+    try_add(&mut mempool, (0xa, 0), (0xa, 0), 100).unwrap();
+    try_add(&mut mempool, (0xa, 1), (0xa, 1), 101).unwrap();
+    try_add(&mut mempool, (0xa, 2), (0xa, 2), 102).unwrap();
+    try_add(&mut mempool, (0xb, 0), (0xa, 3), 103).unwrap();
+
+    // the origin cap doesn't count sponsored transactions
+    match MemPoolDB::check_pending_limit(mempool.conn(), 3, true, &addr(0xa), 0, 3).unwrap_err() {
+        MemPoolRejection::TooManyPendingTransactions {
+            is_origin: true,
+            max_pending: 3,
+            ..
+        } => {}
+        e => panic!("Unexpected rejection: {e:?}"),
+    }
+    MemPoolDB::check_pending_limit(mempool.conn(), 4, true, &addr(0xa), 0, 3).unwrap();
+    // replacements don't add a pending transaction
+    MemPoolDB::check_pending_limit(mempool.conn(), 3, true, &addr(0xa), 0, 1).unwrap();
+    // transactions below the account nonce have been mined
+    MemPoolDB::check_pending_limit(mempool.conn(), 3, true, &addr(0xa), 1, 3).unwrap();
+
+    // the sponsor cap only counts transactions sponsored for others
+    match MemPoolDB::check_pending_limit(mempool.conn(), 1, false, &addr(0xa), 0, 4).unwrap_err() {
+        MemPoolRejection::TooManyPendingTransactions {
+            is_origin: false,
+            max_pending: 1,
+            ..
+        } => {}
+        e => panic!("Unexpected rejection: {e:?}"),
+    }
+    MemPoolDB::check_pending_limit(mempool.conn(), 2, false, &addr(0xa), 0, 4).unwrap();
+    MemPoolDB::check_pending_limit(mempool.conn(), 1, false, &addr(0xa), 4, 4).unwrap();
+}
+
+#[test]
+fn mempool_size_limit_evicts_chain_tails() {
+    let (mut mempool, mut try_add, tx_len, observer) = setup_admission_test(function_name!());
+    let assert_total_bytes = |mempool: &MemPoolDB| {
+        let sum: i64 = mempool
+            .conn()
+            .query_row("SELECT IFNULL(SUM(length), 0) FROM mempool", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(
+            MemPoolDB::get_total_bytes(mempool.conn()).unwrap(),
+            u64::try_from(sum).unwrap()
+        );
+    };
+
+    // 0xa's cheap transaction is followed by an expensive one
+    let txid_a_0 = try_add(&mut mempool, (0xa, 0), (0xa, 0), 10).unwrap();
+    let txid_a_1 = try_add(&mut mempool, (0xa, 1), (0xa, 1), 100).unwrap();
+    let txid_b_0 = try_add(&mut mempool, (0xb, 0), (0xb, 0), 50).unwrap();
+    // 0xd sponsors 0xc's transaction, and then sends an expensive transaction of its own
+    let txid_c_0 = try_add(&mut mempool, (0xc, 0), (0xd, 0), 20).unwrap();
+    let txid_d_1 = try_add(&mut mempool, (0xd, 1), (0xd, 1), 90).unwrap();
+
+    mempool.set_admission_policy(MemPoolAdmissionPolicy {
+        max_bytes: Some(5 * tx_len),
+        ..MemPoolAdmissionPolicy::default()
+    });
+
+    // 0xa's and 0xc's transactions are the cheapest, but are followed by others
+    let txid_e_0 = try_add(&mut mempool, (0xe, 0), (0xe, 0), 60).unwrap();
+    assert!(!MemPoolDB::db_has_tx(mempool.conn(), &txid_b_0).unwrap());
+    for txid in [&txid_a_0, &txid_a_1, &txid_c_0, &txid_d_1, &txid_e_0] {
+        assert!(MemPoolDB::db_has_tx(mempool.conn(), txid).unwrap());
+    }
+    assert_total_bytes(&mempool);
+    assert_eq!(
+        observer.dropped.take(),
+        vec![(vec![txid_b_0.clone()], "SizeLimitEviction".to_string())]
+    );
+    let bf = mempool.get_txid_bloom_filter().unwrap();
+    assert!(!bf.contains_raw(&txid_b_0.0));
+    assert!(bf.contains_raw(&txid_e_0.0));
+
+    // too cheap to evict the chain tails, although a transaction further down pays less
+    match try_add(&mut mempool, (0xf, 0), (0xf, 0), 55).unwrap_err() {
+        MemPoolRejection::MemPoolFull => {}
+        e => panic!("Unexpected rejection: {e:?}"),
+    }
+
+    // a new transaction doesn't evict the transactions it follows
+    match try_add(&mut mempool, (0xe, 1), (0xe, 1), 65).unwrap_err() {
+        MemPoolRejection::MemPoolFull => {}
+        e => panic!("Unexpected rejection: {e:?}"),
+    }
+    assert!(observer.dropped.borrow().is_empty());
+
+    // evicting a chain's tail exposes the transactions before it: 0xd's own transaction is
+    // evicted before the one it sponsors
+    mempool.set_admission_policy(MemPoolAdmissionPolicy {
+        max_bytes: Some(3 * tx_len),
+        ..MemPoolAdmissionPolicy::default()
+    });
+    let txid_f_0 = try_add(&mut mempool, (0xf, 0), (0xf, 0), 95).unwrap();
+    for txid in [&txid_e_0, &txid_d_1, &txid_c_0] {
+        assert!(!MemPoolDB::db_has_tx(mempool.conn(), txid).unwrap());
+    }
+    for txid in [&txid_a_0, &txid_a_1, &txid_f_0] {
+        assert!(MemPoolDB::db_has_tx(mempool.conn(), txid).unwrap());
+    }
+    assert_total_bytes(&mempool);
+    assert_eq!(
+        observer.dropped.take(),
+        vec![(
+            vec![txid_e_0, txid_d_1, txid_c_0],
+            "SizeLimitEviction".to_string()
+        )]
+    );
+
+    // replacements keep the running total
+    try_add(&mut mempool, (0xa, 1), (0xa, 1), 200).unwrap();
+    assert_total_bytes(&mempool);
+}
+
+#[test]
+fn mempool_fee_rate_distribution_skips_mined_txs() {
+    let (mut mempool, mut try_add, ..) = setup_admission_test(function_name!());
+
+    // mined, going by 0xa's nonce
+    let txid_a_0 = try_add(&mut mempool, (0xa, 0), (0xa, 0), 100).unwrap();
+    let txid_a_1 = try_add(&mut mempool, (0xa, 1), (0xa, 1), 200).unwrap();
+    // mined, going by its sponsor 0xc's nonce
+    let txid_b_0 = try_add(&mut mempool, (0xb, 0), (0xc, 0), 300).unwrap();
+    // 0xd's nonce isn't known
+    let txid_d_5 = try_add(&mut mempool, (0xd, 5), (0xd, 5), 400).unwrap();
+    // no fee rate estimate
+    try_add(&mut mempool, (0xe, 0), (0xe, 0), 500).unwrap();
+
+    for (txid, fee_rate) in [
+        (&txid_a_0, 10.0),
+        (&txid_a_1, 20.0),
+        (&txid_b_0, 30.0),
+        (&txid_d_5, 40.0),
+    ] {
+        mempool
+            .conn()
+            .execute(
+                "UPDATE mempool SET fee_rate = ?1 WHERE txid = ?2",
+                params![fee_rate, txid],
+            )
+            .unwrap();
+    }
+    for (byte, nonce) in [(0xa, 1), (0xc, 1)] {
+        let addr = StacksAddress::new(22, Hash160::from_data(&[byte; 32])).unwrap();
+        mempool
+            .conn()
+            .execute(
+                "INSERT INTO nonces (address, nonce) VALUES (?1, ?2)",
+                params![addr.to_string(), nonce],
+            )
+            .unwrap();
+    }
+
+    assert_eq!(
+        MemPoolDB::get_fee_rate_distribution(mempool.conn(), u64::MAX).unwrap(),
+        vec![(40.0, 10), (20.0, 10)]
+    );
+    // stops once the cost budget is reached
+    assert_eq!(
+        MemPoolDB::get_fee_rate_distribution(mempool.conn(), 10).unwrap(),
+        vec![(40.0, 10)]
+    );
+}
+
+#[test]
+fn mempool_find_tx_nonces() {
+    let (mut mempool, mut try_add, ..) = setup_admission_test(function_name!());
+    let addr = |byte: u8| StacksAddress::new(22, Hash160::from_data(&[byte; 32])).unwrap();
+
+    // 0xa has no transaction with nonce 2, and sponsors 0xb's transaction
+    try_add(&mut mempool, (0xa, 0), (0xa, 0), 100).unwrap();
+    try_add(&mut mempool, (0xa, 1), (0xa, 1), 101).unwrap();
+    try_add(&mut mempool, (0xa, 3), (0xa, 3), 102).unwrap();
+    try_add(&mut mempool, (0xb, 0), (0xa, 5), 103).unwrap();
+
+    // the contract doesn't matter, since none of these transactions call it
+    let by_origin = MemPoolTxFilter {
+        origin: Some(addr(0xa)),
+        contract: Some(QualifiedContractIdentifier::transient()),
+        ..MemPoolTxFilter::default()
+    };
+    assert!(MemPoolDB::find_txs(mempool.conn(), &by_origin, 10)
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        MemPoolDB::find_tx_nonces(mempool.conn(), &by_origin, 0, 10).unwrap(),
+        vec![0, 1, 3]
+    );
+    assert_eq!(
+        MemPoolDB::find_tx_nonces(mempool.conn(), &by_origin, 1, 2).unwrap(),
+        vec![1]
+    );
+
+    let by_sponsor = MemPoolTxFilter {
+        sponsor: Some(addr(0xa)),
+        ..MemPoolTxFilter::default()
+    };
+    assert_eq!(
+        MemPoolDB::find_tx_nonces(mempool.conn(), &by_sponsor, 0, 10).unwrap(),
+        vec![0, 1, 3, 5]
+    );
+}
+
 #[rstest]
 #[case(MempoolCollectionBehavior::ByStacksHeight)]
 #[case(MempoolCollectionBehavior::ByReceiveTime)]
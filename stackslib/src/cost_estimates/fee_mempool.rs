@@ -0,0 +1,166 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+use clarity::vm::costs::ExecutionCost;
+
+use super::metrics::CostMetric;
+use super::{EstimatorError, FeeRateEstimate};
+use crate::chainstate::stacks::MAX_BLOCK_LEN;
+use crate::core::mempool::MemPoolDB;
+use crate::util_lib::db::{DBConn, Error as DBError};
+
+/// How soon a transaction should be mined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmationTarget {
+    /// In the next block
+    NextBlock,
+    /// By the end of the next tenure
+    NextTenure,
+    /// By the end of the given number of tenures, after the ongoing one
+    WithinTenures(u64),
+}
+
+impl fmt::Display for ConfirmationTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NextBlock => write!(f, "next_block"),
+            Self::NextTenure => write!(f, "next_tenure"),
+            Self::WithinTenures(tenures) => write!(f, "within_{tenures}_tenures"),
+        }
+    }
+}
+
+/// This struct estimates the fee rate needed to be mined by a `ConfirmationTarget`, by combining
+/// the historical fee rates from a `FeeEstimator` with the transactions currently in the mempool.
+///
+/// Miners consider transactions in fee rate order, so a transaction is only mined by a target if
+/// it outbids enough of the mempool to fit in the block budget available by then: the rest of
+/// the ongoing tenure's budget for the next block, plus one whole tenure budget for each tenure
+/// after that.  The fee rate of the mempool transaction at that depth is the "clearing" rate.
+/// If the mempool is too small to fill the budget, only the historical estimate applies.
+///
+/// The historical estimate sets a floor for each target: the high estimate for the next block,
+/// the middle estimate for the next tenure, and the low estimate for later tenures.  This keeps
+/// estimates from collapsing while the mempool is briefly empty.
+pub struct MempoolFeeRateEstimator {
+    historical: Option<FeeRateEstimate>,
+    /// Fee rate and scalar cost of each mempool transaction with a fee rate, highest rate first
+    mempool: Vec<(f64, u64)>,
+    /// Scalar size of a whole tenure's budget
+    tenure_capacity: u64,
+    /// Scalar size of what remains of the ongoing tenure's budget
+    remaining_capacity: u64,
+}
+
+impl MempoolFeeRateEstimator {
+    /// Make an estimator from the mempool's fee rate distribution and the given capacities, which
+    /// are in the units of the `CostMetric` that the fee rates were computed with.
+    pub fn new(
+        historical: Option<FeeRateEstimate>,
+        mut mempool: Vec<(f64, u64)>,
+        tenure_capacity: u64,
+        remaining_capacity: u64,
+    ) -> Self {
+        mempool.sort_by(|a, b| b.0.total_cmp(&a.0));
+        Self {
+            historical,
+            mempool,
+            tenure_capacity,
+            remaining_capacity: remaining_capacity.min(tenure_capacity),
+        }
+    }
+
+    /// Make an estimator from the current contents of the mempool.  `block_limit` is the tenure
+    /// budget, and `tenure_cost` is how much of it the ongoing tenure has spent so far, if known.
+    /// Only as much of the mempool is read as `WithinTenures(max_tenures)` can clear, so the
+    /// estimator cannot answer for later targets.
+    pub fn from_mempool(
+        historical: Option<FeeRateEstimate>,
+        mempool_conn: &DBConn,
+        metric: &dyn CostMetric,
+        block_limit: &ExecutionCost,
+        tenure_cost: Option<&ExecutionCost>,
+        max_tenures: u64,
+    ) -> Result<Self, DBError> {
+        let max_len = u64::from(MAX_BLOCK_LEN);
+        let tenure_capacity = metric.from_cost_and_len(block_limit, block_limit, max_len);
+        let remaining_capacity = match tenure_cost {
+            Some(tenure_cost) => {
+                // each block has its own length limit, so only execution costs carry over
+                let mut remaining = block_limit.clone();
+                if remaining.sub(tenure_cost).is_err() {
+                    remaining = ExecutionCost::ZERO;
+                }
+                metric.from_cost_and_len(&remaining, block_limit, max_len)
+            }
+            None => tenure_capacity,
+        };
+        let max_cost = remaining_capacity
+            .min(tenure_capacity)
+            .saturating_add(tenure_capacity.saturating_mul(max_tenures.max(1)));
+        let fee_rates = MemPoolDB::get_fee_rate_distribution(mempool_conn, max_cost)?;
+        Ok(Self::new(
+            historical,
+            fee_rates,
+            tenure_capacity,
+            remaining_capacity,
+        ))
+    }
+
+    /// The lowest fee rate which outbids enough of the mempool to fit within `capacity`, or
+    /// `None` if the whole mempool fits
+    fn clearing_rate(&self, capacity: u64) -> Option<f64> {
+        let mut total: u64 = 0;
+        for (fee_rate, cost) in self.mempool.iter() {
+            total = total.saturating_add(*cost);
+            if total >= capacity {
+                return Some(*fee_rate);
+            }
+        }
+        None
+    }
+
+    /// Estimate the fee rate needed to be mined by `target`
+    pub fn get_rate_estimate(&self, target: ConfirmationTarget) -> Result<f64, EstimatorError> {
+        // if the ongoing tenure's budget is spent, the next block starts a new tenure
+        let next_block_capacity = if self.remaining_capacity > 0 {
+            self.remaining_capacity
+        } else {
+            self.tenure_capacity
+        };
+        let (capacity, floor) = match target {
+            ConfirmationTarget::NextBlock => (
+                next_block_capacity,
+                self.historical.as_ref().map(|h| h.high),
+            ),
+            ConfirmationTarget::NextTenure => (
+                self.remaining_capacity.saturating_add(self.tenure_capacity),
+                self.historical.as_ref().map(|h| h.middle),
+            ),
+            ConfirmationTarget::WithinTenures(tenures) => (
+                self.remaining_capacity
+                    .saturating_add(self.tenure_capacity.saturating_mul(tenures.max(1))),
+                self.historical.as_ref().map(|h| h.low),
+            ),
+        };
+        match (self.clearing_rate(capacity), floor) {
+            (Some(clearing), Some(floor)) => Ok(clearing.max(floor)),
+            (Some(rate), None) | (None, Some(rate)) => Ok(rate),
+            (None, None) => Err(EstimatorError::NoEstimateAvailable),
+        }
+    }
+}
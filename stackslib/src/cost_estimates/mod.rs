@@ -15,6 +15,7 @@ use crate::chainstate::stacks::events::{StacksTransactionReceipt, TransactionOri
 use crate::chainstate::stacks::{StacksBlock, TransactionPayload};
 
 pub mod fee_medians;
+pub mod fee_mempool;
 pub mod fee_rate_fuzzer;
 pub mod fee_scalar;
 pub mod metrics;
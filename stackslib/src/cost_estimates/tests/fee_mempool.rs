@@ -0,0 +1,140 @@
+use crate::cost_estimates::fee_mempool::{ConfirmationTarget, MempoolFeeRateEstimator};
+use crate::cost_estimates::{EstimatorError, FeeRateEstimate};
+
+/// Mempool transactions as (fee rate, scalar cost), deliberately out of order.  Cumulative costs
+/// in fee rate order are 30, 60, 120, 220, 320.
+fn congested_mempool() -> Vec<(f64, u64)> {
+    vec![
+        (20f64, 60),
+        (100f64, 30),
+        (5f64, 100),
+        (50f64, 30),
+        (10f64, 100),
+    ]
+}
+
+fn historical() -> FeeRateEstimate {
+    FeeRateEstimate {
+        high: 30f64,
+        middle: 15f64,
+        low: 1f64,
+    }
+}
+
+#[test]
+fn test_no_estimate_available() {
+    let estimator = MempoolFeeRateEstimator::new(None, vec![], 100, 100);
+    for target in [
+        ConfirmationTarget::NextBlock,
+        ConfirmationTarget::NextTenure,
+        ConfirmationTarget::WithinTenures(3),
+    ] {
+        assert!(matches!(
+            estimator.get_rate_estimate(target),
+            Err(EstimatorError::NoEstimateAvailable)
+        ));
+    }
+}
+
+#[test]
+fn test_empty_mempool_uses_historical() {
+    let estimator = MempoolFeeRateEstimator::new(Some(historical()), vec![], 100, 40);
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::NextBlock)
+            .unwrap(),
+        30f64
+    );
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::NextTenure)
+            .unwrap(),
+        15f64
+    );
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::WithinTenures(3))
+            .unwrap(),
+        1f64
+    );
+}
+
+#[test]
+fn test_congested_mempool() {
+    // without history, only the mempool's clearing rates apply
+    let estimator = MempoolFeeRateEstimator::new(None, congested_mempool(), 100, 40);
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::NextBlock)
+            .unwrap(),
+        50f64
+    );
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::NextTenure)
+            .unwrap(),
+        10f64
+    );
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::WithinTenures(2))
+            .unwrap(),
+        5f64
+    );
+    // the whole mempool fits in four tenures
+    assert!(matches!(
+        estimator.get_rate_estimate(ConfirmationTarget::WithinTenures(3)),
+        Err(EstimatorError::NoEstimateAvailable)
+    ));
+
+    // with history, each target is at least its historical estimate
+    let estimator = MempoolFeeRateEstimator::new(Some(historical()), congested_mempool(), 100, 40);
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::NextBlock)
+            .unwrap(),
+        50f64
+    );
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::NextTenure)
+            .unwrap(),
+        15f64
+    );
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::WithinTenures(2))
+            .unwrap(),
+        5f64
+    );
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::WithinTenures(3))
+            .unwrap(),
+        1f64
+    );
+}
+
+#[test]
+fn test_spent_tenure_budget() {
+    // the next block starts a new tenure, so it gets a whole tenure budget
+    let estimator = MempoolFeeRateEstimator::new(None, congested_mempool(), 100, 0);
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::NextBlock)
+            .unwrap(),
+        20f64
+    );
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::NextTenure)
+            .unwrap(),
+        20f64
+    );
+    assert_eq!(
+        estimator
+            .get_rate_estimate(ConfirmationTarget::WithinTenures(2))
+            .unwrap(),
+        10f64
+    );
+}
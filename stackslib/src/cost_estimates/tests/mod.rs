@@ -3,6 +3,7 @@ use crate::cost_estimates::FeeRateEstimate;
 pub mod common;
 pub mod cost_estimators;
 pub mod fee_medians;
+pub mod fee_mempool;
 pub mod fee_rate_fuzzer;
 pub mod fee_scalar;
 pub mod metrics;
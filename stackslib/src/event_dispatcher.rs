@@ -18,10 +18,8 @@ use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 use std::sync::mpsc::{channel, Receiver, Sender};
-#[cfg(test)]
-use std::sync::LazyLock;
-use std::sync::{Arc, Mutex};
-use std::thread::sleep;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::thread::{self, sleep};
 use std::time::Duration;
 
 use clarity::vm::analysis::contract_interface_builder::build_contract_interface;
@@ -33,47 +31,48 @@ use lazy_static::lazy_static;
 use rand::Rng;
 use rusqlite::{params, Connection};
 use serde_json::json;
-use stacks::burnchains::{PoxConstants, Txid};
-use stacks::chainstate::burn::operations::BlockstackOperationType;
-use stacks::chainstate::burn::ConsensusHash;
-use stacks::chainstate::coordinator::BlockEventDispatcher;
-use stacks::chainstate::nakamoto::NakamotoBlock;
-use stacks::chainstate::stacks::address::PoxAddress;
-use stacks::chainstate::stacks::boot::{
+use stacks_common::bitvec::BitVec;
+use stacks_common::codec::StacksMessageCodec;
+use stacks_common::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash, StacksBlockId};
+use stacks_common::util::hash::{bytes_to_hex, Sha512Trunc256Sum};
+use stacks_common::util::secp256k1::MessageSignature;
+
+use crate::burnchains::{PoxConstants, Txid};
+use crate::chainstate::burn::operations::BlockstackOperationType;
+use crate::chainstate::burn::ConsensusHash;
+use crate::chainstate::coordinator::BlockEventDispatcher;
+use crate::chainstate::nakamoto::NakamotoBlock;
+use crate::chainstate::stacks::address::PoxAddress;
+use crate::chainstate::stacks::boot::{
     NakamotoSignerEntry, PoxStartCycleInfo, RewardSet, RewardSetData, SIGNERS_NAME,
 };
-use stacks::chainstate::stacks::db::accounts::MinerReward;
-use stacks::chainstate::stacks::db::unconfirmed::ProcessedUnconfirmedState;
-use stacks::chainstate::stacks::db::{MinerRewardInfo, StacksBlockHeaderTypes, StacksHeaderInfo};
-use stacks::chainstate::stacks::events::{
+use crate::chainstate::stacks::db::accounts::MinerReward;
+use crate::chainstate::stacks::db::unconfirmed::ProcessedUnconfirmedState;
+use crate::chainstate::stacks::db::{MinerRewardInfo, StacksBlockHeaderTypes, StacksHeaderInfo};
+use crate::chainstate::stacks::events::{
     StackerDBChunksEvent, StacksBlockEventData, StacksTransactionEvent, StacksTransactionReceipt,
     TransactionOrigin,
 };
-use stacks::chainstate::stacks::miner::TransactionEvent;
-use stacks::chainstate::stacks::{
+use crate::chainstate::stacks::miner::TransactionEvent;
+use crate::chainstate::stacks::{
     StacksBlock, StacksMicroblock, StacksTransaction, TransactionPayload,
 };
-use stacks::config::{EventKeyType, EventObserverConfig};
-use stacks::core::mempool::{MemPoolDropReason, MemPoolEventDispatcher, ProposalCallbackReceiver};
-use stacks::libstackerdb::StackerDBChunkData;
-use stacks::net::api::postblock_proposal::{
+use crate::config::{EventKeyType, EventObserverConfig, EventObserverFilter, EventSinkKind};
+use crate::core::mempool::{MemPoolDropReason, MemPoolEventDispatcher, ProposalCallbackReceiver};
+use crate::libstackerdb::StackerDBChunkData;
+use crate::net::api::geteventstream::{RPCEventStreamHub, RPCStreamEvent};
+use crate::net::api::postblock_proposal::{
     BlockValidateOk, BlockValidateReject, BlockValidateResponse,
 };
-use stacks::net::atlas::{Attachment, AttachmentInstance};
-use stacks::net::http::HttpRequestContents;
-use stacks::net::httpcore::{send_http_request, StacksHttpRequest};
-use stacks::net::stackerdb::StackerDBEventDispatcher;
-use stacks::util::hash::to_hex;
+use crate::net::atlas::{Attachment, AttachmentInstance};
+use crate::net::stackerdb::StackerDBEventDispatcher;
+use crate::util::hash::to_hex;
 #[cfg(any(test, feature = "testing"))]
-use stacks::util::tests::TestFlag;
-use stacks::util_lib::db::Error as db_error;
-use stacks_common::bitvec::BitVec;
-use stacks_common::codec::StacksMessageCodec;
-use stacks_common::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash, StacksBlockId};
-use stacks_common::types::net::PeerHost;
-use stacks_common::util::hash::{bytes_to_hex, Sha512Trunc256Sum};
-use stacks_common::util::secp256k1::MessageSignature;
-use url::Url;
+use crate::util::tests::TestFlag;
+use crate::util_lib::db::Error as db_error;
+
+pub mod payload_filter;
+pub mod sinks;
 
 #[cfg(any(test, feature = "testing"))]
 lazy_static! {
@@ -86,13 +85,34 @@ pub struct EventObserver {
     /// Path to the database where pending payloads are stored. If `None`, then
     /// the database is not used and events are not recoverable across restarts.
     pub db_path: Option<PathBuf>,
-    /// URL to which events will be sent
+    /// URL to which events will be sent (or, for file and socket sinks, the path to write to)
     pub endpoint: String,
+    /// Transport used to deliver events to this observer
+    pub sink: EventSinkKind,
     /// Timeout for sending events to this observer
     pub timeout: Duration,
     /// If true, the stacks-node will not retry if event delivery fails for any reason.
     /// WARNING: This should not be set on observers that require successful delivery of all events.
     pub disable_retries: bool,
+    /// If set, only the transactions matching this filter (and their events) are included in
+    /// this observer's `/new_block` payloads
+    pub filter: Option<EventObserverFilter>,
+    /// If set, deliveries are handed off to this observer's background delivery thread instead
+    /// of being carried out on the calling thread.
+    delivery_queue: Option<Sender<PendingDelivery>>,
+}
+
+/// A unit of work for an observer's delivery thread
+#[derive(Debug)]
+enum PendingDelivery {
+    /// Send this payload to this destination, without consulting the database
+    Direct {
+        payload: serde_json::Value,
+        destination: String,
+        disable_retries: bool,
+    },
+    /// Send all payloads pending in the database at this path
+    FlushDatabase(PathBuf),
 }
 
 struct ReceiptPayloadInfo<'a> {
@@ -337,6 +357,13 @@ impl RewardSetEventPayload {
 #[cfg(test)]
 static TEST_EVENT_OBSERVER_SKIP_RETRY: LazyLock<TestFlag<bool>> = LazyLock::new(TestFlag::default);
 
+/// Serializes draining of each observer's pending payloads. Observers with background delivery
+/// threads share the same database, so without this two threads could both deliver (and then
+/// both delete) the same pending payload. Keyed by observer endpoint, so that an unreachable
+/// observer only holds up its own deliveries.
+static PENDING_PAYLOADS_LOCKS: LazyLock<Mutex<HashMap<String, Arc<Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
 impl EventObserver {
     fn init_db(db_path: &str) -> Result<Connection, db_error> {
         let conn = Connection::open(db_path)?;
@@ -345,15 +372,43 @@ impl EventObserver {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 url TEXT NOT NULL,
                 payload TEXT NOT NULL,
-                timeout INTEGER NOT NULL
+                timeout INTEGER NOT NULL,
+                observer TEXT
             )",
             [],
         )?;
+        // Databases written before payloads were keyed by observer lack the column
+        let has_observer_column = conn
+            .prepare("SELECT 1 FROM pragma_table_info('pending_payloads') WHERE name = 'observer'")?
+            .exists([])?;
+        if !has_observer_column {
+            conn.execute("ALTER TABLE pending_payloads ADD COLUMN observer TEXT", [])?;
+        }
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS index_pending_payloads_observer ON pending_payloads(observer, id)",
+            [],
+        )?;
         Ok(conn)
     }
 
+    /// Assign the pending payloads written before payloads were keyed by observer to the
+    /// observer at `endpoint`, if they are destined for it
+    fn claim_unkeyed_payloads(
+        conn: &Connection,
+        endpoint: &str,
+        destination_prefix: &str,
+    ) -> Result<(), db_error> {
+        conn.execute(
+            "UPDATE pending_payloads SET observer = ?1
+             WHERE observer IS NULL AND substr(url, 1, length(?2)) = ?2",
+            params![endpoint, destination_prefix],
+        )?;
+        Ok(())
+    }
+
     fn insert_payload(
         conn: &Connection,
+        endpoint: &str,
         url: &str,
         payload: &serde_json::Value,
         timeout: Duration,
@@ -361,8 +416,8 @@ impl EventObserver {
         let payload_text = payload.to_string();
         let timeout_ms: u64 = timeout.as_millis().try_into().expect("Timeout too large");
         conn.execute(
-            "INSERT INTO pending_payloads (url, payload, timeout) VALUES (?1, ?2, ?3)",
-            params![url, payload_text, timeout_ms],
+            "INSERT INTO pending_payloads (url, payload, timeout, observer) VALUES (?1, ?2, ?3, ?4)",
+            params![url, payload_text, timeout_ms, endpoint],
         )?;
         Ok(())
     }
@@ -370,6 +425,7 @@ impl EventObserver {
     /// Insert a payload into the database, retrying on failure.
     fn insert_payload_with_retry(
         conn: &Connection,
+        endpoint: &str,
         url: &str,
         payload: &serde_json::Value,
         timeout: Duration,
@@ -379,7 +435,7 @@ impl EventObserver {
         let max_backoff = Duration::from_secs(5); // Cap the backoff duration
 
         loop {
-            match Self::insert_payload(conn, url, payload, timeout) {
+            match Self::insert_payload(conn, endpoint, url, payload, timeout) {
                 Ok(_) => {
                     // Successful insert, break the loop
                     return;
@@ -403,13 +459,16 @@ impl EventObserver {
         }
     }
 
+    /// Get the payloads pending delivery to the observer at `endpoint`, oldest first
     fn get_pending_payloads(
         conn: &Connection,
+        endpoint: &str,
     ) -> Result<Vec<(i64, String, serde_json::Value, u64)>, db_error> {
-        let mut stmt =
-            conn.prepare("SELECT id, url, payload, timeout FROM pending_payloads ORDER BY id")?;
+        let mut stmt = conn.prepare(
+            "SELECT id, url, payload, timeout FROM pending_payloads WHERE observer = ?1 ORDER BY id",
+        )?;
         let payload_iter = stmt.query_and_then(
-            [],
+            params![endpoint],
             |row| -> Result<(i64, String, serde_json::Value, u64), db_error> {
                 let id: i64 = row.get(0)?;
                 let url: String = row.get(1)?;
@@ -428,8 +487,16 @@ impl EventObserver {
         Ok(())
     }
 
-    fn process_pending_payloads(conn: &Connection) {
-        let pending_payloads = match Self::get_pending_payloads(conn) {
+    /// Deliver the payloads pending delivery to the observer at `endpoint`, oldest first
+    fn process_pending_payloads(conn: &Connection, endpoint: &str) {
+        let lock = PENDING_PAYLOADS_LOCKS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(endpoint.to_string())
+            .or_default()
+            .clone();
+        let _guard = lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let pending_payloads = match Self::get_pending_payloads(conn, endpoint) {
             Ok(payloads) => payloads,
             Err(e) => {
                 error!(
@@ -461,22 +528,17 @@ impl EventObserver {
 
     fn send_payload_directly(
         payload: &serde_json::Value,
-        full_url: &str,
+        destination: &str,
         timeout: Duration,
         disable_retries: bool,
     ) {
         debug!(
-            "Event dispatcher: Sending payload"; "url" => %full_url, "payload" => ?payload
+            "Event dispatcher: Sending payload"; "url" => %destination, "payload" => ?payload
         );
 
-        let url = Url::parse(full_url)
-            .unwrap_or_else(|_| panic!("Event dispatcher: unable to parse {full_url} as a URL"));
-
-        let host = url.host_str().expect("Invalid URL: missing host");
-        let port = url.port_or_known_default().unwrap_or(80);
-        let peerhost: PeerHost = format!("{host}:{port}")
-            .parse()
-            .unwrap_or(PeerHost::DNS(host.to_string(), port));
+        let sink = sinks::sink_for_destination(destination).unwrap_or_else(|e| {
+            panic!("Event dispatcher: unable to deliver to {destination}: {e}")
+        });
 
         let mut backoff = Duration::from_millis(100);
         let mut attempts: i32 = 0;
@@ -484,30 +546,11 @@ impl EventObserver {
         let max_backoff = timeout.saturating_mul(3);
 
         loop {
-            let mut request = StacksHttpRequest::new_for_peer(
-                peerhost.clone(),
-                "POST".into(),
-                url.path().into(),
-                HttpRequestContents::new().payload_json(payload.clone()),
-            )
-            .unwrap_or_else(|_| panic!("FATAL: failed to encode infallible data as HTTP request"));
-            request.add_header("Connection".into(), "close".into());
-            match send_http_request(host, port, request, timeout) {
-                Ok(response) => {
-                    if response.preamble().status_code == 200 {
-                        debug!(
-                            "Event dispatcher: Successful POST"; "url" => %url
-                        );
-                        break;
-                    } else {
-                        error!(
-                            "Event dispatcher: Failed POST"; "url" => %url, "response" => ?response.preamble()
-                        );
-                    }
-                }
+            match sink.deliver(payload, timeout) {
+                Ok(()) => break,
                 Err(err) => {
                     warn!(
-                        "Event dispatcher: connection or request failed to {host}:{port} - {err:?}";
+                        "Event dispatcher: delivery to {destination} failed - {err:?}";
                         "backoff" => ?backoff,
                         "attempts" => attempts
                     );
@@ -535,21 +578,44 @@ impl EventObserver {
         }
     }
 
-    fn new(
+    #[cfg(any(test, feature = "testing"))]
+    pub fn new(
+        working_dir: Option<PathBuf>,
+        endpoint: String,
+        timeout: Duration,
+        disable_retries: bool,
+    ) -> Self {
+        Self::new_with_sink(
+            working_dir,
+            endpoint,
+            EventSinkKind::Http,
+            timeout,
+            disable_retries,
+        )
+    }
+
+    fn new_with_sink(
         working_dir: Option<PathBuf>,
         endpoint: String,
+        sink: EventSinkKind,
         timeout: Duration,
         disable_retries: bool,
     ) -> Self {
         let db_path = if let Some(mut db_path) = working_dir {
             db_path.push("event_observers.sqlite");
 
-            Self::init_db(
+            let conn = Self::init_db(
                 db_path
                     .to_str()
                     .expect("Failed to convert chainstate path to string"),
             )
             .expect("Failed to initialize database for event observer");
+            Self::claim_unkeyed_payloads(
+                &conn,
+                &endpoint,
+                &sinks::destination_for(sink, &endpoint, ""),
+            )
+            .expect("Failed to claim pending payloads for event observer");
             Some(db_path)
         } else {
             None
@@ -558,37 +624,108 @@ impl EventObserver {
         EventObserver {
             db_path,
             endpoint,
+            sink,
             timeout,
             disable_retries,
+            filter: None,
+            delivery_queue: None,
+        }
+    }
+
+    /// Spawn a thread which carries out this observer's deliveries from now on, so that
+    /// `send_payload()` no longer blocks on the observer.
+    fn start_delivery_thread(&mut self) {
+        let (queue, deliveries) = channel();
+        let observer = self.clone();
+        let spawn_result = thread::Builder::new()
+            .name(format!("event-observer-{}", &self.endpoint))
+            .spawn(move || {
+                while let Ok(delivery) = deliveries.recv() {
+                    observer.deliver(delivery);
+                }
+                debug!("Event observer delivery thread exiting"; "endpoint" => %observer.endpoint);
+            });
+        match spawn_result {
+            Ok(_) => self.delivery_queue = Some(queue),
+            Err(e) => {
+                error!(
+                    "Event observer: failed to spawn delivery thread, delivering synchronously";
+                    "endpoint" => %self.endpoint,
+                    "error" => ?e
+                );
+            }
         }
     }
 
-    /// Send the payload to the given URL.
+    /// Send the payload to the given path on this observer's sink.
     /// Before sending this payload, any pending payloads in the database will be sent first.
     pub fn send_payload(&self, payload: &serde_json::Value, path: &str) {
-        // Construct the full URL
-        let url_str = if path.starts_with('/') {
-            format!("{}{path}", &self.endpoint)
-        } else {
-            format!("{}/{path}", &self.endpoint)
-        };
-        let full_url = format!("http://{url_str}");
+        let destination = sinks::destination_for(self.sink, &self.endpoint, path);
 
         // if the observer is in "disable_retries" mode quickly send the payload without checking for the db
-        if self.disable_retries {
-            Self::send_payload_directly(payload, &full_url, self.timeout, true);
+        let delivery = if self.disable_retries {
+            PendingDelivery::Direct {
+                payload: payload.clone(),
+                destination,
+                disable_retries: true,
+            }
         } else if let Some(db_path) = &self.db_path {
             let conn =
                 Connection::open(db_path).expect("Failed to open database for event observer");
 
-            // Insert the new payload into the database
-            Self::insert_payload_with_retry(&conn, &full_url, payload, self.timeout);
+            // Insert the new payload into the database.
+            // This happens on the calling thread even in async mode, so that the payload is
+            // durable before we return.
+            Self::insert_payload_with_retry(
+                &conn,
+                &self.endpoint,
+                &destination,
+                payload,
+                self.timeout,
+            );
 
             // Process all pending payloads
-            Self::process_pending_payloads(&conn);
+            PendingDelivery::FlushDatabase(db_path.clone())
         } else {
             // No database, just send the payload
-            Self::send_payload_directly(payload, &full_url, self.timeout, false);
+            PendingDelivery::Direct {
+                payload: payload.clone(),
+                destination,
+                disable_retries: false,
+            }
+        };
+
+        if let Some(queue) = &self.delivery_queue {
+            match queue.send(delivery) {
+                Ok(()) => return,
+                Err(e) => {
+                    error!(
+                        "Event observer: delivery thread is gone, delivering synchronously";
+                        "endpoint" => %self.endpoint
+                    );
+                    self.deliver(e.0);
+                }
+            }
+        } else {
+            self.deliver(delivery);
+        }
+    }
+
+    /// Carry out a delivery on the calling thread
+    fn deliver(&self, delivery: PendingDelivery) {
+        match delivery {
+            PendingDelivery::Direct {
+                payload,
+                destination,
+                disable_retries,
+            } => {
+                Self::send_payload_directly(&payload, &destination, self.timeout, disable_retries);
+            }
+            PendingDelivery::FlushDatabase(db_path) => {
+                let conn =
+                    Connection::open(db_path).expect("Failed to open database for event observer");
+                Self::process_pending_payloads(&conn, &self.endpoint);
+            }
         }
     }
 
@@ -821,9 +958,34 @@ impl EventObserver {
         block_timestamp: Option<u64>,
         coinbase_height: u64,
     ) -> serde_json::Value {
+        // Whether this observer's filter includes each receipt, by its index in the block.
+        // Transactions which are included keep their index in the block.
+        let included_receipts: Option<Vec<bool>> = self.filter.as_ref().map(|filter| {
+            receipts
+                .iter()
+                .map(|receipt| payload_filter::receipt_matches(filter, receipt))
+                .collect()
+        });
+        // Events are numbered across the receipts in order, so this tells whether the receipt
+        // which emitted each event is included, by event index
+        let included_events: Option<Vec<bool>> = included_receipts.as_ref().map(|included| {
+            receipts
+                .iter()
+                .zip(included.iter())
+                .flat_map(|(receipt, included)| {
+                    std::iter::repeat(*included).take(receipt.events.len())
+                })
+                .collect()
+        });
+
         // Serialize events to JSON
         let serialized_events: Vec<serde_json::Value> = filtered_events
             .iter()
+            .filter(|(event_index, _)| {
+                included_events.as_ref().map_or(true, |included| {
+                    included.get(*event_index).copied().unwrap_or(true)
+                })
+            })
             .map(|(event_index, (committed, txid, event))| {
                 event
                     .json_serialize(*event_index, txid, *committed)
@@ -833,6 +995,12 @@ impl EventObserver {
 
         let mut serialized_txs = vec![];
         for (tx_index, receipt) in receipts.iter().enumerate() {
+            if included_receipts
+                .as_ref()
+                .is_some_and(|included| !included[tx_index])
+            {
+                continue;
+            }
             let payload = EventObserver::make_new_block_txs_payload(
                 receipt,
                 tx_index
@@ -945,6 +1113,8 @@ pub struct EventDispatcher {
     block_proposal_observers_lookup: HashSet<u16>,
     /// Channel for sending StackerDB events to the miner coordinator
     pub stackerdb_channel: Arc<Mutex<StackerDBChannel>>,
+    /// Subscribers to the node's `/v3/events/stream` RPC endpoint, if enabled
+    event_stream: Option<RPCEventStreamHub>,
 }
 
 /// This struct is used specifically for receiving proposal responses.
@@ -1157,9 +1327,20 @@ impl EventDispatcher {
             mined_microblocks_observers_lookup: HashSet::new(),
             stackerdb_observers_lookup: HashSet::new(),
             block_proposal_observers_lookup: HashSet::new(),
+            event_stream: None,
         }
     }
 
+    /// Publish processed blocks, burn blocks, mempool admissions and tenure changes to this hub,
+    /// in addition to any registered observers.
+    pub fn set_event_stream(&mut self, event_stream: RPCEventStreamHub) {
+        self.event_stream = Some(event_stream);
+    }
+
+    pub fn get_event_stream(&self) -> Option<&RPCEventStreamHub> {
+        self.event_stream.as_ref()
+    }
+
     pub fn process_burn_block(
         &self,
         burn_block: &BurnchainHeaderHash,
@@ -1169,6 +1350,14 @@ impl EventDispatcher {
         recipient_info: Vec<PoxAddress>,
         consensus_hash: &ConsensusHash,
     ) {
+        if let Some(event_stream) = self.event_stream.as_ref() {
+            event_stream.publish(RPCStreamEvent::new_burn_block(
+                burn_block,
+                burn_block_height,
+                consensus_hash,
+            ));
+        }
+
         // lazily assemble payload only if we have observers
         let interested_observers = self.filter_observers(&self.burn_block_observers_lookup, true);
         if interested_observers.is_empty() {
@@ -1310,6 +1499,21 @@ impl EventDispatcher {
         block_timestamp: Option<u64>,
         coinbase_height: u64,
     ) {
+        if let Some(event_stream) = self.event_stream.as_ref() {
+            event_stream.publish(RPCStreamEvent::new_block(
+                metadata,
+                parent_index_hash,
+                receipts.len(),
+            ));
+            for receipt in receipts.iter() {
+                if let TransactionOrigin::Stacks(ref tx) = receipt.transaction {
+                    if let TransactionPayload::TenureChange(ref tc) = tx.payload {
+                        event_stream.publish(RPCStreamEvent::new_tenure_change(tc, metadata));
+                    }
+                }
+            }
+        }
+
         let all_receipts = receipts.to_owned();
         let (dispatch_matrix, events) = self.create_dispatch_matrix_and_event_vector(&all_receipts);
 
@@ -1456,6 +1660,12 @@ impl EventDispatcher {
     }
 
     pub fn process_new_mempool_txs(&self, txs: Vec<StacksTransaction>) {
+        if let Some(event_stream) = self.event_stream.as_ref() {
+            for tx in txs.iter() {
+                event_stream.publish(RPCStreamEvent::new_mempool_tx(tx));
+            }
+        }
+
         // lazily assemble payload only if we have observers
         let interested_observers = self.filter_observers(&self.mempool_observers_lookup, true);
 
@@ -1683,10 +1893,11 @@ impl EventDispatcher {
     }
 
     pub fn register_observer(&mut self, conf: &EventObserverConfig, working_dir: PathBuf) {
-        info!("Registering event observer at: {}", conf.endpoint);
-        let event_observer = EventObserver::new(
+        info!("Registering event observer at: {}", conf.endpoint; "sink" => ?conf.sink);
+        let mut event_observer = EventObserver::new_with_sink(
             Some(working_dir),
             conf.endpoint.clone(),
+            conf.sink,
             Duration::from_millis(conf.timeout_ms),
             conf.disable_retries,
         );
@@ -1695,6 +1906,12 @@ impl EventDispatcher {
             warn!("Observer {} is configured in \"disable_retries\" mode: events are not guaranteed to be delivered", conf.endpoint);
         }
 
+        event_observer.filter = conf.filter.clone();
+
+        if conf.async_delivery {
+            event_observer.start_delivery_thread();
+        }
+
         let observer_index = self.registered_observers.len() as u16;
 
         for event_key_type in conf.events_keys.iter() {
@@ -1780,20 +1997,30 @@ mod test {
     use std::time::Instant;
 
     use clarity::vm::costs::ExecutionCost;
+    use clarity::vm::events::STXTransferEventData;
+    use clarity::vm::types::{BuffData, PrincipalData};
     use serial_test::serial;
-    use stacks::burnchains::{PoxConstants, Txid};
-    use stacks::chainstate::nakamoto::{NakamotoBlock, NakamotoBlockHeader};
-    use stacks::chainstate::stacks::db::{StacksBlockHeaderTypes, StacksHeaderInfo};
-    use stacks::chainstate::stacks::events::StacksBlockEventData;
-    use stacks::chainstate::stacks::StacksBlock;
-    use stacks::types::chainstate::BlockHeaderHash;
-    use stacks::util::secp256k1::MessageSignature;
     use stacks_common::bitvec::BitVec;
-    use stacks_common::types::chainstate::{BurnchainHeaderHash, StacksBlockId};
+    use stacks_common::types::chainstate::{BurnchainHeaderHash, StacksAddress, StacksBlockId};
+    use stacks_common::types::net::PeerHost;
     use tempfile::tempdir;
     use tiny_http::{Method, Response, Server, StatusCode};
 
     use super::*;
+    use crate::burnchains::{PoxConstants, Txid};
+    use crate::chainstate::burn::operations::TransferStxOp;
+    use crate::chainstate::nakamoto::{NakamotoBlock, NakamotoBlockHeader};
+    use crate::chainstate::stacks::db::{StacksBlockHeaderTypes, StacksHeaderInfo};
+    use crate::chainstate::stacks::events::StacksBlockEventData;
+    use crate::chainstate::stacks::{
+        StacksBlock, StacksPrivateKey, TokenTransferMemo, TransactionAuth, TransactionVersion,
+    };
+    use crate::config::EventTxType;
+    use crate::core::CHAIN_ID_TESTNET;
+    use crate::net::http::HttpRequestContents;
+    use crate::net::httpcore::{send_http_request, StacksHttpRequest};
+    use crate::types::chainstate::BlockHeaderHash;
+    use crate::util::secp256k1::MessageSignature;
 
     #[test]
     fn build_block_processed_event() {
@@ -1856,6 +2083,169 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_block_processed_event_filter() {
+        let sender = StacksPrivateKey::random();
+        let recipient = PrincipalData::from(StacksAddress::burn_address(false));
+        let boot_addr = StacksAddress::burn_address(false);
+
+        let make_tx = |nonce: u64, payload: TransactionPayload| {
+            let mut tx = StacksTransaction::new(
+                TransactionVersion::Testnet,
+                TransactionAuth::from_p2pkh(&sender).unwrap(),
+                payload,
+            );
+            tx.chain_id = CHAIN_ID_TESTNET;
+            tx.set_tx_fee(180);
+            tx.set_origin_nonce(nonce);
+            tx
+        };
+        let transfer_tx = make_tx(
+            0,
+            TransactionPayload::TokenTransfer(recipient.clone(), 100, TokenTransferMemo([0; 34])),
+        );
+        let call_tx = make_tx(
+            1,
+            TransactionPayload::new_contract_call(boot_addr, "pox-4", "stack-stx", vec![]).unwrap(),
+        );
+        let transfer_event = StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+            STXTransferEventData {
+                sender: PrincipalData::from(transfer_tx.origin_address()),
+                recipient: recipient.clone(),
+                amount: 100,
+                memo: BuffData::empty(),
+            },
+        ));
+        let mut receipts = vec![
+            StacksTransactionReceipt::from_stx_transfer(
+                transfer_tx.clone(),
+                vec![transfer_event.clone()],
+                Value::okay_true(),
+                ExecutionCost::ZERO,
+            ),
+            StacksTransactionReceipt::from_contract_call(
+                call_tx.clone(),
+                vec![],
+                Value::okay_true(),
+                0,
+                ExecutionCost::ZERO,
+            ),
+        ];
+        // a burnchain operation which happens to share the transfer's txid
+        receipts.push(StacksTransactionReceipt {
+            transaction: TransactionOrigin::Burn(BlockstackOperationType::TransferStx(
+                TransferStxOp {
+                    sender: transfer_tx.origin_address(),
+                    recipient: boot_addr,
+                    transfered_ustx: 100,
+                    memo: vec![],
+                    txid: transfer_tx.txid(),
+                    vtxindex: 0,
+                    block_height: 0,
+                    burn_header_hash: BurnchainHeaderHash([0; 32]),
+                },
+            )),
+            events: vec![],
+            result: Value::okay_true(),
+            post_condition_aborted: false,
+            stx_burned: 0,
+            contract_analysis: None,
+            execution_cost: ExecutionCost::ZERO,
+            microblock_header: None,
+            tx_index: 2,
+            vm_error: None,
+        });
+        let events = [(true, transfer_tx.txid(), &transfer_event)];
+
+        let make_payload = |filter: Option<EventObserverFilter>| {
+            let mut observer =
+                EventObserver::new(None, "nowhere".to_string(), Duration::from_secs(3), false);
+            observer.filter = filter;
+            observer.make_new_block_processed_payload(
+                vec![(0, &events[0])],
+                &StacksBlock::genesis_block().into(),
+                &StacksHeaderInfo::regtest_genesis(),
+                &receipts,
+                &StacksBlockId([0; 32]),
+                &Txid([0; 32]),
+                &serde_json::Value::Array(vec![]),
+                BurnchainHeaderHash([0; 32]),
+                0,
+                0,
+                &ExecutionCost::ZERO,
+                &ExecutionCost::ZERO,
+                &PoxConstants::testnet_default(),
+                &None,
+                &None,
+                None,
+                1,
+            )
+        };
+        let txids = |payload: &serde_json::Value| -> Vec<(String, u64)> {
+            payload["transactions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|tx| {
+                    (
+                        tx["txid"].as_str().unwrap().to_string(),
+                        tx["tx_index"].as_u64().unwrap(),
+                    )
+                })
+                .collect()
+        };
+
+        // no filter: everything is included
+        let payload = make_payload(None);
+        assert_eq!(txids(&payload).len(), 3);
+        assert_eq!(payload["events"].as_array().unwrap().len(), 1);
+
+        // transactions are filtered by their index, so a txid shared with an excluded
+        // transaction does not matter
+        let payload = make_payload(Some(EventObserverFilter {
+            tx_types: vec![EventTxType::BurnOp],
+            ..EventObserverFilter::default()
+        }));
+        assert_eq!(
+            txids(&payload),
+            vec![(format!("0x{}", transfer_tx.txid()), 2)]
+        );
+        assert!(payload["events"].as_array().unwrap().is_empty());
+
+        // by tx type: included transactions keep their index in the block
+        let payload = make_payload(Some(EventObserverFilter {
+            tx_types: vec![EventTxType::ContractCall],
+            ..EventObserverFilter::default()
+        }));
+        assert_eq!(txids(&payload), vec![(format!("0x{}", call_tx.txid()), 1)]);
+        assert!(payload["events"].as_array().unwrap().is_empty());
+
+        // by called function
+        let pox_4 = QualifiedContractIdentifier::new(boot_addr.clone().into(), "pox-4".into());
+        let payload = make_payload(Some(EventObserverFilter {
+            contract_calls: vec![(pox_4.clone(), Some("stack-stx".into()))],
+            ..EventObserverFilter::default()
+        }));
+        assert_eq!(txids(&payload), vec![(format!("0x{}", call_tx.txid()), 1)]);
+        let payload = make_payload(Some(EventObserverFilter {
+            contract_calls: vec![(pox_4, Some("delegate-stx".into()))],
+            ..EventObserverFilter::default()
+        }));
+        assert!(txids(&payload).is_empty());
+
+        // by principal, which the transfer involves as its recipient
+        let payload = make_payload(Some(EventObserverFilter {
+            principals: vec![recipient],
+            tx_types: vec![EventTxType::TokenTransfer, EventTxType::ContractCall],
+            ..EventObserverFilter::default()
+        }));
+        assert_eq!(
+            txids(&payload),
+            vec![(format!("0x{}", transfer_tx.txid()), 0)]
+        );
+        assert_eq!(payload["events"].as_array().unwrap().len(), 1);
+    }
+
     #[test]
     fn test_block_processed_event_nakamoto() {
         let observer =
@@ -2014,12 +2404,13 @@ mod test {
         let timeout = Duration::from_secs(5);
 
         // Insert payload
-        let insert_result = EventObserver::insert_payload(&conn, url, &payload, timeout);
+        let insert_result =
+            EventObserver::insert_payload(&conn, "example.com", url, &payload, timeout);
         assert!(insert_result.is_ok(), "Failed to insert payload");
 
         // Get pending payloads
-        let pending_payloads =
-            EventObserver::get_pending_payloads(&conn).expect("Failed to get pending payloads");
+        let pending_payloads = EventObserver::get_pending_payloads(&conn, "example.com")
+            .expect("Failed to get pending payloads");
         assert_eq!(pending_payloads.len(), 1, "Expected one pending payload");
 
         let (_id, retrieved_url, retrieved_payload, timeout_ms) = &pending_payloads[0];
@@ -2045,12 +2436,12 @@ mod test {
         let timeout = Duration::from_secs(5);
 
         // Insert payload
-        EventObserver::insert_payload(&conn, url, &payload, timeout)
+        EventObserver::insert_payload(&conn, "example.com", url, &payload, timeout)
             .expect("Failed to insert payload");
 
         // Get pending payloads
-        let pending_payloads =
-            EventObserver::get_pending_payloads(&conn).expect("Failed to get pending payloads");
+        let pending_payloads = EventObserver::get_pending_payloads(&conn, "example.com")
+            .expect("Failed to get pending payloads");
         assert_eq!(pending_payloads.len(), 1, "Expected one pending payload");
 
         let (id, _, _, _) = pending_payloads[0];
@@ -2060,8 +2451,8 @@ mod test {
         assert!(delete_result.is_ok(), "Failed to delete payload");
 
         // Verify that the pending payloads list is empty
-        let pending_payloads =
-            EventObserver::get_pending_payloads(&conn).expect("Failed to get pending payloads");
+        let pending_payloads = EventObserver::get_pending_payloads(&conn, "example.com")
+            .expect("Failed to get pending payloads");
         assert_eq!(pending_payloads.len(), 0, "Expected no pending payloads");
     }
 
@@ -2093,21 +2484,104 @@ mod test {
         TEST_EVENT_OBSERVER_SKIP_RETRY.set(false);
 
         // Insert payload
-        EventObserver::insert_payload(&conn, url, &payload, timeout)
+        EventObserver::insert_payload(&conn, "example.com", url, &payload, timeout)
             .expect("Failed to insert payload");
 
         // Process pending payloads
-        EventObserver::process_pending_payloads(&conn);
+        EventObserver::process_pending_payloads(&conn, "example.com");
 
         // Verify that the pending payloads list is empty
-        let pending_payloads =
-            EventObserver::get_pending_payloads(&conn).expect("Failed to get pending payloads");
+        let pending_payloads = EventObserver::get_pending_payloads(&conn, "example.com")
+            .expect("Failed to get pending payloads");
         assert_eq!(pending_payloads.len(), 0, "Expected no pending payloads");
 
         // Verify that the mock was called
         _m.assert();
     }
 
+    #[test]
+    fn test_process_pending_payloads_only_drains_own_observer() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test_process_own_payloads.sqlite");
+        let conn = EventObserver::init_db(db_path.to_str().unwrap())
+            .expect("Failed to initialize the database");
+
+        let payload = json!({"key": "value"});
+        let timeout = Duration::from_secs(5);
+
+        let mut server = mockito::Server::new();
+        let _m = server
+            .mock("POST", "/api")
+            .with_status(200)
+            .expect(1)
+            .create();
+        let url = &format!("{}/api", &server.url());
+
+        TEST_EVENT_OBSERVER_SKIP_RETRY.set(false);
+
+        // Both payloads would be delivered successfully, but only the first belongs to the
+        // observer being drained
+        EventObserver::insert_payload(&conn, "observer-a", url, &payload, timeout)
+            .expect("Failed to insert payload");
+        EventObserver::insert_payload(&conn, "observer-b", url, &payload, timeout)
+            .expect("Failed to insert payload");
+
+        EventObserver::process_pending_payloads(&conn, "observer-a");
+
+        assert!(EventObserver::get_pending_payloads(&conn, "observer-a")
+            .expect("Failed to get pending payloads")
+            .is_empty());
+        let pending_b = EventObserver::get_pending_payloads(&conn, "observer-b")
+            .expect("Failed to get pending payloads");
+        assert_eq!(pending_b.len(), 1);
+        assert_eq!(pending_b[0].1, *url);
+
+        _m.assert();
+    }
+
+    #[test]
+    fn test_unkeyed_payloads_are_claimed_by_their_observer() {
+        let dir = tempdir().unwrap();
+        let db_path = dir.path().join("test_claim_payloads.sqlite");
+        let db_path_str = db_path.to_str().unwrap();
+
+        // A database written before payloads were keyed by observer
+        let conn = Connection::open(db_path_str).unwrap();
+        conn.execute(
+            "CREATE TABLE pending_payloads (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                timeout INTEGER NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+        for url in [
+            "http://127.0.0.1:3700/new_block",
+            "http://127.0.0.1:3701/new_block",
+        ] {
+            conn.execute(
+                "INSERT INTO pending_payloads (url, payload, timeout) VALUES (?1, '{}', 1000)",
+                params![url],
+            )
+            .unwrap();
+        }
+        drop(conn);
+
+        let conn = EventObserver::init_db(db_path_str).expect("Failed to initialize the database");
+        EventObserver::claim_unkeyed_payloads(&conn, "127.0.0.1:3700", "http://127.0.0.1:3700/")
+            .expect("Failed to claim payloads");
+
+        let pending = EventObserver::get_pending_payloads(&conn, "127.0.0.1:3700")
+            .expect("Failed to get pending payloads");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].1, "http://127.0.0.1:3700/new_block");
+        assert!(EventObserver::get_pending_payloads(&conn, "127.0.0.1:3701")
+            .expect("Failed to get pending payloads")
+            .is_empty());
+    }
+
     #[test]
     fn test_new_event_observer_with_db() {
         let dir = tempdir().unwrap();
@@ -2177,8 +2651,8 @@ mod test {
         let db_path = observer.db_path.unwrap();
         let db_path_str = db_path.to_str().unwrap();
         let conn = Connection::open(db_path_str).expect("Failed to open database");
-        let pending_payloads =
-            EventObserver::get_pending_payloads(&conn).expect("Failed to get pending payloads");
+        let pending_payloads = EventObserver::get_pending_payloads(&conn, "example.com")
+            .expect("Failed to get pending payloads");
         assert_eq!(pending_payloads.len(), 0, "Expected no pending payloads");
     }
 
@@ -2494,6 +2968,149 @@ mod test {
         observer.send_payload(&payload, "/test");
     }
 
+    /// Read back the newline-delimited records written by a file sink
+    fn read_ndjson_records(path: &std::path::Path) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_send_payload_file_sink() {
+        let dir = tempdir().unwrap();
+        let events_path = dir.path().join("events.ndjson");
+
+        let observer = EventObserver::new_with_sink(
+            Some(dir.path().to_path_buf()),
+            events_path.to_str().unwrap().to_string(),
+            EventSinkKind::File,
+            Duration::from_secs(5),
+            false,
+        );
+
+        let payload = json!({"key": "value"});
+        let payload2 = json!({"key": "value2"});
+        observer.send_payload(&payload, PATH_BLOCK_PROCESSED);
+        observer.send_payload(&payload2, "/new_burn_block");
+
+        let records = read_ndjson_records(&events_path);
+        assert_eq!(
+            records,
+            vec![
+                json!({"path": "new_block", "payload": payload}),
+                json!({"path": "new_burn_block", "payload": payload2}),
+            ]
+        );
+
+        // Verify that the database is empty. A file sink's payloads are keyed by the file's path.
+        assert_eq!(observer.endpoint, events_path.to_str().unwrap());
+        let conn = Connection::open(observer.db_path.unwrap()).unwrap();
+        let pending_payloads = EventObserver::get_pending_payloads(&conn, &observer.endpoint)
+            .expect("Failed to get pending payloads");
+        assert_eq!(pending_payloads.len(), 0, "Expected no pending payloads");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_send_payload_unix_socket_sink() {
+        use std::io::{BufRead, BufReader};
+        use std::os::unix::net::UnixListener;
+
+        let dir = tempdir().unwrap();
+        let socket_path = dir.path().join("observer.sock");
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                let mut line = String::new();
+                BufReader::new(stream).read_line(&mut line).unwrap();
+                tx.send(serde_json::from_str::<serde_json::Value>(&line).unwrap())
+                    .unwrap();
+            }
+        });
+
+        let observer = EventObserver::new_with_sink(
+            None,
+            socket_path.to_str().unwrap().to_string(),
+            EventSinkKind::UnixSocket,
+            Duration::from_secs(5),
+            false,
+        );
+
+        let payload = json!({"key": "value"});
+        observer.send_payload(&payload, PATH_BURN_BLOCK_SUBMIT);
+        observer.send_payload(&payload, PATH_MEMPOOL_TX_SUBMIT);
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(
+            rx.recv_timeout(timeout).unwrap(),
+            json!({"path": "new_burn_block", "payload": payload})
+        );
+        assert_eq!(
+            rx.recv_timeout(timeout).unwrap(),
+            json!({"path": "new_mempool_tx", "payload": payload})
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_send_payload_async_delivery() {
+        let port = get_random_port();
+        let dir = tempdir().unwrap();
+
+        // Set up a channel to release the server once the payload has been handed off
+        let (release_tx, release_rx) = channel::<()>();
+        let (tx, rx) = channel();
+
+        let server = Server::http(format!("127.0.0.1:{port}")).unwrap();
+        thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            // Hold the response until the test has observed that send_payload() returned
+            release_rx.recv().unwrap();
+            let mut payload = String::new();
+            request.as_reader().read_to_string(&mut payload).unwrap();
+            request
+                .respond(Response::from_string("HTTP/1.1 200 OK"))
+                .unwrap();
+            tx.send(payload).unwrap();
+        });
+
+        let mut event_dispatcher = EventDispatcher::new();
+        let config = EventObserverConfig {
+            endpoint: format!("127.0.0.1:{port}"),
+            events_keys: vec![EventKeyType::BurnchainBlocks],
+            timeout_ms: 10_000,
+            disable_retries: false,
+            sink: EventSinkKind::Http,
+            async_delivery: true,
+            filter: None,
+        };
+        event_dispatcher.register_observer(&config, dir.path().to_path_buf());
+
+        TEST_EVENT_OBSERVER_SKIP_RETRY.set(false);
+
+        // Returns even though the observer has not answered yet
+        event_dispatcher.process_burn_block(
+            &BurnchainHeaderHash([0x01; 32]),
+            1,
+            vec![],
+            0,
+            vec![],
+            &ConsensusHash([0x02; 20]),
+        );
+        release_tx.send(()).unwrap();
+
+        let payload = rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Server did not receive request in time");
+        let payload: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(payload["burn_block_height"], 1);
+    }
+
     #[test]
     #[ignore]
     /// This test generates a new block and ensures the "disable_retries" events_observer will not block.
@@ -2507,6 +3124,9 @@ mod test {
             events_keys: vec![EventKeyType::MinedBlocks],
             timeout_ms: 1000,
             disable_retries: true,
+            sink: EventSinkKind::Http,
+            async_delivery: false,
+            filter: None,
         };
         event_dispatcher.register_observer(&config, working_dir);
 
@@ -0,0 +1,139 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Matching of transaction receipts against an observer's `EventObserverFilter`
+
+use clarity::vm::events::{FTEventType, NFTEventType, STXEventType, StacksTransactionEvent};
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier};
+
+use crate::chainstate::stacks::events::{StacksTransactionReceipt, TransactionOrigin};
+use crate::chainstate::stacks::TransactionPayload;
+use crate::config::{EventObserverFilter, EventTxType};
+
+/// Does `receipt` satisfy every non-empty criterion of `filter`?
+pub fn receipt_matches(filter: &EventObserverFilter, receipt: &StacksTransactionReceipt) -> bool {
+    if !filter.tx_types.is_empty() && !filter.tx_types.contains(&tx_type(&receipt.transaction)) {
+        return false;
+    }
+    if !filter.contract_calls.is_empty() && !matches_contract_call(filter, &receipt.transaction) {
+        return false;
+    }
+    if !filter.principals.is_empty()
+        && !involved_principals(receipt)
+            .iter()
+            .any(|principal| filter.principals.contains(principal))
+    {
+        return false;
+    }
+    true
+}
+
+fn tx_type(origin: &TransactionOrigin) -> EventTxType {
+    let TransactionOrigin::Stacks(tx) = origin else {
+        return EventTxType::BurnOp;
+    };
+    match &tx.payload {
+        TransactionPayload::TokenTransfer(..) => EventTxType::TokenTransfer,
+        TransactionPayload::ContractCall(..) => EventTxType::ContractCall,
+        TransactionPayload::SmartContract(..) => EventTxType::SmartContract,
+        TransactionPayload::PoisonMicroblock(..) => EventTxType::PoisonMicroblock,
+        TransactionPayload::Coinbase(..) => EventTxType::Coinbase,
+        TransactionPayload::TenureChange(..) => EventTxType::TenureChange,
+    }
+}
+
+fn matches_contract_call(filter: &EventObserverFilter, origin: &TransactionOrigin) -> bool {
+    let TransactionOrigin::Stacks(tx) = origin else {
+        return false;
+    };
+    let TransactionPayload::ContractCall(call) = &tx.payload else {
+        return false;
+    };
+    let contract = call.to_clarity_contract_id();
+    filter
+        .contract_calls
+        .iter()
+        .any(|(filter_contract, filter_function)| {
+            *filter_contract == contract
+                && filter_function.as_ref().map_or(true, |function| {
+                    function.as_str() == call.function_name.as_str()
+                })
+        })
+}
+
+/// The principals involved in a transaction: its sender and sponsor, the recipient of a token
+/// transfer, the called or deployed contract, and the principals in its STX and asset events
+fn involved_principals(receipt: &StacksTransactionReceipt) -> Vec<PrincipalData> {
+    let mut principals = vec![];
+    if let TransactionOrigin::Stacks(tx) = &receipt.transaction {
+        principals.push(tx.origin_address().into());
+        if let Some(sponsor) = tx.sponsor_address() {
+            principals.push(sponsor.into());
+        }
+        match &tx.payload {
+            TransactionPayload::TokenTransfer(recipient, ..) => principals.push(recipient.clone()),
+            TransactionPayload::ContractCall(call) => {
+                principals.push(call.to_clarity_contract_id().into())
+            }
+            TransactionPayload::SmartContract(contract, _) => {
+                principals.push(PrincipalData::Contract(QualifiedContractIdentifier::new(
+                    tx.origin_address().into(),
+                    contract.name.clone(),
+                )))
+            }
+            _ => {}
+        }
+    }
+    for event in receipt.events.iter() {
+        match event {
+            StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(data)) => {
+                principals.push(data.sender.clone());
+                principals.push(data.recipient.clone());
+            }
+            StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(data)) => {
+                principals.push(data.recipient.clone());
+            }
+            StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(data)) => {
+                principals.push(data.sender.clone());
+            }
+            StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(data)) => {
+                principals.push(data.locked_address.clone());
+            }
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(data)) => {
+                principals.push(data.sender.clone());
+                principals.push(data.recipient.clone());
+            }
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(data)) => {
+                principals.push(data.recipient.clone());
+            }
+            StacksTransactionEvent::NFTEvent(NFTEventType::NFTBurnEvent(data)) => {
+                principals.push(data.sender.clone());
+            }
+            StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(data)) => {
+                principals.push(data.sender.clone());
+                principals.push(data.recipient.clone());
+            }
+            StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(data)) => {
+                principals.push(data.recipient.clone());
+            }
+            StacksTransactionEvent::FTEvent(FTEventType::FTBurnEvent(data)) => {
+                principals.push(data.sender.clone());
+            }
+            StacksTransactionEvent::SmartContractEvent(_) => {}
+        }
+    }
+    principals
+}
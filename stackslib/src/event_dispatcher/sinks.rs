@@ -0,0 +1,210 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Transports over which event observer payloads are delivered.
+//!
+//! Every payload sent to an observer is addressed by a *destination* string, which is what gets
+//! persisted in the pending-payloads database so that undelivered payloads can be retried after a
+//! restart.  HTTP destinations are plain `http://` URLs.  The file and Unix domain socket sinks
+//! use `file://{path}#{event}` and `unix://{path}#{event}`, where `{event}` is the observer path
+//! (e.g. `new_block`) that an HTTP observer would have been sent the payload on.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde_json::json;
+use stacks_common::types::net::PeerHost;
+use url::Url;
+
+use crate::config::EventSinkKind;
+use crate::net::http::HttpRequestContents;
+use crate::net::httpcore::{send_http_request, StacksHttpRequest};
+
+const FILE_SCHEME: &str = "file://";
+const UNIX_SOCKET_SCHEME: &str = "unix://";
+
+/// A transport which can deliver a single event observer payload.
+///
+/// Implementations make exactly one delivery attempt; retries, backoff and persistence of
+/// pending payloads are handled by the `EventObserver`.
+pub trait EventSink {
+    /// Attempt to deliver `payload`, giving up after `timeout`.
+    fn deliver(&self, payload: &serde_json::Value, timeout: Duration) -> io::Result<()>;
+}
+
+/// Construct the destination string for a payload sent to `path` on an observer whose sink
+/// is `kind` and whose configured endpoint is `endpoint`.
+pub fn destination_for(kind: EventSinkKind, endpoint: &str, path: &str) -> String {
+    let path = path.trim_start_matches('/');
+    match kind {
+        EventSinkKind::Http => format!("http://{endpoint}/{path}"),
+        EventSinkKind::File => format!("{FILE_SCHEME}{endpoint}#{path}"),
+        EventSinkKind::UnixSocket => format!("{UNIX_SOCKET_SCHEME}{endpoint}#{path}"),
+    }
+}
+
+/// Instantiate the sink which delivers to `destination`, as produced by `destination_for()`.
+pub fn sink_for_destination(destination: &str) -> Result<Box<dyn EventSink>, String> {
+    if let Some(rest) = destination.strip_prefix(FILE_SCHEME) {
+        let (file_path, event) = split_event(rest)?;
+        return Ok(Box::new(FileEventSink {
+            path: file_path,
+            event,
+        }));
+    }
+    if let Some(rest) = destination.strip_prefix(UNIX_SOCKET_SCHEME) {
+        let (socket_path, event) = split_event(rest)?;
+        return Ok(Box::new(UnixSocketEventSink {
+            path: socket_path,
+            event,
+        }));
+    }
+    let url = Url::parse(destination)
+        .map_err(|e| format!("Unable to parse {destination} as a URL: {e:?}"))?;
+    Ok(Box::new(HttpEventSink::new(url)?))
+}
+
+fn split_event(destination: &str) -> Result<(PathBuf, String), String> {
+    let (path, event) = destination
+        .rsplit_once('#')
+        .ok_or_else(|| format!("Event destination {destination} is missing an event path"))?;
+    Ok((PathBuf::from(path), event.to_string()))
+}
+
+/// Encode a payload as a single newline-terminated JSON record, tagged with its event path.
+fn ndjson_record(event: &str, payload: &serde_json::Value) -> Vec<u8> {
+    let mut line = json!({
+        "path": event,
+        "payload": payload,
+    })
+    .to_string()
+    .into_bytes();
+    line.push(b'\n');
+    line
+}
+
+/// POSTs payloads as JSON to an HTTP endpoint.  Only a `200` response counts as delivered.
+pub struct HttpEventSink {
+    url: Url,
+    host: String,
+    port: u16,
+}
+
+impl HttpEventSink {
+    pub fn new(url: Url) -> Result<Self, String> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| format!("Invalid URL {url}: missing host"))?
+            .to_string();
+        let port = url.port_or_known_default().unwrap_or(80);
+        Ok(Self { url, host, port })
+    }
+}
+
+impl EventSink for HttpEventSink {
+    fn deliver(&self, payload: &serde_json::Value, timeout: Duration) -> io::Result<()> {
+        let peerhost: PeerHost = format!("{}:{}", &self.host, self.port)
+            .parse()
+            .unwrap_or(PeerHost::DNS(self.host.clone(), self.port));
+
+        let mut request = StacksHttpRequest::new_for_peer(
+            peerhost,
+            "POST".into(),
+            self.url.path().into(),
+            HttpRequestContents::new().payload_json(payload.clone()),
+        )
+        .unwrap_or_else(|_| panic!("FATAL: failed to encode infallible data as HTTP request"));
+        request.add_header("Connection".into(), "close".into());
+
+        let response = send_http_request(&self.host, self.port, request, timeout)?;
+        if response.preamble().status_code == 200 {
+            debug!(
+                "Event dispatcher: Successful POST"; "url" => %self.url
+            );
+            Ok(())
+        } else {
+            error!(
+                "Event dispatcher: Failed POST"; "url" => %self.url, "response" => ?response.preamble()
+            );
+            Err(io::Error::other(format!(
+                "HTTP status {}",
+                response.preamble().status_code
+            )))
+        }
+    }
+}
+
+/// Appends payloads as newline-delimited JSON records to a local file.  Each record is
+/// `{"path": <event path>, "payload": <payload>}`, and is synced to disk before the delivery
+/// is considered successful.
+pub struct FileEventSink {
+    path: PathBuf,
+    event: String,
+}
+
+impl EventSink for FileEventSink {
+    fn deliver(&self, payload: &serde_json::Value, _timeout: Duration) -> io::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        // a single write_all() on an O_APPEND file keeps records from interleaving
+        file.write_all(&ndjson_record(&self.event, payload))?;
+        file.sync_data()?;
+        debug!(
+            "Event dispatcher: Appended payload to file"; "path" => %self.path.display(), "event" => %self.event
+        );
+        Ok(())
+    }
+}
+
+/// Writes payloads as newline-delimited JSON records (in the same format as `FileEventSink`)
+/// to a Unix domain socket.  A fresh connection is made for each payload, so the listener sees
+/// exactly one record per connection.
+pub struct UnixSocketEventSink {
+    path: PathBuf,
+    event: String,
+}
+
+impl EventSink for UnixSocketEventSink {
+    #[cfg(unix)]
+    fn deliver(&self, payload: &serde_json::Value, timeout: Duration) -> io::Result<()> {
+        let mut stream = UnixStream::connect(&self.path)?;
+        // a zero timeout is rejected by set_write_timeout(), and means "no timeout" here
+        stream.set_write_timeout(Some(timeout).filter(|t| !t.is_zero()))?;
+        stream.write_all(&ndjson_record(&self.event, payload))?;
+        stream.flush()?;
+        debug!(
+            "Event dispatcher: Wrote payload to socket"; "path" => %self.path.display(), "event" => %self.event
+        );
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn deliver(&self, _payload: &serde_json::Value, _timeout: Duration) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "Unix domain socket event sinks are not supported on this platform ({})",
+                self.path.display()
+            ),
+        ))
+    }
+}
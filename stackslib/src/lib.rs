@@ -68,6 +68,7 @@ pub mod config;
 pub mod core;
 pub mod cost_estimates;
 pub mod deps;
+pub mod event_dispatcher;
 pub mod monitoring;
 
 // set via _compile-time_ envars
@@ -1608,11 +1608,41 @@ check if the associated microblocks can be downloaded
         process::exit(0);
     }
 
+    if argv[1] == "export-snapshot" {
+        cli::command_export_snapshot(&argv[1..], common_opts.config.as_ref());
+        process::exit(0);
+    }
+
+    if argv[1] == "marf-verify" {
+        cli::command_marf_verify(&argv[1..]);
+        process::exit(0);
+    }
+
+    if argv[1] == "migrate-marf-storage" {
+        cli::command_migrate_marf_storage(&argv[1..]);
+        process::exit(0);
+    }
+
+    if argv[1] == "export-mempool" {
+        cli::command_export_mempool(&argv[1..], common_opts.config.as_ref());
+        process::exit(0);
+    }
+
+    if argv[1] == "import-mempool" {
+        cli::command_import_mempool(&argv[1..], common_opts.config.as_ref());
+        process::exit(0);
+    }
+
     if argv[1] == "replay-mock-mining" {
         cli::command_replay_mock_mining(&argv[1..], common_opts.config.as_ref());
         process::exit(0);
     }
 
+    if argv[1] == "replay-events" {
+        cli::command_replay_events(&argv[1..], common_opts.config.as_ref());
+        process::exit(0);
+    }
+
     if argv[1] == "dump-consts" {
         dump_consts();
     }
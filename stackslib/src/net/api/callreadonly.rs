@@ -41,6 +41,7 @@ use crate::burnchains::Burnchain;
 use crate::chainstate::burn::db::sortdb::SortitionDB;
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::Error as ChainError;
+use crate::clarity_vm::clarity::ClarityReadOnlyConnection;
 use crate::core::mempool::MemPoolDB;
 use crate::net::http::{
     parse_json, Error, HttpBadRequest, HttpContentType, HttpNotFound, HttpRequest,
@@ -75,6 +76,143 @@ pub struct CallReadOnlyResponse {
     pub cause: Option<String>,
 }
 
+impl CallReadOnlyResponse {
+    /// Translate the outcome of a read-only function call into a response
+    pub fn from_call_result(
+        call_result: Result<Value, ClarityRuntimeError>,
+    ) -> Result<Self, NetError> {
+        let resp = match call_result {
+            Ok(data) => {
+                let hex_result = data
+                    .serialize_to_hex()
+                    .map_err(|e| NetError::SerializeError(format!("{:?}", &e)))?;
+
+                CallReadOnlyResponse {
+                    okay: true,
+                    result: Some(format!("0x{}", hex_result)),
+                    cause: None,
+                }
+            }
+            Err(e) => match e {
+                Unchecked(CheckErrors::CostBalanceExceeded(actual_cost, _))
+                    if actual_cost.write_count > 0 =>
+                {
+                    CallReadOnlyResponse {
+                        okay: false,
+                        result: None,
+                        cause: Some("NotReadOnly".to_string()),
+                    }
+                }
+                _ => CallReadOnlyResponse {
+                    okay: false,
+                    result: None,
+                    cause: Some(e.to_string()),
+                },
+            },
+        };
+        Ok(resp)
+    }
+}
+
+/// A single read-only function call
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadOnlyCall {
+    pub contract_identifier: QualifiedContractIdentifier,
+    pub function: ClarityName,
+    pub arguments: Vec<Value>,
+}
+
+/// Evaluate a read-only function call in an open read-only Clarity connection.
+/// The call may not write to the chain state; the write dimensions of `cost_limit` are
+/// zeroed out so that any write exhausts the call's budget.
+pub fn call_read_only_function(
+    clarity_tx: &mut ClarityReadOnlyConnection,
+    mainnet: bool,
+    chain_id: u32,
+    cost_limit: ExecutionCost,
+    call: &ReadOnlyCall,
+    sender: PrincipalData,
+    sponsor: Option<PrincipalData>,
+) -> Result<Value, ClarityRuntimeError> {
+    call_read_only_function_metered(
+        clarity_tx, mainnet, chain_id, cost_limit, call, sender, sponsor,
+    )
+    .0
+}
+
+/// Evaluate a read-only function call like `call_read_only_function()`, and also return the
+/// execution cost it consumed, whether or not it succeeded.
+pub fn call_read_only_function_metered(
+    clarity_tx: &mut ClarityReadOnlyConnection,
+    mainnet: bool,
+    chain_id: u32,
+    mut cost_limit: ExecutionCost,
+    call: &ReadOnlyCall,
+    sender: PrincipalData,
+    sponsor: Option<PrincipalData>,
+) -> (Result<Value, ClarityRuntimeError>, ExecutionCost) {
+    cost_limit.write_length = 0;
+    cost_limit.write_count = 0;
+
+    let args: Vec<_> = call
+        .arguments
+        .iter()
+        .map(|x| SymbolicExpression::atom_value(x.clone()))
+        .collect();
+
+    let epoch = clarity_tx.get_epoch();
+    let cost_track = match clarity_tx.with_clarity_db_readonly(|clarity_db| {
+        LimitedCostTracker::new_mid_block(mainnet, chain_id, cost_limit, clarity_db, epoch)
+    }) {
+        Ok(cost_track) => cost_track,
+        Err(_) => {
+            return (
+                Err(InterpreterError::CostContractLoadFailure.into()),
+                ExecutionCost::ZERO,
+            );
+        }
+    };
+
+    let clarity_version = match clarity_tx.with_analysis_db_readonly(|analysis_db| {
+        analysis_db.get_clarity_version(&call.contract_identifier)
+    }) {
+        Ok(clarity_version) => clarity_version,
+        Err(_) => {
+            return (
+                Err(CheckErrors::NoSuchContract(format!("{}", &call.contract_identifier)).into()),
+                ExecutionCost::ZERO,
+            );
+        }
+    };
+
+    let mut cost_used = ExecutionCost::ZERO;
+    let result = clarity_tx.with_readonly_clarity_env(
+        mainnet,
+        chain_id,
+        clarity_version,
+        sender,
+        sponsor,
+        cost_track,
+        |env| {
+            // we want to execute any function as long as no actual writes are made as
+            // opposed to be limited to purely calling `define-read-only` functions,
+            // so use `read_only = false`.  This broadens the number of functions that
+            // can be called, and also circumvents limitations on `define-read-only`
+            // functions that can not use `contrac-call?`, even when calling other
+            // read-only functions
+            let result = env.execute_contract(
+                &call.contract_identifier,
+                call.function.as_str(),
+                &args,
+                false,
+            );
+            cost_used = env.global_context.cost_track.get_total();
+            result
+        },
+    );
+    (result, cost_used)
+}
+
 #[derive(Clone)]
 pub struct RPCCallReadOnlyRequestHandler {
     maximum_call_argument_size: u32,
@@ -220,67 +358,25 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
             .take()
             .ok_or(NetError::SendError("Missing `arguments`".into()))?;
 
+        let call = ReadOnlyCall {
+            contract_identifier,
+            function,
+            arguments,
+        };
+
         // run the read-only call
         let data_resp =
             node.with_node_state(|_network, sortdb, chainstate, _mempool, _rpc_args| {
-                let args: Vec<_> = arguments
-                    .iter()
-                    .map(|x| SymbolicExpression::atom_value(x.clone()))
-                    .collect();
-
                 let mainnet = chainstate.mainnet;
                 let chain_id = chainstate.chain_id;
-                let mut cost_limit = self.read_only_call_limit.clone();
-                cost_limit.write_length = 0;
-                cost_limit.write_count = 0;
+                let cost_limit = self.read_only_call_limit.clone();
 
                 chainstate.maybe_read_only_clarity_tx(
                     &sortdb.index_handle_at_block(chainstate, &tip)?,
                     &tip,
                     |clarity_tx| {
-                        let epoch = clarity_tx.get_epoch();
-                        let cost_track = clarity_tx
-                            .with_clarity_db_readonly(|clarity_db| {
-                                LimitedCostTracker::new_mid_block(
-                                    mainnet, chain_id, cost_limit, clarity_db, epoch,
-                                )
-                            })
-                            .map_err(|_| {
-                                ClarityRuntimeError::from(InterpreterError::CostContractLoadFailure)
-                            })?;
-
-                        let clarity_version = clarity_tx
-                            .with_analysis_db_readonly(|analysis_db| {
-                                analysis_db.get_clarity_version(&contract_identifier)
-                            })
-                            .map_err(|_| {
-                                ClarityRuntimeError::from(CheckErrors::NoSuchContract(format!(
-                                    "{}",
-                                    &contract_identifier
-                                )))
-                            })?;
-
-                        clarity_tx.with_readonly_clarity_env(
-                            mainnet,
-                            chain_id,
-                            clarity_version,
-                            sender,
-                            sponsor,
-                            cost_track,
-                            |env| {
-                                // we want to execute any function as long as no actual writes are made as
-                                // opposed to be limited to purely calling `define-read-only` functions,
-                                // so use `read_only = false`.  This broadens the number of functions that
-                                // can be called, and also circumvents limitations on `define-read-only`
-                                // functions that can not use `contrac-call?`, even when calling other
-                                // read-only functions
-                                env.execute_contract(
-                                    &contract_identifier,
-                                    function.as_str(),
-                                    &args,
-                                    false,
-                                )
-                            },
+                        call_read_only_function(
+                            clarity_tx, mainnet, chain_id, cost_limit, &call, sender, sponsor,
                         )
                     },
                 )
@@ -288,33 +384,7 @@ impl RPCRequestHandler for RPCCallReadOnlyRequestHandler {
 
         // decode the response
         let data_resp = match data_resp {
-            Ok(Some(Ok(data))) => {
-                let hex_result = data
-                    .serialize_to_hex()
-                    .map_err(|e| NetError::SerializeError(format!("{:?}", &e)))?;
-
-                CallReadOnlyResponse {
-                    okay: true,
-                    result: Some(format!("0x{}", hex_result)),
-                    cause: None,
-                }
-            }
-            Ok(Some(Err(e))) => match e {
-                Unchecked(CheckErrors::CostBalanceExceeded(actual_cost, _))
-                    if actual_cost.write_count > 0 =>
-                {
-                    CallReadOnlyResponse {
-                        okay: false,
-                        result: None,
-                        cause: Some("NotReadOnly".to_string()),
-                    }
-                }
-                _ => CallReadOnlyResponse {
-                    okay: false,
-                    result: None,
-                    cause: Some(e.to_string()),
-                },
-            },
+            Ok(Some(call_result)) => CallReadOnlyResponse::from_call_result(call_result)?,
             Ok(None) | Err(_) => {
                 return StacksHttpResponse::new_error(
                     &preamble,
@@ -0,0 +1,359 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::costs::ExecutionCost;
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier};
+use clarity::vm::{ClarityName, Value};
+use regex::{Captures, Regex};
+use stacks_common::types::chainstate::StacksBlockId;
+use stacks_common::types::net::PeerHost;
+
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::net::api::callreadonly::{
+    call_read_only_function_metered, CallReadOnlyResponse, ReadOnlyCall,
+};
+use crate::net::http::{
+    parse_json, Error, HttpContentType, HttpNotFound, HttpRequest, HttpRequestContents,
+    HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
+    HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttpRequest,
+    StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+/// Maximum number of read-only calls in a single batch
+pub const MAX_READ_ONLY_CALLS_PER_BATCH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallReadOnlyBatchEntry {
+    /// fully-qualified contract identifier
+    pub contract: String,
+    pub function: String,
+    /// 0x-prefixed, hex-encoded Clarity values
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallReadOnlyBatchRequestBody {
+    pub sender: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sponsor: Option<String>,
+    pub calls: Vec<CallReadOnlyBatchEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallReadOnlyBatchResponse {
+    /// the block against which every call was evaluated
+    pub tip: StacksBlockId,
+    /// the Stacks height of `tip`
+    pub tip_height: u64,
+    /// one result per call, in request order
+    pub results: Vec<CallReadOnlyResponse>,
+}
+
+#[derive(Clone)]
+pub struct RPCCallReadOnlyBatchRequestHandler {
+    maximum_call_argument_size: u32,
+    read_only_call_limit: ExecutionCost,
+
+    /// Runtime fields
+    pub sender: Option<PrincipalData>,
+    pub sponsor: Option<PrincipalData>,
+    pub calls: Option<Vec<ReadOnlyCall>>,
+}
+
+impl RPCCallReadOnlyBatchRequestHandler {
+    pub fn new(maximum_call_argument_size: u32, read_only_call_limit: ExecutionCost) -> Self {
+        Self {
+            maximum_call_argument_size,
+            read_only_call_limit,
+            sender: None,
+            sponsor: None,
+            calls: None,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCCallReadOnlyBatchRequestHandler {
+    fn verb(&self) -> &'static str {
+        "POST"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/contracts/call-read$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/contracts/call-read"
+    }
+
+    /// Try to decode this request.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < self.maximum_call_argument_size) {
+            return Err(Error::DecodeError(format!(
+                "Invalid Http request: invalid body length for CallReadOnlyBatch ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(Error::DecodeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let body: CallReadOnlyBatchRequestBody = serde_json::from_slice(body)
+            .map_err(|_e| Error::DecodeError("Failed to parse JSON body".into()))?;
+
+        if body.calls.is_empty() || body.calls.len() > MAX_READ_ONLY_CALLS_PER_BATCH {
+            return Err(Error::DecodeError(format!(
+                "Invalid Http request: expected between 1 and {} calls",
+                MAX_READ_ONLY_CALLS_PER_BATCH
+            )));
+        }
+
+        let sender = PrincipalData::parse(&body.sender)
+            .map_err(|_e| Error::DecodeError("Failed to parse sender principal".into()))?;
+
+        let sponsor = if let Some(sponsor) = body.sponsor {
+            Some(
+                PrincipalData::parse(&sponsor)
+                    .map_err(|_e| Error::DecodeError("Failed to parse sponsor principal".into()))?,
+            )
+        } else {
+            None
+        };
+
+        let mut calls = Vec::with_capacity(body.calls.len());
+        for entry in body.calls.into_iter() {
+            let contract_identifier = QualifiedContractIdentifier::parse(&entry.contract)
+                .map_err(|_e| Error::DecodeError("Failed to parse contract identifier".into()))?;
+            let function = ClarityName::try_from(entry.function)
+                .map_err(|_e| Error::DecodeError("Failed to parse function name".into()))?;
+
+            // arguments must be valid Clarity values
+            let arguments = entry
+                .arguments
+                .into_iter()
+                .map(|hex| Value::try_deserialize_hex_untyped(&hex).ok())
+                .collect::<Option<Vec<Value>>>()
+                .ok_or_else(|| Error::DecodeError("Failed to deserialize argument value".into()))?;
+
+            calls.push(ReadOnlyCall {
+                contract_identifier,
+                function,
+                arguments,
+            });
+        }
+
+        self.sender = Some(sender);
+        self.sponsor = sponsor;
+        self.calls = Some(calls);
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+/// Handle the HTTP request
+impl RPCRequestHandler for RPCCallReadOnlyBatchRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.sender = None;
+        self.sponsor = None;
+        self.calls = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let sender = self
+            .sender
+            .take()
+            .ok_or(NetError::SendError("Missing `sender`".into()))?;
+        let sponsor = self.sponsor.take();
+        let calls = self
+            .calls
+            .take()
+            .ok_or(NetError::SendError("Missing `calls`".into()))?;
+
+        // run every call against the same snapshot of the chain state.
+        // The calls share one cost budget: each call may only spend what the calls before it
+        // left over, so a batch costs no more than a single read-only call.
+        let batch_res =
+            node.with_node_state(|_network, sortdb, chainstate, _mempool, _rpc_args| {
+                let Some(header) = NakamotoChainState::get_block_header(chainstate.db(), &tip)?
+                else {
+                    return Err(NetError::NotFoundError);
+                };
+
+                let mainnet = chainstate.mainnet;
+                let chain_id = chainstate.chain_id;
+                let call_results = chainstate
+                    .maybe_read_only_clarity_tx(
+                        &sortdb.index_handle_at_block(chainstate, &tip)?,
+                        &tip,
+                        |clarity_tx| {
+                            let mut budget = self.read_only_call_limit.clone();
+                            calls
+                                .iter()
+                                .map(|call| {
+                                    let (result, cost) = call_read_only_function_metered(
+                                        clarity_tx,
+                                        mainnet,
+                                        chain_id,
+                                        budget.clone(),
+                                        call,
+                                        sender.clone(),
+                                        sponsor.clone(),
+                                    );
+                                    if budget.sub(&cost).is_err() {
+                                        // the call overran what was left
+                                        budget = ExecutionCost::ZERO;
+                                    }
+                                    result
+                                })
+                                .collect::<Vec<_>>()
+                        },
+                    )?
+                    .ok_or(NetError::NotFoundError)?;
+
+                Ok((header.stacks_block_height, call_results))
+            });
+
+        let (tip_height, call_results) = match batch_res {
+            Ok(batch) => batch,
+            Err(NetError::NotFoundError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Chain tip not found".to_string()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Err(e) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpServerError::new(format!("Failed to run read-only calls: {:?}", &e)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let results = call_results
+            .into_iter()
+            .map(CallReadOnlyResponse::from_call_result)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let batch_resp = CallReadOnlyBatchResponse {
+            tip,
+            tip_height,
+            results,
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&batch_resp)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCCallReadOnlyBatchRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let batch_resp: CallReadOnlyBatchResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(batch_resp)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request to run a batch of read-only functions
+    pub fn new_callreadonlybatch(
+        host: PeerHost,
+        sender: PrincipalData,
+        sponsor: Option<PrincipalData>,
+        calls: Vec<ReadOnlyCall>,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        let calls = calls
+            .into_iter()
+            .map(|call| CallReadOnlyBatchEntry {
+                contract: call.contract_identifier.to_string(),
+                function: call.function.to_string(),
+                arguments: call
+                    .arguments
+                    .iter()
+                    .map(|v| {
+                        v.serialize_to_hex()
+                            .expect("FATAL: failed to serialize Clarity value")
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        StacksHttpRequest::new_for_peer(
+            host,
+            "POST".into(),
+            "/v3/contracts/call-read".to_string(),
+            HttpRequestContents::new().for_tip(tip_req).payload_json(
+                serde_json::to_value(CallReadOnlyBatchRequestBody {
+                    sender: sender.to_string(),
+                    sponsor: sponsor.map(|s| s.to_string()),
+                    calls,
+                })
+                .expect("FATAL: failed to encode infallible data"),
+            ),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_call_readonly_batch_response(
+        self,
+    ) -> Result<CallReadOnlyBatchResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: CallReadOnlyBatchResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
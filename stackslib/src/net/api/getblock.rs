@@ -29,9 +29,9 @@ use {serde, serde_json};
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::{Error as ChainError, StacksBlock};
 use crate::net::http::{
-    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpNotFound,
-    HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse, HttpResponseContents,
-    HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
+    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpGone,
+    HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse,
+    HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
 };
 use crate::net::httpcore::{
     HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
@@ -144,12 +144,26 @@ impl RPCRequestHandler for RPCBlocksRequestHandler {
 
         let stream_res =
             node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                if StacksChainState::is_block_pruned(chainstate.db(), &block_id)? {
+                    return Err(ChainError::PrunedBlockError);
+                }
                 StacksBlockStream::new(chainstate, &block_id)
             });
 
         // start loading up the block
         let stream = match stream_res {
             Ok(stream) => stream,
+            Err(ChainError::PrunedBlockError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpGone::new(format!(
+                        "Block {} has been pruned by this node\n",
+                        &block_id
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from)
+            }
             Err(ChainError::NoSuchBlockError) => {
                 return StacksHttpResponse::new_error(
                     &preamble,
@@ -30,9 +30,9 @@ use crate::chainstate::nakamoto::{NakamotoBlock, NakamotoChainState, NakamotoSta
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::Error as ChainError;
 use crate::net::http::{
-    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpNotFound,
-    HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse, HttpResponseContents,
-    HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
+    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpGone,
+    HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse,
+    HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
 };
 use crate::net::httpcore::{
     HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
@@ -181,6 +181,9 @@ impl RPCRequestHandler for RPCNakamotoBlockRequestHandler {
 
         let stream_res =
             node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                if StacksChainState::is_block_pruned(chainstate.db(), &block_id)? {
+                    return Err(ChainError::PrunedBlockError);
+                }
                 let Some((tenure_id, parent_block_id)) = chainstate
                     .nakamoto_blocks_db()
                     .get_tenure_and_parent_block_id(&block_id)?
@@ -193,6 +196,17 @@ impl RPCRequestHandler for RPCNakamotoBlockRequestHandler {
         // start loading up the block
         let stream = match stream_res {
             Ok(stream) => stream,
+            Err(ChainError::PrunedBlockError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpGone::new(format!(
+                        "Block {} has been pruned by this node\n",
+                        &block_id
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from)
+            }
             Err(ChainError::NoSuchBlockError) => {
                 return StacksHttpResponse::new_error(
                     &preamble,
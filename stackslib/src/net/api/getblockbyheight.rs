@@ -32,9 +32,9 @@ use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::Error as ChainError;
 use crate::net::api::getblock_v3::{NakamotoBlockStream, RPCNakamotoBlockRequestHandler};
 use crate::net::http::{
-    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpNotFound,
-    HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse, HttpResponseContents,
-    HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
+    parse_bytes, Error, HttpBadRequest, HttpChunkGenerator, HttpContentType, HttpGone,
+    HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble, HttpResponse,
+    HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError, HttpVersion,
 };
 use crate::net::httpcore::{
     HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
@@ -155,6 +155,9 @@ impl RPCRequestHandler for RPCNakamotoBlockByHeightRequestHandler {
 
         let stream_res =
             node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                if StacksChainState::is_block_pruned(chainstate.db(), &block_id)? {
+                    return Err(ChainError::PrunedBlockError);
+                }
                 let Some((tenure_id, parent_block_id)) = chainstate
                     .nakamoto_blocks_db()
                     .get_tenure_and_parent_block_id(&block_id)?
@@ -167,6 +170,17 @@ impl RPCRequestHandler for RPCNakamotoBlockByHeightRequestHandler {
         // start loading up the block
         let stream = match stream_res {
             Ok(stream) => stream,
+            Err(ChainError::PrunedBlockError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpGone::new(format!(
+                        "Block #{} has been pruned by this node\n",
+                        block_height
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from)
+            }
             Err(ChainError::NoSuchBlockError) => {
                 return StacksHttpResponse::new_error(
                     &preamble,
@@ -0,0 +1,483 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::sync::{Arc, Mutex};
+
+use regex::{Captures, Regex};
+use serde_json::json;
+use stacks_common::codec::{StacksMessageCodec, MAX_MESSAGE_LEN};
+use stacks_common::types::chainstate::{BurnchainHeaderHash, ConsensusHash, StacksBlockId};
+use stacks_common::types::net::PeerHost;
+use stacks_common::util::get_epoch_time_ms;
+use stacks_common::util::hash::to_hex;
+
+use crate::chainstate::stacks::db::StacksHeaderInfo;
+use crate::chainstate::stacks::{StacksTransaction, TenureChangePayload};
+use crate::net::http::common::parse_raw_bytes;
+use crate::net::http::{
+    Error, HttpChunkGenerator, HttpContentType, HttpRequest, HttpRequestContents,
+    HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
+    HttpResponsePreamble, HttpServiceUnavailable,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// How often to send a comment line to an idle subscriber, so the connection is not reaped
+pub const EVENT_STREAM_HEARTBEAT_MS: u128 = 5_000;
+
+/// Kinds of events which can be streamed to subscribers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RPCStreamEventKind {
+    /// A Stacks block was processed
+    Block,
+    /// A burnchain block was processed
+    BurnBlock,
+    /// A transaction was admitted to the mempool
+    MempoolTx,
+    /// A processed Stacks block started or extended a tenure
+    TenureChange,
+}
+
+impl RPCStreamEventKind {
+    pub const ALL: [RPCStreamEventKind; 4] = [
+        RPCStreamEventKind::Block,
+        RPCStreamEventKind::BurnBlock,
+        RPCStreamEventKind::MempoolTx,
+        RPCStreamEventKind::TenureChange,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::BurnBlock => "burn_block",
+            Self::MempoolTx => "mempool_tx",
+            Self::TenureChange => "tenure_change",
+        }
+    }
+}
+
+impl FromStr for RPCStreamEventKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| format!("Unknown event kind '{s}'"))
+    }
+}
+
+impl fmt::Display for RPCStreamEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An event to stream to subscribers
+#[derive(Debug, Clone, PartialEq)]
+pub struct RPCStreamEvent {
+    pub kind: RPCStreamEventKind,
+    pub payload: serde_json::Value,
+}
+
+impl RPCStreamEvent {
+    /// A Stacks block was processed
+    pub fn new_block(
+        metadata: &StacksHeaderInfo,
+        parent_index_block_hash: &StacksBlockId,
+        tx_count: usize,
+    ) -> Self {
+        Self {
+            kind: RPCStreamEventKind::Block,
+            payload: json!({
+                "block_hash": format!("0x{}", metadata.anchored_header.block_hash()),
+                "index_block_hash": format!("0x{}", metadata.index_block_hash()),
+                "parent_index_block_hash": format!("0x{}", parent_index_block_hash),
+                "block_height": metadata.stacks_block_height,
+                "consensus_hash": format!("0x{}", metadata.consensus_hash),
+                "burn_block_hash": format!("0x{}", metadata.burn_header_hash),
+                "burn_block_height": metadata.burn_header_height,
+                "tx_count": tx_count,
+            }),
+        }
+    }
+
+    /// A burnchain block was processed
+    pub fn new_burn_block(
+        burn_block_hash: &BurnchainHeaderHash,
+        burn_block_height: u64,
+        consensus_hash: &ConsensusHash,
+    ) -> Self {
+        Self {
+            kind: RPCStreamEventKind::BurnBlock,
+            payload: json!({
+                "burn_block_hash": format!("0x{}", burn_block_hash),
+                "burn_block_height": burn_block_height,
+                "consensus_hash": format!("0x{}", consensus_hash),
+            }),
+        }
+    }
+
+    /// A transaction was admitted to the mempool
+    pub fn new_mempool_tx(tx: &StacksTransaction) -> Self {
+        Self {
+            kind: RPCStreamEventKind::MempoolTx,
+            payload: json!({
+                "txid": format!("0x{}", tx.txid()),
+                "origin": tx.origin_address().to_string(),
+                "nonce": tx.get_origin_nonce(),
+                "fee": tx.get_tx_fee(),
+                "tx": format!("0x{}", to_hex(&tx.serialize_to_vec())),
+            }),
+        }
+    }
+
+    /// A processed Stacks block contained a tenure change
+    pub fn new_tenure_change(tc: &TenureChangePayload, metadata: &StacksHeaderInfo) -> Self {
+        Self {
+            kind: RPCStreamEventKind::TenureChange,
+            payload: json!({
+                "tenure_consensus_hash": format!("0x{}", tc.tenure_consensus_hash),
+                "prev_tenure_consensus_hash": format!("0x{}", tc.prev_tenure_consensus_hash),
+                "burn_view_consensus_hash": format!("0x{}", tc.burn_view_consensus_hash),
+                "previous_tenure_end": format!("0x{}", tc.previous_tenure_end),
+                "previous_tenure_blocks": tc.previous_tenure_blocks,
+                "cause": tc.cause,
+                "index_block_hash": format!("0x{}", metadata.index_block_hash()),
+                "block_height": metadata.stacks_block_height,
+            }),
+        }
+    }
+
+    /// Encode as a server-sent event
+    pub fn to_sse(&self, event_id: u64) -> String {
+        format!(
+            "id: {}\nevent: {}\ndata: {}\n\n",
+            event_id, self.kind, &self.payload
+        )
+    }
+}
+
+struct RPCEventStreamSubscriber {
+    kinds: HashSet<RPCStreamEventKind>,
+    sender: SyncSender<String>,
+}
+
+#[derive(Default)]
+struct RPCEventStreamHubState {
+    subscribers: Vec<RPCEventStreamSubscriber>,
+    next_event_id: u64,
+}
+
+/// Fan-out of processed events to `/v3/events/stream` subscribers.
+/// Clones share the same set of subscribers, so the node's event dispatcher can publish to the
+/// same hub that the RPC server subscribes to.
+#[derive(Clone)]
+pub struct RPCEventStreamHub {
+    state: Arc<Mutex<RPCEventStreamHubState>>,
+    max_subscribers: usize,
+    buffer_len: usize,
+}
+
+impl RPCEventStreamHub {
+    pub fn new(max_subscribers: usize, buffer_len: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(RPCEventStreamHubState::default())),
+            max_subscribers,
+            buffer_len,
+        }
+    }
+
+    /// Subscribe to the given kinds of events.
+    /// Returns None if there are already too many subscribers.
+    pub fn subscribe(&self, kinds: HashSet<RPCStreamEventKind>) -> Option<Receiver<String>> {
+        let mut state = self
+            .state
+            .lock()
+            .expect("FATAL: event stream hub mutex poisoned");
+        if state.subscribers.len() >= self.max_subscribers {
+            return None;
+        }
+        let (sender, receiver) = sync_channel(self.buffer_len);
+        state
+            .subscribers
+            .push(RPCEventStreamSubscriber { kinds, sender });
+        Some(receiver)
+    }
+
+    /// Send an event to each interested subscriber.
+    /// Subscribers which hung up, or which have fallen too far behind, are dropped.  A dropped
+    /// subscriber's stream ends once it has sent the events it already buffered.
+    pub fn publish(&self, event: RPCStreamEvent) {
+        let mut state = self
+            .state
+            .lock()
+            .expect("FATAL: event stream hub mutex poisoned");
+        if state.subscribers.is_empty() {
+            return;
+        }
+        let event_id = state.next_event_id;
+        state.next_event_id = state.next_event_id.wrapping_add(1);
+
+        let mut sse = None;
+        state.subscribers.retain(|subscriber| {
+            if !subscriber.kinds.contains(&event.kind) {
+                return true;
+            }
+            let sse = sse.get_or_insert_with(|| event.to_sse(event_id));
+            match subscriber.sender.try_send(sse.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    debug!("Dropping event stream subscriber which has fallen behind");
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            }
+        });
+    }
+
+    /// How many subscribers are there?
+    pub fn num_subscribers(&self) -> usize {
+        self.state
+            .lock()
+            .expect("FATAL: event stream hub mutex poisoned")
+            .subscribers
+            .len()
+    }
+}
+
+/// Long-lived stream of server-sent events for a single subscriber
+pub struct RPCEventStreamGenerator {
+    receiver: Receiver<String>,
+    open: bool,
+    started: bool,
+    last_send_ms: u128,
+}
+
+impl RPCEventStreamGenerator {
+    pub fn new(receiver: Receiver<String>) -> Self {
+        Self {
+            receiver,
+            open: true,
+            started: false,
+            last_send_ms: 0,
+        }
+    }
+}
+
+impl HttpChunkGenerator for RPCEventStreamGenerator {
+    fn hint_chunk_size(&self) -> usize {
+        4096
+    }
+
+    fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Send whatever events have been published since the last chunk.
+    /// If there are none, then send a comment line every so often to keep the connection alive.
+    fn generate_next_chunk(&mut self) -> Result<Vec<u8>, String> {
+        let mut chunk = vec![];
+        if !self.started {
+            self.started = true;
+            chunk.extend_from_slice(b": stream opened\n\n");
+        }
+        while chunk.len() < self.hint_chunk_size() {
+            match self.receiver.try_recv() {
+                Ok(sse) => chunk.extend_from_slice(sse.as_bytes()),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.open = false;
+                    break;
+                }
+            }
+        }
+
+        let now = get_epoch_time_ms();
+        if chunk.is_empty()
+            && self.open
+            && now.saturating_sub(self.last_send_ms) >= EVENT_STREAM_HEARTBEAT_MS
+        {
+            chunk.extend_from_slice(b": keep-alive\n\n");
+        }
+        if !chunk.is_empty() {
+            self.last_send_ms = now;
+        }
+        Ok(chunk)
+    }
+}
+
+#[derive(Clone)]
+pub struct RPCGetEventStreamRequestHandler {
+    pub kinds: Option<HashSet<RPCStreamEventKind>>,
+}
+
+impl RPCGetEventStreamRequestHandler {
+    pub fn new() -> Self {
+        Self { kinds: None }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetEventStreamRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/events/stream$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/events/stream"
+    }
+
+    /// Try to decode this request.
+    /// The optional `events=` query parameter is a comma-separated list of event kinds.  If it is
+    /// not given, then all kinds of events are streamed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let req_contents = HttpRequestContents::new().query_string(query);
+        let kinds = match req_contents.get_query_arg("events") {
+            Some(kinds_str) => kinds_str
+                .split(',')
+                .map(|kind_str| {
+                    RPCStreamEventKind::from_str(kind_str.trim()).map_err(Error::DecodeError)
+                })
+                .collect::<Result<HashSet<_>, _>>()?,
+            None => RPCStreamEventKind::ALL.into_iter().collect(),
+        };
+
+        self.kinds = Some(kinds);
+        Ok(req_contents)
+    }
+}
+
+impl RPCRequestHandler for RPCGetEventStreamRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.kinds = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let kinds = self
+            .kinds
+            .take()
+            .ok_or(NetError::SendError("`kinds` not set".into()))?;
+
+        let receiver_res =
+            node.with_node_state(|_network, _sortdb, _chainstate, _mempool, rpc_args| {
+                let Some(hub) = rpc_args.event_stream else {
+                    return Err("Event streaming is not enabled on this node");
+                };
+                hub.subscribe(kinds)
+                    .ok_or("Too many event stream subscribers")
+            });
+
+        let receiver = match receiver_res {
+            Ok(receiver) => receiver,
+            Err(msg) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpServiceUnavailable::new(msg.to_string()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let mut resp_preamble = HttpResponsePreamble::from_http_request_preamble(
+            &preamble,
+            200,
+            "OK",
+            None,
+            HttpContentType::EventStream,
+        );
+        resp_preamble.add_header("Cache-Control".into(), "no-cache".into());
+        resp_preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+
+        Ok((
+            resp_preamble,
+            HttpResponseContents::from_stream(Box::new(RPCEventStreamGenerator::new(receiver))),
+        ))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetEventStreamRequestHandler {
+    /// Decode whatever part of the stream the client has received
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let bytes = parse_raw_bytes(
+            preamble,
+            body,
+            MAX_MESSAGE_LEN.into(),
+            HttpContentType::EventStream,
+        )?;
+        Ok(HttpResponsePayload::Text(
+            String::from_utf8_lossy(&bytes).to_string(),
+        ))
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request to subscribe to the event stream
+    pub fn new_geteventstream(host: PeerHost, kinds: &[RPCStreamEventKind]) -> StacksHttpRequest {
+        let mut contents = HttpRequestContents::new();
+        if !kinds.is_empty() {
+            let kinds_str: Vec<_> = kinds.iter().map(|kind| kind.as_str()).collect();
+            contents = contents.query_arg("events".into(), kinds_str.join(","));
+        }
+        StacksHttpRequest::new_for_peer(host, "GET".into(), "/v3/events/stream".into(), contents)
+            .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    /// Get the raw server-sent events received so far
+    pub fn decode_event_stream(self) -> Result<String, NetError> {
+        match self.get_http_payload_ok()? {
+            HttpResponsePayload::Text(text) => Ok(text),
+            _ => Err(NetError::DeserializeError(
+                "Invalid event stream: expected text".to_string(),
+            )),
+        }
+    }
+}
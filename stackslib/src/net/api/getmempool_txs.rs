@@ -0,0 +1,339 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::types::{QualifiedContractIdentifier, StacksAddressExtensions};
+use regex::{Captures, Regex};
+use stacks_common::codec::StacksMessageCodec;
+use stacks_common::types::chainstate::{StacksAddress, StacksBlockId};
+use stacks_common::types::net::PeerHost;
+use stacks_common::types::Address;
+use stacks_common::util::hash::to_hex;
+
+use crate::burnchains::Txid;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::core::mempool::{MemPoolDB, MemPoolTxFilter, MemPoolTxInfo};
+use crate::net::http::{
+    parse_json, Error, HttpBadRequest, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, RPCRequestHandler, StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState};
+
+/// Number of transactions returned if the request does not set `limit`
+pub const MEMPOOL_TXS_DEFAULT_LIMIT: u64 = 200;
+/// Most transactions returned for one request
+pub const MEMPOOL_TXS_MAX_LIMIT: u64 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MempoolTransaction {
+    pub txid: Txid,
+    /// Hex-encoded transaction
+    pub tx: String,
+    pub origin_address: String,
+    pub origin_nonce: u64,
+    pub sponsor_address: String,
+    pub sponsor_nonce: u64,
+    pub tx_fee: u64,
+    pub length: u64,
+    pub accept_time: u64,
+}
+
+impl From<MemPoolTxInfo> for MempoolTransaction {
+    fn from(txinfo: MemPoolTxInfo) -> Self {
+        let md = txinfo.metadata;
+        Self {
+            txid: md.txid,
+            tx: to_hex(&txinfo.tx.serialize_to_vec()),
+            origin_address: md.origin_address.to_string(),
+            origin_nonce: md.origin_nonce,
+            sponsor_address: md.sponsor_address.to_string(),
+            sponsor_nonce: md.sponsor_nonce,
+            tx_fee: md.tx_fee,
+            length: md.len,
+            accept_time: md.accept_time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MempoolTransactionsResponse {
+    /// Confirmed nonce of the queried origin (or sponsor) at the canonical chain tip.  Pending
+    /// transactions have nonces at or above it.
+    pub account_nonce: Option<u64>,
+    /// Nonces from `account_nonce` up to the highest returned nonce which have no pending
+    /// transaction, and so hold up the transactions after them
+    pub missing_nonces: Vec<u64>,
+    pub transactions: Vec<MempoolTransaction>,
+}
+
+#[derive(Clone, Default)]
+pub struct RPCGetMempoolTransactionsRequestHandler {
+    pub filter: Option<MemPoolTxFilter>,
+    pub limit: Option<u64>,
+}
+
+impl RPCGetMempoolTransactionsRequestHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn parse_address_arg(
+    contents: &HttpRequestContents,
+    key: &str,
+) -> Result<Option<StacksAddress>, Error> {
+    contents
+        .get_query_arg(key)
+        .map(|addr| {
+            StacksAddress::from_string(addr)
+                .ok_or_else(|| Error::DecodeError(format!("Failed to parse `{key}` address")))
+        })
+        .transpose()
+}
+
+fn parse_u64_arg(contents: &HttpRequestContents, key: &str) -> Result<Option<u64>, Error> {
+    contents
+        .get_query_arg(key)
+        .map(|value| {
+            value
+                .parse::<u64>()
+                .map_err(|_| Error::DecodeError(format!("Failed to parse `{key}`")))
+        })
+        .transpose()
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetMempoolTransactionsRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/mempool/transactions$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/mempool/transactions"
+    }
+
+    /// Try to decode this request.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+
+        let contents = HttpRequestContents::new().query_string(query);
+        let contract = contents
+            .get_query_arg("contract")
+            .map(|contract| {
+                QualifiedContractIdentifier::parse(contract)
+                    .map_err(|_| Error::DecodeError("Failed to parse `contract`".to_string()))
+            })
+            .transpose()?;
+        let filter = MemPoolTxFilter {
+            origin: parse_address_arg(&contents, "origin")?,
+            sponsor: parse_address_arg(&contents, "sponsor")?,
+            contract,
+            min_nonce: parse_u64_arg(&contents, "min_nonce")?,
+            max_nonce: parse_u64_arg(&contents, "max_nonce")?,
+        };
+        // the contract is not indexed, so it only narrows down an account's transactions
+        if filter.contract.is_some() && filter.origin.is_none() && filter.sponsor.is_none() {
+            return Err(Error::DecodeError(
+                "`contract` requires `origin` or `sponsor`".to_string(),
+            ));
+        }
+        if let (Some(min_nonce), Some(max_nonce)) = (filter.min_nonce, filter.max_nonce) {
+            if min_nonce > max_nonce {
+                return Err(Error::DecodeError(
+                    "`min_nonce` is greater than `max_nonce`".to_string(),
+                ));
+            }
+        }
+        let limit = parse_u64_arg(&contents, "limit")?.unwrap_or(MEMPOOL_TXS_DEFAULT_LIMIT);
+        if limit == 0 || limit > MEMPOOL_TXS_MAX_LIMIT {
+            return Err(Error::DecodeError(format!(
+                "`limit` must be between 1 and {MEMPOOL_TXS_MAX_LIMIT}"
+            )));
+        }
+
+        self.filter = Some(filter);
+        self.limit = Some(limit);
+        Ok(contents)
+    }
+}
+
+impl RPCRequestHandler for RPCGetMempoolTransactionsRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.filter = None;
+        self.limit = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        _contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let mut filter = self
+            .filter
+            .take()
+            .ok_or(NetError::SendError("`filter` not set".into()))?;
+        let limit = self
+            .limit
+            .take()
+            .ok_or(NetError::SendError("`limit` not set".into()))?;
+
+        let resp_res = node.with_node_state(|network, sortdb, chainstate, mempool, _rpc_args| {
+            // mined transactions stay in the mempool until garbage-collected, so skip any
+            // transactions below the account's nonce
+            let account_nonce = match filter.origin.as_ref().or(filter.sponsor.as_ref()) {
+                Some(address) => {
+                    let tip = StacksBlockId::new(
+                        &network.stacks_tip.consensus_hash,
+                        &network.stacks_tip.block_hash,
+                    );
+                    let principal = address.to_account_principal();
+                    let nonce = chainstate
+                        .with_read_only_clarity_tx(
+                            &sortdb.index_handle_at_block(chainstate, &tip)?,
+                            &tip,
+                            |clarity_tx| StacksChainState::get_nonce(clarity_tx, &principal),
+                        )
+                        .ok_or(NetError::NotFoundError)?;
+                    filter.min_nonce = Some(filter.min_nonce.unwrap_or(0).max(nonce));
+                    Some(nonce)
+                }
+                None => None,
+            };
+
+            let txs = MemPoolDB::find_txs(mempool.conn(), &filter, limit)?;
+            // a gap is a nonce with no pending transaction at all, whether or not it matches
+            // the contract filter
+            let missing_nonces = match (account_nonce, txs.last()) {
+                (Some(account_nonce), Some(last)) => {
+                    let start = filter.min_nonce.unwrap_or(account_nonce);
+                    let end = filter.nonce(&last.metadata);
+                    let nonces = MemPoolDB::find_tx_nonces(mempool.conn(), &filter, start, end)?;
+                    (start..end)
+                        .filter(|nonce| nonces.binary_search(nonce).is_err())
+                        .collect()
+                }
+                _ => vec![],
+            };
+
+            Ok::<_, NetError>(MempoolTransactionsResponse {
+                account_nonce,
+                missing_nonces,
+                transactions: txs.into_iter().map(MempoolTransaction::from).collect(),
+            })
+        });
+
+        let resp = match resp_res {
+            Ok(resp) => resp,
+            Err(NetError::NotFoundError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpBadRequest::new("Canonical chain tip is not available".into()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Err(e) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpServerError::new(format!("Failed to query mempool: {e:?}")),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&resp)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetMempoolTransactionsRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let resp: MempoolTransactionsResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(resp)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for pending mempool transactions
+    pub fn new_get_mempool_transactions(
+        host: PeerHost,
+        filter: &MemPoolTxFilter,
+        limit: Option<u64>,
+    ) -> StacksHttpRequest {
+        let mut contents = HttpRequestContents::new();
+        if let Some(origin) = filter.origin.as_ref() {
+            contents = contents.query_arg("origin".into(), origin.to_string());
+        }
+        if let Some(sponsor) = filter.sponsor.as_ref() {
+            contents = contents.query_arg("sponsor".into(), sponsor.to_string());
+        }
+        if let Some(contract) = filter.contract.as_ref() {
+            contents = contents.query_arg("contract".into(), contract.to_string());
+        }
+        if let Some(min_nonce) = filter.min_nonce {
+            contents = contents.query_arg("min_nonce".into(), min_nonce.to_string());
+        }
+        if let Some(max_nonce) = filter.max_nonce {
+            contents = contents.query_arg("max_nonce".into(), max_nonce.to_string());
+        }
+        if let Some(limit) = limit {
+            contents = contents.query_arg("limit".into(), limit.to_string());
+        }
+        StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            "/v3/mempool/transactions".into(),
+            contents,
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_mempool_transactions(self) -> Result<MempoolTransactionsResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let response_json: serde_json::Value = contents.try_into()?;
+        let resp: MempoolTransactionsResponse = serde_json::from_value(response_json)
+            .map_err(|_e| Error::DecodeError("Failed to decode JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -0,0 +1,221 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use regex::{Captures, Regex};
+use stacks_common::types::chainstate::StacksBlockId;
+use stacks_common::types::net::PeerHost;
+
+use crate::burnchains::Txid;
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::net::http::{
+    parse_json, Error, HttpNotFound, HttpRequest, HttpRequestContents, HttpRequestPreamble,
+    HttpResponse, HttpResponseContents, HttpResponsePayload, HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    request, HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler,
+    StacksHttpRequest, StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+/// A confirmed transaction, as served by `/v3/transactions/{txid}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionResponse {
+    /// index block hash of the block which contains the transaction
+    pub index_block_hash: StacksBlockId,
+    /// height of that block
+    pub block_height: u64,
+    /// position of the transaction in that block
+    pub tx_index: u32,
+    /// hex-encoded transaction
+    pub tx: String,
+    /// 0x-prefixed, hex-encoded Clarity value returned by the transaction
+    pub result: String,
+    /// events emitted by the transaction, in the same format as the event observer interface
+    pub events: serde_json::Value,
+}
+
+#[derive(Clone)]
+pub struct RPCGetTransactionRequestHandler {
+    pub txid: Option<Txid>,
+}
+impl RPCGetTransactionRequestHandler {
+    pub fn new() -> Self {
+        Self { txid: None }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCGetTransactionRequestHandler {
+    fn verb(&self) -> &'static str {
+        "GET"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/transactions/(?P<txid>[0-9a-f]{64})$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/transactions/:txid"
+    }
+
+    /// Try to decode this request.
+    /// There's nothing to load here, so just make sure the request is well-formed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() != 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected 0-length body for GetTransaction".to_string(),
+            ));
+        }
+
+        let txid = request::get_txid(captures, "txid")?;
+        self.txid = Some(txid);
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCGetTransactionRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.txid = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let txid = self
+            .txid
+            .take()
+            .ok_or(NetError::SendError("`txid` no set".into()))?;
+
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let txinfo_res =
+            node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                // the same transaction can be mined in more than one fork, so only report the
+                // copy which is an ancestor of `tip`
+                for entry in StacksChainState::get_indexed_transactions(chainstate.db(), &txid)? {
+                    let Some(header) = NakamotoChainState::get_block_header(
+                        chainstate.db(),
+                        &entry.index_block_hash,
+                    )?
+                    else {
+                        continue;
+                    };
+                    let ancestor = chainstate
+                        .index_conn()
+                        .get_ancestor_block_hash(header.stacks_block_height, &tip)?;
+                    if ancestor.as_ref() != Some(&entry.index_block_hash) {
+                        continue;
+                    }
+                    return Ok(TransactionResponse {
+                        index_block_hash: entry.index_block_hash,
+                        block_height: header.stacks_block_height,
+                        tx_index: entry.tx_index,
+                        tx: entry.tx_hex,
+                        result: format!("0x{}", &entry.result_hex),
+                        events: entry.events,
+                    });
+                }
+                Err(NetError::NotFoundError)
+            });
+
+        let txinfo = match txinfo_res {
+            Ok(txinfo) => txinfo,
+            Err(NetError::NotFoundError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new(format!(
+                        "Transaction {} not found in the transaction index",
+                        &txid
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Err(e) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpServerError::new(format!(
+                        "Failed to query transaction {}: {:?}",
+                        &txid, &e
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&txinfo)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCGetTransactionRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let txinfo: TransactionResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(txinfo)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new get-confirmed-tx request
+    pub fn new_gettransaction(
+        host: PeerHost,
+        txid: Txid,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "GET".into(),
+            format!("/v3/transactions/{}", &txid),
+            HttpRequestContents::new().for_tip(tip_req),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_gettransaction(self) -> Result<TransactionResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let response_json: serde_json::Value = contents.try_into()?;
+        let txinfo: TransactionResponse = serde_json::from_value(response_json)
+            .map_err(|_e| Error::DecodeError("Failed to decode JSON".to_string()))?;
+        Ok(txinfo)
+    }
+}
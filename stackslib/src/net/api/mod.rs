@@ -37,6 +37,7 @@ use crate::net::Error as NetError;
 use crate::stacks_common::codec::StacksMessageCodec;
 
 pub mod callreadonly;
+pub mod callreadonlybatch;
 pub mod get_tenures_fork_info;
 pub mod getaccount;
 pub mod getattachment;
@@ -50,10 +51,13 @@ pub mod getconstantval;
 pub mod getcontractabi;
 pub mod getcontractsrc;
 pub mod getdatavar;
+pub mod geteventstream;
 pub mod getheaders;
 pub mod getinfo;
 pub mod getistraitimplemented;
 pub mod getmapentry;
+pub mod getmempool_stats;
+pub mod getmempool_txs;
 pub mod getmicroblocks_confirmed;
 pub mod getmicroblocks_indexed;
 pub mod getmicroblocks_unconfirmed;
@@ -68,6 +72,7 @@ pub mod getstxtransfercost;
 pub mod gettenure;
 pub mod gettenureinfo;
 pub mod gettenuretip;
+pub mod gettransaction;
 pub mod gettransaction_unconfirmed;
 pub mod liststackerdbreplicas;
 pub mod postblock;
@@ -75,10 +80,12 @@ pub mod postblock_proposal;
 #[warn(unused_imports)]
 pub mod postblock_v3;
 pub mod postfeerate;
+pub mod postmarfproof;
 pub mod postmempoolquery;
 pub mod postmicroblock;
 pub mod poststackerdbchunk;
 pub mod posttransaction;
+pub mod posttransaction_simulate;
 
 #[cfg(test)]
 mod tests;
@@ -91,6 +98,10 @@ impl StacksHttp {
             self.maximum_call_argument_size,
             self.read_only_call_limit.clone(),
         ));
+        self.register_rpc_endpoint(callreadonlybatch::RPCCallReadOnlyBatchRequestHandler::new(
+            self.maximum_call_argument_size,
+            self.read_only_call_limit.clone(),
+        ));
         self.register_rpc_endpoint(getaccount::RPCGetAccountRequestHandler::new());
         self.register_rpc_endpoint(getattachment::RPCGetAttachmentRequestHandler::new());
         self.register_rpc_endpoint(getattachmentsinv::RPCGetAttachmentsInvRequestHandler::new());
@@ -103,12 +114,15 @@ impl StacksHttp {
         self.register_rpc_endpoint(getcontractabi::RPCGetContractAbiRequestHandler::new());
         self.register_rpc_endpoint(getcontractsrc::RPCGetContractSrcRequestHandler::new());
         self.register_rpc_endpoint(getdatavar::RPCGetDataVarRequestHandler::new());
+        self.register_rpc_endpoint(geteventstream::RPCGetEventStreamRequestHandler::new());
         self.register_rpc_endpoint(getheaders::RPCHeadersRequestHandler::new());
         self.register_rpc_endpoint(getinfo::RPCPeerInfoRequestHandler::new());
         self.register_rpc_endpoint(
             getistraitimplemented::RPCGetIsTraitImplementedRequestHandler::new(),
         );
         self.register_rpc_endpoint(getmapentry::RPCGetMapEntryRequestHandler::new());
+        self.register_rpc_endpoint(getmempool_stats::RPCGetMempoolStatsRequestHandler::new());
+        self.register_rpc_endpoint(getmempool_txs::RPCGetMempoolTransactionsRequestHandler::new());
         self.register_rpc_endpoint(
             getmicroblocks_confirmed::RPCMicroblocksConfirmedRequestHandler::new(),
         );
@@ -131,6 +145,7 @@ impl StacksHttp {
         self.register_rpc_endpoint(gettenureinfo::RPCNakamotoTenureInfoRequestHandler::new());
         self.register_rpc_endpoint(gettenuretip::RPCNakamotoTenureTipRequestHandler::new());
         self.register_rpc_endpoint(get_tenures_fork_info::GetTenuresForkInfo::default());
+        self.register_rpc_endpoint(gettransaction::RPCGetTransactionRequestHandler::new());
         self.register_rpc_endpoint(
             gettransaction_unconfirmed::RPCGetTransactionUnconfirmedRequestHandler::new(),
         );
@@ -146,10 +161,17 @@ impl StacksHttp {
             self.auth_token.clone(),
         ));
         self.register_rpc_endpoint(postfeerate::RPCPostFeeRateRequestHandler::new());
+        self.register_rpc_endpoint(postmarfproof::RPCPostMarfProofRequestHandler::new());
         self.register_rpc_endpoint(postmempoolquery::RPCMempoolQueryRequestHandler::new());
         self.register_rpc_endpoint(postmicroblock::RPCPostMicroblockRequestHandler::new());
         self.register_rpc_endpoint(poststackerdbchunk::RPCPostStackerDBChunkRequestHandler::new());
         self.register_rpc_endpoint(posttransaction::RPCPostTransactionRequestHandler::new());
+        self.register_rpc_endpoint(
+            posttransaction_simulate::RPCPostTransactionSimulateRequestHandler::new(
+                self.enable_transaction_simulation,
+                self.simulate_cost_limit.clone(),
+            ),
+        );
     }
 }
 
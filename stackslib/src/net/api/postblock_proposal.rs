@@ -47,6 +47,7 @@ use crate::chainstate::burn::db::sortdb::{SortitionDB, SortitionHandleConn};
 use crate::chainstate::nakamoto::miner::NakamotoBlockBuilder;
 use crate::chainstate::nakamoto::{NakamotoBlock, NakamotoChainState, NAKAMOTO_BLOCK_VERSION};
 use crate::chainstate::stacks::db::blocks::MINIMUM_TX_FEE_RATE_PER_BYTE;
+use crate::chainstate::stacks::db::parallel::ParallelTxBatch;
 use crate::chainstate::stacks::db::{StacksBlockHeaderTypes, StacksChainState};
 use crate::chainstate::stacks::miner::{BlockBuilder, BlockLimitFunction, TransactionResult};
 use crate::chainstate::stacks::{
@@ -537,9 +538,18 @@ impl NakamotoBlockProposal {
             None,
         )?;
 
+        let tx_execution_threads = chainstate.tx_execution_threads;
         let mut miner_tenure_info =
             builder.load_tenure_info(chainstate, &burn_dbconn, tenure_cause)?;
         let mut tenure_tx = builder.tenure_begin(&burn_dbconn, &mut miner_tenure_info)?;
+        if tx_execution_threads > 1 {
+            builder.set_parallel_txs(ParallelTxBatch::execute(
+                &mut tenure_tx,
+                &self.block.txs,
+                tx_execution_threads,
+                ASTRules::PrecheckSize,
+            ));
+        }
 
         for (i, tx) in self.block.txs.iter().enumerate() {
             let tx_len = tx.tx_len();
@@ -31,11 +31,13 @@ use stacks_common::util::retry::BoundReader;
 use crate::burnchains::affirmation::AffirmationMap;
 use crate::burnchains::Txid;
 use crate::chainstate::burn::db::sortdb::SortitionDB;
+use crate::chainstate::nakamoto::NakamotoChainState;
 use crate::chainstate::stacks::db::blocks::MINIMUM_TX_FEE_RATE_PER_BYTE;
 use crate::chainstate::stacks::db::StacksChainState;
 use crate::chainstate::stacks::TransactionPayload;
 use crate::core::mempool::MemPoolDB;
 use crate::core::StacksEpoch;
+use crate::cost_estimates::fee_mempool::{ConfirmationTarget, MempoolFeeRateEstimator};
 use crate::cost_estimates::metrics::CostMetric;
 use crate::cost_estimates::{CostEstimator, FeeEstimator, FeeRateEstimate};
 use crate::net::http::{
@@ -49,11 +51,17 @@ use crate::net::httpcore::{
 use crate::net::p2p::PeerNetwork;
 use crate::net::{Error as NetError, StacksNodeState};
 
+/// Number of tenures for the `within_tenures` confirmation target if the request does not set it
+pub const DEFAULT_FEE_ESTIMATE_WITHIN_TENURES: u64 = 3;
+
 #[derive(Serialize, Deserialize)]
 pub struct FeeRateEstimateRequestBody {
     #[serde(default)]
     pub estimated_len: Option<u64>,
     pub transaction_payload: String,
+    /// Number of tenures for the `within_tenures` confirmation target
+    #[serde(default)]
+    pub within_tenures: Option<u64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -82,18 +90,30 @@ impl RPCFeeEstimate {
     }
 }
 
+/// Fee estimate for a transaction to be mined by a confirmation target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCTargetFeeEstimate {
+    pub target: String,
+    pub fee_rate: f64,
+    pub fee: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RPCFeeEstimateResponse {
     pub estimated_cost: ExecutionCost,
     pub estimated_cost_scalar: u64,
     pub estimations: Vec<RPCFeeEstimate>,
     pub cost_scalar_change_by_byte: f64,
+    /// Estimates which account for the transactions currently in the mempool
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub target_estimations: Vec<RPCTargetFeeEstimate>,
 }
 
 #[derive(Clone)]
 pub struct RPCPostFeeRateRequestHandler {
     pub estimated_len: Option<u64>,
     pub transaction_payload: Option<TransactionPayload>,
+    pub within_tenures: Option<u64>,
 }
 
 impl RPCPostFeeRateRequestHandler {
@@ -101,6 +121,7 @@ impl RPCPostFeeRateRequestHandler {
         Self {
             estimated_len: None,
             transaction_payload: None,
+            within_tenures: None,
         }
     }
 
@@ -137,8 +158,37 @@ impl RPCPostFeeRateRequestHandler {
             estimations,
             estimated_cost_scalar: scalar_cost,
             cost_scalar_change_by_byte: metric.change_per_byte(),
+            target_estimations: vec![],
         })
     }
+
+    /// Estimate a transaction fee for each confirmation target, given its scalar cost and length
+    /// estimation.  Targets without an estimate are omitted.
+    pub fn estimate_target_fees(
+        estimator: &MempoolFeeRateEstimator,
+        scalar_cost: u64,
+        estimated_len: u64,
+        within_tenures: u64,
+    ) -> Vec<RPCTargetFeeEstimate> {
+        let minimum_fee = estimated_len * MINIMUM_TX_FEE_RATE_PER_BYTE;
+        let targets = [
+            ConfirmationTarget::NextBlock,
+            ConfirmationTarget::NextTenure,
+            ConfirmationTarget::WithinTenures(within_tenures),
+        ];
+        targets
+            .into_iter()
+            .filter_map(|target| {
+                let fee_rate = estimator.get_rate_estimate(target).ok()?;
+                let fee = ((fee_rate * scalar_cost as f64) as u64).max(minimum_fee);
+                Some(RPCTargetFeeEstimate {
+                    target: target.to_string(),
+                    fee_rate,
+                    fee,
+                })
+            })
+            .collect()
+    }
 }
 
 /// Decode the HTTP request
@@ -194,9 +244,18 @@ impl HttpRequest for RPCPostFeeRateRequestHandler {
         let tx = TransactionPayload::consensus_deserialize(&mut payload_data.as_slice())?;
         let estimated_len =
             std::cmp::max(body.estimated_len.unwrap_or(0), payload_data.len() as u64);
+        let within_tenures = body
+            .within_tenures
+            .unwrap_or(DEFAULT_FEE_ESTIMATE_WITHIN_TENURES);
+        if within_tenures == 0 {
+            return Err(Error::DecodeError(
+                "Invalid `within_tenures`: must be at least 1".into(),
+            ));
+        }
 
         self.transaction_payload = Some(tx);
         self.estimated_len = Some(estimated_len);
+        self.within_tenures = Some(within_tenures);
         Ok(HttpRequestContents::new().query_string(query))
     }
 }
@@ -206,6 +265,7 @@ impl RPCRequestHandler for RPCPostFeeRateRequestHandler {
     fn restart(&mut self) {
         self.estimated_len = None;
         self.transaction_payload = None;
+        self.within_tenures = None;
     }
 
     /// Make the response
@@ -225,41 +285,79 @@ impl RPCRequestHandler for RPCPostFeeRateRequestHandler {
             .transaction_payload
             .take()
             .ok_or(NetError::SendError("`transaction_payload` not set".into()))?;
-
-        let data_resp =
-            node.with_node_state(|_network, sortdb, _chainstate, _mempool, rpc_args| {
-                let tip = self.get_canonical_burn_chain_tip(&preamble, sortdb)?;
-                let stacks_epoch = self.get_stacks_epoch(&preamble, sortdb, tip.block_height)?;
-
-                if let Some((cost_estimator, fee_estimator, metric)) = rpc_args.get_estimators_ref()
-                {
-                    let estimated_cost = cost_estimator
-                        .estimate_cost(&tx, &stacks_epoch.epoch_id)
-                        .map_err(|e| {
-                            StacksHttpResponse::new_error(
-                                &preamble,
-                                &HttpBadRequest::new_json(e.into_json()),
-                            )
-                        })?;
-
-                    Self::estimate_tx_fee_from_cost_and_length(
+        let within_tenures = self
+            .within_tenures
+            .take()
+            .ok_or(NetError::SendError("`within_tenures` not set".into()))?;
+
+        let data_resp = node.with_node_state(|network, sortdb, chainstate, mempool, rpc_args| {
+            let tip = self.get_canonical_burn_chain_tip(&preamble, sortdb)?;
+            let stacks_epoch = self.get_stacks_epoch(&preamble, sortdb, tip.block_height)?;
+            let block_limit = stacks_epoch.block_limit.clone();
+
+            if let Some((cost_estimator, fee_estimator, metric)) = rpc_args.get_estimators_ref() {
+                let estimated_cost = cost_estimator
+                    .estimate_cost(&tx, &stacks_epoch.epoch_id)
+                    .map_err(|e| {
+                        StacksHttpResponse::new_error(
+                            &preamble,
+                            &HttpBadRequest::new_json(e.into_json()),
+                        )
+                    })?;
+
+                let mut fee_resp = Self::estimate_tx_fee_from_cost_and_length(
+                    &preamble,
+                    fee_estimator,
+                    metric,
+                    estimated_cost,
+                    estimated_len,
+                    stacks_epoch,
+                )?;
+
+                // only Nakamoto tenures span multiple blocks, so there is no partly-spent
+                // tenure budget before then
+                let stacks_tip = StacksBlockId::new(
+                    &network.stacks_tip.consensus_hash,
+                    &network.stacks_tip.block_hash,
+                );
+                let tenure_cost =
+                    NakamotoChainState::get_total_tenure_cost_at(chainstate.db(), &stacks_tip)
+                        .unwrap_or_else(|e| {
+                            warn!("Failed to load tenure cost at the Stacks tip";
+                                      "stacks_tip" => %stacks_tip,
+                                      "err" => ?e);
+                            None
+                        });
+
+                let mempool_estimator = MempoolFeeRateEstimator::from_mempool(
+                    fee_estimator.get_rate_estimates().ok(),
+                    mempool.conn(),
+                    metric,
+                    &block_limit,
+                    tenure_cost.as_ref(),
+                    within_tenures,
+                )
+                .map_err(|e| {
+                    StacksHttpResponse::new_error(
                         &preamble,
-                        fee_estimator,
-                        metric,
-                        estimated_cost,
-                        estimated_len,
-                        stacks_epoch,
+                        &HttpServerError::new(format!("Failed to query mempool fee rates: {e:?}")),
                     )
-                } else {
-                    debug!("Fee and cost estimation not configured on this stacks node");
-                    Err(StacksHttpResponse::new_error(
-                        &preamble,
-                        &HttpBadRequest::new_json(json!(
-                            "Fee estimation not supported on this node"
-                        )),
-                    ))
-                }
-            });
+                })?;
+                fee_resp.target_estimations = Self::estimate_target_fees(
+                    &mempool_estimator,
+                    fee_resp.estimated_cost_scalar,
+                    estimated_len,
+                    within_tenures,
+                );
+                Ok(fee_resp)
+            } else {
+                debug!("Fee and cost estimation not configured on this stacks node");
+                Err(StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpBadRequest::new_json(json!("Fee estimation not supported on this node")),
+                ))
+            }
+        });
 
         let data_resp = match data_resp {
             Ok(data) => data,
@@ -0,0 +1,414 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::database::{ClarityDatabase, SqliteConnection, StoreType};
+use clarity::vm::types::{QualifiedContractIdentifier, BOUND_VALUE_SERIALIZATION_HEX};
+use clarity::vm::{ClarityName, Value};
+use regex::{Captures, Regex};
+use stacks_common::codec::StacksMessageCodec;
+use stacks_common::types::chainstate::{ConsensusHash, StacksBlockId, TrieHash};
+use stacks_common::types::net::PeerHost;
+use stacks_common::util::hash::to_hex;
+
+use crate::chainstate::nakamoto::NakamotoChainState;
+use crate::chainstate::stacks::db::StacksBlockHeaderTypes;
+use crate::chainstate::stacks::index::marf::MarfConnection;
+use crate::chainstate::stacks::index::Error as MARFError;
+use crate::net::http::{
+    parse_json, Error, HttpContentType, HttpNotFound, HttpRequest, HttpRequestContents,
+    HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
+    HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttpRequest,
+    StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+/// The chain state to prove
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MarfProofTarget {
+    /// A contract's data variable
+    DataVar {
+        /// fully-qualified contract identifier
+        contract: String,
+        name: String,
+    },
+    /// An entry in a contract's data map
+    MapEntry {
+        /// fully-qualified contract identifier
+        contract: String,
+        name: String,
+        /// 0x-prefixed, hex-encoded Clarity value of the map key
+        key: String,
+    },
+    /// Any MARF key, by its hash (as used by `/v2/clarity/marf/:key_hash`)
+    KeyHash { key_hash: String },
+}
+
+/// A block header whose trie root hash the proof passes through
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarfProofHeader {
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    pub block_height: u64,
+    /// root hash of the block's MARF trie
+    pub state_index_root: TrieHash,
+    /// whether `header` is a Nakamoto block header, or a Stacks 2.x block header
+    pub nakamoto: bool,
+    /// 0x-prefixed, consensus-serialized block header
+    pub header: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarfProofResponse {
+    /// the block against which the proof was made
+    pub tip: StacksBlockId,
+    /// the Stacks height of `tip`
+    pub tip_height: u64,
+    /// the MARF key, if the request named a data variable or map entry
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// the hash of the MARF key, which is the path the proof walks
+    pub key_hash: TrieHash,
+    /// whether the key has a value.  If so, `proof` is a `TrieMerkleProof` of its value;
+    /// otherwise, it is a `TrieNonInclusionProof`.
+    pub included: bool,
+    /// 0x-prefixed, hex-encoded value stored for the key
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// hex-encoded MARF leaf value for the key, i.e. the hash of `value`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub marf_value: Option<String>,
+    /// 0x-prefixed, consensus-serialized proof
+    pub proof: String,
+    /// headers of `tip` and of every ancestor block whose trie the proof passes through
+    pub headers: Vec<MarfProofHeader>,
+}
+
+/// The MARF path to prove, and the key it was derived from (if known)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarfProofKey {
+    pub key: Option<String>,
+    pub key_hash: TrieHash,
+}
+
+impl MarfProofKey {
+    pub fn from_target(target: MarfProofTarget) -> Result<Self, Error> {
+        let key = match target {
+            MarfProofTarget::DataVar { contract, name } => {
+                let contract = QualifiedContractIdentifier::parse(&contract).map_err(|_e| {
+                    Error::DecodeError("Failed to parse contract identifier".into())
+                })?;
+                let name = ClarityName::try_from(name)
+                    .map_err(|_e| Error::DecodeError("Failed to parse data var name".into()))?;
+                ClarityDatabase::make_key_for_trip(&contract, StoreType::Variable, &name)
+            }
+            MarfProofTarget::MapEntry {
+                contract,
+                name,
+                key,
+            } => {
+                let contract = QualifiedContractIdentifier::parse(&contract).map_err(|_e| {
+                    Error::DecodeError("Failed to parse contract identifier".into())
+                })?;
+                let name = ClarityName::try_from(name)
+                    .map_err(|_e| Error::DecodeError("Failed to parse map name".into()))?;
+                let key = Value::try_deserialize_hex_untyped(&key)
+                    .map_err(|_e| Error::DecodeError("Failed to deserialize map key".into()))?;
+                ClarityDatabase::make_key_for_data_map_entry(&contract, &name, &key)
+                    .map_err(|_e| Error::DecodeError("Failed to serialize map key".into()))?
+            }
+            MarfProofTarget::KeyHash { key_hash } => {
+                let key_hash = TrieHash::from_hex(&key_hash)
+                    .map_err(|_e| Error::DecodeError("Failed to parse key hash".into()))?;
+                return Ok(Self {
+                    key: None,
+                    key_hash,
+                });
+            }
+        };
+        Ok(Self {
+            key_hash: TrieHash::from_key(&key),
+            key: Some(key),
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct RPCPostMarfProofRequestHandler {
+    pub key: Option<MarfProofKey>,
+}
+
+impl RPCPostMarfProofRequestHandler {
+    pub fn new() -> Self {
+        Self { key: None }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCPostMarfProofRequestHandler {
+    fn verb(&self) -> &'static str {
+        "POST"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/proofs$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/proofs"
+    }
+
+    /// Try to decode this request.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < BOUND_VALUE_SERIALIZATION_HEX) {
+            return Err(Error::DecodeError(format!(
+                "Invalid Http request: invalid body length for MarfProof ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(Error::DecodeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let target: MarfProofTarget = serde_json::from_slice(body)
+            .map_err(|_e| Error::DecodeError("Failed to parse JSON body".into()))?;
+
+        self.key = Some(MarfProofKey::from_target(target)?);
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+/// Load the header of a block whose trie a proof passes through.
+/// `state_index_root` is the block's trie root hash, as read from the MARF.  This is the header's
+/// `state_index_root` for every block except the boot block, whose header does not commit to it.
+fn load_proof_header(
+    chainstate_db: &rusqlite::Connection,
+    block_id: &StacksBlockId,
+    state_index_root: TrieHash,
+) -> Result<MarfProofHeader, NetError> {
+    let header_info = NakamotoChainState::get_block_header(chainstate_db, block_id)?
+        .ok_or(NetError::NotFoundError)?;
+
+    let (nakamoto, header_bytes) = match &header_info.anchored_header {
+        StacksBlockHeaderTypes::Epoch2(header) => (false, header.serialize_to_vec()),
+        StacksBlockHeaderTypes::Nakamoto(header) => (true, header.serialize_to_vec()),
+    };
+
+    Ok(MarfProofHeader {
+        index_block_hash: block_id.clone(),
+        consensus_hash: header_info.consensus_hash,
+        block_height: header_info.stacks_block_height,
+        state_index_root,
+        nakamoto,
+        header: format!("0x{}", to_hex(&header_bytes)),
+    })
+}
+
+/// Handle the HTTP request
+impl RPCRequestHandler for RPCPostMarfProofRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.key = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let MarfProofKey { key, key_hash } = self
+            .key
+            .take()
+            .ok_or(NetError::SendError("Missing `key`".into()))?;
+
+        let proof_res =
+            node.with_node_state(|_network, _sortdb, chainstate, _mempool, _rpc_args| {
+                let Some(tip_header) = NakamotoChainState::get_block_header(chainstate.db(), &tip)?
+                else {
+                    return Err(NetError::NotFoundError);
+                };
+
+                let (included, value, marf_value, proof_bytes, trie_roots) = chainstate
+                    .with_clarity_marf(|marf| {
+                        let (included, value, marf_value, proof_bytes, backptr_blocks) = match marf
+                            .get_with_proof_from_hash(&tip, &key_hash)
+                        {
+                            Ok(Some((marf_value, proof))) => {
+                                let side_key = marf_value.to_hex();
+                                let value = SqliteConnection::get(marf.sqlite_conn(), &side_key)
+                                    .map_err(|e| NetError::ChainstateError(format!("{:?}", &e)))?
+                                    .ok_or_else(|| {
+                                        NetError::ChainstateError(format!(
+                                            "MARF value {} not found in side storage",
+                                            &side_key
+                                        ))
+                                    })?;
+                                (
+                                    true,
+                                    Some(format!("0x{}", value)),
+                                    Some(side_key),
+                                    proof.serialize_to_vec(),
+                                    proof.get_backptr_blocks(),
+                                )
+                            }
+                            Ok(None) | Err(MARFError::NotFoundError) => {
+                                let proof = marf
+                                    .get_non_inclusion_proof_from_hash(&tip, &key_hash)
+                                    .map_err(NetError::MARFError)?
+                                    .ok_or_else(|| {
+                                        NetError::ChainstateError(
+                                            "Key has a value, but no inclusion proof".into(),
+                                        )
+                                    })?;
+                                (
+                                    false,
+                                    None,
+                                    None,
+                                    proof.serialize_to_vec(),
+                                    proof.get_backptr_blocks(),
+                                )
+                            }
+                            Err(e) => {
+                                return Err(NetError::MARFError(e));
+                            }
+                        };
+
+                        // the verifier needs to map each visited trie's root hash to its block
+                        let mut trie_roots: Vec<(StacksBlockId, TrieHash)> = vec![];
+                        for block_id in [&tip].into_iter().chain(backptr_blocks.iter()) {
+                            if trie_roots.iter().any(|(visited, _)| visited == block_id) {
+                                continue;
+                            }
+                            let root_hash = marf
+                                .get_root_hash_at(block_id)
+                                .map_err(NetError::MARFError)?;
+                            trie_roots.push((block_id.clone(), root_hash));
+                        }
+                        Ok((included, value, marf_value, proof_bytes, trie_roots))
+                    })?;
+
+                let headers = trie_roots
+                    .into_iter()
+                    .map(|(block_id, root_hash)| {
+                        load_proof_header(chainstate.db(), &block_id, root_hash)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(MarfProofResponse {
+                    tip: tip,
+                    tip_height: tip_header.stacks_block_height,
+                    key: key.clone(),
+                    key_hash: key_hash,
+                    included,
+                    value,
+                    marf_value,
+                    proof: format!("0x{}", to_hex(&proof_bytes)),
+                    headers,
+                })
+            });
+
+        let proof_resp = match proof_res {
+            Ok(proof_resp) => proof_resp,
+            Err(NetError::NotFoundError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new("Chain tip not found".to_string()),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Err(e) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpServerError::new(format!("Failed to make MARF proof: {:?}", &e)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&proof_resp)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCPostMarfProofRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let proof_resp: MarfProofResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(proof_resp)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new request for a proof of a piece of chain state
+    pub fn new_postmarfproof(
+        host: PeerHost,
+        target: MarfProofTarget,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "POST".into(),
+            "/v3/proofs".to_string(),
+            HttpRequestContents::new().for_tip(tip_req).payload_json(
+                serde_json::to_value(target).expect("FATAL: failed to encode infallible data"),
+            ),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_marf_proof_response(self) -> Result<MarfProofResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let contents_json: serde_json::Value = contents.try_into()?;
+        let resp: MarfProofResponse = serde_json::from_value(contents_json)
+            .map_err(|_e| NetError::DeserializeError("Failed to load from JSON".to_string()))?;
+        Ok(resp)
+    }
+}
@@ -0,0 +1,314 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use clarity::vm::clarity::ClarityConnection;
+use clarity::vm::costs::ExecutionCost;
+use regex::{Captures, Regex};
+use stacks_common::codec::{Error as CodecError, StacksMessageCodec, MAX_PAYLOAD_LEN};
+use stacks_common::types::net::PeerHost;
+
+use crate::burnchains::Txid;
+use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::StacksTransaction;
+use crate::net::http::{
+    parse_json, Error, HttpContentType, HttpNotFound, HttpRequest, HttpRequestContents,
+    HttpRequestPreamble, HttpResponse, HttpResponseContents, HttpResponsePayload,
+    HttpResponsePreamble, HttpServerError,
+};
+use crate::net::httpcore::{
+    HttpPreambleExtensions, HttpRequestContentsExtensions, RPCRequestHandler, StacksHttpRequest,
+    StacksHttpResponse,
+};
+use crate::net::{Error as NetError, StacksNodeState, TipRequest};
+
+/// The outcome of a transaction which could be mined
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulatedTransactionReceipt {
+    /// fee paid by the transaction
+    pub fee: u64,
+    /// 0x-prefixed, hex-encoded Clarity value returned by the transaction
+    pub result: String,
+    /// whether or not the transaction was aborted by its post-conditions
+    pub post_condition_aborted: bool,
+    /// the runtime error raised by the transaction, if any
+    pub vm_error: Option<String>,
+    pub stx_burned: u128,
+    /// events emitted by the transaction, in the same format as the event observer interface
+    pub events: Vec<serde_json::Value>,
+    pub execution_cost: ExecutionCost,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransactionSimulationResponse {
+    pub txid: Txid,
+    /// whether or not the transaction's signatures are valid.
+    /// Unsigned transactions are simulated all the same.
+    pub signatures_valid: bool,
+    /// the transaction's receipt, if it could be mined
+    pub receipt: Option<SimulatedTransactionReceipt>,
+    /// why the transaction could not be mined, if it could not be
+    pub error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct RPCPostTransactionSimulateRequestHandler {
+    /// whether or not this node serves simulation requests
+    pub enabled: bool,
+    /// the execution budget of a simulated transaction
+    pub cost_limit: ExecutionCost,
+    pub tx: Option<StacksTransaction>,
+}
+impl RPCPostTransactionSimulateRequestHandler {
+    pub fn new(enabled: bool, cost_limit: ExecutionCost) -> Self {
+        Self {
+            enabled,
+            cost_limit,
+            tx: None,
+        }
+    }
+}
+
+/// Decode the HTTP request
+impl HttpRequest for RPCPostTransactionSimulateRequestHandler {
+    fn verb(&self) -> &'static str {
+        "POST"
+    }
+
+    fn path_regex(&self) -> Regex {
+        Regex::new(r#"^/v3/transactions/simulate$"#).unwrap()
+    }
+
+    fn metrics_identifier(&self) -> &str {
+        "/v3/transactions/simulate"
+    }
+
+    /// Try to decode this request.
+    /// The body must be a bare transaction, which need not be signed.
+    fn try_parse_request(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        mut body: &[u8],
+    ) -> Result<HttpRequestContents, Error> {
+        if preamble.get_content_length() == 0 {
+            return Err(Error::DecodeError(
+                "Invalid Http request: expected non-zero-length body for PostTransactionSimulate"
+                    .to_string(),
+            ));
+        }
+
+        if preamble.get_content_length() > MAX_PAYLOAD_LEN {
+            return Err(Error::DecodeError(
+                "Invalid Http request: PostTransactionSimulate body is too big".to_string(),
+            ));
+        }
+
+        if preamble.content_type != Some(HttpContentType::Bytes) {
+            return Err(Error::DecodeError(
+                "Wrong Content-Type for transaction; expected application/octet-stream".to_string(),
+            ));
+        }
+
+        let tx = StacksTransaction::consensus_deserialize(&mut body).map_err(|e| {
+            if let CodecError::DeserializeError(msg) = e {
+                Error::DecodeError(format!("Failed to deserialize posted transaction: {}", msg))
+            } else {
+                e.into()
+            }
+        })?;
+        self.tx = Some(tx);
+
+        Ok(HttpRequestContents::new().query_string(query))
+    }
+}
+
+impl RPCRequestHandler for RPCPostTransactionSimulateRequestHandler {
+    /// Reset internal state
+    fn restart(&mut self) {
+        self.tx = None;
+    }
+
+    /// Make the response
+    fn try_handle_request(
+        &mut self,
+        preamble: HttpRequestPreamble,
+        contents: HttpRequestContents,
+        node: &mut StacksNodeState,
+    ) -> Result<(HttpResponsePreamble, HttpResponseContents), NetError> {
+        let tx = self
+            .tx
+            .take()
+            .ok_or(NetError::SendError("`tx` not set".into()))?;
+
+        if !self.enabled {
+            return StacksHttpResponse::new_error(
+                &preamble,
+                &HttpNotFound::new("Transaction simulation is not enabled on this node".into()),
+            )
+            .try_into_contents()
+            .map_err(NetError::from);
+        }
+
+        let tip = match node.load_stacks_chain_tip(&preamble, &contents) {
+            Ok(tip) => tip,
+            Err(error_resp) => {
+                return error_resp.try_into_contents().map_err(NetError::from);
+            }
+        };
+
+        let simulation_res =
+            node.with_node_state(|network, sortdb, chainstate, _mempool, _rpc_args| {
+                let mainnet = chainstate.mainnet;
+                let chain_id = chainstate.chain_id;
+                let config = chainstate.config();
+                let ast_rules = network.ast_rules;
+
+                // evaluate the transaction on top of `tip` in a read-only view of the chain
+                // state, which discards everything the transaction writes
+                chainstate
+                    .maybe_read_only_clarity_tx(
+                        &sortdb.index_handle_at_block(chainstate, &tip)?,
+                        &tip,
+                        |clarity_tx| {
+                            let epoch = clarity_tx.get_epoch();
+                            clarity_tx.as_simulated_transaction(
+                                mainnet,
+                                chain_id,
+                                self.cost_limit.clone(),
+                                |transaction| {
+                                    StacksChainState::simulate_transaction(
+                                        &config,
+                                        transaction,
+                                        &tx,
+                                        epoch,
+                                        ast_rules,
+                                    )
+                                },
+                            )
+                        },
+                    )?
+                    .ok_or(NetError::NotFoundError)?
+                    .map_err(NetError::from)
+            });
+
+        let result = match simulation_res {
+            Ok(result) => result,
+            Err(NetError::NotFoundError) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpNotFound::new(format!("No such chain tip {}", &tip)),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+            Err(e) => {
+                return StacksHttpResponse::new_error(
+                    &preamble,
+                    &HttpServerError::new(format!(
+                        "Failed to simulate transaction {}: {:?}",
+                        &tx.txid(),
+                        &e
+                    )),
+                )
+                .try_into_contents()
+                .map_err(NetError::from);
+            }
+        };
+
+        let mut response = TransactionSimulationResponse {
+            txid: tx.txid(),
+            signatures_valid: tx.verify().is_ok(),
+            receipt: None,
+            error: None,
+        };
+        match result {
+            Ok((fee, receipt)) => {
+                let committed = !receipt.post_condition_aborted;
+                let events = receipt
+                    .events
+                    .iter()
+                    .enumerate()
+                    .map(|(event_index, event)| {
+                        event.json_serialize(event_index, &response.txid, committed)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| NetError::SerializeError(format!("{:?}", &e)))?;
+                let result_hex = receipt
+                    .result
+                    .serialize_to_hex()
+                    .map_err(|e| NetError::SerializeError(format!("{:?}", &e)))?;
+                response.receipt = Some(SimulatedTransactionReceipt {
+                    fee,
+                    result: format!("0x{}", result_hex),
+                    post_condition_aborted: receipt.post_condition_aborted,
+                    vm_error: receipt.vm_error,
+                    stx_burned: receipt.stx_burned,
+                    events,
+                    execution_cost: receipt.execution_cost,
+                });
+            }
+            Err(e) => {
+                response.error = Some(e.to_string());
+            }
+        }
+
+        let mut preamble = HttpResponsePreamble::ok_json(&preamble);
+        preamble.set_canonical_stacks_tip_height(Some(node.canonical_stacks_tip_height()));
+        let body = HttpResponseContents::try_from_json(&response)?;
+        Ok((preamble, body))
+    }
+}
+
+/// Decode the HTTP response
+impl HttpResponse for RPCPostTransactionSimulateRequestHandler {
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        let response: TransactionSimulationResponse = parse_json(preamble, body)?;
+        Ok(HttpResponsePayload::try_from_json(response)?)
+    }
+}
+
+impl StacksHttpRequest {
+    /// Make a new transaction simulation request
+    pub fn new_post_transaction_simulate(
+        host: PeerHost,
+        tx: StacksTransaction,
+        tip_req: TipRequest,
+    ) -> StacksHttpRequest {
+        StacksHttpRequest::new_for_peer(
+            host,
+            "POST".into(),
+            "/v3/transactions/simulate".to_string(),
+            HttpRequestContents::new()
+                .for_tip(tip_req)
+                .payload_stacks(&tx),
+        )
+        .expect("FATAL: failed to construct request from infallible data")
+    }
+}
+
+impl StacksHttpResponse {
+    pub fn decode_transaction_simulation(self) -> Result<TransactionSimulationResponse, NetError> {
+        let contents = self.get_http_payload_ok()?;
+        let response_json: serde_json::Value = contents.try_into()?;
+        let response: TransactionSimulationResponse = serde_json::from_value(response_json)
+            .map_err(|_e| Error::DecodeError("Failed to decode JSON".to_string()))?;
+        Ok(response)
+    }
+}
@@ -0,0 +1,379 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use clarity::vm::types::{PrincipalData, QualifiedContractIdentifier};
+use clarity::vm::Value;
+use stacks_common::types::chainstate::{ConsensusHash, StacksBlockId};
+
+use super::TestRPC;
+use crate::core::BLOCK_LIMIT_MAINNET_21;
+use crate::net::api::callreadonly::ReadOnlyCall;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{
+    HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
+};
+use crate::net::{ProtocolFamily, TipRequest};
+
+fn make_call(contract: &str, function: &str, arguments: Vec<Value>) -> ReadOnlyCall {
+    ReadOnlyCall {
+        contract_identifier: QualifiedContractIdentifier::parse(&format!(
+            "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.{}",
+            contract
+        ))
+        .unwrap(),
+        function: function.into(),
+        arguments,
+    }
+}
+
+fn sender() -> PrincipalData {
+    PrincipalData::parse("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap()
+}
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let calls = vec![
+        make_call("hello-world", "ro-confirmed", vec![]),
+        make_call(
+            "hello-world",
+            "ro-test",
+            vec![Value::UInt(1), Value::Int(-1)],
+        ),
+    ];
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        calls.clone(),
+        TipRequest::AtStacksHeight(1),
+    );
+    assert_eq!(
+        request.contents().tip_request(),
+        TipRequest::AtStacksHeight(1)
+    );
+
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler =
+        callreadonlybatch::RPCCallReadOnlyBatchRequestHandler::new(4096, BLOCK_LIMIT_MAINNET_21);
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    assert_eq!(handler.sender, Some(sender()));
+    assert_eq!(handler.sponsor, None);
+    assert_eq!(handler.calls, Some(calls));
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, contents) = parsed_request.destruct();
+
+    assert_eq!(&preamble, request.preamble());
+    assert_eq!(contents.tip_request(), TipRequest::AtStacksHeight(1));
+
+    // restart clears the handler state
+    handler.restart();
+    assert!(handler.sender.is_none());
+    assert!(handler.sponsor.is_none());
+    assert!(handler.calls.is_none());
+
+    // empty batches are rejected
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        vec![],
+        TipRequest::UseLatestAnchoredTip,
+    );
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    assert!(http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .is_err());
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let rpc_test = TestRPC::setup(function_name!());
+    let canonical_tip = rpc_test.canonical_tip.clone();
+    let consensus_hash = rpc_test.consensus_hash.clone();
+    let mut requests = vec![];
+
+    // several calls against the canonical tip
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        vec![
+            make_call("hello-world", "ro-confirmed", vec![]),
+            make_call("does-not-exist", "ro-confirmed", vec![]),
+        ],
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // an undefined function, in a batch of its own so that it has the whole budget
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        vec![make_call("hello-world", "does-not-exist", vec![])],
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // before the contract was deployed
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        vec![make_call("hello-world", "ro-confirmed", vec![])],
+        TipRequest::AtStacksHeight(0),
+    );
+    requests.push(request);
+
+    // the chain is not this tall
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        vec![make_call("hello-world", "ro-confirmed", vec![])],
+        TipRequest::AtStacksHeight(100),
+    );
+    requests.push(request);
+
+    // the burnchain is not this tall, so this is the canonical tip
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        vec![make_call("hello-world", "ro-confirmed", vec![])],
+        TipRequest::AtBurnHeight(u64::from(u32::MAX)),
+    );
+    requests.push(request);
+
+    // non-existent tip
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        vec![make_call("hello-world", "ro-confirmed", vec![])],
+        TipRequest::SpecificTip(StacksBlockId([0x11; 32])),
+    );
+    requests.push(request);
+
+    // the tip's tenure
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        vec![make_call("hello-world", "ro-confirmed", vec![])],
+        TipRequest::AtTenure(consensus_hash),
+    );
+    requests.push(request);
+
+    // non-existent tenure
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        vec![make_call("hello-world", "ro-confirmed", vec![])],
+        TipRequest::AtTenure(ConsensusHash([0x11; 20])),
+    );
+    requests.push(request);
+
+    // the calls share one budget, which runs out partway through the batch
+    let request = StacksHttpRequest::new_callreadonlybatch(
+        addr.into(),
+        sender(),
+        None,
+        vec![make_call("hello-world", "ro-confirmed", vec![]); 4],
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // malformed tip selectors
+    for query in ["at_height=bad", "at_burn_height=-1", "at_tenure=bad"] {
+        let mut request = StacksHttpRequest::new_callreadonlybatch(
+            addr.into(),
+            sender(),
+            None,
+            vec![make_call("hello-world", "ro-confirmed", vec![])],
+            TipRequest::UseLatestAnchoredTip,
+        );
+        request.preamble_mut().path_and_query_str = format!("/v3/contracts/call-read?{query}");
+        requests.push(request);
+    }
+
+    let mut responses = rpc_test.run(requests);
+
+    // canonical tip
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_call_readonly_batch_response().unwrap();
+    assert_eq!(resp.tip, canonical_tip);
+    assert_eq!(resp.tip_height, 1);
+    assert_eq!(resp.results.len(), 2);
+
+    // u1
+    assert!(resp.results[0].okay);
+    assert_eq!(
+        resp.results[0].result.as_ref().unwrap(),
+        "0x0100000000000000000000000000000001"
+    );
+    assert!(!resp.results[1].okay);
+    assert!(resp.results[1]
+        .cause
+        .as_ref()
+        .unwrap()
+        .contains("NoSuchContract"));
+
+    // undefined function
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_call_readonly_batch_response().unwrap();
+    assert_eq!(resp.results.len(), 1);
+    assert!(!resp.results[0].okay);
+    assert!(resp.results[0]
+        .cause
+        .as_ref()
+        .unwrap()
+        .contains("UndefinedFunction"));
+
+    // at height 0
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_call_readonly_batch_response().unwrap();
+    assert_ne!(resp.tip, canonical_tip);
+    assert_eq!(resp.tip_height, 0);
+    assert!(!resp.results[0].okay);
+    assert!(resp.results[0]
+        .cause
+        .as_ref()
+        .unwrap()
+        .contains("NoSuchContract"));
+
+    // at height 100
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let (preamble, payload) = response.destruct();
+    assert_eq!(preamble.status_code, 404);
+
+    // at the highest burn height
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_call_readonly_batch_response().unwrap();
+    assert_eq!(resp.tip, canonical_tip);
+    assert!(resp.results[0].okay);
+
+    // non-existent tip
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let (preamble, payload) = response.destruct();
+    assert_eq!(preamble.status_code, 404);
+
+    // the tip's tenure
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_call_readonly_batch_response().unwrap();
+    assert_eq!(resp.tip, canonical_tip);
+    assert!(resp.results[0].okay);
+
+    // non-existent tenure
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let (preamble, payload) = response.destruct();
+    assert_eq!(preamble.status_code, 404);
+
+    // shared budget
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_call_readonly_batch_response().unwrap();
+    assert_eq!(resp.results.len(), 4);
+    assert!(resp.results[0].okay);
+    assert!(!resp.results[3].okay);
+    assert!(resp.results[3]
+        .cause
+        .as_ref()
+        .unwrap()
+        .contains("CostBalanceExceeded"));
+
+    // malformed tip selectors
+    for _ in 0..3 {
+        let response = responses.remove(0);
+        debug!(
+            "Response:\n{}\n",
+            std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+        );
+
+        let (preamble, payload) = response.destruct();
+        assert_eq!(preamble.status_code, 400);
+    }
+}
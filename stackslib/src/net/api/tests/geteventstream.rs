@@ -0,0 +1,213 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashSet;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use stacks_common::types::chainstate::{BurnchainHeaderHash, ConsensusHash};
+
+use super::TestRPC;
+use crate::net::api::geteventstream::{
+    RPCEventStreamGenerator, RPCEventStreamHub, RPCStreamEvent, RPCStreamEventKind,
+};
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::http::HttpChunkGenerator;
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::ProtocolFamily;
+
+fn make_burn_block_event(height: u64) -> RPCStreamEvent {
+    RPCStreamEvent::new_burn_block(
+        &BurnchainHeaderHash([height as u8; 32]),
+        height,
+        &ConsensusHash([height as u8; 20]),
+    )
+}
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    // no filter means all events
+    let request = StacksHttpRequest::new_geteventstream(addr.into(), &[]);
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = geteventstream::RPCGetEventStreamRequestHandler::new();
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    assert_eq!(
+        handler.kinds,
+        Some(RPCStreamEventKind::ALL.into_iter().collect())
+    );
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, _contents) = parsed_request.destruct();
+    assert_eq!(&preamble, request.preamble());
+
+    handler.restart();
+    assert!(handler.kinds.is_none());
+
+    // filtered
+    let request = StacksHttpRequest::new_geteventstream(
+        addr.into(),
+        &[RPCStreamEventKind::Block, RPCStreamEventKind::MempoolTx],
+    );
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap();
+
+    assert_eq!(
+        handler.kinds,
+        Some(HashSet::from([
+            RPCStreamEventKind::Block,
+            RPCStreamEventKind::MempoolTx
+        ]))
+    );
+
+    // unknown event kinds are rejected
+    let bytes =
+        b"GET /v3/events/stream?events=block,nope HTTP/1.1\r\nHost: 127.0.0.1:33333\r\n\r\n";
+    let (parsed_preamble, offset) = http.read_preamble(bytes).unwrap();
+    assert!(http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .is_err());
+}
+
+#[test]
+fn test_event_stream_hub() {
+    let hub = RPCEventStreamHub::new(2, 2);
+
+    let all_rx = hub
+        .subscribe(RPCStreamEventKind::ALL.into_iter().collect())
+        .unwrap();
+    let blocks_rx = hub
+        .subscribe(HashSet::from([RPCStreamEventKind::Block]))
+        .unwrap();
+
+    // full
+    assert!(hub
+        .subscribe(RPCStreamEventKind::ALL.into_iter().collect())
+        .is_none());
+    assert_eq!(hub.num_subscribers(), 2);
+
+    // only the unfiltered subscriber gets burn block events
+    hub.publish(make_burn_block_event(1));
+    let sse = all_rx.try_recv().unwrap();
+    assert!(sse.starts_with("id: 0\nevent: burn_block\ndata: {"));
+    assert!(sse.ends_with("}\n\n"));
+    assert!(sse.contains("\"burn_block_height\":1"));
+    assert!(blocks_rx.try_recv().is_err());
+
+    // a subscriber which falls behind is dropped, but can drain what it already received
+    hub.publish(make_burn_block_event(2));
+    hub.publish(make_burn_block_event(3));
+    assert_eq!(hub.num_subscribers(), 2);
+    hub.publish(make_burn_block_event(4));
+    assert_eq!(hub.num_subscribers(), 1);
+
+    assert!(all_rx.try_recv().unwrap().starts_with("id: 1\n"));
+    assert!(all_rx.try_recv().unwrap().starts_with("id: 2\n"));
+    assert!(all_rx.try_recv().is_err());
+
+    // a subscriber which hangs up is dropped
+    drop(blocks_rx);
+    hub.publish(RPCStreamEvent {
+        kind: RPCStreamEventKind::Block,
+        payload: serde_json::json!({}),
+    });
+    assert_eq!(hub.num_subscribers(), 0);
+
+    // room for new subscribers
+    assert!(hub
+        .subscribe(RPCStreamEventKind::ALL.into_iter().collect())
+        .is_some());
+}
+
+#[test]
+fn test_event_stream_generator() {
+    let hub = RPCEventStreamHub::new(1, 16);
+    let rx = hub
+        .subscribe(RPCStreamEventKind::ALL.into_iter().collect())
+        .unwrap();
+    let mut stream = RPCEventStreamGenerator::new(rx);
+    assert!(stream.is_open());
+
+    // announced on open
+    let chunk = stream.generate_next_chunk().unwrap();
+    assert_eq!(chunk, b": stream opened\n\n".to_vec());
+
+    // nothing to send, and too soon for a heartbeat
+    let chunk = stream.generate_next_chunk().unwrap();
+    assert!(chunk.is_empty());
+    assert!(stream.is_open());
+
+    // pending events are sent together
+    hub.publish(make_burn_block_event(1));
+    hub.publish(make_burn_block_event(2));
+    let chunk = String::from_utf8(stream.generate_next_chunk().unwrap()).unwrap();
+    let events: Vec<_> = chunk.split_terminator("\n\n").collect();
+    assert_eq!(events.len(), 2);
+    assert!(events[0].starts_with("id: 0\nevent: burn_block\n"));
+    assert!(events[1].starts_with("id: 1\nevent: burn_block\n"));
+
+    // the stream ends once the hub lets go of the subscriber
+    drop(hub);
+    let chunk = stream.generate_next_chunk().unwrap();
+    assert!(chunk.is_empty());
+    assert!(!stream.is_open());
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let rpc_test = TestRPC::setup(function_name!());
+    let mut requests = vec![];
+
+    // event streaming is not enabled on the test peer
+    let request = StacksHttpRequest::new_geteventstream(addr.into(), &[]);
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let (preamble, _payload) = response.destruct();
+    assert_eq!(preamble.status_code, 503);
+}
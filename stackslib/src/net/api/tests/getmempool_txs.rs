@@ -0,0 +1,185 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use clarity::vm::types::QualifiedContractIdentifier;
+use stacks_common::types::chainstate::{StacksAddress, StacksPublicKey};
+use stacks_common::util::hash::Hash160;
+
+use super::TestRPC;
+use crate::core::mempool::MemPoolTxFilter;
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::ProtocolFamily;
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let filter = MemPoolTxFilter {
+        origin: Some(StacksAddress::new(26, Hash160([0x11; 20])).unwrap()),
+        sponsor: None,
+        contract: Some(
+            QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.pox-4").unwrap(),
+        ),
+        min_nonce: Some(2),
+        max_nonce: Some(5),
+    };
+    let request = StacksHttpRequest::new_get_mempool_transactions(addr.into(), &filter, Some(10));
+    let bytes = request.try_serialize().unwrap();
+
+    debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = getmempool_txs::RPCGetMempoolTransactionsRequestHandler::new();
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    assert_eq!(handler.filter, Some(filter));
+    assert_eq!(handler.limit, Some(10));
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, _contents) = parsed_request.destruct();
+    assert_eq!(&preamble, request.preamble());
+
+    handler.restart();
+    assert!(handler.filter.is_none());
+    assert!(handler.limit.is_none());
+
+    // inverted nonce range
+    let filter = MemPoolTxFilter {
+        min_nonce: Some(5),
+        max_nonce: Some(2),
+        ..MemPoolTxFilter::default()
+    };
+    let request = StacksHttpRequest::new_get_mempool_transactions(addr.into(), &filter, None);
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap_err();
+
+    // the contract alone would scan the whole mempool
+    let filter = MemPoolTxFilter {
+        contract: Some(
+            QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.pox-4").unwrap(),
+        ),
+        ..MemPoolTxFilter::default()
+    };
+    let request = StacksHttpRequest::new_get_mempool_transactions(addr.into(), &filter, None);
+    let bytes = request.try_serialize().unwrap();
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    http.handle_try_parse_request(
+        &mut handler,
+        &parsed_preamble.expect_request(),
+        &bytes[offset..],
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let rpc_test = TestRPC::setup(function_name!());
+    // all of the test mempool transactions come from this account
+    let origin = StacksAddress::p2pkh(false, &StacksPublicKey::from_private(&rpc_test.privk2));
+    let num_mempool_txs = rpc_test.mempool_txids.len() as u64;
+
+    let mut requests = vec![];
+
+    let by_origin = MemPoolTxFilter {
+        origin: Some(origin.clone()),
+        ..MemPoolTxFilter::default()
+    };
+    requests.push(StacksHttpRequest::new_get_mempool_transactions(
+        addr.into(),
+        &by_origin,
+        None,
+    ));
+
+    // at most two, with nonces from 5
+    let range = MemPoolTxFilter {
+        origin: Some(origin.clone()),
+        min_nonce: Some(5),
+        ..MemPoolTxFilter::default()
+    };
+    requests.push(StacksHttpRequest::new_get_mempool_transactions(
+        addr.into(),
+        &range,
+        Some(2),
+    ));
+
+    // these are all STX transfers
+    let by_contract = MemPoolTxFilter {
+        origin: Some(origin),
+        contract: Some(
+            QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.pox-4").unwrap(),
+        ),
+        ..MemPoolTxFilter::default()
+    };
+    requests.push(StacksHttpRequest::new_get_mempool_transactions(
+        addr.into(),
+        &by_contract,
+        None,
+    ));
+
+    requests.push(StacksHttpRequest::new_get_mempool_stats(addr.into()));
+
+    let mut responses = rpc_test.run(requests);
+
+    let resp = responses.remove(0).decode_mempool_transactions().unwrap();
+    let account_nonce = resp.account_nonce.unwrap();
+    assert_eq!(
+        resp.transactions.len() as u64,
+        num_mempool_txs.saturating_sub(account_nonce)
+    );
+    for (i, tx) in resp.transactions.iter().enumerate() {
+        assert_eq!(tx.origin_address, origin.to_string());
+        assert_eq!(tx.origin_nonce, account_nonce + i as u64);
+    }
+    assert!(resp.missing_nonces.is_empty());
+
+    let resp = responses.remove(0).decode_mempool_transactions().unwrap();
+    let nonces: Vec<_> = resp.transactions.iter().map(|tx| tx.origin_nonce).collect();
+    let first = account_nonce.max(5);
+    assert_eq!(nonces, vec![first, first + 1]);
+
+    let resp = responses.remove(0).decode_mempool_transactions().unwrap();
+    assert_eq!(resp.account_nonce, Some(account_nonce));
+    assert!(resp.transactions.is_empty());
+    assert!(resp.missing_nonces.is_empty());
+
+    let stats = responses.remove(0).decode_mempool_stats().unwrap();
+    assert_eq!(stats.tx_count, num_mempool_txs);
+    assert_eq!(stats.no_fee_rate_count, num_mempool_txs);
+    assert!(stats.total_bytes > 0);
+    assert!(stats
+        .fee_rate_histogram
+        .iter()
+        .all(|bucket| bucket.tx_count == 0));
+}
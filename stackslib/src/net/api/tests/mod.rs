@@ -55,6 +55,7 @@ use crate::net::{
 };
 
 mod callreadonly;
+mod callreadonlybatch;
 mod get_tenures_fork_info;
 mod getaccount;
 mod getattachment;
@@ -68,10 +69,12 @@ mod getconstantval;
 mod getcontractabi;
 mod getcontractsrc;
 mod getdatavar;
+mod geteventstream;
 mod getheaders;
 mod getinfo;
 mod getistraitimplemented;
 mod getmapentry;
+mod getmempool_txs;
 mod getmicroblocks_confirmed;
 mod getmicroblocks_indexed;
 mod getmicroblocks_unconfirmed;
@@ -85,16 +88,19 @@ mod getstxtransfercost;
 mod gettenure;
 mod gettenureinfo;
 mod gettenuretip;
+mod gettransaction;
 mod gettransaction_unconfirmed;
 mod liststackerdbreplicas;
 mod postblock;
 mod postblock_proposal;
 mod postblock_v3;
 mod postfeerate;
+mod postmarfproof;
 mod postmempoolquery;
 mod postmicroblock;
 mod poststackerdbchunk;
 mod posttransaction;
+mod posttransaction_simulate;
 
 const TEST_CONTRACT: &str = "
     (define-trait test-trait
@@ -294,6 +300,7 @@ impl<'a> TestRPC<'a> {
         };
         peer_1_config.connection_opts.maximum_call_argument_size = 4096;
         peer_1_config.connection_opts.auth_token = Some("password".to_string());
+        peer_1_config.connection_opts.enable_transaction_simulation = true;
 
         peer_2_config.connection_opts.read_only_call_limit = ExecutionCost {
             write_length: 0,
@@ -304,6 +311,7 @@ impl<'a> TestRPC<'a> {
         };
         peer_2_config.connection_opts.maximum_call_argument_size = 4096;
         peer_2_config.connection_opts.auth_token = Some("password".to_string());
+        peer_2_config.connection_opts.enable_transaction_simulation = true;
 
         // stacker DBs get initialized thru reconfiguration when the above block gets processed
         peer_1_config.add_stacker_db(
@@ -339,6 +347,9 @@ impl<'a> TestRPC<'a> {
         peer_1.rpc_handler_args = rpc_handler_args_opt_1;
         peer_2.rpc_handler_args = rpc_handler_args_opt_2;
 
+        peer_1.coord.chain_state_db.txindex = true;
+        peer_2.coord.chain_state_db.txindex = true;
+
         // mine one block with a contract in it
         // first the coinbase
         // make a coinbase for this miner
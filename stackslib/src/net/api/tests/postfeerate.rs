@@ -55,6 +55,7 @@ fn test_try_parse_request() {
         postfeerate::FeeRateEstimateRequestBody {
             estimated_len: Some(123),
             transaction_payload: to_hex(&tx_payload.serialize_to_vec()),
+            within_tenures: None,
         },
     );
     let bytes = request.try_serialize().unwrap();
@@ -73,6 +74,10 @@ fn test_try_parse_request() {
 
     assert_eq!(handler.estimated_len, Some(123));
     assert_eq!(handler.transaction_payload, Some(tx_payload));
+    assert_eq!(
+        handler.within_tenures,
+        Some(postfeerate::DEFAULT_FEE_ESTIMATE_WITHIN_TENURES)
+    );
 
     // parsed request consumes headers that would not be in a constructed reqeuest
     parsed_request.clear_headers();
@@ -83,6 +88,7 @@ fn test_try_parse_request() {
     handler.restart();
     assert!(handler.estimated_len.is_none());
     assert!(handler.transaction_payload.is_none());
+    assert!(handler.within_tenures.is_none());
 }
 
 #[test]
@@ -102,6 +108,7 @@ fn test_try_make_response() {
         postfeerate::FeeRateEstimateRequestBody {
             estimated_len: Some(123),
             transaction_payload: to_hex(&tx_payload.serialize_to_vec()),
+            within_tenures: None,
         },
     );
     requests.push(request);
@@ -129,6 +136,7 @@ fn test_try_make_response() {
         postfeerate::FeeRateEstimateRequestBody {
             estimated_len: Some(123),
             transaction_payload: to_hex(&tx_payload.serialize_to_vec()),
+            within_tenures: None,
         },
     );
     requests.push(request);
@@ -166,6 +174,7 @@ fn test_try_make_response() {
         postfeerate::FeeRateEstimateRequestBody {
             estimated_len: Some(123),
             transaction_payload: to_hex(&tx_payload.serialize_to_vec()),
+            within_tenures: Some(2),
         },
     );
     requests.push(request);
@@ -189,4 +198,16 @@ fn test_try_make_response() {
     // get back a JSON object and a 200
     assert_eq!(preamble.status_code, 200);
     debug!("Response JSON success: {}", &body_json);
+
+    // there's one estimate for each confirmation target
+    let fee_resp: postfeerate::RPCFeeEstimateResponse = serde_json::from_value(body_json).unwrap();
+    let targets: Vec<_> = fee_resp
+        .target_estimations
+        .iter()
+        .map(|estimate| estimate.target.as_str())
+        .collect();
+    assert_eq!(
+        targets,
+        vec!["next_block", "next_tenure", "within_2_tenures"]
+    );
 }
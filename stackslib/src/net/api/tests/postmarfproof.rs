@@ -0,0 +1,365 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use clarity::vm::database::{ClarityDatabase, StoreType};
+use clarity::vm::types::QualifiedContractIdentifier;
+use clarity::vm::Value;
+use libmarfproof::{InclusionProof, NonInclusionProof};
+use stacks_common::codec::StacksMessageCodec;
+use stacks_common::types::chainstate::{StacksBlockId, TrieHash};
+use stacks_common::util::hash::hex_bytes;
+
+use super::TestRPC;
+use crate::chainstate::stacks::index::MARFValue;
+use crate::chainstate::stacks::StacksBlockHeader;
+use crate::net::api::postmarfproof::{MarfProofKey, MarfProofResponse, MarfProofTarget};
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{
+    HttpRequestContentsExtensions, RPCRequestHandler, StacksHttp, StacksHttpRequest,
+};
+use crate::net::{ProtocolFamily, TipRequest};
+
+const TEST_CONTRACT_ID: &str = "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world";
+
+fn data_var(name: &str) -> MarfProofTarget {
+    MarfProofTarget::DataVar {
+        contract: TEST_CONTRACT_ID.into(),
+        name: name.into(),
+    }
+}
+
+fn map_entry(name: &str, key: Value) -> MarfProofTarget {
+    MarfProofTarget::MapEntry {
+        contract: TEST_CONTRACT_ID.into(),
+        name: name.into(),
+        key: key.serialize_to_hex().unwrap(),
+    }
+}
+
+/// Check the returned headers, and verify the proof against them the way a light client would
+fn verify_response(resp: &MarfProofResponse) {
+    let tip_header = &resp.headers[0];
+    assert_eq!(tip_header.index_block_hash, resp.tip);
+
+    for header in resp.headers.iter() {
+        assert!(!header.nakamoto);
+        let block_header = StacksBlockHeader::consensus_deserialize(
+            &mut &hex_bytes(&header.header[2..]).unwrap()[..],
+        )
+        .unwrap();
+        if header.block_height == 0 {
+            // the boot block's header does not commit to its trie
+            continue;
+        }
+
+        // the trie root hashes are committed to by their block headers
+        assert_eq!(
+            StacksBlockId::new(&header.consensus_hash, &block_header.block_hash()),
+            header.index_block_hash
+        );
+        assert_eq!(block_header.state_index_root, header.state_index_root);
+    }
+
+    let root_to_block = |root: &[u8; 32]| {
+        resp.headers
+            .iter()
+            .find(|header| header.state_index_root.0 == *root)
+            .map(|header| header.index_block_hash.0)
+    };
+    let proof_bytes = hex_bytes(&resp.proof[2..]).unwrap();
+    let root_hash = tip_header.state_index_root.0;
+
+    if resp.included {
+        let value = resp.value.as_ref().unwrap();
+        let marf_value = MARFValue::from_value(&value[2..]);
+        assert_eq!(resp.marf_value.as_ref().unwrap(), &marf_value.to_hex());
+
+        let proof = InclusionProof::from_bytes(&proof_bytes).unwrap();
+        proof
+            .verify(&resp.key_hash.0, &marf_value.0, &root_hash, root_to_block)
+            .unwrap();
+        assert!(proof
+            .verify(&resp.key_hash.0, &[0u8; 40], &root_hash, root_to_block)
+            .is_err());
+    } else {
+        assert!(resp.value.is_none());
+        assert!(resp.marf_value.is_none());
+
+        let proof = NonInclusionProof::from_bytes(&proof_bytes).unwrap();
+        proof
+            .verify(&resp.key_hash.0, &root_hash, root_to_block)
+            .unwrap();
+        assert!(proof
+            .verify(&resp.key_hash.0, &[0x11; 32], root_to_block)
+            .is_err());
+    }
+}
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let contract_id = QualifiedContractIdentifier::parse(TEST_CONTRACT_ID).unwrap();
+    let var_key = ClarityDatabase::make_key_for_trip(&contract_id, StoreType::Variable, "bar");
+    let map_key =
+        ClarityDatabase::make_key_for_data_map_entry(&contract_id, "test-map", &Value::UInt(1))
+            .unwrap();
+    let key_hash = TrieHash([0x33; 32]);
+
+    let targets = vec![
+        (
+            data_var("bar"),
+            MarfProofKey {
+                key_hash: TrieHash::from_key(&var_key),
+                key: Some(var_key),
+            },
+        ),
+        (
+            map_entry("test-map", Value::UInt(1)),
+            MarfProofKey {
+                key_hash: TrieHash::from_key(&map_key),
+                key: Some(map_key),
+            },
+        ),
+        (
+            MarfProofTarget::KeyHash {
+                key_hash: key_hash.to_hex(),
+            },
+            MarfProofKey {
+                key: None,
+                key_hash,
+            },
+        ),
+    ];
+
+    for (target, expected_key) in targets.into_iter() {
+        let request = StacksHttpRequest::new_postmarfproof(
+            addr.into(),
+            target,
+            TipRequest::SpecificTip(StacksBlockId([0x22; 32])),
+        );
+        assert_eq!(
+            request.contents().tip_request(),
+            TipRequest::SpecificTip(StacksBlockId([0x22; 32]))
+        );
+        let bytes = request.try_serialize().unwrap();
+
+        debug!("Request:\n{}\n", std::str::from_utf8(&bytes).unwrap());
+
+        let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let mut handler = postmarfproof::RPCPostMarfProofRequestHandler::new();
+        let mut parsed_request = http
+            .handle_try_parse_request(
+                &mut handler,
+                &parsed_preamble.expect_request(),
+                &bytes[offset..],
+            )
+            .unwrap();
+
+        assert_eq!(handler.key, Some(expected_key));
+
+        // parsed request consumes headers that would not be in a constructed reqeuest
+        parsed_request.clear_headers();
+        let (preamble, contents) = parsed_request.destruct();
+
+        assert_eq!(&preamble, request.preamble());
+
+        handler.restart();
+        assert!(handler.key.is_none());
+    }
+
+    // malformed targets are rejected
+    let bad_targets = vec![
+        MarfProofTarget::KeyHash {
+            key_hash: "not a hash".into(),
+        },
+        MarfProofTarget::DataVar {
+            contract: "not a contract".into(),
+            name: "bar".into(),
+        },
+        MarfProofTarget::MapEntry {
+            contract: TEST_CONTRACT_ID.into(),
+            name: "test-map".into(),
+            key: "not a value".into(),
+        },
+    ];
+    for target in bad_targets.into_iter() {
+        let request = StacksHttpRequest::new_postmarfproof(
+            addr.into(),
+            target,
+            TipRequest::UseLatestAnchoredTip,
+        );
+        let bytes = request.try_serialize().unwrap();
+        let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let mut handler = postmarfproof::RPCPostMarfProofRequestHandler::new();
+        assert!(http
+            .handle_try_parse_request(
+                &mut handler,
+                &parsed_preamble.expect_request(),
+                &bytes[offset..],
+            )
+            .is_err());
+    }
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let rpc_test = TestRPC::setup(function_name!());
+    let canonical_tip = rpc_test.canonical_tip.clone();
+    let mut requests = vec![];
+
+    // data var with a value
+    let request = StacksHttpRequest::new_postmarfproof(
+        addr.into(),
+        data_var("bar"),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // map entry with a value
+    let request = StacksHttpRequest::new_postmarfproof(
+        addr.into(),
+        map_entry("test-map", Value::UInt(1)),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // map entry without a value
+    let request = StacksHttpRequest::new_postmarfproof(
+        addr.into(),
+        map_entry("test-map", Value::UInt(999)),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // data var of a contract that does not exist
+    let request = StacksHttpRequest::new_postmarfproof(
+        addr.into(),
+        MarfProofTarget::DataVar {
+            contract: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.does-not-exist".into(),
+            name: "bar".into(),
+        },
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // data var before its contract was deployed
+    let request = StacksHttpRequest::new_postmarfproof(
+        addr.into(),
+        data_var("bar"),
+        TipRequest::AtStacksHeight(0),
+    );
+    requests.push(request);
+
+    // non-existent tip
+    let request = StacksHttpRequest::new_postmarfproof(
+        addr.into(),
+        data_var("bar"),
+        TipRequest::SpecificTip(StacksBlockId([0x11; 32])),
+    );
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    // data var with a value
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_marf_proof_response().unwrap();
+    assert_eq!(resp.tip, canonical_tip);
+    assert_eq!(resp.tip_height, 1);
+    assert!(resp.included);
+    assert_eq!(
+        resp.value,
+        Some(format!("0x{}", Value::Int(0).serialize_to_hex().unwrap()))
+    );
+    verify_response(&resp);
+
+    // map entry with a value
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_marf_proof_response().unwrap();
+    assert!(resp.included);
+    assert_eq!(
+        resp.value,
+        Some(format!(
+            "0x{}",
+            Value::some(Value::UInt(2))
+                .unwrap()
+                .serialize_to_hex()
+                .unwrap()
+        ))
+    );
+    verify_response(&resp);
+
+    // map entry without a value
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_marf_proof_response().unwrap();
+    assert_eq!(resp.tip, canonical_tip);
+    assert!(!resp.included);
+    verify_response(&resp);
+
+    // data var of a contract that does not exist
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_marf_proof_response().unwrap();
+    assert!(!resp.included);
+    verify_response(&resp);
+
+    // data var before its contract was deployed
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let resp = response.decode_marf_proof_response().unwrap();
+    assert_ne!(resp.tip, canonical_tip);
+    assert_eq!(resp.tip_height, 0);
+    assert!(!resp.included);
+    verify_response(&resp);
+
+    // non-existent tip
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+
+    let (preamble, payload) = response.destruct();
+    assert_eq!(preamble.status_code, 404);
+}
@@ -0,0 +1,256 @@
+// Copyright (C) 2024 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use clarity::vm::costs::ExecutionCost;
+use clarity::vm::Value;
+use stacks_common::address::{AddressHashMode, C32_ADDRESS_VERSION_TESTNET_SINGLESIG};
+use stacks_common::types::chainstate::{StacksAddress, StacksPrivateKey, StacksPublicKey};
+
+use super::TestRPC;
+use crate::chainstate::stacks::{
+    StacksTransaction, StacksTransactionSigner, TransactionAuth, TransactionPayload,
+    TransactionVersion,
+};
+use crate::net::api::*;
+use crate::net::connection::ConnectionOptions;
+use crate::net::httpcore::{RPCRequestHandler, StacksHttp, StacksHttpRequest};
+use crate::net::rpc::ConversationHttp;
+use crate::net::{ProtocolFamily, TipRequest, UrlString};
+
+/// Make an unsigned call to `add-unit` in the test contract, from the account which deployed it
+fn make_add_unit_call(nonce: u64) -> StacksTransaction {
+    // ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R
+    let privk1 = StacksPrivateKey::from_hex(
+        "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
+    )
+    .unwrap();
+
+    let addr1 = StacksAddress::from_public_keys(
+        C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+        &AddressHashMode::SerializeP2PKH,
+        1,
+        &vec![StacksPublicKey::from_private(&privk1)],
+    )
+    .unwrap();
+
+    let mut tx_cc = StacksTransaction::new(
+        TransactionVersion::Testnet,
+        TransactionAuth::from_p2pkh(&privk1).unwrap(),
+        TransactionPayload::new_contract_call(addr1, "hello-world", "add-unit", vec![]).unwrap(),
+    );
+    tx_cc.chain_id = 0x80000000;
+    tx_cc.auth.set_origin_nonce(nonce);
+    tx_cc.set_tx_fee(123);
+    tx_cc
+}
+
+fn sign(tx: &StacksTransaction) -> StacksTransaction {
+    let privk1 = StacksPrivateKey::from_hex(
+        "9f1f85a512a96a244e4c0d762788500687feb97481639572e3bffbd6860e6ab001",
+    )
+    .unwrap();
+    let mut tx_signer = StacksTransactionSigner::new(tx);
+    tx_signer.sign_origin(&privk1).unwrap();
+    tx_signer.get_tx().unwrap()
+}
+
+#[test]
+fn test_try_parse_request() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+    let mut http = StacksHttp::new(addr.clone(), &ConnectionOptions::default());
+
+    let tx = make_add_unit_call(2);
+    let request = StacksHttpRequest::new_post_transaction_simulate(
+        addr.into(),
+        tx.clone(),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    let bytes = request.try_serialize().unwrap();
+
+    let (parsed_preamble, offset) = http.read_preamble(&bytes).unwrap();
+    let mut handler = posttransaction_simulate::RPCPostTransactionSimulateRequestHandler::new(
+        true,
+        ExecutionCost::max_value(),
+    );
+    let mut parsed_request = http
+        .handle_try_parse_request(
+            &mut handler,
+            &parsed_preamble.expect_request(),
+            &bytes[offset..],
+        )
+        .unwrap();
+
+    assert_eq!(handler.tx, Some(tx));
+
+    // parsed request consumes headers that would not be in a constructed reqeuest
+    parsed_request.clear_headers();
+    let (preamble, contents) = parsed_request.destruct();
+
+    assert_eq!(&preamble, request.preamble());
+
+    handler.restart();
+    assert!(handler.tx.is_none());
+}
+
+#[test]
+fn test_try_make_response() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let rpc_test = TestRPC::setup(function_name!());
+    let mut requests = vec![];
+
+    // unsigned, but otherwise mineable
+    let unsigned_tx = make_add_unit_call(2);
+    let request = StacksHttpRequest::new_post_transaction_simulate(
+        addr.into(),
+        unsigned_tx.clone(),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // signed and mineable.  The previous simulation must not have bumped the nonce.
+    let signed_tx = sign(&make_add_unit_call(2));
+    let request = StacksHttpRequest::new_post_transaction_simulate(
+        addr.into(),
+        signed_tx.clone(),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    // bad nonce
+    let stale_tx = sign(&make_add_unit_call(0));
+    let request = StacksHttpRequest::new_post_transaction_simulate(
+        addr.into(),
+        stale_tx.clone(),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    requests.push(request);
+
+    let mut responses = rpc_test.run(requests);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+    let resp = response.decode_transaction_simulation().unwrap();
+    assert_eq!(resp.txid, unsigned_tx.txid());
+    assert!(!resp.signatures_valid);
+    assert!(resp.error.is_none());
+    let receipt = resp.receipt.unwrap();
+    assert_eq!(receipt.fee, 123);
+    assert_eq!(
+        receipt.result,
+        format!(
+            "0x{}",
+            Value::okay(Value::Int(1))
+                .unwrap()
+                .serialize_to_hex()
+                .unwrap()
+        )
+    );
+    assert!(!receipt.post_condition_aborted);
+    assert!(receipt.vm_error.is_none());
+    assert!(receipt.execution_cost.runtime > 0);
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+    let resp = response.decode_transaction_simulation().unwrap();
+    assert_eq!(resp.txid, signed_tx.txid());
+    assert!(resp.signatures_valid);
+    assert!(resp.error.is_none());
+    assert!(resp.receipt.is_some());
+
+    let response = responses.remove(0);
+    debug!(
+        "Response:\n{}\n",
+        std::str::from_utf8(&response.try_serialize().unwrap()).unwrap()
+    );
+    let resp = response.decode_transaction_simulation().unwrap();
+    assert_eq!(resp.txid, stale_tx.txid());
+    assert!(resp.signatures_valid);
+    assert!(resp.receipt.is_none());
+    assert!(resp.error.is_some());
+}
+
+/// Make peer 2 serve requests with the given connection options
+fn reconnect_peer_2(rpc_test: &mut TestRPC, conn_opts: &ConnectionOptions) {
+    rpc_test.convo_2 = ConversationHttp::new(
+        format!("127.0.0.1:{}", rpc_test.peer_2.config.http_port)
+            .parse::<SocketAddr>()
+            .unwrap(),
+        Some(UrlString::try_from("http://peer2.com".to_string()).unwrap()),
+        rpc_test.peer_2.to_peer_host(),
+        conn_opts,
+        1,
+        32,
+    );
+}
+
+#[test]
+fn test_try_make_response_disabled() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    let mut conn_opts = rpc_test.peer_2.config.connection_opts.clone();
+    conn_opts.enable_transaction_simulation = false;
+    reconnect_peer_2(&mut rpc_test, &conn_opts);
+
+    let request = StacksHttpRequest::new_post_transaction_simulate(
+        addr.into(),
+        sign(&make_add_unit_call(2)),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    let mut responses = rpc_test.run(vec![request]);
+
+    let response = responses.remove(0);
+    let (preamble, _body) = response.destruct();
+    assert_eq!(preamble.status_code, 404);
+}
+
+#[test]
+fn test_try_make_response_cost_limit() {
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 33333);
+
+    let mut rpc_test = TestRPC::setup(function_name!());
+    let mut conn_opts = rpc_test.peer_2.config.connection_opts.clone();
+    conn_opts.simulate_cost_limit = ExecutionCost {
+        write_length: 0,
+        write_count: 0,
+        read_length: 0,
+        read_count: 0,
+        runtime: 0,
+    };
+    reconnect_peer_2(&mut rpc_test, &conn_opts);
+
+    let tx = sign(&make_add_unit_call(2));
+    let request = StacksHttpRequest::new_post_transaction_simulate(
+        addr.into(),
+        tx.clone(),
+        TipRequest::UseLatestAnchoredTip,
+    );
+    let mut responses = rpc_test.run(vec![request]);
+
+    let response = responses.remove(0);
+    let resp = response.decode_transaction_simulation().unwrap();
+    assert_eq!(resp.txid, tx.txid());
+    assert!(resp.receipt.is_none());
+    assert!(resp.error.is_some());
+}
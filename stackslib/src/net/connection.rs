@@ -442,6 +442,15 @@ pub struct ConnectionOptions {
     pub block_proposal_max_age_secs: u64,
     /// StackerDB replicas to talk to for a particular smart contract
     pub stackerdb_hint_replicas: HashMap<QualifiedContractIdentifier, Vec<NeighborAddress>>,
+    /// Whether or not to serve `/v3/transactions/simulate`
+    pub enable_transaction_simulation: bool,
+    /// Maximum execution budget of a simulated transaction
+    pub simulate_cost_limit: ExecutionCost,
+    /// Maximum number of concurrent `/v3/events/stream` subscribers.  0 (the default) disables
+    /// the endpoint.
+    pub max_event_stream_subscribers: u64,
+    /// Maximum number of events to buffer for an event stream subscriber before it is dropped
+    pub event_stream_buffer_len: u64,
 
     // fault injection
     /// Disable neighbor walk and discovery
@@ -576,6 +585,16 @@ impl std::default::Default for ConnectionOptions {
             auth_token: None,
             block_proposal_max_age_secs: DEFAULT_BLOCK_PROPOSAL_MAX_AGE_SECS,
             stackerdb_hint_replicas: HashMap::new(),
+            enable_transaction_simulation: false,
+            simulate_cost_limit: ExecutionCost {
+                write_length: 100000,
+                write_count: 30,
+                read_length: 100000,
+                read_count: 30,
+                runtime: 1_000_000_000,
+            },
+            max_event_stream_subscribers: 0,
+            event_stream_buffer_len: 1024,
 
             // no faults on by default
             disable_neighbor_walk: false,
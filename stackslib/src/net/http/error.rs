@@ -129,6 +129,7 @@ pub fn http_error_from_code_and_text(code: u16, message: String) -> Box<dyn Http
         402 => Box::new(HttpPaymentRequired::new(message)),
         403 => Box::new(HttpForbidden::new(message)),
         404 => Box::new(HttpNotFound::new(message)),
+        410 => Box::new(HttpGone::new(message)),
         500 => Box::new(HttpServerError::new(message)),
         503 => Box::new(HttpServiceUnavailable::new(message)),
         _ => Box::new(HttpError::new(code, message)),
@@ -292,6 +293,33 @@ impl HttpErrorResponse for HttpNotFound {
     }
 }
 
+/// HTTP 410
+pub struct HttpGone {
+    error_text: String,
+}
+
+impl HttpGone {
+    pub fn new(error_text: String) -> Self {
+        Self { error_text }
+    }
+}
+
+impl HttpErrorResponse for HttpGone {
+    fn code(&self) -> u16 {
+        410
+    }
+    fn payload(&self) -> HttpResponsePayload {
+        HttpResponsePayload::Text(self.error_text.clone())
+    }
+    fn try_parse_response(
+        &self,
+        preamble: &HttpResponsePreamble,
+        body: &[u8],
+    ) -> Result<HttpResponsePayload, Error> {
+        try_parse_error_response(preamble.status_code, preamble.content_type, body)
+    }
+}
+
 /// HTTP 500
 pub struct HttpServerError {
     error_text: String,
@@ -38,8 +38,8 @@ pub use crate::net::http::common::{
 };
 pub use crate::net::http::error::{
     http_error_from_code_and_text, http_reason, HttpBadRequest, HttpError, HttpErrorResponse,
-    HttpForbidden, HttpNotFound, HttpPaymentRequired, HttpServerError, HttpServiceUnavailable,
-    HttpUnauthorized,
+    HttpForbidden, HttpGone, HttpNotFound, HttpPaymentRequired, HttpServerError,
+    HttpServiceUnavailable, HttpUnauthorized,
 };
 pub use crate::net::http::request::{
     HttpRequest, HttpRequestContents, HttpRequestPayload, HttpRequestPreamble,
@@ -153,6 +153,8 @@ pub enum HttpContentType {
     Bytes,
     Text,
     JSON,
+    /// Server-sent events
+    EventStream,
 }
 
 impl fmt::Display for HttpContentType {
@@ -167,6 +169,7 @@ impl HttpContentType {
             HttpContentType::Bytes => "application/octet-stream",
             HttpContentType::Text => "text/plain",
             HttpContentType::JSON => "application/json",
+            HttpContentType::EventStream => "text/event-stream",
         }
     }
 }
@@ -182,6 +185,8 @@ impl FromStr for HttpContentType {
             Ok(HttpContentType::Text)
         } else if s == "application/json" || s.starts_with("application/json;") {
             Ok(HttpContentType::JSON)
+        } else if s == "text/event-stream" || s.starts_with("text/event-stream;") {
+            Ok(HttpContentType::EventStream)
         } else {
             Err(CodecError::DeserializeError(format!(
                 "Unsupported HTTP content type: {header}"
@@ -111,6 +111,15 @@ impl HttpResponseContents {
         }
     }
 
+    /// Is this a long-lived stream which is still open?  Such a stream can have nothing to send
+    /// right now, but more data later.
+    pub fn is_open(&self) -> bool {
+        match self {
+            Self::Stream(ref inner_stream) => inner_stream.generator.is_open(),
+            Self::RAM(..) => false,
+        }
+    }
+
     /// Write data for this to a pipe writer, which buffers it up.
     /// Return Ok(Some(..)) if there is mroe data to send.
     /// Once all data is sent, return Ok(None)
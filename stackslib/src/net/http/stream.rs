@@ -36,9 +36,17 @@ pub trait HttpChunkGenerator: Send {
     fn generate_next_chunk(&mut self) -> Result<Vec<u8>, String>;
     fn hint_chunk_size(&self) -> usize;
 
+    /// Is this stream still open, even though it may have nothing to send right now?
+    /// Long-lived streams (such as event subscriptions) return `true` until they are closed.
+    /// While a stream is open, an empty chunk means "nothing yet" instead of "no more chunks".
+    fn is_open(&self) -> bool {
+        false
+    }
+
     /// Stream one chunk to the pipe writer.  This never blocks.
     /// Returns Ok(num-bytes > 0) if there are more chunks (i.e. the caller should call this again)
-    /// Returns Ok(0) if there are no more chunks (i.e. the caller should not call this again)
+    /// Returns Ok(0) if there are no more chunks (i.e. the caller should not call this again), or
+    /// if an open stream has nothing to send yet (i.e. the caller should try again later)
     /// Returns Err(..) on irrecoverable I/O error
     #[cfg_attr(test, mutants::skip)]
     fn stream_to(
@@ -54,6 +62,10 @@ pub trait HttpChunkGenerator: Send {
         let mut encoder = HttpChunkedTransferWriter::from_writer_state(fd, encoder_state);
 
         if chunk.is_empty() {
+            if self.is_open() {
+                // nothing to send yet, but the stream is not done
+                return Ok(0);
+            }
             // no more chunks, but be sure to cork the stream
             if !encoder.corked() {
                 encoder.flush()?;
@@ -61,6 +73,10 @@ pub trait HttpChunkGenerator: Send {
             }
         } else {
             encoder.write_all(&chunk)?;
+            if self.is_open() {
+                // don't hold back data from a long-lived stream
+                encoder.flush_buffered_chunk()?;
+            }
         }
 
         Ok(chunk.len() as u64)
@@ -70,12 +70,20 @@ pub const STACKS_REQUEST_ID: &str = "X-Request-Id";
 /// from non-Stacks nodes (like Gaia hubs, CDNs, vanilla HTTP servers, and so on).
 pub const HTTP_REQUEST_ID_RESERVED: u32 = 0;
 
-/// All representations of the `tip=` query parameter value
+/// All representations of the `tip=`, `at_height=`, `at_burn_height=` and `at_tenure=` query
+/// parameter values
 #[derive(Debug, Clone, PartialEq)]
 pub enum TipRequest {
     UseLatestAnchoredTip,
     UseLatestUnconfirmedTip,
     SpecificTip(StacksBlockId),
+    /// The ancestor of the canonical tip at this Stacks block height
+    AtStacksHeight(u64),
+    /// The highest ancestor of the canonical tip which was mined at or below this burnchain
+    /// block height
+    AtBurnHeight(u64),
+    /// The highest ancestor of the canonical tip in the tenure with this consensus hash
+    AtTenure(ConsensusHash),
 }
 
 impl TipRequest {}
@@ -86,6 +94,9 @@ impl fmt::Display for TipRequest {
             Self::UseLatestAnchoredTip => write!(f, ""),
             Self::UseLatestUnconfirmedTip => write!(f, "latest"),
             Self::SpecificTip(ref tip) => write!(f, "{tip}"),
+            Self::AtStacksHeight(height) => write!(f, "at_height={height}"),
+            Self::AtBurnHeight(height) => write!(f, "at_burn_height={height}"),
+            Self::AtTenure(consensus_hash) => write!(f, "at_tenure={consensus_hash}"),
         }
     }
 }
@@ -303,6 +314,8 @@ pub trait HttpRequestContentsExtensions {
     fn for_tip(self, tip_req: TipRequest) -> Self;
     /// Identify the tip request
     fn tip_request(&self) -> TipRequest;
+    /// Identify the tip request, failing if a tip selector is malformed
+    fn try_tip_request(&self) -> Result<TipRequest, HttpError>;
     /// Determine if we should return a MARF proof
     fn get_with_proof(&self) -> bool;
 }
@@ -315,22 +328,61 @@ impl HttpRequestContentsExtensions for HttpRequestContents {
 
     /// Use a particular tip request
     fn for_tip(mut self, tip_req: TipRequest) -> Self {
-        if tip_req != TipRequest::UseLatestAnchoredTip {
-            self.query_arg("tip".to_string(), tip_req.to_string())
-        } else {
-            let _ = self.take_query_arg(&"tip".to_string());
-            self
+        match tip_req {
+            TipRequest::UseLatestAnchoredTip => {
+                let _ = self.take_query_arg(&"tip".to_string());
+                self
+            }
+            TipRequest::AtStacksHeight(height) => {
+                self.query_arg("at_height".to_string(), format!("{}", height))
+            }
+            TipRequest::AtBurnHeight(height) => {
+                self.query_arg("at_burn_height".to_string(), format!("{}", height))
+            }
+            TipRequest::AtTenure(consensus_hash) => {
+                self.query_arg("at_tenure".to_string(), format!("{}", consensus_hash))
+            }
+            _ => self.query_arg("tip".to_string(), tip_req.to_string()),
         }
     }
 
-    /// Ref the tip request
+    /// Ref the tip request.
+    /// A malformed `at_height=`, `at_burn_height=` or `at_tenure=` selects the latest tip;
+    /// use `try_tip_request()` to reject it instead.
     fn tip_request(&self) -> TipRequest {
-        self.get_query_args()
-            .get("tip")
-            .map(|tip| tip.as_str().into())
+        self.try_tip_request()
             .unwrap_or(TipRequest::UseLatestAnchoredTip)
     }
 
+    /// Ref the tip request.
+    /// `tip=` takes precedence over `at_height=`, which takes precedence over
+    /// `at_burn_height=`, which takes precedence over `at_tenure=`.
+    fn try_tip_request(&self) -> Result<TipRequest, HttpError> {
+        let query_args = self.get_query_args();
+        if let Some(tip) = query_args.get("tip") {
+            return Ok(tip.as_str().into());
+        }
+        if let Some(height) = query_args.get("at_height") {
+            let height = height.parse::<u64>().map_err(|_| {
+                HttpError::DecodeError(format!("Invalid `at_height`: {height}"))
+            })?;
+            return Ok(TipRequest::AtStacksHeight(height));
+        }
+        if let Some(height) = query_args.get("at_burn_height") {
+            let height = height.parse::<u64>().map_err(|_| {
+                HttpError::DecodeError(format!("Invalid `at_burn_height`: {height}"))
+            })?;
+            return Ok(TipRequest::AtBurnHeight(height));
+        }
+        if let Some(consensus_hash) = query_args.get("at_tenure") {
+            let consensus_hash = ConsensusHash::from_hex(consensus_hash).map_err(|_| {
+                HttpError::DecodeError(format!("Invalid `at_tenure`: {consensus_hash}"))
+            })?;
+            return Ok(TipRequest::AtTenure(consensus_hash));
+        }
+        Ok(TipRequest::UseLatestAnchoredTip)
+    }
+
     /// Get the proof= query parameter value
     fn get_with_proof(&self) -> bool {
         let proof_value = self
@@ -887,12 +939,12 @@ impl HttpResponse for RPCArbitraryResponseHandler {
                 let json = parse_json(preamble, body)?;
                 Ok(HttpResponsePayload::JSON(json))
             }
-            HttpContentType::Text => {
+            HttpContentType::Text | HttpContentType::EventStream => {
                 let text_bytes = parse_raw_bytes(
                     preamble,
                     body,
                     MAX_MESSAGE_LEN.into(),
-                    HttpContentType::Text,
+                    preamble.content_type,
                 )?;
                 let text = String::from_utf8_lossy(&text_bytes).to_string();
                 Ok(HttpResponsePayload::Text(text))
@@ -936,6 +988,10 @@ pub struct StacksHttp {
     pub read_only_call_limit: ExecutionCost,
     /// The authorization token to enable access to privileged features, such as the block proposal RPC endpoint
     pub auth_token: Option<String>,
+    /// Whether or not to serve transaction simulation requests
+    pub enable_transaction_simulation: bool,
+    /// Maximum execution budget of a simulated transaction
+    pub simulate_cost_limit: ExecutionCost,
     /// Allow arbitrary responses to be handled in addition to request handlers
     allow_arbitrary_response: bool,
 }
@@ -956,6 +1012,8 @@ impl StacksHttp {
             maximum_call_argument_size: conn_opts.maximum_call_argument_size,
             read_only_call_limit: conn_opts.read_only_call_limit.clone(),
             auth_token: conn_opts.auth_token.clone(),
+            enable_transaction_simulation: conn_opts.enable_transaction_simulation,
+            simulate_cost_limit: conn_opts.simulate_cost_limit.clone(),
             allow_arbitrary_response: false,
         };
         http.register_rpc_methods();
@@ -977,6 +1035,8 @@ impl StacksHttp {
             maximum_call_argument_size: conn_opts.maximum_call_argument_size,
             read_only_call_limit: conn_opts.read_only_call_limit.clone(),
             auth_token: conn_opts.auth_token.clone(),
+            enable_transaction_simulation: conn_opts.enable_transaction_simulation,
+            simulate_cost_limit: conn_opts.simulate_cost_limit.clone(),
             allow_arbitrary_response: true,
         }
     }
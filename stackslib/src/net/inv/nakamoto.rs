@@ -447,6 +447,11 @@ impl InvGenerator {
             cur_height = cur_height.saturating_sub(1);
         }
 
+        if StacksChainState::is_reward_cycle_pruned(chainstate.db(), reward_cycle)? {
+            // this is a pruned node, and it no longer has these tenures' blocks
+            tenure_status.iter_mut().for_each(|bit| *bit = false);
+        }
+
         tenure_status.reverse();
         trace!(
             "Tenure bits off of {nakamoto_tip} and {}: {tenure_status:?}",
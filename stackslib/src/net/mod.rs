@@ -73,7 +73,7 @@ use crate::chainstate::stacks::boot::{
     BOOT_TEST_POX_4_AGG_KEY_CONTRACT, BOOT_TEST_POX_4_AGG_KEY_FNAME,
 };
 use crate::chainstate::stacks::db::blocks::MemPoolRejection;
-use crate::chainstate::stacks::db::StacksChainState;
+use crate::chainstate::stacks::db::{StacksChainState, StacksHeaderInfo};
 use crate::chainstate::stacks::index::Error as marf_error;
 use crate::chainstate::stacks::{
     Error as chainstate_error, Error as chain_error, StacksBlock, StacksBlockHeader,
@@ -84,9 +84,10 @@ use crate::core::mempool::*;
 use crate::core::{StacksEpoch, POX_REWARD_CYCLE_LENGTH};
 use crate::cost_estimates::metrics::CostMetric;
 use crate::cost_estimates::{CostEstimator, FeeEstimator, FeeRateEstimate};
+use crate::net::api::geteventstream::RPCEventStreamHub;
 use crate::net::atlas::{Attachment, AttachmentInstance};
 use crate::net::dns::*;
-use crate::net::http::error::{HttpNotFound, HttpServerError};
+use crate::net::http::error::{HttpBadRequest, HttpGone, HttpNotFound, HttpServerError};
 use crate::net::http::{
     Error as HttpErr, HttpRequestContents, HttpRequestPreamble, HttpResponsePreamble,
 };
@@ -636,6 +637,8 @@ pub struct RPCHandlerArgs<'a> {
     pub cost_metric: Option<&'a dyn CostMetric>,
     /// coordinator channels
     pub coord_comms: Option<&'a CoordinatorChannels>,
+    /// subscribers to the `/v3/events/stream` endpoint
+    pub event_stream: Option<&'a RPCEventStreamHub>,
 }
 
 impl RPCHandlerArgs<'_> {
@@ -763,8 +766,10 @@ impl<'a> StacksNodeState<'a> {
         contents: &HttpRequestContents,
     ) -> Result<StacksBlockId, StacksHttpResponse> {
         self.with_node_state(|_network, sortdb, chainstate, _mempool, _rpc_args| {
-            let tip_req = contents.tip_request();
-            match tip_req {
+            let tip_req = contents.try_tip_request().map_err(|e| {
+                StacksHttpResponse::new_error(preamble, &HttpBadRequest::new(e.to_string()))
+            })?;
+            let tip = match tip_req {
                 TipRequest::UseLatestUnconfirmedTip => {
                     let unconfirmed_chain_tip_opt = match &mut chainstate.unconfirmed_state {
                         Some(unconfirmed_state) => {
@@ -812,33 +817,101 @@ impl<'a> StacksNodeState<'a> {
                 }
                 TipRequest::SpecificTip(tip) => Ok(tip.clone()),
                 TipRequest::UseLatestAnchoredTip => {
-                    match NakamotoChainState::get_canonical_block_header(chainstate.db(), sortdb) {
-                        Ok(Some(tip)) => Ok(StacksBlockId::new(
-                            &tip.consensus_hash,
-                            &tip.anchored_header.block_hash(),
-                        )),
-                        Ok(None) => {
-                            return Err(StacksHttpResponse::new_error(
-                                preamble,
-                                &HttpNotFound::new(
-                                    "No stacks chain tip exists at this point in time.".to_string(),
-                                ),
-                            ));
-                        }
-                        Err(e) => {
-                            return Err(StacksHttpResponse::new_error(
-                                preamble,
-                                &HttpServerError::new(format!(
-                                    "Failed to load chain tip: {:?}",
-                                    &e
-                                )),
-                            ));
-                        }
-                    }
+                    Self::load_canonical_stacks_chain_tip(preamble, sortdb, chainstate)
+                }
+                TipRequest::AtStacksHeight(height) => {
+                    let tip = Self::load_canonical_stacks_chain_tip(preamble, sortdb, chainstate)?;
+                    let header_res = NakamotoChainState::get_ancestor_block_header_at_height(
+                        &chainstate.index_conn(),
+                        &tip,
+                        height,
+                    );
+                    Self::ancestor_tip_or_error(preamble, header_res, &tip_req)
                 }
+                TipRequest::AtBurnHeight(burn_height) => {
+                    let tip = Self::load_canonical_stacks_chain_tip(preamble, sortdb, chainstate)?;
+                    let header_res = NakamotoChainState::get_ancestor_block_header_at_burn_height(
+                        &chainstate.index_conn(),
+                        &tip,
+                        burn_height,
+                    );
+                    Self::ancestor_tip_or_error(preamble, header_res, &tip_req)
+                }
+                TipRequest::AtTenure(ref consensus_hash) => {
+                    let tip = Self::load_canonical_stacks_chain_tip(preamble, sortdb, chainstate)?;
+                    let header_res = NakamotoChainState::get_ancestor_block_header_in_tenure(
+                        &mut chainstate.index_conn(),
+                        &tip,
+                        consensus_hash,
+                    );
+                    Self::ancestor_tip_or_error(preamble, header_res, &tip_req)
+                }
+            }?;
+
+            // a pruned node cannot read state as of old blocks
+            match StacksChainState::is_block_state_pruned(chainstate.db(), &tip) {
+                Ok(false) => Ok(tip),
+                Ok(true) => Err(StacksHttpResponse::new_error(
+                    preamble,
+                    &HttpGone::new(format!("State at block {tip} has been pruned by this node")),
+                )),
+                Err(e) => Err(StacksHttpResponse::new_error(
+                    preamble,
+                    &HttpServerError::new(format!("Failed to load chain tip: {:?}", &e)),
+                )),
             }
         })
     }
+
+    /// Load the canonical Stacks chain tip.
+    /// If the chain tip could not be found, then it returns Err(HttpNotFound)
+    /// If there was an error querying the DB, then it returns Err(HttpServerError)
+    fn load_canonical_stacks_chain_tip(
+        preamble: &HttpRequestPreamble,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+    ) -> Result<StacksBlockId, StacksHttpResponse> {
+        match NakamotoChainState::get_canonical_block_header(chainstate.db(), sortdb) {
+            Ok(Some(tip)) => Ok(StacksBlockId::new(
+                &tip.consensus_hash,
+                &tip.anchored_header.block_hash(),
+            )),
+            Ok(None) => Err(StacksHttpResponse::new_error(
+                preamble,
+                &HttpNotFound::new("No stacks chain tip exists at this point in time.".to_string()),
+            )),
+            Err(e) => Err(StacksHttpResponse::new_error(
+                preamble,
+                &HttpServerError::new(format!("Failed to load chain tip: {:?}", &e)),
+            )),
+        }
+    }
+
+    /// Map the result of an `at_height=` or `at_burn_height=` lookup to the chain tip it
+    /// selects, or to an HTTP error if there is no such block on the canonical fork.
+    fn ancestor_tip_or_error(
+        preamble: &HttpRequestPreamble,
+        header_res: Result<Option<StacksHeaderInfo>, chainstate_error>,
+        tip_req: &TipRequest,
+    ) -> Result<StacksBlockId, StacksHttpResponse> {
+        match header_res {
+            Ok(Some(header)) => Ok(header.index_block_hash()),
+            Ok(None) => Err(StacksHttpResponse::new_error(
+                preamble,
+                &HttpNotFound::new(format!(
+                    "No block on the canonical Stacks fork matches {}",
+                    tip_req
+                )),
+            )),
+            Err(e) => Err(StacksHttpResponse::new_error(
+                preamble,
+                &HttpServerError::new(format!(
+                    "Failed to load chain tip for {}: {:?}",
+                    tip_req, &e
+                )),
+            )),
+        }
+    }
 }
 
 pub const STACKS_PUBLIC_KEY_ENCODED_SIZE: u32 = 33;
@@ -2305,6 +2378,7 @@ pub mod test {
     use crate::cost_estimates::metrics::UnitMetric;
     use crate::cost_estimates::tests::fee_rate_fuzzer::ConstantFeeEstimator;
     use crate::cost_estimates::UnitEstimator;
+    use crate::event_dispatcher::EventDispatcher;
     use crate::net::asn::*;
     use crate::net::atlas::*;
     use crate::net::chat::*;
@@ -2517,6 +2591,8 @@ pub mod test {
 
     pub struct TestEventObserver {
         blocks: Mutex<Vec<TestEventObserverBlock>>,
+        /// Dispatcher to pass the announcements on to, if any
+        forward_to: Option<EventDispatcher>,
     }
 
     impl TestEventObserver {
@@ -2527,6 +2603,15 @@ pub mod test {
         pub fn new() -> TestEventObserver {
             TestEventObserver {
                 blocks: Mutex::new(vec![]),
+                forward_to: None,
+            }
+        }
+
+        /// Record announcements, and also pass them on to `dispatcher`
+        pub fn forwarding_to(dispatcher: EventDispatcher) -> TestEventObserver {
+            TestEventObserver {
+                blocks: Mutex::new(vec![]),
+                forward_to: Some(dispatcher),
             }
         }
     }
@@ -2544,14 +2629,35 @@ pub mod test {
             parent_burn_block_hash: BurnchainHeaderHash,
             parent_burn_block_height: u32,
             parent_burn_block_timestamp: u64,
-            _anchor_block_cost: &ExecutionCost,
-            _confirmed_mblock_cost: &ExecutionCost,
+            anchor_block_cost: &ExecutionCost,
+            confirmed_mblock_cost: &ExecutionCost,
             pox_constants: &PoxConstants,
             reward_set_data: &Option<RewardSetData>,
-            _signer_bitvec: &Option<BitVec<4000>>,
-            _block_timestamp: Option<u64>,
-            _coinbase_height: u64,
+            signer_bitvec: &Option<BitVec<4000>>,
+            block_timestamp: Option<u64>,
+            coinbase_height: u64,
         ) {
+            if let Some(dispatcher) = self.forward_to.as_ref() {
+                dispatcher.announce_block(
+                    block,
+                    metadata,
+                    receipts,
+                    parent,
+                    winner_txid,
+                    matured_rewards,
+                    matured_rewards_info,
+                    parent_burn_block_hash,
+                    parent_burn_block_height,
+                    parent_burn_block_timestamp,
+                    anchor_block_cost,
+                    confirmed_mblock_cost,
+                    pox_constants,
+                    reward_set_data,
+                    signer_bitvec,
+                    block_timestamp,
+                    coinbase_height,
+                );
+            }
             self.blocks.lock().unwrap().push(TestEventObserverBlock {
                 block: block.clone(),
                 metadata: metadata.clone(),
@@ -2566,14 +2672,23 @@ pub mod test {
 
         fn announce_burn_block(
             &self,
-            _burn_block: &BurnchainHeaderHash,
-            _burn_block_height: u64,
-            _rewards: Vec<(PoxAddress, u64)>,
-            _burns: u64,
-            _reward_recipients: Vec<PoxAddress>,
-            _consensus_hash: &ConsensusHash,
+            burn_block: &BurnchainHeaderHash,
+            burn_block_height: u64,
+            rewards: Vec<(PoxAddress, u64)>,
+            burns: u64,
+            reward_recipients: Vec<PoxAddress>,
+            consensus_hash: &ConsensusHash,
         ) {
-            // pass
+            if let Some(dispatcher) = self.forward_to.as_ref() {
+                dispatcher.announce_burn_block(
+                    burn_block,
+                    burn_block_height,
+                    rewards,
+                    burns,
+                    reward_recipients,
+                    consensus_hash,
+                );
+            }
         }
     }
 
@@ -2585,6 +2700,7 @@ pub mod test {
         fee_estimator: None,
         cost_metric: None,
         coord_comms: None,
+        event_stream: None,
     };
 
     const NULL_COST_ESTIMATOR: () = ();
@@ -2598,6 +2714,7 @@ pub mod test {
         fee_estimator: Some(&NULL_FEE_ESTIMATOR),
         cost_metric: Some(&NULL_COST_METRIC),
         coord_comms: None,
+        event_stream: None,
     };
 
     const UNIT_COST_ESTIMATOR: UnitEstimator = UnitEstimator {};
@@ -2611,6 +2728,7 @@ pub mod test {
         fee_estimator: Some(&CONSTANT_FEE_ESTIMATOR),
         cost_metric: Some(&UNIT_COST_METRIC),
         coord_comms: None,
+        event_stream: None,
     };
 
     /// Templates for RPC Handler Args (which must be owned by the TestPeer, and cannot be a bare
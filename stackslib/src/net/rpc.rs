@@ -179,6 +179,15 @@ impl ConversationHttp {
         self.reply_streams.len()
     }
 
+    /// Are we sending a long-lived stream (such as an event subscription)?
+    /// Such a stream can get new data at any time, not just when the socket is ready.
+    pub fn has_open_stream(&self) -> bool {
+        self.reply_streams
+            .front()
+            .map(|(_, http_response, _)| http_response.is_open())
+            .unwrap_or(false)
+    }
+
     /// What's our outbound URL?
     pub fn get_url(&self) -> Option<&UrlString> {
         self.outbound_url.as_ref()
@@ -311,6 +320,11 @@ impl ConversationHttp {
                 if let Some(pipe_fd) = reply.inner_pipe_out() {
                     let num_written = http_response.pipe_out(pipe_fd)?;
                     if num_written == 0 {
+                        if http_response.is_open() {
+                            // long-lived stream with nothing to send right now.
+                            // Push out what we have, and come back on the next pass.
+                            break;
+                        }
                         // no more chunks
                         drained_stream = true;
                     }
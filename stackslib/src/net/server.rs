@@ -608,6 +608,14 @@ impl HttpPeer {
             if let Err(e) = convo.try_flush() {
                 info!("Broken HTTP connection {:?}: {:?}", convo, &e);
                 close.push(*event_id);
+            } else if convo.has_open_stream() {
+                // long-lived streams get new data in between socket events, so push it out now
+                if let Some(client_sock) = self.sockets.get_mut(event_id) {
+                    if let Err(e) = HttpPeer::saturate_http_socket(client_sock, convo) {
+                        debug!("Failed to send streamed HTTP data on {:?}: {:?}", convo, &e);
+                        close.push(*event_id);
+                    }
+                }
             }
             if convo.is_drained() && !convo.is_keep_alive() {
                 // did some work, but nothing more to do and we're not keep-alive
@@ -21,7 +21,9 @@ use std::time::{Duration, Instant};
 use std::{str, thread};
 
 use stacks_common::codec::StacksMessageCodec;
-use stacks_common::types::chainstate::{StacksAddress, StacksBlockId, StacksPrivateKey};
+use stacks_common::types::chainstate::{
+    ConsensusHash, StacksAddress, StacksBlockId, StacksPrivateKey,
+};
 use stacks_common::types::net::{PeerAddress, PeerHost};
 use stacks_common::types::StacksEpochId;
 use stacks_common::util::chunked_encoding::{
@@ -972,6 +974,48 @@ fn test_http_parse_proof_tip_query() {
     assert_eq!(tip_req, TipRequest::UseLatestAnchoredTip);
 }
 
+#[test]
+fn test_http_parse_tip_selector_query() {
+    let contents = HttpRequestContents::new().query_string(Some("at_height=12"));
+    assert_eq!(
+        contents.try_tip_request().unwrap(),
+        TipRequest::AtStacksHeight(12)
+    );
+
+    let contents = HttpRequestContents::new().query_string(Some("at_burn_height=34"));
+    assert_eq!(
+        contents.try_tip_request().unwrap(),
+        TipRequest::AtBurnHeight(34)
+    );
+
+    let contents = HttpRequestContents::new()
+        .query_string(Some("at_tenure=0101010101010101010101010101010101010101"));
+    assert_eq!(
+        contents.try_tip_request().unwrap(),
+        TipRequest::AtTenure(ConsensusHash([0x01; 20]))
+    );
+
+    // tip= takes precedence
+    let contents = HttpRequestContents::new().query_string(Some(
+        "at_height=bad&tip=7070f213d719143d6045e08fd80f85014a161f8bbd3a42d1251576740826a392",
+    ));
+    assert!(matches!(
+        contents.try_tip_request().unwrap(),
+        TipRequest::SpecificTip(_)
+    ));
+
+    // malformed selectors are rejected
+    for query_txt in [
+        "at_height=bad",
+        "at_height=-1",
+        "at_burn_height=",
+        "at_tenure=01",
+    ] {
+        let contents = HttpRequestContents::new().query_string(Some(query_txt));
+        assert!(contents.try_tip_request().is_err(), "{query_txt}");
+    }
+}
+
 #[test]
 fn test_http_parse_proof_request_query() {
     let query_txt = "";
@@ -23,8 +23,11 @@ use std::thread::JoinHandle;
 use clarity::vm::types::PrincipalData;
 use stacks_common::address::{AddressHashMode, C32_ADDRESS_VERSION_TESTNET_SINGLESIG};
 use stacks_common::codec::{read_next, StacksMessageCodec};
-use stacks_common::types::chainstate::{StacksAddress, StacksPrivateKey, StacksPublicKey};
+use stacks_common::types::chainstate::{
+    StacksAddress, StacksBlockId, StacksPrivateKey, StacksPublicKey,
+};
 use stacks_common::types::net::PeerAddress;
+use stacks_common::types::sqlite::NO_PARAMS;
 use stacks_common::types::StacksEpoch;
 use stacks_common::util::hash::Hash160;
 
@@ -53,7 +56,7 @@ use crate::net::{
     PeerNetworkComms, StacksMessage, StacksMessageType,
 };
 use crate::stacks_common::types::Address;
-use crate::util_lib::db::Error as DBError;
+use crate::util_lib::db::{query_count, Error as DBError};
 
 /// Handshake with and get the reward cycle inventories for a range of reward cycles
 pub fn peer_get_nakamoto_invs<'a>(
@@ -2427,3 +2430,102 @@ fn test_nakamoto_invs_shadow_blocks() {
     stored_block_ids.reverse();
     assert_eq!(stored_block_ids, expected_ids);
 }
+
+#[test]
+fn test_nakamoto_inv_pruned() {
+    let mut peer = simple_nakamoto_coordinator_10_tenures_10_sortitions();
+    let burnchain = peer.config.burnchain.clone();
+    let sort_db = peer.sortdb.take().unwrap();
+    let chainstate = &mut peer.stacks_node.as_mut().unwrap().chainstate;
+    let (stacks_tip_ch, stacks_tip_bh) =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db.conn()).unwrap();
+
+    let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+    assert_eq!(tip.block_height, 46);
+
+    // an archival node prunes nothing
+    assert!(chainstate
+        .prune_chainstate(&burnchain, tip.block_height)
+        .unwrap()
+        .is_none());
+
+    // keep reward cycles 8 and 9, and prune reward cycle 7
+    chainstate.prune_reward_cycles = Some(2);
+    let (horizon, stats) = chainstate
+        .prune_chainstate(&burnchain, tip.block_height)
+        .unwrap()
+        .unwrap();
+    assert_eq!(horizon.reward_cycle, 8);
+    // the epoch 2.x blocks which booted the chain are gone too, and marked as such
+    assert!(stats.epoch2_blocks > 0);
+    let num_pruned_epoch2: u64 = query_count(
+        chainstate.db(),
+        "SELECT COUNT(*) FROM staging_blocks WHERE pruned = 1",
+        NO_PARAMS,
+    )
+    .unwrap()
+    .try_into()
+    .unwrap();
+    assert_eq!(num_pruned_epoch2, stats.epoch2_blocks);
+
+    // nothing more to do until the next reward cycle
+    assert!(chainstate
+        .prune_chainstate(&burnchain, tip.block_height)
+        .unwrap()
+        .is_none());
+
+    // only the blocks of tenures which started before reward cycle 8 are gone
+    let mut cursor = StacksBlockId::new(&stacks_tip_ch, &stacks_tip_bh);
+    let mut num_pruned = 0;
+    while let Some(header) =
+        NakamotoChainState::get_block_header_nakamoto(chainstate.db(), &cursor).unwrap()
+    {
+        let pruned = u64::from(header.burn_header_height) < horizon.burn_height;
+        assert_eq!(
+            StacksChainState::is_block_pruned(chainstate.db(), &cursor).unwrap(),
+            pruned
+        );
+        assert_eq!(
+            chainstate
+                .nakamoto_blocks_db()
+                .get_nakamoto_block(&cursor)
+                .unwrap()
+                .is_none(),
+            pruned
+        );
+        if pruned {
+            num_pruned += 1;
+        }
+        cursor = NakamotoChainState::get_nakamoto_parent_block_id(chainstate.db(), &cursor)
+            .unwrap()
+            .unwrap();
+    }
+    assert!(num_pruned > 0);
+    assert_eq!(stats.nakamoto_blocks, num_pruned);
+
+    // pruned reward cycles are not advertised
+    let mut inv_generator = InvGenerator::new();
+    let bitvec = inv_generator
+        .make_tenure_bitvector(
+            &tip,
+            &sort_db,
+            chainstate,
+            &stacks_tip_ch,
+            &stacks_tip_bh,
+            7,
+        )
+        .unwrap();
+    assert_eq!(bitvec, vec![false, false, false, false, false]);
+
+    let bitvec = inv_generator
+        .make_tenure_bitvector(
+            &tip,
+            &sort_db,
+            chainstate,
+            &stacks_tip_ch,
+            &stacks_tip_bh,
+            8,
+        )
+        .unwrap();
+    assert_eq!(bitvec, vec![true, true, true, true, true]);
+}
@@ -16,7 +16,6 @@ use stacks_common::util::hash::hex_bytes;
 pub mod monitoring;
 
 pub mod burnchains;
-pub mod event_dispatcher;
 pub mod genesis_data;
 pub mod globals;
 pub mod keychain;
@@ -29,7 +28,8 @@ pub mod syncctl;
 pub mod tenure;
 
 use std::collections::HashMap;
-use std::{env, panic, process};
+use std::path::PathBuf;
+use std::{env, fs, panic, process};
 
 use backtrace::Backtrace;
 use pico_args::Arguments;
@@ -38,9 +38,13 @@ use stacks::chainstate::burn::operations::leader_block_commit::RewardSetInfo;
 use stacks::chainstate::coordinator::{get_next_recipients, OnChainRewardSetProvider};
 use stacks::chainstate::stacks::address::PoxAddress;
 use stacks::chainstate::stacks::db::blocks::DummyEventDispatcher;
+use stacks::chainstate::stacks::db::snapshot::{import_snapshot, SnapshotError};
 use stacks::chainstate::stacks::db::StacksChainState;
 use stacks::config::chain_data::MinerStats;
 pub use stacks::config::{Config, ConfigFile};
+pub use stacks::event_dispatcher;
+use stacks::net::api::getstackers::GetStackersResponse;
+use stacks::types::chainstate::StacksBlockId;
 #[cfg(not(any(target_os = "macos", target_os = "windows", target_arch = "arm")))]
 use tikv_jemallocator::Jemalloc;
 
@@ -285,6 +289,8 @@ fn main() {
         info!("Will begin mining once Stacks chain has synced to height >= {mine_start}");
     }
 
+    let mut import_snapshot: Option<(String, StacksBlockId, String)> = None;
+
     let config_file = match subcommand.as_str() {
         "mocknet" => {
             args.finish();
@@ -329,7 +335,24 @@ fn main() {
         }
         "start" => {
             let config_path: String = args.value_from_str("--config").unwrap();
+            let snapshot_dir: Option<String> =
+                args.opt_value_from_str("--import-snapshot").unwrap();
+            let trusted_block_id: Option<StacksBlockId> = args
+                .opt_value_from_fn("--trusted-block-id", StacksBlockId::from_hex)
+                .unwrap();
+            let trusted_reward_set: Option<String> =
+                args.opt_value_from_str("--trusted-reward-set").unwrap();
             args.finish();
+            import_snapshot = match (snapshot_dir, trusted_block_id, trusted_reward_set) {
+                (Some(snapshot_dir), Some(trusted_block_id), Some(trusted_reward_set)) => {
+                    Some((snapshot_dir, trusted_block_id, trusted_reward_set))
+                }
+                (Some(_), _, _) => {
+                    warn!("--import-snapshot requires --trusted-block-id and --trusted-reward-set");
+                    process::exit(1);
+                }
+                (None, _, _) => None,
+            };
             info!("Loading config at path {config_path}");
             match ConfigFile::from_path(&config_path) {
                 Ok(config_file) => config_file,
@@ -408,6 +431,10 @@ fn main() {
         }
     };
 
+    if let Some((snapshot_dir, trusted_block_id, trusted_reward_set)) = import_snapshot {
+        cli_import_snapshot(&conf, &snapshot_dir, &trusted_block_id, &trusted_reward_set);
+    }
+
     debug!("node configuration {:?}", &conf.node);
     debug!("burnchain configuration {:?}", &conf.burnchain);
     debug!("connection configuration {:?}", &conf.connection_options);
@@ -432,6 +459,51 @@ fn main() {
     }
 }
 
+/// Import a chainstate snapshot made with `stacks-inspect export-snapshot` before starting the
+/// node.  The snapshot block must be `trusted_block_id`, signed by the signers in the
+/// `/v3/stacker_set` response stored at `trusted_reward_set_path`.  Does nothing if the node
+/// already has chainstate, so that the node can be restarted with the same arguments.
+fn cli_import_snapshot(
+    conf: &Config,
+    snapshot_dir: &str,
+    trusted_block_id: &StacksBlockId,
+    trusted_reward_set_path: &str,
+) {
+    let trusted_reward_set: GetStackersResponse = match fs::read_to_string(trusted_reward_set_path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(response) => response,
+        Err(e) => {
+            error!("Failed to load trusted reward set {trusted_reward_set_path}: {e}");
+            process::exit(1);
+        }
+    };
+    match import_snapshot(
+        &PathBuf::from(snapshot_dir),
+        &conf.get_burnchain(),
+        &conf.get_chainstate_path_str(),
+        conf.is_mainnet(),
+        conf.burnchain.chain_id,
+        trusted_block_id,
+        &trusted_reward_set.stacker_set,
+    ) {
+        Ok(manifest) => {
+            info!(
+                "Imported snapshot at Stacks block {} (height {}); resuming sync from there",
+                &manifest.block_id, manifest.stacks_block_height
+            );
+        }
+        Err(SnapshotError::AlreadyInitialized(path)) => {
+            warn!("Not importing snapshot {snapshot_dir}: node already has chainstate at {path}");
+        }
+        Err(e) => {
+            error!("Failed to import snapshot {snapshot_dir}: {e}");
+            process::exit(1);
+        }
+    }
+}
+
 fn version() -> String {
     stacks::version_string("stacks-node", option_env!("STACKS_NODE_VERSION"))
 }
@@ -467,6 +539,13 @@ testnet\t\tStart a node that will join and stream blocks from the public testnet
 start\t\tStart a node with a config of your own. Can be used for joining a network, starting new chain, etc.
 \t\tArguments:
 \t\t  --config: path of the config (such as https://github.com/blockstack/stacks-blockchain/blob/master/sample/conf/testnet-follower-conf.toml).
+\t\t  --import-snapshot: optional path of a chainstate snapshot made with `stacks-inspect export-snapshot`,
+\t\t    to verify and import before syncing the rest of the chain. Ignored if the node already has chainstate.
+\t\t  --trusted-block-id: required with --import-snapshot. The index block hash of the snapshot block, as
+\t\t    reported by a node you trust. The snapshot itself cannot vouch for its own contents.
+\t\t  --trusted-reward-set: required with --import-snapshot. A file holding the response of a node you trust
+\t\t    to `GET /v3/stacker_set/<cycle>`, for the snapshot block's reward cycle. The snapshot block must be
+\t\t    signed by these signers.
 \t\tExample:
 \t\t  stacks-node start --config /path/to/config.toml
 
@@ -274,6 +274,7 @@ impl PeerThread {
                 cost_metric: Some(cost_metric.as_ref()),
                 fee_estimator: fee_estimator.map(|boxed_estimator| boxed_estimator.as_ref()),
                 coord_comms: Some(&self.globals.coord_comms),
+                event_stream: event_dispatcher.get_event_stream(),
             };
             self.net.run(
                 indexer,
@@ -20,6 +20,7 @@ use stacks::chainstate::coordinator::{
 use stacks::chainstate::stacks::db::{ChainStateBootData, StacksChainState};
 use stacks::chainstate::stacks::miner::{signal_mining_blocked, signal_mining_ready, MinerStatus};
 use stacks::core::StacksEpochId;
+use stacks::net::api::geteventstream::RPCEventStreamHub;
 use stacks::net::atlas::{AtlasConfig, AtlasDB, Attachment};
 #[cfg(test)]
 use stacks::util::tests::TestFlag;
@@ -278,6 +279,14 @@ impl RunLoop {
         for observer in config.events_observers.iter() {
             event_dispatcher.register_observer(observer, config.get_working_dir());
         }
+        if config.connection_options.max_event_stream_subscribers > 0 {
+            event_dispatcher.set_event_stream(RPCEventStreamHub::new(
+                usize::try_from(config.connection_options.max_event_stream_subscribers)
+                    .unwrap_or(usize::MAX),
+                usize::try_from(config.connection_options.event_stream_buffer_len)
+                    .unwrap_or(usize::MAX),
+            ));
+        }
 
         Self {
             config,
@@ -585,7 +594,7 @@ impl RunLoop {
         };
 
         info!("About to call open_and_exec");
-        let (chain_state_db, receipts) = StacksChainState::open_and_exec(
+        let (mut chain_state_db, receipts) = StacksChainState::open_and_exec(
             self.config.is_mainnet(),
             self.config.burnchain.chain_id,
             &self.config.get_chainstate_path_str(),
@@ -593,6 +602,8 @@ impl RunLoop {
             Some(self.config.node.get_marf_opts()),
         )
         .unwrap();
+        chain_state_db.txindex = self.config.node.txindex;
+        chain_state_db.tx_execution_threads = self.config.node.tx_execution_threads;
         run_loop::announce_boot_receipts(
             &mut self.event_dispatcher,
             &chain_state_db,
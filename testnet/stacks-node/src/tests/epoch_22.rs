@@ -8,7 +8,7 @@ use stacks::chainstate::stacks::address::PoxAddress;
 use stacks::chainstate::stacks::db::StacksChainState;
 use stacks::chainstate::stacks::miner::{signal_mining_blocked, signal_mining_ready};
 use stacks::clarity_cli::vm_execute as execute;
-use stacks::config::{EventKeyType, EventObserverConfig, InitialBalance};
+use stacks::config::{EventKeyType, EventObserverConfig, EventSinkKind, InitialBalance};
 use stacks::core::{self, EpochList, STACKS_EPOCH_MAX};
 use stacks::util_lib::boot::boot_code_id;
 use stacks_common::types::chainstate::{StacksAddress, StacksBlockId};
@@ -645,6 +645,9 @@ fn pox_2_unlock_all() {
         events_keys: vec![EventKeyType::AnyEvent],
         timeout_ms: 1000,
         disable_retries: false,
+        sink: EventSinkKind::Http,
+        async_delivery: false,
+        filter: None,
     });
     conf.initial_balances.append(&mut initial_balances);
 
@@ -40,7 +40,9 @@ use stacks::chainstate::stacks::{
 use stacks::clarity_cli::vm_execute as execute;
 use stacks::cli;
 use stacks::codec::StacksMessageCodec;
-use stacks::config::{EventKeyType, EventObserverConfig, FeeEstimatorName, InitialBalance};
+use stacks::config::{
+    EventKeyType, EventObserverConfig, EventSinkKind, FeeEstimatorName, InitialBalance,
+};
 use stacks::core::mempool::MemPoolWalkTxTypes;
 use stacks::core::{
     self, EpochList, StacksEpoch, StacksEpochId, BLOCK_LIMIT_MAINNET_20, BLOCK_LIMIT_MAINNET_205,
@@ -199,7 +201,7 @@ pub mod test_observer {
     use stacks::chainstate::stacks::events::StackerDBChunksEvent;
     use stacks::chainstate::stacks::StacksTransaction;
     use stacks::codec::StacksMessageCodec;
-    use stacks::config::{EventKeyType, EventObserverConfig};
+    use stacks::config::{EventKeyType, EventObserverConfig, EventSinkKind};
     use stacks::net::api::postblock_proposal::BlockValidateResponse;
     use stacks::util::hash::hex_bytes;
     use stacks_common::types::chainstate::StacksBlockId;
@@ -658,6 +660,9 @@ pub mod test_observer {
             events_keys: event_keys.to_vec(),
             timeout_ms: 1000,
             disable_retries: false,
+            sink: EventSinkKind::Http,
+            async_delivery: false,
+            filter: None,
         });
     }
 
@@ -7492,6 +7497,9 @@ fn atlas_integration_test() {
             events_keys: vec![EventKeyType::AnyEvent],
             timeout_ms: 1000,
             disable_retries: false,
+            sink: EventSinkKind::Http,
+            async_delivery: false,
+            filter: None,
         });
 
     conf_follower_node.node.always_use_affirmation_maps = false;
@@ -8026,6 +8034,9 @@ fn antientropy_integration_test() {
             events_keys: vec![EventKeyType::AnyEvent],
             timeout_ms: 1000,
             disable_retries: false,
+            sink: EventSinkKind::Http,
+            async_delivery: false,
+            filter: None,
         });
 
     conf_follower_node.node.mine_microblocks = true;
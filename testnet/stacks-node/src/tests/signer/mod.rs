@@ -31,7 +31,9 @@ use stacks::chainstate::nakamoto::signer_set::NakamotoSigners;
 use stacks::chainstate::nakamoto::NakamotoBlock;
 use stacks::chainstate::stacks::boot::{NakamotoSignerEntry, SIGNERS_NAME};
 use stacks::chainstate::stacks::StacksPrivateKey;
-use stacks::config::{Config as NeonConfig, EventKeyType, EventObserverConfig, InitialBalance};
+use stacks::config::{
+    Config as NeonConfig, EventKeyType, EventObserverConfig, EventSinkKind, InitialBalance,
+};
 use stacks::net::api::postblock_proposal::{
     BlockValidateOk, BlockValidateReject, BlockValidateResponse,
 };
@@ -737,6 +739,9 @@ fn setup_stx_btc_node<G: FnMut(&mut NeonConfig)>(
             ],
             timeout_ms: 1000,
             disable_retries: false,
+            sink: EventSinkKind::Http,
+            async_delivery: false,
+            filter: None,
         });
     }
 
@@ -753,6 +758,9 @@ fn setup_stx_btc_node<G: FnMut(&mut NeonConfig)>(
         ],
         timeout_ms: 1000,
         disable_retries: false,
+        sink: EventSinkKind::Http,
+        async_delivery: false,
+        filter: None,
     });
 
     // The signers need some initial balances in order to pay for epoch 2.5 transaction votes
@@ -41,7 +41,7 @@ use stacks::chainstate::stacks::db::{StacksBlockHeaderTypes, StacksChainState, S
 use stacks::chainstate::stacks::miner::{TransactionEvent, TransactionSuccessEvent};
 use stacks::chainstate::stacks::{StacksTransaction, TenureChangeCause, TransactionPayload};
 use stacks::codec::StacksMessageCodec;
-use stacks::config::{Config as NeonConfig, EventKeyType, EventObserverConfig};
+use stacks::config::{Config as NeonConfig, EventKeyType, EventObserverConfig, EventSinkKind};
 use stacks::core::{StacksEpochId, CHAIN_ID_TESTNET};
 use stacks::libstackerdb::StackerDBChunkData;
 use stacks::net::api::getsigner::GetSignerResponse;
@@ -1548,12 +1548,7 @@ fn regr_use_block_header_pk() {
         .lock()
         .unwrap()
         .drain(..)
-        .map(|endpoint| EventObserver {
-            endpoint,
-            db_path: None,
-            timeout: Duration::from_secs(120),
-            disable_retries: false,
-        })
+        .map(|endpoint| EventObserver::new(None, endpoint, Duration::from_secs(120), false))
         .collect();
 
     let bad_signer = Secp256k1PrivateKey::from_seed(&[0xde, 0xad, 0xbe, 0xef]);
@@ -4625,6 +4620,9 @@ fn signer_set_rollover() {
                     ],
                     timeout_ms: 1000,
                     disable_retries: false,
+                    sink: EventSinkKind::Http,
+                    async_delivery: false,
+                    filter: None,
                 });
             }
             naka_conf.node.rpc_bind = rpc_bind.clone();
@@ -7604,7 +7602,7 @@ fn block_validation_pending_table() {
     let signer_keys = signer_test
         .signer_configs
         .iter()
-        .map(|c| StacksPublicKey::from_private(&c.stacks_private_key))
+        .map(|c| c.stacks_public_key)
         .collect::<Vec<_>>();
     wait_for_block_rejections_from_signers(30, &block.header.signer_signature_hash(), &signer_keys)
         .expect("Timed out waiting for block rejections");
@@ -9152,6 +9150,9 @@ fn injected_signatures_are_ignored_across_boundaries() {
                 ],
                 timeout_ms: 1000,
                 disable_retries: false,
+                sink: EventSinkKind::Http,
+                async_delivery: false,
+                filter: None,
             });
             naka_conf.node.rpc_bind = rpc_bind.clone();
         },